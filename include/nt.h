@@ -0,0 +1,50 @@
+/* C interface to nt_core (link with -lnt_core)
+ *
+ * Build the library with `cargo build --release --lib`; it is written to
+ * target/release/libnt_core.so (libnt_core.dylib on macOS).
+ */
+#ifndef NT_H
+#define NT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque prime set; primality lookups up to a fixed limit */
+typedef struct NtPrimeSet NtPrimeSet;
+
+/* Sieve every prime <= limit into a new array stored in *out_primes.
+ * Returns the number of primes; release the array with nt_free_primes. */
+size_t nt_sieve(uint64_t limit, uint64_t **out_primes);
+
+/* Release an array returned by nt_sieve */
+void nt_free_primes(uint64_t *primes, size_t len);
+
+/* Deterministic primality test for any 64-bit n */
+bool nt_is_prime(uint64_t n);
+
+/* Sieve the primes <= limit and save them as a prime set file.
+ * Returns 0 on success, -1 on error. */
+int32_t nt_create_primeset(uint64_t limit, const char *path);
+
+/* Open (memory-map) a prime set file; NULL on error */
+NtPrimeSet *nt_open_primeset(const char *path);
+
+/* Whether n is prime; false for n above the set's limit */
+bool nt_primeset_contains(const NtPrimeSet *set, uint64_t n);
+
+/* Largest number the set can answer for */
+uint64_t nt_primeset_limit(const NtPrimeSet *set);
+
+/* Unmap and free a set from nt_open_primeset */
+void nt_close_primeset(NtPrimeSet *set);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NT_H */
@@ -0,0 +1,176 @@
+// abc triples (`nt abc`)
+//
+// Coprime a + b = c with a < b is an abc triple of quality q = ln c / ln rad(abc), rad
+// being the product of the distinct primes dividing abc. Almost every triple has q < 1;
+// the abc conjecture says only finitely many pass any bound above 1, and those above 1.4
+// are rare (1 + 4374 = 4375 with q ≈ 1.568 is the best up to 20000). Radicals come from a
+// smallest-prime-factor table. Rather than trying every a for each c, the numbers are
+// sorted by radical: q > bound needs rad(a)·rad(b)·rad(c) < c^(1/bound) with rad(b) >= 2,
+// so a walk through that order stops at the first a with 2·rad(a)·rad(c) past the limit,
+// and most c are ruled out by rad(c) alone. Ranges of c are shared out to worker threads.
+
+use crate::SpfTable;
+use crate::parallel::{chunk_size, parallel_chunks};
+use crate::rational::gcd;
+
+// Smaller than parallel::MIN_CHUNK: a c with a small radical walks a long stretch of the
+// radical order, so a few of them can make one chunk far slower than the rest
+const MIN_CHUNK: usize = 1 << 12;
+
+/// a + b = c with gcd(a, b) = 1 and a < b
+#[derive(Clone, Debug, PartialEq)]
+pub struct Triple {
+    pub a: usize,
+    pub b: usize,
+    pub c: usize,
+    /// rad(abc)
+    pub radical: u128,
+    /// ln c / ln rad(abc)
+    pub quality: f64,
+}
+
+/// Parse a quality bound, which must be at least 1: below it nearly every triple passes
+pub fn parse_quality(input: &str) -> Result<f64, String> {
+    match input.trim().parse::<f64>() {
+        Ok(quality) if quality >= 1.0 => Ok(quality),
+        _ => Err(format!("'{}' is not a quality of at least 1", input)),
+    }
+}
+
+/// rad(n) for every n <= limit (rad(0) is left at 0); `table` must cover `limit`
+pub fn radicals(table: &SpfTable, limit: usize) -> Vec<u64> {
+    let mut rads = vec![0; limit + 1];
+    for (n, rad) in rads.iter_mut().enumerate().skip(1) {
+        let mut product = 1;
+        let mut rest = n;
+        while rest > 1 {
+            let p = table.smallest_factor(rest);
+            product *= p as u64;
+            while rest % p == 0 {
+                rest /= p;
+            }
+        }
+        *rad = product;
+    }
+    rads
+}
+
+/// Every triple with c <= limit and quality above `min_quality` (at least 1), best first
+/// `table` must cover `limit`
+pub fn find_triples(
+    table: &SpfTable,
+    limit: usize,
+    min_quality: f64,
+    num_workers: usize,
+) -> Vec<Triple> {
+    assert!(
+        limit <= table.limit(),
+        "limit {} is above the spf table limit {}",
+        limit,
+        table.limit()
+    );
+    if limit < 3 {
+        return Vec::new();
+    }
+    let rads = radicals(table, limit);
+    let mut by_radical: Vec<(u64, usize)> = (1..=limit).map(|n| (rads[n], n)).collect();
+    by_radical.sort_unstable();
+
+    // c runs over [3, limit]; small c are cheap and large c with small radicals are not,
+    // so chunks are sized as if for twice the workers
+    let mut triples = Vec::new();
+    parallel_chunks(
+        3..=limit,
+        chunk_size(limit - 2, num_workers.max(1) * 2, MIN_CHUNK),
+        num_workers,
+        |low, high| {
+            let mut triples = Vec::new();
+            for c in low..=high {
+                triples_for(c, &rads, &by_radical, min_quality, &mut triples);
+            }
+            triples
+        },
+        |found| triples.extend(found),
+    );
+
+    triples.sort_by(|x, y| y.quality.total_cmp(&x.quality).then(x.c.cmp(&y.c)));
+    triples
+}
+
+/// Append the triples with this `c` and quality above `min_quality` to `out`
+fn triples_for(
+    c: usize,
+    rads: &[u64],
+    by_radical: &[(u64, usize)],
+    min_quality: f64,
+    out: &mut Vec<Triple>,
+) {
+    let rad_c = rads[c];
+    // rad(abc) has to stay below this
+    let bound = (c as f64).powf(1.0 / min_quality);
+    if 2.0 * rad_c as f64 >= bound {
+        return;
+    }
+    for &(rad_a, a) in by_radical {
+        if 2.0 * rad_a as f64 * rad_c as f64 >= bound {
+            break;
+        }
+        if 2 * a >= c || gcd(rad_a, rad_c) != 1 {
+            continue;
+        }
+        let b = c - a;
+        let radical = rad_a as u128 * rads[b] as u128 * rad_c as u128;
+        let quality = (c as f64).ln() / (radical as f64).ln();
+        if quality > min_quality {
+            out.push(Triple {
+                a,
+                b,
+                c,
+                radical,
+                quality,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quality() {
+        assert_eq!(parse_quality("1.4"), Ok(1.4));
+        assert_eq!(parse_quality("1"), Ok(1.0));
+        assert!(parse_quality("0.9").is_err());
+        assert!(parse_quality("NaN").is_err());
+    }
+
+    #[test]
+    fn test_radicals() {
+        let table = SpfTable::new(100);
+        let rads = radicals(&table, 100);
+        assert_eq!(&rads[1..13], [1, 2, 3, 2, 5, 6, 7, 2, 3, 10, 11, 6]);
+        assert_eq!(rads[72], 6);
+        assert_eq!(rads[97], 97);
+    }
+
+    #[test]
+    fn test_triples_match_brute_force() {
+        let table = SpfTable::new(20_000);
+        // Checked against trying every coprime a < b
+        let best: Vec<(usize, usize, usize)> = find_triples(&table, 20_000, 1.4, 4)
+            .iter()
+            .map(|t| (t.a, t.b, t.c))
+            .collect();
+        assert_eq!(best, [(1, 4374, 4375), (1, 2400, 2401), (3, 125, 128)]);
+        let top = &find_triples(&table, 5_000, 1.4, 1)[0];
+        assert_eq!(top.radical, 210);
+        assert!((top.quality - 1.567_887).abs() < 1e-6);
+
+        // Every abc hit up to 5000, across chunk boundaries
+        let hits = find_triples(&table, 5_000, 1.0, 3);
+        assert_eq!(hits.len(), 80);
+        assert!(hits.iter().any(|t| (t.a, t.b, t.c) == (32, 49, 81)));
+        assert_eq!(find_triples(&table, 5_000, 1.2, 3).len(), 17);
+    }
+}
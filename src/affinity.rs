@@ -0,0 +1,182 @@
+// CPU pinning for parallel sieve workers and consumers (variations 8 and 9)
+//
+// On multi-socket machines the scheduler migrates threads between sockets, so a worker's
+// segment buffer ends up on a remote NUMA node. Pinning each thread before it allocates
+// keeps its buffers node-local (Linux places pages on the node that first touches them).
+
+use tracing::warn;
+
+/// Assignment of worker and consumer threads to cores
+/// Cores are ordered node by node so consecutive threads share a NUMA node
+pub struct CorePlan {
+    cores: Vec<usize>,
+    num_workers: usize,
+}
+
+impl CorePlan {
+    pub fn new(num_workers: usize) -> Self {
+        Self {
+            cores: numa_ordered_cores(),
+            num_workers,
+        }
+    }
+
+    /// Core for worker `worker_id` (0-based); workers take the first cores
+    pub fn worker_core(&self, worker_id: usize) -> Option<usize> {
+        self.core_at(worker_id)
+    }
+
+    /// Core for consumer `consumer_id` (1-based); consumers follow the workers
+    pub fn consumer_core(&self, consumer_id: usize) -> Option<usize> {
+        self.core_at(self.num_workers + consumer_id - 1)
+    }
+
+    fn core_at(&self, slot: usize) -> Option<usize> {
+        if self.cores.is_empty() {
+            return None;
+        }
+        // More threads than cores wraps around
+        Some(self.cores[slot % self.cores.len()])
+    }
+
+    /// Pin the calling thread to the core for worker `worker_id`
+    pub fn pin_worker(&self, worker_id: usize) {
+        if let Some(core) = self.worker_core(worker_id)
+            && let Err(e) = pin_current_thread(core)
+        {
+            warn!(
+                "Warning: Could not pin worker {} to core {}: {}",
+                worker_id, core, e
+            );
+        }
+    }
+
+    /// Pin the calling thread to the core for consumer `consumer_id`
+    pub fn pin_consumer(&self, consumer_id: usize) {
+        if let Some(core) = self.consumer_core(consumer_id)
+            && let Err(e) = pin_current_thread(core)
+        {
+            warn!(
+                "Warning: Could not pin consumer {} to core {}: {}",
+                consumer_id, core, e
+            );
+        }
+    }
+
+    pub fn num_cores(&self) -> usize {
+        self.cores.len()
+    }
+}
+
+/// Cores this process may run on, grouped by NUMA node (node 0 first)
+#[cfg(target_os = "linux")]
+fn numa_ordered_cores() -> Vec<usize> {
+    let allowed = allowed_cores();
+    let mut ordered = Vec::with_capacity(allowed.len());
+
+    // /sys/devices/system/node/nodeN/cpulist, e.g. "0-15,32-47"
+    let mut node = 0;
+    while let Ok(cpulist) =
+        std::fs::read_to_string(format!("/sys/devices/system/node/node{}/cpulist", node))
+    {
+        for core in parse_cpulist(&cpulist) {
+            if allowed.contains(&core) && !ordered.contains(&core) {
+                ordered.push(core);
+            }
+        }
+        node += 1;
+    }
+
+    // Anything sysfs didn't report (or no sysfs at all) goes last in plain order
+    for core in allowed {
+        if !ordered.contains(&core) {
+            ordered.push(core);
+        }
+    }
+
+    ordered
+}
+
+#[cfg(not(target_os = "linux"))]
+fn numa_ordered_cores() -> Vec<usize> {
+    Vec::new()
+}
+
+/// Cores in this process's affinity mask
+#[cfg(target_os = "linux")]
+fn allowed_cores() -> Vec<usize> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return Vec::new();
+        }
+        (0..libc::CPU_SETSIZE as usize)
+            .filter(|&core| libc::CPU_ISSET(core, &set))
+            .collect()
+    }
+}
+
+/// Parse a kernel cpulist like "0-3,8,10-11"
+fn parse_cpulist(cpulist: &str) -> Vec<usize> {
+    let mut cores = Vec::new();
+    for part in cpulist.trim().split(',').filter(|part| !part.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<usize>(), end.parse::<usize>()) {
+                    cores.extend(start..=end);
+                }
+            }
+            None => {
+                if let Ok(core) = part.parse::<usize>() {
+                    cores.push(core);
+                }
+            }
+        }
+    }
+    cores
+}
+
+#[cfg(target_os = "linux")]
+fn pin_current_thread(core: usize) -> std::io::Result<()> {
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_current_thread(_core: usize) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "thread pinning is only supported on Linux",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cpulist() {
+        assert_eq!(parse_cpulist("0-3,8,10-11\n"), vec![0, 1, 2, 3, 8, 10, 11]);
+        assert_eq!(parse_cpulist(""), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_consumers_follow_workers() {
+        let plan = CorePlan {
+            cores: vec![0, 2, 4, 6],
+            num_workers: 3,
+        };
+        assert_eq!(plan.worker_core(0), Some(0));
+        assert_eq!(plan.worker_core(2), Some(4));
+        assert_eq!(plan.consumer_core(1), Some(6));
+        // Wraps around once every core is taken
+        assert_eq!(plan.consumer_core(2), Some(0));
+    }
+}
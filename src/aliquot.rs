@@ -0,0 +1,80 @@
+// Perfect, amicable and untouchable numbers (`nt perfect`, `nt amicable`, `nt untouchable`)
+//
+// All three read s(n) = σ(n) − n from the table `nt arith aliquot <limit> --binary` writes,
+// so one sieve run serves every search. Perfect numbers are the fixed points of s and
+// amicable pairs its 2-cycles. An untouchable number is s(m) for no m at all; a composite m
+// has s(m) > sqrt(m), so a table up to L settles every n with (n − 1)² <= L.
+
+use crate::arith_table::ArithTable;
+
+/// n <= limit with s(n) = n
+pub fn perfect(table: &ArithTable, limit: usize) -> Vec<usize> {
+    (1..=limit).filter(|&n| table.get(n) == n as i64).collect()
+}
+
+/// Pairs a < b <= limit with s(a) = b and s(b) = a
+pub fn amicable(table: &ArithTable, limit: usize) -> Vec<(usize, usize)> {
+    (1..=limit)
+        .filter_map(|a| {
+            let b = table.get(a) as usize;
+            (b > a && b <= limit && table.get(b) == a as i64).then_some((a, b))
+        })
+        .collect()
+}
+
+/// Largest n a table up to `table_limit` can show is untouchable
+pub fn untouchable_bound(table_limit: usize) -> usize {
+    table_limit.isqrt() + 1
+}
+
+/// Untouchable numbers up to `untouchable_bound(table.limit())`
+pub fn untouchable(table: &ArithTable) -> Vec<usize> {
+    let bound = untouchable_bound(table.limit());
+    let mut touched = vec![false; bound + 1];
+    for m in 1..=table.limit() {
+        let s = table.get(m) as usize;
+        if s <= bound {
+            touched[s] = true;
+        }
+    }
+    (2..=bound).filter(|&n| !touched[n]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arith::{ArithFunction, Scratch, sieve_segment};
+
+    #[test]
+    fn test_searches_match_known_values() {
+        let limit: usize = 10_000;
+        let base_primes = crate::primes::sieve(limit.isqrt());
+        let mut scratch = Scratch::default();
+        let values = sieve_segment(ArithFunction::Aliquot, &base_primes, 1, limit, &mut scratch);
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let path =
+            std::env::temp_dir().join(format!("nt_aliquot_search_{}.bin", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        let table = ArithTable::open(&path).unwrap();
+
+        assert_eq!(perfect(&table, limit), [6, 28, 496, 8128]);
+        assert_eq!(perfect(&table, 495), [6, 28]);
+        assert_eq!(
+            amicable(&table, limit),
+            [
+                (220, 284),
+                (1184, 1210),
+                (2620, 2924),
+                (5020, 5564),
+                (6232, 6368)
+            ]
+        );
+        // 6368 is past the limit, so the last pair is left out
+        assert_eq!(amicable(&table, 6300).len(), 4);
+        // A005114 up to sqrt(10^4) + 1
+        assert_eq!(untouchable(&table), [2, 5, 52, 88, 96]);
+
+        drop(table);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,345 @@
+// Segmented sieves for arithmetic functions: Euler's totient φ, Möbius μ, divisor sum σ and
+// aliquot sum s(n) = σ(n) − n
+//
+// Every n in a segment starts with value 1 and its unfactored part at n. Each base prime
+// p <= sqrt(limit) walks its multiples in the segment, divides out p^e and folds the prime
+// power into the value; whatever is left above 1 afterwards is a single prime > sqrt(n).
+// Segments go through the shared pipeline in segments.rs (workers, routed channels,
+// backpressure), with workers encoding values straight into output bytes. Consumers write
+// <function>_<id>.txt/.bin shards the way variation 9 writes primes; with one consumer
+// (the default) the whole table is in order in a single file, which arith_table.rs maps
+// for lookups.
+
+use clap::ValueEnum;
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::mpsc;
+use std::thread;
+use tracing::{error, info};
+
+use crate::backpressure::Backpressure;
+use crate::buffer_pool::BufferPool;
+use crate::segment_format::{EncodedSegment, SegmentEncoding};
+use crate::segments::{Pipeline, SegmentPlan, consume_in_order};
+use crate::storage_direct::BinaryOutputOptions;
+use crate::storage_writer::{PrimeWriter, file_name};
+
+// Numbers per segment (each needs 16 bytes of worker scratch)
+pub const ARITH_SEGMENT_SIZE: usize = 64 * 1024;
+
+// Segments buffered per consumer channel
+const CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ArithFunction {
+    /// Euler's totient φ(n)
+    Phi,
+    /// Möbius function μ(n): -1, 0 or 1
+    Mu,
+    /// Sum of divisors σ(n)
+    Sigma,
+    /// Sum of proper divisors s(n) = σ(n) − n, for aliquot sequences
+    Aliquot,
+}
+
+impl ArithFunction {
+    /// File stem for this function's table
+    pub fn stem(self) -> &'static str {
+        match self {
+            ArithFunction::Phi => "phi",
+            ArithFunction::Mu => "mu",
+            ArithFunction::Sigma => "sigma",
+            ArithFunction::Aliquot => "aliquot",
+        }
+    }
+
+    /// Fold the prime power p^e (e >= 1) of n into the value so far
+    #[inline]
+    fn fold(self, value: i64, p: u64, e: u32) -> i64 {
+        let p = p as i64;
+        match self {
+            ArithFunction::Phi => value * (p - 1) * p.pow(e - 1),
+            ArithFunction::Mu => {
+                if e > 1 {
+                    0
+                } else {
+                    -value
+                }
+            }
+            // 1 + p + ... + p^e term by term: p^(e + 1) overflows for a cofactor p > 3.04e9
+            ArithFunction::Sigma | ArithFunction::Aliquot => {
+                let mut sum = 1;
+                let mut term = 1;
+                for _ in 0..e {
+                    term *= p;
+                    sum += term;
+                }
+                value * sum
+            }
+        }
+    }
+}
+
+/// Reusable per-worker buffers
+#[derive(Default)]
+pub struct Scratch {
+    values: Vec<i64>,
+    rest: Vec<u64>,
+}
+
+/// Compute `function` for every n in [low, high] into `scratch`, returning the values
+/// `base_primes` must hold every prime <= sqrt(high)
+pub fn sieve_segment<'a>(
+    function: ArithFunction,
+    base_primes: &[usize],
+    low: usize,
+    high: usize,
+    scratch: &'a mut Scratch,
+) -> &'a [i64] {
+    let Scratch { values, rest } = scratch;
+    values.clear();
+    values.resize(high - low + 1, 1);
+    rest.clear();
+    rest.extend(low as u64..=high as u64);
+
+    for &p in base_primes {
+        let mut multiple = low.div_ceil(p) * p;
+        let p = p as u64;
+        while multiple <= high {
+            let idx = multiple - low;
+            let mut e = 0;
+            while rest[idx].is_multiple_of(p) {
+                rest[idx] /= p;
+                e += 1;
+            }
+            values[idx] = function.fold(values[idx], p, e);
+            multiple += p as usize;
+        }
+    }
+
+    // A cofactor above 1 has no prime factor <= sqrt(n), so it is prime
+    for (value, &rest) in values.iter_mut().zip(rest.iter()) {
+        if rest > 1 {
+            *value = function.fold(*value, rest, 1);
+        }
+    }
+    if function == ArithFunction::Aliquot {
+        for (value, n) in values.iter_mut().zip(low as i64..) {
+            *value -= n;
+        }
+    }
+    values
+}
+
+fn encode(values: &[i64], encoding: SegmentEncoding, bytes: &mut Vec<u8>) {
+    match encoding {
+        SegmentEncoding::Text => {
+            let mut itoa_buf = itoa::Buffer::new();
+            for &value in values {
+                bytes.extend_from_slice(itoa_buf.format(value).as_bytes());
+                bytes.push(b'\n');
+            }
+        }
+        SegmentEncoding::Binary => {
+            for &value in values {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+}
+
+/// Sieve `function` for n = 1..=limit and write the table
+/// Returns the number of values written
+pub fn run(
+    function: ArithFunction,
+    limit: usize,
+    encoding: SegmentEncoding,
+    num_workers: usize,
+    num_consumers: usize,
+    max_memory_mb: Option<usize>,
+) -> usize {
+    let base_primes = crate::primes::sieve(limit.isqrt());
+    let plan = SegmentPlan::new(1, limit, ARITH_SEGMENT_SIZE);
+
+    let total_sent = Arc::new(AtomicUsize::new(0));
+    let total_received = Arc::new(AtomicUsize::new(0));
+    let backpressure = max_memory_mb.map(|max_memory_mb| {
+        info!("Adaptive backpressure: {} MB memory budget", max_memory_mb);
+        Backpressure::new(
+            Arc::clone(&total_sent),
+            Arc::clone(&total_received),
+            max_memory_mb,
+            num_consumers,
+        )
+    });
+    let pipeline = Pipeline {
+        num_workers,
+        total_sent,
+        backpressure: backpressure.as_ref(),
+        pinning: None,
+        deterministic: false,
+    };
+    let pool = BufferPool::<u8>::new();
+
+    info!(
+        "Sieving {}(n) for n <= {}: {} segments, {} workers, {} consumers",
+        function.stem(),
+        limit,
+        plan.total_segments(),
+        num_workers,
+        num_consumers
+    );
+
+    thread::scope(|scope| {
+        let mut senders = Vec::new();
+        let mut consumers = Vec::new();
+        for consumer_id in 1..=num_consumers {
+            let (tx, rx) = mpsc::sync_channel::<EncodedSegment>(CHANNEL_CAPACITY);
+            senders.push(tx);
+            let (pool, total_received) = (&pool, &total_received);
+            consumers.push(scope.spawn(move || {
+                let stem = format!("{}_{}", function.stem(), consumer_id);
+                let options = BinaryOutputOptions::default();
+                let mut writer = match PrimeWriter::create(&stem, encoding, &options, 256 * 1024) {
+                    Ok(w) => w,
+                    Err(e) => {
+                        error!("Error opening {}: {}", file_name(&stem, encoding), e);
+                        return 0;
+                    }
+                };
+
+                consume_in_order(
+                    rx,
+                    consumer_id,
+                    num_consumers,
+                    total_received,
+                    |seg| seg.segment_id,
+                    |seg| {
+                        if let Err(e) = writer.write_encoded(&seg.bytes, seg.count) {
+                            error!("Error writing to {}: {}", writer.name(), e);
+                        }
+                        pool.give_back(seg.bytes);
+                    },
+                );
+
+                let name = writer.name().to_string();
+                match writer.finish() {
+                    Ok(count) => {
+                        info!(
+                            "Consumer {}: Saved {} values to {}",
+                            consumer_id, count, name
+                        );
+                        count
+                    }
+                    Err(e) => {
+                        error!("Error flushing {}: {}", name, e);
+                        0
+                    }
+                }
+            }));
+        }
+
+        pipeline.run(
+            &plan,
+            senders,
+            |seg: &EncodedSegment| seg.bytes.len(),
+            Scratch::default,
+            |scratch, low, high, segment_id| {
+                let values = sieve_segment(function, &base_primes, low, high, scratch);
+                let mut bytes = pool.take(values.len() * 8);
+                encode(values, encoding, &mut bytes);
+                EncodedSegment {
+                    bytes,
+                    count: values.len(),
+                    segment_id,
+                }
+            },
+        );
+
+        consumers
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or(0))
+            .sum()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive(function: ArithFunction, n: usize) -> i64 {
+        let divisors: Vec<usize> = (1..=n).filter(|d| n.is_multiple_of(*d)).collect();
+        match function {
+            ArithFunction::Phi => (1..=n).filter(|&k| gcd(k, n) == 1).count() as i64,
+            ArithFunction::Mu => {
+                let mut m = n;
+                let mut mu = 1;
+                for p in 2..=n {
+                    if m.is_multiple_of(p) {
+                        m /= p;
+                        if m.is_multiple_of(p) {
+                            return 0;
+                        }
+                        mu = -mu;
+                    }
+                }
+                mu
+            }
+            ArithFunction::Sigma => divisors.iter().sum::<usize>() as i64,
+            ArithFunction::Aliquot => (divisors.iter().sum::<usize>() - n) as i64,
+        }
+    }
+
+    fn gcd(a: usize, b: usize) -> usize {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+
+    #[test]
+    fn test_segments_match_naive() {
+        let limit: usize = 3000;
+        let base_primes = crate::primes::sieve(limit.isqrt());
+        let mut scratch = Scratch::default();
+        for function in [
+            ArithFunction::Phi,
+            ArithFunction::Mu,
+            ArithFunction::Sigma,
+            ArithFunction::Aliquot,
+        ] {
+            // Odd-sized segments so boundaries fall everywhere
+            for low in (1..=limit).step_by(337) {
+                let high = (low + 336).min(limit);
+                let values = sieve_segment(function, &base_primes, low, high, &mut scratch);
+                for (n, &value) in (low..=high).zip(values) {
+                    assert_eq!(value, naive(function, n), "{:?}({})", function, n);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sigma_with_a_large_prime_cofactor() {
+        // 4e9 + 7 is prime, so its cofactor p is left over and p² overflows i64
+        let low: usize = 4_000_000_000;
+        let high = low + 200;
+        let base_primes = crate::primes::sieve(high.isqrt());
+        let mut scratch = Scratch::default();
+        let sigma =
+            sieve_segment(ArithFunction::Sigma, &base_primes, low, high, &mut scratch).to_vec();
+        let aliquot = sieve_segment(
+            ArithFunction::Aliquot,
+            &base_primes,
+            low,
+            high,
+            &mut scratch,
+        );
+        for ((n, &sigma), &aliquot) in (low..=high).zip(&sigma).zip(aliquot) {
+            let expected: u64 = crate::factor::factorize(n as u64)
+                .iter()
+                .map(|&(p, e)| (0..=e).map(|k| p.pow(k)).sum::<u64>())
+                .product();
+            assert_eq!(sigma, expected as i64, "σ({})", n);
+            assert_eq!(aliquot, expected as i64 - n as i64, "s({})", n);
+        }
+        assert_eq!(sigma[7], 4_000_000_008);
+    }
+}
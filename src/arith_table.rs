@@ -0,0 +1,134 @@
+// Lookups into arithmetic-function tables written by `nt arith --binary`
+//
+// A single-consumer binary table is headerless: f(1), f(2), ... as signed little-endian
+// i64, so the value for n sits at byte 8 (n − 1) and the limit is the file length / 8.
+// Tables are memory-mapped where supported, so anything that needs s(n) or σ(n) for many
+// n reuses one run of `nt arith aliquot <limit> --binary` and only touches the pages it
+// reads: `nt perfect`, `nt amicable` and `nt untouchable` all search that table
+// (aliquot.rs).
+
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use crate::arith::ArithFunction;
+#[cfg(all(unix, target_endian = "little"))]
+use crate::mmap::MappedFile;
+use crate::segment_format::SegmentEncoding;
+use crate::storage::get_nt_data_dir;
+use crate::storage_writer::file_name;
+
+/// Values f(1..=limit) of one arithmetic function
+pub struct ArithTable {
+    values: Values,
+}
+
+/// Table values either read into memory or mapped from the file
+enum Values {
+    #[cfg(not(all(unix, target_endian = "little")))]
+    Owned(Vec<i64>),
+    #[cfg(all(unix, target_endian = "little"))]
+    Mapped(MappedFile),
+}
+
+impl Deref for Values {
+    type Target = [i64];
+
+    fn deref(&self) -> &[i64] {
+        match self {
+            #[cfg(not(all(unix, target_endian = "little")))]
+            Values::Owned(values) => values,
+            #[cfg(all(unix, target_endian = "little"))]
+            Values::Mapped(mapped) => mapped.i64s(0),
+        }
+    }
+}
+
+impl ArithTable {
+    /// Where `nt arith <function> --binary` leaves the table with one consumer
+    pub fn default_path(function: ArithFunction) -> PathBuf {
+        let stem = format!("{}_1", function.stem());
+        get_nt_data_dir().join(file_name(&stem, SegmentEncoding::Binary))
+    }
+
+    /// Open a binary table, memory-mapping it where supported
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len() as usize;
+        if !len.is_multiple_of(8) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "table length is not a whole number of 8-byte values",
+            ));
+        }
+
+        #[cfg(all(unix, target_endian = "little"))]
+        {
+            Ok(Self {
+                values: Values::Mapped(MappedFile::map(&file)?),
+            })
+        }
+
+        #[cfg(not(all(unix, target_endian = "little")))]
+        {
+            use std::io::Read;
+
+            let mut bytes = Vec::with_capacity(len);
+            let mut file = file;
+            file.read_to_end(&mut bytes)?;
+            let values = bytes
+                .chunks_exact(8)
+                .map(|chunk| i64::from_le_bytes(chunk.try_into().unwrap()))
+                .collect();
+            Ok(Self {
+                values: Values::Owned(values),
+            })
+        }
+    }
+
+    /// Largest n in the table
+    pub fn limit(&self) -> usize {
+        self.values.len()
+    }
+
+    /// f(n) for 1 <= n <= limit
+    pub fn get(&self, n: usize) -> i64 {
+        assert!(
+            n >= 1 && n <= self.limit(),
+            "{} is outside the table range 1..={}",
+            n,
+            self.limit()
+        );
+        self.values[n - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arith::{Scratch, sieve_segment};
+
+    #[test]
+    fn test_reads_aliquot_table() {
+        let limit: usize = 10_000;
+        let base_primes = crate::primes::sieve(limit.isqrt());
+        let mut scratch = Scratch::default();
+        let values = sieve_segment(ArithFunction::Aliquot, &base_primes, 1, limit, &mut scratch);
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let path = std::env::temp_dir().join(format!("nt_aliquot_{}.bin", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+
+        let table = ArithTable::open(&path).unwrap();
+        assert_eq!(table.limit(), limit);
+        // s(1) = 0, s(p) = 1, perfect numbers are fixed points, 220 and 284 are amicable
+        assert_eq!(table.get(1), 0);
+        assert_eq!(table.get(9973), 1);
+        for perfect in [6, 28, 496, 8128] {
+            assert_eq!(table.get(perfect), perfect as i64);
+        }
+        assert_eq!((table.get(220), table.get(284)), (284, 220));
+        drop(table);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
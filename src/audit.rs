@@ -0,0 +1,232 @@
+// Completeness audit for variation 9 (--audit)
+//
+// Consumers record every segment id they write in a process-wide table (the same pattern as
+// tui.rs, so nothing is threaded through the consumer signatures). At the end `verify`
+// checks that each segment 1..=total was written exactly once, by the consumer it was
+// routed to. Without it a dropped sender or a consumer that died mid-run leaves a hole in
+// the shards that nothing reports.
+
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{error, info};
+
+// Longest list of segment ids printed per problem
+const MAX_LISTED: usize = 10;
+
+struct Audit {
+    num_consumers: usize,
+    // Times each segment was written, indexed by segment id (0 = small primes, unused)
+    writes: Vec<AtomicUsize>,
+    // Consumer (1-based) that last wrote each segment
+    writers: Vec<AtomicUsize>,
+    consumer_segments: Vec<AtomicUsize>,
+}
+
+static AUDIT: OnceLock<Audit> = OnceLock::new();
+
+/// Start recording writes for segments 1..=total_segments
+pub fn start(total_segments: usize, num_consumers: usize) {
+    let _ = AUDIT.set(Audit::new(total_segments, num_consumers));
+}
+
+/// Record that consumer `consumer_id` (1-based) wrote segment `segment_id`
+/// Only call once the write succeeded; a no-op unless `start` was called
+#[inline]
+pub fn record_segment(consumer_id: usize, segment_id: usize) {
+    if let Some(audit) = AUDIT.get() {
+        audit.record_segment(consumer_id, segment_id);
+    }
+}
+
+/// Report per-consumer coverage and check every segment was written exactly once
+/// Returns true when the audit passed (or was never started)
+pub fn verify() -> bool {
+    AUDIT.get().is_none_or(Audit::verify)
+}
+
+// The table behind start/record_segment/verify; tests build their own rather than share the
+// process-wide one
+impl Audit {
+    fn new(total_segments: usize, num_consumers: usize) -> Self {
+        let counters = |n: usize| (0..n).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+        Audit {
+            num_consumers,
+            writes: counters(total_segments + 1),
+            writers: counters(total_segments + 1),
+            consumer_segments: counters(num_consumers),
+        }
+    }
+
+    #[inline]
+    fn record_segment(&self, consumer_id: usize, segment_id: usize) {
+        if let Some(writes) = self.writes.get(segment_id) {
+            writes.fetch_add(1, Ordering::Relaxed);
+            self.writers[segment_id].store(consumer_id, Ordering::Relaxed);
+        }
+        if let Some(count) = self.consumer_segments.get(consumer_id.wrapping_sub(1)) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn verify(&self) -> bool {
+        let total_segments = self.writes.len() - 1;
+
+        for (idx, count) in self.consumer_segments.iter().enumerate() {
+            let consumer_id = idx + 1;
+            let expected = expected_segments(consumer_id, self.num_consumers, total_segments);
+            info!(
+                "Audit: consumer {} wrote {} of {} segments",
+                consumer_id,
+                count.load(Ordering::Relaxed),
+                expected
+            );
+        }
+
+        let mut missing = Vec::new();
+        let mut duplicated = Vec::new();
+        let mut misrouted = Vec::new();
+        for segment_id in 1..=total_segments {
+            match self.writes[segment_id].load(Ordering::Relaxed) {
+                0 => missing.push(segment_id),
+                1 => {}
+                _ => duplicated.push(segment_id),
+            }
+            let writer = self.writers[segment_id].load(Ordering::Relaxed);
+            if writer != 0 && writer != owner(segment_id, self.num_consumers) {
+                misrouted.push(segment_id);
+            }
+        }
+
+        report("missing", &missing);
+        report("written more than once", &duplicated);
+        report("written by the wrong consumer", &misrouted);
+
+        let passed = missing.is_empty() && duplicated.is_empty() && misrouted.is_empty();
+        if passed {
+            info!(
+                "Audit passed: all {} segments written exactly once",
+                total_segments
+            );
+        }
+        passed
+    }
+}
+
+/// Consumer (1-based) that segment `segment_id` is routed to
+fn owner(segment_id: usize, num_consumers: usize) -> usize {
+    (segment_id - 1) % num_consumers + 1
+}
+
+/// Segments routed to `consumer_id` out of 1..=total_segments
+fn expected_segments(consumer_id: usize, num_consumers: usize, total_segments: usize) -> usize {
+    if consumer_id > total_segments {
+        0
+    } else {
+        (total_segments - consumer_id) / num_consumers + 1
+    }
+}
+
+fn report(problem: &str, segment_ids: &[usize]) {
+    if segment_ids.is_empty() {
+        return;
+    }
+    let listed: Vec<String> = segment_ids
+        .iter()
+        .take(MAX_LISTED)
+        .map(|id| id.to_string())
+        .collect();
+    let more = if segment_ids.len() > MAX_LISTED {
+        format!(" and {} more", segment_ids.len() - MAX_LISTED)
+    } else {
+        String::new()
+    };
+    error!(
+        "Audit failed: {} segments {}: {}{}",
+        segment_ids.len(),
+        problem,
+        listed.join(", "),
+        more
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_segments_cover_total() {
+        for num_consumers in 1..=5 {
+            for total in 0..20 {
+                let sum: usize = (1..=num_consumers)
+                    .map(|id| expected_segments(id, num_consumers, total))
+                    .sum();
+                assert_eq!(sum, total);
+                for consumer_id in 1..=num_consumers {
+                    let owned = (1..=total)
+                        .filter(|&id| owner(id, num_consumers) == consumer_id)
+                        .count();
+                    assert_eq!(owned, expected_segments(consumer_id, num_consumers, total));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_clean_run_passes() {
+        let audit = Audit::new(7, 3);
+        for segment_id in 1..=7 {
+            audit.record_segment(owner(segment_id, 3), segment_id);
+        }
+        assert!(audit.verify());
+    }
+
+    #[test]
+    fn test_gap_duplicate_and_misroute_fail() {
+        let all_but = |skip: usize| {
+            let audit = Audit::new(7, 3);
+            for segment_id in (1..=7).filter(|&id| id != skip) {
+                audit.record_segment(owner(segment_id, 3), segment_id);
+            }
+            audit
+        };
+
+        // Segment 4 never written
+        assert!(!all_but(4).verify());
+
+        // Segment 5 written twice by its own consumer
+        let audit = all_but(0);
+        audit.record_segment(owner(5, 3), 5);
+        assert!(!audit.verify());
+
+        // Segment 6 written once, but by consumer 1 instead of 3
+        let audit = all_but(6);
+        audit.record_segment(1, 6);
+        assert!(!audit.verify());
+    }
+
+    #[test]
+    fn test_started_audit_fails_on_a_bad_run() {
+        // The only test to touch the process-wide table, which can be started once
+        assert!(verify());
+        start(7, 3);
+        for segment_id in [1, 2, 3, 5, 5, 7] {
+            record_segment(owner(segment_id, 3), segment_id);
+        }
+        record_segment(1, 6);
+        assert!(!verify());
+    }
+
+    #[test]
+    fn test_owner_matches_the_pipeline_routing() {
+        // Three consumers take segments round-robin from segment 1
+        let owners: Vec<usize> = (1..=7).map(|id| owner(id, 3)).collect();
+        assert_eq!(owners, [1, 2, 3, 1, 2, 3, 1]);
+        assert_eq!(owner(5, 1), 1);
+        for segment_id in 1..=20 {
+            assert_eq!(
+                owner(segment_id, 4),
+                crate::segments::route(segment_id, 4) + 1
+            );
+        }
+    }
+}
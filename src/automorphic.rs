@@ -0,0 +1,122 @@
+// Automorphic and trimorphic numbers (`nt automorphic`)
+//
+// n with d digits is automorphic when n² ≡ n (mod 10^d) (76² = 5776) and trimorphic when
+// n³ ≡ n (mod 10^d) (49³ = 117649). A solution modulo 10^(d+1) reduces to one modulo 10^d,
+// so all of them come from lifting the solutions one digit at a time: try the 10 possible
+// next digits of each and keep those that still solve the congruence. There are only 4
+// square and 15 cube solutions per modulus, and the d-digit numbers are the solutions
+// >= 10^(d−1), so no n is ever tested by itself.
+//
+// Past 0 and 1 the automorphic numbers are the truncations of the two 10-adic idempotents
+// ...8212890625 and ...1787109376 (which sum to 1). For thousands of digits each new digit
+// is solved for directly instead of searched: with x² − x = t·10^d, the next digit k of
+// x' = x + k·10^d must make t + k(2x − 1) ≡ 0 (mod 10), and t for x' follows from t with a
+// few linear-time operations, so no big multiplication is ever needed.
+
+use rug::Integer;
+
+/// Every n <= limit with n^power ≡ n (mod 10^digits(n)), in increasing order
+pub fn enumerate(limit: u64, power: u32) -> Vec<Integer> {
+    let mut found = Vec::new();
+    let mut residues: Vec<Integer> = Vec::new();
+    let mut modulus = Integer::from(1);
+    let limit = Integer::from(limit);
+    loop {
+        let lower = modulus.clone();
+        let next_modulus = Integer::from(&modulus * 10u32);
+        let mut lifted = Vec::new();
+        let bases = if residues.is_empty() {
+            vec![Integer::new()]
+        } else {
+            residues
+        };
+        for r in &bases {
+            for k in 0..10u32 {
+                let x = r + Integer::from(&modulus * k);
+                let x_power =
+                    Integer::from(x.pow_mod_ref(&Integer::from(power), &next_modulus).unwrap());
+                if x_power == x {
+                    lifted.push(x);
+                }
+            }
+        }
+        // One-digit numbers include 0; longer ones must not start with 0
+        for x in &lifted {
+            if (lower == 1 || *x >= lower) && *x <= limit {
+                found.push(x.clone());
+            }
+        }
+        residues = lifted;
+        modulus = next_modulus;
+        if modulus > limit {
+            break;
+        }
+    }
+    found.sort_unstable();
+    found
+}
+
+/// The idempotents ending in 5 and in 6 modulo 10^digits, built digit by digit
+pub fn idempotents(digits: usize) -> (Integer, Integer) {
+    (idempotent_from(5, digits), idempotent_from(6, digits))
+}
+
+fn idempotent_from(last_digit: u32, digits: usize) -> Integer {
+    let mut x = Integer::from(last_digit);
+    // x² − x = t · 10^d
+    let mut t = Integer::from(last_digit * (last_digit - 1) / 10);
+    let mut power = Integer::from(10);
+    // (2x − 1)^-1 mod 10: 2x − 1 ends in 9 for x ending in 5 and in 1 for 6
+    let inverse = if last_digit == 5 { 9 } else { 1 };
+    for _ in 1..digits {
+        let k = (10 - t.mod_u(10) * inverse % 10) % 10;
+        // t' = (t + k(2x − 1) + k² 10^d) / 10
+        let odd = Integer::from(&x * 2u32) - 1u32;
+        t += odd * k;
+        t += Integer::from(&power * (k * k));
+        t /= 10u32;
+        x += Integer::from(&power * k);
+        power *= 10u32;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rug::ops::Pow;
+
+    fn values(found: Vec<Integer>) -> Vec<u64> {
+        found.iter().map(|x| x.to_u64().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_enumerate_matches_oeis() {
+        // A003226
+        assert_eq!(
+            values(enumerate(10_000_000_000, 2)),
+            [
+                0, 1, 5, 6, 25, 76, 376, 625, 9376, 90625, 109376, 890625, 2890625, 7109376,
+                12890625, 87109376, 212890625, 787109376, 1787109376, 8212890625
+            ]
+        );
+        // A033819
+        assert_eq!(
+            values(enumerate(1000, 3)),
+            [
+                0, 1, 4, 5, 6, 9, 24, 25, 49, 51, 75, 76, 99, 125, 249, 251, 375, 376, 499, 501,
+                624, 625, 749, 751, 875, 999
+            ]
+        );
+    }
+
+    #[test]
+    fn test_idempotents_digit_by_digit() {
+        let (five, six) = idempotents(20);
+        assert_eq!(five, 92_256_259_918_212_890_625u128);
+        assert_eq!(six, Integer::from(10).pow(20) + 1u32 - &five);
+        let (five, _) = idempotents(2000);
+        let modulus = Integer::from(10).pow(2000);
+        assert_eq!(Integer::from(five.square_ref()) % &modulus, five);
+    }
+}
@@ -0,0 +1,141 @@
+// Adaptive backpressure for variation 9 producers
+//
+// The bounded channels cap how many segments each consumer can have queued, but they
+// know nothing about segment size or process memory. This throttles workers on the
+// global sent/received gap instead, sized from a memory budget, and tightens the limit
+// whenever RSS goes over that budget.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::storage::get_process_memory_mb;
+
+// How often (in segments sent) to sample RSS; reading /proc is too slow to do per segment
+const RSS_CHECK_INTERVAL: usize = 256;
+
+// How long a throttled worker sleeps before re-checking the gap
+const THROTTLE_SLEEP: Duration = Duration::from_micros(200);
+
+// Never halve the gap limit more than this many times
+const MAX_TIGHTENINGS: usize = 16;
+
+/// Shared throttle for producer workers
+/// Keeps (sent - received) × segment bytes under the memory budget
+pub struct Backpressure {
+    total_sent: Arc<AtomicUsize>,
+    total_received: Arc<AtomicUsize>,
+    max_memory_mb: usize,
+    // Lower bound for the gap so every consumer can always have a segment in flight
+    min_gap: usize,
+    // Number of times the gap limit has been halved after RSS exceeded the budget
+    tightenings: AtomicUsize,
+    sends: AtomicUsize,
+    throttled: AtomicUsize,
+}
+
+impl Backpressure {
+    pub fn new(
+        total_sent: Arc<AtomicUsize>,
+        total_received: Arc<AtomicUsize>,
+        max_memory_mb: usize,
+        num_consumers: usize,
+    ) -> Self {
+        Self {
+            total_sent,
+            total_received,
+            max_memory_mb,
+            min_gap: num_consumers.max(1),
+            tightenings: AtomicUsize::new(0),
+            sends: AtomicUsize::new(0),
+            throttled: AtomicUsize::new(0),
+        }
+    }
+
+    /// Maximum segments allowed between producers and consumers for this segment size
+    pub fn gap_limit(&self, segment_bytes: usize) -> usize {
+        let budget_bytes = self.max_memory_mb * 1024 * 1024;
+        let limit = budget_bytes / segment_bytes.max(1);
+        let limit = limit >> self.tightenings.load(Ordering::Relaxed);
+        limit.max(self.min_gap)
+    }
+
+    /// Block the calling worker until there is room to send a segment of `segment_bytes`
+    pub fn wait_for_capacity(&self, segment_bytes: usize) {
+        let sends = self.sends.fetch_add(1, Ordering::Relaxed);
+        if sends.is_multiple_of(RSS_CHECK_INTERVAL) {
+            self.check_rss();
+        }
+
+        let mut waited = false;
+        loop {
+            let sent = self.total_sent.load(Ordering::Relaxed);
+            let received = self.total_received.load(Ordering::Relaxed);
+            if sent.saturating_sub(received) < self.gap_limit(segment_bytes) {
+                break;
+            }
+            waited = true;
+            thread::sleep(THROTTLE_SLEEP);
+        }
+
+        if waited {
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Halve the gap limit if the process is over its memory budget
+    fn check_rss(&self) {
+        let Some((rss_mb, _)) = get_process_memory_mb() else {
+            return;
+        };
+
+        if rss_mb > self.max_memory_mb as f64 {
+            let tightenings = self.tightenings.load(Ordering::Relaxed);
+            if tightenings < MAX_TIGHTENINGS
+                && self
+                    .tightenings
+                    .compare_exchange(
+                        tightenings,
+                        tightenings + 1,
+                        Ordering::Relaxed,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+            {
+                warn!(
+                    "[Backpressure] RSS={:.2} MB over {} MB budget, halving producer lead",
+                    rss_mb, self.max_memory_mb
+                );
+            }
+        }
+    }
+
+    /// Number of sends that had to wait for consumers to catch up
+    pub fn throttled(&self) -> usize {
+        self.throttled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gap_limit_scales_with_budget_and_tightening() {
+        let sent = Arc::new(AtomicUsize::new(0));
+        let received = Arc::new(AtomicUsize::new(0));
+        let backpressure = Backpressure::new(sent, received, 64, 4);
+
+        // 64 MB budget of 1 MB segments
+        assert_eq!(backpressure.gap_limit(1024 * 1024), 64);
+
+        backpressure.tightenings.store(2, Ordering::Relaxed);
+        assert_eq!(backpressure.gap_limit(1024 * 1024), 16);
+
+        // Never drops below one segment per consumer
+        backpressure.tightenings.store(10, Ordering::Relaxed);
+        assert_eq!(backpressure.gap_limit(1024 * 1024), 4);
+    }
+}
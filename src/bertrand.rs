@@ -0,0 +1,165 @@
+// Bertrand's postulate (`nt bertrand`)
+//
+// Counts the primes in (n, 2n) for each n up to a limit as π(2n − 1) − π(n), with a lead
+// and a trailing reader streaming the same stored prime file. The file has to reach past
+// 2n to vouch for n, so the check stops at the last n it covers.
+
+use std::io;
+
+/// The primes strictly between n and 2n
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interval {
+    pub n: usize,
+    pub primes: u64,
+}
+
+impl Interval {
+    /// n / ln(3n/2), the count the prime number theorem expects
+    pub fn expected(&self) -> f64 {
+        let n = self.n as f64;
+        n / (1.5 * n).ln()
+    }
+
+    /// Primes found over primes expected
+    pub fn ratio(&self) -> f64 {
+        self.primes as f64 / self.expected()
+    }
+}
+
+/// What counting the intervals found
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Check {
+    /// Largest n checked, short of the limit when the file ends first
+    pub checked_to: Option<usize>,
+    /// Intervals with no prime, which would contradict the postulate (a bad file)
+    pub empty: Vec<usize>,
+    /// Fewest primes, largest n on ties
+    pub fewest: Option<Interval>,
+    /// Lowest ratio of primes to the expected count, smallest n on ties
+    pub sparsest: Option<Interval>,
+}
+
+impl Check {
+    fn add(&mut self, interval: Interval) {
+        self.checked_to = Some(interval.n);
+        if interval.primes == 0 {
+            self.empty.push(interval.n);
+        }
+        if self
+            .fewest
+            .is_none_or(|fewest| interval.primes <= fewest.primes)
+        {
+            self.fewest = Some(interval);
+        }
+        if self
+            .sparsest
+            .is_none_or(|sparsest| interval.ratio() < sparsest.ratio())
+        {
+            self.sparsest = Some(interval);
+        }
+    }
+}
+
+/// Count the primes in (n, 2n) for n in [max(from, 2), limit]; `lead` and `trail` are two
+/// readers of the same increasing list of every prime from 2
+pub fn check(
+    mut lead: impl Iterator<Item = io::Result<usize>>,
+    mut trail: impl Iterator<Item = io::Result<usize>>,
+    from: usize,
+    limit: usize,
+) -> io::Result<Check> {
+    let mut checked = Check::default();
+    // Primes below 2n and up to n read so far
+    let (mut below_double, mut up_to_n) = (0_u64, 0_u64);
+    let mut next_lead = lead.next().transpose()?;
+    let mut next_trail = trail.next().transpose()?;
+    for n in from.max(2)..=limit {
+        loop {
+            match next_lead {
+                Some(p) if p < 2 * n => {
+                    below_double += 1;
+                    next_lead = lead.next().transpose()?;
+                }
+                Some(_) => break,
+                // Nothing vouches for (n, 2n) past the last prime
+                None => return Ok(checked),
+            }
+        }
+        while let Some(p) = next_trail
+            && p <= n
+        {
+            up_to_n += 1;
+            next_trail = trail.next().transpose()?;
+        }
+        checked.add(Interval {
+            n,
+            primes: below_double - up_to_n,
+        });
+    }
+    Ok(checked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn readers(limit: usize) -> [impl Iterator<Item = io::Result<usize>>; 2] {
+        let primes = crate::primes::sieve(limit);
+        [
+            primes.clone().into_iter().map(Ok),
+            primes.into_iter().map(Ok),
+        ]
+    }
+
+    #[test]
+    fn test_check() {
+        let [lead, trail] = readers(4_000);
+        let checked = check(lead, trail, 0, 1_000).unwrap();
+        assert_eq!(checked.checked_to, Some(1_000));
+        assert!(checked.empty.is_empty());
+        // (5, 10) is the last interval with a single prime, 7
+        assert_eq!(checked.fewest, Some(Interval { n: 5, primes: 1 }));
+
+        let [lead, trail] = readers(4_000);
+        let checked = check(lead, trail, 100, 1_000).unwrap();
+        assert_eq!(checked.fewest, Some(Interval { n: 113, primes: 18 }));
+        assert_eq!(checked.sparsest, Some(Interval { n: 113, primes: 18 }));
+
+        // 997 is the last prime to 1000, so it vouches for n up to 498
+        let [lead, trail] = readers(1_000);
+        assert_eq!(check(lead, trail, 2, 1_000).unwrap().checked_to, Some(498));
+    }
+
+    #[test]
+    fn test_from_above_limit() {
+        let [lead, trail] = readers(1_000);
+        assert_eq!(check(lead, trail, 300, 299).unwrap(), Check::default());
+    }
+
+    #[test]
+    fn test_counts_across_segment_boundaries() {
+        // Primes streamed a sieve segment at a time, with n running over several segments
+        let limit = crate::primes::SEGMENT_SIZE_NUMBERS * 3 / 2;
+        let mut primes = Vec::new();
+        crate::primes::for_each_prime_in_range(0, 2 * limit + 1_000, |segment| {
+            primes.extend_from_slice(segment)
+        });
+        let from = limit - 5_000;
+        let reader = || primes.clone().into_iter().map(Ok);
+        let checked = check(reader(), reader(), from, limit).unwrap();
+        assert_eq!(checked.checked_to, Some(limit));
+
+        let count =
+            |n: usize| primes.partition_point(|&p| p < 2 * n) - primes.partition_point(|&p| p <= n);
+        let fewest = (from..=limit)
+            .max_by_key(|&n| (std::cmp::Reverse(count(n)), n))
+            .unwrap();
+        assert_eq!(
+            checked.fewest,
+            Some(Interval {
+                n: fewest,
+                primes: count(fewest) as u64
+            })
+        );
+    }
+}
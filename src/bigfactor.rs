@@ -0,0 +1,505 @@
+// Factoring integers of any size (`nt factor`)
+//
+// Small factors come out by trial division. Each remaining cofactor is then either
+// probably prime (Baillie–PSW, bpsw.rs), a perfect power (split into its root), small
+// enough for the exact u64 path in factor.rs, or split by the chosen algorithm: Pollard's
+// rho for factors up to ~15 digits, Fermat's method (fermat.rs) for two factors close to
+// sqrt(n), Pollard's p−1 and Williams' p+1 (pm1.rs) for factors of
+// any size next to a smooth number, the elliptic curve method (ecm.rs) for ~20-35 digits.
+// Cofactors no algorithm could split are reported as composite instead of looping forever.
+
+use clap::ValueEnum;
+use rug::Integer;
+use rug::ops::RemRounding;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tracing::{debug, info};
+
+use crate::ecm::{self, EcmParams};
+use crate::fermat::{self, DifferenceOfSquares};
+use crate::{factor, pm1};
+
+// Trial division bound
+const TRIAL_DIVISION_LIMIT: usize = 10_000;
+
+// Fermat steps --algorithm auto spends first, for factors very close to sqrt(m)
+const AUTO_FERMAT_STEPS: usize = 100_000;
+
+// Rho iterations before --algorithm auto hands a cofactor to ECM
+const AUTO_RHO_ITERATIONS: usize = 200_000;
+
+// Iterations between gcds in rho (the differences are multiplied up in between)
+const RHO_BATCH: usize = 128;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum FactorAlgorithm {
+    /// Brief Fermat and Pollard rho runs, then p−1, p+1 and ECM for what they could not split
+    #[default]
+    Auto,
+    /// Pollard's rho (Brent's variant) only
+    Rho,
+    /// Fermat's difference of squares only, for factors close to sqrt(n)
+    Fermat,
+    /// Pollard's p−1 only
+    Pm1,
+    /// Williams' p+1 only
+    Pp1,
+    /// Elliptic curve method only
+    Ecm,
+}
+
+/// How hard to try on cofactors trial division leaves
+#[derive(Clone, Copy, Debug)]
+pub struct FactorOptions {
+    pub algorithm: FactorAlgorithm,
+    /// Rho iterations per polynomial for --algorithm rho
+    pub rho_iterations: usize,
+    /// Values of a tried by --algorithm fermat
+    pub fermat_steps: usize,
+    /// Stage 1 and stage 2 bounds for p−1 and p+1
+    pub pm1_b1: usize,
+    pub pm1_b2: usize,
+    pub ecm: EcmParams,
+}
+
+impl Default for FactorOptions {
+    /// The `nt factor` defaults, with ECM curves spread over every core
+    fn default() -> Self {
+        let workers = crate::parallel::default_workers();
+        Self {
+            algorithm: FactorAlgorithm::Auto,
+            rho_iterations: 10_000_000,
+            fermat_steps: 100_000_000,
+            pm1_b1: 1_000_000,
+            pm1_b2: 100_000_000,
+            ecm: EcmParams::new(50_000, None, 200, workers),
+        }
+    }
+}
+
+/// Prime factors found, plus any composite cofactors that could not be split
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Factorization {
+    /// (prime, exponent) pairs in increasing order
+    pub factors: Vec<(Integer, u32)>,
+    pub unfactored: Vec<Integer>,
+    /// Composites Fermat's method split, with the a² − b² it found
+    pub squares: Vec<(Integer, DifferenceOfSquares)>,
+}
+
+impl Factorization {
+    pub fn is_complete(&self) -> bool {
+        self.unfactored.is_empty()
+    }
+}
+
+impl fmt::Display for Factorization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut terms: Vec<String> = self
+            .factors
+            .iter()
+            .map(|(p, exp)| {
+                if *exp == 1 {
+                    p.to_string()
+                } else {
+                    format!("{}^{}", p, exp)
+                }
+            })
+            .collect();
+        terms.extend(
+            self.unfactored
+                .iter()
+                .map(|c| format!("C{}({})", c.to_string().len(), c)),
+        );
+        if terms.is_empty() {
+            terms.push("1".to_string());
+        }
+        write!(f, "{}", terms.join(" * "))
+    }
+}
+
+/// How `nt factor` writes a factorization
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum FactorFormat {
+    /// 2^3 * 3 * 5^2
+    #[default]
+    Plain,
+    /// 2³·3·5², with Ω(n), ω(n) and rad(n)
+    Unicode,
+    /// 2^{3} \cdot 3 \cdot 5^{2}, with Ω(n), ω(n) and rad(n)
+    Latex,
+}
+
+/// Ω(n) (prime factors with multiplicity), ω(n) (distinct prime factors) and rad(n)
+/// (their product) of a complete factorization
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Summary {
+    pub big_omega: u64,
+    pub omega: usize,
+    pub radical: Integer,
+}
+
+impl Factorization {
+    /// None while a cofactor is still composite, as every value depends on its factors
+    pub fn summary(&self) -> Option<Summary> {
+        if !self.is_complete() {
+            return None;
+        }
+        Some(Summary {
+            big_omega: self.factors.iter().map(|&(_, exp)| exp as u64).sum(),
+            omega: self.factors.len(),
+            radical: self.factors.iter().map(|(p, _)| p).product(),
+        })
+    }
+
+    /// The factors in `format`; Plain is the Display form
+    pub fn render(&self, format: FactorFormat) -> String {
+        let latex = match format {
+            FactorFormat::Plain => return self.to_string(),
+            FactorFormat::Unicode => false,
+            FactorFormat::Latex => true,
+        };
+        let mut terms: Vec<String> = self
+            .factors
+            .iter()
+            .map(|(p, exp)| match (*exp, latex) {
+                (1, _) => p.to_string(),
+                (exp, false) => format!("{}{}", p, superscript(exp)),
+                (exp, true) => format!("{}^{{{}}}", p, exp),
+            })
+            .collect();
+        terms.extend(self.unfactored.iter().map(|c| {
+            let digits = c.to_string().len();
+            if latex {
+                format!("C_{{{}}}({})", digits, c)
+            } else {
+                format!("C{}({})", digits, c)
+            }
+        }));
+        if terms.is_empty() {
+            terms.push("1".to_string());
+        }
+        terms.join(if latex { " \\cdot " } else { "·" })
+    }
+}
+
+impl Summary {
+    /// "Ω(n) = 6, ω(n) = 3, rad(n) = 30", or in LaTeX
+    pub fn render(&self, format: FactorFormat) -> String {
+        match format {
+            FactorFormat::Latex => format!(
+                "\\Omega(n) = {},\\ \\omega(n) = {},\\ \\operatorname{{rad}}(n) = {}",
+                self.big_omega, self.omega, self.radical
+            ),
+            _ => format!(
+                "Ω(n) = {}, ω(n) = {}, rad(n) = {}",
+                self.big_omega, self.omega, self.radical
+            ),
+        }
+    }
+}
+
+fn superscript(n: u32) -> String {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    n.to_string()
+        .bytes()
+        .map(|b| DIGITS[(b - b'0') as usize])
+        .collect()
+}
+
+/// Factor `n` (n >= 0; 0 and 1 have no prime factors)
+pub fn factorize(n: &Integer, options: &FactorOptions) -> Factorization {
+    let mut primes = Vec::new();
+    let mut unfactored = Vec::new();
+    if *n <= 1 {
+        return Factorization::default();
+    }
+
+    let mut n = n.clone();
+    for p in crate::primes::sieve(TRIAL_DIVISION_LIMIT) {
+        if n < p * p {
+            break;
+        }
+        while n.is_divisible_u(p as u32) {
+            n /= p as u32;
+            primes.push(Integer::from(p));
+        }
+    }
+
+    let mut squares = Vec::new();
+    let mut stack = vec![n];
+    while let Some(m) = stack.pop() {
+        if m == 1 {
+            continue;
+        }
+        if crate::bpsw::is_probable_prime(&m) {
+            primes.push(m);
+            continue;
+        }
+        if let Some((root, power)) = perfect_power(&m) {
+            stack.extend(std::iter::repeat_n(root, power as usize));
+            continue;
+        }
+        // Every u64 splits exactly with the machine-word path
+        if let Some(small) = m.to_u64() {
+            for (p, exp) in factor::factorize(small) {
+                primes.extend(std::iter::repeat_n(Integer::from(p), exp as usize));
+            }
+            continue;
+        }
+
+        match split(&m, options, &mut squares) {
+            Some(divisor) => {
+                let cofactor = Integer::from(&m / &divisor);
+                stack.push(divisor);
+                stack.push(cofactor);
+            }
+            None => unfactored.push(m),
+        }
+    }
+
+    primes.sort_unstable();
+    let mut factors: Vec<(Integer, u32)> = Vec::new();
+    for p in primes {
+        match factors.last_mut() {
+            Some((last, exp)) if *last == p => *exp += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+    unfactored.sort_unstable();
+    Factorization {
+        factors,
+        unfactored,
+        squares,
+    }
+}
+
+/// A proper divisor of the composite `m` from the chosen algorithm
+/// A Fermat split also records its a² − b² in `squares`
+fn split(
+    m: &Integer,
+    options: &FactorOptions,
+    squares: &mut Vec<(Integer, DifferenceOfSquares)>,
+) -> Option<Integer> {
+    let digits = m.to_string().len();
+    let mut fermat = |max_steps| {
+        let found = fermat::fermat(m, max_steps)?;
+        let (divisor, _) = found.factors();
+        info!("Fermat: C{} = {}² − {}²", digits, found.a, found.b);
+        squares.push((m.clone(), found));
+        Some(divisor)
+    };
+    let (b1, b2) = (options.pm1_b1, options.pm1_b2);
+    let pm1 = || {
+        info!("p−1 on C{}: B1 = {}, B2 = {}", digits, b1, b2);
+        pm1::p_minus_1(m, &primes_up_to(b2), b1, b2)
+    };
+    let pp1 = || {
+        info!("p+1 on C{}: B1 = {}, B2 = {}", digits, b1, b2);
+        pm1::p_plus_1(m, &primes_up_to(b2), b1, b2)
+    };
+    match options.algorithm {
+        FactorAlgorithm::Rho => pollard_rho(m, options.rho_iterations),
+        FactorAlgorithm::Fermat => fermat(options.fermat_steps),
+        FactorAlgorithm::Pm1 => pm1(),
+        FactorAlgorithm::Pp1 => pp1(),
+        FactorAlgorithm::Ecm => run_ecm(m, digits, &options.ecm),
+        FactorAlgorithm::Auto => fermat(AUTO_FERMAT_STEPS)
+            .or_else(|| pollard_rho(m, AUTO_RHO_ITERATIONS))
+            .or_else(|| {
+                debug!("Rho found nothing in C{}, trying p−1 and p+1", digits);
+                pm1().or_else(pp1)
+            })
+            .or_else(|| run_ecm(m, digits, &options.ecm)),
+    }
+}
+
+/// Every prime <= bound (and possibly more), from stored `nt primes` output when it reaches
+/// that far; kept for the whole process so batch runs load the stage primes once
+pub fn primes_up_to(bound: usize) -> Arc<Vec<usize>> {
+    static CACHE: Mutex<Option<(usize, Arc<Vec<usize>>)>> = Mutex::new(None);
+    // Held while loading so concurrent callers wait instead of sieving too
+    let mut cache = CACHE.lock().unwrap();
+    if let Some((covered, primes)) = cache.as_ref()
+        && *covered >= bound
+    {
+        return Arc::clone(primes);
+    }
+
+    let primes = Arc::new(crate::primes::base_primes(bound));
+    *cache = Some((bound, Arc::clone(&primes)));
+    primes
+}
+
+fn run_ecm(m: &Integer, digits: usize, params: &EcmParams) -> Option<Integer> {
+    info!(
+        "ECM on C{}: up to {} curves, B1 = {}, B2 = {}, {} workers",
+        digits, params.curves, params.b1, params.b2, params.workers
+    );
+    ecm::find_factor(m, params)
+}
+
+/// (root, k) with root^k = m and k > 1 as large as possible
+fn perfect_power(m: &Integer) -> Option<(Integer, u32)> {
+    if !m.is_perfect_power() {
+        return None;
+    }
+    // Largest exponent first so the root is not itself a power
+    (2..=m.significant_bits()).rev().find_map(|k| {
+        let (root, rem) = m.clone().root_rem(Integer::new(), k);
+        (rem == 0).then_some((root, k))
+    })
+}
+
+/// A proper divisor of the odd composite `m` by Brent's rho, or None within `max_iterations`
+/// per polynomial x² + c (three polynomials are tried)
+fn pollard_rho(m: &Integer, max_iterations: usize) -> Option<Integer> {
+    if m.is_even() {
+        return Some(Integer::from(2));
+    }
+
+    for c in 1u32..=3 {
+        let f = |x: &Integer| (Integer::from(x.square_ref()) + c).rem_euc(m);
+        let mut y = Integer::from(2);
+        let mut x = y.clone();
+        let mut ys = y.clone();
+        let mut product = Integer::from(1);
+        let mut power = 1;
+        let mut iterations = 0;
+        let mut divisor = Integer::from(1);
+
+        while divisor == 1 && iterations < max_iterations {
+            x.clone_from(&y);
+            for _ in 0..power {
+                y = f(&y);
+            }
+            let mut k = 0;
+            while k < power && divisor == 1 {
+                ys.clone_from(&y);
+                for _ in 0..RHO_BATCH.min(power - k) {
+                    y = f(&y);
+                    product = (product * Integer::from(&x - &y)).rem_euc(m);
+                }
+                divisor = Integer::from(product.gcd_ref(m));
+                k += RHO_BATCH;
+            }
+            iterations += power;
+            power *= 2;
+        }
+
+        // The batch overshot: replay it one step at a time
+        if divisor == *m {
+            loop {
+                ys = f(&ys);
+                divisor = Integer::from(Integer::from(&x - &ys).gcd_ref(m));
+                if divisor != 1 {
+                    break;
+                }
+            }
+        }
+        if divisor != 1 && divisor != *m {
+            return Some(divisor);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rug::ops::Pow;
+
+    fn options(algorithm: FactorAlgorithm) -> FactorOptions {
+        FactorOptions {
+            algorithm,
+            rho_iterations: 1 << 20,
+            fermat_steps: 1 << 20,
+            pm1_b1: 1000,
+            pm1_b2: 200_000,
+            ecm: EcmParams::new(3000, None, 40, 2),
+        }
+    }
+
+    fn parse(n: &str) -> Integer {
+        n.parse().unwrap()
+    }
+
+    #[test]
+    fn test_small_and_powers() {
+        let auto = options(FactorAlgorithm::Auto);
+        assert!(factorize(&Integer::from(1), &auto).factors.is_empty());
+        assert_eq!(
+            factorize(&Integer::from(360), &auto).to_string(),
+            "2^3 * 3^2 * 5"
+        );
+        // 1000000007^3 is beyond u64 and a perfect power
+        let cube = Integer::from(1_000_000_007u64).pow(3);
+        assert_eq!(factorize(&cube, &auto).to_string(), "1000000007^3");
+    }
+
+    #[test]
+    fn test_unicode_and_latex_formats() {
+        let result = factorize(&Integer::from(1800), &options(FactorAlgorithm::Auto));
+        assert_eq!(result.render(FactorFormat::Unicode), "2³·3²·5²");
+        assert_eq!(
+            result.render(FactorFormat::Latex),
+            "2^{3} \\cdot 3^{2} \\cdot 5^{2}"
+        );
+        let summary = result.summary().unwrap();
+        assert_eq!(
+            summary.render(FactorFormat::Unicode),
+            "Ω(n) = 7, ω(n) = 3, rad(n) = 30"
+        );
+        assert_eq!(
+            summary.render(FactorFormat::Latex),
+            "\\Omega(n) = 7,\\ \\omega(n) = 3,\\ \\operatorname{rad}(n) = 30"
+        );
+        assert_eq!(superscript(10), "¹⁰");
+
+        let partial = Factorization {
+            factors: vec![(Integer::from(3), 1)],
+            unfactored: vec![Integer::from(221)],
+            squares: Vec::new(),
+        };
+        assert_eq!(partial.render(FactorFormat::Latex), "3 \\cdot C_{3}(221)");
+        assert!(partial.summary().is_none());
+    }
+
+    #[test]
+    fn test_rho_splits_beyond_u64() {
+        let n = Integer::from(10_000_000_019u64) * 10_000_000_033u64;
+        let result = factorize(&n, &options(FactorAlgorithm::Rho));
+        assert_eq!(result.to_string(), "10000000019 * 10000000033");
+    }
+
+    #[test]
+    fn test_ecm_splits_beyond_u64() {
+        let n = parse("1000000000039") * parse("1000000000000037");
+        let result = factorize(&n, &options(FactorAlgorithm::Ecm));
+        assert!(result.is_complete(), "{}", result);
+        assert_eq!(result.to_string(), "1000000000039 * 1000000000000037");
+    }
+
+    #[test]
+    fn test_fermat_records_squares() {
+        let p = parse("100000000000000000039");
+        let q = parse("100000001000000000171");
+        let n = Integer::from(&p * &q);
+        let result = factorize(&n, &options(FactorAlgorithm::Fermat));
+        assert_eq!(result.to_string(), format!("{} * {}", p, q));
+        let (composite, squares) = &result.squares[0];
+        assert_eq!(*composite, n);
+        assert_eq!(squares.factors(), (p, q));
+    }
+
+    #[test]
+    fn test_pm1_and_pp1_split_beyond_u64() {
+        // 1000000000561 − 1 and 1000000000063 + 1 are 200000-smooth
+        let n = parse("1000000000039") * parse("1000000000561");
+        let result = factorize(&n, &options(FactorAlgorithm::Pm1));
+        assert_eq!(result.to_string(), "1000000000039 * 1000000000561");
+        let n = parse("1000000000039") * parse("1000000000063");
+        let result = factorize(&n, &options(FactorAlgorithm::Pp1));
+        assert_eq!(result.to_string(), "1000000000039 * 1000000000063");
+    }
+}
@@ -0,0 +1,239 @@
+// Baillie–PSW probable-prime test (`nt is-prime`)
+//
+// A strong Fermat test to base 2 followed by a strong Lucas test with Selfridge's
+// parameters: the first D in 5, −7, 9, −11, ... with (D | n) = −1, P = 1 and
+// Q = (1 − D)/4. The composites that fool one test are very different from those that fool
+// the other, and no number passing both has ever been found (none exists below 2^64), so
+// "probable prime" here is about as strong as a label gets without a proof. Every
+// big-integer primality check in the crate goes through `is_probable_prime`, which hands
+// numbers below 2^64 to the deterministic Miller-Rabin in factor.rs instead.
+
+use rug::Integer;
+use std::sync::OnceLock;
+
+use crate::factor;
+
+// Trial divisors before the two tests
+const SMALL_PRIMES: [u32; 25] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+// Trial division bound for numbers past 2^64, where a division is far cheaper than a
+// modular power
+const TRIAL_DIVISION_LIMIT: usize = 2000;
+
+static TRIAL_PRIMES: OnceLock<Vec<u32>> = OnceLock::new();
+
+/// The test that showed a number composite
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    TrialDivision,
+    /// Perfect squares have no D with (D | n) = −1
+    Square,
+    StrongFermat,
+    StrongLucas,
+}
+
+/// What Baillie–PSW says about a number
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Small enough for trial division to settle
+    Prime,
+    ProbablePrime,
+    Composite(Stage),
+}
+
+/// Run Baillie–PSW on `n`
+pub fn bpsw(n: &Integer) -> Verdict {
+    if *n < 2 {
+        return Verdict::Composite(Stage::TrialDivision);
+    }
+    for p in SMALL_PRIMES {
+        if n.is_divisible_u(p) {
+            return if *n == p {
+                Verdict::Prime
+            } else {
+                Verdict::Composite(Stage::TrialDivision)
+            };
+        }
+    }
+    if *n < 100 * 100 {
+        return Verdict::Prime;
+    }
+    let trial_primes = TRIAL_PRIMES.get_or_init(|| {
+        crate::primes::sieve(TRIAL_DIVISION_LIMIT)
+            .into_iter()
+            .filter(|&p| p > 100)
+            .map(|p| p as u32)
+            .collect()
+    });
+    if n.significant_bits() > 64 && trial_primes.iter().any(|&p| n.is_divisible_u(p)) {
+        return Verdict::Composite(Stage::TrialDivision);
+    }
+    if !strong_fermat_base_2(n) {
+        return Verdict::Composite(Stage::StrongFermat);
+    }
+    if n.is_perfect_square() {
+        return Verdict::Composite(Stage::Square);
+    }
+    if !strong_lucas(n) {
+        return Verdict::Composite(Stage::StrongLucas);
+    }
+    Verdict::ProbablePrime
+}
+
+/// Whether `n` is prime: exactly below 2^64, by Baillie–PSW above
+pub fn is_probable_prime(n: &Integer) -> bool {
+    match n.to_u64() {
+        Some(small) => factor::is_prime(small),
+        None => !matches!(bpsw(n), Verdict::Composite(_)),
+    }
+}
+
+/// Miller-Rabin to base 2, for odd n > 2
+fn strong_fermat_base_2(n: &Integer) -> bool {
+    let n_minus_1 = Integer::from(n - 1u32);
+    let s = n_minus_1.find_one(0).unwrap_or(0);
+    let d = Integer::from(&n_minus_1 >> s);
+    let mut x = Integer::from(Integer::from(2).pow_mod_ref(&d, n).unwrap());
+    if x == 1 || x == n_minus_1 {
+        return true;
+    }
+    for _ in 1..s {
+        x = Integer::from(x.square_ref()) % n;
+        if x == n_minus_1 {
+            return true;
+        }
+    }
+    false
+}
+
+/// The strong Lucas test with Selfridge's parameters, for odd n that is not a square
+fn strong_lucas(n: &Integer) -> bool {
+    let mut d: i64 = 5;
+    loop {
+        let jacobi = Integer::from(d).jacobi(n);
+        if jacobi == -1 {
+            break;
+        }
+        // A common factor, unless D is n itself
+        if jacobi == 0 && *n != d.unsigned_abs() {
+            return false;
+        }
+        d = if d > 0 { -(d + 2) } else { -d + 2 };
+    }
+    let p = Integer::from(1);
+    let q = Integer::from((1 - d) / 4);
+
+    // n + 1 = k × 2^s with k odd
+    let n_plus_1 = Integer::from(n + 1u32);
+    let s = n_plus_1.find_one(0).unwrap_or(0);
+    let k = Integer::from(&n_plus_1 >> s);
+    let (u, mut v, mut q_k) = lucas(&p, &q, &k, n);
+    if u == 0 || v == 0 {
+        return true;
+    }
+    for _ in 1..s {
+        // V_2j = V_j² − 2Q^j
+        v = (Integer::from(v.square_ref()) - Integer::from(&q_k * 2u32)).modulo(n);
+        if v == 0 {
+            return true;
+        }
+        q_k = Integer::from(q_k.square_ref()).modulo(n);
+    }
+    false
+}
+
+/// (U_k, V_k, Q^k) mod n for the Lucas sequences with parameters P and Q, n odd
+pub(crate) fn lucas(
+    p: &Integer,
+    q: &Integer,
+    k: &Integer,
+    n: &Integer,
+) -> (Integer, Integer, Integer) {
+    let d = Integer::from(p.square_ref()) - Integer::from(q * 4u32);
+    // Halving mod n: odd values get n added first
+    let half = |x: Integer| {
+        let x = x.modulo(n);
+        if x.is_odd() { (x + n) >> 1 } else { x >> 1 }
+    };
+    let mut u = Integer::from(0);
+    let mut v = Integer::from(2).modulo(n);
+    let mut q_k = Integer::from(1).modulo(n);
+    for bit in (0..k.significant_bits()).rev() {
+        // U_2j = U_j V_j, V_2j = V_j² − 2Q^j
+        u = Integer::from(&u * &v).modulo(n);
+        v = (Integer::from(v.square_ref()) - Integer::from(&q_k * 2u32)).modulo(n);
+        q_k = Integer::from(q_k.square_ref()).modulo(n);
+        if k.get_bit(bit) {
+            // U_(j+1) = (P U_j + V_j)/2, V_(j+1) = (D U_j + P V_j)/2
+            let u_next = half(Integer::from(p * &u) + &v);
+            v = half(Integer::from(&d * &u) + Integer::from(p * &v));
+            u = u_next;
+            q_k = Integer::from(&q_k * q).modulo(n);
+        }
+    }
+    (u, v, q_k)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lucas_matches_recurrence() {
+        // P = 1, Q = −1 gives the Fibonacci (U) and Lucas (V) numbers
+        let n = Integer::from(1_000_003);
+        let (p, q) = (Integer::from(1), Integer::from(-1));
+        let (mut u, mut v) = (vec![0i64, 1], vec![2i64, 1]);
+        for k in 2..60 {
+            u.push(u[k - 1] + u[k - 2]);
+            v.push(v[k - 1] + v[k - 2]);
+        }
+        for k in 0..60 {
+            let (u_k, v_k, _) = lucas(&p, &q, &Integer::from(k), &n);
+            assert_eq!(u_k, Integer::from(u[k]).modulo(&n), "U_{}", k);
+            assert_eq!(v_k, Integer::from(v[k]).modulo(&n), "V_{}", k);
+        }
+    }
+
+    #[test]
+    fn test_bpsw_agrees_with_exact_test() {
+        for n in (10_001u64..60_000).step_by(2) {
+            let verdict = bpsw(&Integer::from(n));
+            assert_eq!(
+                !matches!(verdict, Verdict::Composite(_)),
+                factor::is_prime(n),
+                "n {}",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn test_pseudoprimes_are_caught() {
+        // 3215031751 = 151 · 751 · 28351 is a strong pseudoprime to base 2, and 22499 =
+        // 149 · 151 and 25199 = 113 · 223 are strong Lucas pseudoprimes; none has a factor
+        // below 100, so each gets as far as the test it does not fool
+        assert_eq!(
+            bpsw(&Integer::from(3_215_031_751u64)),
+            Verdict::Composite(Stage::StrongLucas)
+        );
+        for n in [22499, 25199] {
+            assert_eq!(
+                bpsw(&Integer::from(n)),
+                Verdict::Composite(Stage::StrongFermat),
+                "n {}",
+                n
+            );
+            assert!(strong_lucas(&Integer::from(n)), "n {}", n);
+        }
+        // 2^127 − 1 and (2^127 − 1)²
+        let m127 = (Integer::from(1) << 127) - 1u32;
+        assert_eq!(bpsw(&m127), Verdict::ProbablePrime);
+        assert_eq!(
+            bpsw(&Integer::from(m127.square_ref())),
+            Verdict::Composite(Stage::StrongFermat)
+        );
+    }
+}
@@ -0,0 +1,95 @@
+// Recycling pool for segment buffers (variations 6-9)
+//
+// Producers take a buffer per segment and consumers hand it back through a second
+// channel once the primes are written, so after warm-up a run allocates nothing new.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// Pool of reusable Vec<T> buffers shared by producers and consumers
+/// Cloning is cheap; every clone refers to the same pool
+pub struct BufferPool<T> {
+    returns: Sender<Vec<T>>,
+    free: Arc<Mutex<Receiver<Vec<T>>>>,
+    allocations: Arc<AtomicUsize>,
+}
+
+impl<T> Clone for BufferPool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            returns: self.returns.clone(),
+            free: Arc::clone(&self.free),
+            allocations: Arc::clone(&self.allocations),
+        }
+    }
+}
+
+impl<T> Default for BufferPool<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> BufferPool<T> {
+    pub fn new() -> Self {
+        let (returns, free) = mpsc::channel();
+        Self {
+            returns,
+            free: Arc::new(Mutex::new(free)),
+            allocations: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Take an empty buffer, reusing a returned one when available
+    /// Only allocates (with `capacity`) when every buffer is still in flight
+    pub fn take(&self, capacity: usize) -> Vec<T> {
+        let recycled = self.free.lock().unwrap().try_recv();
+
+        match recycled {
+            Ok(mut buffer) => {
+                buffer.clear();
+                buffer
+            }
+            Err(_) => {
+                self.allocations.fetch_add(1, Ordering::Relaxed);
+                Vec::with_capacity(capacity)
+            }
+        }
+    }
+
+    /// Return a buffer to the pool once its contents have been consumed
+    pub fn give_back(&self, buffer: Vec<T>) {
+        // Ignore errors: the pool outlives every handle that can send to it
+        let _ = self.returns.send(buffer);
+    }
+
+    /// Number of buffers allocated so far (stays flat once the pool is warm)
+    pub fn allocations(&self) -> usize {
+        self.allocations.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_returned_buffers_are_reused() {
+        let pool: BufferPool<usize> = BufferPool::new();
+
+        let mut first = pool.take(16);
+        first.extend_from_slice(&[2, 3, 5]);
+        let capacity = first.capacity();
+        pool.give_back(first);
+
+        let second = pool.take(16);
+        assert!(second.is_empty());
+        assert_eq!(second.capacity(), capacity);
+        assert_eq!(pool.allocations(), 1);
+
+        // Nothing returned yet, so a third buffer must be allocated
+        let _third = pool.take(16);
+        assert_eq!(pool.allocations(), 2);
+    }
+}
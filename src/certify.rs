@@ -0,0 +1,433 @@
+// Pratt primality certificates (`nt certify`)
+//
+// Miller-Rabin only ever says "probably prime". A Pratt certificate proves it: p is prime
+// when some a has a^(p−1) ≡ 1 (mod p) but a^((p−1)/q) ≢ 1 for every prime q dividing p − 1,
+// since a then has order p − 1 and every number below p is coprime to p. Each q needs a
+// proof of its own, down to 2, so a certificate lists a witness and the factorization of
+// p − 1 for n and for every prime in those factorizations. Checking one takes a few
+// modular powers per entry and trusts nothing else; building one needs each p − 1 in the
+// chain factored completely, which bigfactor.rs manages far beyond the sieve's range but
+// not for every n.
+//
+// Certificates are JSON with the numbers as strings, as in `nt factor --stdin`:
+//   { "n": "...", "certificates": [
+//     { "p": "...", "witness": "...", "factors": [ { "p": "...", "e": 1 } ] } ] }
+// with one entry per prime, largest first.
+
+use rug::Integer;
+use rug::ops::Pow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Write as _;
+
+use crate::bigfactor::{self, FactorOptions};
+use crate::bpsw;
+
+// Witnesses tried per prime; the least primitive root is almost always far smaller
+const MAX_WITNESS: u32 = 100_000;
+
+/// The proof that one prime p is prime
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub p: Integer,
+    /// An element of order p − 1 modulo p
+    pub witness: Integer,
+    /// p − 1 as (prime, exponent) pairs
+    pub factors: Vec<(Integer, u32)>,
+}
+
+/// A Pratt certificate for `n`: a step for n and for every prime its steps rely on
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Certificate {
+    pub n: Integer,
+    pub steps: Vec<Step>,
+}
+
+/// Why no certificate could be built
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CertifyError {
+    /// The number is composite
+    Composite(Integer),
+    /// p − 1 could not be factored completely for this p
+    Unfactored(Integer),
+    /// No witness was found for this p, which is then almost surely composite
+    NoWitness(Integer),
+}
+
+impl fmt::Display for CertifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CertifyError::Composite(p) => write!(f, "{} is composite", p),
+            CertifyError::Unfactored(p) => {
+                write!(f, "could not factor {} − 1 completely", p)
+            }
+            CertifyError::NoWitness(p) => {
+                write!(f, "no witness below {} for {}", MAX_WITNESS, p)
+            }
+        }
+    }
+}
+
+/// Build a certificate that `n` is prime, factoring with `options`
+pub fn certify(n: &Integer, options: &FactorOptions) -> Result<Certificate, CertifyError> {
+    if *n < 2 || !bpsw::is_probable_prime(n) {
+        return Err(CertifyError::Composite(n.clone()));
+    }
+    let mut steps: BTreeMap<Integer, Step> = BTreeMap::new();
+    let mut pending = vec![n.clone()];
+    while let Some(p) = pending.pop() {
+        if steps.contains_key(&p) {
+            continue;
+        }
+        let step = prove(&p, options)?;
+        pending.extend(step.factors.iter().map(|(q, _)| q.clone()));
+        steps.insert(p, step);
+    }
+    Ok(Certificate {
+        n: n.clone(),
+        steps: steps.into_values().rev().collect(),
+    })
+}
+
+/// Factor p − 1 and find a witness for p
+fn prove(p: &Integer, options: &FactorOptions) -> Result<Step, CertifyError> {
+    let m = Integer::from(p - 1u32);
+    let factorization = bigfactor::factorize(&m, options);
+    if !factorization.is_complete() {
+        return Err(CertifyError::Unfactored(p.clone()));
+    }
+    // 1 is the witness for 2, where p − 1 has no prime factors to rule out
+    for a in 1..MAX_WITNESS {
+        let a = Integer::from(a);
+        if pow_mod(&a, &m, p) != 1 {
+            return Err(CertifyError::Composite(p.clone()));
+        }
+        if has_full_order(&a, &m, p, &factorization.factors) {
+            return Ok(Step {
+                p: p.clone(),
+                witness: a,
+                factors: factorization.factors,
+            });
+        }
+    }
+    Err(CertifyError::NoWitness(p.clone()))
+}
+
+fn pow_mod(a: &Integer, exponent: &Integer, p: &Integer) -> Integer {
+    Integer::from(a.pow_mod_ref(exponent, p).unwrap())
+}
+
+/// Whether a^((p−1)/q) ≢ 1 (mod p) for every q in `factors`
+fn has_full_order(a: &Integer, m: &Integer, p: &Integer, factors: &[(Integer, u32)]) -> bool {
+    factors
+        .iter()
+        .all(|(q, _)| pow_mod(a, &Integer::from(m / q), p) != 1)
+}
+
+/// Check every step of `certificate`; Ok means n is prime
+pub fn verify(certificate: &Certificate) -> Result<(), String> {
+    let by_prime: BTreeMap<&Integer, &Step> = certificate
+        .steps
+        .iter()
+        .map(|step| (&step.p, step))
+        .collect();
+    if !by_prime.contains_key(&certificate.n) {
+        return Err(format!("no entry for n = {}", certificate.n));
+    }
+    for step in &certificate.steps {
+        let p = &step.p;
+        if *p < 2 {
+            return Err(format!("{} is not a prime candidate", p));
+        }
+        let m = Integer::from(p - 1u32);
+        let mut product = Integer::from(1);
+        for (q, e) in &step.factors {
+            if *q < 2 || *e == 0 {
+                return Err(format!("{}: bad factor {}^{}", p, q, e));
+            }
+            if !by_prime.contains_key(q) {
+                return Err(format!("{}: factor {} has no entry of its own", p, q));
+            }
+            product *= q.clone().pow(*e);
+        }
+        if product != m {
+            return Err(format!(
+                "{}: the factors multiply to {}, not {}",
+                p, product, m
+            ));
+        }
+        if pow_mod(&step.witness, &m, p) != 1 {
+            return Err(format!(
+                "{}: {}^(p−1) is not 1 (mod p), so p is composite",
+                p, step.witness
+            ));
+        }
+        if let Some((q, _)) = step
+            .factors
+            .iter()
+            .find(|(q, _)| pow_mod(&step.witness, &Integer::from(&m / q), p) == 1)
+        {
+            return Err(format!(
+                "{}: {}^((p−1)/{}) is 1 (mod p), so {} is not a witness",
+                p, step.witness, q, step.witness
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl Certificate {
+    /// The certificate as a JSON object
+    pub fn to_json(&self) -> String {
+        let mut json = format!("{{\n  \"n\": \"{}\",\n  \"certificates\": [\n", self.n);
+        for (i, step) in self.steps.iter().enumerate() {
+            let factors: Vec<String> = step
+                .factors
+                .iter()
+                .map(|(q, e)| format!("{{ \"p\": \"{}\", \"e\": {} }}", q, e))
+                .collect();
+            let comma = if i + 1 < self.steps.len() { "," } else { "" };
+            let _ = writeln!(
+                json,
+                "    {{ \"p\": \"{}\", \"witness\": \"{}\", \"factors\": [{}] }}{}",
+                step.p,
+                step.witness,
+                factors.join(", "),
+                comma
+            );
+        }
+        json.push_str("  ]\n}\n");
+        json
+    }
+
+    /// Read a certificate written by `to_json`
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            text: text.as_bytes(),
+            at: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.at != text.len() {
+            return Err(format!("unexpected text at byte {}", parser.at));
+        }
+
+        let steps = value
+            .field("certificates")?
+            .array()?
+            .iter()
+            .map(|entry| {
+                let factors = entry
+                    .field("factors")?
+                    .array()?
+                    .iter()
+                    .map(|factor| {
+                        let e = factor.field("e")?.number()?;
+                        let e =
+                            u32::try_from(e).map_err(|_| format!("exponent {} too large", e))?;
+                        Ok((factor.field("p")?.integer()?, e))
+                    })
+                    .collect::<Result<_, String>>()?;
+                Ok(Step {
+                    p: entry.field("p")?.integer()?,
+                    witness: entry.field("witness")?.integer()?,
+                    factors,
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            n: value.field("n")?.integer()?,
+            steps,
+        })
+    }
+}
+
+// Just enough JSON for certificates: objects, arrays, strings and non-negative integers
+#[derive(Debug)]
+enum Json {
+    Object(Vec<(String, Json)>),
+    Array(Vec<Json>),
+    String(String),
+    Number(u64),
+}
+
+impl Json {
+    fn field(&self, name: &str) -> Result<&Json, String> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value)
+                .ok_or_else(|| format!("missing \"{}\"", name)),
+            _ => Err(format!("expected an object with \"{}\"", name)),
+        }
+    }
+
+    fn array(&self) -> Result<&[Json], String> {
+        match self {
+            Json::Array(items) => Ok(items),
+            _ => Err("expected an array".to_string()),
+        }
+    }
+
+    fn number(&self) -> Result<u64, String> {
+        match self {
+            Json::Number(n) => Ok(*n),
+            _ => Err("expected a number".to_string()),
+        }
+    }
+
+    /// A big integer, written as a decimal string
+    fn integer(&self) -> Result<Integer, String> {
+        match self {
+            Json::String(digits) => digits
+                .parse()
+                .map_err(|_| format!("'{}' is not an integer", digits)),
+            Json::Number(n) => Ok(Integer::from(*n)),
+            _ => Err("expected an integer string".to_string()),
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.at < self.text.len() && self.text[self.at].is_ascii_whitespace() {
+            self.at += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_whitespace();
+        if self.text.get(self.at) == Some(&byte) {
+            self.at += 1;
+            Ok(())
+        } else {
+            Err(format!("expected '{}' at byte {}", byte as char, self.at))
+        }
+    }
+
+    /// Skip `byte` if it comes next
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.text.get(self.at) == Some(&byte);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.text.get(self.at) {
+            Some(b'{') => {
+                self.at += 1;
+                let mut fields = Vec::new();
+                if !self.eat(b'}') {
+                    loop {
+                        self.skip_whitespace();
+                        let key = self.string()?;
+                        self.expect(b':')?;
+                        fields.push((key, self.value()?));
+                        if !self.eat(b',') {
+                            break;
+                        }
+                    }
+                    self.expect(b'}')?;
+                }
+                Ok(Json::Object(fields))
+            }
+            Some(b'[') => {
+                self.at += 1;
+                let mut items = Vec::new();
+                if !self.eat(b']') {
+                    loop {
+                        items.push(self.value()?);
+                        if !self.eat(b',') {
+                            break;
+                        }
+                    }
+                    self.expect(b']')?;
+                }
+                Ok(Json::Array(items))
+            }
+            Some(b'"') => Ok(Json::String(self.string()?)),
+            Some(b'0'..=b'9') => {
+                let start = self.at;
+                while self.at < self.text.len() && self.text[self.at].is_ascii_digit() {
+                    self.at += 1;
+                }
+                let digits = std::str::from_utf8(&self.text[start..self.at]).unwrap();
+                digits
+                    .parse()
+                    .map(Json::Number)
+                    .map_err(|_| format!("number {} too large", digits))
+            }
+            _ => Err(format!("unexpected input at byte {}", self.at)),
+        }
+    }
+
+    /// A string without escapes, which certificates never need
+    fn string(&mut self) -> Result<String, String> {
+        if self.text.get(self.at) != Some(&b'"') {
+            return Err(format!("expected a string at byte {}", self.at));
+        }
+        let start = self.at + 1;
+        let Some(length) = self.text[start..].iter().position(|&b| b == b'"') else {
+            return Err("unterminated string".to_string());
+        };
+        let content = &self.text[start..start + length];
+        if content.contains(&b'\\') {
+            return Err(format!("escapes are not supported (byte {})", start));
+        }
+        self.at = start + length + 1;
+        String::from_utf8(content.to_vec()).map_err(|_| "string is not UTF-8".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> FactorOptions {
+        FactorOptions {
+            ecm: crate::ecm::EcmParams::new(50_000, None, 50, 1),
+            ..FactorOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_certificate_round_trip() {
+        // 2^61 − 1, a Mersenne prime
+        let n = Integer::from(2305843009213693951u64);
+        let certificate = certify(&n, &options()).unwrap();
+        assert_eq!(certificate.steps[0].p, n);
+        assert_eq!(certificate.steps.last().unwrap().p, 2);
+        assert_eq!(verify(&certificate), Ok(()));
+
+        let parsed = Certificate::from_json(&certificate.to_json()).unwrap();
+        assert_eq!(parsed, certificate);
+    }
+
+    #[test]
+    fn test_composites_and_forgeries_fail() {
+        assert_eq!(
+            certify(&Integer::from(561), &options()),
+            Err(CertifyError::Composite(Integer::from(561)))
+        );
+
+        let mut certificate = certify(&Integer::from(1_000_003), &options()).unwrap();
+        // A witness of smaller order than p − 1 proves nothing
+        certificate.steps[0].witness = Integer::from(1);
+        assert!(verify(&certificate).is_err());
+
+        // Dropping the proof for a factor leaves it unproven
+        let mut certificate = certify(&Integer::from(1_000_003), &options()).unwrap();
+        certificate.steps.pop();
+        assert!(verify(&certificate).unwrap_err().contains("has no entry"));
+    }
+}
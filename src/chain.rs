@@ -1,44 +1,56 @@
-use crate::storage;
-use std::collections::HashMap;
-use std::collections::hash_map::RandomState;
-use std::hash::{BuildHasher, Hash, Hasher};
+use crate::random::Rng;
+use crate::storage::{self, commit_output, get_nt_data_dir, staging_path};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use tracing::error;
 
-fn shuffle<T>(vec: &mut Vec<T>) {
-    let random_state = RandomState::new();
-    let len = vec.len();
+fn shuffle<T>(vec: &mut [T], rng: &mut Rng) {
+    for i in (1..vec.len()).rev() {
+        // Random index from 0 to i (inclusive)
+        let j = rng.below(i as u64 + 1) as usize;
+        vec.swap(i, j);
+    }
+}
 
-    for i in (1..len).rev() {
-        // Generate random index from 0 to i (inclusive)
-        let mut hasher = random_state.build_hasher();
-        i.hash(&mut hasher);
-        let j = (hasher.finish() as usize) % (i + 1);
+/// Primes with at least overlap + 1 digits, as strings
+fn chain_primes(primes: Vec<usize>, overlap: usize) -> Vec<String> {
+    let min_digits = overlap + 1; // Need at least overlap + 1 digits to be useful
+    primes
+        .into_iter()
+        .map(|p| p.to_string())
+        .filter(|p| p.len() >= min_digits)
+        .collect()
+}
 
-        vec.swap(i, j);
+/// Map from first N digits to list of primes starting with those digits
+fn build_prefix_index(primes: &[String], overlap: usize) -> HashMap<String, Vec<String>> {
+    let mut prefix_index: HashMap<String, Vec<String>> = HashMap::new();
+    for prime in primes {
+        if prime.len() >= overlap {
+            let prefix = prime[..overlap].to_string();
+            prefix_index.entry(prefix).or_default().push(prime.clone());
+        }
     }
+    prefix_index
 }
 
 pub fn build_chain(overlap: usize, target_length: usize) {
-    // Load primes from primes.txt
+    // Load primes from primes.txt (or variation 9 shards)
     let primes = match storage::load_all_primes() {
         Ok(primes) => primes,
         Err(e) => {
-            eprintln!("Error loading primes.txt: {}", e);
+            error!("Error loading primes: {}", e);
             return;
         }
     };
 
-    // Filter primes that have at least 'overlap' digits
-    let min_digits = overlap + 1; // Need at least overlap + 1 digits to be useful
-    let valid_primes: Vec<String> = primes
-        .into_iter()
-        .map(|p| p.to_string())
-        .filter(|p| p.len() >= min_digits)
-        .collect();
-
+    let valid_primes = chain_primes(primes, overlap);
     if valid_primes.is_empty() {
-        eprintln!(
+        error!(
             "No primes with at least {} digits found in primes.txt",
-            min_digits
+            overlap + 1
         );
         return;
     }
@@ -48,17 +60,8 @@ pub fn build_chain(overlap: usize, target_length: usize) {
     println!("Available primes: {}", valid_primes.len());
     println!();
 
-    // Build index: map from first N digits to list of primes starting with those digits
-    let mut prefix_index: HashMap<String, Vec<String>> = HashMap::new();
-    for prime in &valid_primes {
-        if prime.len() >= overlap {
-            let prefix = prime[..overlap].to_string();
-            prefix_index
-                .entry(prefix)
-                .or_insert_with(Vec::new)
-                .push(prime.clone());
-        }
-    }
+    let prefix_index = build_prefix_index(&valid_primes, overlap);
+    let mut rng = Rng::from_entropy();
 
     // Try to build a chain starting from different primes
     let mut best_chain = String::new();
@@ -68,7 +71,7 @@ pub fn build_chain(overlap: usize, target_length: usize) {
     for start_prime in &valid_primes {
         attempts += 1;
         let (chain, chain_primes) =
-            build_chain_from_start(start_prime, overlap, target_length, &prefix_index);
+            build_chain_from_start(start_prime, overlap, target_length, &prefix_index, &mut rng);
 
         if chain.len() > best_chain.len() {
             best_chain = chain;
@@ -108,6 +111,7 @@ fn build_chain_from_start(
     overlap: usize,
     target_length: usize,
     prefix_index: &HashMap<String, Vec<String>>,
+    rng: &mut Rng,
 ) -> (String, Vec<String>) {
     let mut chain = start_prime.to_string();
     let mut used_primes = vec![start_prime.to_string()];
@@ -129,7 +133,7 @@ fn build_chain_from_start(
             None => break, // No matching primes found
         };
 
-        shuffle(&mut candidates);
+        shuffle(&mut candidates, rng);
 
         // Find a prime we haven't used yet
         let next_prime = candidates.iter().find(|p| !used_set.contains(*p));
@@ -148,3 +152,379 @@ fn build_chain_from_start(
 
     (chain, used_primes)
 }
+
+/// What the runs of `nt chain --tournament` built
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Tournament {
+    /// Digits in each run's chain, capped at the target length
+    pub lengths: Vec<usize>,
+    /// The longest chain (the first one, on ties) and its primes
+    pub best: (String, Vec<String>),
+    /// Primes used over all runs, and how many of them were different
+    pub primes_used: usize,
+    pub distinct_primes: usize,
+    /// The prime the most runs used, and how many
+    pub most_used: Option<(String, usize)>,
+}
+
+impl Tournament {
+    pub fn mean_length(&self) -> f64 {
+        self.lengths.iter().sum::<usize>() as f64 / self.lengths.len().max(1) as f64
+    }
+
+    pub fn max_length(&self) -> usize {
+        self.lengths.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Share of prime uses that repeat a prime an earlier run used
+    pub fn reuse(&self) -> f64 {
+        if self.primes_used == 0 {
+            return 0.0;
+        }
+        1.0 - self.distinct_primes as f64 / self.primes_used as f64
+    }
+}
+
+/// Build `runs` chains from random starting primes, each taking a random unused
+/// continuation at every step
+pub fn tournament(
+    valid_primes: &[String],
+    overlap: usize,
+    target_length: usize,
+    runs: usize,
+    rng: &mut Rng,
+) -> Tournament {
+    let prefix_index = build_prefix_index(valid_primes, overlap);
+    let mut result = Tournament::default();
+    let mut uses: HashMap<String, usize> = HashMap::new();
+    for _ in 0..runs {
+        let start = &valid_primes[rng.below(valid_primes.len() as u64) as usize];
+        let (mut chain, primes) =
+            build_chain_from_start(start, overlap, target_length, &prefix_index, rng);
+        chain.truncate(target_length);
+        result.lengths.push(chain.len());
+        result.primes_used += primes.len();
+        for prime in &primes {
+            *uses.entry(prime.clone()).or_default() += 1;
+        }
+        if chain.len() > result.best.0.len() {
+            result.best = (chain, primes);
+        }
+    }
+    result.distinct_primes = uses.len();
+    result.most_used = uses
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)));
+    result
+}
+
+/// Where the best tournament chain is kept for `overlap` and a prime set, named by the
+/// set's size and largest prime so that chains from other prime files don't compete
+pub fn best_chain_path(overlap: usize, valid_primes: &[String]) -> PathBuf {
+    let largest = valid_primes.last().map_or("none", |p| p.as_str());
+    get_nt_data_dir().join("chains").join(format!(
+        "overlap_{}_primes_{}_to_{}.txt",
+        overlap,
+        valid_primes.len(),
+        largest
+    ))
+}
+
+/// The chain saved at `path` (the chain on the first line, then one prime per line)
+pub fn read_best(path: &Path) -> Option<(String, Vec<String>)> {
+    let text = fs::read_to_string(path).ok()?;
+    let mut lines = text.lines();
+    let chain = lines.next()?.to_string();
+    Some((chain, lines.map(str::to_string).collect()))
+}
+
+/// Save `best` at `path` if it is longer than the chain already there; returns whether it was
+pub fn update_best(path: &Path, best: &(String, Vec<String>)) -> io::Result<bool> {
+    if read_best(path).is_some_and(|(chain, _)| chain.len() >= best.0.len()) {
+        return Ok(false);
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut text = format!("{}\n", best.0);
+    for prime in &best.1 {
+        text.push_str(prime);
+        text.push('\n');
+    }
+    fs::write(staging_path(path), text)?;
+    commit_output(path)?;
+    Ok(true)
+}
+
+/// `nt chain --tournament`: run `runs` random chains on the stored primes, print how long
+/// they got and how much they shared primes, and keep the best one in the data directory
+pub fn run_tournament(overlap: usize, target_length: usize, runs: usize, seed: Option<u64>) {
+    let primes = match storage::load_all_primes() {
+        Ok(primes) => primes,
+        Err(e) => {
+            error!("Error loading primes: {}", e);
+            return;
+        }
+    };
+    let valid_primes = chain_primes(primes, overlap);
+    if valid_primes.is_empty() {
+        error!(
+            "No primes with at least {} digits found in primes.txt",
+            overlap + 1
+        );
+        return;
+    }
+    let mut rng = match seed {
+        Some(seed) => Rng::new(seed),
+        None => Rng::from_entropy(),
+    };
+
+    let result = tournament(&valid_primes, overlap, target_length, runs, &mut rng);
+    let mut sorted = result.lengths.clone();
+    sorted.sort_unstable();
+    println!(
+        "{} runs with {} digit overlap, target {} digits:",
+        runs, overlap, target_length
+    );
+    println!(
+        "Length: mean {:.1}, median {}, min {}, max {}",
+        result.mean_length(),
+        sorted[sorted.len() / 2],
+        sorted[0],
+        result.max_length()
+    );
+    println!(
+        "Reached the target: {} of {} runs",
+        sorted.iter().filter(|&&len| len >= target_length).count(),
+        runs
+    );
+    println!(
+        "Primes used: {} ({} different, {:.1}% reuse)",
+        result.primes_used,
+        result.distinct_primes,
+        100.0 * result.reuse()
+    );
+    if let Some((prime, count)) = &result.most_used {
+        println!("Most used: {} in {} runs", prime, count);
+    }
+
+    let path = best_chain_path(overlap, &valid_primes);
+    match update_best(&path, &result.best) {
+        Ok(true) => println!(
+            "\nNew best chain of {} digits, saved to {}:\n{}",
+            result.best.0.len(),
+            path.display(),
+            result.best.0
+        ),
+        Ok(false) => {
+            if let Some((chain, _)) = read_best(&path) {
+                println!(
+                    "\nBest so far is {} digits ({}):\n{}",
+                    chain.len(),
+                    path.display(),
+                    chain
+                );
+            }
+        }
+        Err(e) => error!("Error saving {}: {}", path.display(), e),
+    }
+}
+
+// Most next primes listed at each step of an interactive chain
+const MAX_LISTED: usize = 20;
+
+/// Unused primes that can follow `chain`, in increasing order
+fn next_primes<'a>(
+    chain: &str,
+    overlap: usize,
+    prefix_index: &'a HashMap<String, Vec<String>>,
+    used: &HashSet<String>,
+) -> Vec<&'a String> {
+    let suffix = &chain[chain.len() - overlap..];
+    let mut next: Vec<&String> = prefix_index
+        .get(suffix)
+        .map(|primes| primes.iter().filter(|p| !used.contains(*p)).collect())
+        .unwrap_or_default();
+    next.sort_by_key(|p| (p.len(), p.to_string()));
+    next
+}
+
+/// Build a chain by hand: the first line of `input` is the starting prime, then each line
+/// picks the next prime by its number in the list (or the prime itself), "u" takes the
+/// last one back and "q" stops. Returns the primes used
+pub fn interactive_session(
+    primes: Vec<usize>,
+    overlap: usize,
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<Vec<String>> {
+    let valid_primes = chain_primes(primes, overlap);
+    let known: HashSet<&String> = valid_primes.iter().collect();
+    let prefix_index = build_prefix_index(&valid_primes, overlap);
+
+    let mut used_primes: Vec<String> = Vec::new();
+    let mut used_set = HashSet::new();
+    // Digit count of the chain after each prime
+    let mut lengths: Vec<usize> = Vec::new();
+    let mut chain = String::new();
+    writeln!(output, "Starting prime ({} or more digits):", overlap + 1)?;
+    for line in input.lines() {
+        let line = line?;
+        let choice = line.trim();
+        let options = if used_primes.is_empty() {
+            Vec::new()
+        } else {
+            next_primes(&chain, overlap, &prefix_index, &used_set)
+        };
+        let picked = match choice {
+            "q" | "quit" => break,
+            "u" | "undo" => {
+                if let Some(prime) = used_primes.pop() {
+                    used_set.remove(&prime);
+                    lengths.pop();
+                    chain.truncate(lengths.last().copied().unwrap_or(0));
+                }
+                None
+            }
+            _ if used_primes.is_empty() => {
+                if !known.contains(&choice.to_string()) {
+                    writeln!(
+                        output,
+                        "{} is not a stored prime with {} or more digits",
+                        choice,
+                        overlap + 1
+                    )?;
+                    continue;
+                }
+                Some(choice.to_string())
+            }
+            _ => {
+                let by_number = choice
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&i| (1..=options.len().min(MAX_LISTED)).contains(&i))
+                    .map(|i| options[i - 1]);
+                let by_value = options.iter().copied().find(|p| p.as_str() == choice);
+                match by_number.or(by_value) {
+                    Some(prime) => Some(prime.clone()),
+                    None => {
+                        writeln!(output, "{} cannot come next", choice)?;
+                        continue;
+                    }
+                }
+            }
+        };
+
+        if let Some(prime) = picked {
+            if used_primes.is_empty() {
+                chain.push_str(&prime);
+            } else {
+                chain.push_str(&prime[overlap..]);
+            }
+            used_set.insert(prime.clone());
+            used_primes.push(prime);
+            lengths.push(chain.len());
+        }
+        if used_primes.is_empty() {
+            writeln!(output, "Starting prime ({} or more digits):", overlap + 1)?;
+            continue;
+        }
+
+        let options = next_primes(&chain, overlap, &prefix_index, &used_set);
+        writeln!(
+            output,
+            "\n{} ({} digits, {} primes)",
+            chain,
+            chain.len(),
+            used_primes.len()
+        )?;
+        writeln!(
+            output,
+            "Suffix {}: {} next primes",
+            &chain[chain.len() - overlap..],
+            options.len()
+        )?;
+        for (i, prime) in options.iter().take(MAX_LISTED).enumerate() {
+            // Continuations left after picking this one
+            let chain_after = format!("{}{}", chain, &prime[overlap..]);
+            let onward = next_primes(&chain_after, overlap, &prefix_index, &used_set)
+                .into_iter()
+                .filter(|p| p != prime)
+                .count();
+            writeln!(output, "{:>4}. {} ({} after)", i + 1, prime, onward)?;
+        }
+        if options.is_empty() {
+            writeln!(output, "Dead end: u to take the last prime back, q to stop")?;
+        }
+    }
+    Ok(used_primes)
+}
+
+/// Interactive chain building on the stored primes, reading choices from stdin
+pub fn run_interactive(overlap: usize) {
+    let primes = match storage::load_all_primes() {
+        Ok(primes) => primes,
+        Err(e) => {
+            error!("Error loading primes: {}", e);
+            return;
+        }
+    };
+    let mut output = io::stdout().lock();
+    match interactive_session(primes, overlap, io::stdin().lock(), &mut output) {
+        Ok(used) if !used.is_empty() => {
+            println!("\nPrimes used ({}):", used.len());
+            for (i, prime) in used.iter().enumerate() {
+                println!("{}. {}", i + 1, prime);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => error!("Error: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interactive_session() {
+        let primes = crate::primes::sieve(1000);
+        let mut output = Vec::new();
+        // 13, then 37 by value and 73 by its number (after 71); 31 is taken back
+        let input = "4\n13\n37\n2\n99\n1\nu\nq\n";
+        let used = interactive_session(primes, 1, input.as_bytes(), &mut output).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.contains("4 is not a stored prime"));
+        assert!(text.contains("\n137 (3 digits, 2 primes)"));
+        assert!(text.contains("\n13731 (5 digits, 4 primes)"));
+        assert!(text.contains("99 cannot come next"));
+        assert_eq!(used, ["13", "37", "73"]);
+    }
+
+    #[test]
+    fn test_tournament() {
+        let valid_primes = chain_primes(crate::primes::sieve(10_000), 2);
+        let result = tournament(&valid_primes, 2, 200, 50, &mut Rng::new(7));
+        assert_eq!(result.lengths.len(), 50);
+        assert_eq!(result.best.0.len(), result.max_length());
+        assert!(result.lengths.iter().all(|&len| (3..=200).contains(&len)));
+        assert!(result.distinct_primes <= result.primes_used);
+        // Each prime of the best chain overlaps the one before by two digits
+        let (chain, primes) = &result.best;
+        assert!(chain.starts_with(&primes[0]));
+        for pair in primes.windows(2) {
+            assert_eq!(pair[0][pair[0].len() - 2..], pair[1][..2]);
+        }
+        // Same seed, same tournament
+        assert_eq!(
+            tournament(&valid_primes, 2, 200, 50, &mut Rng::new(7)),
+            result
+        );
+
+        let path = std::env::temp_dir().join(format!("nt_chain_{}.txt", std::process::id()));
+        assert!(update_best(&path, &result.best).unwrap());
+        assert!(!update_best(&path, &("1193".to_string(), vec![])).unwrap());
+        assert_eq!(read_best(&path).unwrap(), result.best);
+        fs::remove_file(&path).unwrap();
+    }
+}
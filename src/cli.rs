@@ -0,0 +1,1791 @@
+// Command line definition shared by argument parsing and the generated docs
+//
+// Kept apart from main so `nt completions` and `nt man` can build the same clap::Command
+// the parser uses, and new subcommands show up in both without extra wiring.
+
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::numeric_arg;
+use nt_core::abc;
+use nt_core::arith::ArithFunction;
+use nt_core::bigfactor::{FactorAlgorithm, FactorFormat};
+use nt_core::convert::Format as StoredFormat;
+use nt_core::data;
+use nt_core::digit_source::{self, SourceSpec};
+use nt_core::distributed::DistributedRole;
+use nt_core::export::ExportFormat;
+use nt_core::gpu::SieveBackend;
+use nt_core::import::InputFormat;
+use nt_core::primes_bases::RowOrder;
+use nt_core::rational::Ratio;
+use nt_core::scan::{HeatmapFormat, Pattern};
+use nt_core::sequence::DigitSequence;
+use nt_core::storage_writer::TeeTarget;
+use nt_core::stream_output::OutputTarget;
+use nt_core::watchdog::OnLimit;
+
+#[derive(Parser)]
+#[command(name = "nt")]
+#[command(about = "Number Theory CLI - Various number theory programs", long_about = None)]
+pub struct Cli {
+    #[arg(short, long, help = "Only print errors")]
+    pub quiet: bool,
+    #[arg(
+        short,
+        long,
+        action = clap::ArgAction::Count,
+        help = "Print more detail (-v for memory reports, -vv for everything)"
+    )]
+    pub verbose: u8,
+    #[command(subcommand)]
+    pub command: Commands,
+}
+
+#[derive(Subcommand)]
+pub enum Commands {
+    #[command(about = "Find all prime numbers up to a given limit")]
+    Primes(PrimesArgs),
+    #[command(about = "Find all prime numbers up to a given limit (storing all in memory)")]
+    PrimesAllMem(PrimesAllMemArgs),
+    #[command(about = "Output primes from primes.txt (or stdin) as different bases")]
+    PrimesBases(PrimesBasesArgs),
+    #[command(about = "Calculate and print pi to a specified number of decimal places")]
+    Pi(PiArgs),
+    #[command(about = "Calculate the Euler–Mascheroni constant γ and search its digits for primes")]
+    Gamma(GammaArgs),
+    #[command(about = "Generate random digits and search them for primes or other patterns")]
+    Random(RandomArgs),
+    #[command(about = "Generate random primes with a given number of digits or digit pattern")]
+    RandomPrime(RandomPrimeArgs),
+    #[command(about = "Build a chain of overlapping primes")]
+    Chain(ChainArgs),
+    #[command(about = "Export stored primes to other formats for analytics tools")]
+    Export(ExportArgs),
+    #[command(about = "Compare two prime files and list the primes only one of them has")]
+    Diff(DiffArgs),
+    #[command(about = "Index of a prime among the stored primes, from a sparse index")]
+    Rank(RankArgs),
+    #[command(about = "The kth stored prime (from 1), from a sparse index")]
+    Select(SelectArgs),
+    #[command(about = "Convert a prime file between text, u64le, delta-varint and bitset")]
+    Convert(ConvertArgs),
+    #[command(about = "Import prime lists from other programs (e.g. primesieve) into primes.txt")]
+    Import(ImportArgs),
+    #[command(about = "List, size and clean files in the nt data directory")]
+    Data(DataArgs),
+    #[command(about = "Find the first prime pair for each even gap and write them as CSV")]
+    GapFirsts(GapFirstsArgs),
+    #[command(about = "Factor an integer of any size")]
+    Factor(FactorArgs),
+    #[command(about = "Sieve a table of φ(n), μ(n), σ(n) or s(n) for every n up to a limit")]
+    Arith(ArithArgs),
+    #[command(about = "List the perfect numbers in a saved aliquot table")]
+    Perfect(PerfectArgs),
+    #[command(about = "List the amicable pairs in a saved aliquot table")]
+    Amicable(AmicableArgs),
+    #[command(about = "List the untouchable numbers a saved aliquot table settles")]
+    Untouchable(UntouchableArgs),
+    #[command(about = "Count the primes up to x exactly, or estimate the count with error bars")]
+    PrimeCount(PrimeCountArgs),
+    #[command(about = "Save π(x) at regular checkpoints while sieving, or look x up in the table")]
+    PiTable(PiTableArgs),
+    #[command(about = "Sum the primes in a range exactly, optionally with their squares")]
+    PrimeSum(PrimeSumArgs),
+    #[command(about = "Sum the primes and their reciprocals and count them per residue class")]
+    PrimeStats(PrimeStatsArgs),
+    #[command(
+        about = "Count leading digits, last digits, digit sums and last-digit transitions of the primes"
+    )]
+    PrimeDigits(PrimeDigitsArgs),
+    #[command(about = "Compute the primorial p_n# (or x#) as an exact big integer")]
+    Primorial(PrimorialArgs),
+    #[command(
+        about = "Find the Fortunate number of each primorial: the smallest m > 1 with p# + m prime"
+    )]
+    Fortunate(FortunateArgs),
+    #[command(about = "Check Wilson's theorem for n by computing (n − 1)! mod n")]
+    Wilson(WilsonArgs),
+    #[command(about = "Find the smallest number of each multiplicative persistence")]
+    Persistence(PersistenceArgs),
+    #[command(about = "Classify abundant numbers up to a limit as semiperfect or weird")]
+    Weird(WeirdArgs),
+    #[command(about = "Count the primes between n and 2n for each n from a stored prime file")]
+    Bertrand(BertrandArgs),
+    #[command(about = "Check Legendre's conjecture: a prime between n² and (n + 1)² for each n")]
+    Legendre(LegendreArgs),
+    #[command(about = "Find the windows of consecutive integers with record prime counts")]
+    Clusters(ClustersArgs),
+    #[command(about = "Find abc triples a + b = c whose radical rad(abc) is small next to c")]
+    Abc(AbcArgs),
+    #[command(about = "Find consecutive integers whose prime factors have equal sums")]
+    RuthAaron(RuthAaronArgs),
+    #[command(about = "Iterate a Ducci sequence to zero or a cycle, or tally random starts")]
+    Ducci(DucciArgs),
+    #[command(about = "Find automorphic numbers, whose squares end in the number itself")]
+    Automorphic(AutomorphicArgs),
+    #[command(about = "List the Farey sequence F_n, count it, or find a fraction's neighbours")]
+    Farey(FareyArgs),
+    #[command(about = "Print the path from 1/1 to a fraction in the Stern–Brocot tree")]
+    SternBrocot(SternBrocotArgs),
+    #[command(about = "Evaluate the Riemann zeta function ζ(s) to any number of digits")]
+    Zeta(ZetaArgs),
+    #[command(about = "Evaluate the prime zeta function P(s) = Σ p^−s for s > 1")]
+    PrimeZeta(PrimeZetaArgs),
+    #[command(about = "Search the digits of a constant (or of stdin) for primes or other patterns")]
+    Scan(ScanArgs),
+    #[command(about = "Generate digit sequences such as look-and-say to pipe into nt scan")]
+    Sequence(SequenceArgs),
+    #[command(about = "Convert integers of any size between bases 2 and 62")]
+    Base(BaseArgs),
+    #[command(about = "Find the bases from 2 to 255 in which numbers are palindromes")]
+    Palindromes(PalindromesArgs),
+    #[command(
+        about = "Test concatenations of the first k primes (Smarandache–Wellin numbers) for primality"
+    )]
+    Smarandache(SmarandacheArgs),
+    #[command(about = "Explore the tree of primes formed by adding digits one at a time")]
+    DigitTree(DigitTreeArgs),
+    #[command(about = "Draw the Ulam spiral or rank its diagonals by prime density")]
+    Ulam(UlamArgs),
+    #[command(about = "Prove n prime with a Pratt certificate (JSON), or check a certificate")]
+    Certify(CertifyArgs),
+    #[command(about = "Test numbers for primality (exact below 2^64, Baillie–PSW above)")]
+    IsPrime(IsPrimeArgs),
+    #[command(
+        about = "Record a property for many numbers in their <n>.txt files (safe to rerun)",
+        group = clap::ArgGroup::new("input").required(true).multiple(true)
+    )]
+    Tag(TagArgs),
+    #[command(about = "Print the numbers whose stored properties include all the given ones")]
+    Query(QueryArgs),
+    #[command(about = "Find Ormiston pairs: consecutive primes whose digits are anagrams")]
+    Ormiston(OrmistonArgs),
+    #[command(about = "Show the primes before and after n and whether n is prime")]
+    Near(NearArgs),
+    #[command(about = "Build and query a smallest-prime-factor table for instant factoring")]
+    Spf(SpfArgs),
+    #[command(about = "Time the sieve's hot loops: segment sieve, bit unpack, itoa, channel send")]
+    Microbench(MicrobenchArgs),
+    #[command(about = "Run every variation and output format and check they agree")]
+    Selftest(SelftestArgs),
+    #[command(about = "Print a shell completion script to stdout")]
+    Completions(CompletionsArgs),
+    #[command(about = "Generate man pages")]
+    Man(ManArgs),
+}
+
+// Each command's arguments get their own struct, so clap builds them in a function per
+// command. Inline in the enum they all landed in one stack frame, which outgrew a 2MB test
+// thread in debug builds once there were ~50 commands.
+
+#[derive(Args)]
+pub struct PrimesArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        required_unless_present_any = ["connect", "unbounded", "to"],
+        help = "The upper limit to search for primes (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: Option<usize>,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        requires = "to",
+        conflicts_with_all = ["limit", "unbounded", "distributed"],
+        help = "Start of a window [from, to] to sieve on its own, e.g. --from 1e15 --to 1e15+1e9"
+    )]
+    pub from: Option<usize>,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        requires = "from",
+        help = "End of the --from window; writes range_<from>_<to>.txt (or .bin)"
+    )]
+    pub to: Option<usize>,
+    #[arg(short, long, default_value = "1", help = "Algorithm variation to use")]
+    pub variation: u32,
+    #[arg(long, help = "Save each prime as an individual property file")]
+    pub save_as_property: bool,
+    #[arg(
+        short,
+        long,
+        help = "Number of worker threads for parallel processing (variation 8+ only)"
+    )]
+    pub workers: Option<usize>,
+    #[arg(
+        short,
+        long,
+        help = "Save primes in binary format (8 bytes per prime, little-endian)"
+    )]
+    pub binary: bool,
+    #[arg(
+        long,
+        default_value = "2",
+        help = "Number of consumer threads for parallel I/O (variation 9 only)"
+    )]
+    pub consumers: usize,
+    #[arg(
+        long,
+        help = "Use async I/O (io_uring on Linux 5.1+, thread pool elsewhere; variation 9 only, requires --binary)"
+    )]
+    pub async_io: bool,
+    #[arg(
+        long,
+        default_value = "64",
+        help = "Write buffers per consumer for --async-io (registered 256KB buffers with io_uring)"
+    )]
+    pub io_buffers: usize,
+    #[arg(
+        long,
+        help = "Open output files with O_DIRECT to bypass the page cache (Linux only)"
+    )]
+    pub direct_io: bool,
+    #[arg(long, help = "Preallocate output files from the estimated prime count")]
+    pub preallocate: bool,
+    #[arg(
+        long,
+        default_value = "100",
+        value_parser = numeric_arg::parse_count,
+        help = "Segments each consumer channel can hold before producers block (variation 9 only)"
+    )]
+    pub channel_capacity: usize,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "Memory budget in MB; a watchdog pauses producers or stops the run before RSS exceeds it (variation 9 also adapts its backpressure)"
+    )]
+    pub max_memory: Option<usize>,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "throttle",
+        requires = "max_memory",
+        help = "What the watchdog does as RSS nears --max-memory: pause producers, or stop the run"
+    )]
+    pub on_memory_limit: OnLimit,
+    #[arg(
+        long,
+        help = "Pin sieve workers and consumers to cores, grouped by NUMA node (variations 8-9, Linux only)"
+    )]
+    pub pin_workers: bool,
+    #[arg(
+        long,
+        help = "Back the whole-range sieve arrays of variations 1-4 and 10 with transparent huge pages (Linux only; segment buffers are left on normal pages)"
+    )]
+    pub huge_pages: bool,
+    #[arg(
+        long,
+        help = "Round the limit up to a whole number of segments and keep the extra primes (variations 5-9)"
+    )]
+    pub align_segments: bool,
+    #[arg(
+        long,
+        help = "Write output files in place instead of via a temp file and rename (saves disk space, but a failed run destroys the old file)"
+    )]
+    pub no_atomic: bool,
+    #[arg(
+        long,
+        help = "Start even if the estimated output is larger than the free disk space"
+    )]
+    pub force: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "cpu",
+        help = "Sieve segments on CPU threads or the GPU (variation 9 only; gpu needs a build with --features gpu)"
+    )]
+    pub backend: SieveBackend,
+    #[arg(
+        long,
+        help = "Workers format primes straight into output bytes; consumers only write (variations 8-9)"
+    )]
+    pub preformat: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["binary", "preformat"],
+        help = "Format text on the consumer and write it from a second thread, overlapping the two (variations 6 and 8)"
+    )]
+    pub write_behind: bool,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        conflicts_with = "preformat",
+        help = "Spread the output over primes_small and N shard files written in parallel, laid out like variation 9's, with a manifest (variations 6 and 8)"
+    )]
+    pub shards: Option<usize>,
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with_all = ["preformat", "async_io"],
+        help = "Also write the output as text or binary beside it, or print the primes on stdout (repeatable)"
+    )]
+    pub tee: Vec<TeeTarget>,
+    #[arg(
+        long,
+        conflicts_with_all = ["preformat", "async_io", "unbounded", "distributed"],
+        help = "Stream the output to fifo:/path (a named pipe, created if missing), tcp:host:port or - (stdout) instead of writing primes.txt/.bin"
+    )]
+    pub output: Option<OutputTarget>,
+    #[arg(
+        long,
+        help = "Check at the end that every segment was written exactly once (variation 9 only)"
+    )]
+    pub audit: bool,
+    #[arg(
+        long,
+        conflicts_with = "distributed",
+        help = "Give worker w segments w, w + workers, ... so every run schedules the same way (variations 8-9; 8 always does)"
+    )]
+    pub deterministic: bool,
+    #[arg(
+        long,
+        help = "Gather gap statistics (max gap, histogram) while writing and save them to gaps.json"
+    )]
+    pub track_gaps: bool,
+    #[arg(long, help = "Show a progress bar with ETA (variations 5-9)")]
+    pub progress: bool,
+    #[arg(
+        long,
+        conflicts_with = "progress",
+        help = "Show a live dashboard of workers, consumers, memory and disk writes (variation 9 only)"
+    )]
+    pub tui: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["limit", "distributed"],
+        help = "Stream primes with no limit into rolling stream_NNNNNN files until Ctrl-C or a --max-* budget"
+    )]
+    pub unbounded: bool,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        requires = "unbounded",
+        help = "Stop --unbounded after writing this many bytes (accepts 10G, 1e12)"
+    )]
+    pub max_bytes: Option<usize>,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        requires = "unbounded",
+        help = "Stop --unbounded after this many seconds"
+    )]
+    pub max_seconds: Option<usize>,
+    #[arg(
+        long,
+        default_value = "1G",
+        value_parser = numeric_arg::parse_count,
+        help = "Start a new --unbounded output file after this many bytes"
+    )]
+    pub roll_bytes: usize,
+    #[arg(
+        long,
+        requires = "unbounded",
+        help = "Continue --unbounded after the last prime of the highest finished stream_NNNNNN file"
+    )]
+    pub resume: bool,
+    #[arg(
+        long,
+        value_enum,
+        help = "Sieve across machines: coordinator collects and writes, workers sieve (variation 9 only)"
+    )]
+    pub distributed: Option<DistributedRole>,
+    #[arg(
+        long,
+        default_value = "0.0.0.0:7878",
+        help = "Address the coordinator listens on for workers"
+    )]
+    pub listen: String,
+    #[arg(
+        long,
+        required_if_eq("distributed", "worker"),
+        help = "Coordinator address a worker connects to (host:port)"
+    )]
+    pub connect: Option<String>,
+}
+
+#[derive(Args)]
+pub struct PrimesAllMemArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "The upper limit to search for primes (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(short, long, default_value = "1", help = "Algorithm variation to use")]
+    pub variation: u32,
+    #[arg(long, help = "Save each prime as an individual property file")]
+    pub save_as_property: bool,
+    #[arg(
+        long,
+        help = "Back the whole-range sieve arrays of variations 1-4 and 10 with transparent huge pages (Linux only; segment buffers are left on normal pages)"
+    )]
+    pub huge_pages: bool,
+    #[arg(
+        long,
+        help = "Write output files in place instead of via a temp file and rename (saves disk space, but a failed run destroys the old file)"
+    )]
+    pub no_atomic: bool,
+    #[arg(
+        long,
+        help = "Round the limit up to a whole number of segments and keep the extra primes (variation 5 only)"
+    )]
+    pub align_segments: bool,
+}
+
+#[derive(Args)]
+pub struct PrimesBasesArgs {
+    #[arg(long, help = "Only display palindromes, show dash for non-palindromes")]
+    pub pal_only: bool,
+    #[arg(
+        long,
+        help = "Only show rows containing this specific palindrome value"
+    )]
+    pub pal: Option<String>,
+    #[arg(
+        long,
+        help = "Read the primes from stdin, one per line, instead of primes.txt"
+    )]
+    pub stdin: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "prime",
+        help = "Order of the rows; sorting by palindromes holds the whole table in memory"
+    )]
+    pub sort: RowOrder,
+}
+
+#[derive(Args)]
+pub struct PiArgs {
+    #[arg(
+        default_value = "100",
+        value_parser = numeric_arg::parse_count,
+        help = "Number of decimal places to calculate"
+    )]
+    pub digits: usize,
+    #[arg(long, help = "Show series convergence progress with ETA")]
+    pub progress: bool,
+    #[arg(
+        long,
+        conflicts_with = "progress",
+        help = "List palindromic runs of digits in the cached expansion instead of primes"
+    )]
+    pub palindromes: bool,
+    #[arg(
+        long,
+        default_value = "7",
+        requires = "palindromes",
+        value_parser = clap::value_parser!(u32).range(2..),
+        help = "Shortest palindrome to list"
+    )]
+    pub min_length: u32,
+    #[arg(
+        long,
+        default_value = "10",
+        requires = "palindromes",
+        value_parser = clap::value_parser!(u32).range(2..=36),
+        help = "Base to write the digits in before searching"
+    )]
+    pub base: u32,
+}
+
+#[derive(Args)]
+pub struct GammaArgs {
+    #[arg(
+        default_value = "100",
+        value_parser = numeric_arg::parse_count,
+        help = "Number of decimal places to calculate"
+    )]
+    pub digits: usize,
+    #[arg(long, help = "Show series convergence progress with ETA")]
+    pub progress: bool,
+}
+
+#[derive(Args)]
+pub struct RandomArgs {
+    #[arg(
+        default_value = "100",
+        value_parser = numeric_arg::parse_count,
+        help = "Number of random digits to generate"
+    )]
+    pub digits: usize,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "primes",
+        help = "What to search the digits for"
+    )]
+    pub pattern: Pattern,
+    #[arg(
+        long,
+        default_value = "12",
+        value_parser = clap::value_parser!(u32).range(4..=19),
+        help = "Longest squares, cubes or Fibonacci numbers to search for"
+    )]
+    pub max_digits: u32,
+}
+
+#[derive(Args)]
+pub struct RandomPrimeArgs {
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        required_unless_present = "pattern",
+        help = "Number of digits [default: the pattern's length]"
+    )]
+    pub digits: Option<usize>,
+    #[arg(
+        long,
+        help = "Digits the prime must match: ? is any digit, * any run of digits (e.g. 12??3*7)"
+    )]
+    pub pattern: Option<String>,
+    #[arg(
+        short = 'n',
+        long,
+        default_value = "1",
+        value_parser = numeric_arg::parse_count,
+        help = "How many distinct primes to generate"
+    )]
+    pub count: usize,
+    #[arg(
+        long,
+        default_value = "1000000",
+        value_parser = numeric_arg::parse_count,
+        help = "Give up after this many candidates"
+    )]
+    pub max_attempts: usize,
+    #[arg(long, help = "Seed for the random digits [default: random]")]
+    pub seed: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct ChainArgs {
+    #[arg(
+        short,
+        long,
+        default_value = "4",
+        help = "Number of digits that overlap between primes"
+    )]
+    pub overlap: usize,
+    #[arg(
+        short,
+        long,
+        default_value = "100",
+        value_parser = numeric_arg::parse_count,
+        help = "Target length of the digit chain"
+    )]
+    pub length: usize,
+    #[arg(
+        short,
+        long,
+        help = "Pick each next prime yourself from stdin (u to undo, q to stop)"
+    )]
+    pub interactive: bool,
+    #[arg(
+        long,
+        conflicts_with = "interactive",
+        help = "Build many random chains, report their statistics and keep the best one"
+    )]
+    pub tournament: bool,
+    #[arg(
+        long,
+        default_value = "1000",
+        requires = "tournament",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Chains to build in a tournament"
+    )]
+    pub runs: u64,
+    #[arg(
+        long,
+        requires = "tournament",
+        help = "Seed for the tournament's random choices [default: random]"
+    )]
+    pub seed: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[arg(
+        short,
+        long,
+        value_enum,
+        default_value = "parquet",
+        help = "Output format"
+    )]
+    pub format: ExportFormat,
+    #[arg(
+        short,
+        long,
+        help = "Path of the file to write (a directory for text-ranges)"
+    )]
+    pub output: PathBuf,
+    #[arg(
+        short,
+        long,
+        help = "Read primes from primes.bin instead of primes.txt"
+    )]
+    pub binary: bool,
+    #[arg(
+        long,
+        default_value = "1e9",
+        value_parser = numeric_arg::parse_count,
+        help = "Numbers covered by each text-ranges file"
+    )]
+    pub range_size: usize,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    #[arg(
+        help = "Reference file (.bin is binary, other names text; primes_small.* reads the shards)"
+    )]
+    pub a: PathBuf,
+    #[arg(help = "File to check against it")]
+    pub b: PathBuf,
+    #[arg(
+        long,
+        default_value = "20",
+        value_parser = numeric_arg::parse_count,
+        help = "List at most this many differences (all are counted)"
+    )]
+    pub max_listed: usize,
+}
+
+#[derive(Args)]
+pub struct RankArgs {
+    #[arg(value_parser = numeric_arg::parse_count, help = "Prime to look up")]
+    pub n: usize,
+    #[arg(
+        long,
+        help = "Prime file, .bin for binary [default: primes.bin, else primes.txt]"
+    )]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct SelectArgs {
+    #[arg(value_parser = numeric_arg::parse_count, help = "Index of the prime, from 1")]
+    pub k: usize,
+    #[arg(
+        long,
+        help = "Prime file, .bin for binary [default: primes.bin, else primes.txt]"
+    )]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    #[arg(help = "File to read (format from its magic bytes, else .bin is u64le and others text)")]
+    pub input: PathBuf,
+    #[arg(long, help = "File to write")]
+    pub to: PathBuf,
+    #[arg(long, value_enum, help = "Input format [default: detected]")]
+    pub from_format: Option<StoredFormat>,
+    #[arg(
+        long,
+        value_enum,
+        help = "Output format [default: from the extension: .txt, .bin, .delta or .bits]"
+    )]
+    pub format: Option<StoredFormat>,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    #[arg(required = true, help = "Sorted lists to merge; they may overlap")]
+    pub files: Vec<PathBuf>,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "auto",
+        help = "How the lists are laid out"
+    )]
+    pub format: InputFormat,
+    #[arg(short, long, help = "Write primes.bin instead of primes.txt")]
+    pub binary: bool,
+    #[arg(
+        long,
+        help = "Replace an existing primes.txt or primes.bin, and start even if the merged list may not fit on disk"
+    )]
+    pub force: bool,
+}
+
+#[derive(Args)]
+pub struct DataArgs {
+    #[command(subcommand)]
+    pub action: DataAction,
+}
+
+#[derive(Args)]
+pub struct GapFirstsArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "The upper limit to search for primes (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(
+        short,
+        long,
+        default_value = "9",
+        value_parser = clap::value_parser!(u32).range(8..=9),
+        help = "Parallel variation to sieve with (8 or 9)"
+    )]
+    pub variation: u32,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+    #[arg(
+        long,
+        default_value = "2",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Number of consumer threads (variation 9 only)"
+    )]
+    pub consumers: u32,
+    #[arg(
+        short,
+        long,
+        help = "Path of the CSV file to write (default: gap_firsts.csv in the data directory)"
+    )]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct FactorArgs {
+    #[arg(
+        required_unless_present = "stdin",
+        help = "Non-negative integer to factor, in decimal"
+    )]
+    pub n: Option<String>,
+    #[arg(
+        long,
+        conflicts_with = "n",
+        help = "Factor one integer per line of stdin, printing a JSON line for each"
+    )]
+    pub stdin: bool,
+    #[arg(
+        short,
+        long,
+        value_enum,
+        default_value = "auto",
+        help = "Algorithm for cofactors left after trial division"
+    )]
+    pub algorithm: FactorAlgorithm,
+    #[arg(
+        long,
+        default_value = "200",
+        value_parser = numeric_arg::parse_count,
+        help = "ECM curves to try per cofactor"
+    )]
+    pub curves: usize,
+    #[arg(
+        long,
+        default_value = "5e4",
+        value_parser = numeric_arg::parse_count,
+        help = "ECM stage 1 bound (1e4 for ~20-digit factors, 1e6 for ~35)"
+    )]
+    pub b1: usize,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "ECM stage 2 bound [default: 100 × B1]"
+    )]
+    pub b2: Option<usize>,
+    #[arg(
+        long,
+        default_value = "1e7",
+        value_parser = numeric_arg::parse_count,
+        help = "Pollard rho iterations per polynomial (--algorithm rho)"
+    )]
+    pub rho_iterations: usize,
+    #[arg(
+        long,
+        default_value = "1e8",
+        value_parser = numeric_arg::parse_count,
+        help = "Values of a to try with --algorithm fermat (n = a² − b²)"
+    )]
+    pub fermat_steps: usize,
+    #[arg(
+        long,
+        default_value = "1e6",
+        value_parser = numeric_arg::parse_count,
+        help = "p−1 and p+1 stage 1 bound (finds p when p ∓ 1 is B1-smooth)"
+    )]
+    pub pm1_b1: usize,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "p−1 and p+1 stage 2 bound, for one larger prime in p ∓ 1 [default: 100 × B1]"
+    )]
+    pub pm1_b2: Option<usize>,
+    #[arg(
+        short,
+        long,
+        help = "Worker threads for parallel ECM curves, or numbers factored at once with --stdin"
+    )]
+    pub workers: Option<usize>,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "plain",
+        conflicts_with = "stdin",
+        help = "Write 2³·3·5² (unicode) or LaTeX, followed by Ω(n), ω(n) and rad(n)"
+    )]
+    pub format: FactorFormat,
+}
+
+#[derive(Args)]
+pub struct ArithArgs {
+    #[arg(value_enum, help = "Arithmetic function to tabulate")]
+    pub function: ArithFunction,
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Last n in the table (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+    #[arg(
+        long,
+        default_value = "1",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Number of consumer threads; each writes every Nth segment to <function>_<id> (1 keeps the table in one file)"
+    )]
+    pub consumers: u32,
+    #[arg(
+        short,
+        long,
+        help = "Save values in binary format (8 bytes per value, signed little-endian)"
+    )]
+    pub binary: bool,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "Memory budget in MB; enables adaptive backpressure on consumer lag and RSS"
+    )]
+    pub max_memory: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct PerfectArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Largest n to check [default: the table's limit]"
+    )]
+    pub limit: Option<usize>,
+    #[arg(
+        long,
+        help = "Table from `nt arith aliquot <limit> --binary` [default: aliquot_1.bin in the data directory]"
+    )]
+    pub table: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct AmicableArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Largest member of a pair [default: the table's limit]"
+    )]
+    pub limit: Option<usize>,
+    #[arg(
+        long,
+        help = "Table from `nt arith aliquot <limit> --binary` [default: aliquot_1.bin in the data directory]"
+    )]
+    pub table: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct UntouchableArgs {
+    #[arg(
+        long,
+        help = "Table from `nt arith aliquot <limit> --binary` [default: aliquot_1.bin in the data directory]"
+    )]
+    pub table: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct PrimeCountArgs {
+    #[arg(help = "Upper limit x (accepts 1e30, 2.5e20, 1_000_000)")]
+    pub x: String,
+    #[arg(
+        long,
+        help = "Estimate from li(x), Riemann's R(x) and sampled strips instead of sieving"
+    )]
+    pub estimate: bool,
+    #[arg(
+        long,
+        default_value = "1000",
+        value_parser = numeric_arg::parse_count,
+        requires = "estimate",
+        help = "Strips to sample with --estimate, one per equal stratum of [2, x]"
+    )]
+    pub strips: usize,
+    #[arg(
+        long,
+        default_value = "1e4",
+        value_parser = numeric_arg::parse_count,
+        requires = "estimate",
+        help = "Numbers per sampled strip"
+    )]
+    pub width: usize,
+    #[arg(
+        long,
+        default_value = "1",
+        requires = "estimate",
+        help = "Seed for the strip positions"
+    )]
+    pub seed: u64,
+    #[arg(short, long, help = "Worker threads for sampling strips")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct PiTableArgs {
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        required_unless_present = "query",
+        conflicts_with = "query",
+        help = "Sieve up to this limit and save π at every step (accepts 1e10, 10G)"
+    )]
+    pub limit: Option<usize>,
+    #[arg(
+        long,
+        default_value = "1e6",
+        value_parser = numeric_arg::parse_count,
+        help = "Distance between checkpoints"
+    )]
+    pub step: usize,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "Bracket π(x) between the saved checkpoints around x and compare li(x)"
+    )]
+    pub query: Option<usize>,
+    #[arg(short, long, help = "Table file [default: <data dir>/pi_table.bin]")]
+    pub table: Option<PathBuf>,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct PrimeSumArgs {
+    #[arg(
+        long,
+        default_value = "0",
+        value_parser = numeric_arg::parse_count,
+        help = "Start of the range (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub from: usize,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "End of the range, included"
+    )]
+    pub to: usize,
+    #[arg(long, help = "Also sum the squares of the primes")]
+    pub squares: bool,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct PrimeStatsArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Upper limit (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "3,4,5,6,8,10,12",
+        help = "Moduli to count residue classes for, comma separated"
+    )]
+    pub moduli: Vec<usize>,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct PrimeDigitsArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Upper limit (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+}
+
+#[derive(Args)]
+pub struct PrimorialArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Number of primes to multiply (accepts 1e6, 10M, 1_000_000)"
+    )]
+    pub n: usize,
+    #[arg(long, help = "Treat n as a bound x and multiply every prime <= x")]
+    pub up_to: bool,
+    #[arg(
+        long,
+        help = "Also test p# − 1 and p# + 1, proving the primes from their known n ∓ 1"
+    )]
+    pub prime: bool,
+    #[arg(short, long, help = "Write the full decimal value to this file")]
+    pub output: Option<PathBuf>,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct FortunateArgs {
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "Number of primorials, p_1# through p_terms# (accepts 1e3, 10K, 1_000)"
+    )]
+    pub terms: usize,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct WilsonArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Number to test (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub n: usize,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct PersistenceArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Upper limit of the scan (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct WeirdArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Upper limit (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct BertrandArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Largest n to check (accepts 1e6, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(
+        long,
+        default_value = "2",
+        value_parser = numeric_arg::parse_count,
+        help = "Smallest n to check, so the minima are looked for higher up"
+    )]
+    pub from: usize,
+    #[arg(
+        long,
+        help = "Prime file in any stored format, with every prime from 2 past 2 × limit [default: primes.bin or primes.txt]"
+    )]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct LegendreArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Largest n to check (accepts 1e6, 10M, 1_000_000)"
+    )]
+    pub n_max: usize,
+    #[arg(
+        long,
+        default_value = "1",
+        value_parser = numeric_arg::parse_count,
+        help = "Smallest n to check, so the sparsest interval is looked for higher up"
+    )]
+    pub from: usize,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct ClustersArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Largest number a window may reach (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(
+        long,
+        default_value = "100",
+        value_parser = numeric_arg::parse_count,
+        help = "Integers per window"
+    )]
+    pub window: usize,
+    #[arg(
+        long,
+        default_value = "1",
+        value_parser = numeric_arg::parse_count,
+        help = "Smallest number a window may start at, to look for records high up"
+    )]
+    pub from: usize,
+}
+
+#[derive(Args)]
+pub struct AbcArgs {
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "Largest c to check (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(
+        long,
+        default_value = "1.4",
+        value_parser = abc::parse_quality,
+        help = "List triples whose quality ln c / ln rad(abc) is above this"
+    )]
+    pub min_quality: f64,
+    #[arg(
+        short,
+        long,
+        help = "Smallest-prime-factor table [default: <data dir>/spf.bin if it covers the limit]"
+    )]
+    pub table: Option<PathBuf>,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct RuthAaronArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Largest n + 1 to check (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(
+        long,
+        help = "Sum each distinct prime once instead of counting multiplicity"
+    )]
+    pub distinct: bool,
+    #[arg(
+        short,
+        long,
+        help = "Smallest-prime-factor table [default: <data dir>/spf.bin if it covers the limit]"
+    )]
+    pub table: Option<PathBuf>,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct DucciArgs {
+    #[arg(
+        value_delimiter = ',',
+        required_unless_present = "scan",
+        conflicts_with = "scan",
+        help = "Starting tuple, comma separated (e.g. 1,5,9,3)"
+    )]
+    pub tuple: Vec<u64>,
+    #[arg(
+        long,
+        default_value = "10000",
+        value_parser = numeric_arg::parse_count,
+        help = "Give up after this many steps"
+    )]
+    pub max_steps: usize,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "Run this many random starting tuples and report how they end"
+    )]
+    pub scan: Option<usize>,
+    #[arg(
+        long,
+        default_value = "4",
+        requires = "scan",
+        help = "Entries per random tuple"
+    )]
+    pub length: usize,
+    #[arg(
+        long,
+        default_value = "1000",
+        requires = "scan",
+        help = "Random entries are below this"
+    )]
+    pub max_value: u64,
+    #[arg(
+        long,
+        requires = "scan",
+        help = "Seed for the random tuples [default: random]"
+    )]
+    pub seed: Option<u64>,
+}
+
+#[derive(Args)]
+pub struct AutomorphicArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        required_unless_present = "digits",
+        conflicts_with = "digits",
+        help = "Upper limit of the search (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: Option<usize>,
+    #[arg(long, help = "Match cubes instead of squares (trimorphic numbers)")]
+    pub cubes: bool,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        conflicts_with = "cubes",
+        help = "Build the two automorphic numbers with this many digits"
+    )]
+    pub digits: Option<usize>,
+    #[arg(
+        short,
+        long,
+        requires = "digits",
+        help = "Write the full decimal values to this file"
+    )]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct FareyArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Largest denominator (accepts 1e6, 10M, 1_000_000)"
+    )]
+    pub n: usize,
+    #[arg(long, conflicts_with = "around", help = "Only print the length |F_n|")]
+    pub count: bool,
+    #[arg(
+        long,
+        help = "Print the terms of F_n on either side of this fraction (e.g. 3/7)"
+    )]
+    pub around: Option<Ratio>,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct SternBrocotArgs {
+    #[arg(help = "Positive fraction to find (e.g. 3/7)")]
+    pub fraction: Ratio,
+}
+
+#[derive(Args)]
+pub struct ZetaArgs {
+    #[arg(
+        allow_hyphen_values = true,
+        help = "Real argument, anything but 1 (e.g. 3, 0.5, -2.5)"
+    )]
+    pub s: String,
+    #[arg(
+        long,
+        default_value = "50",
+        value_parser = numeric_arg::parse_count,
+        help = "Significant decimal digits"
+    )]
+    pub digits: usize,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "Compare with the Euler product over the primes up to this limit (s > 1)"
+    )]
+    pub euler_product: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct PrimeZetaArgs {
+    #[arg(help = "Real argument above 1 (e.g. 2, 1.5)")]
+    pub s: String,
+    #[arg(
+        long,
+        default_value = "50",
+        value_parser = numeric_arg::parse_count,
+        help = "Significant decimal digits"
+    )]
+    pub digits: usize,
+    #[arg(
+        long,
+        value_parser = numeric_arg::parse_count,
+        help = "Also sum p^−s exactly over the primes up to this limit and compare"
+    )]
+    pub partial: Option<usize>,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct ScanArgs {
+    #[arg(
+        required_unless_present = "stdin",
+        conflicts_with = "stdin",
+        value_parser = digit_source::parse_spec,
+        help = "Digits to search: pi, e, gamma, catalan, apery, random[:<seed>], \
+                champernowne, file:<path> or - for stdin"
+    )]
+    pub source: Option<SourceSpec>,
+    #[arg(
+        long,
+        help = "Search the digits read from stdin instead (other characters are skipped)"
+    )]
+    pub stdin: bool,
+    #[arg(
+        long,
+        default_value = "1000",
+        value_parser = numeric_arg::parse_count,
+        help = "Number of decimal places (or digits) to search; files and stdin are read whole"
+    )]
+    pub digits: usize,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "primes",
+        help = "What to search the digits for"
+    )]
+    pub pattern: Pattern,
+    #[arg(
+        long,
+        default_value = "12",
+        value_parser = clap::value_parser!(u32).range(4..=19),
+        help = "Longest squares, cubes or Fibonacci numbers to search for"
+    )]
+    pub max_digits: u32,
+    #[arg(
+        long,
+        value_name = "BUCKETS",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Print how the occurrences spread over this many slices of the digits instead"
+    )]
+    pub heatmap: Option<u32>,
+    #[arg(
+        long,
+        value_enum,
+        default_value = "sparkline",
+        requires = "heatmap",
+        help = "Heatmap as sparklines against the density of random digits, or CSV"
+    )]
+    pub heatmap_format: HeatmapFormat,
+    #[arg(
+        long,
+        conflicts_with = "heatmap",
+        help = "List only the occurrences that cover the most digits without overlapping"
+    )]
+    pub non_overlapping: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["heatmap", "non_overlapping"],
+        help = "List palindromic runs of digits instead of numbers"
+    )]
+    pub palindromes: bool,
+    #[arg(
+        long,
+        default_value = "7",
+        requires = "palindromes",
+        value_parser = clap::value_parser!(u32).range(2..),
+        help = "Shortest palindrome to list"
+    )]
+    pub min_length: u32,
+    #[arg(
+        long,
+        conflicts_with_all = ["heatmap", "non_overlapping", "palindromes"],
+        help = "Print digit frequencies and a χ² test against random digits instead"
+    )]
+    pub stats: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["heatmap", "palindromes", "stats"],
+        help = "Save the occurrences to a results file for this source in the data directory"
+    )]
+    pub save: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["heatmap", "palindromes", "stats"],
+        help = "Search only the digits added since the saved results, then save them all"
+    )]
+    pub resume: bool,
+}
+
+#[derive(Args)]
+pub struct SequenceArgs {
+    #[arg(value_enum, help = "Sequence to generate")]
+    pub kind: DigitSequence,
+    #[arg(
+        long,
+        default_value = "10",
+        value_parser = numeric_arg::parse_count,
+        help = "Number of terms"
+    )]
+    pub terms: usize,
+    #[arg(long, help = "Print the terms run together as one digit string")]
+    pub concat: bool,
+}
+
+#[derive(Args)]
+pub struct BaseArgs {
+    #[arg(
+        allow_negative_numbers = true,
+        required_unless_present = "stdin",
+        conflicts_with = "stdin",
+        help = "Numbers to convert"
+    )]
+    pub numbers: Vec<String>,
+    #[arg(
+        long,
+        default_value = "10",
+        value_parser = clap::value_parser!(u32).range(2..=62),
+        help = "Base the numbers are written in"
+    )]
+    pub from: u32,
+    #[arg(
+        long,
+        default_value = "10",
+        value_parser = clap::value_parser!(u32).range(2..=62),
+        help = "Base to write them in (digits 0-9, A-Z, then a-z)"
+    )]
+    pub to: u32,
+    #[arg(long, help = "Convert one number per line of stdin")]
+    pub stdin: bool,
+}
+
+#[derive(Args)]
+pub struct PalindromesArgs {
+    #[arg(
+        long,
+        required_unless_present = "search",
+        conflicts_with = "search",
+        help = "List the bases in which this number is a palindrome"
+    )]
+    pub number: Option<u64>,
+    #[arg(
+        long,
+        help = "Find the smallest number that is a palindrome in at least this many bases"
+    )]
+    pub search: Option<usize>,
+    #[arg(
+        long,
+        default_value = "1e9",
+        value_parser = numeric_arg::parse_count,
+        help = "Largest number to try with --search (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct SmarandacheArgs {
+    #[arg(
+        long,
+        default_value = "500",
+        value_parser = numeric_arg::parse_count,
+        help = "Largest number of primes to concatenate"
+    )]
+    pub max_terms: usize,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+    #[arg(long, help = "Show primality test progress with ETA")]
+    pub progress: bool,
+}
+
+#[derive(Args)]
+pub struct DigitTreeArgs {
+    #[arg(
+        long,
+        help = "Prepend digits on the left instead of appending on the right"
+    )]
+    pub left: bool,
+    #[arg(
+        long,
+        help = "Explore below this prime instead of the one-digit primes"
+    )]
+    pub from: Option<String>,
+    #[arg(long, help = "Stop after this many levels")]
+    pub depth: Option<usize>,
+    #[arg(
+        long,
+        default_value = "5",
+        help = "Number of deepest primes to show with their paths"
+    )]
+    pub paths: usize,
+    #[arg(
+        short,
+        long,
+        help = "Walk the tree from stdin: a digit to descend, u to go up, q to quit"
+    )]
+    pub interactive: bool,
+}
+
+#[derive(Args)]
+pub struct UlamArgs {
+    #[arg(
+        default_value = "20",
+        help = "Cells from the center to the edge of the drawing"
+    )]
+    pub radius: u32,
+    #[arg(
+        long,
+        help = "Rank the diagonal polynomials 4n² + bn + c instead of drawing"
+    )]
+    pub analyze: bool,
+    #[arg(
+        long,
+        default_value = "10",
+        help = "Number of diagonals to show with --analyze"
+    )]
+    pub top: usize,
+    #[arg(
+        long,
+        default_value = "1000",
+        value_parser = numeric_arg::parse_count,
+        help = "Terms of each diagonal to test with --analyze"
+    )]
+    pub terms: usize,
+    #[arg(
+        long,
+        default_value = "10000",
+        value_parser = numeric_arg::parse_count,
+        help = "Largest value a diagonal may start at with --analyze"
+    )]
+    pub max_start: usize,
+    #[arg(short, long, help = "Number of worker threads")]
+    pub workers: Option<usize>,
+}
+
+#[derive(Args)]
+pub struct CertifyArgs {
+    #[arg(
+        required_unless_present = "verify",
+        help = "Prime to certify, in decimal"
+    )]
+    pub n: Option<String>,
+    #[arg(
+        long,
+        conflicts_with = "n",
+        help = "Check the certificate in this file instead"
+    )]
+    pub verify: Option<PathBuf>,
+    #[arg(short, long, help = "Write the certificate here instead of stdout")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct IsPrimeArgs {
+    #[arg(required = true, help = "Numbers to test, in decimal")]
+    pub numbers: Vec<String>,
+    #[arg(
+        long,
+        help = "Run Baillie–PSW on every number and report the stage that decided it"
+    )]
+    pub bpsw: bool,
+}
+
+#[derive(Args)]
+pub struct TagArgs {
+    #[arg(long, help = "Property to record, e.g. twin or palindrome")]
+    pub property: String,
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        group = "input",
+        help = "Numbers to tag (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub numbers: Vec<usize>,
+    #[arg(
+        long,
+        group = "input",
+        help = "Also tag the numbers in this file, one per line (# starts a comment)"
+    )]
+    pub from_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct QueryArgs {
+    #[arg(
+        long,
+        required = true,
+        help = "Property the numbers must have (repeat to intersect, e.g. --property palindrome --property twin)"
+    )]
+    pub property: Vec<String>,
+    #[arg(
+        long,
+        num_args = 2,
+        value_names = ["LOW", "HIGH"],
+        value_parser = numeric_arg::parse_count,
+        help = "Only numbers from LOW to HIGH inclusive (accepts 1e6, 10M)"
+    )]
+    pub between: Option<Vec<usize>>,
+}
+
+#[derive(Args)]
+pub struct OrmistonArgs {
+    #[arg(
+        value_parser = numeric_arg::parse_count,
+        help = "Upper limit (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+    #[arg(
+        long,
+        help = "Prime file in any stored format, with every prime up to the limit [default: primes.bin or primes.txt]"
+    )]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct NearArgs {
+    #[arg(
+        required = true,
+        value_parser = numeric_arg::parse_count,
+        help = "Numbers to look around (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub numbers: Vec<usize>,
+    #[arg(
+        long,
+        help = "Prime set to look up [default: primeset.bin in the data directory, if present]"
+    )]
+    pub set: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct SpfArgs {
+    #[command(subcommand)]
+    pub action: SpfAction,
+}
+
+#[derive(Args)]
+pub struct MicrobenchArgs {
+    #[arg(help = "Benchmarks to run: sieve, unpack, itoa, channel [default: all]")]
+    pub names: Vec<String>,
+    #[arg(
+        long,
+        default_value = "1000",
+        value_parser = numeric_arg::parse_count,
+        help = "Milliseconds to spend on each benchmark"
+    )]
+    pub millis: usize,
+}
+
+#[derive(Args)]
+pub struct SelftestArgs {
+    #[arg(
+        long,
+        default_value = "1e7",
+        value_parser = numeric_arg::parse_count,
+        help = "Upper limit for every run (accepts 1e9, 10M, 1_000_000)"
+    )]
+    pub limit: usize,
+}
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    #[arg(value_enum, help = "Shell to generate completions for")]
+    pub shell: Shell,
+}
+
+#[derive(Args)]
+pub struct ManArgs {
+    #[arg(
+        long,
+        help = "Write nt.1 and one page per subcommand into this directory instead of printing nt.1"
+    )]
+    pub out_dir: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum DataAction {
+    #[command(about = "List every file with its kind, size and age")]
+    List,
+    #[command(about = "Show file counts and total size per kind")]
+    Size,
+    #[command(
+        about = "Remove files matching all given filters",
+        group = clap::ArgGroup::new("filter").required(true).multiple(true)
+    )]
+    Clean {
+        #[arg(
+            long,
+            value_parser = data::parse_age,
+            group = "filter",
+            help = "Only files last modified longer ago than this (30s, 15m, 12h, 7d, 2w)"
+        )]
+        older_than: Option<Duration>,
+        #[arg(
+            long,
+            group = "filter",
+            help = "Only files whose name matches this glob (e.g. 'primes_*.bin', '*.txt')"
+        )]
+        pattern: Option<String>,
+        #[arg(long, help = "Print what would be removed without removing anything")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SpfAction {
+    #[command(about = "Sieve the table up to a limit and save it (~1 byte per number)")]
+    Build {
+        #[arg(
+            value_parser = numeric_arg::parse_count,
+            help = "Largest number the table covers (accepts 1e9, 10M, 1_000_000)"
+        )]
+        limit: usize,
+        #[arg(short, long, help = "Table file [default: <data dir>/spf.bin]")]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "Factor numbers up to the table's limit by repeated lookups")]
+    Query {
+        #[arg(
+            required = true,
+            value_parser = numeric_arg::parse_count,
+            help = "Numbers to factor"
+        )]
+        numbers: Vec<usize>,
+        #[arg(short, long, help = "Table file [default: <data dir>/spf.bin]")]
+        table: Option<PathBuf>,
+    },
+}
+
+/// The full `nt` command tree
+pub fn build() -> clap::Command {
+    Cli::command()
+}
+
+/// Write a completion script for `shell` to stdout
+pub fn print_completions(shell: Shell) {
+    let mut cmd = build();
+    let name = cmd.get_name().to_string();
+    clap_complete::generate(shell, &mut cmd, name, &mut io::stdout());
+}
+
+/// Print the top-level man page to stdout
+pub fn print_man_page() -> io::Result<()> {
+    clap_mangen::Man::new(build()).render(&mut io::stdout())
+}
+
+/// Write nt.1 plus nt-<subcommand>.1 pages into `out_dir`
+pub fn write_man_pages(out_dir: &Path) -> io::Result<()> {
+    std::fs::create_dir_all(out_dir)?;
+    clap_mangen::generate_to(build(), out_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cli_definition_is_valid() {
+        // Catches conflicting short flags and bad defaults at test time rather than at run time
+        build().debug_assert();
+    }
+}
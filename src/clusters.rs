@@ -0,0 +1,122 @@
+// Prime-rich windows (`nt clusters`)
+//
+// A window of `width` consecutive integers slides up through [from, limit], and every
+// window holding more primes than all before it is reported: the densest stretches of a
+// given length, and how rare each new record is. Only windows ending at a prime need
+// checking (moving a window's end back to its last prime loses nothing), so the primes
+// stream in segment by segment and a deque holds the ones inside the window ending at
+// the newest. Windows that would start below `from` are skipped, so a search high up is
+// not dominated by the dense primes near 0.
+
+use std::collections::VecDeque;
+
+use crate::primes::for_each_prime_in_range;
+
+/// A window [start, end] and the primes in it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cluster {
+    pub start: usize,
+    pub end: usize,
+    pub primes: Vec<usize>,
+}
+
+impl Cluster {
+    /// Primes a window this wide holds on average here, width / ln(middle)
+    pub fn expected(&self) -> f64 {
+        let middle = self.start + (self.end - self.start) / 2;
+        (self.end - self.start + 1) as f64 / (middle.max(3) as f64).ln()
+    }
+}
+
+/// The primes in the window that ends at the latest prime pushed
+pub struct Window {
+    width: usize,
+    from: usize,
+    primes: VecDeque<usize>,
+    record: usize,
+}
+
+impl Window {
+    /// A window of `width` (at least 1) integers that never starts below `from`
+    pub fn new(width: usize, from: usize) -> Self {
+        Self {
+            width,
+            from,
+            primes: VecDeque::new(),
+            record: 0,
+        }
+    }
+
+    /// Slide the window on to end at `p`, the next prime; Some when it now holds more
+    /// primes than any window before
+    pub fn push(&mut self, p: usize) -> Option<Cluster> {
+        self.primes.push_back(p);
+        // A window ending below width − 1 would start below 0
+        let start = (p + 1).saturating_sub(self.width);
+        while self.primes.front().is_some_and(|&q| q < start) {
+            self.primes.pop_front();
+        }
+        if p + 1 < self.width || start < self.from || self.primes.len() <= self.record {
+            return None;
+        }
+        self.record = self.primes.len();
+        Some(Cluster {
+            start,
+            end: p,
+            primes: self.primes.iter().copied().collect(),
+        })
+    }
+}
+
+/// Every record window of `width` integers within [from, limit], in increasing order
+pub fn records(from: usize, limit: usize, width: usize) -> Vec<Cluster> {
+    let mut window = Window::new(width, from);
+    let mut found = Vec::new();
+    for_each_prime_in_range(from, limit, |primes| {
+        found.extend(primes.iter().filter_map(|&p| window.push(p)));
+    });
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records() {
+        // Nothing beats the 26 primes in [2, 101]
+        let found = records(1, 10_000, 100);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].start, found[0].end), (2, 101));
+        assert_eq!(found[0].primes.len(), 26);
+
+        // From an independent sieve and slide up to 2·10^6
+        let summary: Vec<(usize, usize)> = records(1_000_000, 2_000_000, 100)
+            .iter()
+            .map(|cluster| (cluster.start, cluster.primes.len()))
+            .collect();
+        assert_eq!(
+            &summary[7..],
+            [
+                (1_006_120, 13),
+                (1_006_142, 14),
+                (1_017_292, 15),
+                (1_219_780, 16),
+                (1_652_830, 17)
+            ]
+        );
+        assert_eq!(summary[0], (1_000_000, 6));
+    }
+
+    #[test]
+    fn test_window_skips_primes_that_fall_out() {
+        let mut window = Window::new(10, 1000);
+        let pushed: Vec<Option<usize>> = [1009, 1013, 1019, 1021, 1031]
+            .iter()
+            .map(|&p| window.push(p).map(|cluster| cluster.primes.len()))
+            .collect();
+        // [1004, 1013] holds 1009 and 1013; [1012, 1021] holds 1013, 1019 and 1021
+        assert_eq!(pushed, [Some(1), Some(2), None, Some(3), None]);
+        assert!(window.push(1033).is_none());
+    }
+}
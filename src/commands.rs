@@ -0,0 +1,13 @@
+// Subcommand handlers, grouped by what they work on
+//
+// main.rs parses the command line and passes each command to the module that runs it.
+// Handlers print their results and exit with status 2 for bad arguments and 1 for
+// failures.
+
+pub mod arithmetic;
+pub mod constants;
+pub mod digits;
+pub mod distribution;
+pub mod primality;
+pub mod sieve;
+pub mod stored;
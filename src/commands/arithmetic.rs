@@ -0,0 +1,436 @@
+// Arithmetic functions and the sequences and searches built on them
+
+use std::path::PathBuf;
+use std::time::Instant;
+use tracing::{error, info};
+
+use crate::cli::{
+    AbcArgs, AmicableArgs, ArithArgs, AutomorphicArgs, Commands, DucciArgs, FareyArgs, PerfectArgs,
+    PersistenceArgs, RuthAaronArgs, SternBrocotArgs, UntouchableArgs, WeirdArgs,
+};
+use nt_core::arith_table::ArithTable;
+use nt_core::rational::Ratio;
+use nt_core::{
+    abc, aliquot, arith, automorphic, ducci, farey, parallel, persistence, random, ruth_aaron,
+    segment_format, spf, stern_brocot, storage, weird,
+};
+
+/// Run an arithmetic function command
+pub fn run(command: Commands) {
+    match command {
+        Commands::Arith(ArithArgs {
+            function,
+            limit,
+            workers,
+            consumers,
+            binary,
+            max_memory,
+        }) => {
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let encoding = if binary {
+                segment_format::SegmentEncoding::Binary
+            } else {
+                segment_format::SegmentEncoding::Text
+            };
+            let start = Instant::now();
+            let count = arith::run(
+                function,
+                limit,
+                encoding,
+                num_workers,
+                consumers as usize,
+                max_memory,
+            );
+            info!(
+                "Wrote {} values in {:.2}s",
+                count,
+                start.elapsed().as_secs_f64()
+            );
+        }
+        Commands::Perfect(PerfectArgs { limit, table }) => {
+            let (table, limit) = open_aliquot_table(table, limit);
+            let perfect = aliquot::perfect(&table, limit);
+            for n in &perfect {
+                println!("{}", n);
+            }
+            info!("{} perfect numbers up to {}", perfect.len(), limit);
+        }
+        Commands::Amicable(AmicableArgs { limit, table }) => {
+            let (table, limit) = open_aliquot_table(table, limit);
+            let pairs = aliquot::amicable(&table, limit);
+            for (a, b) in &pairs {
+                println!("{} {}", a, b);
+            }
+            info!("{} amicable pairs up to {}", pairs.len(), limit);
+        }
+        Commands::Untouchable(UntouchableArgs { table }) => {
+            let (table, _) = open_aliquot_table(table, None);
+            let untouchable = aliquot::untouchable(&table);
+            for n in &untouchable {
+                println!("{}", n);
+            }
+            info!(
+                "{} untouchable numbers up to {} (from s(m) for m <= {})",
+                untouchable.len(),
+                aliquot::untouchable_bound(table.limit()),
+                table.limit()
+            );
+        }
+        Commands::Persistence(PersistenceArgs { limit, workers }) => {
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let records = persistence::records(limit as u64, num_workers);
+            info!("Scanned in {:.2}s", start.elapsed().as_secs_f64());
+            println!("Persistence records up to {}:", limit);
+            for &(steps, n) in &records {
+                println!("{:>4}  {}", steps, n);
+            }
+
+            // The current record, one digit product per line
+            let Some(&(steps, record)) = records.last() else {
+                return;
+            };
+            println!("\n{} reaches one digit in {} steps:", record, steps);
+            let products = persistence::DigitProducts::new();
+            for pair in products.chain(record).windows(2) {
+                let digits: Vec<String> = pair[0].to_string().chars().map(String::from).collect();
+                println!("  {} = {}", digits.join("×"), pair[1]);
+            }
+        }
+        Commands::Weird(WeirdArgs { limit, workers }) => {
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let stats = weird::classify(limit, num_workers);
+            for n in &stats.weird {
+                println!("{}", n);
+            }
+            info!(
+                "{} abundant numbers up to {}: {} semiperfect, {} weird ({:.2}s)",
+                stats.abundant,
+                limit,
+                stats.semiperfect,
+                stats.weird.len(),
+                start.elapsed().as_secs_f64()
+            );
+        }
+        Commands::Abc(AbcArgs {
+            limit,
+            min_quality,
+            table,
+            workers,
+        }) => {
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let default_table = storage::get_nt_data_dir().join("spf.bin");
+            let saved = match &table {
+                Some(path) => match spf::SpfTable::open(path) {
+                    Ok(table) if table.limit() >= limit => Some(table),
+                    Ok(table) => {
+                        error!(
+                            "Error: {} only covers n <= {}",
+                            path.display(),
+                            table.limit()
+                        );
+                        std::process::exit(2);
+                    }
+                    Err(e) => {
+                        error!("Error opening {}: {}", path.display(), e);
+                        std::process::exit(1);
+                    }
+                },
+                None => spf::SpfTable::open(&default_table)
+                    .ok()
+                    .filter(|table| table.limit() >= limit),
+            };
+            let start = Instant::now();
+            let table = saved.unwrap_or_else(|| {
+                info!("Sieving smallest prime factors up to {}", limit);
+                spf::SpfTable::new(limit)
+            });
+            let triples = abc::find_triples(&table, limit, min_quality, num_workers);
+            for triple in &triples {
+                println!(
+                    "{} + {} = {}\tq = {:.6}\trad(abc) = {}",
+                    triple.a, triple.b, triple.c, triple.quality, triple.radical
+                );
+            }
+            info!(
+                "Found {} triples of quality above {} with c <= {} in {:.2}s",
+                triples.len(),
+                min_quality,
+                limit,
+                start.elapsed().as_secs_f64()
+            );
+        }
+        Commands::RuthAaron(RuthAaronArgs {
+            limit,
+            distinct,
+            table,
+            workers,
+        }) => {
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let default_table = storage::get_nt_data_dir().join("spf.bin");
+            let saved = match &table {
+                Some(path) => match spf::SpfTable::open(path) {
+                    Ok(table) if table.limit() >= limit => Some(table),
+                    Ok(table) => {
+                        error!(
+                            "Error: {} only covers n <= {}",
+                            path.display(),
+                            table.limit()
+                        );
+                        std::process::exit(2);
+                    }
+                    Err(e) => {
+                        error!("Error opening {}: {}", path.display(), e);
+                        std::process::exit(1);
+                    }
+                },
+                None => spf::SpfTable::open(&default_table)
+                    .ok()
+                    .filter(|table| table.limit() >= limit),
+            };
+            let start = Instant::now();
+            let table = saved.unwrap_or_else(|| {
+                info!("Sieving smallest prime factors up to {}", limit);
+                spf::SpfTable::new(limit)
+            });
+            let pairs = ruth_aaron::find_pairs(&table, limit, distinct, num_workers);
+            for &(n, sum) in &pairs {
+                println!("{}, {}: {}", n, n + 1, sum);
+            }
+            info!(
+                "Found {} Ruth–Aaron pairs up to {} in {:.2}s",
+                pairs.len(),
+                limit,
+                start.elapsed().as_secs_f64()
+            );
+        }
+        Commands::Ducci(DucciArgs {
+            tuple,
+            max_steps,
+            scan,
+            length,
+            max_value,
+            seed,
+        }) => {
+            let show = |tuple: &[u64]| {
+                let entries: Vec<String> = tuple.iter().map(|x| x.to_string()).collect();
+                format!("({})", entries.join(", "))
+            };
+            let Some(count) = scan else {
+                if tuple.len() < 2 {
+                    error!("Error: a Ducci tuple needs at least 2 entries");
+                    std::process::exit(2);
+                }
+                let (trajectory, outcome) = ducci::run(&tuple, max_steps);
+                for (i, tuple) in trajectory.iter().enumerate() {
+                    println!("{:>6}  {}", i, show(tuple));
+                }
+                match outcome {
+                    ducci::Outcome::Zero { steps } => {
+                        println!("Reached zero after {} steps", steps)
+                    }
+                    ducci::Outcome::Cycle { start, period } => {
+                        println!("Entered a cycle of period {} after {} steps", period, start)
+                    }
+                    ducci::Outcome::Unresolved => {
+                        println!("No zero or cycle within {} steps", max_steps)
+                    }
+                }
+                return;
+            };
+
+            if length < 2 || max_value == 0 {
+                error!("Error: --length must be at least 2 and --max-value positive");
+                std::process::exit(2);
+            }
+            let seed = seed.unwrap_or_else(|| random::Rng::from_entropy().next_u64());
+            let stats = ducci::scan(count, length, max_value, max_steps, seed);
+            println!(
+                "{} random {}-tuples with entries below {} (seed {})",
+                stats.tuples, length, max_value, seed
+            );
+            let percent = |n: usize| n as f64 * 100.0 / stats.tuples.max(1) as f64;
+            println!(
+                "Reached zero: {} ({:.2}%), {:.2} steps on average, at most {}",
+                stats.zero,
+                percent(stats.zero),
+                stats.zero_steps_total as f64 / stats.zero.max(1) as f64,
+                stats.zero_steps_max
+            );
+            let cycled: usize = stats.cycles.values().sum();
+            println!(
+                "Cycled:       {} ({:.2}%), entered after at most {} steps",
+                cycled,
+                percent(cycled),
+                stats.cycle_start_max
+            );
+            for (period, starts) in &stats.cycles {
+                println!("  period {:>6}: {}", period, starts);
+            }
+            if stats.unresolved > 0 {
+                println!(
+                    "Unresolved:   {} within {} steps",
+                    stats.unresolved, max_steps
+                );
+            }
+        }
+        Commands::Automorphic(AutomorphicArgs {
+            limit,
+            cubes,
+            digits,
+            output,
+        }) => {
+            let Some(digits) = digits else {
+                let limit = limit.unwrap();
+                let power = if cubes { 3 } else { 2 };
+                let found = automorphic::enumerate(limit as u64, power);
+                for n in &found {
+                    println!("{}", n);
+                }
+                info!(
+                    "{} {} numbers up to {}",
+                    found.len(),
+                    if cubes { "trimorphic" } else { "automorphic" },
+                    limit
+                );
+                return;
+            };
+
+            if digits == 0 {
+                error!("Error: --digits must be at least 1");
+                std::process::exit(2);
+            }
+            let start = Instant::now();
+            let (five, six) = automorphic::idempotents(digits);
+            info!("Built in {:.2}s", start.elapsed().as_secs_f64());
+            // Either branch may have a 0 in its top digit, leaving no d-digit number there
+            let values: Vec<String> = [five, six]
+                .iter()
+                .map(|x| {
+                    let value = x.to_string();
+                    "0".repeat(digits - value.len()) + &value
+                })
+                .collect();
+            if let Some(path) = &output {
+                if let Err(e) = std::fs::write(path, format!("{}\n", values.join("\n"))) {
+                    error!("Error writing {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+                info!("Wrote {} digits to {}", digits, path.display());
+            }
+            for value in &values {
+                let shown = if digits <= 60 {
+                    value.clone()
+                } else {
+                    format!("{}...{}", &value[..20], &value[digits - 20..])
+                };
+                if value.starts_with('0') && digits > 1 {
+                    println!("{} (leading zero, not a {}-digit number)", shown, digits);
+                } else {
+                    println!("{} ({} digits)", shown, digits);
+                }
+            }
+        }
+        Commands::Farey(FareyArgs {
+            n,
+            count,
+            around,
+            workers,
+        }) => {
+            if n == 0 {
+                error!("Error: n must be at least 1");
+                std::process::exit(2);
+            }
+            if count {
+                let num_workers = workers.unwrap_or_else(parallel::default_workers);
+                let start = Instant::now();
+                let length = farey::length(n, num_workers);
+                info!("Counted in {:.2}s", start.elapsed().as_secs_f64());
+                println!("|F_{}| = {}", n, length);
+                return;
+            }
+            if let Some(x) = around {
+                if x > Ratio::integer(1) {
+                    error!("Error: {} is outside [0, 1]", x);
+                    std::process::exit(2);
+                }
+                let (below, above) = farey::neighbors(x, n as u64);
+                let show = |term: Option<Ratio>| term.map_or("none".to_string(), |t| t.to_string());
+                let membership = if x.den() as usize <= n {
+                    "in"
+                } else {
+                    "not in"
+                };
+                println!("{} is {} F_{}", x, membership, n);
+                println!("Below: {}", show(below));
+                println!("Above: {}", show(above));
+                return;
+            }
+
+            use std::io::Write;
+            let mut output = std::io::BufWriter::new(std::io::stdout().lock());
+            for term in farey::Farey::new(n as u64) {
+                if let Err(e) = writeln!(output, "{}", term) {
+                    // Stop quietly when piped into head
+                    if e.kind() != std::io::ErrorKind::BrokenPipe {
+                        error!("Error writing output: {}", e);
+                        std::process::exit(1);
+                    }
+                    return;
+                }
+            }
+            let _ = output.flush();
+        }
+        Commands::SternBrocot(SternBrocotArgs { fraction }) => {
+            if fraction.num() == 0 {
+                error!("Error: 0 is not in the Stern–Brocot tree");
+                std::process::exit(2);
+            }
+            let runs = stern_brocot::path(fraction);
+            let depth: u64 = runs.iter().map(|run| run.steps).sum();
+            let compact: Vec<String> = runs
+                .iter()
+                .map(|run| format!("{}^{}", run.direction.letter(), run.steps))
+                .collect();
+            if runs.is_empty() {
+                println!("{} is the root (depth 0)", fraction);
+                return;
+            }
+            println!("{}: {} (depth {})", fraction, compact.join(" "), depth);
+            for run in &runs {
+                println!(
+                    "  {} ×{:<6} → {}",
+                    run.direction.letter(),
+                    run.steps,
+                    run.node
+                );
+            }
+        }
+        _ => unreachable!("not a arithmetic command"),
+    }
+}
+
+/// Open the aliquot table at `path` (or the default one) and check it covers `limit`
+/// Returns the table and the limit to search, the table's own when none was given
+fn open_aliquot_table(path: Option<PathBuf>, limit: Option<usize>) -> (ArithTable, usize) {
+    let path = path.unwrap_or_else(|| ArithTable::default_path(arith::ArithFunction::Aliquot));
+    let table = match ArithTable::open(&path) {
+        Ok(table) => table,
+        Err(e) => {
+            error!("Error opening {}: {}", path.display(), e);
+            error!("Build it with `nt arith aliquot <limit> --binary`.");
+            std::process::exit(1);
+        }
+    };
+    let limit = limit.unwrap_or(table.limit());
+    if limit > table.limit() {
+        error!(
+            "Error: {} only covers n <= {}",
+            path.display(),
+            table.limit()
+        );
+        std::process::exit(2);
+    }
+    (table, limit)
+}
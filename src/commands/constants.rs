@@ -0,0 +1,151 @@
+// Constants to any number of digits: π, γ, ζ(s) and the prime zeta function
+
+use std::time::Instant;
+use tracing::{error, info};
+
+use crate::cli::{Commands, GammaArgs, PiArgs, PrimeZetaArgs, ZetaArgs};
+use nt_core::constants::{cache, gamma, prime_zeta, zeta};
+use nt_core::{constants, parallel, pi, scan};
+
+/// Run a constants command
+pub fn run(command: Commands) {
+    match command {
+        Commands::Pi(PiArgs {
+            digits,
+            progress,
+            palindromes,
+            min_length,
+            base,
+        }) => {
+            if !palindromes {
+                pi::calculate_and_print(digits, progress);
+                return;
+            }
+            let expansion = match cache::expansion(cache::NamedConstant::Pi, digits) {
+                Ok((expansion, _)) => expansion,
+                Err(e) => {
+                    error!(
+                        "Error caching {}: {}",
+                        cache::cache_path(cache::NamedConstant::Pi).display(),
+                        e
+                    );
+                    std::process::exit(1);
+                }
+            };
+            let places = pi::fraction_in_base(&expansion, base);
+            scan::print_palindromes(
+                &places,
+                min_length as usize,
+                &format!("base {} places of π", base),
+            );
+        }
+        Commands::Gamma(GammaArgs { digits, progress }) => {
+            gamma::calculate_and_print(digits, progress);
+        }
+        Commands::Zeta(ZetaArgs {
+            s,
+            digits,
+            euler_product,
+        }) => {
+            if digits == 0 {
+                error!("Error: --digits must be at least 1");
+                std::process::exit(2);
+            }
+            let precision = constants::precision_for_digits(digits).max(64);
+            let argument = match zeta::parse_argument(&s, precision) {
+                Ok(argument) => argument,
+                Err(e) => {
+                    error!("Error: {}", e);
+                    std::process::exit(2);
+                }
+            };
+            let start = Instant::now();
+            let Some(value) = zeta::zeta(&argument, precision) else {
+                error!("Error: ζ has a pole at s = 1");
+                std::process::exit(2);
+            };
+            info!("Evaluated in {:.2}s", start.elapsed().as_secs_f64());
+            let name = if argument == 3 {
+                " (Apéry's constant)"
+            } else {
+                ""
+            };
+            println!(
+                "ζ({}) = {}{}",
+                s.trim(),
+                value.to_string_radix(10, Some(digits)),
+                name
+            );
+
+            if let Some(limit) = euler_product {
+                if argument <= 1 {
+                    error!("Error: the Euler product only converges for s > 1");
+                    std::process::exit(2);
+                }
+                let start = Instant::now();
+                let product = zeta::euler_product(&argument, limit, precision);
+                info!("Multiplied in {:.2}s", start.elapsed().as_secs_f64());
+                let gap = rug::Float::with_val(precision, &value - &product);
+                println!(
+                    "Euler product over primes <= {} = {}",
+                    limit,
+                    product.to_string_radix(10, Some(digits))
+                );
+                println!(
+                    "ζ(s) − product = {} (relative {:.3e})",
+                    gap.to_string_radix(10, Some(6)),
+                    (gap / &value).to_f64()
+                );
+            }
+        }
+        Commands::PrimeZeta(PrimeZetaArgs {
+            s,
+            digits,
+            partial,
+            workers,
+        }) => {
+            if digits == 0 {
+                error!("Error: --digits must be at least 1");
+                std::process::exit(2);
+            }
+            let precision = constants::precision_for_digits(digits).max(64);
+            let argument = match zeta::parse_argument(&s, precision) {
+                Ok(argument) => argument,
+                Err(e) => {
+                    error!("Error: {}", e);
+                    std::process::exit(2);
+                }
+            };
+            let start = Instant::now();
+            let Some(value) = prime_zeta::prime_zeta(&argument, precision) else {
+                error!("Error: P(s) only converges for s > 1");
+                std::process::exit(2);
+            };
+            info!("Evaluated in {:.2}s", start.elapsed().as_secs_f64());
+            println!(
+                "P({}) = {}",
+                s.trim(),
+                value.to_string_radix(10, Some(digits))
+            );
+
+            if let Some(limit) = partial {
+                let num_workers = workers.unwrap_or_else(parallel::default_workers);
+                let start = Instant::now();
+                let sum = prime_zeta::partial_sum(&argument, limit, precision, num_workers);
+                info!("Summed in {:.2}s", start.elapsed().as_secs_f64());
+                let tail = rug::Float::with_val(precision, &value - &sum);
+                println!(
+                    "Σ p^−s over primes <= {} = {}",
+                    limit,
+                    sum.to_string_radix(10, Some(digits))
+                );
+                println!(
+                    "P(s) − partial sum = {} (primes above {})",
+                    tail.to_string_radix(10, Some(6)),
+                    limit
+                );
+            }
+        }
+        _ => unreachable!("not a constants command"),
+    }
+}
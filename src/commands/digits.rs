@@ -0,0 +1,344 @@
+// Commands about decimal digits: random and constant digit streams, searches through them,
+// base conversion, palindromes and digit-built primes
+
+use std::time::Instant;
+use tracing::{error, info};
+
+use crate::cli::{
+    BaseArgs, ChainArgs, Commands, DigitTreeArgs, PalindromesArgs, PrimesBasesArgs, RandomArgs,
+    ScanArgs, SequenceArgs, SmarandacheArgs,
+};
+use nt_core::{
+    chain, digit_source, digit_tree, palindromes, parallel, primes_bases, radix, random, scan,
+    scan_results, sequence, smarandache,
+};
+
+/// Run a digits command
+pub fn run(command: Commands) {
+    match command {
+        Commands::PrimesBases(PrimesBasesArgs {
+            pal_only,
+            pal,
+            stdin,
+            sort,
+        }) => {
+            primes_bases::run(pal_only, pal, stdin, sort);
+        }
+        Commands::Random(RandomArgs {
+            digits,
+            pattern,
+            max_digits,
+        }) => {
+            random::generate_and_scan(digits, pattern, max_digits as usize);
+        }
+        Commands::Chain(ChainArgs {
+            overlap,
+            length,
+            interactive,
+            tournament,
+            runs,
+            seed,
+        }) => {
+            if interactive {
+                chain::run_interactive(overlap);
+            } else if tournament {
+                chain::run_tournament(overlap, length, runs as usize, seed);
+            } else {
+                chain::build_chain(overlap, length);
+            }
+        }
+        Commands::Scan(ScanArgs {
+            source,
+            stdin,
+            digits,
+            pattern,
+            max_digits,
+            heatmap,
+            heatmap_format,
+            non_overlapping,
+            palindromes,
+            min_length,
+            stats,
+            save,
+            resume,
+        }) => {
+            let spec = if stdin {
+                digit_source::SourceSpec::Stdin
+            } else {
+                source.unwrap()
+            };
+            let mut source = spec.open();
+            let input = match source.digits(digits) {
+                Ok(input) => input,
+                Err(e) => {
+                    error!("Error reading {}: {}", source.name(), e);
+                    std::process::exit(1);
+                }
+            };
+            info!("Scanning {}...", source.name());
+            let what = format!("digits of {}", source.name());
+            if palindromes {
+                scan::print_palindromes(&input, min_length as usize, &what);
+            } else if stats {
+                scan::print_statistics(&input, &what);
+            } else if let Some(buckets) = heatmap {
+                scan::heatmap_for(
+                    &input,
+                    pattern,
+                    max_digits as usize,
+                    buckets as usize,
+                    heatmap_format,
+                );
+            } else if save || resume {
+                let pattern_source = match pattern.source(max_digits as usize) {
+                    Ok(pattern_source) => pattern_source,
+                    Err(e) => {
+                        error!("{}", e);
+                        std::process::exit(1);
+                    }
+                };
+                let name = spec.to_string();
+                let path = scan_results::results_path(&name, pattern);
+                let previous = if resume {
+                    match scan_results::read(&path) {
+                        Ok(previous) => previous,
+                        Err(e) => {
+                            error!("Error reading {}: {}", path.display(), e);
+                            std::process::exit(1);
+                        }
+                    }
+                } else {
+                    None
+                };
+                if let Some(previous) = &previous {
+                    info!(
+                        "Resuming after {} digits with {} occurrences",
+                        previous.digits,
+                        previous.found.len()
+                    );
+                }
+                scan::print_header(&input, pattern_source.as_ref());
+                let (record, new) =
+                    match scan_results::scan(&name, &input, pattern_source.as_ref(), previous) {
+                        Ok(scanned) => scanned,
+                        Err(e) => {
+                            error!("Can't resume from {}: {}", path.display(), e);
+                            std::process::exit(1);
+                        }
+                    };
+                if let Err(e) = scan_results::write(&path, &record) {
+                    error!("Error writing {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+                info!("{} new occurrences, saved to {}", new, path.display());
+                scan::print_found(&input, record.found, non_overlapping);
+            } else {
+                scan::scan_for(&input, pattern, max_digits as usize, non_overlapping);
+            }
+        }
+        Commands::Sequence(SequenceArgs {
+            kind,
+            terms,
+            concat,
+        }) => {
+            use std::io::Write;
+            let mut output = std::io::BufWriter::new(std::io::stdout().lock());
+            let separator = if concat { "" } else { "\n" };
+            for term in sequence::terms(kind, terms) {
+                if let Err(e) = write!(output, "{}{}", term, separator) {
+                    // Stop quietly when piped into head
+                    if e.kind() != std::io::ErrorKind::BrokenPipe {
+                        error!("Error writing output: {}", e);
+                        std::process::exit(1);
+                    }
+                    return;
+                }
+            }
+            if concat {
+                let _ = writeln!(output);
+            }
+            let _ = output.flush();
+        }
+        Commands::Base(BaseArgs {
+            numbers,
+            from,
+            to,
+            stdin,
+        }) => {
+            let convert = |text: &str| {
+                radix::parse_big(text, from).map(|value| radix::to_base_big(&value, to))
+            };
+            if !stdin {
+                for number in &numbers {
+                    match convert(number) {
+                        Ok(converted) => println!("{}", converted),
+                        Err(e) => {
+                            error!("Error: {}", e);
+                            std::process::exit(2);
+                        }
+                    }
+                }
+                return;
+            }
+
+            use std::io::{BufRead, Write};
+            let mut output = std::io::BufWriter::new(std::io::stdout().lock());
+            let mut invalid = 0;
+            for line in std::io::stdin().lock().lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(e) => {
+                        error!("Error reading stdin: {}", e);
+                        std::process::exit(1);
+                    }
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match convert(&line) {
+                    Ok(converted) => {
+                        if let Err(e) = writeln!(output, "{}", converted) {
+                            // Stop quietly when piped into head
+                            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                                error!("Error writing output: {}", e);
+                                std::process::exit(1);
+                            }
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        error!("Error: {}", e);
+                        invalid += 1;
+                    }
+                }
+            }
+            let _ = output.flush();
+            if invalid > 0 {
+                std::process::exit(1);
+            }
+        }
+        Commands::Palindromes(PalindromesArgs {
+            number,
+            search,
+            limit,
+            workers,
+        }) => {
+            if let Some(n) = number {
+                let bases = palindromes::palindromic_bases(n);
+                for &base in &bases {
+                    println!("{:>4}  {}", base, palindromes::format_in(n, base));
+                }
+                info!(
+                    "{} is a palindrome in {} of the bases 2-{}",
+                    n,
+                    bases.len(),
+                    palindromes::MAX_BASE
+                );
+                return;
+            }
+
+            let Some(k) = search else { return };
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let found = palindromes::smallest_with(k, limit as u64, num_workers);
+            info!("Searched in {:.2}s", start.elapsed().as_secs_f64());
+            match found {
+                Some(n) => {
+                    println!(
+                        "{} is the smallest number that is a palindrome in at least {} bases:",
+                        n, k
+                    );
+                    for base in palindromes::palindromic_bases(n) {
+                        println!("{:>4}  {}", base, palindromes::format_in(n, base));
+                    }
+                }
+                None => {
+                    println!("No number up to {} is a palindrome in {} bases", limit, k);
+                }
+            }
+        }
+        Commands::Smarandache(SmarandacheArgs {
+            max_terms,
+            workers,
+            progress,
+        }) => {
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let found = smarandache::prime_terms(max_terms, num_workers, progress);
+            info!("Tested in {:.2}s", start.elapsed().as_secs_f64());
+            println!(
+                "Concatenations of the first k <= {} primes that are probable primes:",
+                max_terms
+            );
+            for prime in &found {
+                println!(
+                    "k = {:>6}  through {:>8}  {:>8} digits",
+                    prime.terms, prime.last_prime, prime.digits
+                );
+            }
+            if found.is_empty() {
+                println!("none");
+            }
+        }
+        Commands::DigitTree(DigitTreeArgs {
+            left,
+            from,
+            depth,
+            paths,
+            interactive,
+        }) => {
+            let side = if left {
+                digit_tree::Side::Left
+            } else {
+                digit_tree::Side::Right
+            };
+            let start = match from.as_deref().map(|text| text.parse::<rug::Integer>()) {
+                None => None,
+                Some(Ok(n)) if digit_tree::is_prime(&n) => Some(n),
+                Some(_) => {
+                    error!("Error: --from must be a prime");
+                    std::process::exit(2);
+                }
+            };
+
+            if interactive {
+                let mut output = std::io::stdout().lock();
+                if let Err(e) =
+                    digit_tree::interactive(start, side, std::io::stdin().lock(), &mut output)
+                {
+                    error!("Error: {}", e);
+                    std::process::exit(1);
+                }
+                return;
+            }
+
+            let roots = start.map_or_else(digit_tree::roots, |n| vec![n]);
+            let tree = digit_tree::explore(roots, side, depth);
+            println!(
+                "{:>6}  {:>8}  {:>8}  {:>8}  {:>9}",
+                "digits", "primes", "children", "leaves", "branching"
+            );
+            for level in &tree.levels {
+                println!(
+                    "{:>6}  {:>8}  {:>8}  {:>8}  {:>9.3}",
+                    level.digits,
+                    level.nodes,
+                    level.children,
+                    level.leaves,
+                    level.branching()
+                );
+            }
+            info!("{} primes in the tree", tree.size());
+
+            println!("\nDeepest primes reached, from the root:");
+            for node in tree.deepest.iter().rev().take(paths) {
+                let path: Vec<String> = digit_tree::path(node, side)
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect();
+                println!("  {}", path.join(" → "));
+            }
+        }
+        _ => unreachable!("not a digits command"),
+    }
+}
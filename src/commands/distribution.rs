@@ -0,0 +1,511 @@
+// How the primes are spread out: counts, sums, gaps, digit statistics and the classic
+// conjectures about primes in short intervals
+
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::cli::{
+    BertrandArgs, ClustersArgs, Commands, GapFirstsArgs, LegendreArgs, OrmistonArgs, PiTableArgs,
+    PrimeCountArgs, PrimeDigitsArgs, PrimeStatsArgs, PrimeSumArgs, UlamArgs,
+};
+use nt_core::{
+    bertrand, clusters, convert, gap_firsts, legendre, ormiston, parallel, pi_table, prime_count,
+    prime_digits, prime_stats, prime_sum, storage, ulam,
+};
+
+/// Run a prime distribution command
+pub fn run(command: Commands) {
+    match command {
+        Commands::GapFirsts(GapFirstsArgs {
+            limit,
+            variation,
+            workers,
+            consumers,
+            output,
+        }) => {
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let stats = gap_firsts::find(limit, variation, num_workers, consumers as usize);
+
+            let output =
+                output.unwrap_or_else(|| storage::get_nt_data_dir().join("gap_firsts.csv"));
+            match gap_firsts::write_csv(&stats, &output) {
+                Ok(rows) => info!(
+                    "Wrote first occurrences of {} gaps (largest {} after {}) to {} in {:.2}s",
+                    rows,
+                    stats.max_gap,
+                    stats.max_gap_start,
+                    output.display(),
+                    start.elapsed().as_secs_f64()
+                ),
+                Err(e) => error!("Error writing {}: {}", output.display(), e),
+            }
+        }
+        Commands::PrimeCount(PrimeCountArgs {
+            x,
+            estimate,
+            strips,
+            width,
+            seed,
+            workers,
+        }) => {
+            let Some(x) = prime_count::parse_x(&x) else {
+                error!("Error: '{}' is not a whole number >= 2", x);
+                std::process::exit(2);
+            };
+            let start = Instant::now();
+
+            if !estimate {
+                let Some(limit) = x.to_usize() else {
+                    error!("Error: {} is too large to count exactly; use --estimate", x);
+                    std::process::exit(2);
+                };
+                println!("π({}) = {}", x, prime_count::count(limit));
+                info!("Counted in {:.2}s", start.elapsed().as_secs_f64());
+                return;
+            }
+
+            let options = prime_count::SampleOptions {
+                strips,
+                width,
+                seed,
+                workers: workers.unwrap_or_else(parallel::default_workers),
+            };
+            let result = prime_count::estimate(&x, &options);
+            let round = |value: &rug::Float| value.to_integer().unwrap_or_default();
+            println!("x          = {}", x);
+            println!("li(x)      = {}", round(&result.li));
+            println!("R(x)       = {}", round(&result.r));
+            if let Some(bound) = &result.rh_bound {
+                println!(
+                    "RH bound   = ±{} (Schoenfeld, |π(x) − li(x)|)",
+                    round(bound)
+                );
+            }
+            println!(
+                "Sampled    = {} ± {} ({} strips of {}, {} primes found)",
+                round(&result.sampled),
+                round(&result.sampled_half_width),
+                result.strips,
+                result.width,
+                result.primes_found
+            );
+            println!(
+                "π(x)       ≈ {} ± {} (95%)",
+                round(&result.estimate),
+                round(&result.half_width)
+            );
+            info!("Estimated in {:.2}s", start.elapsed().as_secs_f64());
+        }
+        Commands::PiTable(PiTableArgs {
+            limit,
+            step,
+            query,
+            table,
+            workers,
+        }) => {
+            let path = table.unwrap_or_else(|| storage::get_nt_data_dir().join("pi_table.bin"));
+            if let Some(x) = query {
+                let table = match pi_table::PiTable::open(&path) {
+                    Ok(table) => table,
+                    Err(e) => {
+                        error!("Error opening {}: {}", path.display(), e);
+                        std::process::exit(1);
+                    }
+                };
+                let Some(bracket) = table.bracket(x) else {
+                    error!(
+                        "Error: {} is past the table's limit of {}",
+                        x,
+                        table.limit()
+                    );
+                    std::process::exit(2);
+                };
+                match bracket.exact() {
+                    Some(pi) => println!("π({}) = {}", x, pi),
+                    None => println!(
+                        "π({}) ≈ {:.0}, between π({}) = {} and π({}) = {}",
+                        x,
+                        bracket.estimate,
+                        bracket.low.0,
+                        bracket.low.1,
+                        bracket.high.0,
+                        bracket.high.1
+                    ),
+                }
+                if x >= 2 {
+                    let li = prime_count::li(&rug::Integer::from(x)).to_f64();
+                    println!(
+                        "li({}) = {:.0}, li − π ≈ {:.0}",
+                        x,
+                        li,
+                        li - bracket.estimate
+                    );
+                }
+                return;
+            }
+
+            let limit = limit.unwrap();
+            if step == 0 || step > u32::MAX as usize {
+                error!("Error: --step must be between 1 and {}", u32::MAX);
+                std::process::exit(2);
+            }
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            if let Some(parent) = path.parent()
+                && let Err(e) = std::fs::create_dir_all(parent)
+            {
+                error!("Error creating {}: {}", parent.display(), e);
+                std::process::exit(1);
+            }
+            let start = Instant::now();
+            let table = pi_table::PiTable::build(limit, step, num_workers);
+            if let Err(e) = table
+                .save(&storage::staging_path(&path))
+                .and_then(|()| storage::commit_output(&path))
+            {
+                error!("Error writing {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+            if let Some((x, pi)) = table.checkpoints().last() {
+                println!("π({}) = {}", x, pi);
+            }
+            info!(
+                "Saved π at {} checkpoints up to {} to {} in {:.2}s",
+                table.checkpoints().count(),
+                limit,
+                path.display(),
+                start.elapsed().as_secs_f64()
+            );
+        }
+        Commands::PrimeDigits(PrimeDigitsArgs { limit }) => {
+            let start = Instant::now();
+            let (stats, from_file) = prime_digits::compute(limit);
+            info!(
+                "Counted {} primes ({} from the stored file) in {:.2}s",
+                stats.count,
+                from_file,
+                start.elapsed().as_secs_f64()
+            );
+            if stats.count == 0 {
+                println!("No primes up to {}", limit);
+                return;
+            }
+            let share = |count: u64| 100.0 * count as f64 / stats.count as f64;
+
+            println!(
+                "Leading digit  {:>12}  {:>7}  {:>7}",
+                "primes", "share", "Benford"
+            );
+            for d in 1..10 {
+                println!(
+                    "{:>13}  {:>12}  {:>6.2}%  {:>6.2}%",
+                    d,
+                    stats.leading[d],
+                    share(stats.leading[d]),
+                    100.0 * prime_digits::benford(d)
+                );
+            }
+
+            println!("\nLast digit     {:>12}  {:>7}", "primes", "share");
+            for d in (0..10).filter(|&d| stats.last[d] > 0) {
+                println!(
+                    "{:>13}  {:>12}  {:>6.2}%",
+                    d,
+                    stats.last[d],
+                    share(stats.last[d])
+                );
+            }
+
+            println!("\nDigit sum      {:>12}  {:>7}", "primes", "share");
+            for (sum, &count) in stats.digit_sums.iter().enumerate() {
+                if count > 0 {
+                    println!("{:>13}  {:>12}  {:>6.2}%", sum, count, share(count));
+                }
+            }
+
+            // Past 5 every prime ends in 1, 3, 7 or 9; independence would give 25% each
+            println!("\nLast digit of consecutive primes (share of each row)");
+            println!("  from \\ to {:>8}{:>8}{:>8}{:>8}", 1, 3, 7, 9);
+            for from in [1, 3, 7, 9] {
+                let row: Vec<String> = [1, 3, 7, 9]
+                    .iter()
+                    .map(|&to| format!("{:>7.2}%", 100.0 * stats.transition_share(from, to)))
+                    .collect();
+                println!("{:>10} {}", from, row.join(""));
+            }
+        }
+        Commands::PrimeSum(PrimeSumArgs {
+            from,
+            to,
+            squares,
+            workers,
+        }) => {
+            if to < from {
+                error!("Error: --to {} is below --from {}", to, from);
+                std::process::exit(2);
+            }
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let sums = prime_sum::compute(from, to, squares, num_workers);
+            println!("primes   = {}", sums.count);
+            println!("Σ p      = {}", sums.sum);
+            if let Some(sum_of_squares) = &sums.sum_of_squares {
+                println!("Σ p²     = {}", sum_of_squares);
+            }
+            info!(
+                "Summed the primes in [{}, {}] in {:.2}s",
+                from,
+                to,
+                start.elapsed().as_secs_f64()
+            );
+        }
+        Commands::PrimeStats(PrimeStatsArgs {
+            limit,
+            moduli,
+            workers,
+        }) => {
+            if let Some(&m) = moduli.iter().find(|&&m| m < 2) {
+                error!("Error: modulus {} must be at least 2", m);
+                std::process::exit(2);
+            }
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let stats = prime_stats::compute(limit, &moduli, num_workers);
+            println!("π(x)     = {}", stats.count);
+            println!("Σ p      = {}", stats.sum);
+            print!("Σ 1/p    = {:.12}", stats.reciprocal_sum);
+            match stats.mertens_estimate() {
+                Some(mertens) => println!(
+                    " (ln ln x + M = {:.12}, difference {:+.3e})",
+                    mertens,
+                    stats.reciprocal_sum - mertens
+                ),
+                None => println!(),
+            }
+            for residue in &stats.residues {
+                let classes = residue.coprime_classes();
+                let total: u64 = classes.iter().map(|&(_, count)| count).sum();
+                let classes: Vec<String> = classes
+                    .into_iter()
+                    .map(|(r, count)| {
+                        let share = count as f64 * 100.0 / total.max(1) as f64;
+                        format!("{}: {} ({:.3}%)", r, count, share)
+                    })
+                    .collect();
+                println!("mod {:<4} {}", residue.modulus, classes.join(", "));
+            }
+            info!("Computed in {:.2}s", start.elapsed().as_secs_f64());
+        }
+        Commands::Bertrand(BertrandArgs { limit, from, file }) => {
+            let path = file.unwrap_or_else(storage::default_prime_file);
+            let open =
+                || convert::Format::detect(&path).and_then(|format| convert::read(&path, format));
+            let (lead, trail) = match (open(), open()) {
+                (Ok(lead), Ok(trail)) => (lead, trail),
+                (Err(e), _) | (_, Err(e)) => {
+                    error!("Error opening {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            };
+            let start = Instant::now();
+            let checked = match bertrand::check(lead, trail, from, limit) {
+                Ok(checked) => checked,
+                Err(e) => {
+                    error!("Error reading {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            };
+            let Some(checked_to) = checked.checked_to else {
+                error!(
+                    "Error: {} does not reach past 2 × {}",
+                    path.display(),
+                    from.max(2)
+                );
+                std::process::exit(1);
+            };
+            let describe = |interval: &bertrand::Interval| {
+                format!(
+                    "{} primes between {} and {} (expected {:.1})",
+                    interval.primes,
+                    interval.n,
+                    2 * interval.n,
+                    interval.expected()
+                )
+            };
+            if let (Some(fewest), Some(sparsest)) = (&checked.fewest, &checked.sparsest) {
+                println!("Fewest:   {}", describe(fewest));
+                println!(
+                    "Sparsest: {}, {:.3} of expected",
+                    describe(sparsest),
+                    sparsest.ratio()
+                );
+            }
+            for n in &checked.empty {
+                println!("No prime between {} and {}", n, 2 * n);
+            }
+            if checked_to < limit {
+                warn!(
+                    "{} ends before 2 × {}; checked n up to {}",
+                    path.display(),
+                    limit,
+                    checked_to
+                );
+            }
+            info!("Checked in {:.2}s", start.elapsed().as_secs_f64());
+        }
+        Commands::Legendre(LegendreArgs {
+            n_max,
+            from,
+            workers,
+        }) => {
+            if from == 0 || from > n_max {
+                error!("Error: --from must be between 1 and {}", n_max);
+                std::process::exit(2);
+            }
+            if n_max
+                .checked_add(1)
+                .and_then(|m| m.checked_mul(m))
+                .is_none()
+            {
+                error!("Error: ({} + 1)² does not fit in 64 bits", n_max);
+                std::process::exit(2);
+            }
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let checked = legendre::check(from, n_max, num_workers);
+            let describe = |interval: &legendre::Interval| {
+                format!(
+                    "{} primes between {}² and {}² (expected {:.1})",
+                    interval.primes,
+                    interval.n,
+                    interval.n + 1,
+                    interval.expected()
+                )
+            };
+            println!("Fewest:   {}", describe(&checked.fewest));
+            println!(
+                "Sparsest: {}, {:.3} of expected",
+                describe(&checked.sparsest),
+                checked.sparsest.ratio()
+            );
+            if checked.empty.is_empty() {
+                println!("Every interval for {} <= n <= {} has a prime", from, n_max);
+            } else {
+                for n in &checked.empty {
+                    println!("No prime between {}² and {}²", n, n + 1);
+                }
+            }
+            info!("Checked in {:.2}s", start.elapsed().as_secs_f64());
+            if !checked.empty.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Clusters(ClustersArgs {
+            limit,
+            window,
+            from,
+        }) => {
+            if window == 0 {
+                error!("Error: --window must be at least 1");
+                std::process::exit(2);
+            }
+            let start = Instant::now();
+            let found = clusters::records(from, limit, window);
+            for cluster in &found {
+                println!(
+                    "{} primes in [{}, {}] (expected {:.1}): {} .. {}",
+                    cluster.primes.len(),
+                    cluster.start,
+                    cluster.end,
+                    cluster.expected(),
+                    cluster.primes[0],
+                    cluster.end
+                );
+            }
+            info!(
+                "Found {} record windows of {} up to {} in {:.2}s",
+                found.len(),
+                window,
+                limit,
+                start.elapsed().as_secs_f64()
+            );
+        }
+        Commands::Ulam(UlamArgs {
+            radius,
+            analyze,
+            top,
+            terms,
+            max_start,
+            workers,
+        }) => {
+            if !analyze {
+                for row in ulam::render(radius as i64) {
+                    println!("{}", row);
+                }
+                return;
+            }
+
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let found = ulam::hot_spots(max_start as u64, terms, top, num_workers);
+            info!("Ranked in {:.2}s", start.elapsed().as_secs_f64());
+            println!(
+                "Diagonals starting at most {} with the most primes in {} terms:",
+                max_start, terms
+            );
+            for (rank, diagonal) in found.iter().enumerate() {
+                println!(
+                    "{:>4}  {:<22}  {:>7} primes  {:>6.2}%  from {} at ({}, {}) heading {}",
+                    rank + 1,
+                    diagonal.polynomial(),
+                    diagonal.primes,
+                    100.0 * diagonal.hit_rate(),
+                    diagonal.c,
+                    diagonal.start.0,
+                    diagonal.start.1,
+                    diagonal.heading()
+                );
+            }
+        }
+        Commands::Ormiston(OrmistonArgs { limit, file }) => {
+            let path = file.unwrap_or_else(storage::default_prime_file);
+            let primes = match convert::Format::detect(&path)
+                .and_then(|format| convert::read(&path, format))
+            {
+                Ok(primes) => primes,
+                Err(e) => {
+                    error!("Error opening {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            };
+            let start = Instant::now();
+            let found = match ormiston::search(primes, limit) {
+                Ok(found) => found,
+                Err(e) => {
+                    error!("Error reading {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            };
+            for (p, q) in &found.pairs {
+                println!("{} {}", p, q);
+            }
+            if let Some(class) = found.largest_class() {
+                println!(
+                    "Largest anagram class: {} primes with the digits of {}",
+                    class.primes, class.smallest
+                );
+            }
+            let reached = found.reached.unwrap_or(0);
+            if !found.complete {
+                warn!("{} ends at {}, short of {}", path.display(), reached, limit);
+            }
+            info!(
+                "Found {} Ormiston pairs up to {} in {:.2}s",
+                found.pairs.len(),
+                reached,
+                start.elapsed().as_secs_f64()
+            );
+        }
+        _ => unreachable!("not a distribution command"),
+    }
+}
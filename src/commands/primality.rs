@@ -0,0 +1,407 @@
+// Testing, proving and factoring single numbers, and primes built from primorials
+
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+use crate::cli::{
+    CertifyArgs, Commands, FactorArgs, FortunateArgs, IsPrimeArgs, NearArgs, PrimorialArgs,
+    RandomPrimeArgs, WilsonArgs,
+};
+use nt_core::{
+    bigfactor, bpsw, certify, ecm, factor_batch, fortunate, near, parallel, pocklington, primorial,
+    random, random_prime, storage, wilson,
+};
+
+/// Run a primality or factoring command
+pub fn run(command: Commands) {
+    match command {
+        Commands::RandomPrime(RandomPrimeArgs {
+            digits,
+            pattern,
+            count,
+            max_attempts,
+            seed,
+        }) => {
+            let template = match random_prime::Template::new(pattern.as_deref(), digits) {
+                Ok(template) => template,
+                Err(e) => {
+                    error!("Error: {}", e);
+                    std::process::exit(2);
+                }
+            };
+            let mut rng = match seed {
+                Some(seed) => random::Rng::new(seed),
+                None => random::Rng::from_entropy(),
+            };
+            let found = random_prime::search(&template, count, max_attempts, &mut rng);
+            for p in &found.primes {
+                println!("{}", p);
+            }
+            if found.primes.len() < count {
+                error!(
+                    "Error: found {} of {} primes in {} candidates",
+                    found.primes.len(),
+                    count,
+                    found.attempts
+                );
+                std::process::exit(1);
+            }
+        }
+        Commands::Factor(FactorArgs {
+            n,
+            stdin,
+            algorithm,
+            curves,
+            b1,
+            b2,
+            rho_iterations,
+            fermat_steps,
+            pm1_b1,
+            pm1_b2,
+            workers,
+            format,
+        }) => {
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let mut options = bigfactor::FactorOptions {
+                algorithm,
+                rho_iterations,
+                fermat_steps,
+                pm1_b1,
+                pm1_b2: pm1_b2.unwrap_or(pm1_b1.saturating_mul(100)).max(pm1_b1),
+                ecm: ecm::EcmParams::new(b1, b2, curves, num_workers),
+            };
+
+            if stdin {
+                // Workers split the numbers, so each number's ECM curves run on one thread
+                options.ecm.workers = 1;
+                let start = Instant::now();
+                let output = std::io::BufWriter::new(std::io::stdout());
+                match factor_batch::run(std::io::stdin().lock(), output, &options, num_workers) {
+                    Ok(summary) => {
+                        info!(
+                            "Factored {} numbers in {:.3}s ({} incomplete, {} invalid)",
+                            summary.numbers,
+                            start.elapsed().as_secs_f64(),
+                            summary.incomplete,
+                            summary.invalid
+                        );
+                        if summary.incomplete > 0 || summary.invalid > 0 {
+                            std::process::exit(1);
+                        }
+                    }
+                    Err(e) => {
+                        error!("Error during batch factoring: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
+
+            let n = n.unwrap_or_default();
+            let n = match n.trim().parse::<rug::Integer>() {
+                Ok(n) if n >= 0 => n,
+                _ => {
+                    error!("Error: '{}' is not a non-negative integer", n);
+                    std::process::exit(2);
+                }
+            };
+
+            if n <= 1 {
+                println!("{} has no prime factors", n);
+                return;
+            }
+
+            let start = Instant::now();
+            let result = bigfactor::factorize(&n, &options);
+            println!("{} = {}", n, result.render(format));
+            if format != bigfactor::FactorFormat::Plain
+                && let Some(summary) = result.summary()
+            {
+                println!("{}", summary.render(format));
+            }
+            for (composite, squares) in &result.squares {
+                let (low, high) = squares.factors();
+                println!(
+                    "{} = {}² − {}² = {} * {}",
+                    composite, squares.a, squares.b, low, high
+                );
+            }
+            info!("Factored in {:.3}s", start.elapsed().as_secs_f64());
+            if !result.is_complete() {
+                warn!("Some cofactors are composite; try more --curves or a larger --b1");
+                std::process::exit(1);
+            }
+        }
+        Commands::Primorial(PrimorialArgs {
+            n,
+            up_to,
+            prime,
+            output,
+            workers,
+        }) => {
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let (value, name) = if up_to {
+                (primorial::primorial(n, num_workers), format!("{}#", n))
+            } else {
+                let (value, last) = primorial::nth_primorial(n, num_workers);
+                let name = match last {
+                    Some(p) => format!("p_{}# = {}#", n, p),
+                    None => format!("p_{}#", n),
+                };
+                (value, name)
+            };
+            info!("Multiplied in {:.2}s", start.elapsed().as_secs_f64());
+            let digits = value.to_string();
+            if let Some(path) = &output {
+                if let Err(e) = std::fs::write(path, format!("{}\n", digits)) {
+                    error!("Error writing {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+                info!("Wrote {} digits to {}", digits.len(), path.display());
+            }
+            if digits.len() <= 60 {
+                println!("{} = {} ({} digits)", name, digits, digits.len());
+            } else {
+                println!(
+                    "{} = {}...{} ({} digits)",
+                    name,
+                    &digits[..20],
+                    &digits[digits.len() - 20..],
+                    digits.len()
+                );
+            }
+
+            if prime {
+                let factors: Vec<(rug::Integer, u32)> = primorial::primorial_primes(n, up_to)
+                    .into_iter()
+                    .map(|p| (rug::Integer::from(p), 1))
+                    .collect();
+                let short = if up_to {
+                    format!("{}#", n)
+                } else {
+                    format!("p_{}#", n)
+                };
+                // p# − 1 has n + 1 = p#, p# + 1 has n − 1 = p#
+                for (sign, candidate, method) in [
+                    (
+                        "−",
+                        rug::Integer::from(&value - 1u32),
+                        pocklington::Method::NPlus1,
+                    ),
+                    (
+                        "+",
+                        rug::Integer::from(&value + 1u32),
+                        pocklington::Method::NMinus1,
+                    ),
+                ] {
+                    let start = Instant::now();
+                    let verdict = match pocklington::prove(&candidate, method, &factors) {
+                        pocklington::Outcome::Prime(proof) => format!("is prime: {}", proof),
+                        pocklington::Outcome::Composite => "is composite".to_string(),
+                        pocklington::Outcome::Unproven(reason) => {
+                            format!("is a probable prime, unproven: {}", reason)
+                        }
+                    };
+                    println!("{} {} 1 {}", short, sign, verdict);
+                    info!("Tested in {:.2}s", start.elapsed().as_secs_f64());
+                }
+            }
+        }
+        Commands::Fortunate(FortunateArgs { terms, workers }) => {
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let found = fortunate::terms(terms, num_workers);
+            let composite: Vec<&fortunate::Term> =
+                found.iter().filter(|term| !term.is_prime()).collect();
+            for term in &found {
+                println!(
+                    "p_{}# = {}#  m = {}{}",
+                    term.k,
+                    term.p,
+                    term.fortunate,
+                    if term.is_prime() { "" } else { "  (composite)" }
+                );
+            }
+            info!("Searched in {:.2}s", start.elapsed().as_secs_f64());
+            if composite.is_empty() {
+                println!("All {} Fortunate numbers are prime", found.len());
+            } else {
+                for term in &composite {
+                    println!(
+                        "p_{}# + {} is prime with {} composite, against Fortune's conjecture",
+                        term.k, term.fortunate, term.fortunate
+                    );
+                }
+                std::process::exit(1);
+            }
+        }
+        Commands::Wilson(WilsonArgs { n, workers }) => {
+            if n < 2 {
+                error!("Error: n must be at least 2");
+                std::process::exit(2);
+            }
+            let num_workers = workers.unwrap_or_else(parallel::default_workers);
+            let start = Instant::now();
+            let result = wilson::wilson(n as u64, num_workers);
+            info!("Multiplied in {:.2}s", start.elapsed().as_secs_f64());
+
+            let verdict = if result.is_prime() {
+                format!("≡ −1, so {} is prime", n)
+            } else {
+                format!("≢ −1, so {} is composite", n)
+            };
+            println!(
+                "({} − 1)! ≡ {} (mod {}) {}",
+                n, result.factorial, n, verdict
+            );
+            if let Some(h) = result.half_factorial {
+                let note = if !result.is_prime() {
+                    ""
+                } else if n % 4 == 1 {
+                    ", a square root of −1"
+                } else {
+                    ", which is ±1"
+                };
+                println!("(({} − 1) / 2)! ≡ {} (mod {}){}", n, h, n, note);
+            }
+            if let Some(q) = result.wilson_quotient {
+                let note = if q == 0 { ", a Wilson prime" } else { "" };
+                println!(
+                    "Wilson quotient ((n − 1)! + 1) / n ≡ {} (mod {}){}",
+                    q, n, note
+                );
+            }
+        }
+        Commands::Certify(CertifyArgs { n, verify, output }) => {
+            if let Some(path) = verify {
+                let certificate = match std::fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|text| certify::Certificate::from_json(&text))
+                {
+                    Ok(certificate) => certificate,
+                    Err(e) => {
+                        error!("Error reading {}: {}", path.display(), e);
+                        std::process::exit(2);
+                    }
+                };
+                match certify::verify(&certificate) {
+                    Ok(()) => println!(
+                        "Valid certificate: {} is prime ({} entries checked)",
+                        certificate.n,
+                        certificate.steps.len()
+                    ),
+                    Err(e) => {
+                        println!("Invalid certificate: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+                return;
+            }
+
+            let n = n.unwrap_or_default();
+            let n = match n.trim().parse::<rug::Integer>() {
+                Ok(n) if n >= 0 => n,
+                _ => {
+                    error!("Error: '{}' is not a non-negative integer", n);
+                    std::process::exit(2);
+                }
+            };
+            let start = Instant::now();
+            let certificate = match certify::certify(&n, &bigfactor::FactorOptions::default()) {
+                Ok(certificate) => certificate,
+                Err(e) => {
+                    error!("Cannot certify {}: {}", n, e);
+                    std::process::exit(1);
+                }
+            };
+            info!(
+                "Certified {} with {} entries in {:.3}s",
+                n,
+                certificate.steps.len(),
+                start.elapsed().as_secs_f64()
+            );
+            match output {
+                Some(path) => match std::fs::write(&path, certificate.to_json()) {
+                    Ok(()) => info!("Saved certificate to {}", path.display()),
+                    Err(e) => {
+                        error!("Error writing {}: {}", path.display(), e);
+                        std::process::exit(1);
+                    }
+                },
+                None => print!("{}", certificate.to_json()),
+            }
+        }
+        Commands::IsPrime(IsPrimeArgs { numbers, bpsw }) => {
+            let mut all_prime = true;
+            for text in numbers {
+                let n = match text.trim().parse::<rug::Integer>() {
+                    Ok(n) => n,
+                    Err(_) => {
+                        error!("Error: '{}' is not an integer", text);
+                        std::process::exit(2);
+                    }
+                };
+                let (prime, verdict) = if bpsw {
+                    let verdict = bpsw::bpsw(&n);
+                    let prime = !matches!(verdict, bpsw::Verdict::Composite(_));
+                    let label = match verdict {
+                        bpsw::Verdict::Prime => "prime (trial division)".to_string(),
+                        bpsw::Verdict::ProbablePrime => "probable prime (Baillie–PSW)".to_string(),
+                        bpsw::Verdict::Composite(stage) => format!("composite ({:?})", stage),
+                    };
+                    (prime, label)
+                } else {
+                    let prime = bpsw::is_probable_prime(&n);
+                    let label = match (prime, n.to_u64().is_some()) {
+                        (false, _) => "composite",
+                        (true, true) => "prime",
+                        (true, false) => "probable prime (Baillie–PSW)",
+                    };
+                    (prime, label.to_string())
+                };
+                all_prime &= prime;
+                println!("{}: {}", n, verdict);
+            }
+            if !all_prime {
+                std::process::exit(1);
+            }
+        }
+        Commands::Near(NearArgs { numbers, set }) => {
+            let default_set = storage::get_nt_data_dir().join("primeset.bin");
+            let path = set.or_else(|| default_set.exists().then_some(default_set));
+            let set = path.map(|path| match nt_core::PrimeSet::open(&path) {
+                Ok(set) => {
+                    info!("Using {} (primes up to {})", path.display(), set.limit());
+                    set
+                }
+                Err(e) => {
+                    error!("Error opening {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            });
+            let finder = near::NearestPrimes::new(set.as_ref());
+            for n in numbers {
+                let result = finder.near(n);
+                let kind = if result.is_prime {
+                    "prime"
+                } else {
+                    "not prime"
+                };
+                let previous = result
+                    .previous
+                    .map_or("none".to_string(), |p| p.to_string());
+                let next = result.next.map_or("none".to_string(), |p| p.to_string());
+                let gap = match (result.previous, result.next) {
+                    (Some(p), Some(q)) if !result.is_prime => format!(" (gap {})", q - p),
+                    _ => String::new(),
+                };
+                println!(
+                    "{} is {}; previous prime {}, next prime {}{}",
+                    n, kind, previous, next, gap
+                );
+            }
+        }
+        _ => unreachable!("not a primality command"),
+    }
+}
@@ -0,0 +1,1015 @@
+// Sieving primes up to a limit (`nt primes`, `nt primes-all-mem`) and checking the sieves
+// themselves (`nt microbench`, `nt selftest`)
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::cli::{Commands, MicrobenchArgs, PrimesAllMemArgs, PrimesArgs, SelftestArgs};
+use nt_core::{
+    affinity, audit, backpressure, buffer_pool, data, distributed, gaps, gpu, huge_pages, logging,
+    microbench, parallel, primes, progress, segment_format, selftest, storage, storage_async,
+    storage_direct, storage_writer, stream_output, throughput, tui, unbounded, watchdog,
+};
+
+/// Run `nt primes`, `nt primes-all-mem`, `nt microbench` or `nt selftest`
+pub fn run(command: Commands) {
+    match command {
+        Commands::PrimesAllMem(PrimesAllMemArgs {
+            limit,
+            variation,
+            save_as_property,
+            huge_pages,
+            no_atomic,
+            align_segments,
+        }) => {
+            let start = Instant::now();
+
+            if no_atomic {
+                storage::disable_atomic_writes();
+            }
+            if huge_pages {
+                huge_pages::enable();
+                if let Some(mode) = huge_pages::thp_mode() {
+                    info!("Transparent huge pages: {}", mode);
+                }
+            }
+
+            // For variation 5 (segmented sieve), check the limit covers at least one segment
+            let (effective_limit, original_limit) = if variation == 5 {
+                if limit < primes::SEGMENT_SIZE_NUMBERS {
+                    error!(
+                        "Variation 5 (segmented sieve) requires limit >= {}",
+                        primes::SEGMENT_SIZE_NUMBERS
+                    );
+                    error!("For smaller limits, use variation 2 or 4 instead.");
+                    return;
+                }
+
+                // Calculate sqrt_limit once and use it consistently
+                let sqrt_limit = (limit as f64).sqrt() as usize;
+                let low = (sqrt_limit + 1) | 1; // First odd after sqrt (where segments start)
+                let range_to_cover = if limit >= low { limit - low + 1 } else { 0 };
+                let num_segments = range_to_cover.div_ceil(primes::SEGMENT_SIZE_NUMBERS);
+                // The last segment is clamped to the limit unless --align-segments asks for
+                // the old behavior of sieving it whole
+                let effective_limit = if align_segments {
+                    low + (num_segments * primes::SEGMENT_SIZE_NUMBERS) - 1
+                } else {
+                    limit
+                };
+
+                if effective_limit != limit {
+                    info!(
+                        "Adjusting limit from {} to {} (sqrt={}, low={}, segments={})",
+                        limit, effective_limit, sqrt_limit, low, num_segments
+                    );
+                }
+
+                (effective_limit, limit)
+            } else {
+                (limit, limit)
+            };
+
+            info!(
+                "Finding primes up to {} (variation {})...",
+                effective_limit, variation
+            );
+
+            let primes = primes::find_primes(effective_limit, variation);
+
+            if save_as_property {
+                match storage::tag_all(primes.iter().copied(), "prime") {
+                    Ok(report) => info!(
+                        "Tagged {} primes as prime ({} already were)",
+                        report.tagged, report.already
+                    ),
+                    Err(e) => error!("Error saving prime properties: {}", e),
+                }
+            }
+
+            // Save all primes to primes.txt in XDG_DATA_HOME
+            match storage::save_all_primes(&primes) {
+                Ok(_) => info!("\nSaved all primes to primes.txt"),
+                Err(e) => error!("Error saving primes.txt: {}", e),
+            }
+
+            info!("\nTotal: {} primes found", primes.len());
+
+            let duration = start.elapsed();
+            let duration_us = duration.as_micros();
+
+            info!(
+                "Execution time: {}us ({:.2}ms)",
+                duration_us,
+                duration_us as f64 / 1000.0
+            );
+
+            if let Err(e) = storage::log_execution(
+                "primes-all-mem",
+                &original_limit.to_string(),
+                variation,
+                duration_us,
+                None,
+            ) {
+                warn!("Warning: Failed to log execution: {}", e);
+            }
+        }
+        Commands::Primes(PrimesArgs {
+            limit,
+            from,
+            to,
+            variation,
+            save_as_property,
+            workers,
+            binary,
+            consumers,
+            async_io,
+            io_buffers,
+            direct_io,
+            preallocate,
+            channel_capacity,
+            max_memory,
+            on_memory_limit,
+            pin_workers,
+            huge_pages,
+            align_segments,
+            no_atomic,
+            force,
+            backend,
+            preformat,
+            write_behind,
+            shards,
+            tee,
+            output,
+            audit: audit_segments,
+            deterministic,
+            track_gaps,
+            progress,
+            tui,
+            unbounded: unbounded_stream,
+            max_bytes,
+            max_seconds,
+            roll_bytes,
+            resume,
+            distributed: role,
+            listen,
+            connect,
+        }) => {
+            let start = Instant::now();
+            throughput::start();
+
+            if no_atomic {
+                storage::disable_atomic_writes();
+            }
+            if let Some(max_memory_mb) = max_memory {
+                watchdog::start(max_memory_mb, on_memory_limit);
+            }
+            if tee.contains(&storage_writer::TeeTarget::Stdout) {
+                if variation == 9 || shards.is_some() {
+                    error!("--tee stdout needs a single output file, not shards");
+                    return;
+                }
+                logging::reserve_stdout();
+            }
+            storage_writer::tee_outputs(&tee);
+            // Open the stream before sieving: a pipe blocks here until its reader is there
+            let streaming = output.is_some();
+            if let Some(target) = output {
+                if variation == 9 || shards.is_some() {
+                    error!("--output needs a single output file, not shards");
+                    return;
+                }
+                match target {
+                    stream_output::OutputTarget::Fifo(_) => {
+                        info!("Waiting for a reader on {}", target)
+                    }
+                    stream_output::OutputTarget::Stdout => logging::reserve_stdout(),
+                    stream_output::OutputTarget::Tcp(_) => {}
+                }
+                match target.open() {
+                    Ok(stream) => {
+                        info!("Streaming output to {}", target);
+                        storage_writer::redirect_output(&target.to_string(), stream);
+                    }
+                    Err(e) => {
+                        error!("Error opening {}: {}", target, e);
+                        return;
+                    }
+                }
+            }
+
+            // A distributed worker only sieves what its coordinator assigns
+            if role == Some(distributed::DistributedRole::Worker) {
+                let coordinator = connect.unwrap_or_default();
+                let num_workers = workers.unwrap_or_else(parallel::default_workers);
+                info!(
+                    "Sieving for coordinator {} with {} worker threads",
+                    coordinator, num_workers
+                );
+                match distributed::run_worker(&coordinator, num_workers) {
+                    Ok(segments) => info!(
+                        "Worker finished: {} segments in {:.2}s",
+                        segments,
+                        start.elapsed().as_secs_f64()
+                    ),
+                    Err(e) => error!("Error: lost coordinator {}: {}", coordinator, e),
+                }
+                return;
+            }
+
+            // No limit: one incremental sieve into rolling files, none of the variations apply
+            if unbounded_stream {
+                let encoding = if binary {
+                    segment_format::SegmentEncoding::Binary
+                } else {
+                    segment_format::SegmentEncoding::Text
+                };
+                let options = storage_direct::BinaryOutputOptions {
+                    direct_io,
+                    preallocate_bytes: 0,
+                };
+                let budget = unbounded::Budget {
+                    max_bytes: max_bytes.map(|bytes| bytes as u64),
+                    max_duration: max_seconds.map(|secs| Duration::from_secs(secs as u64)),
+                };
+                unbounded::run(encoding, options, roll_bytes as u64, budget, resume);
+                return;
+            }
+
+            // A window far from zero: sieve it alone from the base primes up to sqrt(to)
+            if let (Some(from), Some(to)) = (from, to) {
+                let encoding = if binary {
+                    segment_format::SegmentEncoding::Binary
+                } else {
+                    segment_format::SegmentEncoding::Text
+                };
+                let stem = format!("range_{}_{}", from, to);
+                let options = storage_direct::BinaryOutputOptions {
+                    direct_io,
+                    preallocate_bytes: 0,
+                };
+                let mut writer =
+                    match storage_writer::PrimeWriter::create(&stem, encoding, &options, 1 << 20) {
+                        Ok(writer) => writer,
+                        Err(e) => {
+                            error!("Error creating {}: {}", stem, e);
+                            return;
+                        }
+                    };
+                let mut write_error = None;
+                primes::for_each_prime_in_range(from, to, |segment| {
+                    if write_error.is_none()
+                        && let Err(e) = writer.write_primes(segment)
+                    {
+                        write_error = Some(e);
+                    }
+                });
+                let name = writer.name().to_string();
+                match write_error.map_or_else(|| writer.finish(), Err) {
+                    Ok(count) => info!(
+                        "Saved {} primes in [{}, {}] to {} in {:.2}s",
+                        count,
+                        from,
+                        to,
+                        name,
+                        start.elapsed().as_secs_f64()
+                    ),
+                    Err(e) => error!("Error writing {}: {}", name, e),
+                }
+                return;
+            }
+
+            let Some(limit) = limit else {
+                error!("--connect requires --distributed worker");
+                return;
+            };
+            let coordinator = role == Some(distributed::DistributedRole::Coordinator);
+            if coordinator && variation != 9 {
+                error!("--distributed requires variation 9");
+                return;
+            }
+            if coordinator && preformat {
+                error!("--preformat is not supported with --distributed");
+                return;
+            }
+
+            // Open the GPU before any output is touched so a missing device fails fast
+            let gpu_sieve = if backend == gpu::SieveBackend::Gpu {
+                if variation != 9 {
+                    error!("--backend gpu requires variation 9");
+                    return;
+                }
+                if coordinator {
+                    error!("--backend gpu is not supported with --distributed");
+                    return;
+                }
+                if deterministic {
+                    error!("--backend gpu is not supported with --deterministic");
+                    return;
+                }
+                match gpu::GpuSieve::new() {
+                    Ok(gpu_sieve) => {
+                        info!("Sieving on GPU: {}", gpu_sieve.adapter_name());
+                        Some(gpu_sieve)
+                    }
+                    Err(e) => {
+                        error!("Error: --backend gpu: {}", e);
+                        return;
+                    }
+                }
+            } else {
+                None
+            };
+
+            if huge_pages {
+                huge_pages::enable();
+                if let Some(mode) = huge_pages::thp_mode() {
+                    info!("Transparent huge pages: {}", mode);
+                }
+            }
+
+            // For variation 5, 6, 7, 8, or 9, check the limit covers at least one segment
+            let (effective_limit, original_limit, sqrt_limit) = if variation == 5
+                || variation == 6
+                || variation == 7
+                || variation == 8
+                || variation == 9
+            {
+                if limit < primes::SEGMENT_SIZE_NUMBERS {
+                    error!(
+                        "Variation {} (segmented sieve) requires limit >= {}",
+                        variation,
+                        primes::SEGMENT_SIZE_NUMBERS
+                    );
+                    error!("For smaller limits, use variation 2 or 4 instead.");
+                    return;
+                }
+
+                // Calculate sqrt_limit once and use it consistently
+                let sqrt_limit = (limit as f64).sqrt() as usize;
+                let low = (sqrt_limit + 1) | 1; // First odd after sqrt (where segments start)
+                let range_to_cover = if limit >= low { limit - low + 1 } else { 0 };
+                let num_segments = range_to_cover.div_ceil(primes::SEGMENT_SIZE_NUMBERS);
+                // The last segment is clamped to the limit unless --align-segments asks for
+                // the old behavior of sieving it whole
+                let effective_limit = if align_segments {
+                    low + (num_segments * primes::SEGMENT_SIZE_NUMBERS) - 1
+                } else {
+                    limit
+                };
+
+                if effective_limit != limit {
+                    info!(
+                        "Adjusting limit from {} to {} (sqrt={}, low={}, segments={})",
+                        limit, effective_limit, sqrt_limit, low, num_segments
+                    );
+                }
+
+                (effective_limit, limit, sqrt_limit)
+            } else {
+                (limit, limit, 0) // sqrt_limit not needed for other variations
+            };
+
+            info!(
+                "Finding primes up to {} (variation {})...",
+                effective_limit, variation
+            );
+
+            // Every variation writes text unless --binary is given
+            let encoding = if binary {
+                segment_format::SegmentEncoding::Binary
+            } else {
+                segment_format::SegmentEncoding::Text
+            };
+            let expected_bytes = storage::estimate_output_bytes(effective_limit, binary);
+
+            // Fail now rather than hours in when the disk fills up
+            let writes_files = !streaming
+                || tee
+                    .iter()
+                    .any(|target| target != &storage_writer::TeeTarget::Stdout);
+            if writes_files && !storage::check_disk_space(expected_bytes, force) {
+                return;
+            }
+
+            // Progress is counted in segments, so only segmented variations can show it
+            if progress {
+                if sqrt_limit > 0 {
+                    let low = (sqrt_limit + 1) | 1;
+                    let total_segments =
+                        (effective_limit - low + 1).div_ceil(primes::SEGMENT_SIZE_NUMBERS);
+                    progress::start(total_segments as u64, "segments");
+                } else {
+                    warn!("--progress requires a segmented variation (5-9), ignoring");
+                }
+            }
+            if tui && variation != 9 {
+                warn!("--tui requires variation 9, ignoring");
+            }
+            if audit_segments && variation != 9 {
+                warn!("--audit requires variation 9, ignoring");
+            }
+            if deterministic && variation != 8 && variation != 9 {
+                warn!("--deterministic requires variation 8 or 9, ignoring");
+            }
+            if write_behind && variation != 6 && variation != 8 {
+                warn!("--write-behind requires variation 6 or 8, ignoring");
+            }
+            if shards == Some(0) {
+                error!("Number of shards must be at least 1");
+                return;
+            }
+            if shards.is_some() && variation != 6 && variation != 8 {
+                warn!(
+                    "--shards requires variation 6 or 8 (variation 9 shards with --consumers), ignoring"
+                );
+            }
+            let shards = shards.filter(|_| variation == 6 || variation == 8);
+            if shards.is_some() {
+                data::remove_old_shards();
+            }
+            if track_gaps {
+                gaps::start();
+            }
+
+            // Preallocation is sized from the PNT upper bound on the output size
+            let output_options = storage_direct::BinaryOutputOptions {
+                direct_io,
+                preallocate_bytes: if preallocate { expected_bytes } else { 0 },
+            };
+
+            // For variation 6, use batched channel; for variation 7, use segment channel;
+            // for variation 8, use parallel segment channel; otherwise use single-prime channel
+            let consumer_handle = if variation == 6 {
+                let (tx, rx) = mpsc::channel::<Vec<usize>>();
+                let pool = buffer_pool::BufferPool::new();
+                let consumer_pool = pool.clone();
+
+                // Spawn consumer thread for batched segments
+                let handle = thread::spawn(move || {
+                    if let Some(num_shards) = shards {
+                        // Segments arrive in order, the small primes first
+                        let segments = rx.into_iter().enumerate().map(|(segment_id, primes)| {
+                            primes::SegmentPrimes { primes, segment_id }
+                        });
+                        return storage::save_primes_sharded(
+                            segments,
+                            num_shards,
+                            effective_limit,
+                            encoding,
+                            output_options,
+                            consumer_pool,
+                        );
+                    }
+                    storage::save_primes_streaming_batched(
+                        rx,
+                        encoding,
+                        output_options,
+                        consumer_pool,
+                        write_behind,
+                    )
+                });
+
+                // Generate primes and send batched to consumer thread
+                primes::find_primes_v6_streaming(effective_limit, sqrt_limit, tx, pool.clone());
+                info!("Segment buffers allocated: {}", pool.allocations());
+
+                handle
+            } else if variation == 7 {
+                let (tx, rx) = mpsc::channel::<primes::SegmentData>();
+                let pool = buffer_pool::BufferPool::new();
+                let consumer_pool = pool.clone();
+
+                // Spawn consumer thread for raw segments (unpacking on consumer side)
+                let handle = thread::spawn(move || {
+                    storage::save_primes_streaming_segments(
+                        rx,
+                        effective_limit,
+                        encoding,
+                        output_options,
+                        consumer_pool,
+                    )
+                });
+
+                // Generate primes and send raw segments to consumer thread
+                primes::find_primes_v7_streaming(effective_limit, sqrt_limit, tx, pool.clone());
+                info!("Segment buffers allocated: {}", pool.allocations());
+
+                handle
+            } else if variation == 8 {
+                // Determine number of workers (default to CPU count)
+                let num_workers = workers.unwrap_or_else(parallel::default_workers);
+
+                info!(
+                    "Using {} worker threads for parallel processing",
+                    num_workers
+                );
+
+                let pinning = pin_workers.then(|| Arc::new(affinity::CorePlan::new(num_workers)));
+                if let Some(plan) = &pinning {
+                    info!(
+                        "Pinning workers and consumer across {} cores",
+                        plan.num_cores()
+                    );
+                }
+
+                let consumer_pinning = pinning.clone();
+
+                if preformat {
+                    let (tx, rx) = mpsc::channel::<segment_format::EncodedSegment>();
+                    let builder = segment_format::EncodedSegments {
+                        pool: buffer_pool::BufferPool::new(),
+                        encoding,
+                    };
+                    let consumer_pool = builder.pool.clone();
+
+                    // Consumer only reorders and writes the bytes the workers formatted
+                    let handle = thread::spawn(move || {
+                        if let Some(plan) = consumer_pinning {
+                            plan.pin_consumer(1);
+                        }
+                        storage::save_encoded_segments_parallel(
+                            rx,
+                            encoding,
+                            output_options,
+                            consumer_pool,
+                        )
+                    });
+
+                    primes::find_primes_v8_parallel(
+                        effective_limit,
+                        sqrt_limit,
+                        tx,
+                        num_workers,
+                        &builder,
+                        pinning.as_deref(),
+                    );
+                    info!("Segment buffers allocated: {}", builder.pool.allocations());
+
+                    handle
+                } else {
+                    let (tx, rx) = mpsc::channel::<primes::SegmentPrimes>();
+                    let builder = segment_format::UnpackedSegments {
+                        pool: buffer_pool::BufferPool::new(),
+                    };
+                    let consumer_pool = builder.pool.clone();
+
+                    // Spawn consumer thread for parallel segments (with reordering)
+                    let handle = thread::spawn(move || {
+                        if let Some(plan) = consumer_pinning {
+                            plan.pin_consumer(1);
+                        }
+                        if let Some(num_shards) = shards {
+                            return storage::save_primes_sharded(
+                                rx.into_iter(),
+                                num_shards,
+                                effective_limit,
+                                encoding,
+                                output_options,
+                                consumer_pool,
+                            );
+                        }
+                        storage::save_primes_streaming_segments_parallel(
+                            rx,
+                            encoding,
+                            output_options,
+                            consumer_pool,
+                            write_behind,
+                        )
+                    });
+
+                    // Generate primes in parallel and send unpacked segments to consumer thread
+                    primes::find_primes_v8_parallel(
+                        effective_limit,
+                        sqrt_limit,
+                        tx,
+                        num_workers,
+                        &builder,
+                        pinning.as_deref(),
+                    );
+                    info!("Segment buffers allocated: {}", builder.pool.allocations());
+
+                    handle
+                }
+            } else if variation == 9 {
+                // Variation 9: Multiple consumers for parallel I/O
+                if consumers < 1 {
+                    error!("Number of consumers must be at least 1");
+                    return;
+                }
+
+                if preformat && async_io {
+                    error!("--preformat is not supported with --async-io");
+                    return;
+                }
+
+                // The async backends encode straight into binary write buffers
+                if async_io && !binary {
+                    error!("--async-io writes binary shards only, add --binary");
+                    return;
+                }
+
+                // Determine number of workers (default to CPU count)
+                let num_workers = workers.unwrap_or_else(parallel::default_workers);
+
+                info!(
+                    "Using {} worker threads with {} consumers for parallel I/O",
+                    num_workers, consumers
+                );
+
+                data::remove_old_shards();
+
+                let mut consumer_handles = Vec::new();
+
+                // Create atomic counters to track channel depth
+                let total_sent = Arc::new(AtomicUsize::new(0));
+                let total_received = Arc::new(AtomicUsize::new(0));
+
+                // Each consumer writes roughly 1/N of the primes; small primes go elsewhere
+                let consumer_options = storage_direct::BinaryOutputOptions {
+                    preallocate_bytes: expected_bytes / consumers as u64,
+                    ..output_options
+                };
+
+                // Channel capacity: limits buffering to prevent OOM
+                // With 15 consumers × 100 capacity = 1,500 segments max = ~240 MB
+                if channel_capacity < 1 {
+                    error!("Channel capacity must be at least 1");
+                    return;
+                }
+
+                // Adaptive mode throttles producers on the sent/received gap and RSS
+                let backpressure = max_memory.map(|max_memory_mb| {
+                    info!("Adaptive backpressure: {} MB memory budget", max_memory_mb);
+                    backpressure::Backpressure::new(
+                        Arc::clone(&total_sent),
+                        Arc::clone(&total_received),
+                        max_memory_mb,
+                        consumers,
+                    )
+                });
+
+                let low = (sqrt_limit + 1) | 1;
+                let total_segments =
+                    (effective_limit - low + 1).div_ceil(primes::SEGMENT_SIZE_NUMBERS);
+                if audit_segments {
+                    audit::start(total_segments, consumers);
+                }
+                if tui {
+                    tui::start(
+                        total_segments,
+                        num_workers,
+                        consumers,
+                        Arc::clone(&total_sent),
+                        Arc::clone(&total_received),
+                    );
+                }
+
+                let pinning = pin_workers.then(|| Arc::new(affinity::CorePlan::new(num_workers)));
+                if let Some(plan) = &pinning {
+                    info!(
+                        "Pinning workers and consumers across {} cores",
+                        plan.num_cores()
+                    );
+                }
+
+                // Buffer pools are shared by all workers and consumers so buffers flow back to
+                // whichever worker is free
+                let (small_primes, buffers_allocated) = if preformat {
+                    let builder = segment_format::EncodedSegments {
+                        pool: buffer_pool::BufferPool::new(),
+                        encoding,
+                    };
+                    let mut encoded_senders = Vec::new();
+
+                    for consumer_id in 1..=consumers {
+                        let (tx, rx) =
+                            mpsc::sync_channel::<segment_format::EncodedSegment>(channel_capacity);
+                        encoded_senders.push(tx);
+
+                        let total_received_clone = Arc::clone(&total_received);
+                        let consumer_pool = builder.pool.clone();
+                        let consumer_pinning = pinning.clone();
+                        consumer_handles.push(thread::spawn(move || {
+                            if let Some(plan) = consumer_pinning {
+                                plan.pin_consumer(consumer_id);
+                            }
+                            storage::save_encoded_multi_consumer(
+                                rx,
+                                consumer_id,
+                                consumers,
+                                total_received_clone,
+                                encoding,
+                                consumer_options,
+                                consumer_pool,
+                            )
+                        }));
+                    }
+
+                    // Generate primes and get small_primes back (blocks until producer done)
+                    let small_primes = if let Some(gpu_sieve) = &gpu_sieve {
+                        match gpu_sieve.find_primes_v9(
+                            effective_limit,
+                            sqrt_limit,
+                            encoded_senders,
+                            num_workers,
+                            total_sent,
+                            &builder,
+                            backpressure.as_ref(),
+                        ) {
+                            Ok(small_primes) => small_primes,
+                            Err(e) => {
+                                error!("Error: GPU sieve failed: {}", e);
+                                tui::finish();
+                                return;
+                            }
+                        }
+                    } else {
+                        primes::find_primes_v9_multi_consumers(
+                            effective_limit,
+                            sqrt_limit,
+                            encoded_senders,
+                            num_workers,
+                            total_sent,
+                            &builder,
+                            backpressure.as_ref(),
+                            pinning.as_deref(),
+                            deterministic,
+                        )
+                    };
+                    (small_primes, builder.pool.allocations())
+                } else {
+                    let builder = segment_format::UnpackedSegments {
+                        pool: buffer_pool::BufferPool::new(),
+                    };
+
+                    // Create channels for each consumer
+                    let mut senders = Vec::new();
+
+                    for consumer_id in 1..=consumers {
+                        let (tx, rx) =
+                            mpsc::sync_channel::<primes::SegmentPrimes>(channel_capacity);
+                        senders.push(tx);
+
+                        // Spawn consumer thread with appropriate I/O strategy
+                        let total_received_clone = Arc::clone(&total_received);
+                        let total_sent_clone = Arc::clone(&total_sent);
+                        let consumer_pool = builder.pool.clone();
+                        let consumer_pinning = pinning.clone();
+                        let handle = if async_io {
+                            // Use async I/O (io_uring where available)
+                            thread::spawn(move || {
+                                if let Some(plan) = consumer_pinning {
+                                    plan.pin_consumer(consumer_id);
+                                }
+                                storage_async::save_primes_multi_consumer_async(
+                                    rx,
+                                    consumer_id,
+                                    consumers,
+                                    total_received_clone,
+                                    total_sent_clone,
+                                    consumer_options,
+                                    io_buffers,
+                                    consumer_pool,
+                                )
+                            })
+                        } else {
+                            // Use standard sync I/O
+                            thread::spawn(move || {
+                                if let Some(plan) = consumer_pinning {
+                                    plan.pin_consumer(consumer_id);
+                                }
+                                storage::save_primes_multi_consumer(
+                                    rx,
+                                    consumer_id,
+                                    consumers,
+                                    total_received_clone,
+                                    total_sent_clone,
+                                    encoding,
+                                    consumer_options,
+                                    consumer_pool,
+                                )
+                            })
+                        };
+                        consumer_handles.push(handle);
+                    }
+
+                    // Generate primes and get small_primes back (blocks until producer done)
+                    let small_primes = if coordinator {
+                        // Remote workers replace the local ones; consumers are unchanged
+                        match distributed::run_coordinator(
+                            &listen,
+                            effective_limit,
+                            sqrt_limit,
+                            senders,
+                            total_sent,
+                            builder.pool.clone(),
+                        ) {
+                            Ok(small_primes) => small_primes,
+                            Err(e) => {
+                                error!("Error: coordinator on {} failed: {}", listen, e);
+                                tui::finish();
+                                return;
+                            }
+                        }
+                    } else if let Some(gpu_sieve) = &gpu_sieve {
+                        match gpu_sieve.find_primes_v9(
+                            effective_limit,
+                            sqrt_limit,
+                            senders,
+                            num_workers,
+                            total_sent,
+                            &builder,
+                            backpressure.as_ref(),
+                        ) {
+                            Ok(small_primes) => small_primes,
+                            Err(e) => {
+                                error!("Error: GPU sieve failed: {}", e);
+                                tui::finish();
+                                return;
+                            }
+                        }
+                    } else {
+                        primes::find_primes_v9_multi_consumers(
+                            effective_limit,
+                            sqrt_limit,
+                            senders,
+                            num_workers,
+                            total_sent,
+                            &builder,
+                            backpressure.as_ref(),
+                            pinning.as_deref(),
+                            deterministic,
+                        )
+                    };
+                    (small_primes, builder.pool.allocations())
+                };
+                info!("Segment buffers allocated: {}", buffers_allocated);
+                if let Some(backpressure) = &backpressure {
+                    info!("Backpressure: {} sends throttled", backpressure.throttled());
+                }
+
+                // Return handle that waits for all consumers and computes total
+                // Save small primes in this thread to avoid affecting producer timing
+                thread::spawn(move || {
+                    // Save small primes while consumers are working
+                    let small_count = storage::save_small_primes(&small_primes, encoding);
+
+                    // Wait for all consumers to finish
+                    let mut consumer_counts = Vec::new();
+                    for (i, handle) in consumer_handles.into_iter().enumerate() {
+                        // A panicked consumer wrote nothing we can count; --audit names the
+                        // segments it lost
+                        let count = handle.join().unwrap_or_else(|_| {
+                            error!("Error: consumer {} panicked", i + 1);
+                            0
+                        });
+                        consumer_counts.push((i + 1, count));
+                    }
+
+                    let consumers_total: usize = consumer_counts.iter().map(|(_, c)| c).sum();
+                    let total = small_count + consumers_total;
+
+                    let mut summary = format!("Total primes: {} (small: {}", total, small_count);
+                    for (id, count) in &consumer_counts {
+                        summary.push_str(&format!(", consumer{}: {}", id, count));
+                    }
+                    info!("{})", summary);
+
+                    let counts: Vec<usize> = std::iter::once(small_count)
+                        .chain(consumer_counts.iter().map(|&(_, count)| count))
+                        .collect();
+                    storage::save_manifest(encoding, effective_limit, &counts);
+
+                    total
+                })
+            } else {
+                let (tx, rx) = mpsc::channel();
+
+                // Spawn consumer thread for individual primes
+                let handle = thread::spawn(move || {
+                    storage::save_primes_streaming(rx, encoding, output_options, save_as_property)
+                });
+
+                // Generate primes and send to consumer thread
+                primes::find_primes_streaming(effective_limit, variation, tx);
+
+                handle
+            };
+
+            progress::finish();
+
+            let producer_done = start.elapsed();
+            info!(
+                "\nProducer finished: {}us ({:.2}ms)",
+                producer_done.as_micros(),
+                producer_done.as_micros() as f64 / 1000.0
+            );
+
+            // Wait for consumer to finish and get prime count
+            let prime_count = consumer_handle.join().unwrap();
+            tui::finish();
+
+            let consumer_done = start.elapsed();
+            let consumer_lag = consumer_done - producer_done;
+
+            info!(
+                "Consumer finished: {}us ({:.2}ms)",
+                consumer_done.as_micros(),
+                consumer_done.as_micros() as f64 / 1000.0
+            );
+            info!(
+                "Consumer lag: {}us ({:.2}ms)",
+                consumer_lag.as_micros(),
+                consumer_lag.as_micros() as f64 / 1000.0
+            );
+
+            info!("\nTotal: {} primes found", prime_count);
+            let audit_passed = audit::verify();
+
+            // Consumers have all joined, so every segment has been counted
+            if let Some(stats) = gaps::finish() {
+                match gaps::save(&stats) {
+                    Ok(path) => info!(
+                        "Max gap: {} after {} ({} distinct gaps) -> {}",
+                        stats.max_gap,
+                        stats.max_gap_start,
+                        stats.histogram.len(),
+                        path.display()
+                    ),
+                    Err(e) => error!("Error saving gaps.json: {}", e),
+                }
+            }
+
+            let duration = start.elapsed();
+            let duration_us = duration.as_micros();
+
+            info!(
+                "Total execution time: {}us ({:.2}ms)",
+                duration_us,
+                duration_us as f64 / 1000.0
+            );
+
+            if let Some(report) = watchdog::stop()
+                && report.pauses > 0
+            {
+                info!(
+                    "Memory watchdog: producers paused {} times for {:.2}s, peak RSS {:.2} MB",
+                    report.pauses,
+                    report.paused.as_secs_f64(),
+                    report.peak_rss_mb
+                );
+            }
+
+            let throughput = throughput::totals();
+            if let Some(throughput) = &throughput {
+                info!("Throughput: {}", throughput.summary());
+            }
+
+            if let Err(e) = storage::log_execution(
+                "primes",
+                &original_limit.to_string(),
+                variation,
+                duration_us,
+                throughput.as_ref(),
+            ) {
+                warn!("Warning: Failed to log execution: {}", e);
+            }
+
+            if !audit_passed {
+                std::process::exit(1);
+            }
+        }
+        Commands::Microbench(MicrobenchArgs { names, millis }) => {
+            let budget = std::time::Duration::from_millis(millis as u64);
+            match microbench::run(&names, budget) {
+                Ok(timings) => {
+                    for timing in timings {
+                        println!(
+                            "{:<8} {:>10.2} ns/{:<8} {:>14.0} {}s/s",
+                            timing.name,
+                            timing.nanos_per_op,
+                            timing.unit,
+                            timing.ops_per_second(),
+                            timing.unit
+                        );
+                    }
+                }
+                Err(e) => {
+                    error!("Error: {}", e);
+                    std::process::exit(2);
+                }
+            }
+        }
+        Commands::Selftest(SelftestArgs { limit }) => {
+            if !selftest::run(limit) {
+                std::process::exit(1);
+            }
+        }
+        _ => unreachable!("not a sieve command"),
+    }
+}
@@ -0,0 +1,297 @@
+// Commands over the files in the data directory: comparing, indexing, converting,
+// importing and exporting prime files, per-number properties and the SPF table
+
+use std::time::Instant;
+use tracing::{error, info};
+
+use crate::cli::{
+    Commands, ConvertArgs, DataAction, DataArgs, DiffArgs, ExportArgs, ImportArgs, QueryArgs,
+    RankArgs, SelectArgs, SpfAction, SpfArgs, TagArgs,
+};
+use nt_core::{
+    convert, data, export, import, prime_diff, prime_index, segment_format, spf, storage,
+    storage_writer,
+};
+
+/// Run a command over the stored files
+pub fn run(command: Commands) {
+    match command {
+        Commands::Diff(DiffArgs { a, b, max_listed }) => {
+            let open = |path: &std::path::Path| match storage::open_primes(path) {
+                Ok(primes) => primes,
+                Err(e) => {
+                    error!("Error opening {}: {}", path.display(), e);
+                    std::process::exit(2);
+                }
+            };
+            let result = match prime_diff::diff(open(a.as_path()), open(b.as_path()), max_listed) {
+                Ok(result) => result,
+                Err(e) => {
+                    error!("Error: {}", e);
+                    std::process::exit(2);
+                }
+            };
+            for difference in &result.listed {
+                println!("{}", difference);
+            }
+            if result.missing + result.extra > result.listed.len() as u64 {
+                println!("...");
+            }
+            println!(
+                "A: {} primes, B: {} primes; {} missing from B, {} extra in B",
+                result.count_a, result.count_b, result.missing, result.extra
+            );
+            if !result.is_same() {
+                std::process::exit(1);
+            }
+        }
+        Commands::Rank(RankArgs { n, file }) => {
+            let index = match prime_index::open_default(file.as_deref()) {
+                Ok(index) => index,
+                Err(e) => {
+                    error!("Error opening primes: {}", e);
+                    std::process::exit(2);
+                }
+            };
+            match index.rank(n as u64) {
+                Ok(prime_index::Rank::Prime(k)) => println!("{} is prime #{}", n, k),
+                Ok(prime_index::Rank::Between(below)) => {
+                    println!("{} is not prime; {} primes are smaller", n, below);
+                    std::process::exit(1);
+                }
+                Ok(prime_index::Rank::Beyond) => {
+                    error!("{} is past the last stored prime", n);
+                    std::process::exit(2);
+                }
+                Err(e) => {
+                    error!("Error reading primes: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Select(SelectArgs { k, file }) => {
+            let index = match prime_index::open_default(file.as_deref()) {
+                Ok(index) => index,
+                Err(e) => {
+                    error!("Error opening primes: {}", e);
+                    std::process::exit(2);
+                }
+            };
+            match index.select(k as u64) {
+                Ok(Some(p)) => println!("{}", p),
+                Ok(None) => {
+                    error!(
+                        "Error: k must be between 1 and {}, the number of stored primes",
+                        index.count
+                    );
+                    std::process::exit(2);
+                }
+                Err(e) => {
+                    error!("Error reading primes: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Convert(ConvertArgs {
+            input,
+            to,
+            from_format,
+            format,
+        }) => {
+            let Some(format) = format.or_else(|| convert::Format::from_extension(&to)) else {
+                error!(
+                    "Error: can't tell the format of {} from its name, add --format",
+                    to.display()
+                );
+                std::process::exit(2);
+            };
+            if input.canonicalize().ok() == to.canonicalize().ok() && to.exists() {
+                error!("Error: --to would overwrite the input");
+                std::process::exit(2);
+            }
+            let from = match from_format.map_or_else(|| convert::Format::detect(&input), Ok) {
+                Ok(from) => from,
+                Err(e) => {
+                    error!("Error opening {}: {}", input.display(), e);
+                    std::process::exit(2);
+                }
+            };
+            let start = Instant::now();
+            match convert::convert(&input, from, &to, format) {
+                Ok(count) => info!(
+                    "Converted {} primes from {} ({}) to {} ({}) in {:.2}s",
+                    count,
+                    input.display(),
+                    from,
+                    to.display(),
+                    format,
+                    start.elapsed().as_secs_f64()
+                ),
+                Err(e) => {
+                    error!("Error converting {}: {}", input.display(), e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Import(ImportArgs {
+            files,
+            format,
+            binary,
+            force,
+        }) => {
+            let encoding = if binary {
+                segment_format::SegmentEncoding::Binary
+            } else {
+                segment_format::SegmentEncoding::Text
+            };
+            let name = storage_writer::file_name("primes", encoding);
+            if !force && storage::get_nt_data_dir().join(&name).exists() {
+                error!("{} already exists, add --force to replace it", name);
+                std::process::exit(2);
+            }
+            if let Some(bytes) = import::estimate_output_bytes(&files, format, encoding)
+                && !storage::check_disk_space(bytes, force)
+            {
+                std::process::exit(1);
+            }
+            match import::run(&files, format, encoding) {
+                Ok((report, name)) => info!(
+                    "Imported {} primes up to {} into {} ({} read, {} repeats dropped)",
+                    report.written, report.largest, name, report.read, report.duplicates
+                ),
+                Err(e) => {
+                    error!("Error: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Export(ExportArgs {
+            format,
+            output,
+            binary,
+            range_size,
+        }) => {
+            export::run(format, &output, binary, range_size);
+        }
+        Commands::Data(DataArgs { action }) => match action {
+            DataAction::List => data::list(),
+            DataAction::Size => data::size(),
+            DataAction::Clean {
+                older_than,
+                pattern,
+                dry_run,
+            } => data::clean(older_than, pattern.as_deref(), dry_run),
+        },
+        Commands::Tag(TagArgs {
+            property,
+            mut numbers,
+            from_file,
+        }) => {
+            if let Some(path) = from_file {
+                match storage::read_number_list(&path) {
+                    Ok(listed) => numbers.extend(listed),
+                    Err(e) => {
+                        error!("Error reading {}: {}", path.display(), e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            match storage::tag_all(numbers, &property) {
+                Ok(report) => info!(
+                    "Tagged {} numbers as {} ({} already were)",
+                    report.tagged, property, report.already
+                ),
+                Err(e) => {
+                    error!("Error tagging as {}: {}", property, e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Query(QueryArgs { property, between }) => {
+            let (low, high) = match between.as_deref() {
+                Some(&[low, high]) => (low, high),
+                _ => (0, usize::MAX),
+            };
+            if low > high {
+                error!("--between needs LOW <= HIGH");
+                std::process::exit(1);
+            }
+            match storage::query_properties(&property, low, high) {
+                Ok(found) => {
+                    for n in &found {
+                        println!("{}", n);
+                    }
+                    info!("{} numbers are {}", found.len(), property.join(" and "));
+                }
+                Err(e) => {
+                    error!("Error reading stored properties: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Spf(SpfArgs { action }) => match action {
+            SpfAction::Build { limit, output } => {
+                let path = output.unwrap_or_else(|| storage::get_nt_data_dir().join("spf.bin"));
+                if let Some(parent) = path.parent()
+                    && let Err(e) = std::fs::create_dir_all(parent)
+                {
+                    error!("Error creating {}: {}", parent.display(), e);
+                    std::process::exit(1);
+                }
+                let start = Instant::now();
+                match spf::SpfTable::build(limit, &path) {
+                    Ok(bytes) => {
+                        info!(
+                            "Saved smallest prime factors up to {} to {} ({:.1} MB) in {:.2}s",
+                            limit,
+                            path.display(),
+                            bytes as f64 / (1024.0 * 1024.0),
+                            start.elapsed().as_secs_f64()
+                        );
+                    }
+                    Err(e) => {
+                        error!("Error writing {}: {}", path.display(), e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            SpfAction::Query { numbers, table } => {
+                let path = table.unwrap_or_else(|| storage::get_nt_data_dir().join("spf.bin"));
+                let table = match spf::SpfTable::open(&path) {
+                    Ok(table) => table,
+                    Err(e) => {
+                        error!(
+                            "Error opening {}: {} (run `nt spf build <limit>` first)",
+                            path.display(),
+                            e
+                        );
+                        std::process::exit(1);
+                    }
+                };
+                for n in numbers {
+                    if n > table.limit() {
+                        error!("Error: {} is above the table limit {}", n, table.limit());
+                        std::process::exit(2);
+                    }
+                    let terms: Vec<String> = table
+                        .factorize(n)
+                        .into_iter()
+                        .map(|(p, exp)| {
+                            if exp == 1 {
+                                p.to_string()
+                            } else {
+                                format!("{}^{}", p, exp)
+                            }
+                        })
+                        .collect();
+                    if terms.is_empty() {
+                        println!("{} has no prime factors", n);
+                    } else {
+                        println!("{} = {}", n, terms.join(" * "));
+                    }
+                }
+            }
+        },
+        _ => unreachable!("not a stored command"),
+    }
+}
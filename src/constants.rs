@@ -0,0 +1,144 @@
+// Mathematical constants to arbitrary precision
+//
+// Pure Rust so it also builds without rug (wasm32). Uses the same Machin formula as
+// `nt pi`, evaluated in fixed point with base 10^9 limbs: every step is a division by a
+// small integer, which is O(digits) per term and O(digits²) overall.
+//
+// Constants that need real powers or special functions use rug and live in submodules
+// built only with the `native` feature (zeta, prime_zeta, gamma, catalan), along with the
+// cache that keeps their expansions in the data dir for `nt scan`.
+
+#[cfg(feature = "native")]
+pub mod cache;
+#[cfg(feature = "native")]
+pub mod catalan;
+#[cfg(feature = "native")]
+pub mod gamma;
+#[cfg(feature = "native")]
+pub mod prime_zeta;
+#[cfg(feature = "native")]
+pub mod zeta;
+
+// Decimal digits per limb
+const LIMB_DIGITS: usize = 9;
+const LIMB: u64 = 1_000_000_000;
+
+// Extra limbs carried past the requested digits to absorb truncation error
+const GUARD_LIMBS: usize = 2;
+
+/// Working precision in bits for `digits` decimal digits (for rug-based series)
+/// Roughly 3.32 bits per digit, with 50% headroom for rounding in the series
+pub fn precision_for_digits(digits: usize) -> u32 {
+    ((digits as f64) * 3.32 * 1.5) as u32
+}
+
+/// π truncated to `digits` decimal places, e.g. pi(5) == "3.14159"
+pub fn pi(digits: usize) -> String {
+    let limbs = digits.div_ceil(LIMB_DIGITS) + GUARD_LIMBS + 1;
+
+    // π = 16·arctan(1/5) - 4·arctan(1/239)
+    let mut pi = arctan_inv(5, limbs);
+    mul_small(&mut pi, 16);
+    let mut tail = arctan_inv(239, limbs);
+    mul_small(&mut tail, 4);
+    sub_assign(&mut pi, &tail);
+
+    let mut out = pi[0].to_string();
+    if digits > 0 {
+        out.push('.');
+        let fraction: String = pi[1..].iter().map(|limb| format!("{:09}", limb)).collect();
+        out.push_str(&fraction[..digits]);
+    }
+    out
+}
+
+/// arctan(1/x) as fixed point: limb 0 is the integer part, the rest are base 10^9 fraction
+fn arctan_inv(x: u64, limbs: usize) -> Vec<u64> {
+    // arctan(1/x) = 1/x - 1/(3x³) + 1/(5x⁵) - ...
+    let mut power = vec![0; limbs];
+    power[0] = 1;
+    div_small(&mut power, x);
+
+    let mut sum = power.clone();
+    let x_squared = x * x;
+    let mut n = 1;
+    let mut add = false;
+
+    loop {
+        div_small(&mut power, x_squared);
+        if power.iter().all(|&limb| limb == 0) {
+            break;
+        }
+        n += 2;
+
+        let mut term = power.clone();
+        div_small(&mut term, n);
+        if add {
+            add_assign(&mut sum, &term);
+        } else {
+            sub_assign(&mut sum, &term);
+        }
+        add = !add;
+    }
+
+    sum
+}
+
+fn div_small(value: &mut [u64], divisor: u64) {
+    let mut remainder = 0;
+    for limb in value.iter_mut() {
+        let current = remainder * LIMB + *limb;
+        *limb = current / divisor;
+        remainder = current % divisor;
+    }
+}
+
+// The integer part (limb 0) is small, so it absorbs the final carry without wrapping
+fn mul_small(value: &mut [u64], factor: u64) {
+    let mut carry = 0;
+    for limb in value[1..].iter_mut().rev() {
+        let current = *limb * factor + carry;
+        *limb = current % LIMB;
+        carry = current / LIMB;
+    }
+    value[0] = value[0] * factor + carry;
+}
+
+fn add_assign(value: &mut [u64], other: &[u64]) {
+    let mut carry = 0;
+    for (limb, &rhs) in value[1..].iter_mut().zip(&other[1..]).rev() {
+        let current = *limb + rhs + carry;
+        *limb = current % LIMB;
+        carry = current / LIMB;
+    }
+    value[0] += other[0] + carry;
+}
+
+/// value -= other; callers guarantee value >= other
+fn sub_assign(value: &mut [u64], other: &[u64]) {
+    let mut borrow = 0;
+    for (limb, &rhs) in value.iter_mut().zip(other).rev() {
+        let rhs = rhs + borrow;
+        if *limb >= rhs {
+            *limb -= rhs;
+            borrow = 0;
+        } else {
+            *limb = *limb + LIMB - rhs;
+            borrow = 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCURATE_PI: &str = "3.1415926535897932384626433832795028841971693993751058209749445923078164062862089986280348253421170679";
+
+    #[test]
+    fn test_pi_digits() {
+        assert_eq!(pi(0), "3");
+        assert_eq!(pi(5), "3.14159");
+        assert_eq!(pi(100), ACCURATE_PI);
+    }
+}
@@ -0,0 +1,124 @@
+// Decimal expansions of the named constants, cached in the data dir (`nt scan`)
+//
+// Scanning a constant's digits for primes only needs the digits, and recomputing 10^6
+// digits of ζ(3) for every scan would dominate the run. Each expansion is kept as a single
+// line "1.2020569..." in <data dir>/constants/<name>.txt; a request for no more places
+// than the file holds is served from its prefix, and a longer one recomputes and replaces
+// it. The digits are truncated, never rounded, so every prefix of a cached expansion is the
+// expansion to that many places.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use rug::Float;
+
+use super::{catalan, gamma, precision_for_digits, zeta};
+use crate::storage::{commit_output, get_nt_data_dir, staging_path};
+
+/// A constant whose digits can be cached and scanned
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum NamedConstant {
+    /// π
+    Pi,
+    /// Euler–Mascheroni constant γ
+    Gamma,
+    /// Catalan's constant G
+    Catalan,
+    /// Apéry's constant ζ(3)
+    Apery,
+    /// Euler's number e
+    E,
+}
+
+impl NamedConstant {
+    /// File stem for this constant's cache
+    pub fn stem(self) -> &'static str {
+        match self {
+            NamedConstant::Pi => "pi",
+            NamedConstant::Gamma => "gamma",
+            NamedConstant::Catalan => "catalan",
+            NamedConstant::Apery => "apery",
+            NamedConstant::E => "e",
+        }
+    }
+
+    /// The expansion truncated to `places` decimal places, e.g. "3.14159" for π and 5
+    pub fn compute(self, places: usize) -> String {
+        // Guard digits so truncation never sees a rounded last place
+        let precision = precision_for_digits(places + 10).max(64);
+        let value = match self {
+            NamedConstant::Pi => return super::pi(places),
+            NamedConstant::Gamma => gamma::euler_gamma(places + 10),
+            NamedConstant::Catalan => catalan::catalan(precision),
+            NamedConstant::Apery => zeta::zeta(&Float::with_val(precision, 3), precision).unwrap(),
+            NamedConstant::E => Float::with_val(precision, 1).exp(),
+        };
+        let whole = Float::with_val(precision, value.floor_ref());
+        let fraction = Float::with_val(precision, &value - &whole);
+        let mut expansion = whole.to_integer().unwrap_or_default().to_string();
+        if places > 0 {
+            expansion.push('.');
+            expansion.push_str(&gamma::fraction_digits(&fraction, places));
+        }
+        expansion
+    }
+}
+
+/// Where `constant`'s expansion is cached
+pub fn cache_path(constant: NamedConstant) -> PathBuf {
+    get_nt_data_dir()
+        .join("constants")
+        .join(format!("{}.txt", constant.stem()))
+}
+
+/// Places after the decimal point in an expansion
+fn places_in(expansion: &str) -> usize {
+    expansion
+        .split_once('.')
+        .map_or(0, |(_, fraction)| fraction.len())
+}
+
+/// `constant` to `places` decimal places from the cache, computing and caching it if the
+/// cache is missing or too short; also reports whether the cache was used
+pub fn expansion(constant: NamedConstant, places: usize) -> io::Result<(String, bool)> {
+    let path = cache_path(constant);
+    if let Ok(cached) = fs::read_to_string(&path) {
+        let cached = cached.trim();
+        if places_in(cached) >= places {
+            let (whole, fraction) = cached.split_once('.').unwrap_or((cached, ""));
+            let expansion = if places == 0 {
+                whole.to_string()
+            } else {
+                format!("{}.{}", whole, &fraction[..places])
+            };
+            return Ok((expansion, true));
+        }
+    }
+
+    let expansion = constant.compute(places);
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(staging_path(&path), format!("{}\n", expansion))?;
+    commit_output(&path)?;
+    Ok((expansion, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expansions_truncate() {
+        assert_eq!(NamedConstant::Pi.compute(5), "3.14159");
+        assert_eq!(NamedConstant::Gamma.compute(5), "0.57721");
+        // G = 0.9159655941 77..., ζ(3) = 1.2020569031 59...
+        assert_eq!(NamedConstant::Catalan.compute(10), "0.9159655941");
+        assert_eq!(NamedConstant::Apery.compute(10), "1.2020569031");
+        assert_eq!(NamedConstant::Apery.compute(0), "1");
+        // e = 2.7182818284 59...
+        assert_eq!(NamedConstant::E.compute(10), "2.7182818284");
+        let long = NamedConstant::Catalan.compute(200);
+        assert!(long.starts_with(&NamedConstant::Catalan.compute(150)));
+    }
+}
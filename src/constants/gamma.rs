@@ -0,0 +1,94 @@
+// The Euler–Mascheroni constant γ to arbitrary precision (`nt gamma`)
+//
+// Brent and McMillan's algorithm B1: with A_0 = −ln n, B_0 = 1 and
+// B_k = B_(k−1) n²/k², A_k = (A_(k−1) n²/k + B_k)/k,
+// γ = Σ A_k / Σ B_k − O(e^(−4n)), so n ≈ digits · ln 10 / 4 gives every digit asked for.
+// The terms peak around k = n and fall below the error bound by k ≈ 3.59n (the root of
+// α(ln α − 1) = 1), so the series has a known length and reports progress the way
+// `nt pi` does. Each step is a few multiplications and divisions by small integers, so
+// the whole sum is O(n) operations at the working precision.
+//
+// Like π, the digits are then searched for stored primes with scan::scan_for_primes.
+
+use rug::ops::Pow;
+use rug::{Float, Integer};
+
+use crate::constants::precision_for_digits;
+use crate::progress;
+use crate::scan;
+
+// α with α(ln α − 1) = 1: terms past k = αn are below e^(−4n)
+const TERMS_PER_N: f64 = 3.5911;
+
+/// n for Brent–McMillan at `digits` decimal digits
+fn parameter(digits: usize) -> u64 {
+    (digits as f64 * std::f64::consts::LN_10 / 4.0).ceil() as u64 + 2
+}
+
+/// Number of series terms for `digits` decimal digits
+pub fn series_terms(digits: usize) -> u64 {
+    (parameter(digits) as f64 * TERMS_PER_N).ceil() as u64 + 1
+}
+
+/// γ to `digits` decimal digits (computed at `precision_for_digits(digits)` bits)
+pub fn euler_gamma(digits: usize) -> Float {
+    let precision = precision_for_digits(digits).max(64);
+    let n = parameter(digits);
+    let n_squared = n * n;
+
+    let mut a = -Float::with_val(precision, n).ln();
+    let mut b = Float::with_val(precision, 1);
+    let mut u = a.clone();
+    let mut v = b.clone();
+    for k in 1..=series_terms(digits) {
+        b *= n_squared;
+        b /= k * k;
+        a *= n_squared;
+        a /= k;
+        a += &b;
+        a /= k;
+        u += &a;
+        v += &b;
+        progress::inc(1);
+    }
+    u / v
+}
+
+/// The first `digits` decimal places of 0 <= value < 1, truncated
+pub fn fraction_digits(value: &Float, digits: usize) -> String {
+    let scale = Integer::from(10).pow(digits as u32);
+    let scaled = Float::with_val(value.prec(), value * &scale).trunc();
+    let places = scaled.to_integer().unwrap_or_default().to_string();
+    "0".repeat(digits.saturating_sub(places.len())) + &places
+}
+
+/// Compute γ, print it and scan its digits for primes
+pub fn calculate_and_print(digits: usize, show_progress: bool) {
+    if show_progress {
+        progress::start(series_terms(digits), "terms");
+    }
+    let gamma = euler_gamma(digits);
+    progress::finish();
+
+    println!("γ to {} decimal places:", digits);
+    let places = fraction_digits(&gamma, digits);
+    println!("0.{}", places);
+
+    println!("\nScanning for primes in γ...");
+    scan::scan_for_primes(&places);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCURATE_GAMMA: &str =
+        "5772156649015328606065120900824024310421593359399235988057672348848677267776646709";
+
+    #[test]
+    fn test_gamma_digits() {
+        let places = fraction_digits(&euler_gamma(ACCURATE_GAMMA.len()), ACCURATE_GAMMA.len());
+        assert_eq!(places, ACCURATE_GAMMA);
+        assert_eq!(fraction_digits(&euler_gamma(5), 5), "57721");
+    }
+}
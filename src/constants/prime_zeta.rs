@@ -0,0 +1,141 @@
+// The prime zeta function P(s) = Σ p^−s over the primes (`nt prime-zeta`)
+//
+// Taking logs of the Euler product gives ln ζ(s) = Σ_k P(ks)/k, and Möbius inversion turns
+// that around: P(s) = Σ_k μ(k)/k · ln ζ(ks) for s > 1. ln ζ(ks) ≈ 2^−ks, so about
+// precision/s terms are needed. The first few come from the Borwein series in zeta.rs;
+// once ks is so large that Σ n^−ks reaches full precision within as many terms as Borwein
+// would use, ζ(ks) − 1 is summed directly and ln_1p keeps it accurate.
+//
+// The partial sum over the actual primes up to a limit converges far more slowly (the
+// tail is about x^(1−s) / ((s − 1) ln x)), which is the point of comparing the two. The
+// primes come from primes::base_primes, so stored `nt primes` output is read when it
+// reaches the limit and the range is sieved otherwise; workers sum chunks of them.
+
+use rug::ops::Pow;
+use rug::{Float, Integer};
+
+use super::zeta;
+use crate::factor;
+use crate::parallel::parallel_chunks;
+
+// Bits carried past the requested precision to absorb rounding in the Möbius sum
+const GUARD_BITS: u32 = 32;
+
+// Primes per chunk handed to a worker
+const CHUNK: usize = 1 << 14;
+
+/// μ(k): 0 if k has a square factor, else (−1)^(number of prime factors)
+fn mobius(k: u64) -> i32 {
+    let factors = factor::factorize(k);
+    if factors.iter().any(|&(_, exp)| exp > 1) {
+        0
+    } else if factors.len().is_multiple_of(2) {
+        1
+    } else {
+        -1
+    }
+}
+
+/// ln ζ(t) for t > 1 at `precision` bits
+fn ln_zeta(t: &Float, precision: u32) -> Float {
+    // n^−t < 2^−precision once n > 2^(precision/t)
+    let direct_terms = 2f64.powf(precision as f64 / t.to_f64()).ceil();
+    if direct_terms > precision as f64 / 2.5 {
+        return zeta::zeta(t, precision).unwrap().ln();
+    }
+    let mut tail = Float::new(precision);
+    for n in 2..=direct_terms as u64 + 1 {
+        let power = Float::with_val(precision, Float::with_val(precision, n).pow(t));
+        tail += 1u32 / power;
+    }
+    tail.ln_1p()
+}
+
+/// P(s) to `precision` bits, None unless s > 1
+pub fn prime_zeta(s: &Float, precision: u32) -> Option<Float> {
+    if *s <= 1 {
+        return None;
+    }
+    let work = precision + GUARD_BITS;
+    let mut sum = Float::new(work);
+    for k in 1u64.. {
+        let t = Float::with_val(work, s * Integer::from(k));
+        // ln ζ(ks) ≈ 2^−ks is below the working precision from here on
+        if t.to_f64() > work as f64 + 1.0 {
+            break;
+        }
+        let mu = mobius(k);
+        if mu == 0 {
+            continue;
+        }
+        let term = ln_zeta(&t, work) / Float::with_val(work, k);
+        if mu > 0 {
+            sum += term;
+        } else {
+            sum -= term;
+        }
+    }
+    Some(Float::with_val(precision, sum))
+}
+
+/// Σ p^−s over the primes p <= limit at `precision` bits on `num_workers` threads
+pub fn partial_sum(s: &Float, limit: usize, precision: u32, num_workers: usize) -> Float {
+    let primes = crate::primes::base_primes(limit);
+    let mut total = Float::new(precision);
+    if primes.is_empty() {
+        return total;
+    }
+    parallel_chunks(
+        0..=primes.len() - 1,
+        CHUNK,
+        num_workers,
+        |low, high| {
+            let mut sum = Float::new(precision);
+            for &p in &primes[low..=high] {
+                let power = Float::with_val(precision, Float::with_val(precision, p).pow(s));
+                sum += 1u32 / power;
+            }
+            sum
+        },
+        |sum| total += sum,
+    );
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRECISION: u32 = 300;
+
+    fn at(s: &str) -> Float {
+        prime_zeta(&zeta::parse_argument(s, PRECISION).unwrap(), PRECISION).unwrap()
+    }
+
+    #[test]
+    fn test_known_values() {
+        // A085548 and A085541
+        assert_eq!(
+            at("2").to_string_radix(10, Some(50)),
+            "4.5224742004106549850654336483224793417323134323989e-1"
+        );
+        assert_eq!(
+            at("3").to_string_radix(10, Some(50)),
+            "1.7476263929944353642311331466570670097541212192615e-1"
+        );
+        assert!(prime_zeta(&Float::with_val(PRECISION, 1), PRECISION).is_none());
+        assert_eq!(
+            [mobius(1), mobius(6), mobius(12), mobius(30)],
+            [1, 1, 0, -1]
+        );
+    }
+
+    #[test]
+    fn test_partial_sum_approaches_prime_zeta() {
+        let s = zeta::parse_argument("2", 128).unwrap();
+        let partial = partial_sum(&s, 1_000_000, 128, 3);
+        let gap = Float::with_val(128, prime_zeta(&s, 128).unwrap() - &partial).to_f64();
+        // Tail ≈ 1/(x ln x) ≈ 7.2e-8
+        assert!(gap > 5e-8 && gap < 1e-7, "gap {}", gap);
+    }
+}
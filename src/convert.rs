@@ -0,0 +1,417 @@
+// Convert stored primes between formats in one streaming pass (`nt convert`)
+//
+// Four formats, all readable and writable a prime at a time, so memory stays at a few
+// buffers whatever the file size:
+//
+// - text: one decimal prime per line (primes.txt)
+// - u64le: 8 bytes per prime, little-endian (primes.bin)
+// - delta-varint: b"NTDELTA1", then the first prime and each gap after it as LEB128
+//   varints; gaps below 128 take one byte, so this is about an eighth of u64le
+// - bitset: the prime set layout of prime_set.rs (b"NTPSET01", limit, count, one bit per
+//   odd number), which `nt near --set` and PrimeSet::open read directly
+//
+// The bitset header holds the limit and count, known only at the end, so it is written
+// as zeros first and filled in on finish. A bitset can only say which numbers up to its
+// limit are prime, so a list converted to one should hold every prime from 2.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use clap::ValueEnum;
+
+const DELTA_MAGIC: &[u8; 8] = b"NTDELTA1";
+const BITSET_MAGIC: &[u8; 8] = b"NTPSET01";
+
+// Buffer size for readers and writers
+const BUFFER_BYTES: usize = 256 * 1024;
+
+/// A stored prime format
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// One decimal prime per line (.txt)
+    Text,
+    /// 8 bytes per prime, little-endian (.bin)
+    U64le,
+    /// First prime and gaps as LEB128 varints (.delta)
+    DeltaVarint,
+    /// One bit per odd number, the prime set layout (.bits)
+    Bitset,
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Text => "text",
+            Format::U64le => "u64le",
+            Format::DeltaVarint => "delta-varint",
+            Format::Bitset => "bitset",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Format {
+    /// The format an output path's extension names
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "txt" => Some(Format::Text),
+            "bin" => Some(Format::U64le),
+            "delta" => Some(Format::DeltaVarint),
+            "bits" => Some(Format::Bitset),
+            _ => None,
+        }
+    }
+
+    /// The format of an existing file: its magic if it has one, else its extension, else
+    /// text
+    pub fn detect(path: &Path) -> io::Result<Self> {
+        let mut magic = [0_u8; 8];
+        let mut file = File::open(path)?;
+        let mut filled = 0;
+        while filled < magic.len() {
+            match file.read(&mut magic[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        Ok(match &magic {
+            DELTA_MAGIC => Format::DeltaVarint,
+            BITSET_MAGIC => Format::Bitset,
+            _ => match Self::from_extension(path) {
+                Some(Format::U64le) => Format::U64le,
+                _ => Format::Text,
+            },
+        })
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Primes read from a file, with read and format errors in line
+pub type Primes = Box<dyn Iterator<Item = io::Result<usize>>>;
+
+/// Stream the primes in `path`, stored as `format`
+pub fn read(path: &Path, format: Format) -> io::Result<Primes> {
+    let mut reader = BufReader::with_capacity(BUFFER_BYTES, File::open(path)?);
+    Ok(match format {
+        Format::Text => Box::new(reader.lines().filter_map(|line| {
+            match line {
+                Ok(line) if line.trim().is_empty() => None,
+                Ok(line) => Some(
+                    line.trim()
+                        .parse::<usize>()
+                        .map_err(|_| invalid(format!("'{}' is not a number", line.trim()))),
+                ),
+                Err(e) => Some(Err(e)),
+            }
+        })),
+        Format::U64le => Box::new(words(reader).map(|word| word.map(|w| w as usize))),
+        Format::DeltaVarint => {
+            let mut magic = [0_u8; 8];
+            reader.read_exact(&mut magic)?;
+            if &magic != DELTA_MAGIC {
+                return Err(invalid("not a delta-varint file".to_string()));
+            }
+            let mut last: Option<usize> = None;
+            Box::new(std::iter::from_fn(move || {
+                let delta = read_varint(&mut reader).transpose()?;
+                Some(delta.and_then(|delta| {
+                    let prime = match last {
+                        None => delta,
+                        Some(_) if delta == 0 => {
+                            return Err(invalid("zero gap in delta-varint file".to_string()));
+                        }
+                        Some(last) => last
+                            .checked_add(delta)
+                            .ok_or_else(|| invalid("prime past usize".to_string()))?,
+                    };
+                    last = Some(prime);
+                    Ok(prime)
+                }))
+            }))
+        }
+        Format::Bitset => {
+            let mut header = [0_u8; 24];
+            reader.read_exact(&mut header)?;
+            if &header[..8] != BITSET_MAGIC {
+                return Err(invalid("not a prime set file".to_string()));
+            }
+            let limit = u64::from_le_bytes(header[8..16].try_into().unwrap()) as usize;
+            let two = (limit >= 2).then_some(Ok(2));
+            let odd = words(reader).enumerate().flat_map(|(word_idx, word)| {
+                let mut word = match word {
+                    Ok(word) => word,
+                    Err(e) => return vec![Err(e)],
+                };
+                let mut primes = Vec::with_capacity(word.count_ones() as usize);
+                while word != 0 {
+                    // Bit i is the odd number 2i + 1
+                    let idx = word_idx * 64 + word.trailing_zeros() as usize;
+                    primes.push(Ok(2 * idx + 1));
+                    word &= word - 1;
+                }
+                primes
+            });
+            Box::new(two.into_iter().chain(odd))
+        }
+    })
+}
+
+/// Little-endian u64 words until the end of `reader`; a partial word is an error
+fn words(mut reader: impl Read + 'static) -> impl Iterator<Item = io::Result<u64>> {
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let mut bytes = [0_u8; 8];
+        let mut filled = 0;
+        while filled < 8 {
+            match reader.read(&mut bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        match filled {
+            0 => None,
+            8 => Some(Ok(u64::from_le_bytes(bytes))),
+            _ => {
+                done = true;
+                Some(Err(invalid(
+                    "length is not a multiple of 8 bytes".to_string(),
+                )))
+            }
+        }
+    })
+}
+
+/// One LEB128 varint, or None at a clean end of input
+fn read_varint(reader: &mut impl Read) -> io::Result<Option<usize>> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0_u8; 1];
+        if reader.read(&mut byte)? == 0 {
+            return if shift == 0 {
+                Ok(None)
+            } else {
+                Err(invalid(
+                    "delta-varint file ends inside a number".to_string(),
+                ))
+            };
+        }
+        if shift >= 64 {
+            return Err(invalid("varint longer than 64 bits".to_string()));
+        }
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value as usize));
+        }
+        shift += 7;
+    }
+}
+
+fn write_varint(writer: &mut impl Write, mut value: usize) -> io::Result<()> {
+    let mut bytes = [0_u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            bytes[len] = byte;
+            len += 1;
+            break;
+        }
+        bytes[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&bytes[..len])
+}
+
+/// Writes primes in one format; they must arrive strictly increasing
+pub struct Writer {
+    format: Format,
+    out: BufWriter<File>,
+    itoa_buf: itoa::Buffer,
+    last: Option<usize>,
+    count: u64,
+    // Bitset: index of the word being filled, and its bits so far
+    word_idx: usize,
+    word: u64,
+}
+
+impl Writer {
+    pub fn create(path: &Path, format: Format) -> io::Result<Self> {
+        let mut out = BufWriter::with_capacity(BUFFER_BYTES, File::create(path)?);
+        match format {
+            Format::DeltaVarint => out.write_all(DELTA_MAGIC)?,
+            // Limit and count are filled in by finish
+            Format::Bitset => out.write_all(&[0_u8; 24])?,
+            Format::Text | Format::U64le => {}
+        }
+        Ok(Self {
+            format,
+            out,
+            itoa_buf: itoa::Buffer::new(),
+            last: None,
+            count: 0,
+            word_idx: 0,
+            word: 0,
+        })
+    }
+
+    pub fn write(&mut self, prime: usize) -> io::Result<()> {
+        if let Some(last) = self.last
+            && prime <= last
+        {
+            return Err(invalid(format!(
+                "input is not increasing: {} after {}",
+                prime, last
+            )));
+        }
+        match self.format {
+            Format::Text => {
+                self.out.write_all(self.itoa_buf.format(prime).as_bytes())?;
+                self.out.write_all(b"\n")?;
+            }
+            Format::U64le => self.out.write_all(&(prime as u64).to_le_bytes())?,
+            Format::DeltaVarint => {
+                write_varint(&mut self.out, prime - self.last.unwrap_or(0))?;
+            }
+            Format::Bitset => {
+                if prime.is_multiple_of(2) {
+                    // 2 is implied by the limit; no other even number is prime
+                    if prime != 2 {
+                        return Err(invalid(format!("{} is even", prime)));
+                    }
+                } else {
+                    let idx = prime / 2;
+                    while self.word_idx < idx / 64 {
+                        self.out.write_all(&self.word.to_le_bytes())?;
+                        self.word = 0;
+                        self.word_idx += 1;
+                    }
+                    self.word |= 1 << (idx % 64);
+                }
+            }
+        }
+        self.last = Some(prime);
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Flush, fill in the bitset header, and return the number of primes written
+    pub fn finish(mut self) -> io::Result<u64> {
+        if self.format == Format::Bitset {
+            let limit = self.last.unwrap_or(0);
+            // Words up to the one holding the limit, as PrimeSet expects
+            let words = limit.div_ceil(2).div_ceil(64);
+            while self.word_idx < words {
+                self.out.write_all(&self.word.to_le_bytes())?;
+                self.word = 0;
+                self.word_idx += 1;
+            }
+            self.out.seek(SeekFrom::Start(0))?;
+            self.out.write_all(BITSET_MAGIC)?;
+            self.out.write_all(&(limit as u64).to_le_bytes())?;
+            self.out.write_all(&self.count.to_le_bytes())?;
+        }
+        self.out.flush()?;
+        Ok(self.count)
+    }
+}
+
+/// Copy the primes in `input` (stored as `from`) to `output` as `to`
+pub fn convert(input: &Path, from: Format, output: &Path, to: Format) -> io::Result<u64> {
+    let primes = read(input, from)?;
+    let mut writer = Writer::create(output, to)?;
+    for prime in primes {
+        writer.write(prime?)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PrimeSet;
+
+    fn scratch(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("nt_convert_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_every_format_round_trips() {
+        let primes = crate::primes::sieve(100_000);
+        let text = scratch("primes.txt");
+        std::fs::write(
+            &text,
+            primes
+                .iter()
+                .map(|p| format!("{}\n", p))
+                .collect::<String>(),
+        )
+        .unwrap();
+        for format in [
+            Format::Text,
+            Format::U64le,
+            Format::DeltaVarint,
+            Format::Bitset,
+        ] {
+            let path = scratch(&format!("out_{}", format));
+            assert_eq!(
+                convert(&text, Format::Text, &path, format).unwrap(),
+                primes.len() as u64
+            );
+            let back: Vec<usize> = read(&path, format)
+                .unwrap()
+                .collect::<io::Result<_>>()
+                .unwrap();
+            assert_eq!(back, primes, "{}", format);
+            std::fs::remove_file(&path).unwrap();
+        }
+        std::fs::remove_file(&text).unwrap();
+    }
+
+    #[test]
+    fn test_bitset_opens_as_prime_set() {
+        let path = scratch("set.bits");
+        let mut writer = Writer::create(&path, Format::Bitset).unwrap();
+        for p in crate::primes::sieve(1000) {
+            writer.write(p).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), 168);
+        assert_eq!(Format::detect(&path).unwrap(), Format::Bitset);
+        let set = PrimeSet::open(&path).unwrap();
+        assert_eq!(set.iter().collect::<Vec<_>>(), crate::primes::sieve(997));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_varints_and_order() {
+        let mut bytes = Vec::new();
+        for value in [0, 1, 127, 128, 300, usize::MAX] {
+            write_varint(&mut bytes, value).unwrap();
+        }
+        let mut reader = bytes.as_slice();
+        for value in [0, 1, 127, 128, 300, usize::MAX] {
+            assert_eq!(read_varint(&mut reader).unwrap(), Some(value));
+        }
+        assert_eq!(read_varint(&mut reader).unwrap(), None);
+
+        let path = scratch("order.bin");
+        let mut writer = Writer::create(&path, Format::U64le).unwrap();
+        writer.write(3).unwrap();
+        assert!(writer.write(3).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,372 @@
+// Management of the nt data directory (`nt data`)
+//
+// Everything nt writes lands in one directory: primes.txt / primes.bin, shards from variation
+// 9 or --shards (primes_small and primes_N, .txt or .bin, and primes_manifest.json), rolling
+// stream_NNNNNN files from --unbounded, range_<from>_<to> windows from --from/--to, one
+// <n>.txt property file per number, the execution log, and .tmp files from runs that died
+// before renaming their output into place. These helpers list, size and prune it so nothing
+// has to be deleted by hand.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+use tracing::{error, info, warn};
+
+use crate::storage::get_nt_data_dir;
+
+/// What a file in the data directory holds
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FileKind {
+    /// primes.txt or primes.bin
+    Primes,
+    /// <file>.idx, the rank/select index beside a prime file
+    Index,
+    /// Sharded output: primes_small and primes_N, .bin or .txt, and primes_manifest.json
+    Shard,
+    /// Rolling output of --unbounded: stream_NNNNNN, .bin or .txt
+    Stream,
+    /// A --from/--to window: range_<from>_<to>, .bin or .txt
+    Range,
+    /// <n>.txt written by --save-as-property
+    Property,
+    /// execution_log.txt
+    Log,
+    /// <name>.tmp left behind by an interrupted run
+    Partial,
+    Other,
+}
+
+impl FileKind {
+    pub fn of(name: &str) -> Self {
+        if name.ends_with(".tmp") {
+            FileKind::Partial
+        } else if name.ends_with(".idx") {
+            FileKind::Index
+        } else if name == "primes.txt" || name == "primes.bin" {
+            FileKind::Primes
+        } else if name.starts_with("primes_")
+            && (name.ends_with(".bin") || name.ends_with(".txt") || name.ends_with(".json"))
+        {
+            FileKind::Shard
+        } else if name.starts_with("stream_") && (name.ends_with(".bin") || name.ends_with(".txt"))
+        {
+            FileKind::Stream
+        } else if name.starts_with("range_") && (name.ends_with(".bin") || name.ends_with(".txt")) {
+            FileKind::Range
+        } else if name == "execution_log.txt" {
+            FileKind::Log
+        } else if let Some(stem) = name.strip_suffix(".txt")
+            && !stem.is_empty()
+            && stem.bytes().all(|b| b.is_ascii_digit())
+        {
+            FileKind::Property
+        } else {
+            FileKind::Other
+        }
+    }
+}
+
+impl fmt::Display for FileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            FileKind::Primes => "primes",
+            FileKind::Index => "index",
+            FileKind::Shard => "shard",
+            FileKind::Stream => "stream",
+            FileKind::Range => "range",
+            FileKind::Property => "property",
+            FileKind::Log => "log",
+            FileKind::Partial => "partial",
+            FileKind::Other => "other",
+        };
+        f.write_str(label)
+    }
+}
+
+/// One regular file in the data directory
+pub struct DataFile {
+    pub path: PathBuf,
+    pub name: String,
+    pub kind: FileKind,
+    pub bytes: u64,
+    pub modified: SystemTime,
+}
+
+/// Every regular file in the data directory, sorted by name
+/// A data directory that does not exist yet is empty
+pub fn list_files() -> io::Result<Vec<DataFile>> {
+    let entries = match fs::read_dir(get_nt_data_dir()) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        files.push(DataFile {
+            path: entry.path(),
+            kind: FileKind::of(&name),
+            name,
+            bytes: metadata.len(),
+            modified: metadata.modified()?,
+        });
+    }
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+/// Print every file with its kind, size and age
+pub fn list() {
+    let files = match list_files() {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Error reading {}: {}", get_nt_data_dir().display(), e);
+            return;
+        }
+    };
+
+    println!("{}", get_nt_data_dir().display());
+    for file in &files {
+        println!(
+            "{:<9} {:>10}  {:>8}  {}",
+            file.kind.to_string(),
+            format_bytes(file.bytes),
+            format_age(file.modified),
+            file.name
+        );
+    }
+    println!("{} files", files.len());
+}
+
+/// Print file count and total size per kind
+pub fn size() {
+    let files = match list_files() {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Error reading {}: {}", get_nt_data_dir().display(), e);
+            return;
+        }
+    };
+
+    // (kind, files, bytes) in FileKind order
+    let mut totals: Vec<(FileKind, usize, u64)> = Vec::new();
+    for file in &files {
+        match totals.iter_mut().find(|(kind, _, _)| *kind == file.kind) {
+            Some((_, count, bytes)) => {
+                *count += 1;
+                *bytes += file.bytes;
+            }
+            None => totals.push((file.kind, 1, file.bytes)),
+        }
+    }
+    totals.sort_by_key(|(kind, _, _)| *kind);
+
+    for (kind, count, bytes) in &totals {
+        println!(
+            "{:<9} {:>8} files {:>10}",
+            kind.to_string(),
+            count,
+            format_bytes(*bytes)
+        );
+    }
+    let total_bytes: u64 = files.iter().map(|f| f.bytes).sum();
+    println!(
+        "{:<9} {:>8} files {:>10}",
+        "total",
+        files.len(),
+        format_bytes(total_bytes)
+    );
+}
+
+/// Remove files matching every given filter; with `dry_run` only print what would go
+pub fn clean(older_than: Option<Duration>, pattern: Option<&str>, dry_run: bool) {
+    let files = match list_files() {
+        Ok(files) => files,
+        Err(e) => {
+            error!("Error reading {}: {}", get_nt_data_dir().display(), e);
+            return;
+        }
+    };
+
+    let now = SystemTime::now();
+    let selected = files.iter().filter(|file| {
+        let old_enough = older_than.is_none_or(|age| {
+            now.duration_since(file.modified)
+                .is_ok_and(|elapsed| elapsed >= age)
+        });
+        let matches = pattern.is_none_or(|pattern| glob_match(pattern, &file.name));
+        old_enough && matches
+    });
+
+    let mut removed = 0;
+    let mut freed = 0;
+    for file in selected {
+        if dry_run {
+            println!("Would remove {}", file.name);
+        } else if let Err(e) = fs::remove_file(&file.path) {
+            warn!("Warning: Could not remove {}: {}", file.name, e);
+            continue;
+        }
+        removed += 1;
+        freed += file.bytes;
+    }
+
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    info!("{} {} files ({})", verb, removed, format_bytes(freed));
+}
+
+/// Remove the shards and manifest of an earlier sharded run
+/// Used before sharded runs so shards from an earlier run with more of them are not read
+/// back as part of this one
+pub fn remove_old_shards() {
+    let removed = ["primes_*.bin", "primes_*.txt", "primes_*.json"]
+        .into_iter()
+        .try_fold(0, |removed, pattern| {
+            Ok::<_, io::Error>(removed + remove_matching(pattern)?)
+        });
+    match removed {
+        Ok(0) => {}
+        Ok(removed) => info!("Removed {} shard files from a previous run", removed),
+        Err(e) => warn!("Warning: Could not remove old shard files: {}", e),
+    }
+}
+
+/// Remove files whose name matches `pattern`, returning how many were removed
+pub fn remove_matching(pattern: &str) -> io::Result<usize> {
+    let mut removed = 0;
+    for file in list_files()? {
+        if glob_match(pattern, &file.name) {
+            fs::remove_file(&file.path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Shell-style match of a file name: `*` is any run of characters, `?` any one character
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Greedy match with backtracking to the most recent `*`
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Parse an age like "30s", "15m", "12h", "7d" or "2w" (used as a clap value_parser)
+pub fn parse_age(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| format!("missing unit in '{}' (use s, m, h, d or w)", input))?;
+    let (number, unit) = input.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid age '{}'", input))?;
+
+    let unit_secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(format!("unknown unit '{}' (use s, m, h, d or w)", unit)),
+    };
+    number
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("age '{}' is too large", input))
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn format_age(modified: SystemTime) -> String {
+    let secs = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default()
+        .as_secs();
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("primes_*.bin", "primes_12.bin"));
+        assert!(glob_match("primes_*.bin", "primes_small.bin"));
+        assert!(!glob_match("primes_*.bin", "primes.bin"));
+        assert!(glob_match("*.txt", "97.txt"));
+        assert!(glob_match("?.txt", "7.txt"));
+        assert!(!glob_match("?.txt", "97.txt"));
+        assert!(glob_match("*log*", "execution_log.txt"));
+    }
+
+    #[test]
+    fn test_file_kinds() {
+        assert_eq!(FileKind::of("primes.bin"), FileKind::Primes);
+        assert_eq!(FileKind::of("primes.bin.idx"), FileKind::Index);
+        assert_eq!(FileKind::of("primes_3.bin"), FileKind::Shard);
+        assert_eq!(FileKind::of("primes_small.txt"), FileKind::Shard);
+        assert_eq!(FileKind::of("primes_manifest.json"), FileKind::Shard);
+        assert_eq!(FileKind::of("stream_000002.bin"), FileKind::Stream);
+        assert_eq!(FileKind::of("104729.txt"), FileKind::Property);
+        assert_eq!(FileKind::of("execution_log.txt"), FileKind::Log);
+        assert_eq!(FileKind::of("primes_2.bin.tmp"), FileKind::Partial);
+        assert_eq!(FileKind::of("notes.txt"), FileKind::Other);
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_age("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert!(parse_age("7").is_err());
+        assert!(parse_age("3y").is_err());
+    }
+}
@@ -0,0 +1,236 @@
+// Where a string of digits to analyse comes from (`nt scan <source>`)
+//
+// The pattern scan, palindrome search and digit statistics in scan.rs all take a plain
+// digit string, and a DigitSource is anything that can produce one: the cached expansion
+// of a constant (π, e, γ, ...), random digits, the concatenated positive integers
+// (Champernowne's constant), a file or stdin. Files and stdin keep only their ASCII
+// digits, so a prime list or a formatted expansion can be read as is.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use tracing::info;
+
+use crate::constants::cache::{self, NamedConstant};
+use crate::random::Rng;
+
+/// Something that produces decimal digits
+pub trait DigitSource {
+    /// What the digits are of, for messages
+    fn name(&self) -> String;
+    /// The first `count` digits; files and stdin give all of theirs
+    fn digits(&mut self, count: usize) -> io::Result<String>;
+}
+
+/// A constant's cached expansion, whole part included, to `count` decimal places
+pub struct Constant(pub NamedConstant);
+
+impl DigitSource for Constant {
+    fn name(&self) -> String {
+        self.0.stem().to_string()
+    }
+
+    fn digits(&mut self, count: usize) -> io::Result<String> {
+        let start = Instant::now();
+        let path = cache::cache_path(self.0);
+        let (expansion, cached) = cache::expansion(self.0, count)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+        if cached {
+            info!("Read {} places from {}", count, path.display());
+        } else {
+            info!(
+                "Computed {} places in {:.2}s, cached in {}",
+                count,
+                start.elapsed().as_secs_f64(),
+                path.display()
+            );
+        }
+        Ok(expansion.replace('.', ""))
+    }
+}
+
+/// Uniformly random digits
+pub struct RandomDigits(pub Rng);
+
+impl DigitSource for RandomDigits {
+    fn name(&self) -> String {
+        "random digits".to_string()
+    }
+
+    fn digits(&mut self, count: usize) -> io::Result<String> {
+        Ok((0..count)
+            .map(|_| char::from(b'0' + self.0.below(10) as u8))
+            .collect())
+    }
+}
+
+/// 123456789101112..., the digits of Champernowne's constant after the point
+pub struct Champernowne;
+
+impl DigitSource for Champernowne {
+    fn name(&self) -> String {
+        "Champernowne's constant".to_string()
+    }
+
+    fn digits(&mut self, count: usize) -> io::Result<String> {
+        let mut digits = String::with_capacity(count + 20);
+        let mut n = 1_u64;
+        while digits.len() < count {
+            digits.push_str(&n.to_string());
+            n += 1;
+        }
+        digits.truncate(count);
+        Ok(digits)
+    }
+}
+
+/// The digits in a file
+pub struct FileDigits(pub PathBuf);
+
+impl DigitSource for FileDigits {
+    fn name(&self) -> String {
+        self.0.display().to_string()
+    }
+
+    fn digits(&mut self, _count: usize) -> io::Result<String> {
+        let mut text = fs::read_to_string(&self.0)?;
+        text.retain(|c| c.is_ascii_digit());
+        Ok(text)
+    }
+}
+
+/// The digits read from stdin
+pub struct Stdin;
+
+impl DigitSource for Stdin {
+    fn name(&self) -> String {
+        "stdin".to_string()
+    }
+
+    fn digits(&mut self, _count: usize) -> io::Result<String> {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        text.retain(|c| c.is_ascii_digit());
+        Ok(text)
+    }
+}
+
+/// A source named on the command line
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SourceSpec {
+    Constant(NamedConstant),
+    /// With a seed, the same digits every time
+    Random(Option<u64>),
+    Champernowne,
+    File(PathBuf),
+    Stdin,
+}
+
+impl SourceSpec {
+    pub fn open(&self) -> Box<dyn DigitSource> {
+        match self {
+            SourceSpec::Constant(constant) => Box::new(Constant(*constant)),
+            SourceSpec::Random(seed) => Box::new(RandomDigits(match seed {
+                Some(seed) => Rng::new(*seed),
+                None => Rng::from_entropy(),
+            })),
+            SourceSpec::Champernowne => Box::new(Champernowne),
+            SourceSpec::File(path) => Box::new(FileDigits(path.clone())),
+            SourceSpec::Stdin => Box::new(Stdin),
+        }
+    }
+}
+
+impl fmt::Display for SourceSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceSpec::Constant(constant) => write!(f, "{}", constant.stem()),
+            SourceSpec::Random(None) => write!(f, "random"),
+            SourceSpec::Random(Some(seed)) => write!(f, "random:{}", seed),
+            SourceSpec::Champernowne => write!(f, "champernowne"),
+            SourceSpec::File(path) => write!(f, "file:{}", path.display()),
+            SourceSpec::Stdin => write!(f, "-"),
+        }
+    }
+}
+
+/// Parse a source: a constant (pi, e, gamma, catalan, apery), random or random:<seed>,
+/// champernowne, file:<path> or - for stdin
+pub fn parse_spec(input: &str) -> Result<SourceSpec, String> {
+    let input = input.trim();
+    if let Some(path) = input.strip_prefix("file:") {
+        return Ok(SourceSpec::File(PathBuf::from(path)));
+    }
+    if let Some(seed) = input.strip_prefix("random:") {
+        return seed
+            .parse()
+            .map(|seed| SourceSpec::Random(Some(seed)))
+            .map_err(|_| format!("'{}' is not a seed", seed));
+    }
+    match input.to_ascii_lowercase().as_str() {
+        "-" | "stdin" => return Ok(SourceSpec::Stdin),
+        "random" => return Ok(SourceSpec::Random(None)),
+        "champernowne" => return Ok(SourceSpec::Champernowne),
+        _ => {}
+    }
+    <NamedConstant as clap::ValueEnum>::from_str(input, true)
+        .map(SourceSpec::Constant)
+        .map_err(|_| {
+            format!(
+                "'{}' is not a digit source: use pi, e, gamma, catalan, apery, random, \
+                 random:<seed>, champernowne, file:<path> or -",
+                input
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spec() {
+        assert_eq!(
+            parse_spec("pi"),
+            Ok(SourceSpec::Constant(NamedConstant::Pi))
+        );
+        assert_eq!(parse_spec("E"), Ok(SourceSpec::Constant(NamedConstant::E)));
+        assert_eq!(parse_spec("random:7"), Ok(SourceSpec::Random(Some(7))));
+        assert_eq!(
+            parse_spec("file:digits.txt"),
+            Ok(SourceSpec::File(PathBuf::from("digits.txt")))
+        );
+        assert_eq!(parse_spec("-"), Ok(SourceSpec::Stdin));
+        assert!(parse_spec("random:x").is_err());
+        assert!(parse_spec("tau").is_err());
+        for spec in [
+            "gamma",
+            "random",
+            "random:7",
+            "champernowne",
+            "file:a.txt",
+            "-",
+        ] {
+            assert_eq!(parse_spec(spec).unwrap().to_string(), spec);
+        }
+    }
+
+    #[test]
+    fn test_generated_sources() {
+        assert_eq!(Champernowne.digits(15).unwrap(), "123456789101112");
+        let mut a = SourceSpec::Random(Some(3)).open();
+        let mut b = SourceSpec::Random(Some(3)).open();
+        let digits = a.digits(1000).unwrap();
+        assert_eq!(digits.len(), 1000);
+        assert!(digits.bytes().all(|b| b.is_ascii_digit()));
+        assert_eq!(b.digits(1000).unwrap(), digits);
+
+        let path = std::env::temp_dir().join(format!("nt_digits_{}.txt", std::process::id()));
+        fs::write(&path, "2\n3\n5, 7\n").unwrap();
+        assert_eq!(FileDigits(path.clone()).digits(0).unwrap(), "2357");
+        fs::remove_file(&path).unwrap();
+    }
+}
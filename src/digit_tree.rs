@@ -0,0 +1,258 @@
+// Trees of truncatable primes (`nt digit-tree`)
+//
+// Start from the one-digit primes and keep appending a digit on the right while the result
+// stays prime: every node is a right-truncatable prime, since chopping its last digits
+// walks back up the tree. Only 1, 3, 7 and 9 can be appended (anything else makes an even
+// number or a multiple of 5), and the tree dies out after 83 primes, the deepest five
+// having eight digits, up to 73939133. Prepending digits on the left instead (no zeros,
+// which would vanish) gives the left-truncatable primes: 4260 of them, ending with the
+// 24-digit 357686312646216567629137.
+//
+// The tree is walked a level at a time. Nodes outgrow u64 on the left, so they are rug
+// Integers and each child is tested with Baillie–PSW instead of looked up in a sieve.
+
+use std::io::{self, BufRead, Write};
+
+use rug::Integer;
+use rug::ops::Pow;
+
+use crate::bpsw;
+
+/// Which end digits are added at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Nodes and children at one depth of the tree
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Level {
+    /// Digits in each node at this level
+    pub digits: usize,
+    pub nodes: usize,
+    /// Children of this level's nodes, i.e. the nodes of the next level
+    pub children: usize,
+    /// Nodes without children
+    pub leaves: usize,
+}
+
+impl Level {
+    /// Mean number of children per node
+    pub fn branching(&self) -> f64 {
+        self.children as f64 / self.nodes as f64
+    }
+}
+
+/// A tree walked down to its last level
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Tree {
+    pub levels: Vec<Level>,
+    /// The nodes of the last level reached, in increasing order
+    pub deepest: Vec<Integer>,
+}
+
+impl Tree {
+    /// Nodes in the whole tree
+    pub fn size(&self) -> usize {
+        self.levels.iter().map(|level| level.nodes).sum()
+    }
+}
+
+/// The one-digit primes, the roots of both trees
+pub fn roots() -> Vec<Integer> {
+    [2, 3, 5, 7].into_iter().map(Integer::from).collect()
+}
+
+/// Whether n is prime (exact below 2^64, Baillie–PSW above)
+pub fn is_prime(n: &Integer) -> bool {
+    bpsw::is_probable_prime(n)
+}
+
+/// The primes formed by adding one digit to `node` on `side`, in increasing order
+pub fn children(node: &Integer, side: Side) -> Vec<Integer> {
+    let candidates: Vec<Integer> = match side {
+        Side::Right => [1, 3, 7, 9]
+            .into_iter()
+            .map(|d| Integer::from(node * 10u32) + d)
+            .collect(),
+        Side::Left => {
+            let digits = node.to_string().len() as u32;
+            let scale = Integer::from(10).pow(digits);
+            (1..=9u32)
+                .map(|d| Integer::from(&scale * d) + node)
+                .collect()
+        }
+    };
+    candidates.into_iter().filter(is_prime).collect()
+}
+
+/// Walk the tree below `roots` on `side`, stopping after `max_depth` levels if given
+pub fn explore(roots: Vec<Integer>, side: Side, max_depth: Option<usize>) -> Tree {
+    let mut levels = Vec::new();
+    let mut level = roots;
+    loop {
+        let mut next = Vec::new();
+        let mut leaves = 0;
+        for node in &level {
+            let found = children(node, side);
+            if found.is_empty() {
+                leaves += 1;
+            }
+            next.extend(found);
+        }
+        levels.push(Level {
+            digits: level[0].to_string().len(),
+            nodes: level.len(),
+            children: next.len(),
+            leaves,
+        });
+        if next.is_empty() || max_depth.is_some_and(|depth| levels.len() >= depth) {
+            let mut deepest = level;
+            deepest.sort();
+            return Tree { levels, deepest };
+        }
+        level = next;
+    }
+}
+
+/// `node` and every ancestor up to its root, root first
+pub fn path(node: &Integer, side: Side) -> Vec<Integer> {
+    let text = node.to_string();
+    (1..=text.len())
+        .map(|len| match side {
+            Side::Right => &text[..len],
+            Side::Left => &text[text.len() - len..],
+        })
+        .map(|digits| digits.parse().unwrap())
+        .collect()
+}
+
+/// Print the children of `node` (the roots for None) with the size of each subtree
+fn show_children(node: Option<&Integer>, side: Side, output: &mut impl Write) -> io::Result<()> {
+    let found = match node {
+        Some(node) => children(node, side),
+        None => roots(),
+    };
+    match node {
+        Some(node) => writeln!(output, "{}", node)?,
+        None => writeln!(output, "(roots)")?,
+    }
+    if found.is_empty() {
+        writeln!(output, "  no children: a leaf")?;
+    }
+    for child in found {
+        let below = explore(vec![child.clone()], side, None).size() - 1;
+        writeln!(output, "  {:<30} {} below", child, below)?;
+    }
+    Ok(())
+}
+
+/// Walk the tree from `start` (the roots for None) one digit at a time: each line of
+/// `input` is a digit to add on `side`, "u" to go back up or "q" to stop
+pub fn interactive(
+    start: Option<Integer>,
+    side: Side,
+    input: impl BufRead,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    let mut stack: Vec<Integer> = start.into_iter().collect();
+    show_children(stack.last(), side, output)?;
+    for line in input.lines() {
+        let line = line?;
+        let command = line.trim();
+        match command {
+            "q" | "quit" => break,
+            "u" | "up" => {
+                stack.pop();
+            }
+            _ => {
+                let Some(digit) = command.parse::<u32>().ok().filter(|&d| d <= 9) else {
+                    writeln!(output, "enter a digit, u to go up or q to quit")?;
+                    continue;
+                };
+                let next = match (stack.last(), side) {
+                    (None, _) => Integer::from(digit),
+                    (Some(node), Side::Right) => Integer::from(node * 10u32) + digit,
+                    (Some(node), Side::Left) => {
+                        let scale = Integer::from(10).pow(node.to_string().len() as u32);
+                        scale * digit + node
+                    }
+                };
+                if (digit == 0 && side == Side::Left) || !is_prime(&next) {
+                    writeln!(output, "{} is not prime", next)?;
+                    continue;
+                }
+                stack.push(next);
+            }
+        }
+        show_children(stack.last(), side, output)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_right_truncatable_tree() {
+        // A024770: 83 right-truncatable primes, the largest 73939133
+        let tree = explore(roots(), Side::Right, None);
+        assert_eq!(tree.size(), 83);
+        assert_eq!(tree.levels.len(), 8);
+        assert_eq!(
+            tree.deepest,
+            [23_399_339, 29_399_999, 37_337_999, 59_393_339, 73_939_133]
+        );
+        assert_eq!(tree.levels[0].children, 9);
+        assert_eq!(
+            path(&tree.deepest[4], Side::Right),
+            [7, 73, 739, 7393, 73939, 739391, 7393913, 73939133]
+        );
+    }
+
+    #[test]
+    fn test_left_children_and_depth_limit() {
+        assert_eq!(
+            children(&Integer::from(3), Side::Left),
+            [13, 23, 43, 53, 73, 83]
+        );
+        assert_eq!(
+            children(&Integer::from(7), Side::Left),
+            [17, 37, 47, 67, 97]
+        );
+        let tree = explore(roots(), Side::Left, Some(2));
+        assert_eq!(tree.levels.len(), 2);
+        // Two-digit left-truncatable primes: 13, 17, 23, 37, 43, 47, 53, 67, 73, 83, 97
+        assert_eq!(tree.deepest.len(), 11);
+        assert_eq!(path(&Integer::from(317), Side::Left), [7, 17, 317]);
+
+        // A024785: 4260 left-truncatable primes
+        let tree = explore(roots(), Side::Left, None);
+        assert_eq!(tree.size(), 4260);
+        assert_eq!(tree.levels.len(), 24);
+        assert_eq!(
+            tree.deepest,
+            ["357686312646216567629137".parse::<Integer>().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_interactive_walk() {
+        let mut output = Vec::new();
+        interactive(
+            None,
+            Side::Right,
+            "7\n3\n4\n9\nu\nq\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.starts_with("(roots)\n  2 "));
+        assert!(text.contains("\n7\n  71 "));
+        assert!(text.contains("734 is not prime"));
+        assert!(text.contains("\n739\n  7393 "));
+        assert!(text.contains("\n73\n  733 "));
+    }
+}
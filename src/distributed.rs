@@ -0,0 +1,558 @@
+// Distributed variation 9: a coordinator hands out segment ranges to workers over TCP
+//
+// The coordinator replaces the local producer of variation 9. It keeps the usual
+// consumers and feeds them SegmentPrimes received from workers on other machines.
+// Workers open one connection per thread and pull ranges until told they are done.
+// If a worker disconnects, or goes WORKER_TIMEOUT without sending anything, its unfinished
+// range goes back in the queue, and any segments that were already received are dropped
+// as duplicates.
+//
+// Frames are a u32 little-endian length followed by the payload: one message type byte,
+// then the message's numbers as little-endian u64s.
+
+use clap::ValueEnum;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::buffer_pool::BufferPool;
+use crate::primes::{
+    self, SEGMENT_PRIMES_CAPACITY, SEGMENT_SIZE_BITS, SEGMENT_SIZE_NUMBERS, SegmentPrimes,
+};
+use crate::progress;
+use crate::segment_format::{SegmentBuilder, UnpackedSegments};
+
+// Segments handed to a worker connection per request
+const SEGMENTS_PER_ASSIGNMENT: usize = 64;
+
+// Refuse frames larger than this (a segment of primes is ~256 KB)
+const MAX_FRAME_BYTES: usize = 64 * 1024 * 1024;
+
+// How long idle loops sleep before re-checking for work or connections
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// A worker sends a segment every fraction of a second while it has a range; one silent for
+// this long (frozen host, partitioned network) is treated as disconnected
+const WORKER_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Which side of a distributed run this process takes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DistributedRole {
+    /// Hand out segments, collect them and write the output files
+    Coordinator,
+    /// Sieve segments for a coordinator
+    Worker,
+}
+
+const MSG_JOB: u8 = 1;
+const MSG_REQUEST: u8 = 2;
+const MSG_ASSIGN: u8 = 3;
+const MSG_DONE: u8 = 4;
+const MSG_SEGMENT: u8 = 5;
+
+/// Messages exchanged between coordinator and workers
+#[derive(Debug, PartialEq, Eq)]
+enum Message {
+    /// Coordinator → worker on connect: what to sieve
+    Job { limit: usize, sqrt_limit: usize },
+    /// Worker → coordinator: ready for more segments
+    Request,
+    /// Coordinator → worker: sieve segments [first, first + count)
+    Assign { first: usize, count: usize },
+    /// Coordinator → worker: nothing left, disconnect
+    Done,
+    /// Worker → coordinator: primes of one segment
+    Segment {
+        segment_id: usize,
+        primes: Vec<usize>,
+    },
+}
+
+impl Message {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(self.kind());
+        let mut push = |value: usize| out.extend_from_slice(&(value as u64).to_le_bytes());
+        match self {
+            Message::Job { limit, sqrt_limit } => {
+                push(*limit);
+                push(*sqrt_limit);
+            }
+            Message::Request | Message::Done => {}
+            Message::Assign { first, count } => {
+                push(*first);
+                push(*count);
+            }
+            Message::Segment { segment_id, primes } => {
+                push(*segment_id);
+                for &prime in primes {
+                    push(prime);
+                }
+            }
+        }
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            Message::Job { .. } => MSG_JOB,
+            Message::Request => MSG_REQUEST,
+            Message::Assign { .. } => MSG_ASSIGN,
+            Message::Done => MSG_DONE,
+            Message::Segment { .. } => MSG_SEGMENT,
+        }
+    }
+
+    fn decode(payload: &[u8], pool: &BufferPool<usize>) -> io::Result<Self> {
+        let Some((&kind, body)) = payload.split_first() else {
+            return Err(protocol_error("empty frame"));
+        };
+        if !body.len().is_multiple_of(8) {
+            return Err(protocol_error("malformed frame"));
+        }
+        let mut words = body
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()) as usize);
+        let mut next = || words.next().ok_or_else(|| protocol_error("short frame"));
+
+        let message = match kind {
+            MSG_JOB => Message::Job {
+                limit: next()?,
+                sqrt_limit: next()?,
+            },
+            MSG_REQUEST => Message::Request,
+            MSG_ASSIGN => Message::Assign {
+                first: next()?,
+                count: next()?,
+            },
+            MSG_DONE => Message::Done,
+            MSG_SEGMENT => {
+                let segment_id = next()?;
+                let mut primes = pool.take(SEGMENT_PRIMES_CAPACITY);
+                primes.extend(words);
+                Message::Segment { segment_id, primes }
+            }
+            kind => return Err(protocol_error(&format!("unknown message type {}", kind))),
+        };
+        Ok(message)
+    }
+}
+
+fn protocol_error(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.to_string())
+}
+
+fn send(writer: &mut impl Write, message: &Message, buf: &mut Vec<u8>) -> io::Result<()> {
+    buf.clear();
+    message.encode(buf);
+    writer.write_all(&(buf.len() as u32).to_le_bytes())?;
+    writer.write_all(buf)?;
+    writer.flush()
+}
+
+fn receive(
+    reader: &mut impl Read,
+    buf: &mut Vec<u8>,
+    pool: &BufferPool<usize>,
+) -> io::Result<Message> {
+    let mut len = [0_u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME_BYTES {
+        return Err(protocol_error("frame too large"));
+    }
+    buf.resize(len, 0);
+    reader.read_exact(buf)?;
+    Message::decode(buf, pool)
+}
+
+/// Segment bookkeeping shared by all coordinator connections
+struct Work {
+    state: Mutex<WorkState>,
+}
+
+struct WorkState {
+    next_segment: usize,
+    total_segments: usize,
+    // Ranges given back by workers that disconnected before finishing them
+    requeued: Vec<Range<usize>>,
+    received: Vec<bool>,
+    remaining: usize,
+}
+
+impl Work {
+    fn new(total_segments: usize) -> Self {
+        Self {
+            state: Mutex::new(WorkState {
+                next_segment: 0,
+                total_segments,
+                requeued: Vec::new(),
+                received: vec![false; total_segments],
+                remaining: total_segments,
+            }),
+        }
+    }
+
+    /// Next range of segment indices to hand out, if any
+    fn claim(&self) -> Option<Range<usize>> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(range) = state.requeued.pop() {
+            return Some(range);
+        }
+        if state.next_segment >= state.total_segments {
+            return None;
+        }
+        let first = state.next_segment;
+        let end = (first + SEGMENTS_PER_ASSIGNMENT).min(state.total_segments);
+        state.next_segment = end;
+        Some(first..end)
+    }
+
+    /// Record a segment index; false if it was already received
+    fn mark_received(&self, idx: usize) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.received.get(idx) {
+            Some(false) => {
+                state.received[idx] = true;
+                state.remaining -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Put a range back unless every segment in it already arrived
+    fn requeue(&self, range: Range<usize>) {
+        let mut state = self.state.lock().unwrap();
+        if range.clone().any(|idx| !state.received[idx]) {
+            state.requeued.push(range);
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.state.lock().unwrap().remaining == 0
+    }
+}
+
+/// Run variation 9's producer side as a TCP coordinator listening on `listen`
+/// Segments from workers are routed to `senders` exactly like local workers would
+/// Returns the small primes (<= sqrt_limit) for the caller to save
+pub fn run_coordinator(
+    listen: &str,
+    limit: usize,
+    sqrt_limit: usize,
+    senders: Vec<SyncSender<SegmentPrimes>>,
+    total_sent: Arc<AtomicUsize>,
+    pool: BufferPool<usize>,
+) -> io::Result<Vec<usize>> {
+    let small_primes = primes::sieve(sqrt_limit);
+
+    // Same segment layout as find_primes_v9_multi_consumers
+    let low = (sqrt_limit + 1) | 1;
+    let total_segments = (limit - low + 1).div_ceil(SEGMENT_SIZE_NUMBERS);
+    let work = Work::new(total_segments);
+
+    let listener = TcpListener::bind(listen)?;
+    listener.set_nonblocking(true)?;
+    info!(
+        "Coordinator listening on {} for {} segments",
+        listener.local_addr()?,
+        total_segments
+    );
+
+    let workers_seen = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        while !work.is_complete() {
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    let connection_id = workers_seen.fetch_add(1, Ordering::Relaxed) + 1;
+                    debug!("Worker connection {} from {}", connection_id, peer);
+
+                    let work = &work;
+                    let senders = &senders;
+                    let total_sent = &total_sent;
+                    let pool = pool.clone();
+                    scope.spawn(move || {
+                        let mut outstanding = None;
+                        let result = serve_worker(
+                            stream,
+                            limit,
+                            sqrt_limit,
+                            work,
+                            senders,
+                            total_sent,
+                            &pool,
+                            &mut outstanding,
+                            WORKER_TIMEOUT,
+                        );
+                        if let Err(e) = result {
+                            warn!("Worker connection {} ({}) lost: {}", connection_id, peer, e);
+                            if let Some(range) = outstanding {
+                                work.requeue(range);
+                            }
+                        }
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) => warn!("Warning: accept failed: {}", e),
+            }
+        }
+    });
+
+    info!(
+        "Coordinator finished: {} segments from {} worker connections",
+        total_segments,
+        workers_seen.load(Ordering::Relaxed)
+    );
+    Ok(small_primes)
+}
+
+/// Hand out ranges to one worker connection and forward its segments
+/// `outstanding` holds the range the worker is currently sieving, for requeueing on error,
+/// including a TimedOut error once the worker sends nothing for `timeout`
+#[allow(clippy::too_many_arguments)]
+fn serve_worker(
+    stream: TcpStream,
+    limit: usize,
+    sqrt_limit: usize,
+    work: &Work,
+    senders: &[SyncSender<SegmentPrimes>],
+    total_sent: &AtomicUsize,
+    pool: &BufferPool<usize>,
+    outstanding: &mut Option<Range<usize>>,
+    timeout: Duration,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(timeout))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    let mut buf = Vec::new();
+
+    send(&mut writer, &Message::Job { limit, sqrt_limit }, &mut buf)?;
+
+    loop {
+        let message = receive(&mut reader, &mut buf, pool).map_err(|e| match e.kind() {
+            // Unix reports an expired read timeout as WouldBlock, Windows as TimedOut
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("nothing received for {}s", timeout.as_secs()),
+            ),
+            _ => e,
+        })?;
+        match message {
+            Message::Request => {
+                // The previous range (if any) is complete once the worker asks again
+                *outstanding = None;
+                loop {
+                    if let Some(range) = work.claim() {
+                        let assign = Message::Assign {
+                            first: range.start,
+                            count: range.len(),
+                        };
+                        *outstanding = Some(range);
+                        send(&mut writer, &assign, &mut buf)?;
+                        break;
+                    }
+                    // Other workers may still fail and give ranges back, so wait for completion
+                    if work.is_complete() {
+                        send(&mut writer, &Message::Done, &mut buf)?;
+                        return Ok(());
+                    }
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+            Message::Segment { segment_id, primes } => {
+                let idx = segment_id.wrapping_sub(1);
+                if !work.mark_received(idx) {
+                    pool.give_back(primes);
+                    continue;
+                }
+                // Route to consumer based on segment_id: segment S → consumer ((S-1) % N)
+                let segment = SegmentPrimes { primes, segment_id };
+                if senders[idx % senders.len()].send(segment).is_err() {
+                    return Err(io::Error::other("consumer stopped"));
+                }
+                total_sent.fetch_add(1, Ordering::Relaxed);
+                progress::inc(1);
+                crate::watchdog::wait_if_paused();
+            }
+            other => {
+                return Err(protocol_error(&format!("unexpected {:?}", other)));
+            }
+        }
+    }
+}
+
+/// Sieve segments for the coordinator at `coordinator` using `num_threads` connections
+/// Returns the number of segments this process sieved
+pub fn run_worker(coordinator: &str, num_threads: usize) -> io::Result<usize> {
+    let small_primes: OnceLock<Vec<usize>> = OnceLock::new();
+    let sieved = AtomicUsize::new(0);
+
+    let results: Vec<io::Result<()>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..num_threads)
+            .map(|_| scope.spawn(|| worker_connection(coordinator, &small_primes, &sieved)))
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    // Report the first failure, but only if no connection finished cleanly
+    if results.iter().all(|r| r.is_err()) {
+        results
+            .into_iter()
+            .find_map(Result::err)
+            .map_or(Ok(()), Err)?;
+    }
+    Ok(sieved.load(Ordering::Relaxed))
+}
+
+fn worker_connection(
+    coordinator: &str,
+    small_primes: &OnceLock<Vec<usize>>,
+    sieved: &AtomicUsize,
+) -> io::Result<()> {
+    let stream = TcpStream::connect(coordinator)?;
+    stream.set_nodelay(true)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    let mut buf = Vec::new();
+    let builder = UnpackedSegments {
+        pool: BufferPool::new(),
+    };
+
+    let Message::Job { limit, sqrt_limit } = receive(&mut reader, &mut buf, &builder.pool)? else {
+        return Err(protocol_error("expected job"));
+    };
+    let small_primes = small_primes.get_or_init(|| primes::sieve(sqrt_limit));
+    let low = (sqrt_limit + 1) | 1;
+    let mut segment = vec![0_u64; SEGMENT_SIZE_BITS.div_ceil(64)];
+
+    loop {
+        send(&mut writer, &Message::Request, &mut buf)?;
+        match receive(&mut reader, &mut buf, &builder.pool)? {
+            Message::Assign { first, count } => {
+                for segment_idx in first..first + count {
+                    let seg_low = low + segment_idx * SEGMENT_SIZE_NUMBERS;
+                    let seg_high = (seg_low + SEGMENT_SIZE_NUMBERS - 1).min(limit);
+                    primes::sieve_segment(small_primes, seg_low, seg_high, &mut segment);
+
+                    // Segment numbering starts at 1 (0 is reserved for small primes)
+                    let segment_primes =
+                        builder.build_from_bits(&segment, seg_low, seg_high, segment_idx + 1);
+                    let message = Message::Segment {
+                        segment_id: segment_primes.segment_id,
+                        primes: segment_primes.primes,
+                    };
+                    send(&mut writer, &message, &mut buf)?;
+                    if let Message::Segment { primes, .. } = message {
+                        builder.pool.give_back(primes);
+                    }
+                    sieved.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            Message::Done => return Ok(()),
+            other => return Err(protocol_error(&format!("unexpected {:?}", other))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_round_trip() {
+        let pool = BufferPool::new();
+        let messages = vec![
+            Message::Job {
+                limit: 1_000_000,
+                sqrt_limit: 1000,
+            },
+            Message::Request,
+            Message::Assign {
+                first: 128,
+                count: 64,
+            },
+            Message::Done,
+            Message::Segment {
+                segment_id: 7,
+                primes: vec![1_000_003, 1_000_033],
+            },
+        ];
+
+        for message in messages {
+            let mut wire = Vec::new();
+            send(&mut wire, &message, &mut Vec::new()).unwrap();
+            let decoded = receive(&mut wire.as_slice(), &mut Vec::new(), &pool).unwrap();
+            assert_eq!(decoded, message);
+        }
+    }
+
+    #[test]
+    fn test_requeue_skips_finished_ranges() {
+        let work = Work::new(3);
+        let range = work.claim().unwrap();
+        assert_eq!(range, 0..3);
+
+        assert!(work.mark_received(0));
+        assert!(!work.mark_received(0));
+        work.requeue(range);
+        assert_eq!(work.claim(), Some(0..3));
+
+        assert!(work.mark_received(1));
+        assert!(work.mark_received(2));
+        work.requeue(0..3);
+        assert_eq!(work.claim(), None);
+        assert!(work.is_complete());
+    }
+
+    #[test]
+    fn test_silent_worker_times_out_and_its_range_is_requeued() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // Claims a range, then stops responding with the connection still open
+        let worker = thread::spawn(move || {
+            let pool = BufferPool::new();
+            let stream = TcpStream::connect(address).unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = BufWriter::new(stream);
+            let mut buf = Vec::new();
+            receive(&mut reader, &mut buf, &pool).unwrap();
+            send(&mut writer, &Message::Request, &mut buf).unwrap();
+            let assigned = receive(&mut reader, &mut buf, &pool).unwrap();
+            thread::sleep(Duration::from_millis(500));
+            assigned
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let work = Work::new(3);
+        let (sender, _receiver) = std::sync::mpsc::sync_channel(1);
+        let mut outstanding = None;
+        let result = serve_worker(
+            stream,
+            10_000_000,
+            3_162,
+            &work,
+            &[sender],
+            &AtomicUsize::new(0),
+            &BufferPool::new(),
+            &mut outstanding,
+            Duration::from_millis(100),
+        );
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(
+            worker.join().unwrap(),
+            Message::Assign { first: 0, count: 3 }
+        );
+
+        assert_eq!(outstanding, Some(0..3));
+        work.requeue(outstanding.unwrap());
+        assert_eq!(work.claim(), Some(0..3));
+    }
+}
@@ -0,0 +1,143 @@
+// Ducci sequences (`nt ducci`)
+//
+// A Ducci step maps (a_1, ..., a_n) to (|a_1 − a_2|, |a_2 − a_3|, ..., |a_n − a_1|). The
+// entries never grow, so every sequence eventually repeats: for n a power of two it always
+// reaches all zeros, and for other n most starts fall into a cycle whose tuples use only 0
+// and one constant. Each run remembers the step at which every tuple was first seen, which
+// gives the exact step the sequence enters its cycle and the cycle's period. Scan mode runs
+// many random starts (random::Rng, seeded so runs can be repeated) and tallies how they
+// end.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::random::Rng;
+
+/// How a Ducci sequence ends
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// All zeros after `steps` steps
+    Zero { steps: usize },
+    /// The tuple at step `start` recurs every `period` steps (period >= 1, never all zeros)
+    Cycle { start: usize, period: usize },
+    /// Neither within the step limit
+    Unresolved,
+}
+
+/// One Ducci step
+pub fn step(tuple: &[u64]) -> Vec<u64> {
+    (0..tuple.len())
+        .map(|i| tuple[i].abs_diff(tuple[(i + 1) % tuple.len()]))
+        .collect()
+}
+
+/// The tuples from `start` until the sequence reaches zero, closes a cycle (the repeated
+/// tuple is included once more) or takes `max_steps` steps, and how it ended
+pub fn run(start: &[u64], max_steps: usize) -> (Vec<Vec<u64>>, Outcome) {
+    let mut seen: HashMap<Vec<u64>, usize> = HashMap::new();
+    let mut trajectory = vec![start.to_vec()];
+    let mut current = start.to_vec();
+    for steps in 0..=max_steps {
+        if current.iter().all(|&x| x == 0) {
+            return (trajectory, Outcome::Zero { steps });
+        }
+        if let Some(&first) = seen.get(&current) {
+            let outcome = Outcome::Cycle {
+                start: first,
+                period: steps - first,
+            };
+            return (trajectory, outcome);
+        }
+        if steps == max_steps {
+            break;
+        }
+        seen.insert(current.clone(), steps);
+        current = step(&current);
+        trajectory.push(current.clone());
+    }
+    (trajectory, Outcome::Unresolved)
+}
+
+/// Tallies over random starting tuples
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ScanStats {
+    pub tuples: usize,
+    pub zero: usize,
+    /// Steps to zero, summed over the starts that got there
+    pub zero_steps_total: usize,
+    pub zero_steps_max: usize,
+    /// Starts per cycle period
+    pub cycles: BTreeMap<usize, usize>,
+    /// Most steps before entering a cycle
+    pub cycle_start_max: usize,
+    pub unresolved: usize,
+}
+
+/// Run `count` random tuples of `length` entries below `max_value`
+pub fn scan(count: usize, length: usize, max_value: u64, max_steps: usize, seed: u64) -> ScanStats {
+    let mut rng = Rng::new(seed);
+    let mut stats = ScanStats {
+        tuples: count,
+        ..ScanStats::default()
+    };
+    for _ in 0..count {
+        let start: Vec<u64> = (0..length).map(|_| rng.below(max_value)).collect();
+        match run(&start, max_steps).1 {
+            Outcome::Zero { steps } => {
+                stats.zero += 1;
+                stats.zero_steps_total += steps;
+                stats.zero_steps_max = stats.zero_steps_max.max(steps);
+            }
+            Outcome::Cycle { start, period } => {
+                *stats.cycles.entry(period).or_default() += 1;
+                stats.cycle_start_max = stats.cycle_start_max.max(start);
+            }
+            Outcome::Unresolved => stats.unresolved += 1,
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_four_tuple_reaches_zero() {
+        let (trajectory, outcome) = run(&[1, 5, 9, 3], 100);
+        assert_eq!(outcome, Outcome::Zero { steps: 4 });
+        assert_eq!(
+            trajectory,
+            [
+                vec![1, 5, 9, 3],
+                vec![4, 4, 6, 2],
+                vec![0, 2, 4, 2],
+                vec![2, 2, 2, 2],
+                vec![0, 0, 0, 0]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_three_tuple_cycles() {
+        // (0, 1, 1) → (1, 0, 1) → (1, 1, 0) → (0, 1, 1)
+        let (trajectory, outcome) = run(&[0, 1, 1], 100);
+        assert_eq!(
+            outcome,
+            Outcome::Cycle {
+                start: 0,
+                period: 3
+            }
+        );
+        assert_eq!(trajectory.len(), 4);
+        assert_eq!(run(&[1, 2, 4], 1).1, Outcome::Unresolved);
+    }
+
+    #[test]
+    fn test_scan_powers_of_two_always_reach_zero() {
+        let stats = scan(500, 8, 1000, 1000, 7);
+        assert_eq!(stats.zero, 500);
+        assert_eq!(scan(500, 8, 1000, 1000, 7), stats);
+        let stats = scan(500, 5, 1000, 1000, 7);
+        assert_eq!(stats.zero + stats.cycles.values().sum::<usize>(), 500);
+    }
+}
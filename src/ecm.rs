@@ -0,0 +1,264 @@
+// Lenstra's elliptic curve method (`nt factor --algorithm ecm`)
+//
+// Each curve is a Montgomery curve By² = x³ + Ax² + x over Z/nZ built from Suyama's
+// parametrization, worked in projective (X:Z) coordinates so no inversions are needed.
+// Stage 1 multiplies a point by every prime power up to B1; if the curve's group order
+// mod some prime p | n is B1-smooth, the point hits infinity mod p and gcd(Z, n) finds p.
+// Stage 2 catches orders with one extra prime q in (B1, B2] using baby steps j·Q and giant
+// steps mD·Q (q = mD ± j), multiplying up X_m·Z_j − X_j·Z_m for a single gcd at the end.
+// Curves are independent, so workers run them in parallel and stop at the first factor.
+
+use rug::Integer;
+use rug::ops::RemRounding;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use tracing::debug;
+
+// Giant step size for stage 2 (2·3·5·7·11); stage 1 always runs at least this far
+const GIANT_STEP: usize = 2310;
+
+// How many primes to process between checks for another worker's factor
+const STOP_CHECK_INTERVAL: usize = 4096;
+
+/// Bounds and effort for one ECM run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EcmParams {
+    pub b1: usize,
+    pub b2: usize,
+    pub curves: usize,
+    pub workers: usize,
+}
+
+impl EcmParams {
+    /// B2 = 100 × B1 unless given
+    pub fn new(b1: usize, b2: Option<usize>, curves: usize, workers: usize) -> Self {
+        let b1 = b1.max(GIANT_STEP);
+        Self {
+            b1,
+            b2: b2.unwrap_or(b1.saturating_mul(100)).max(b1),
+            curves,
+            workers: workers.max(1),
+        }
+    }
+}
+
+/// Point in projective (X:Z) coordinates; Z = 0 is the point at infinity
+#[derive(Clone, Debug)]
+struct Point {
+    x: Integer,
+    z: Integer,
+}
+
+/// Montgomery curve over Z/nZ, stored as (A + 2) / 4
+struct Curve<'a> {
+    n: &'a Integer,
+    a24: Integer,
+}
+
+/// What a single curve found
+enum CurveResult {
+    Factor(Integer),
+    Nothing,
+    Stopped,
+}
+
+impl Curve<'_> {
+    #[inline]
+    fn reduce(&self, x: Integer) -> Integer {
+        x.rem_euc(self.n)
+    }
+
+    /// 2P
+    fn double(&self, p: &Point) -> Point {
+        let sum = self.reduce(Integer::from(&p.x + &p.z).square());
+        let diff = self.reduce(Integer::from(&p.x - &p.z).square());
+        let t = Integer::from(&sum - &diff);
+        let x = self.reduce(Integer::from(&sum * &diff));
+        let z = self.reduce(self.reduce(Integer::from(&self.a24 * &t)) + diff);
+        let z = self.reduce(z * t);
+        Point { x, z }
+    }
+
+    /// P + Q, given P − Q
+    fn add(&self, p: &Point, q: &Point, difference: &Point) -> Point {
+        let u = self.reduce(Integer::from(&p.x - &p.z) * Integer::from(&q.x + &q.z));
+        let v = self.reduce(Integer::from(&p.x + &p.z) * Integer::from(&q.x - &q.z));
+        let sum = self.reduce(Integer::from(&u + &v).square());
+        let diff = self.reduce(Integer::from(&u - &v).square());
+        Point {
+            x: self.reduce(sum * &difference.z),
+            z: self.reduce(diff * &difference.x),
+        }
+    }
+
+    /// kP by the Montgomery ladder (k >= 1)
+    fn multiply(&self, p: &Point, k: u64) -> Point {
+        let mut r0 = p.clone();
+        let mut r1 = self.double(p);
+        for bit in (0..63 - k.leading_zeros()).rev() {
+            if (k >> bit) & 1 == 1 {
+                r0 = self.add(&r1, &r0, p);
+                r1 = self.double(&r1);
+            } else {
+                r1 = self.add(&r0, &r1, p);
+                r0 = self.double(&r0);
+            }
+        }
+        r0
+    }
+}
+
+/// Curve and starting point for Suyama parameter `sigma`, or a factor found while building it
+fn suyama_curve(n: &Integer, sigma: u64) -> Result<(Curve<'_>, Point), Option<Integer>> {
+    let sigma = Integer::from(sigma);
+    let u = (Integer::from(sigma.square_ref()) - 5u32).rem_euc(n);
+    let v = (sigma * 4u32).rem_euc(n);
+
+    let u3 = Integer::from(u.pow_mod_ref(&Integer::from(3), n).unwrap());
+    let v3 = Integer::from(v.pow_mod_ref(&Integer::from(3), n).unwrap());
+    let numerator = (Integer::from(&v - &u)
+        .pow_mod(&Integer::from(3), n)
+        .unwrap()
+        * (Integer::from(&u * 3u32) + &v))
+        .rem_euc(n);
+    let denominator = (Integer::from(&u3 * &v) * 16u32).rem_euc(n);
+
+    // A non-invertible denominator shares a factor with n
+    let inverse = match denominator.clone().invert(n) {
+        Ok(inverse) => inverse,
+        Err(_) => {
+            let g = denominator.gcd(n);
+            return Err((g != 1 && &g != n).then_some(g));
+        }
+    };
+    let a24 = (numerator * inverse).rem_euc(n);
+    Ok((Curve { n, a24 }, Point { x: u3, z: v3 }))
+}
+
+/// Run one curve through both stages
+fn run_curve(
+    n: &Integer,
+    sigma: u64,
+    params: &EcmParams,
+    primes: &[usize],
+    stop: &AtomicBool,
+) -> CurveResult {
+    let (curve, mut q) = match suyama_curve(n, sigma) {
+        Ok(curve) => curve,
+        Err(Some(factor)) => return CurveResult::Factor(factor),
+        Err(None) => return CurveResult::Nothing,
+    };
+
+    // Stage 1: multiply by the largest power of each prime <= B1
+    let stage1_end = primes.partition_point(|&p| p <= params.b1);
+    for (i, &p) in primes[..stage1_end].iter().enumerate() {
+        if i % STOP_CHECK_INTERVAL == 0 && stop.load(Ordering::Relaxed) {
+            return CurveResult::Stopped;
+        }
+        let mut power = p as u64;
+        while power * p as u64 <= params.b1 as u64 {
+            power *= p as u64;
+        }
+        q = curve.multiply(&q, power);
+    }
+    match check(n, &q.z) {
+        CurveResult::Nothing => {}
+        found => return found,
+    }
+
+    // Stage 2 baby steps: jQ for odd j < D/2 (q = mD ± j is odd, so j is too)
+    let half = GIANT_STEP / 2;
+    let mut baby: Vec<Point> = Vec::with_capacity(half / 2 + 1);
+    let q2 = curve.double(&q);
+    baby.push(q.clone());
+    baby.push(curve.add(&q2, &q, &q));
+    while baby.len() * 2 <= half {
+        let len = baby.len();
+        let next = curve.add(&baby[len - 1], &q2, &baby[len - 2]);
+        baby.push(next);
+    }
+
+    // Giant steps: mDQ from two consecutive ladder results, then by differential addition
+    let stage2 = &primes[stage1_end..primes.partition_point(|&p| p <= params.b2)];
+    let Some(&first) = stage2.first() else {
+        return CurveResult::Nothing;
+    };
+    // m >= 1 because stage 2 primes are above B1 >= D
+    let giant = curve.multiply(&q, GIANT_STEP as u64);
+    let mut m = (first + half) / GIANT_STEP;
+    let mut current = curve.multiply(&q, (m * GIANT_STEP) as u64);
+    let mut next = curve.multiply(&q, ((m + 1) * GIANT_STEP) as u64);
+
+    let mut product = Integer::from(1);
+    for (i, &p) in stage2.iter().enumerate() {
+        if i % STOP_CHECK_INTERVAL == 0 && stop.load(Ordering::Relaxed) {
+            return CurveResult::Stopped;
+        }
+        let target = (p + half) / GIANT_STEP;
+        while m < target {
+            let following = curve.add(&next, &giant, &current);
+            current = std::mem::replace(&mut next, following);
+            m += 1;
+        }
+        let j = (m * GIANT_STEP).abs_diff(p);
+        let b = &baby[j / 2];
+        let cross = Integer::from(&current.x * &b.z) - Integer::from(&b.x * &current.z);
+        product = curve.reduce(product * cross);
+    }
+    check(n, &product)
+}
+
+/// Factor from gcd(value, n), if it is a proper one
+fn check(n: &Integer, value: &Integer) -> CurveResult {
+    let g = Integer::from(value.gcd_ref(n));
+    if g != 1 && &g != n {
+        CurveResult::Factor(g)
+    } else {
+        CurveResult::Nothing
+    }
+}
+
+/// Suyama parameter for curve `index`: spread over [6, 2^32) by a splitmix64 step
+fn sigma_for(index: usize) -> u64 {
+    let mut z = (index as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    6 + z % ((1 << 32) - 6)
+}
+
+/// A proper factor of the composite `n`, or None if no curve found one
+pub fn find_factor(n: &Integer, params: &EcmParams) -> Option<Integer> {
+    let primes = crate::bigfactor::primes_up_to(params.b2);
+    let next_curve = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let found: Mutex<Option<Integer>> = Mutex::new(None);
+
+    thread::scope(|scope| {
+        for _ in 0..params.workers {
+            scope.spawn(|| {
+                while !stop.load(Ordering::Relaxed) {
+                    let index = next_curve.fetch_add(1, Ordering::Relaxed);
+                    if index >= params.curves {
+                        break;
+                    }
+                    let sigma = sigma_for(index);
+                    if let CurveResult::Factor(factor) = run_curve(n, sigma, params, &primes, &stop)
+                    {
+                        debug!(
+                            "ECM curve {} (sigma = {}) found {}",
+                            index + 1,
+                            sigma,
+                            factor
+                        );
+                        stop.store(true, Ordering::Relaxed);
+                        found.lock().unwrap().get_or_insert(factor);
+                    }
+                }
+            });
+        }
+    });
+
+    found.into_inner().unwrap()
+}
@@ -0,0 +1,289 @@
+use arrow_array::{RecordBatch, UInt64Array};
+use arrow_schema::{DataType, Field, Schema};
+use clap::ValueEnum;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::storage;
+
+// Number of primes per Arrow record batch (8 MB of u64 values)
+const BATCH_SIZE: usize = 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    /// Apache Parquet (single non-null UInt64 column named "prime")
+    Parquet,
+    /// A directory of text files, one per --range-size numbers, named <low>-<high>.txt
+    TextRanges,
+    /// One prime per line, compressed by the system's gzip
+    GzipText,
+    /// 4 bytes per prime, little-endian; every prime must be below 2^32
+    U32le,
+}
+
+pub fn run(format: ExportFormat, output: &Path, binary: bool, range_size: usize) {
+    let primes = match storage::stream_primes(binary) {
+        Ok(primes) => primes,
+        Err(e) => {
+            let source = if binary { "primes.bin" } else { "primes.txt" };
+            error!("Error opening {}: {}", source, e);
+            return;
+        }
+    };
+
+    let result = match format {
+        ExportFormat::Parquet => export_parquet(primes, output),
+        ExportFormat::TextRanges => export_text_ranges(primes, output, range_size),
+        ExportFormat::GzipText => export_gzip_text(primes, output),
+        ExportFormat::U32le => export_u32le(primes, output),
+    };
+
+    match result {
+        Ok(count) => info!("Exported {} primes to {}", count, output.display()),
+        Err(e) => error!("Error exporting to {}: {}", output.display(), e),
+    }
+}
+
+/// Write primes to a Parquet file in fixed-size record batches
+/// Memory stays bounded by BATCH_SIZE regardless of the input size; the file is staged and
+/// only moved over `output` once the writer has closed it
+/// Returns the count of primes written
+fn export_parquet(
+    primes: impl Iterator<Item = io::Result<usize>>,
+    output: &Path,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let schema = Arc::new(Schema::new(vec![Field::new(
+        "prime",
+        DataType::UInt64,
+        false,
+    )]));
+
+    let props = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+
+    let file = File::create(storage::staging_path(output))?;
+    let mut writer = ArrowWriter::try_new(file, Arc::clone(&schema), Some(props))?;
+
+    let mut count = 0;
+    let mut batch: Vec<u64> = Vec::with_capacity(BATCH_SIZE);
+
+    for prime in primes {
+        batch.push(prime? as u64);
+
+        if batch.len() == BATCH_SIZE {
+            count += write_batch(&mut writer, &schema, &mut batch)?;
+        }
+    }
+
+    if !batch.is_empty() {
+        count += write_batch(&mut writer, &schema, &mut batch)?;
+    }
+
+    writer.close()?;
+    storage::commit_output(output)?;
+    Ok(count)
+}
+
+fn write_batch(
+    writer: &mut ArrowWriter<File>,
+    schema: &Arc<Schema>,
+    batch: &mut Vec<u64>,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let len = batch.len();
+    let column = UInt64Array::from(std::mem::take(batch));
+    let record_batch = RecordBatch::try_new(Arc::clone(schema), vec![Arc::new(column)])?;
+
+    writer.write(&record_batch)?;
+    batch.reserve(BATCH_SIZE);
+
+    Ok(len)
+}
+
+/// Write primes to text files covering [k·range_size, (k + 1)·range_size − 1] in the
+/// directory `output`; ranges without primes get no file
+/// Each file is staged and moved into place once its range is complete
+fn export_text_ranges(
+    primes: impl Iterator<Item = io::Result<usize>>,
+    output: &Path,
+    range_size: usize,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    if range_size == 0 {
+        return Err("--range-size must be positive".into());
+    }
+    fs::create_dir_all(output)?;
+    let mut itoa_buf = itoa::Buffer::new();
+    let mut current: Option<(usize, PathBuf, BufWriter<File>)> = None;
+    let finish = |path: PathBuf, mut writer: BufWriter<File>| -> io::Result<()> {
+        writer.flush()?;
+        drop(writer);
+        storage::commit_output(&path)
+    };
+    let mut count = 0;
+    for prime in primes {
+        let prime = prime?;
+        let range = prime / range_size;
+        if current.as_ref().is_none_or(|&(open, _, _)| open != range) {
+            if let Some((_, path, writer)) = current.take() {
+                finish(path, writer)?;
+            }
+            let low = range * range_size;
+            let high = low.saturating_add(range_size - 1);
+            let path = output.join(format!("{}-{}.txt", low, high));
+            let file = File::create(storage::staging_path(&path))?;
+            current = Some((range, path, BufWriter::with_capacity(256 * 1024, file)));
+        }
+        let (_, _, writer) = current.as_mut().unwrap();
+        writer.write_all(itoa_buf.format(prime).as_bytes())?;
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    if let Some((_, path, writer)) = current {
+        finish(path, writer)?;
+    }
+    Ok(count)
+}
+
+/// Write primes as text through `gzip -c`, so no compression library is linked in
+fn export_gzip_text(
+    primes: impl Iterator<Item = io::Result<usize>>,
+    output: &Path,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut gzip = Command::new("gzip")
+        .arg("-c")
+        .stdin(Stdio::piped())
+        .stdout(File::create(storage::staging_path(output))?)
+        .spawn()
+        .map_err(|e| format!("could not run gzip: {}", e))?;
+    let mut writer = BufWriter::with_capacity(256 * 1024, gzip.stdin.take().unwrap());
+    let mut itoa_buf = itoa::Buffer::new();
+    let mut count = 0;
+    for prime in primes {
+        writer.write_all(itoa_buf.format(prime?).as_bytes())?;
+        writer.write_all(b"\n")?;
+        count += 1;
+    }
+    writer.flush()?;
+    drop(writer);
+    let status = gzip.wait()?;
+    if !status.success() {
+        return Err(format!("gzip failed ({})", status).into());
+    }
+    storage::commit_output(output)?;
+    Ok(count)
+}
+
+/// Write primes as little-endian u32, half the size of primes.bin
+/// A prime past u32 removes the partial file and fails, rather than wrapping silently,
+/// and leaves any earlier export at `output` as it was
+fn export_u32le(
+    primes: impl Iterator<Item = io::Result<usize>>,
+    output: &Path,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let staged = storage::staging_path(output);
+    let mut writer = BufWriter::with_capacity(256 * 1024, File::create(&staged)?);
+    let mut count = 0;
+    for prime in primes {
+        let prime = prime?;
+        let Ok(value) = u32::try_from(prime) else {
+            drop(writer);
+            let _ = fs::remove_file(&staged);
+            return Err(format!(
+                "u32le holds primes below 2^32 = 4294967296, and the primes go on to {}",
+                prime
+            )
+            .into());
+        };
+        writer.write_all(&value.to_le_bytes())?;
+        count += 1;
+    }
+    writer.flush()?;
+    drop(writer);
+    storage::commit_output(output)?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scratch(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("nt_export_{}_{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_text_ranges_split_by_range() {
+        let dir = scratch("ranges");
+        let primes = crate::primes::sieve(100);
+        assert_eq!(
+            export_text_ranges(primes.into_iter().map(Ok), &dir, 30).unwrap(),
+            25
+        );
+        let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("0-29.txt"), "2\n3\n5\n7\n11\n13\n17\n19\n23\n29\n");
+        assert_eq!(read("90-119.txt"), "97\n");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 4);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parquet_round_trip() {
+        use arrow_array::Array;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let path = scratch("primes.parquet");
+        let primes = crate::primes::sieve(100);
+        assert_eq!(
+            export_parquet(primes.iter().copied().map(Ok), &path).unwrap(),
+            25
+        );
+        assert!(!storage::staging_path(&path).exists());
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let mut read = Vec::new();
+        for batch in reader {
+            let batch = batch.unwrap();
+            assert_eq!(batch.schema().field(0).name(), "prime");
+            let column = batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<UInt64Array>()
+                .unwrap();
+            assert_eq!(column.null_count(), 0);
+            read.extend(column.values().iter().map(|&p| p as usize));
+        }
+        assert_eq!(read, primes);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_u32le_rejects_primes_past_u32() {
+        let path = scratch("u32.bin");
+        assert_eq!(
+            export_u32le([2, 3, 5].into_iter().map(Ok), &path).unwrap(),
+            3
+        );
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            [2, 0, 0, 0, 3, 0, 0, 0, 5, 0, 0, 0]
+        );
+        let err = export_u32le([2, 4_294_967_311].into_iter().map(Ok), &path).unwrap_err();
+        assert!(err.to_string().contains("4294967311"));
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            [2, 0, 0, 0, 3, 0, 0, 0, 5, 0, 0, 0]
+        );
+        assert!(!storage::staging_path(&path).exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
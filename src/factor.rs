@@ -0,0 +1,187 @@
+// Integer factorization for u64
+//
+// Trial division strips the small factors, a deterministic Miller-Rabin test decides
+// when a cofactor is prime, and Pollard's rho (Brent's variant) splits what is left.
+// Every u64 factors in well under a second this way.
+
+// Trial division bound; rho is only used for cofactors without factors below this
+const TRIAL_DIVISION_LIMIT: u64 = 1000;
+
+// Miller-Rabin with these bases is deterministic for every n < 2^64
+const MILLER_RABIN_BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+
+/// Prime factorization of `n` as (prime, exponent) pairs in increasing prime order
+/// 0 and 1 have no prime factors and return an empty list
+pub fn factorize(n: u64) -> Vec<(u64, u32)> {
+    let mut factors = Vec::new();
+    if n < 2 {
+        return factors;
+    }
+
+    let mut n = n;
+    let mut d = 2;
+    while d < TRIAL_DIVISION_LIMIT && d * d <= n {
+        if n.is_multiple_of(d) {
+            let mut exp = 0;
+            while n.is_multiple_of(d) {
+                n /= d;
+                exp += 1;
+            }
+            factors.push((d, exp));
+        }
+        d += if d == 2 { 1 } else { 2 };
+    }
+
+    // Split what trial division left into primes
+    let mut stack = vec![n];
+    let mut large = Vec::new();
+    while let Some(m) = stack.pop() {
+        if m == 1 {
+            continue;
+        }
+        if is_prime(m) {
+            large.push(m);
+            continue;
+        }
+        let divisor = pollard_rho(m);
+        stack.push(divisor);
+        stack.push(m / divisor);
+    }
+
+    large.sort_unstable();
+    for p in large {
+        match factors.last_mut() {
+            Some((last, exp)) if *last == p => *exp += 1,
+            _ => factors.push((p, 1)),
+        }
+    }
+
+    factors
+}
+
+/// Deterministic primality test for any u64
+pub fn is_prime(n: u64) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for &p in &MILLER_RABIN_BASES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    // n - 1 = d × 2^s with d odd
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+
+    'bases: for &a in &MILLER_RABIN_BASES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+#[inline]
+pub(crate) fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// A non-trivial divisor of the odd composite `n` (Brent's cycle detection)
+fn pollard_rho(n: u64) -> u64 {
+    if n.is_multiple_of(2) {
+        return 2;
+    }
+
+    // Retry with a different polynomial x² + c whenever a cycle yields n itself
+    for c in 1.. {
+        let f = |x: u64| (mul_mod(x, x, n) + c) % n;
+        let mut y = 2;
+        let mut power = 1;
+        let mut lam = 1;
+        let mut x = y;
+        let mut divisor = 1;
+
+        while divisor == 1 {
+            if power == lam {
+                x = y;
+                power *= 2;
+                lam = 0;
+            }
+            y = f(y);
+            lam += 1;
+            divisor = gcd(x.abs_diff(y), n);
+        }
+
+        if divisor != n {
+            return divisor;
+        }
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_factorize() {
+        assert!(factorize(1).is_empty());
+        assert_eq!(factorize(360), vec![(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(factorize(3003289), vec![(1733, 2)]);
+        // Two large primes that trial division cannot reach
+        assert_eq!(
+            factorize(1_000_000_007 * 998_244_353),
+            vec![(998_244_353, 1), (1_000_000_007, 1)]
+        );
+        assert_eq!(
+            factorize(u64::MAX),
+            vec![
+                (3, 1),
+                (5, 1),
+                (17, 1),
+                (257, 1),
+                (641, 1),
+                (65537, 1),
+                (6700417, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_prime() {
+        assert!(!is_prime(0));
+        assert!(!is_prime(1));
+        assert!(is_prime(2));
+        assert!(is_prime(18_446_744_073_709_551_557)); // Largest prime below 2^64
+        assert!(!is_prime(3_215_031_751)); // Strong pseudoprime to bases 2, 3, 5, 7
+    }
+}
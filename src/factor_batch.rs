@@ -0,0 +1,242 @@
+// Batch factoring (`nt factor --stdin`)
+//
+// One integer per input line; blank lines are skipped. The reading thread numbers lines
+// and hands them to a pool of workers over a bounded channel, so memory stays flat however
+// long the input is. Workers factor independently (each cofactor's ECM runs on its own
+// worker rather than spreading curves over threads) and send back a JSON line, which the
+// writer puts back in input order before printing. A slow number holds up output, not work.
+
+use rug::Integer;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::bigfactor::{self, FactorOptions, Factorization};
+use crate::storage_writer::Reorder;
+
+// Lines buffered between the reader, the workers and the writer
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Counts for the closing log line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub numbers: usize,
+    /// Numbers with a composite cofactor nothing could split
+    pub incomplete: usize,
+    /// Lines that were not non-negative integers
+    pub invalid: usize,
+}
+
+/// One line's outcome
+enum Outcome {
+    Factored(String),
+    Incomplete(String),
+    Invalid(String),
+}
+
+/// Factor every line of `input` on `num_workers` threads, writing JSON lines to `output`
+pub fn run(
+    input: impl BufRead,
+    mut output: impl Write + Send,
+    options: &FactorOptions,
+    num_workers: usize,
+) -> io::Result<BatchSummary> {
+    let (line_tx, line_rx) = mpsc::sync_channel::<(usize, String)>(CHANNEL_CAPACITY);
+    let (result_tx, result_rx) = mpsc::sync_channel::<(usize, Outcome)>(CHANNEL_CAPACITY);
+    // Owned by the workers, so once they all stop the reader's sends fail instead of blocking
+    let line_rx = Arc::new(Mutex::new(line_rx));
+
+    thread::scope(|scope| {
+        for _ in 0..num_workers.max(1) {
+            let (line_rx, result_tx) = (Arc::clone(&line_rx), result_tx.clone());
+            scope.spawn(move || {
+                loop {
+                    // The guard drops at the end of this statement, before the work
+                    let next = line_rx.lock().unwrap().recv();
+                    let Ok((index, line)) = next else {
+                        break;
+                    };
+                    if result_tx
+                        .send((index, factor_line(&line, options)))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(line_rx);
+        drop(result_tx);
+
+        let writer = scope.spawn(move || -> io::Result<BatchSummary> {
+            let mut summary = BatchSummary::default();
+            let mut reorder = Reorder::new(0, 1);
+            for (index, outcome) in result_rx {
+                reorder.insert(index, outcome);
+                while let Some(outcome) = reorder.pop_ready() {
+                    let json = match outcome {
+                        Outcome::Factored(json) => json,
+                        Outcome::Incomplete(json) => {
+                            summary.incomplete += 1;
+                            json
+                        }
+                        Outcome::Invalid(json) => {
+                            summary.invalid += 1;
+                            json
+                        }
+                    };
+                    summary.numbers += 1;
+                    writeln!(output, "{}", json)?;
+                }
+            }
+            output.flush()?;
+            Ok(summary)
+        });
+
+        let mut index = 0;
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Workers gone means the writer failed; its error is reported below
+            if line_tx.send((index, line)).is_err() {
+                break;
+            }
+            index += 1;
+        }
+        drop(line_tx);
+
+        writer.join().unwrap()
+    })
+}
+
+fn factor_line(line: &str, options: &FactorOptions) -> Outcome {
+    let line = line.trim();
+    match line.parse::<Integer>() {
+        Ok(n) if n >= 0 => {
+            let result = bigfactor::factorize(&n, options);
+            let json = to_json(&n, &result);
+            if result.is_complete() {
+                Outcome::Factored(json)
+            } else {
+                Outcome::Incomplete(json)
+            }
+        }
+        _ => Outcome::Invalid(format!(
+            "{{\"input\":\"{}\",\"error\":\"not a non-negative integer\"}}",
+            escape(line)
+        )),
+    }
+}
+
+/// One JSON object for `n`; numbers are strings since most parsers stop at 2^53
+pub fn to_json(n: &Integer, result: &Factorization) -> String {
+    let mut json = format!("{{\"n\":\"{}\",\"factors\":[", n);
+    for (i, (p, exp)) in result.factors.iter().enumerate() {
+        let comma = if i == 0 { "" } else { "," };
+        let _ = write!(json, "{}{{\"p\":\"{}\",\"e\":{}}}", comma, p, exp);
+    }
+    json.push(']');
+    if !result.unfactored.is_empty() {
+        let composites: Vec<String> = result
+            .unfactored
+            .iter()
+            .map(|c| format!("\"{}\"", c))
+            .collect();
+        let _ = write!(json, ",\"unfactored\":[{}]", composites.join(","));
+    }
+    if !result.squares.is_empty() {
+        let squares: Vec<String> = result
+            .squares
+            .iter()
+            .map(|(m, s)| format!("{{\"n\":\"{}\",\"a\":\"{}\",\"b\":\"{}\"}}", m, s.a, s.b))
+            .collect();
+        let _ = write!(json, ",\"squares\":[{}]", squares.join(","));
+    }
+    let _ = write!(json, ",\"complete\":{}}}", result.is_complete());
+    json
+}
+
+/// JSON string escaping for echoed input
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bigfactor::FactorAlgorithm;
+    use crate::ecm::EcmParams;
+
+    fn options() -> FactorOptions {
+        FactorOptions {
+            algorithm: FactorAlgorithm::Auto,
+            rho_iterations: 1 << 20,
+            fermat_steps: 1 << 20,
+            pm1_b1: 1000,
+            pm1_b2: 100_000,
+            ecm: EcmParams::new(3000, None, 40, 1),
+        }
+    }
+
+    #[test]
+    fn test_output_keeps_input_order() {
+        let options = options();
+        let input = "360\n\n  97 \nabc\"\n1\n100000000000000000039\n";
+        let mut output = Vec::new();
+        let summary = run(input.as_bytes(), &mut output, &options, 3).unwrap();
+
+        assert_eq!(
+            summary,
+            BatchSummary {
+                numbers: 5,
+                incomplete: 0,
+                invalid: 1
+            }
+        );
+        let lines: Vec<&str> = std::str::from_utf8(&output).unwrap().lines().collect();
+        assert_eq!(
+            lines,
+            [
+                r#"{"n":"360","factors":[{"p":"2","e":3},{"p":"3","e":2},{"p":"5","e":1}],"complete":true}"#,
+                r#"{"n":"97","factors":[{"p":"97","e":1}],"complete":true}"#,
+                r#"{"input":"abc\"","error":"not a non-negative integer"}"#,
+                r#"{"n":"1","factors":[],"complete":true}"#,
+                r#"{"n":"100000000000000000039","factors":[{"p":"100000000000000000039","e":1}],"complete":true}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failing_writer_stops_the_reader() {
+        // Closed like a pipe whose reader exited
+        struct Closed;
+        impl Write for Closed {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        // Far more lines than the channels hold, so the reader would block if nobody read
+        let input = "12\n".repeat(20 * CHANNEL_CAPACITY);
+        let err = run(input.as_bytes(), Closed, &options(), 2).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+}
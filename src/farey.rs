@@ -0,0 +1,196 @@
+// Farey sequences (`nt farey`)
+//
+// F_n is every fraction in [0, 1] with denominator at most n, in increasing order:
+// F_5 = 0/1 1/5 1/4 1/3 2/5 1/2 3/5 2/3 3/4 4/5 1/1. Neighbours a/b < c/d in F_n satisfy
+// bc − ad = 1, so each term follows from the two before it, (k c − a)/(k d − b) with
+// k = ⌊(n + b)/d⌋, and the sequence streams without storing anything.
+//
+// |F_n| = 1 + φ(1) + ... + φ(n) ≈ 3n²/π², counted from the totient sieve in arith.rs over
+// chunks of [1, n] on worker threads. The neighbours of a fraction p/q in F_n come from
+// bc − ad = 1 directly when q <= n: b is the largest denominator <= n with b ≡ p⁻¹
+// (mod q). When q > n they are the ends of the Stern–Brocot interval around p/q once the
+// mediants grow past n, with each run of same-side moves taken in one step.
+
+use crate::arith::{self, ArithFunction, Scratch};
+use crate::parallel::{MIN_CHUNK, chunk_size, parallel_chunks};
+use crate::rational::Ratio;
+
+// Values sieved at a time within a chunk
+const SEGMENT_SIZE: usize = 1 << 18;
+
+/// The terms of F_n in increasing order, from 0/1 to 1/1
+pub struct Farey {
+    n: u64,
+    // Current term a/b and the one after it c/d
+    a: u64,
+    b: u64,
+    c: u64,
+    d: u64,
+    done: bool,
+}
+
+impl Farey {
+    /// F_n for n >= 1
+    pub fn new(n: u64) -> Self {
+        assert!(n >= 1, "Farey sequences start at F_1");
+        Self {
+            n,
+            a: 0,
+            b: 1,
+            c: 1,
+            d: n,
+            done: false,
+        }
+    }
+}
+
+impl Iterator for Farey {
+    type Item = Ratio;
+
+    fn next(&mut self) -> Option<Ratio> {
+        if self.done {
+            return None;
+        }
+        let term = Ratio::new(self.a, self.b).unwrap();
+        if self.a == 1 && self.b == 1 {
+            self.done = true;
+        } else {
+            let k = (self.n + self.b) / self.d;
+            let (c, d) = (k * self.c - self.a, k * self.d - self.b);
+            (self.a, self.b, self.c, self.d) = (self.c, self.d, c, d);
+        }
+        Some(term)
+    }
+}
+
+/// |F_n| = 1 + Σ φ(k) for k <= n, on `num_workers` threads
+pub fn length(n: usize, num_workers: usize) -> u64 {
+    let base_primes = crate::primes::sieve(n.isqrt());
+    let mut total = 1;
+    parallel_chunks(
+        1..=n,
+        chunk_size(n, num_workers, MIN_CHUNK),
+        num_workers,
+        |chunk_low, chunk_high| {
+            let mut scratch = Scratch::default();
+            let mut low = chunk_low;
+            let mut sum = 0u64;
+            while low <= chunk_high {
+                let high = (low + SEGMENT_SIZE - 1).min(chunk_high);
+                let phis =
+                    arith::sieve_segment(ArithFunction::Phi, &base_primes, low, high, &mut scratch);
+                sum += phis.iter().map(|&phi| phi as u64).sum::<u64>();
+                low = high + 1;
+            }
+            sum
+        },
+        |sum| total += sum,
+    );
+    total
+}
+
+/// p⁻¹ mod q for coprime p and q >= 2
+fn inverse_mod(p: u64, q: u64) -> u64 {
+    let (mut old_r, mut r) = (p as i128 % q as i128, q as i128);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+    }
+    old_s.rem_euclid(q as i128) as u64
+}
+
+/// The terms of F_n just below and just above x (0 <= x <= 1), None past either end
+pub fn neighbors(x: Ratio, n: u64) -> (Option<Ratio>, Option<Ratio>) {
+    assert!(n >= 1, "Farey sequences start at F_1");
+    assert!(x <= Ratio::integer(1), "{} is outside [0, 1]", x);
+    let (p, q) = (x.num(), x.den());
+    if q == 1 {
+        return if p == 0 {
+            (None, Ratio::new(1, n))
+        } else {
+            (Ratio::new(n - 1, n), None)
+        };
+    }
+    if q <= n {
+        // Left a/b has pb − qa = 1, right c/d has qc − pd = 1
+        let inverse = inverse_mod(p, q);
+        let b = inverse + (n - inverse) / q * q;
+        let a = ((p as u128 * b as u128 - 1) / q as u128) as u64;
+        let d = (q - inverse) + (n - (q - inverse)) / q * q;
+        let c = ((p as u128 * d as u128 + 1) / q as u128) as u64;
+        return (Ratio::new(a, b), Ratio::new(c, d));
+    }
+
+    // Descend the Stern–Brocot tree between 0/1 and 1/1 while the mediants fit in F_n
+    let (p, q, n) = (p as u128, q as u128, n as u128);
+    let (mut left, mut right) = ((0u128, 1u128), (1u128, 1u128));
+    while left.1 + right.1 <= n {
+        let mediant = (left.0 + right.0, left.1 + right.1);
+        if mediant.0 * q < p * mediant.1 {
+            // Add right to left as many times as stays below x with denominator <= n
+            let by_x = (p * left.1 - left.0 * q - 1) / (right.0 * q - p * right.1);
+            let by_n = (n - left.1) / right.1;
+            let k = by_x.min(by_n);
+            left = (left.0 + k * right.0, left.1 + k * right.1);
+        } else {
+            // Mediants never equal x here, so this is the mirror image
+            let by_x = (right.0 * q - p * right.1 - 1) / (p * left.1 - left.0 * q);
+            let by_n = (n - right.1) / left.1;
+            let k = by_x.min(by_n);
+            right = (right.0 + k * left.0, right.1 + k * left.1);
+        }
+    }
+    (
+        Ratio::new(left.0 as u64, left.1 as u64),
+        Ratio::new(right.0 as u64, right.1 as u64),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence_and_length() {
+        let terms: Vec<String> = Farey::new(5).map(|r| r.to_string()).collect();
+        assert_eq!(
+            terms,
+            [
+                "0/1", "1/5", "1/4", "1/3", "2/5", "1/2", "3/5", "2/3", "3/4", "4/5", "1/1"
+            ]
+        );
+        assert_eq!(Farey::new(1).count(), 2);
+        // A005728
+        for (n, len) in [(1, 2), (2, 3), (5, 11), (10, 33), (100, 3045)] {
+            assert_eq!(length(n, 3), len);
+            assert_eq!(Farey::new(n as u64).count() as u64, len);
+        }
+        assert_eq!(length(1_000_000, 4), 303_963_552_393);
+    }
+
+    #[test]
+    fn test_neighbors_match_sequence() {
+        for n in 1..=12u64 {
+            let terms: Vec<Ratio> = Farey::new(n).collect();
+            for q in 1..=15u64 {
+                for p in 0..=q {
+                    let x = Ratio::new(p, q).unwrap();
+                    let below = terms.iter().rev().find(|&&t| t < x).copied();
+                    let above = terms.iter().find(|&&t| t > x).copied();
+                    assert_eq!(neighbors(x, n), (below, above), "{} in F_{}", x, n);
+                }
+            }
+        }
+        // A denominator far above n
+        let x = Ratio::new(333_333_333_333, 1_000_000_000_000).unwrap();
+        let (below, above) = neighbors(x, 1_000_000);
+        let (below, above) = (below.unwrap(), above.unwrap());
+        assert!(below < x && x < above);
+        assert_eq!(
+            above.num() as u128 * below.den() as u128 - below.num() as u128 * above.den() as u128,
+            1
+        );
+    }
+}
@@ -0,0 +1,115 @@
+// Fermat's method (`nt factor --algorithm fermat`)
+//
+// An odd n = p·q is a difference of squares a² − b² with a = (p + q) / 2 and b = (q − p) / 2,
+// so walking a up from ceil(sqrt(n)) until a² − n is a square finds p and q after about
+// (q − p)² / (8 sqrt(n)) steps: instant for close primes, hopeless for distant ones.
+// Most a are ruled out without touching big integers: a² − n must be a square modulo
+// every small m as well, so a table per modulus marks the residues of a that can work.
+// The walk only keeps one small counter per modulus and does the exact perfect-square
+// test when every table agrees, roughly once per few thousand steps.
+
+use rug::Integer;
+
+// Sieve moduli: together they pass about 1 in 3000 candidates that are not squares
+const SIEVE_MODULI: [usize; 9] = [64, 63, 65, 11, 17, 19, 23, 29, 31];
+
+/// n = a² − b², so n = (a − b)(a + b)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DifferenceOfSquares {
+    pub a: Integer,
+    pub b: Integer,
+}
+
+impl DifferenceOfSquares {
+    /// (a − b, a + b)
+    pub fn factors(&self) -> (Integer, Integer) {
+        (
+            Integer::from(&self.a - &self.b),
+            Integer::from(&self.a + &self.b),
+        )
+    }
+}
+
+/// Residue table for one modulus: which a mod m leave a² − n a square mod m
+struct Sieve {
+    modulus: usize,
+    allowed: Vec<bool>,
+    residue: usize,
+}
+
+impl Sieve {
+    fn new(modulus: usize, n: &Integer, a: &Integer) -> Self {
+        let mut squares = vec![false; modulus];
+        for x in 0..modulus {
+            squares[x * x % modulus] = true;
+        }
+        let n_mod = n.mod_u(modulus as u32) as usize;
+        let allowed = (0..modulus)
+            .map(|r| squares[(r * r + modulus - n_mod) % modulus])
+            .collect();
+        Self {
+            modulus,
+            allowed,
+            residue: a.mod_u(modulus as u32) as usize,
+        }
+    }
+}
+
+/// a and b with n = a² − b² and a − b > 1, walking at most `max_steps` values of a
+/// `n` must be odd and not a perfect square
+pub fn fermat(n: &Integer, max_steps: usize) -> Option<DifferenceOfSquares> {
+    let (root, rem) = n.clone().sqrt_rem(Integer::new());
+    let start = if rem == 0 { root } else { root + 1u32 };
+    let mut sieves: Vec<Sieve> = SIEVE_MODULI
+        .iter()
+        .map(|&modulus| Sieve::new(modulus, n, &start))
+        .collect();
+
+    for step in 0..max_steps {
+        if sieves.iter().all(|s| s.allowed[s.residue]) {
+            let a = Integer::from(&start + step as u64);
+            let candidate = Integer::from(a.square_ref()) - n;
+            if candidate.is_perfect_square() {
+                let b = candidate.sqrt();
+                // a − b = 1 is the trivial 1 × n
+                if Integer::from(&a - &b) > 1u32 {
+                    return Some(DifferenceOfSquares { a, b });
+                }
+                return None;
+            }
+        }
+        for s in &mut sieves {
+            s.residue += 1;
+            if s.residue == s.modulus {
+                s.residue = 0;
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_close_primes_and_decomposition() {
+        let p: Integer = "100000000000000000039".parse().unwrap();
+        let q: Integer = "100000001000000000171".parse().unwrap();
+        let n = Integer::from(&p * &q);
+
+        // 1249 steps past ceil(sqrt(n)) are needed
+        assert_eq!(fermat(&n, 1000), None);
+        let found = fermat(&n, 2000).unwrap();
+        assert_eq!(found.factors(), (p.clone(), q.clone()));
+        assert_eq!(
+            Integer::from(found.a.square_ref()) - Integer::from(found.b.square_ref()),
+            n
+        );
+    }
+
+    #[test]
+    fn test_prime_is_not_split() {
+        assert_eq!(fermat(&Integer::from(1_000_003), 1_000_000), None);
+    }
+}
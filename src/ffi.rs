@@ -0,0 +1,148 @@
+// C interface to the sieve and prime sets (declared in include/nt.h)
+//
+// Built into the nt_core cdylib (libnt_core.so / .dylib). Every function is safe to call
+// from any thread. Memory handed to C is released through the matching nt_free_* or
+// nt_close_* function, never with free().
+
+use std::ffi::{CStr, c_char};
+use std::path::Path;
+
+use crate::{PrimeIterator, PrimeSet, factor};
+
+/// Sieve every prime <= limit into a new array
+/// Stores the array in *out_primes and returns its length; release it with nt_free_primes
+/// Returns 0 and stores NULL when there are no primes or out_primes is NULL
+///
+/// # Safety
+/// `out_primes` must be NULL or point to writable storage for one pointer
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nt_sieve(limit: u64, out_primes: *mut *mut u64) -> usize {
+    if out_primes.is_null() {
+        return 0;
+    }
+
+    // Segmented windows, so memory stays at the output plus O(sqrt(limit))
+    let primes: Vec<u64> = PrimeIterator::new()
+        .map(|p| p as u64)
+        .take_while(|&p| p <= limit)
+        .collect();
+
+    if primes.is_empty() {
+        unsafe { *out_primes = std::ptr::null_mut() };
+        return 0;
+    }
+
+    let boxed = primes.into_boxed_slice();
+    let len = boxed.len();
+    unsafe { *out_primes = Box::into_raw(boxed) as *mut u64 };
+    len
+}
+
+/// Release an array returned by nt_sieve
+///
+/// # Safety
+/// `primes` and `len` must come from the same nt_sieve call, and be released only once
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nt_free_primes(primes: *mut u64, len: usize) {
+    if primes.is_null() {
+        return;
+    }
+    unsafe {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            primes, len,
+        )));
+    }
+}
+
+/// Deterministic primality test for any 64-bit n
+#[unsafe(no_mangle)]
+pub extern "C" fn nt_is_prime(n: u64) -> bool {
+    factor::is_prime(n)
+}
+
+/// Sieve the primes <= limit and save them as a prime set file at `path`
+/// Returns 0 on success, -1 on error
+///
+/// # Safety
+/// `path` must be a NUL-terminated string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nt_create_primeset(limit: u64, path: *const c_char) -> i32 {
+    let Some(path) = (unsafe { c_path(path) }) else {
+        return -1;
+    };
+    match PrimeSet::new(limit as usize).save(path) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// Open a prime set file (memory-mapped); returns NULL on error
+/// Close it with nt_close_primeset
+///
+/// # Safety
+/// `path` must be a NUL-terminated string
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nt_open_primeset(path: *const c_char) -> *mut PrimeSet {
+    let Some(path) = (unsafe { c_path(path) }) else {
+        return std::ptr::null_mut();
+    };
+    match PrimeSet::open(path) {
+        Ok(set) => Box::into_raw(Box::new(set)),
+        Err(_) => std::ptr::null_mut(),
+    }
+}
+
+/// Whether n is prime according to the set; false for n above the set's limit
+///
+/// # Safety
+/// `set` must be a live pointer from nt_open_primeset
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nt_primeset_contains(set: *const PrimeSet, n: u64) -> bool {
+    let Some(set) = (unsafe { set.as_ref() }) else {
+        return false;
+    };
+    n <= set.limit() as u64 && set.contains(n as usize)
+}
+
+/// Largest number the set can answer for
+///
+/// # Safety
+/// `set` must be a live pointer from nt_open_primeset
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nt_primeset_limit(set: *const PrimeSet) -> u64 {
+    unsafe { set.as_ref() }.map_or(0, |set| set.limit() as u64)
+}
+
+/// Unmap and free a set from nt_open_primeset
+///
+/// # Safety
+/// `set` must come from nt_open_primeset and be closed only once
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn nt_close_primeset(set: *mut PrimeSet) {
+    if !set.is_null() {
+        drop(unsafe { Box::from_raw(set) });
+    }
+}
+
+/// Borrow a C path; None for NULL or non-UTF-8
+unsafe fn c_path<'a>(path: *const c_char) -> Option<&'a Path> {
+    if path.is_null() {
+        return None;
+    }
+    let path = unsafe { CStr::from_ptr(path) }.to_str().ok()?;
+    Some(Path::new(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sieve_round_trip() {
+        let mut primes = std::ptr::null_mut();
+        let len = unsafe { nt_sieve(30, &mut primes) };
+        let slice = unsafe { std::slice::from_raw_parts(primes, len) };
+        assert_eq!(slice, &[2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+        unsafe { nt_free_primes(primes, len) };
+    }
+}
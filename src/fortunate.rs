@@ -0,0 +1,104 @@
+// Fortunate numbers (`nt fortunate`)
+//
+// The Fortunate number of p_k# is the smallest m > 1 with p_k# + m prime. Every m below
+// p_{k+1} shares a factor with p_k#, so the search starts there and steps over odd m with
+// Baillie–PSW. Workers take the terms largest first, since those are the slowest.
+
+use rug::Integer;
+
+use crate::parallel::parallel_chunks;
+use crate::{bpsw, factor, primorial};
+
+/// The Fortunate number of p_k#
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Term {
+    pub k: usize,
+    /// p_k, the largest prime in the primorial
+    pub p: u64,
+    pub fortunate: u64,
+}
+
+impl Term {
+    /// Whether the Fortunate number is prime, as conjectured
+    pub fn is_prime(&self) -> bool {
+        factor::is_prime(self.fortunate)
+    }
+}
+
+/// Smallest m > 1 with `primorial` + m prime, where `next_prime` is the first prime not
+/// dividing `primorial` (odd, and every smaller prime divides it)
+pub fn fortunate(primorial: &Integer, next_prime: u64) -> u64 {
+    let mut candidate = Integer::from(primorial + next_prime);
+    let mut m = next_prime;
+    while !bpsw::is_probable_prime(&candidate) {
+        candidate += 2;
+        m += 2;
+    }
+    m
+}
+
+/// The Fortunate numbers of p_1# through p_terms#, in order
+pub fn terms(terms: usize, num_workers: usize) -> Vec<Term> {
+    // p_{k+1} starts the search for p_k#
+    let primes = primorial::primorial_primes(terms + 1, false);
+    let mut primorials = Vec::with_capacity(terms);
+    let mut product = Integer::from(1);
+    for &p in &primes[..terms] {
+        product *= p;
+        primorials.push(product.clone());
+    }
+
+    let mut found = Vec::with_capacity(terms);
+    if terms > 0 {
+        parallel_chunks(
+            0..=terms - 1,
+            1,
+            num_workers,
+            |taken, _| {
+                let index = terms - 1 - taken;
+                Term {
+                    k: index + 1,
+                    p: primes[index],
+                    fortunate: fortunate(&primorials[index], primes[index + 1]),
+                }
+            },
+            |term| found.push(term),
+        );
+    }
+    found.sort_unstable_by_key(|term| term.k);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terms() {
+        // OEIS A005235
+        let expected = [
+            3, 5, 7, 13, 23, 17, 19, 23, 37, 61, 67, 61, 71, 47, 107, 59, 61, 109, 89, 103, 79,
+            151, 197, 101, 103, 233, 223, 127, 223, 191,
+        ];
+        let found = terms(30, 4);
+        let fortunates: Vec<u64> = found.iter().map(|term| term.fortunate).collect();
+        assert_eq!(fortunates, expected);
+        assert_eq!((found[9].k, found[9].p), (10, 29));
+        assert!(found.iter().all(Term::is_prime));
+        assert!(terms(0, 2).is_empty());
+    }
+
+    #[test]
+    fn test_single_term_and_idle_workers() {
+        let single = [Term {
+            k: 1,
+            p: 2,
+            fortunate: 3,
+        }];
+        assert_eq!(terms(1, 8), single);
+        assert_eq!(terms(1, 0), single);
+        assert_eq!(terms(6, 0), terms(6, 16));
+        // 30 + 7 = 37 is prime at the first candidate
+        assert_eq!(fortunate(&Integer::from(30), 7), 7);
+    }
+}
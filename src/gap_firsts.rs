@@ -0,0 +1,166 @@
+// First occurrence of each prime gap (`nt gap-firsts`)
+//
+// Sieves with a parallel variation (8 or 9) and has the workers reduce each segment to its
+// gap statistics, so only a few hundred gaps per segment cross the channels instead of
+// every prime. Segments finish out of order, so the consumers chain them by segment id
+// (gaps::GapChain); only then is the gap across each segment boundary known, and with it
+// which prime pair really is the first to achieve each gap. The table is written as CSV.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+
+use crate::gaps::{GapChain, GapStats};
+use crate::primes;
+use crate::segment_format::{SegmentBuilder, for_each_prime};
+use crate::storage::{commit_output, staging_path};
+
+// Segments buffered per variation 9 consumer; each is only a small map of gaps
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Gap statistics of one sieved segment
+pub struct GapSegment {
+    pub stats: GapStats,
+    pub segment_id: usize,
+}
+
+/// Workers send gap statistics instead of primes
+pub struct GapSegments;
+
+impl SegmentBuilder for GapSegments {
+    type Output = GapSegment;
+
+    fn build_from_bits(
+        &self,
+        bits: &[u64],
+        low: usize,
+        high: usize,
+        segment_id: usize,
+    ) -> GapSegment {
+        let mut stats = GapStats::default();
+        for_each_prime(bits, low, high, |num| stats.push(num));
+        GapSegment { stats, segment_id }
+    }
+
+    fn build_from_primes(&self, primes: &[usize], segment_id: usize) -> GapSegment {
+        let mut stats = GapStats::default();
+        stats.extend(primes);
+        GapSegment { stats, segment_id }
+    }
+
+    fn byte_len(output: &GapSegment) -> usize {
+        // Two BTreeMap entries (count and first) per distinct gap
+        output.stats.histogram.len() * 4 * std::mem::size_of::<usize>()
+    }
+}
+
+/// Gap statistics for every prime up to `limit`, sieved with variation 8 or 9
+pub fn find(limit: usize, variation: u32, num_workers: usize, num_consumers: usize) -> GapStats {
+    let sqrt_limit = (limit as f64).sqrt() as usize;
+
+    if variation == 8 {
+        // One consumer; the small primes arrive as segment 0
+        let (tx, rx) = mpsc::channel::<GapSegment>();
+        let mut chain = GapChain::new(0);
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                primes::find_primes_v8_parallel(
+                    limit,
+                    sqrt_limit,
+                    tx,
+                    num_workers,
+                    &GapSegments,
+                    None,
+                )
+            });
+            for segment in rx {
+                chain.insert(segment.segment_id, segment.stats);
+            }
+        });
+        return chain.finish();
+    }
+
+    // Variation 9: consumers receive segments 1.. round-robin and share one chain
+    let chain = Mutex::new(GapChain::new(1));
+    let small_primes = thread::scope(|scope| {
+        let mut senders = Vec::new();
+        for _ in 0..num_consumers {
+            let (tx, rx) = mpsc::sync_channel::<GapSegment>(CHANNEL_CAPACITY);
+            senders.push(tx);
+            let chain = &chain;
+            scope.spawn(move || {
+                for segment in rx {
+                    chain
+                        .lock()
+                        .unwrap()
+                        .insert(segment.segment_id, segment.stats);
+                }
+            });
+        }
+        primes::find_primes_v9_multi_consumers(
+            limit,
+            sqrt_limit,
+            senders,
+            num_workers,
+            Arc::new(AtomicUsize::new(0)),
+            &GapSegments,
+            None,
+            None,
+            false,
+        )
+    });
+
+    // Small primes are returned rather than sent, and come before segment 1
+    let mut stats = GapStats::default();
+    stats.extend(&small_primes);
+    stats.append(chain.into_inner().unwrap().finish());
+    stats
+}
+
+/// Write the first prime pair for each even gap as CSV
+/// Returns the number of rows written
+pub fn write_csv(stats: &GapStats, output: &Path) -> io::Result<usize> {
+    // The data directory may not exist yet if nothing else has been run
+    if let Some(dir) = output.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    let mut out = BufWriter::new(File::create(staging_path(output))?);
+    writeln!(out, "gap,prime,next_prime,count")?;
+
+    let mut rows = 0;
+    // The only odd gap is 2 -> 3
+    for (&gap, &prime) in stats.firsts.iter().filter(|(gap, _)| gap.is_multiple_of(2)) {
+        writeln!(
+            out,
+            "{},{},{},{}",
+            gap,
+            prime,
+            prime + gap,
+            stats.histogram[&gap]
+        )?;
+        rows += 1;
+    }
+
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    commit_output(output)?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variations_match_sequential_firsts() {
+        // Several segments, so first occurrences have to survive the chaining
+        let limit = 3 * primes::SEGMENT_SIZE_NUMBERS + 12_345;
+        let mut expected = GapStats::default();
+        expected.extend(&primes::sieve(limit));
+
+        assert_eq!(find(limit, 8, 3, 1), expected);
+        assert_eq!(find(limit, 9, 3, 2), expected);
+    }
+}
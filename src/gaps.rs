@@ -0,0 +1,260 @@
+// Prime gap statistics gathered while primes are written (--track-gaps)
+//
+// Consumers hand every segment they write to a process-wide tracker keyed by segment id
+// (like audit.rs). Gaps inside a segment are counted as it arrives; segments are then
+// chained in id order so the gap across each boundary is counted too, even when variation
+// 9 spreads neighbouring segments over different shard files. The result is written to
+// gaps.json beside the primes, so gap statistics never need a second pass over the output.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::warn;
+
+use crate::segment_format::SegmentEncoding;
+use crate::storage::{commit_output, get_nt_data_dir, staging_path};
+
+/// Gap statistics for a run of consecutive primes
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GapStats {
+    pub first: Option<usize>,
+    pub last: Option<usize>,
+    pub primes: u64,
+    /// Number of times each gap occurs
+    pub histogram: BTreeMap<usize, u64>,
+    /// Prime each gap first occurs after
+    pub firsts: BTreeMap<usize, usize>,
+    pub max_gap: usize,
+    /// Prime the first maximal gap starts at
+    pub max_gap_start: usize,
+}
+
+impl GapStats {
+    /// Add the next prime in order
+    #[inline]
+    pub fn push(&mut self, prime: usize) {
+        if let Some(last) = self.last {
+            self.add_gap(last, prime - last);
+        } else {
+            self.first = Some(prime);
+        }
+        self.last = Some(prime);
+        self.primes += 1;
+    }
+
+    pub fn extend(&mut self, primes: &[usize]) {
+        for &prime in primes {
+            self.push(prime);
+        }
+    }
+
+    /// Append the statistics of the run that directly follows this one
+    pub fn append(&mut self, next: GapStats) {
+        let (Some(next_first), Some(next_last)) = (next.first, next.last) else {
+            return;
+        };
+        match self.last {
+            Some(last) => self.add_gap(last, next_first - last),
+            None => self.first = Some(next_first),
+        }
+        for (gap, count) in next.histogram {
+            *self.histogram.entry(gap).or_insert(0) += count;
+        }
+        // This run comes first, so its first occurrences win
+        for (gap, start) in next.firsts {
+            self.firsts.entry(gap).or_insert(start);
+        }
+        // Strictly greater keeps the earliest occurrence of the record
+        if next.max_gap > self.max_gap {
+            self.max_gap = next.max_gap;
+            self.max_gap_start = next.max_gap_start;
+        }
+        self.last = Some(next_last);
+        self.primes += next.primes;
+    }
+
+    fn add_gap(&mut self, start: usize, gap: usize) {
+        *self.histogram.entry(gap).or_insert(0) += 1;
+        self.firsts.entry(gap).or_insert(start);
+        if gap > self.max_gap {
+            self.max_gap = gap;
+            self.max_gap_start = start;
+        }
+    }
+
+    /// Mean distance between consecutive primes
+    pub fn mean_gap(&self) -> f64 {
+        match (self.first, self.last) {
+            (Some(first), Some(last)) if self.primes > 1 => {
+                (last - first) as f64 / (self.primes - 1) as f64
+            }
+            _ => 0.0,
+        }
+    }
+
+    /// The summary as a JSON object
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\n");
+        let _ = writeln!(json, "  \"primes\": {},", self.primes);
+        let _ = writeln!(json, "  \"first\": {},", self.first.unwrap_or(0));
+        let _ = writeln!(json, "  \"last\": {},", self.last.unwrap_or(0));
+        let _ = writeln!(json, "  \"max_gap\": {},", self.max_gap);
+        let _ = writeln!(json, "  \"max_gap_start\": {},", self.max_gap_start);
+        let _ = writeln!(json, "  \"mean_gap\": {:.6},", self.mean_gap());
+        let entries: Vec<String> = self
+            .histogram
+            .iter()
+            .map(|(gap, count)| format!("    \"{}\": {}", gap, count))
+            .collect();
+        if entries.is_empty() {
+            json.push_str("  \"histogram\": {}\n");
+        } else {
+            let _ = writeln!(json, "  \"histogram\": {{\n{}\n  }}", entries.join(",\n"));
+        }
+        json.push_str("}\n");
+        json
+    }
+}
+
+/// Chains per-segment statistics in segment id order as segments arrive in any order
+pub struct GapChain {
+    // Segments waiting for an earlier one
+    pending: BTreeMap<usize, GapStats>,
+    next_id: usize,
+    total: GapStats,
+}
+
+impl GapChain {
+    /// Expect segment ids from `first_id` upwards with none skipped
+    pub fn new(first_id: usize) -> Self {
+        GapChain {
+            pending: BTreeMap::new(),
+            next_id: first_id,
+            total: GapStats::default(),
+        }
+    }
+
+    pub fn insert(&mut self, segment_id: usize, stats: GapStats) {
+        self.pending.insert(segment_id, stats);
+        while let Some(next) = self.pending.remove(&self.next_id) {
+            self.total.append(next);
+            self.next_id += 1;
+        }
+    }
+
+    /// Statistics of every segment inserted
+    pub fn finish(mut self) -> GapStats {
+        // Anything still pending sits behind a segment that never arrived
+        if !self.pending.is_empty() {
+            warn!(
+                "Warning: gap statistics are missing segment {}; gaps across it are not counted",
+                self.next_id
+            );
+            for (_, stats) in std::mem::take(&mut self.pending) {
+                self.total.append(stats);
+            }
+        }
+        self.total
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TRACKER: Mutex<Option<GapChain>> = Mutex::new(None);
+
+/// Start tracking; segment ids are expected from 0 upwards with none skipped
+pub fn start() {
+    *TRACKER.lock().unwrap() = Some(GapChain::new(0));
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether --track-gaps is on; consumers skip all gap work otherwise
+#[inline]
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Record the primes of segment `segment_id`, in order
+#[inline]
+pub fn record(segment_id: usize, primes: &[usize]) {
+    if !is_enabled() {
+        return;
+    }
+    let mut stats = GapStats::default();
+    stats.extend(primes);
+    submit(segment_id, stats);
+}
+
+/// Record a segment a worker already encoded (--preformat)
+pub fn record_encoded(segment_id: usize, bytes: &[u8], encoding: SegmentEncoding) {
+    if !is_enabled() {
+        return;
+    }
+    let mut stats = GapStats::default();
+    match encoding {
+        SegmentEncoding::Binary => {
+            for chunk in bytes.chunks_exact(8) {
+                stats.push(u64::from_le_bytes(chunk.try_into().unwrap()) as usize);
+            }
+        }
+        SegmentEncoding::Text => {
+            for line in bytes.split(|&b| b == b'\n').filter(|line| !line.is_empty()) {
+                let prime = line
+                    .iter()
+                    .fold(0, |n, &digit| n * 10 + (digit - b'0') as usize);
+                stats.push(prime);
+            }
+        }
+    }
+    submit(segment_id, stats);
+}
+
+/// Record statistics a consumer gathered itself for segment `segment_id`
+pub fn submit(segment_id: usize, stats: GapStats) {
+    if let Some(tracker) = TRACKER.lock().unwrap().as_mut() {
+        tracker.insert(segment_id, stats);
+    }
+}
+
+/// Stop tracking and return the statistics, or None if tracking was never started
+pub fn finish() -> Option<GapStats> {
+    ENABLED.store(false, Ordering::Relaxed);
+    let tracker = TRACKER.lock().unwrap().take()?;
+    Some(tracker.finish())
+}
+
+/// Write gaps.json to the data directory
+pub fn save(stats: &GapStats) -> io::Result<PathBuf> {
+    let path = get_nt_data_dir().join("gaps.json");
+    fs::write(staging_path(&path), stats.to_json())?;
+    commit_output(&path)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_counts_boundary_gap() {
+        let primes = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31];
+        let mut whole = GapStats::default();
+        whole.extend(&primes);
+
+        let (left, right) = primes.split_at(6);
+        let mut split = GapStats::default();
+        split.extend(left);
+        let mut tail = GapStats::default();
+        tail.extend(right);
+        split.append(tail);
+
+        assert_eq!(split, whole);
+        assert_eq!(whole.max_gap, 6);
+        assert_eq!(whole.max_gap_start, 23);
+        assert_eq!(whole.histogram[&2], 5);
+        assert_eq!(whole.firsts[&4], 7);
+    }
+}
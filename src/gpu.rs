@@ -0,0 +1,458 @@
+// GPU segment sieving for variation 9 (--backend gpu)
+//
+// Segments are sieved on the GPU in batches of BATCH_SEGMENTS: the CPU uploads the small
+// primes once and, per batch, the index of each prime's first odd multiple in the batch's
+// first segment. One compute invocation per (prime, segment, chunk) strikes that prime's
+// multiples with atomicOr into a composite bitmap. Only the bitmaps come back; CPU threads
+// unpack (or preformat) them with the same SegmentBuilder and routing as the CPU producer,
+// so consumers and output are unchanged.
+//
+// The wgpu code is behind the `gpu` cargo feature. Without it `GpuSieve::new` returns an
+// error and --backend gpu stops before any work starts.
+
+use clap::ValueEnum;
+
+/// Where variation 9 sieves its segments
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SieveBackend {
+    /// Worker threads on the CPU
+    #[default]
+    Cpu,
+    /// Compute shader on the first available GPU (needs the `gpu` feature)
+    Gpu,
+}
+
+#[cfg(feature = "gpu")]
+pub use enabled::GpuSieve;
+
+#[cfg(not(feature = "gpu"))]
+pub use disabled::GpuSieve;
+
+#[cfg(feature = "gpu")]
+mod enabled {
+    use std::borrow::Cow;
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::{self, SyncSender};
+    use std::thread;
+    use tracing::debug;
+    use wgpu::util::DeviceExt;
+
+    use crate::backpressure::Backpressure;
+    use crate::primes::{self, SEGMENT_SIZE_BITS, SEGMENT_SIZE_NUMBERS};
+    use crate::segment_format::SegmentBuilder;
+
+    // Segments sieved per dispatch (8 MB of bitmap)
+    const BATCH_SEGMENTS: usize = 256;
+
+    // u32 words per segment bitmap on the GPU
+    const SEGMENT_WORDS_32: usize = SEGMENT_SIZE_BITS / 32;
+
+    // Threads per workgroup along the prime axis (must match the shader)
+    const WORKGROUP_SIZE: u32 = 64;
+
+    // Chunks each segment is split into along z (SEGMENT_SIZE_BITS / CHUNK_BITS in the shader)
+    const SEGMENT_CHUNKS: u32 = 8;
+
+    // The shader hardcodes the segment geometry
+    const _: () = assert!(
+        SEGMENT_SIZE_BITS == 262_144 && SEGMENT_SIZE_BITS / SEGMENT_CHUNKS as usize == 32_768
+    );
+
+    const SHADER: &str = r#"
+struct Params {
+    segments: u32,
+    num_primes: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> primes: array<u32>;
+@group(0) @binding(2) var<storage, read> offsets: array<u32>;
+@group(0) @binding(3) var<storage, read_write> composite: array<atomic<u32>>;
+
+const SEGMENT_BITS: u32 = 262144u;
+const SEGMENT_WORDS: u32 = 8192u;
+const CHUNK_BITS: u32 = 32768u;
+
+// x walks the primes (grid-stride), y is the segment within the batch, z the chunk within
+// the segment (keeps every invocation's loop short, even for p = 3)
+@compute @workgroup_size(64)
+fn main(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(num_workgroups) groups: vec3<u32>,
+) {
+    let segment = id.y;
+    if (segment >= params.segments) {
+        return;
+    }
+    let chunk_low = id.z * CHUNK_BITS;
+    let shift = segment * SEGMENT_BITS + chunk_low;
+    let base = segment * SEGMENT_WORDS;
+    let stride = groups.x * 64u;
+
+    for (var j = id.x; j < params.num_primes; j = j + stride) {
+        let p = primes[j];
+        // offsets[j] is the first multiple's index in the batch; this chunk starts shift
+        // odd numbers later
+        var i = (offsets[j] + p - shift % p) % p;
+        while (i < CHUNK_BITS) {
+            let bit = chunk_low + i;
+            atomicOr(&composite[base + (bit >> 5u)], 1u << (bit & 31u));
+            i = i + p;
+        }
+    }
+}
+"#;
+
+    /// A GPU device with the sieve pipeline compiled
+    pub struct GpuSieve {
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+        pipeline: wgpu::ComputePipeline,
+        adapter_name: String,
+    }
+
+    impl GpuSieve {
+        /// Open the first high-performance adapter and compile the sieve shader
+        pub fn new() -> io::Result<Self> {
+            let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+            let adapter =
+                pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::HighPerformance,
+                    force_fallback_adapter: false,
+                    compatible_surface: None,
+                }))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no GPU adapter found"))?;
+
+            let (device, queue) = pollster::block_on(adapter.request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("nt sieve"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits::default(),
+                    memory_hints: wgpu::MemoryHints::Performance,
+                },
+                None,
+            ))
+            .map_err(io::Error::other)?;
+
+            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("sieve"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+            });
+            let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("sieve"),
+                layout: None,
+                module: &module,
+                entry_point: Some("main"),
+                compilation_options: Default::default(),
+                cache: None,
+            });
+
+            Ok(Self {
+                device,
+                queue,
+                pipeline,
+                adapter_name: adapter.get_info().name,
+            })
+        }
+
+        pub fn adapter_name(&self) -> &str {
+            &self.adapter_name
+        }
+
+        /// Variation 9 producer with the GPU doing the sieving
+        /// Same contract as primes::find_primes_v9_multi_consumers: segment S goes to
+        /// consumer (S-1) % N, and the small primes are returned for primes_small
+        #[allow(clippy::too_many_arguments)]
+        pub fn find_primes_v9<B: SegmentBuilder>(
+            &self,
+            limit: usize,
+            sqrt_limit: usize,
+            senders: Vec<SyncSender<B::Output>>,
+            num_workers: usize,
+            total_sent: Arc<AtomicUsize>,
+            builder: &B,
+            backpressure: Option<&Backpressure>,
+        ) -> io::Result<Vec<usize>> {
+            let small_primes = primes::sieve(sqrt_limit);
+            let num_consumers = senders.len();
+            let low = (sqrt_limit + 1) | 1;
+            if limit < low || num_consumers == 0 {
+                return Ok(small_primes);
+            }
+            let total_segments = (limit - low + 1).div_ceil(SEGMENT_SIZE_NUMBERS);
+
+            // Odd sieving primes as u32; the shader adds two of them without overflowing
+            if sqrt_limit > (u32::MAX / 2) as usize {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "limit too large for the GPU backend (sieving primes must fit in 31 bits)",
+                ));
+            }
+            let odd_primes: Vec<u32> = small_primes.iter().skip(1).map(|&p| p as u32).collect();
+            if odd_primes.is_empty() {
+                return Ok(small_primes);
+            }
+            let primes_bytes = (odd_primes.len() * 4) as u64;
+            if primes_bytes > self.device.limits().max_storage_buffer_binding_size as u64 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "too many sieving primes for one GPU storage buffer",
+                ));
+            }
+
+            let buffers = self.create_buffers(&odd_primes);
+            let num_workers = num_workers.max(1);
+            let mut bits = vec![0_u64; BATCH_SEGMENTS * SEGMENT_SIZE_BITS / 64];
+            let mut offsets = vec![0_u32; odd_primes.len()];
+
+            for batch_start in (0..total_segments).step_by(BATCH_SEGMENTS) {
+                let segments = BATCH_SEGMENTS.min(total_segments - batch_start);
+                let batch_low = low + batch_start * SEGMENT_SIZE_NUMBERS;
+
+                // Index i stands for batch_low + 2i; p divides it when 2i ≡ -batch_low (mod p)
+                for (offset, &p) in offsets.iter_mut().zip(&odd_primes) {
+                    let p = p as u64;
+                    let neg_low = (p - batch_low as u64 % p) % p;
+                    *offset = (neg_low * p.div_ceil(2) % p) as u32;
+                }
+
+                self.sieve_batch(&buffers, segments, &offsets, &mut bits)?;
+
+                // Unpack on the CPU and route exactly like the CPU producer
+                let next = AtomicUsize::new(0);
+                let stopped = thread::scope(|scope| {
+                    let workers: Vec<_> = (0..num_workers)
+                        .map(|_| {
+                            scope.spawn(|| {
+                                loop {
+                                    let k = next.fetch_add(1, Ordering::Relaxed);
+                                    if k >= segments {
+                                        return false;
+                                    }
+                                    let segment_idx = batch_start + k;
+                                    let seg_low = low + segment_idx * SEGMENT_SIZE_NUMBERS;
+                                    let seg_high = (seg_low + SEGMENT_SIZE_NUMBERS - 1).min(limit);
+                                    let words = SEGMENT_SIZE_BITS / 64;
+                                    let segment = &bits[k * words..(k + 1) * words];
+
+                                    let segment_id = segment_idx + 1;
+                                    let output = builder
+                                        .build_from_bits(segment, seg_low, seg_high, segment_id);
+                                    if let Some(backpressure) = backpressure {
+                                        backpressure.wait_for_capacity(B::byte_len(&output));
+                                    }
+                                    let consumer_idx = (segment_id - 1) % num_consumers;
+                                    if senders[consumer_idx].send(output).is_err() {
+                                        return true; // Receiver dropped
+                                    }
+                                    total_sent.fetch_add(1, Ordering::Relaxed);
+                                    crate::progress::inc(1);
+                                    crate::watchdog::wait_if_paused();
+                                }
+                            })
+                        })
+                        .collect();
+                    workers
+                        .into_iter()
+                        .any(|worker| worker.join().unwrap_or(true))
+                });
+                if stopped {
+                    break;
+                }
+                debug!(
+                    "[GPU] Sieved segments {}..{} of {}",
+                    batch_start + 1,
+                    batch_start + segments,
+                    total_segments
+                );
+            }
+
+            Ok(small_primes)
+        }
+
+        fn create_buffers(&self, odd_primes: &[u32]) -> Buffers {
+            let device = &self.device;
+            let bitmap_bytes = (BATCH_SEGMENTS * SEGMENT_WORDS_32 * 4) as u64;
+
+            let params = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("params"),
+                size: 8,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let primes = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("primes"),
+                contents: &u32_bytes(odd_primes),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+            let offsets = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("offsets"),
+                size: (odd_primes.len() * 4) as u64,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let composite = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("composite"),
+                size: bitmap_bytes,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let readback = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("readback"),
+                size: bitmap_bytes,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("sieve"),
+                layout: &self.pipeline.get_bind_group_layout(0),
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: params.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: primes.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: offsets.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: composite.as_entire_binding(),
+                    },
+                ],
+            });
+
+            Buffers {
+                params,
+                offsets,
+                composite,
+                readback,
+                bind_group,
+                num_primes: odd_primes.len() as u32,
+            }
+        }
+
+        /// Sieve `segments` segments and fill `bits` with odd-only prime bitmaps (1 = prime)
+        fn sieve_batch(
+            &self,
+            buffers: &Buffers,
+            segments: usize,
+            offsets: &[u32],
+            bits: &mut [u64],
+        ) -> io::Result<()> {
+            let params = [segments as u32, buffers.num_primes];
+            self.queue
+                .write_buffer(&buffers.params, 0, &u32_bytes(&params));
+            self.queue
+                .write_buffer(&buffers.offsets, 0, &u32_bytes(offsets));
+
+            let bitmap_bytes = (segments * SEGMENT_WORDS_32 * 4) as u64;
+            let mut encoder = self
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.clear_buffer(&buffers.composite, 0, None);
+            {
+                let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("sieve"),
+                    timestamp_writes: None,
+                });
+                pass.set_pipeline(&self.pipeline);
+                pass.set_bind_group(0, &buffers.bind_group, &[]);
+                let prime_groups = buffers.num_primes.div_ceil(WORKGROUP_SIZE).clamp(1, 65_535);
+                pass.dispatch_workgroups(prime_groups, segments as u32, SEGMENT_CHUNKS);
+            }
+            encoder.copy_buffer_to_buffer(
+                &buffers.composite,
+                0,
+                &buffers.readback,
+                0,
+                bitmap_bytes,
+            );
+            self.queue.submit(Some(encoder.finish()));
+
+            let slice = buffers.readback.slice(..bitmap_bytes);
+            let (tx, rx) = mpsc::channel();
+            slice.map_async(wgpu::MapMode::Read, move |result| {
+                let _ = tx.send(result);
+            });
+            let _ = self.device.poll(wgpu::Maintain::Wait);
+            rx.recv()
+                .map_err(io::Error::other)?
+                .map_err(io::Error::other)?;
+
+            // The GPU marks composites; flip to the CPU convention of 1 = prime
+            {
+                let mapped = slice.get_mapped_range();
+                for (word, chunk) in bits.iter_mut().zip(mapped.chunks_exact(8)) {
+                    *word = !u64::from_le_bytes(chunk.try_into().unwrap());
+                }
+            }
+            buffers.readback.unmap();
+            Ok(())
+        }
+    }
+
+    /// GPU buffers reused for every batch
+    struct Buffers {
+        params: wgpu::Buffer,
+        offsets: wgpu::Buffer,
+        composite: wgpu::Buffer,
+        readback: wgpu::Buffer,
+        bind_group: wgpu::BindGroup,
+        num_primes: u32,
+    }
+
+    fn u32_bytes(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+}
+
+#[cfg(not(feature = "gpu"))]
+mod disabled {
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::mpsc::SyncSender;
+
+    use crate::backpressure::Backpressure;
+    use crate::segment_format::SegmentBuilder;
+
+    /// Stand-in when nt is built without the `gpu` feature
+    pub struct GpuSieve;
+
+    impl GpuSieve {
+        pub fn new() -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "nt was built without GPU support (rebuild with --features gpu)",
+            ))
+        }
+
+        pub fn adapter_name(&self) -> &str {
+            ""
+        }
+
+        #[allow(clippy::too_many_arguments)]
+        pub fn find_primes_v9<B: SegmentBuilder>(
+            &self,
+            _limit: usize,
+            _sqrt_limit: usize,
+            _senders: Vec<SyncSender<B::Output>>,
+            _num_workers: usize,
+            _total_sent: Arc<AtomicUsize>,
+            _builder: &B,
+            _backpressure: Option<&Backpressure>,
+        ) -> io::Result<Vec<usize>> {
+            Self::new().map(|_| Vec::new())
+        }
+    }
+}
@@ -0,0 +1,432 @@
+// Import prime lists made by other programs (`nt import`)
+//
+// Each input is either text, one number per line (primesieve's --print output; blank lines
+// and # comments are skipped), or binary little-endian u64. Inputs must each be sorted, as
+// primesieve writes them, but may overlap: they are merged in one pass with repeats
+// dropped, so a list produced in chunks imports directly. Every other file in the data
+// directory assumes primes.txt and primes.bin hold every prime from 2 up to their last, so
+// the merged list is checked against a sieve run alongside it (prime_iter.rs) and rejected
+// if it holds a composite or skips a prime. The output is written through PrimeWriter, so
+// a failed import leaves the previous file in place. Before the merge starts, the largest
+// input value bounds the output size for the same free-space check `nt primes` makes.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+
+use crate::PrimeIterator;
+use crate::segment_format::SegmentEncoding;
+use crate::storage_direct::BinaryOutputOptions;
+use crate::storage_writer::PrimeWriter;
+
+/// How an input file is laid out
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// Binary for .bin files, text otherwise
+    Auto,
+    /// One decimal number per line
+    Text,
+    /// 8 bytes per number, little-endian
+    Binary,
+}
+
+impl InputFormat {
+    fn resolve(self, path: &Path) -> SegmentEncoding {
+        match self {
+            InputFormat::Text => SegmentEncoding::Text,
+            InputFormat::Binary => SegmentEncoding::Binary,
+            InputFormat::Auto if path.extension().is_some_and(|ext| ext == "bin") => {
+                SegmentEncoding::Binary
+            }
+            InputFormat::Auto => SegmentEncoding::Text,
+        }
+    }
+}
+
+/// Why an import stopped
+#[derive(Debug)]
+pub enum ImportError {
+    Io(String, io::Error),
+    /// A line that is not a number
+    Parse {
+        file: String,
+        line: u64,
+        text: String,
+    },
+    /// A binary file whose length is not a multiple of 8
+    Truncated(String),
+    /// A file that goes down
+    Unsorted {
+        file: String,
+        position: u64,
+        value: usize,
+        previous: usize,
+    },
+    NotPrime(usize),
+    /// The first prime below the end of the list that it does not hold
+    Missing(usize),
+    Empty,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(file, e) => write!(f, "{}: {}", file, e),
+            ImportError::Parse { file, line, text } => {
+                write!(f, "{} line {}: '{}' is not a number", file, line, text)
+            }
+            ImportError::Truncated(file) => {
+                write!(f, "{}: length is not a multiple of 8 bytes", file)
+            }
+            ImportError::Unsorted {
+                file,
+                position,
+                value,
+                previous,
+            } => write!(
+                f,
+                "{} is not sorted: #{} is {} after {}",
+                file, position, value, previous
+            ),
+            ImportError::NotPrime(n) => write!(f, "{} is not prime", n),
+            ImportError::Missing(p) => write!(f, "the list skips the prime {}", p),
+            ImportError::Empty => write!(f, "no primes in the input"),
+        }
+    }
+}
+
+/// Numbers read from one input
+pub type Numbers = Box<dyn Iterator<Item = Result<usize, ImportError>>>;
+
+/// Read the numbers in `path`
+pub fn open_input(path: &Path, format: InputFormat) -> Result<Numbers, ImportError> {
+    let name = path.display().to_string();
+    let file = File::open(path).map_err(|e| ImportError::Io(name.clone(), e))?;
+    let reader = BufReader::with_capacity(256 * 1024, file);
+    Ok(match format.resolve(path) {
+        SegmentEncoding::Text => Box::new(text_numbers(name, reader)),
+        SegmentEncoding::Binary => Box::new(binary_numbers(name, reader)),
+    })
+}
+
+fn text_numbers(
+    name: String,
+    reader: impl BufRead,
+) -> impl Iterator<Item = Result<usize, ImportError>> {
+    reader.lines().zip(1..).filter_map(move |(line, number)| {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => return Some(Err(ImportError::Io(name.clone(), e))),
+        };
+        let text = line.trim();
+        if text.is_empty() || text.starts_with('#') {
+            return None;
+        }
+        Some(text.parse().map_err(|_| ImportError::Parse {
+            file: name.clone(),
+            line: number,
+            text: text.to_string(),
+        }))
+    })
+}
+
+fn binary_numbers(
+    name: String,
+    mut reader: impl Read,
+) -> impl Iterator<Item = Result<usize, ImportError>> {
+    let mut failed = false;
+    std::iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        let mut bytes = [0_u8; 8];
+        let mut filled = 0;
+        while filled < 8 {
+            match reader.read(&mut bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    failed = true;
+                    return Some(Err(ImportError::Io(name.clone(), e)));
+                }
+            }
+        }
+        match filled {
+            0 => None,
+            8 => Some(Ok(u64::from_le_bytes(bytes) as usize)),
+            _ => {
+                failed = true;
+                Some(Err(ImportError::Truncated(name.clone())))
+            }
+        }
+    })
+}
+
+/// The last number in `path`, read from the end of the file so a large input costs one seek
+/// None for an input with no numbers near its end; the merge reports what is wrong with it
+pub fn last_number(path: &Path, format: InputFormat) -> io::Result<Option<usize>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    match format.resolve(path) {
+        SegmentEncoding::Binary => {
+            if len < 8 {
+                return Ok(None);
+            }
+            let mut bytes = [0_u8; 8];
+            file.seek(SeekFrom::Start(len / 8 * 8 - 8))?;
+            file.read_exact(&mut bytes)?;
+            Ok(Some(u64::from_le_bytes(bytes) as usize))
+        }
+        SegmentEncoding::Text => {
+            let mut tail = Vec::new();
+            file.seek(SeekFrom::Start(len.saturating_sub(4096)))?;
+            file.read_to_end(&mut tail)?;
+            Ok(String::from_utf8_lossy(&tail)
+                .lines()
+                .rev()
+                .map(str::trim)
+                .find(|text| !text.is_empty() && !text.starts_with('#'))
+                .and_then(|text| text.parse().ok()))
+        }
+    }
+}
+
+/// Upper bound on the bytes importing `inputs` writes, or None if an input's end is unreadable
+pub fn estimate_output_bytes(
+    inputs: &[PathBuf],
+    format: InputFormat,
+    encoding: SegmentEncoding,
+) -> Option<u64> {
+    let mut largest = 0;
+    for path in inputs {
+        largest = largest.max(last_number(path, format).ok()??);
+    }
+    Some(crate::storage::estimate_output_bytes(
+        largest,
+        encoding == SegmentEncoding::Binary,
+    ))
+}
+
+/// What an import read and wrote
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Numbers read over all inputs
+    pub read: u64,
+    /// Numbers dropped as repeats
+    pub duplicates: u64,
+    /// Primes written
+    pub written: u64,
+    pub largest: usize,
+}
+
+/// One input and where it is up to
+struct Source {
+    name: String,
+    numbers: Numbers,
+    position: u64,
+    previous: Option<usize>,
+}
+
+impl Source {
+    fn next(&mut self) -> Result<Option<usize>, ImportError> {
+        let Some(value) = self.numbers.next().transpose()? else {
+            return Ok(None);
+        };
+        self.position += 1;
+        if let Some(previous) = self.previous
+            && value < previous
+        {
+            return Err(ImportError::Unsorted {
+                file: self.name.clone(),
+                position: self.position,
+                value,
+                previous,
+            });
+        }
+        self.previous = Some(value);
+        Ok(Some(value))
+    }
+}
+
+/// Merge sorted `inputs` (name, numbers), drop repeats, check the result is exactly the
+/// primes from 2 to its last, and hand each prime to `emit`
+pub fn merge_validated(
+    inputs: Vec<(String, Numbers)>,
+    mut emit: impl FnMut(usize) -> io::Result<()>,
+) -> Result<ImportReport, ImportError> {
+    let mut sources: Vec<Source> = inputs
+        .into_iter()
+        .map(|(name, numbers)| Source {
+            name,
+            numbers,
+            position: 0,
+            previous: None,
+        })
+        .collect();
+    let mut heads = BinaryHeap::new();
+    for (index, source) in sources.iter_mut().enumerate() {
+        if let Some(value) = source.next()? {
+            heads.push(Reverse((value, index)));
+        }
+    }
+
+    let mut report = ImportReport::default();
+    let mut sieve = PrimeIterator::new();
+    let mut last = None;
+    while let Some(Reverse((value, index))) = heads.pop() {
+        report.read += 1;
+        if let Some(next) = sources[index].next()? {
+            heads.push(Reverse((next, index)));
+        }
+        if last == Some(value) {
+            report.duplicates += 1;
+            continue;
+        }
+        last = Some(value);
+
+        // A prime the sieve gives before `value` is missing from the list
+        match sieve.next() {
+            Some(p) if p < value => return Err(ImportError::Missing(p)),
+            Some(p) if p == value => {}
+            _ => return Err(ImportError::NotPrime(value)),
+        }
+        emit(value).map_err(|e| ImportError::Io("output".to_string(), e))?;
+        report.written += 1;
+        report.largest = value;
+    }
+    if report.written == 0 {
+        return Err(ImportError::Empty);
+    }
+    Ok(report)
+}
+
+/// Import `inputs` into primes.txt or primes.bin in the data directory
+pub fn run(
+    inputs: &[PathBuf],
+    format: InputFormat,
+    encoding: SegmentEncoding,
+) -> Result<(ImportReport, String), ImportError> {
+    let inputs = inputs
+        .iter()
+        .map(|path| Ok((path.display().to_string(), open_input(path, format)?)))
+        .collect::<Result<Vec<_>, ImportError>>()?;
+    let options = BinaryOutputOptions {
+        direct_io: false,
+        preallocate_bytes: 0,
+    };
+    let mut writer = PrimeWriter::create("primes", encoding, &options, 1 << 20)
+        .map_err(|e| ImportError::Io("primes".to_string(), e))?;
+    let report = merge_validated(inputs, |prime| writer.write_prime(prime))?;
+    let name = writer.name().to_string();
+    writer
+        .finish()
+        .map_err(|e| ImportError::Io(name.clone(), e))?;
+    Ok((report, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(name: &str, values: &[usize]) -> (String, Numbers) {
+        let values: Vec<Result<usize, ImportError>> = values.iter().map(|&v| Ok(v)).collect();
+        (name.to_string(), Box::new(values.into_iter()))
+    }
+
+    fn merge(inputs: Vec<(String, Numbers)>) -> Result<(ImportReport, Vec<usize>), ImportError> {
+        let mut out = Vec::new();
+        let report = merge_validated(inputs, |p| {
+            out.push(p);
+            Ok(())
+        })?;
+        Ok((report, out))
+    }
+
+    #[test]
+    fn test_overlapping_chunks_merge_without_repeats() {
+        let (report, primes) = merge(vec![
+            input("b", &[11, 13, 17, 19, 23]),
+            input("a", &[2, 3, 5, 7, 11, 13]),
+        ])
+        .unwrap();
+        assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19, 23]);
+        assert_eq!(
+            report,
+            ImportReport {
+                read: 11,
+                duplicates: 2,
+                written: 9,
+                largest: 23
+            }
+        );
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let err = |values: &[usize]| merge(vec![input("a", values)]).unwrap_err().to_string();
+        assert_eq!(err(&[2, 3, 5, 7, 9, 11]), "9 is not prime");
+        assert_eq!(err(&[2, 3, 7]), "the list skips the prime 5");
+        assert_eq!(err(&[3, 5, 7]), "the list skips the prime 2");
+        assert_eq!(err(&[2, 5, 3]), "a is not sorted: #3 is 3 after 5");
+        assert_eq!(err(&[]), "no primes in the input");
+    }
+
+    #[test]
+    fn test_readers() {
+        let text = "# primesieve\n2\n\n 3 \nfive\n";
+        let numbers: Vec<_> = text_numbers("t".to_string(), text.as_bytes()).collect();
+        assert_eq!(numbers.len(), 3);
+        assert_eq!(numbers[1].as_ref().unwrap(), &3);
+        assert_eq!(
+            numbers[2].as_ref().unwrap_err().to_string(),
+            "t line 5: 'five' is not a number"
+        );
+
+        let mut bytes: Vec<u8> = [2u64, 3].iter().flat_map(|p| p.to_le_bytes()).collect();
+        bytes.push(5);
+        let numbers: Vec<_> = binary_numbers("b".to_string(), bytes.as_slice()).collect();
+        assert_eq!(numbers.len(), 3);
+        assert_eq!(numbers[0].as_ref().unwrap(), &2);
+        assert!(matches!(numbers[2], Err(ImportError::Truncated(_))));
+    }
+
+    #[test]
+    fn test_last_number_bounds_the_output() {
+        let dir = std::env::temp_dir().join(format!("nt_import_last_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let text = dir.join("a.txt");
+        std::fs::write(&text, "2\n3\n5\n7\n\n# done\n").unwrap();
+        let binary = dir.join("b.bin");
+        let bytes: Vec<u8> = [2u64, 3, 5, 7, 11]
+            .iter()
+            .flat_map(|p| p.to_le_bytes())
+            .collect();
+        std::fs::write(&binary, &bytes[..39]).unwrap();
+
+        assert_eq!(last_number(&text, InputFormat::Auto).unwrap(), Some(7));
+        // A trailing partial record is ignored here and reported by the merge
+        assert_eq!(last_number(&binary, InputFormat::Auto).unwrap(), Some(7));
+        assert_eq!(
+            estimate_output_bytes(
+                &[text, binary.clone()],
+                InputFormat::Auto,
+                SegmentEncoding::Text
+            ),
+            Some(crate::storage::estimate_output_bytes(7, false))
+        );
+
+        std::fs::write(&binary, [1, 2, 3]).unwrap();
+        assert_eq!(last_number(&binary, InputFormat::Auto).unwrap(), None);
+        assert_eq!(
+            estimate_output_bytes(&[binary], InputFormat::Auto, SegmentEncoding::Binary),
+            None
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+// Legendre's conjecture (`nt legendre`)
+//
+// Counts the primes between n² and (n + 1)² for each n in a range, reporting any empty
+// interval and the ones with the fewest primes, absolutely and against the
+// (2n + 1) / ln(n² + n) the prime number theorem expects.
+
+use crate::parallel::{chunk_size, parallel_chunks};
+use crate::primes::for_each_prime_in_range;
+
+/// The primes strictly between n² and (n + 1)²
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interval {
+    pub n: usize,
+    pub primes: u64,
+}
+
+impl Interval {
+    /// (2n + 1) / ln(n² + n), the count the prime number theorem expects
+    pub fn expected(&self) -> f64 {
+        let n = self.n as f64;
+        (2.0 * n + 1.0) / (n * n + n).ln()
+    }
+
+    /// Primes found over primes expected
+    pub fn ratio(&self) -> f64 {
+        self.primes as f64 / self.expected()
+    }
+}
+
+/// What checking a range of n found
+#[derive(Clone, Debug, PartialEq)]
+pub struct Check {
+    /// Intervals with no prime, which would disprove the conjecture
+    pub empty: Vec<usize>,
+    /// Fewest primes, smallest n on ties
+    pub fewest: Interval,
+    /// Lowest ratio of primes to the expected count, smallest n on ties
+    pub sparsest: Interval,
+}
+
+impl Check {
+    fn add(&mut self, interval: Interval) {
+        if interval.primes == 0 {
+            self.empty.push(interval.n);
+        }
+        self.consider(interval);
+    }
+
+    /// Keep `interval` if it is the fewest or sparsest so far
+    fn consider(&mut self, interval: Interval) {
+        if (interval.primes, interval.n) < (self.fewest.primes, self.fewest.n) {
+            self.fewest = interval;
+        }
+        if (interval.ratio(), interval.n) < (self.sparsest.ratio(), self.sparsest.n) {
+            self.sparsest = interval;
+        }
+    }
+
+    fn merge(&mut self, other: Check) {
+        self.empty.extend(other.empty);
+        self.consider(other.fewest);
+        self.consider(other.sparsest);
+    }
+}
+
+/// Check the intervals for n in [from, to]; `from` must be at least 1 and at most `to`
+pub fn check(from: usize, to: usize, num_workers: usize) -> Check {
+    assert!(
+        (1..=to).contains(&from),
+        "n must run over 1 <= from <= to, not [{}, {}]",
+        from,
+        to
+    );
+    let mut found = None::<Check>;
+    parallel_chunks(
+        from..=to,
+        chunk_size(to - from + 1, num_workers, 1),
+        num_workers,
+        check_run,
+        |checked| match found.as_mut() {
+            Some(total) => total.merge(checked),
+            None => found = Some(checked),
+        },
+    );
+    let mut total = found.unwrap();
+    total.empty.sort_unstable();
+    total
+}
+
+/// Check the intervals for n in [low, high] on the calling thread
+fn check_run(low: usize, high: usize) -> Check {
+    let mut counts = vec![0_u64; high - low + 1];
+    for_each_prime_in_range(low * low, (high + 1) * (high + 1), |primes| {
+        for &p in primes {
+            counts[p.isqrt() - low] += 1;
+        }
+    });
+    let first = Interval {
+        n: low,
+        primes: counts[0],
+    };
+    let mut checked = Check {
+        empty: Vec::new(),
+        fewest: first,
+        sparsest: first,
+    };
+    for (n, &primes) in (low..=high).zip(&counts) {
+        checked.add(Interval { n, primes });
+    }
+    checked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        // Counts from n = 1: 2, 2, 2, 3, 2, 4, 3, 4, 3, 5, 4, ...
+        let checked = check(1, 3_000, 4);
+        assert!(checked.empty.is_empty());
+        assert_eq!(checked.fewest, Interval { n: 1, primes: 2 });
+        assert_eq!(checked.sparsest.n, 1);
+
+        // Above 100 the sparsest interval is (119², 120²), 19 primes for 25.0 expected
+        let checked = check(100, 3_000, 3);
+        assert_eq!(checked.sparsest, Interval { n: 119, primes: 19 });
+        assert!((checked.sparsest.ratio() - 0.760_526).abs() < 1e-6);
+        assert_eq!(check(5, 5, 1).fewest, Interval { n: 5, primes: 2 });
+    }
+
+    #[test]
+    #[should_panic(expected = "1 <= from <= to")]
+    fn test_from_above_to() {
+        check(10, 9, 2);
+    }
+
+    #[test]
+    fn test_runs_across_segment_boundaries() {
+        // (n + 1)² reaches 9·10^6, so the windows span many sieve segments
+        let (from, to) = (700, 3_000);
+        let mut counts = vec![0_u64; to + 1];
+        for p in crate::primes::sieve((to + 1) * (to + 1)) {
+            if p.isqrt() >= from && p.isqrt() <= to {
+                counts[p.isqrt()] += 1;
+            }
+        }
+        let fewest = (from..=to).min_by_key(|&n| (counts[n], n)).unwrap();
+        for workers in [1, 3, 8] {
+            let checked = check(from, to, workers);
+            assert!(checked.empty.is_empty());
+            assert_eq!(
+                checked.fewest,
+                Interval {
+                    n: fewest,
+                    primes: counts[fewest]
+                }
+            );
+            assert_eq!(checked.sparsest.primes, counts[checked.sparsest.n]);
+        }
+    }
+}
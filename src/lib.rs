@@ -0,0 +1,189 @@
+//! Number theory toolkit behind the `nt` command line tool
+//!
+//! Library entry points:
+//! - `primes::sieve(limit)` for every prime up to a limit (`native` only)
+//! - `PrimeIterator` for primes without a limit
+//! - `PrimeSet` for constant-time primality lookups up to a limit
+//! - `factor::factorize` and `factor::is_prime` for u64
+//! - `constants::pi` for π to any number of digits
+//! - `ffi` for the same from C (include/nt.h, via the cdylib)
+//!
+//! The remaining modules are the storage pipelines and subcommands the CLI is built from.
+//! They need the default `native` feature; without it only the pure Rust core is built
+//! (prime sets and iterators, factorization, base conversion and π), which is what the
+//! `wasm` feature exports to JavaScript.
+
+#[cfg(feature = "native")]
+pub mod abc;
+#[cfg(feature = "native")]
+pub mod affinity;
+#[cfg(feature = "native")]
+pub mod aliquot;
+#[cfg(feature = "native")]
+pub mod arith;
+#[cfg(feature = "native")]
+pub mod arith_table;
+#[cfg(feature = "native")]
+pub mod audit;
+#[cfg(feature = "native")]
+pub mod automorphic;
+#[cfg(feature = "native")]
+pub mod backpressure;
+#[cfg(feature = "native")]
+pub mod bertrand;
+#[cfg(feature = "native")]
+pub mod bigfactor;
+#[cfg(feature = "native")]
+pub mod bpsw;
+#[cfg(feature = "native")]
+pub mod buffer_pool;
+#[cfg(feature = "native")]
+pub mod certify;
+#[cfg(feature = "native")]
+pub mod chain;
+#[cfg(feature = "native")]
+pub mod clusters;
+pub mod constants;
+#[cfg(feature = "native")]
+pub mod convert;
+#[cfg(feature = "native")]
+pub mod data;
+#[cfg(feature = "native")]
+pub mod digit_source;
+#[cfg(feature = "native")]
+pub mod digit_tree;
+#[cfg(feature = "native")]
+pub mod distributed;
+#[cfg(feature = "native")]
+pub mod ducci;
+#[cfg(feature = "native")]
+pub mod ecm;
+#[cfg(feature = "native")]
+pub mod export;
+pub mod factor;
+#[cfg(feature = "native")]
+pub mod factor_batch;
+#[cfg(feature = "native")]
+pub mod farey;
+#[cfg(feature = "native")]
+pub mod fermat;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+#[cfg(feature = "native")]
+pub mod fortunate;
+#[cfg(feature = "native")]
+pub mod gap_firsts;
+#[cfg(feature = "native")]
+pub mod gaps;
+#[cfg(feature = "native")]
+pub mod gpu;
+#[cfg(feature = "native")]
+pub mod huge_pages;
+#[cfg(feature = "native")]
+pub mod import;
+#[cfg(feature = "native")]
+pub mod legendre;
+#[cfg(feature = "native")]
+pub mod logging;
+#[cfg(feature = "native")]
+pub mod microbench;
+#[cfg(unix)]
+pub mod mmap;
+pub mod near;
+#[cfg(feature = "native")]
+pub mod ormiston;
+#[cfg(feature = "native")]
+pub mod palindromes;
+#[cfg(feature = "native")]
+pub mod parallel;
+#[cfg(feature = "native")]
+pub mod persistence;
+#[cfg(feature = "native")]
+pub mod pi;
+#[cfg(feature = "native")]
+pub mod pi_table;
+#[cfg(feature = "native")]
+pub mod pm1;
+#[cfg(feature = "native")]
+pub mod pocklington;
+#[cfg(feature = "native")]
+pub mod prime_count;
+#[cfg(feature = "native")]
+pub mod prime_diff;
+#[cfg(feature = "native")]
+pub mod prime_digits;
+#[cfg(feature = "native")]
+pub mod prime_index;
+pub mod prime_iter;
+pub mod prime_set;
+#[cfg(feature = "native")]
+pub mod prime_stats;
+#[cfg(feature = "native")]
+pub mod prime_sum;
+#[cfg(feature = "native")]
+pub mod primes;
+pub mod primes_bases;
+#[cfg(feature = "native")]
+pub mod primorial;
+#[cfg(feature = "native")]
+pub mod product_tree;
+#[cfg(feature = "native")]
+pub mod progress;
+pub mod radix;
+#[cfg(feature = "native")]
+pub mod random;
+#[cfg(feature = "native")]
+pub mod random_prime;
+pub mod rational;
+#[cfg(feature = "native")]
+pub mod ruth_aaron;
+#[cfg(feature = "native")]
+pub mod scan;
+#[cfg(feature = "native")]
+pub mod scan_results;
+#[cfg(feature = "native")]
+pub mod segment_format;
+#[cfg(feature = "native")]
+pub mod segments;
+#[cfg(feature = "native")]
+pub mod selftest;
+#[cfg(feature = "native")]
+pub mod sequence;
+#[cfg(feature = "native")]
+pub mod shard_manifest;
+#[cfg(feature = "native")]
+pub mod smarandache;
+pub mod spf;
+pub mod stern_brocot;
+#[cfg(feature = "native")]
+pub mod storage;
+#[cfg(feature = "native")]
+pub mod storage_async;
+#[cfg(feature = "native")]
+pub mod storage_direct;
+#[cfg(all(feature = "native", target_os = "linux"))]
+mod storage_uring;
+#[cfg(feature = "native")]
+pub mod storage_writer;
+#[cfg(feature = "native")]
+pub mod stream_output;
+#[cfg(feature = "native")]
+pub mod throughput;
+#[cfg(feature = "native")]
+pub mod tui;
+#[cfg(feature = "native")]
+pub mod ulam;
+#[cfg(feature = "native")]
+pub mod unbounded;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "native")]
+pub mod watchdog;
+#[cfg(feature = "native")]
+pub mod weird;
+#[cfg(feature = "native")]
+pub mod wilson;
+
+pub use prime_iter::PrimeIterator;
+pub use prime_set::PrimeSet;
+pub use spf::SpfTable;
@@ -0,0 +1,128 @@
+// Log output for all subcommands, controlled by the top-level -q/-v flags
+//
+// Levels used across the crate:
+// - error: failures that stop or skip work
+// - warn:  degraded behaviour (fallbacks, oversized reorder buffers)
+// - info:  normal run output (configuration, timings, totals) - the default
+// - debug: periodic memory and channel reports from producers and consumers (-v)
+// - trace: anything noisier (-vv)
+//
+// Full-screen output (the --tui dashboard) suspends the console: info and above are held
+// back and printed once it resumes, debug and trace are dropped since the screen shows them.
+// When stdout carries data (primes from --tee stdout) everything is logged to stderr.
+
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing::{Level, Metadata};
+use tracing_subscriber::fmt::MakeWriter;
+
+static SUSPENDED: AtomicBool = AtomicBool::new(false);
+static STDOUT_RESERVED: AtomicBool = AtomicBool::new(false);
+
+// Lines logged while suspended, with whether they belong on stderr
+static HELD: Mutex<Vec<(bool, Vec<u8>)>> = Mutex::new(Vec::new());
+
+/// Install the global subscriber; call once at startup
+/// Warnings and errors go to stderr, everything else to stdout (like the old eprintln!/println!)
+pub fn init(quiet: bool, verbose: u8) {
+    let level = if quiet {
+        Level::ERROR
+    } else {
+        match verbose {
+            0 => Level::INFO,
+            1 => Level::DEBUG,
+            _ => Level::TRACE,
+        }
+    };
+
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(Console)
+        .without_time()
+        .with_target(false)
+        .with_level(false)
+        .init();
+}
+
+/// Log everything to stderr from now on, leaving stdout to data
+pub fn reserve_stdout() {
+    STDOUT_RESERVED.store(true, Ordering::Relaxed);
+}
+
+/// Stop writing to the terminal until `resume` is called
+pub fn suspend() {
+    SUSPENDED.store(true, Ordering::Relaxed);
+}
+
+/// Write to the terminal again, printing everything held back in the meantime
+pub fn resume() {
+    SUSPENDED.store(false, Ordering::Relaxed);
+
+    let held = std::mem::take(&mut *HELD.lock().unwrap());
+    for (to_stderr, line) in held {
+        let _ = if to_stderr {
+            io::stderr().write_all(&line)
+        } else {
+            io::stdout().write_all(&line)
+        };
+    }
+}
+
+/// Routes each event to stderr (warn and above), stdout, or the held lines
+struct Console;
+
+impl<'a> MakeWriter<'a> for Console {
+    type Writer = Box<dyn Write>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        if STDOUT_RESERVED.load(Ordering::Relaxed) {
+            Box::new(io::stderr())
+        } else {
+            Box::new(io::stdout())
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        let to_stderr = *meta.level() <= Level::WARN || STDOUT_RESERVED.load(Ordering::Relaxed);
+
+        if SUSPENDED.load(Ordering::Relaxed) {
+            if *meta.level() <= Level::INFO {
+                Box::new(HeldLine {
+                    to_stderr,
+                    line: Vec::new(),
+                })
+            } else {
+                Box::new(io::sink())
+            }
+        } else if to_stderr {
+            Box::new(io::stderr())
+        } else {
+            Box::new(io::stdout())
+        }
+    }
+}
+
+/// One formatted event, added to HELD when the formatter is done with it
+struct HeldLine {
+    to_stderr: bool,
+    line: Vec<u8>,
+}
+
+impl Write for HeldLine {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.line.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for HeldLine {
+    fn drop(&mut self) {
+        let line = std::mem::take(&mut self.line);
+        HELD.lock().unwrap().push((self.to_stderr, line));
+    }
+}
@@ -0,0 +1,195 @@
+// Micro-benchmarks of the sieve's hot loops (`nt microbench`)
+//
+// Each benchmark repeats one unit of work from the parallel variations on a segment near
+// 10^9: sieving it (primes.rs), unpacking its bitmap (segment_format.rs), formatting its
+// primes as decimal text, and passing segments through a bounded channel to another thread.
+// A benchmark runs once to warm up, then in batches until its time budget is spent; the
+// reported time per operation is the fastest batch's, which is the least disturbed by
+// scheduling and frequency changes. Operations are primes for unpacking and formatting,
+// segments for sieving and messages for the channel.
+
+use std::hint::black_box;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::primes::{self, SEGMENT_SIZE_BITS, SEGMENT_SIZE_NUMBERS};
+use crate::segment_format::for_each_prime;
+
+// Start of the benchmark segment (odd)
+const SEGMENT_LOW: usize = 1_000_000_001;
+
+// Messages per channel batch and the channel's bound, as in the parallel variations
+const CHANNEL_MESSAGES: usize = 10_000;
+const CHANNEL_BOUND: usize = 64;
+
+// Batches the time budget is split into
+const BATCHES: u32 = 10;
+
+/// The benchmarks, in the order they run
+pub const NAMES: [&str; 4] = ["sieve", "unpack", "itoa", "channel"];
+
+/// Timing of one benchmark
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timing {
+    pub name: &'static str,
+    /// What an operation is
+    pub unit: &'static str,
+    pub operations: u64,
+    /// Fastest batch, per operation
+    pub nanos_per_op: f64,
+}
+
+impl Timing {
+    pub fn ops_per_second(&self) -> f64 {
+        1e9 / self.nanos_per_op
+    }
+}
+
+/// A sieved segment and its primes, shared by the benchmarks
+struct Fixture {
+    base: Vec<usize>,
+    low: usize,
+    high: usize,
+    bits: Vec<u64>,
+    primes: Vec<usize>,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let low = SEGMENT_LOW;
+        let high = low + SEGMENT_SIZE_NUMBERS - 2;
+        let base = primes::sieve(high.isqrt());
+        let mut bits = vec![0_u64; SEGMENT_SIZE_BITS / 64];
+        primes::sieve_segment(&base, low, high, &mut bits);
+        let mut primes = Vec::new();
+        for_each_prime(&bits, low, high, |p| primes.push(p));
+        Self {
+            base,
+            low,
+            high,
+            bits,
+            primes,
+        }
+    }
+}
+
+/// Run the named benchmarks (all of them if `names` is empty), each for about `budget`
+pub fn run(names: &[String], budget: Duration) -> Result<Vec<Timing>, String> {
+    if let Some(unknown) = names.iter().find(|name| !NAMES.contains(&name.as_str())) {
+        return Err(format!(
+            "no benchmark named '{}' (try {})",
+            unknown,
+            NAMES.join(", ")
+        ));
+    }
+    let fixture = Fixture::new();
+    let batch_budget = budget / BATCHES;
+    let timings = NAMES
+        .iter()
+        .filter(|&&name| names.is_empty() || names.iter().any(|n| n == name))
+        .map(|&name| match name {
+            "sieve" => {
+                let mut bits = vec![0_u64; fixture.bits.len()];
+                time(name, "segment", batch_budget, || {
+                    primes::sieve_segment(&fixture.base, fixture.low, fixture.high, &mut bits);
+                    black_box(&bits);
+                    1
+                })
+            }
+            "unpack" => {
+                let mut primes = Vec::with_capacity(fixture.primes.len());
+                time(name, "prime", batch_budget, || {
+                    primes.clear();
+                    for_each_prime(black_box(&fixture.bits), fixture.low, fixture.high, |p| {
+                        primes.push(p)
+                    });
+                    black_box(&primes);
+                    primes.len() as u64
+                })
+            }
+            "itoa" => {
+                let mut bytes = Vec::with_capacity(fixture.primes.len() * 11);
+                let mut itoa_buf = itoa::Buffer::new();
+                time(name, "prime", batch_budget, || {
+                    bytes.clear();
+                    for &p in black_box(&fixture.primes) {
+                        bytes.extend_from_slice(itoa_buf.format(p).as_bytes());
+                        bytes.push(b'\n');
+                    }
+                    black_box(&bytes);
+                    fixture.primes.len() as u64
+                })
+            }
+            _ => time(name, "message", batch_budget, send_messages),
+        })
+        .collect();
+    Ok(timings)
+}
+
+/// Send CHANNEL_MESSAGES small segments to a receiving thread and wait for it
+fn send_messages() -> u64 {
+    let (sender, receiver) = mpsc::sync_channel::<Vec<usize>>(CHANNEL_BOUND);
+    let consumer = thread::spawn(move || receiver.iter().map(|v| v.len()).sum::<usize>());
+    for i in 0..CHANNEL_MESSAGES {
+        sender.send(vec![i]).unwrap();
+    }
+    drop(sender);
+    black_box(consumer.join().unwrap());
+    CHANNEL_MESSAGES as u64
+}
+
+/// Warm up, then run batches of `work` (which returns the operations it did) and keep the
+/// fastest batch
+fn time(
+    name: &'static str,
+    unit: &'static str,
+    batch_budget: Duration,
+    mut work: impl FnMut() -> u64,
+) -> Timing {
+    work();
+    let mut operations = 0;
+    let mut best = f64::INFINITY;
+    for _ in 0..BATCHES {
+        let started = Instant::now();
+        let mut batch_ops = 0;
+        loop {
+            batch_ops += work();
+            if started.elapsed() >= batch_budget {
+                break;
+            }
+        }
+        let nanos = started.elapsed().as_nanos() as f64;
+        best = best.min(nanos / batch_ops as f64);
+        operations += batch_ops;
+    }
+    Timing {
+        name,
+        unit,
+        operations,
+        nanos_per_op: best,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixture_is_the_sieved_segment() {
+        let fixture = Fixture::new();
+        assert_eq!(
+            fixture.primes,
+            primes::sieve_range(fixture.low, fixture.high)
+        );
+    }
+
+    #[test]
+    fn test_run_selects_benchmarks() {
+        let timings = run(&["itoa".to_string()], Duration::from_millis(10)).unwrap();
+        assert_eq!(timings.len(), 1);
+        assert_eq!(timings[0].name, "itoa");
+        assert!(timings[0].operations > 0 && timings[0].nanos_per_op > 0.0);
+        assert!(run(&["bogus".to_string()], Duration::from_millis(10)).is_err());
+    }
+}
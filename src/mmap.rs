@@ -0,0 +1,94 @@
+// Read-only memory maps of saved tables
+//
+// Prime sets, spf tables and arithmetic-function tables are all reopened by mapping the
+// whole file, so a lookup only pages in what it reads. Each format checks its own header
+// and then borrows its entries from the mapping as bytes or 8-byte words.
+
+use std::fs::File;
+use std::io;
+use std::os::fd::AsRawFd;
+
+/// Read-only mapping of a whole file
+pub struct MappedFile {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+// The mapping is read-only and never changes after construction
+unsafe impl Send for MappedFile {}
+unsafe impl Sync for MappedFile {}
+
+impl MappedFile {
+    pub fn map(file: &File) -> io::Result<Self> {
+        let len = file.metadata()?.len() as usize;
+
+        // mmap rejects empty mappings; an empty file maps to nothing
+        if len == 0 {
+            return Ok(Self {
+                ptr: std::ptr::null_mut(),
+                len,
+            });
+        }
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr, len })
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    /// The whole 8-byte words from byte `offset` on, which must be a multiple of 8
+    #[cfg(target_endian = "little")]
+    pub fn u64s(&self, offset: usize) -> &[u64] {
+        match self.word_bytes(offset) {
+            Some(words) => unsafe {
+                std::slice::from_raw_parts(words.as_ptr() as *const u64, words.len() / 8)
+            },
+            None => &[],
+        }
+    }
+
+    /// `u64s` read as signed values
+    #[cfg(target_endian = "little")]
+    pub fn i64s(&self, offset: usize) -> &[i64] {
+        match self.word_bytes(offset) {
+            Some(words) => unsafe {
+                std::slice::from_raw_parts(words.as_ptr() as *const i64, words.len() / 8)
+            },
+            None => &[],
+        }
+    }
+
+    /// The mapped bytes from `offset` on, or None without a whole word to point into
+    #[cfg(target_endian = "little")]
+    fn word_bytes(&self, offset: usize) -> Option<&[u8]> {
+        assert!(offset.is_multiple_of(8));
+        // mmap is page aligned and `offset` is a multiple of 8, so the words are aligned
+        self.bytes().get(offset..).filter(|words| words.len() >= 8)
+    }
+}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
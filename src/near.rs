@@ -0,0 +1,189 @@
+// Nearest-prime queries (`nt near`)
+//
+// With a saved PrimeSet covering n, every answer is a few bit lookups into the mapped
+// file. Otherwise the neighbourhood of n is scanned in windows: each window is sieved by
+// the odd primes below 2^16, which removes ~95% of it, and the survivors are tried with
+// the deterministic u64 Miller-Rabin test in order until one is prime. Prime gaps below
+// 2^64 are at most 1550, so the first window almost always settles it.
+
+use crate::PrimeSet;
+use crate::factor;
+
+// Odd numbers per scan window
+const WINDOW_ODDS: usize = 2048;
+
+// Presieve bound; survivors below its square are prime without further testing
+const PRESIEVE_LIMIT: usize = 1 << 16;
+
+/// Whether n is prime and the primes either side of it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Neighbors {
+    pub n: usize,
+    pub is_prime: bool,
+    /// Largest prime < n
+    pub previous: Option<usize>,
+    /// Smallest prime > n (None past the last prime below usize::MAX)
+    pub next: Option<usize>,
+}
+
+/// Finds neighbouring primes, through `set` wherever it covers the numbers asked about
+pub struct NearestPrimes<'a> {
+    set: Option<&'a PrimeSet>,
+    small_primes: Vec<usize>,
+}
+
+impl<'a> NearestPrimes<'a> {
+    pub fn new(set: Option<&'a PrimeSet>) -> Self {
+        Self {
+            set,
+            small_primes: PrimeSet::new(PRESIEVE_LIMIT).iter().skip(1).collect(),
+        }
+    }
+
+    pub fn is_prime(&self, n: usize) -> bool {
+        match self.set {
+            Some(set) if n <= set.limit() => set.contains(n),
+            _ => factor::is_prime(n as u64),
+        }
+    }
+
+    pub fn near(&self, n: usize) -> Neighbors {
+        Neighbors {
+            n,
+            is_prime: self.is_prime(n),
+            previous: self.previous(n),
+            next: self.next(n),
+        }
+    }
+
+    /// Largest prime < n
+    pub fn previous(&self, n: usize) -> Option<usize> {
+        if n <= 3 {
+            return (n == 3).then_some(2);
+        }
+        // Largest odd number below n
+        let mut high = (n - 1) | 1;
+        if high >= n {
+            high -= 2;
+        }
+        loop {
+            if let Some(set) = self.set
+                && high <= set.limit()
+            {
+                return (3..=high)
+                    .rev()
+                    .step_by(2)
+                    .find(|&m| set.contains(m))
+                    .or(Some(2));
+            }
+            let low = high.saturating_sub(2 * (WINDOW_ODDS - 1)).max(3);
+            let survivors = self.presieve(low, high);
+            if let Some(prime) = survivors.into_iter().rev().find(|&m| self.confirm(m)) {
+                return Some(prime);
+            }
+            if low == 3 {
+                return Some(2);
+            }
+            high = low - 2;
+        }
+    }
+
+    /// Smallest prime > n
+    pub fn next(&self, n: usize) -> Option<usize> {
+        if n < 2 {
+            return Some(2);
+        }
+        // Smallest odd number above n
+        let mut low = n.checked_add(1)? | 1;
+        loop {
+            if let Some(set) = self.set
+                && low <= set.limit()
+            {
+                if let Some(prime) = (low..=set.limit()).step_by(2).find(|&m| set.contains(m)) {
+                    return Some(prime);
+                }
+                low = (set.limit() + 1) | 1;
+                continue;
+            }
+            let high = low.saturating_add(2 * (WINDOW_ODDS - 1));
+            let survivors = self.presieve(low, high);
+            if let Some(prime) = survivors.into_iter().find(|&m| self.confirm(m)) {
+                return Some(prime);
+            }
+            low = high.checked_add(2)?;
+        }
+    }
+
+    /// Odd numbers in [low, high] (both odd) with no odd prime factor below 2^16
+    fn presieve(&self, low: usize, high: usize) -> Vec<usize> {
+        let count = (high - low) / 2 + 1;
+        let mut candidate = vec![true; count];
+        for &p in &self.small_primes {
+            if p * p > high {
+                break;
+            }
+            // Offset of the first odd multiple of p from low, never p itself; offsets keep
+            // this clear of overflow at the top of the range
+            let offset = if low <= p * p {
+                p * p - low
+            } else {
+                let to_multiple = (p - low % p) % p;
+                // low is odd, so an odd step lands on an even multiple
+                if to_multiple % 2 == 1 {
+                    to_multiple + p
+                } else {
+                    to_multiple
+                }
+            };
+            let mut idx = offset / 2;
+            while idx < count {
+                candidate[idx] = false;
+                idx += p;
+            }
+        }
+        (0..count)
+            .filter(|&i| candidate[i])
+            .map(|i| low + 2 * i)
+            .collect()
+    }
+
+    /// Primality of a presieve survivor
+    fn confirm(&self, m: usize) -> bool {
+        m < PRESIEVE_LIMIT * PRESIEVE_LIMIT || factor::is_prime(m as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windowed_matches_prime_set() {
+        let set = PrimeSet::new(20_000);
+        let primes: Vec<usize> = set.iter().collect();
+        let windowed = NearestPrimes::new(None);
+        let mapped = NearestPrimes::new(Some(&set));
+        for n in 0..19_000 {
+            let expected = Neighbors {
+                n,
+                is_prime: primes.binary_search(&n).is_ok(),
+                previous: primes.iter().rev().find(|&&p| p < n).copied(),
+                next: primes.iter().find(|&&p| p > n).copied(),
+            };
+            assert_eq!(windowed.near(n), expected, "n {}", n);
+            assert_eq!(mapped.near(n), expected, "n {}", n);
+        }
+    }
+
+    #[test]
+    fn test_large_gaps_and_the_top_of_u64() {
+        let near = NearestPrimes::new(None);
+        // The maximal gap of 1132 after 1693182318746371
+        let n = 1_693_182_318_746_371 + 600;
+        assert_eq!(near.previous(n), Some(1_693_182_318_746_371));
+        assert_eq!(near.next(n), Some(1_693_182_318_747_503));
+        // 2^64 − 59 is the largest prime below 2^64
+        assert_eq!(near.previous(usize::MAX), Some(18_446_744_073_709_551_557));
+        assert_eq!(near.next(18_446_744_073_709_551_557), None);
+    }
+}
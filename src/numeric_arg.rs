@@ -0,0 +1,121 @@
+// Human-friendly numeric command line arguments
+//
+// Accepts plain integers plus the shorthands people actually type for big sieves:
+// underscores as digit separators (1_000_000), scientific notation (1e9, 2.5e6) and
+// SI suffixes (10K, 10M, 4G, 1T, all powers of ten), and sums of those for windows far
+// from zero (1e15+1e9). The value must come out as a whole number that fits in usize.
+
+/// Parse a count like "1e9", "10M", "1_000_000" or "1e15+1e9" (used as a clap value_parser)
+pub fn parse_count(input: &str) -> Result<usize, String> {
+    // A '+' right after an exponent marker is the exponent's sign, not a sum
+    let mut total: usize = 0;
+    let mut term_start = 0;
+    let bytes = input.as_bytes();
+    for (i, &byte) in bytes.iter().enumerate() {
+        if byte == b'+' && i > 0 && !matches!(bytes[i - 1], b'e' | b'E') {
+            total = total
+                .checked_add(parse_term(&input[term_start..i])?)
+                .ok_or_else(|| format!("'{}' is too large", input))?;
+            term_start = i + 1;
+        }
+    }
+    total
+        .checked_add(parse_term(&input[term_start..])?)
+        .ok_or_else(|| format!("'{}' is too large", input))
+}
+
+/// One term of a count: digits with an optional exponent and SI suffix
+fn parse_term(input: &str) -> Result<usize, String> {
+    let cleaned: String = input.trim().chars().filter(|&c| c != '_').collect();
+    if cleaned.is_empty() {
+        return Err("expected a number".to_string());
+    }
+
+    // SI suffix, e.g. 10M = 10 × 10^6
+    let (number, suffix_exp) = match cleaned.chars().last() {
+        Some('k' | 'K') => (&cleaned[..cleaned.len() - 1], 3),
+        Some('M') => (&cleaned[..cleaned.len() - 1], 6),
+        Some('G') => (&cleaned[..cleaned.len() - 1], 9),
+        Some('T') => (&cleaned[..cleaned.len() - 1], 12),
+        _ => (cleaned.as_str(), 0),
+    };
+
+    // Scientific notation, e.g. 2.5e9
+    let (mantissa, exp) = match number.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => {
+            let exp = exp
+                .parse::<i32>()
+                .map_err(|_| format!("invalid exponent in '{}'", input))?;
+            (mantissa, exp)
+        }
+        None => (number, 0),
+    };
+
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int_part, frac_part);
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("'{}' is not a number", input));
+    }
+
+    // Work with the mantissa as an integer and shift the exponent by the fraction length
+    let too_large = || format!("'{}' is too large", input);
+    let mut value: u128 = digits.parse().map_err(|_| too_large())?;
+    let exp = i32::try_from(frac_part.len())
+        .ok()
+        .and_then(|frac_len| exp.checked_add(suffix_exp)?.checked_sub(frac_len))
+        .ok_or_else(|| format!("exponent out of range in '{}'", input))?;
+
+    // Zero stays zero at any exponent, and would otherwise loop through all of it
+    if value == 0 {
+        return Ok(0);
+    }
+    if exp >= 0 {
+        for _ in 0..exp {
+            value = value.checked_mul(10).ok_or_else(too_large)?;
+        }
+    } else {
+        for _ in 0..-exp {
+            if !value.is_multiple_of(10) {
+                return Err(format!("'{}' is not a whole number", input));
+            }
+            value /= 10;
+        }
+    }
+
+    usize::try_from(value).map_err(|_| too_large())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_count_shorthands() {
+        assert_eq!(parse_count("1000"), Ok(1000));
+        assert_eq!(parse_count("1_000_000"), Ok(1_000_000));
+        assert_eq!(parse_count("1e9"), Ok(1_000_000_000));
+        assert_eq!(parse_count("2.5e6"), Ok(2_500_000));
+        assert_eq!(parse_count("10M"), Ok(10_000_000));
+        assert_eq!(parse_count("1.5k"), Ok(1_500));
+        assert_eq!(parse_count("4G"), Ok(4_000_000_000));
+        assert_eq!(parse_count("1T"), Ok(1_000_000_000_000));
+        assert_eq!(parse_count("1200e-2"), Ok(12));
+        assert_eq!(parse_count("1e15+1e9"), Ok(1_000_001_000_000_000));
+        assert_eq!(parse_count("1e+3 + 5"), Ok(1005));
+    }
+
+    #[test]
+    fn test_parse_count_rejects_bad_input() {
+        assert!(parse_count("").is_err());
+        assert!(parse_count("abc").is_err());
+        assert!(parse_count("1.5").is_err());
+        assert!(parse_count("1e").is_err());
+        assert!(parse_count("-5").is_err());
+        assert!(parse_count("1e40").is_err());
+        assert!(parse_count("5+").is_err());
+        assert!(parse_count("+5").is_err());
+        assert!(parse_count("1e2147483647M").is_err());
+        assert!(parse_count("1.5e-2147483648").is_err());
+        assert_eq!(parse_count("0e2147483647"), Ok(0));
+    }
+}
@@ -0,0 +1,139 @@
+// Ormiston pairs (`nt ormiston`)
+//
+// An Ormiston (or rearrangement) pair is two consecutive primes with the same digits in a
+// different order, such as 1913 and 1931. Two numbers are anagrams when they have the same
+// digit signature: how many of each digit 0-9 they hold, packed five bits to a digit into
+// a u64 (a usize has at most 20 digits). The primes stream from a stored prime file, so
+// each prime is compared with the one before it by signature alone. A file that ends
+// before the limit still covers it when no prime lies between its last prime and the limit.
+//
+// The same signatures key a map of anagram classes, the primes sharing a set of digits,
+// built in the same pass. There are few possible signatures (92,378 for ten digits), so
+// the map stays small however far the file goes; the search reports the largest class.
+
+use std::collections::HashMap;
+use std::io;
+
+/// How many of each digit `n` has, five bits per digit
+pub fn signature(mut n: usize) -> u64 {
+    let mut signature = 0_u64;
+    loop {
+        signature += 1 << (5 * (n % 10));
+        n /= 10;
+        if n == 0 {
+            return signature;
+        }
+    }
+}
+
+/// The primes sharing one digit signature
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Class {
+    pub primes: u64,
+    pub smallest: usize,
+}
+
+/// What streaming the primes up to a limit found
+#[derive(Clone, Debug, Default)]
+pub struct Search {
+    /// Ormiston pairs in increasing order
+    pub pairs: Vec<(usize, usize)>,
+    /// Anagram classes by signature
+    pub classes: HashMap<u64, Class>,
+    /// Largest prime up to the limit
+    pub reached: Option<usize>,
+    /// Whether the file held every prime up to the limit
+    pub complete: bool,
+}
+
+impl Search {
+    /// The anagram class with the most primes, the one with the smaller primes on ties
+    pub fn largest_class(&self) -> Option<Class> {
+        self.classes
+            .values()
+            .copied()
+            .max_by_key(|class| (class.primes, std::cmp::Reverse(class.smallest)))
+    }
+}
+
+/// Find the Ormiston pairs among `primes`, an increasing list, up to `limit`
+pub fn search(primes: impl Iterator<Item = io::Result<usize>>, limit: usize) -> io::Result<Search> {
+    let mut found = Search::default();
+    let mut previous: Option<(usize, u64)> = None;
+    for p in primes {
+        let p = p?;
+        if p > limit {
+            found.complete = true;
+            break;
+        }
+        let sig = signature(p);
+        if let Some((q, q_sig)) = previous
+            && q_sig == sig
+        {
+            found.pairs.push((q, p));
+        }
+        found
+            .classes
+            .entry(sig)
+            .and_modify(|class| class.primes += 1)
+            .or_insert(Class {
+                primes: 1,
+                smallest: p,
+            });
+        previous = Some((p, sig));
+        found.reached = Some(p);
+    }
+    if !found.complete {
+        // The file stopped early; it still covers the limit if the next prime is past it
+        let next = found.reached.map_or(2, |p| p + 1);
+        found.complete = !(next..=limit).any(|n| crate::factor::is_prime(n as u64));
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primes(limit: usize) -> impl Iterator<Item = io::Result<usize>> {
+        crate::primes::sieve(limit).into_iter().map(Ok)
+    }
+
+    #[test]
+    fn test_signature() {
+        assert_eq!(signature(1913), signature(1931));
+        assert_eq!(signature(0), 1);
+        assert_ne!(signature(11), signature(1));
+        assert_ne!(signature(100), signature(10));
+    }
+
+    #[test]
+    fn test_search() {
+        let found = search(primes(1_100_000), 1_000_000).unwrap();
+        assert_eq!(found.pairs.len(), 382);
+        assert_eq!(
+            found.pairs[..4],
+            [(1913, 1931), (18379, 18397), (19013, 19031), (25013, 25031)]
+        );
+        assert_eq!(found.pairs.last(), Some(&(995_513, 995_531)));
+        assert_eq!(found.reached, Some(999_983));
+        assert!(found.complete);
+        assert_eq!(
+            found.largest_class(),
+            Some(Class {
+                primes: 148,
+                smallest: 123_479
+            })
+        );
+
+        // 1237 and 1279 both have 11 anagram primes below 10^4
+        let found = search(primes(10_000), 10_000).unwrap();
+        assert_eq!(found.pairs, [(1913, 1931)]);
+        assert_eq!(found.largest_class().unwrap().smallest, 1237);
+
+        // Sieved exactly to the limit, the file ends at 9973 but still covers 10^4
+        assert!(found.complete);
+        assert!(!search(primes(10_000), 10_007).unwrap().complete);
+        assert!(search(primes(10_000), 10_006).unwrap().complete);
+    }
+}
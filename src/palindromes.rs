@@ -0,0 +1,132 @@
+// Palindromes in bases 2 through 255 (`nt palindromes`)
+//
+// n is a palindrome in base b when its base-b digits read the same backwards and there are
+// at least two of them, the rule `nt primes-bases` has always used (every n below b is a
+// single digit and would otherwise count). Every n >= 3 is "11" in base n − 1, so each n
+// from 3 to 256 has at least that one. Digits above 61 have no character, so bases past 62
+// are written as their decimal digit values, e.g. 1000 = [3:235] in base 255.
+//
+// The search for the smallest n that is a palindrome in at least k bases scans [1, limit]
+// in chunks over worker threads. Chunks are handed out in increasing order and one that
+// starts past the best n found so far is skipped, so the scan ends soon after the first
+// hit instead of at the limit.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::parallel::{chunk_size, parallel_chunks};
+use crate::radix;
+
+/// Largest base checked
+pub const MAX_BASE: u64 = 255;
+
+// Smaller than parallel::MIN_CHUNK: each n is checked in every base up to MAX_BASE
+const MIN_CHUNK: usize = 1 << 14;
+
+// Largest chunk, so the scan notices an early hit without finishing a huge chunk
+const MAX_CHUNK: usize = 1 << 20;
+
+/// Whether n has at least two digits in `base` and they read the same backwards
+pub fn is_palindrome_in(n: u64, base: u64) -> bool {
+    if n < base {
+        return false;
+    }
+    // Least significant digit first; digits below 256 fit a byte
+    let mut digits = [0u8; 64];
+    let mut len = 0;
+    let mut rest = n;
+    while rest > 0 {
+        digits[len] = (rest % base) as u8;
+        rest /= base;
+        len += 1;
+    }
+    (0..len / 2).all(|i| digits[i] == digits[len - 1 - i])
+}
+
+/// Bases from 2 to MAX_BASE in which n is a palindrome
+pub fn palindromic_bases(n: u64) -> Vec<u64> {
+    (2..=MAX_BASE).filter(|&b| is_palindrome_in(n, b)).collect()
+}
+
+/// n written in `base`: characters up to base 62, decimal digit values above
+pub fn format_in(n: u64, base: u64) -> String {
+    if base <= 62 {
+        return radix::to_base(n as usize, base as usize);
+    }
+    let digits: Vec<String> = radix::digits(n, base)
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+    format!("[{}]", digits.join(":"))
+}
+
+/// Smallest n in [low, high] that is a palindrome in at least k bases
+fn chunk_first(k: usize, low: u64, high: u64) -> Option<u64> {
+    (low..=high).find(|&n| {
+        let mut count = 0;
+        for base in 2..=MAX_BASE.min(n) {
+            if is_palindrome_in(n, base) {
+                count += 1;
+                if count >= k {
+                    return true;
+                }
+            }
+        }
+        false
+    })
+}
+
+/// Smallest n <= limit that is a palindrome in at least k of the bases 2 to MAX_BASE
+pub fn smallest_with(k: usize, limit: u64, workers: usize) -> Option<u64> {
+    if limit == 0 {
+        return None;
+    }
+    let limit = limit as usize;
+    let best = AtomicU64::new(u64::MAX);
+    parallel_chunks(
+        1..=limit,
+        chunk_size(limit, workers, MIN_CHUNK).min(MAX_CHUNK),
+        workers,
+        |low, high| {
+            // Later chunks only hold larger n
+            if low as u64 > best.load(Ordering::Relaxed) {
+                return None;
+            }
+            chunk_first(k, low as u64, high as u64)
+        },
+        |first| {
+            if let Some(n) = first {
+                best.fetch_min(n, Ordering::Relaxed);
+            }
+        },
+    );
+    let best = best.into_inner();
+    (best != u64::MAX).then_some(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palindromic_bases() {
+        assert_eq!(palindromic_bases(121), [3, 7, 8, 10, 120]);
+        assert!(palindromic_bases(2).is_empty());
+        assert_eq!(format_in(1000, 255), "[3:235]");
+        assert_eq!(format_in(121, 7), "232");
+        assert!(is_palindrome_in(u64::MAX, 2));
+    }
+
+    #[test]
+    fn test_smallest_with() {
+        let found: Vec<u64> = (1..=16)
+            .map(|k| smallest_with(k, 10_000, 3).unwrap())
+            .collect();
+        assert_eq!(
+            found,
+            [
+                3, 5, 10, 21, 36, 60, 80, 120, 180, 252, 252, 300, 720, 720, 2520, 2520
+            ]
+        );
+        assert_eq!(smallest_with(17, 2519, 2), None);
+    }
+}
@@ -0,0 +1,111 @@
+// Worker threads for the parallel subcommands
+//
+// Most searches split a range of n into chunks that workers take in turn from a shared
+// counter, so a slow chunk only holds up its own worker, and fold each chunk's result into
+// a total as it finishes. parallel_chunks is that loop; the callers only say what one chunk
+// computes and how results combine.
+
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Chunks per worker, so a slow chunk does not leave the others idle at the end
+const CHUNKS_PER_WORKER: usize = 8;
+
+/// Smallest chunk worth handing to a worker, for searches doing a little arithmetic per n;
+/// modules whose n cost much more or much less keep their own
+pub const MIN_CHUNK: usize = 1 << 16;
+
+/// Threads to use when --workers is not given: one per available core, or 4 when the
+/// core count is unknown
+pub fn default_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
+
+/// Chunk length giving each of `workers` about CHUNKS_PER_WORKER chunks of `span` numbers,
+/// but no shorter than `min_chunk`
+pub fn chunk_size(span: usize, workers: usize, min_chunk: usize) -> usize {
+    span.div_ceil(workers.max(1) * CHUNKS_PER_WORKER)
+        .max(min_chunk)
+        .max(1)
+}
+
+/// Cut `range` into chunks of `chunk` numbers, run `work(low, high)` on each from
+/// `workers` threads, and hand every result to `merge` in the order the chunks finish;
+/// chunks start in increasing order, and an empty range (start > end) runs nothing
+pub fn parallel_chunks<T>(
+    range: RangeInclusive<usize>,
+    chunk: usize,
+    workers: usize,
+    work: impl Fn(usize, usize) -> T + Sync,
+    merge: impl FnMut(T) + Send,
+) {
+    let (start, end) = range.into_inner();
+    if start > end {
+        return;
+    }
+    let chunk = chunk.max(1);
+    // (end − start) / chunk + 1 rather than a span, which overflows for the whole of usize
+    let num_chunks = (end - start) / chunk + 1;
+
+    let next = AtomicUsize::new(0);
+    let merge = Mutex::new(merge);
+    thread::scope(|scope| {
+        for _ in 0..workers.max(1).min(num_chunks) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= num_chunks {
+                        break;
+                    }
+                    let low = start + index * chunk;
+                    let high = low.saturating_add(chunk - 1).min(end);
+                    let result = work(low, high);
+                    (merge.lock().unwrap())(result);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_cover_the_range_once() {
+        for (range, chunk, workers) in [
+            (0..=0, 1, 4),
+            (3..=100, 7, 3),
+            (5..=5, 100, 2),
+            (usize::MAX - 10..=usize::MAX, 4, 2),
+        ] {
+            let mut chunks = Vec::new();
+            parallel_chunks(
+                range.clone(),
+                chunk,
+                workers,
+                |low, high| (low, high),
+                |c| chunks.push(c),
+            );
+            chunks.sort_unstable();
+            assert_eq!(chunks[0].0, *range.start());
+            assert_eq!(chunks.last().unwrap().1, *range.end());
+            for pair in chunks.windows(2) {
+                assert_eq!(pair[0].1 + 1, pair[1].0);
+            }
+            assert!(chunks.iter().all(|&(low, high)| high - low < chunk));
+        }
+
+        let (start, end) = (10, 9);
+        let mut ran = false;
+        parallel_chunks(start..=end, 1, 2, |_, _| (), |()| ran = true);
+        assert!(!ran);
+        assert_eq!(chunk_size(1000, 4, 1), 32);
+        assert_eq!(chunk_size(1000, 4, 100), 100);
+        assert_eq!(chunk_size(0, 4, 0), 1);
+    }
+}
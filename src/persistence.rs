@@ -0,0 +1,182 @@
+// Multiplicative persistence (`nt persistence`)
+//
+// Replacing n by the product of its digits until one digit is left takes persistence(n)
+// steps; 277777788888899 needs 11, and no number below 10^30000 is known to need more.
+// The smallest number of each persistence is found by scanning [0, limit] in chunks over
+// worker threads. Digit products come from two tables over 6-digit blocks, one for the
+// leading block (no leading zeros) and one for inner blocks (leading zeros count as
+// digits), so a product costs one lookup and multiply per block. A third table holds the
+// persistence of everything below 10^6, so only the first step or two of a chain is
+// computed at all.
+
+use crate::parallel::{chunk_size, parallel_chunks};
+
+// Digits per table block
+const BLOCK_DIGITS: u32 = 6;
+const BLOCK: u64 = 10u64.pow(BLOCK_DIGITS);
+
+// Larger than parallel::MIN_CHUNK: most n cost one table lookup, so smaller chunks would
+// spend more time on the shared counter than on the numbers
+const MIN_CHUNK: usize = 1 << 20;
+
+// Above the largest persistence any u64 can have
+const MAX_PERSISTENCE: usize = 32;
+
+/// Memoized digit products and persistences of the numbers below 10^6
+pub struct DigitProducts {
+    leading: Vec<u32>,
+    inner: Vec<u32>,
+    persistence: Vec<u8>,
+}
+
+impl Default for DigitProducts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DigitProducts {
+    pub fn new() -> Self {
+        let size = BLOCK as usize;
+        let mut leading = vec![0u32; size];
+        let mut persistence = vec![0u8; size];
+        for n in 0..size {
+            leading[n] = if n < 10 {
+                n as u32
+            } else {
+                leading[n / 10] * (n % 10) as u32
+            };
+            if n >= 10 {
+                // The digit product is below n, so its persistence is already known
+                persistence[n] = persistence[leading[n] as usize] + 1;
+            }
+        }
+        // Inner blocks have exactly BLOCK_DIGITS digits; a leading zero makes them 0
+        let inner = (0..size)
+            .map(|n| if n >= size / 10 { leading[n] } else { 0 })
+            .collect();
+        Self {
+            leading,
+            inner,
+            persistence,
+        }
+    }
+
+    /// Product of the decimal digits of n
+    pub fn product(&self, n: u64) -> u64 {
+        if n < BLOCK {
+            return self.leading[n as usize] as u64;
+        }
+        let mut product = 1;
+        let mut rest = n;
+        while rest >= BLOCK {
+            product *= self.inner[(rest % BLOCK) as usize] as u64;
+            if product == 0 {
+                return 0;
+            }
+            rest /= BLOCK;
+        }
+        product * self.leading[rest as usize] as u64
+    }
+
+    /// Digit product steps from n to a single digit
+    pub fn persistence(&self, n: u64) -> u32 {
+        let mut steps = 0;
+        let mut n = n;
+        while n >= BLOCK {
+            n = self.product(n);
+            steps += 1;
+        }
+        steps + self.persistence[n as usize] as u32
+    }
+
+    /// n, its digit product and so on down to a single digit
+    pub fn chain(&self, n: u64) -> Vec<u64> {
+        let mut chain = vec![n];
+        let mut n = n;
+        while n >= 10 {
+            n = self.product(n);
+            chain.push(n);
+        }
+        chain
+    }
+}
+
+/// Smallest n in [low, high] for each persistence, indexed by persistence
+fn chunk_firsts(products: &DigitProducts, low: u64, high: u64) -> Vec<Option<u64>> {
+    let mut firsts = vec![None; MAX_PERSISTENCE];
+    for n in low..=high {
+        let p = products.persistence(n) as usize;
+        if firsts[p].is_none() {
+            firsts[p] = Some(n);
+        }
+    }
+    firsts
+}
+
+/// (persistence, smallest n <= limit with it) for every persistence reached
+/// These are the records, since the smallest number of each persistence grows with it
+pub fn records(limit: u64, workers: usize) -> Vec<(u32, u64)> {
+    let products = DigitProducts::new();
+    let limit = limit as usize;
+    let mut firsts = vec![None::<u64>; MAX_PERSISTENCE];
+    parallel_chunks(
+        0..=limit,
+        chunk_size(limit, workers, MIN_CHUNK),
+        workers,
+        |low, high| chunk_firsts(&products, low as u64, high as u64),
+        |found| {
+            for (first, n) in firsts.iter_mut().zip(found) {
+                *first = match (*first, n) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+            }
+        },
+    );
+    firsts
+        .into_iter()
+        .enumerate()
+        .filter_map(|(p, n)| n.map(|n| (p as u32, n)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_match_oeis() {
+        // A003001
+        let expected = [0, 10, 25, 39, 77, 679, 6788, 68889, 2677889];
+        let found: Vec<u64> = records(3_000_000, 3).into_iter().map(|(_, n)| n).collect();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_products_and_long_chain() {
+        let products = DigitProducts::new();
+        assert_eq!(products.product(1_000_000), 0);
+        assert_eq!(products.product(1_234_567), 5040);
+        assert_eq!(products.product(99_999_999_999), 9u64.pow(11));
+        let chain = products.chain(277_777_788_888_899);
+        assert_eq!(
+            chain,
+            [
+                277_777_788_888_899,
+                4_996_238_671_872,
+                438_939_648,
+                4_478_976,
+                338_688,
+                27_648,
+                2688,
+                768,
+                336,
+                54,
+                20,
+                0
+            ]
+        );
+        assert_eq!(products.persistence(277_777_788_888_899), 11);
+    }
+}
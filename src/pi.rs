@@ -1,13 +1,26 @@
-use rug::Float;
 use rug::ops::Pow;
+use rug::{Float, Integer};
+
+use crate::constants;
+use crate::progress;
 use crate::scan;
 
-pub fn calculate_and_print(digits: usize) {
+// arctan_series stops after this many terms (n > 100000)
+const MAX_SERIES_TERMS: u64 = 50_000;
+
+pub fn calculate_and_print(digits: usize, show_progress: bool) {
     // Calculate precision needed in bits (roughly 3.32 bits per decimal digit)
-    let precision = ((digits as f64) * 3.32 * 1.5) as u32;
+    let precision = constants::precision_for_digits(digits);
+
+    if show_progress {
+        let total_terms =
+            estimate_series_terms(5.0, precision) + estimate_series_terms(239.0, precision);
+        progress::start(total_terms, "terms");
+    }
 
     // Use Machin's formula: π/4 = 4*arctan(1/5) - arctan(1/239)
     let pi = machin_formula(precision);
+    progress::finish();
 
     // Print pi to the requested number of decimal places
     println!("π to {} decimal places:", digits);
@@ -39,6 +52,13 @@ pub(crate) fn machin_formula(precision: u32) -> Float {
     pi
 }
 
+/// Number of terms arctan_series(1/inv_x) needs to reach its tolerance of 10^(-precision/3)
+/// Term k is x^(2k+1), so k ≈ (precision/3) / (2·log10(inv_x))
+fn estimate_series_terms(inv_x: f64, precision: u32) -> u64 {
+    let terms = (precision as f64 / 3.0) / (2.0 * inv_x.log10());
+    (terms.ceil() as u64 + 1).min(MAX_SERIES_TERMS)
+}
+
 fn arctan_series(x: &Float, precision: u32) -> Float {
     // arctan(x) = x - x^3/3 + x^5/5 - x^7/7 + ...
     let mut sum = Float::with_val(precision, 0);
@@ -65,6 +85,7 @@ fn arctan_series(x: &Float, precision: u32) -> Float {
 
         term *= &x_squared;
         n += 2;
+        progress::inc(1);
         sign *= -1; // Alternate the sign
 
         // Safety check to prevent infinite loops
@@ -76,6 +97,25 @@ fn arctan_series(x: &Float, precision: u32) -> Float {
     sum
 }
 
+/// The digits after the point of a decimal `expansion` in `base` (2 to 36), as many as
+/// the decimal places determine exactly
+pub fn fraction_in_base(expansion: &str, base: u32) -> String {
+    let fraction = expansion
+        .split_once('.')
+        .map_or("", |(_, fraction)| fraction);
+    if base == 10 || fraction.is_empty() {
+        return fraction.to_string();
+    }
+    let numerator: Integer = fraction.parse().unwrap();
+    let denominator = Integer::from(10).pow(fraction.len() as u32);
+    // One digit short of what the places determine, so truncation can't change a digit
+    let places = (fraction.len() as f64 / (base as f64).log10()) as u32;
+    let places = places.saturating_sub(1);
+    let scaled = numerator * Integer::from(base).pow(places) / denominator;
+    let digits = scaled.to_string_radix(base as i32);
+    format!("{:0>width$}", digits, width = places as usize)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -107,4 +147,13 @@ mod tests {
         let pi_over_4 = result.to_f64();
         assert!((pi_over_4 - 0.7853981633974483).abs() < 0.0001);
     }
+
+    #[test]
+    fn test_fraction_in_base() {
+        // π = 3.243f6a8885a308d3... in hex, 11.001001000011111101101... in binary
+        let expansion = "3.14159265358979323846";
+        assert!(fraction_in_base(expansion, 16).starts_with("243f6a8885a308d"));
+        assert!(fraction_in_base(expansion, 2).starts_with("001001000011111101101"));
+        assert_eq!(fraction_in_base("3.14", 10), "14");
+    }
 }
@@ -0,0 +1,236 @@
+// Tables of π(x) at regular checkpoints (`nt pi-table`)
+//
+// Sieving to 10^10 takes a while; a table of π(step), π(2·step), ... up to the limit keeps
+// what that run learned in a few kilobytes. Afterwards π(x) for any x in range is known
+// to lie between the two neighbouring checkpoints, and interpolating between them is a
+// good estimate (the density barely changes across one step), so prime ranks and li(x)
+// comparisons need no sieving at all. The checkpoints are counted while sieving: ranges of
+// them are shared out to worker threads, each counting the primes it sieves per step.
+//
+// File layout (little-endian): b"NTPIT001", limit: u64, step: u64, checkpoint count: u64,
+// then for each checkpoint min(k·step, limit), k = 1, 2, ..., the number of primes since
+// the previous one as a u32. The table holds prefix sums of those.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::parallel::{chunk_size, parallel_chunks};
+use crate::primes::for_each_prime_in_range;
+
+const MAGIC: &[u8; 8] = b"NTPIT001";
+const HEADER_BYTES: usize = 32;
+
+/// π(x) at x = step, 2·step, ..., and at the limit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PiTable {
+    limit: usize,
+    step: usize,
+    // counts[k] = π(checkpoint k)
+    counts: Vec<u64>,
+}
+
+/// Where π(x) lies for an x between two checkpoints
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bracket {
+    /// Checkpoint at or below x, with π there
+    pub low: (usize, u64),
+    /// Checkpoint at or above x, with π there
+    pub high: (usize, u64),
+    /// π(x) interpolated between them
+    pub estimate: f64,
+}
+
+impl Bracket {
+    /// π(x) itself when x is a checkpoint
+    pub fn exact(&self) -> Option<u64> {
+        (self.low == self.high).then_some(self.low.1)
+    }
+}
+
+impl PiTable {
+    /// Sieve [2, limit] and count the primes up to every multiple of `step`
+    /// `step` must be between 1 and u32::MAX
+    pub fn build(limit: usize, step: usize, num_workers: usize) -> Self {
+        assert!(
+            (1..=u32::MAX as usize).contains(&step),
+            "step {} is out of range",
+            step
+        );
+        let checkpoints = limit.div_ceil(step);
+        // Several steps to a chunk, so the base primes are not re-read for every step
+        let mut chunks = Vec::new();
+        if checkpoints > 0 {
+            parallel_chunks(
+                0..=checkpoints - 1,
+                chunk_size(checkpoints, num_workers, 1),
+                num_workers,
+                |first, last| {
+                    // Primes in (first·step, min((last + 1)·step, limit)], per step
+                    let mut per_step = vec![0_u64; last - first + 1];
+                    let low = first * step + 1;
+                    let high = ((last + 1) * step).min(limit);
+                    for_each_prime_in_range(low, high, |primes| {
+                        for &p in primes {
+                            per_step[(p - 1) / step - first] += 1;
+                        }
+                    });
+                    (first, per_step)
+                },
+                |chunk| chunks.push(chunk),
+            );
+        }
+
+        chunks.sort_unstable_by_key(|&(first, _)| first);
+        let counts = chunks
+            .into_iter()
+            .flat_map(|(_, per_step)| per_step)
+            .scan(0, |total, count| {
+                *total += count;
+                Some(*total)
+            })
+            .collect();
+        Self {
+            limit,
+            step,
+            counts,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// The checkpoints and π at each, in increasing order
+    pub fn checkpoints(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(k, &count)| (self.checkpoint(k), count))
+    }
+
+    fn checkpoint(&self, k: usize) -> usize {
+        ((k + 1) * self.step).min(self.limit)
+    }
+
+    /// The checkpoints around `x` (0 counts as one, with π(0) = 0), or None past the limit
+    pub fn bracket(&self, x: usize) -> Option<Bracket> {
+        if x > self.limit {
+            return None;
+        }
+        let low = match x / self.step {
+            0 => (0, 0),
+            k => (self.checkpoint(k - 1), self.counts[k - 1]),
+        };
+        if low.0 == x {
+            return Some(Bracket {
+                low,
+                high: low,
+                estimate: low.1 as f64,
+            });
+        }
+        let k = x / self.step;
+        let high = (self.checkpoint(k), self.counts[k]);
+        let fraction = (x - low.0) as f64 / (high.0 - low.0) as f64;
+        Some(Bracket {
+            low,
+            high,
+            estimate: low.1 as f64 + fraction * (high.1 - low.1) as f64,
+        })
+    }
+
+    /// Write the table to `path` so it can be reopened with `open`
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.limit as u64).to_le_bytes())?;
+        writer.write_all(&(self.step as u64).to_le_bytes())?;
+        writer.write_all(&(self.counts.len() as u64).to_le_bytes())?;
+        let mut previous = 0;
+        for &count in &self.counts {
+            writer.write_all(&((count - previous) as u32).to_le_bytes())?;
+            previous = count;
+        }
+        writer.flush()
+    }
+
+    /// Open a table written by `save`
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, what.to_string());
+        if bytes.len() < HEADER_BYTES || &bytes[..8] != MAGIC {
+            return Err(invalid("not a π table file"));
+        }
+        let field = |i: usize| u64::from_le_bytes(bytes[8 * i..8 * i + 8].try_into().unwrap());
+        let (limit, step, checkpoints) = (field(1) as usize, field(2) as usize, field(3) as usize);
+        if step == 0
+            || checkpoints != limit.div_ceil(step)
+            || bytes.len() != HEADER_BYTES + checkpoints * 4
+        {
+            return Err(invalid("π table file is truncated or has trailing data"));
+        }
+        let counts = bytes[HEADER_BYTES..]
+            .chunks_exact(4)
+            .scan(0, |total, chunk| {
+                *total += u32::from_le_bytes(chunk.try_into().unwrap()) as u64;
+                Some(*total)
+            })
+            .collect();
+        Ok(Self {
+            limit,
+            step,
+            counts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoints_match_known_counts() {
+        // π(10^k) for k = 1..=6
+        let table = PiTable::build(1_000_000, 10, 4);
+        for (x, pi) in [
+            (10, 4),
+            (100, 25),
+            (1_000, 168),
+            (10_000, 1_229),
+            (100_000, 9_592),
+            (1_000_000, 78_498),
+        ] {
+            assert_eq!(table.bracket(x).unwrap().exact(), Some(pi));
+        }
+
+        // A limit that is not a multiple of the step ends on the limit itself
+        let table = PiTable::build(1_000, 300, 2);
+        assert_eq!(
+            table.checkpoints().collect::<Vec<_>>(),
+            [(300, 62), (600, 109), (900, 154), (1_000, 168)]
+        );
+        let bracket = table.bracket(450).unwrap();
+        assert_eq!((bracket.low, bracket.high), ((300, 62), (600, 109)));
+        assert_eq!(bracket.exact(), None);
+        assert_eq!(bracket.estimate, 85.5);
+        assert_eq!(table.bracket(100).unwrap().low, (0, 0));
+        assert_eq!(table.bracket(1_001), None);
+    }
+
+    #[test]
+    fn test_save_and_open() {
+        let table = PiTable::build(100_000, 7_000, 3);
+        let path = std::env::temp_dir().join(format!("nt_pi_table_{}.bin", std::process::id()));
+        table.save(&path).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len(), 32 + 15 * 4);
+        assert_eq!(PiTable::open(&path).unwrap(), table);
+
+        fs::write(&path, b"NTPIT001").unwrap();
+        assert!(PiTable::open(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
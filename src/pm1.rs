@@ -0,0 +1,185 @@
+// Pollard's p−1 and Williams' p+1 (`nt factor --algorithm pm1 / pp1`)
+//
+// Stage 1 raises a base to E = the product of every prime power <= B1: a^E for p−1, the
+// Lucas sequence V_E(A) for p+1. When p−1 (or p+1) is B1-smooth for a prime p | n, that
+// lands on 1 (or 2) mod p and a gcd with n finds p. Stage 2 catches one more prime q in
+// (B1, B2]. Both methods share it by working with Lucas sequences: for p−1 the value
+// x = a + 1/a has V_k(x) = a^k + a^-k. Then q = mD ± j divides the order exactly when
+// V_mD(x) ≡ V_j(x) mod p, so baby steps V_j and giant steps V_mD (as in ecm.rs) cover
+// every q with one multiplication each.
+//
+// The stage 1 primes come from the stored `nt primes` output when it reaches B1.
+
+use rug::Integer;
+use rug::ops::RemRounding;
+use tracing::debug;
+
+// Giant step size for stage 2 (2·3·5·7·11)
+const GIANT_STEP: usize = 2310;
+
+// Starting values for p+1; each works only when A² − 4 is a non-residue mod p, so try a few
+// with different square-free parts of A² − 4 (5, 21 and 2)
+const PP1_SEEDS: [u32; 3] = [3, 5, 6];
+
+/// Exponent E: the largest power of each prime <= b1 (`primes` must cover b1)
+fn stage1_exponent(primes: &[usize], b1: usize) -> Integer {
+    let powers: Vec<u64> = primes
+        .iter()
+        .take_while(|&&p| p <= b1)
+        .map(|&p| {
+            let mut power = p as u64;
+            while power * p as u64 <= b1 as u64 {
+                power *= p as u64;
+            }
+            power
+        })
+        .collect();
+    crate::product_tree::product(&powers, 1)
+}
+
+/// V_k(x) mod n for the Lucas sequence V_0 = 2, V_1 = x, V_{k+1} = x·V_k − V_{k−1}
+fn lucas_v(x: &Integer, k: &Integer, n: &Integer) -> Integer {
+    if *k == 0 {
+        return Integer::from(2);
+    }
+    // Ladder over (V_m, V_{m+1}) using V_2m = V_m² − 2 and V_2m+1 = V_m·V_{m+1} − x
+    let mut low = x.clone();
+    let mut high = (Integer::from(x.square_ref()) - 2u32).rem_euc(n);
+    for bit in (0..k.significant_bits() - 1).rev() {
+        let mixed = (Integer::from(&low * &high) - x).rem_euc(n);
+        if k.get_bit(bit) {
+            low = mixed;
+            high = (high.square() - 2u32).rem_euc(n);
+        } else {
+            high = mixed;
+            low = (low.square() - 2u32).rem_euc(n);
+        }
+    }
+    low
+}
+
+/// Proper factor from gcd(value, n), if there is one
+fn proper_gcd(value: &Integer, n: &Integer) -> Option<Integer> {
+    let g = Integer::from(value.gcd_ref(n));
+    (g != 1 && g != *n).then_some(g)
+}
+
+/// Stage 2 from x = V_1 after stage 1, over the primes in (b1, b2]
+fn lucas_stage2(
+    x: &Integer,
+    n: &Integer,
+    primes: &[usize],
+    b1: usize,
+    b2: usize,
+) -> Option<Integer> {
+    let start = primes.partition_point(|&p| p <= b1);
+    let stage2 = &primes[start..primes.partition_point(|&p| p <= b2)];
+    let &first = stage2.first()?;
+    let half = GIANT_STEP / 2;
+
+    // Baby steps V_j for odd j <= D/2 (V_{j+2} = V_j·V_2 − V_{j−2}, with V_{−1} = V_1)
+    let v2 = (Integer::from(x.square_ref()) - 2u32).rem_euc(n);
+    let mut baby = vec![x.clone(), (Integer::from(x * &v2) - x).rem_euc(n)];
+    while baby.len() * 2 <= half {
+        let len = baby.len();
+        let next = (Integer::from(&baby[len - 1] * &v2) - &baby[len - 2]).rem_euc(n);
+        baby.push(next);
+    }
+
+    // Giant steps V_mD (m may start at 0 when B1 < D), advancing with
+    // V_(m+1)D = V_mD·V_D − V_(m−1)D
+    let giant = lucas_v(x, &Integer::from(GIANT_STEP), n);
+    let mut m = (first + half) / GIANT_STEP;
+    let mut current = lucas_v(x, &Integer::from(m * GIANT_STEP), n);
+    let mut next = lucas_v(x, &Integer::from((m + 1) * GIANT_STEP), n);
+
+    let mut product = Integer::from(1);
+    for &q in stage2 {
+        let target = (q + half) / GIANT_STEP;
+        while m < target {
+            let following = (Integer::from(&next * &giant) - &current).rem_euc(n);
+            current = std::mem::replace(&mut next, following);
+            m += 1;
+        }
+        let j = (m * GIANT_STEP).abs_diff(q);
+        product = (product * Integer::from(&current - &baby[j / 2])).rem_euc(n);
+    }
+    proper_gcd(&product, n)
+}
+
+/// A proper factor of `n` by Pollard's p−1 with bounds B1 and B2 (`primes` must cover B2)
+pub fn p_minus_1(n: &Integer, primes: &[usize], b1: usize, b2: usize) -> Option<Integer> {
+    let exponent = stage1_exponent(primes, b1);
+    let a = Integer::from(2).pow_mod(&exponent, n).ok()?;
+
+    // gcd = n means every factor's p−1 was smooth at once; a smaller B1 would separate them
+    let g = Integer::from((Integer::from(&a) - 1u32).gcd_ref(n));
+    if g == *n {
+        debug!("p−1 stage 1 found every factor at once");
+        return None;
+    }
+    if g != 1 {
+        return Some(g);
+    }
+
+    let Ok(inverse) = a.clone().invert(n) else {
+        return proper_gcd(&a, n);
+    };
+    let x = (a + inverse).rem_euc(n);
+    lucas_stage2(&x, n, primes, b1, b2)
+}
+
+/// A proper factor of `n` by Williams' p+1 with bounds B1 and B2 (`primes` must cover B2)
+pub fn p_plus_1(n: &Integer, primes: &[usize], b1: usize, b2: usize) -> Option<Integer> {
+    let exponent = stage1_exponent(primes, b1);
+
+    for seed in PP1_SEEDS {
+        let v = lucas_v(&Integer::from(seed), &exponent, n);
+        let g = Integer::from((Integer::from(&v) - 2u32).gcd_ref(n));
+        if g != 1 && g != *n {
+            return Some(g);
+        }
+        if g == 1
+            && let Some(factor) = lucas_stage2(&v, n, primes, b1, b2)
+        {
+            return Some(factor);
+        }
+        debug!("p+1 with A = {} found nothing", seed);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lucas_v_matches_recurrence() {
+        let n = Integer::from(1_000_003);
+        let x = Integer::from(5);
+        let (mut previous, mut current) = (Integer::from(2), x.clone());
+        for k in 1..200u32 {
+            assert_eq!(lucas_v(&x, &Integer::from(k), &n), current);
+            let next = (Integer::from(&x * &current) - &previous).rem_euc(&n);
+            previous = std::mem::replace(&mut current, next);
+        }
+    }
+
+    #[test]
+    fn test_stages_find_smooth_factors() {
+        let primes = crate::primes::sieve(200_000);
+        // Neither p − 1 nor p + 1 is 200000-smooth for this one
+        let hard = Integer::from(1_000_000_000_039u64);
+
+        // p − 1 = 2^4 × 3 × 5 × 11 × 29 × 151 × 86501, so stage 2 finds it
+        let smooth = Integer::from(1_000_000_000_561u64);
+        let n = Integer::from(&smooth * &hard);
+        assert_eq!(p_minus_1(&n, &primes, 1000, 200_000), Some(smooth.clone()));
+        assert_eq!(p_minus_1(&n, &primes, 1000, 50_000), None);
+
+        // p + 1 = 2^6 × 13 × 41 × 61 × 157 × 3061
+        let smooth = Integer::from(1_000_000_000_063u64);
+        let n = Integer::from(&smooth * &hard);
+        assert_eq!(p_plus_1(&n, &primes, 1000, 200_000), Some(smooth));
+    }
+}
@@ -0,0 +1,232 @@
+// n − 1 and n + 1 primality proofs for numbers of special form
+//
+// Baillie–PSW can only call p# ± 1 "probably prime", but one of n ∓ 1 is then p# itself,
+// whose prime factors are known. That is enough for a proof (Brillhart, Lehmer and
+// Selfridge 1975) once the factored part F of n ∓ 1 is large enough:
+//
+// - n − 1 (Pocklington): for each prime q | F, some a has a^(n−1) ≡ 1 (mod n) and
+//   gcd(a^((n−1)/q) − 1, n) = 1. Every prime factor of n is then ≡ 1 (mod F), so F ≥
+//   sqrt(n) leaves n no room for two.
+// - n + 1 (Morrison): the same with a Lucas sequence U(P, Q) whose discriminant
+//   D = P² − 4Q has (D | n) = −1: U_(n+1) ≡ 0 (mod n) and gcd(U_((n+1)/q), n) = 1 for
+//   each q | F. D has to be the same for every q; every prime factor of n is then
+//   ≡ ±1 (mod F), so F − 1 has to exceed sqrt(n).
+//
+// Unlike a Pratt certificate (certify.rs) nothing has to be factored past what the form
+// gives away, so a proof takes a few modular powers per prime of F. The primes of F are
+// taken as given; for p# they come from the sieve.
+
+use rug::Integer;
+use rug::ops::Pow;
+use std::fmt;
+
+use crate::bpsw::{self, lucas};
+
+// Bases a (n − 1) or discriminants D (n + 1) tried before giving up
+const MAX_WITNESS: u32 = 1000;
+
+/// Which neighbour of n the proof uses
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Method {
+    /// F divides n − 1 (Pocklington)
+    NMinus1,
+    /// F divides n + 1 (Morrison, Lucas sequences)
+    NPlus1,
+}
+
+/// A primality proof for n from a factored part of n ∓ 1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proof {
+    pub method: Method,
+    /// F as (prime, exponent) pairs
+    pub factors: Vec<(Integer, u32)>,
+    /// For each prime of F, the base a (n − 1) or the discriminant D of the Lucas sequence
+    /// with P = 1, Q = (1 − D)/4 (n + 1) that rules it out
+    pub witnesses: Vec<i64>,
+}
+
+/// What testing n found
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Prime(Proof),
+    Composite,
+    /// A probable prime the given factors cannot prove prime, with the reason
+    Unproven(String),
+}
+
+impl fmt::Display for Proof {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut witnesses = self.witnesses.clone();
+        witnesses.sort_unstable();
+        witnesses.dedup();
+        let witnesses: Vec<String> = witnesses.iter().map(|w| w.to_string()).collect();
+        let primes = self.factors.len();
+        if primes == 0 {
+            return write!(f, "small enough to check by hand");
+        }
+        match self.method {
+            Method::NMinus1 => write!(
+                f,
+                "n − 1 proof (Pocklington) over {} primes of n − 1, bases a = {}",
+                primes,
+                witnesses.join(", ")
+            ),
+            Method::NPlus1 => write!(
+                f,
+                "n + 1 proof (Morrison) over {} primes of n + 1, Lucas P = 1, Q = (1 − D)/4, D = {}",
+                primes,
+                witnesses.join(", ")
+            ),
+        }
+    }
+}
+
+/// Prove `n` prime with `method`, given F = ∏ q^e from `factors` dividing n ∓ 1
+pub fn prove(n: &Integer, method: Method, factors: &[(Integer, u32)]) -> Outcome {
+    if *n < 2 {
+        return Outcome::Composite;
+    }
+    // Too small for F to leave room, and prime by inspection
+    if *n <= 3 {
+        return Outcome::Prime(Proof {
+            method,
+            factors: Vec::new(),
+            witnesses: Vec::new(),
+        });
+    }
+    if n.is_even() || !bpsw::is_probable_prime(n) {
+        return Outcome::Composite;
+    }
+
+    let m = match method {
+        Method::NMinus1 => Integer::from(n - 1u32),
+        Method::NPlus1 => Integer::from(n + 1u32),
+    };
+    let mut f = Integer::from(1);
+    for (q, e) in factors {
+        f *= q.clone().pow(*e);
+    }
+    if !m.is_divisible(&f) {
+        return Outcome::Unproven("the factors do not divide n ∓ 1".to_string());
+    }
+    // Prime factors of n are at least F + 1 (n − 1) or F − 1 (n + 1)
+    let smallest_factor = match method {
+        Method::NMinus1 => Integer::from(&f + 1u32),
+        Method::NPlus1 => Integer::from(&f - 1u32),
+    };
+    if Integer::from(smallest_factor.square_ref()) <= *n {
+        return Outcome::Unproven("the factored part of n ∓ 1 is below sqrt(n)".to_string());
+    }
+
+    let cofactors: Vec<Integer> = factors.iter().map(|(q, _)| Integer::from(&m / q)).collect();
+    let witnesses = match method {
+        Method::NMinus1 => cofactors
+            .iter()
+            .map(|cofactor| pocklington_base(n, &m, cofactor))
+            .collect::<Result<Option<Vec<i64>>, ()>>(),
+        Method::NPlus1 => {
+            morrison_discriminant(n, &m, &cofactors).map(|d| d.map(|d| vec![d; cofactors.len()]))
+        }
+    };
+    match witnesses {
+        Ok(Some(witnesses)) => Outcome::Prime(Proof {
+            method,
+            factors: factors.to_vec(),
+            witnesses,
+        }),
+        Ok(None) => Outcome::Unproven(format!("no witness among the first {}", MAX_WITNESS)),
+        Err(()) => Outcome::Composite,
+    }
+}
+
+/// A base a with a^m ≡ 1 and gcd(a^(m/q) − 1, n) = 1; Err if some a shows n composite
+fn pocklington_base(n: &Integer, m: &Integer, cofactor: &Integer) -> Result<Option<i64>, ()> {
+    for a in 2..MAX_WITNESS + 2 {
+        let a_big = Integer::from(a);
+        if Integer::from(a_big.pow_mod_ref(m, n).unwrap()) != 1 {
+            return Err(());
+        }
+        let partial = Integer::from(a_big.pow_mod_ref(cofactor, n).unwrap()) - 1u32;
+        if partial.gcd(n) == 1 {
+            return Ok(Some(a as i64));
+        }
+    }
+    Ok(None)
+}
+
+/// A discriminant D from 5, −7, 9, −11, ... with (D | n) = −1 whose Lucas sequence
+/// P = 1, Q = (1 − D)/4 has U_m ≡ 0 and gcd(U_(m/q), n) = 1 for every cofactor m/q;
+/// Err if some D shows n composite
+fn morrison_discriminant(
+    n: &Integer,
+    m: &Integer,
+    cofactors: &[Integer],
+) -> Result<Option<i64>, ()> {
+    let p = Integer::from(1);
+    let mut d: i64 = 5;
+    let mut tried = 0;
+    while tried < MAX_WITNESS {
+        let candidate = d;
+        d = if d > 0 { -(d + 2) } else { -d + 2 };
+        let d_big = Integer::from(candidate);
+        match d_big.jacobi(n) {
+            -1 => {}
+            // D shares a factor with n, which is then composite unless it is that factor
+            0 if Integer::from(d_big.abs_ref()) < *n => return Err(()),
+            _ => continue,
+        }
+        tried += 1;
+        // With Q a square mod n, U_(m/2) ≡ 0 for prime n and q = 2 can never be ruled out
+        let q = Integer::from((1 - candidate) / 4);
+        if q.jacobi(n) != -1 {
+            continue;
+        }
+        if lucas(&p, &q, m, n).0 != 0 {
+            return Err(());
+        }
+        if cofactors
+            .iter()
+            .all(|cofactor| lucas(&p, &q, cofactor, n).0.gcd(n) == 1)
+        {
+            return Ok(Some(candidate));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primorial_factors(primes: &[u32]) -> Vec<(Integer, u32)> {
+        primes.iter().map(|&p| (Integer::from(p), 1)).collect()
+    }
+
+    #[test]
+    fn test_primorial_primes() {
+        // 11# + 1 = 2311 and 13# − 1 = 30029 are prime, 13# + 1 = 30031 = 59 · 509 is not
+        let p11 = primorial_factors(&[2, 3, 5, 7, 11]);
+        let p13 = primorial_factors(&[2, 3, 5, 7, 11, 13]);
+        assert!(matches!(
+            prove(&Integer::from(2311), Method::NMinus1, &p11),
+            Outcome::Prime(_)
+        ));
+        let Outcome::Prime(proof) = prove(&Integer::from(30029), Method::NPlus1, &p13) else {
+            panic!("30029 is prime");
+        };
+        assert_eq!(proof.witnesses.len(), 6);
+        assert_eq!(
+            prove(&Integer::from(30031), Method::NMinus1, &p13),
+            Outcome::Composite
+        );
+        // 2 alone is not enough of 30028 = 2² · 7507 to prove anything
+        assert!(matches!(
+            prove(
+                &Integer::from(30029),
+                Method::NMinus1,
+                &primorial_factors(&[2])
+            ),
+            Outcome::Unproven(_)
+        ));
+    }
+}
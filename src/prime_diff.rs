@@ -0,0 +1,224 @@
+// Compare two prime files (`nt diff`)
+//
+// Both files are streamed in one merge pass, so files far larger than memory compare in
+// constant space. Every prime in only one of them is counted and the first few are listed
+// with their position (1-based) in the file that has them, which is what matters when a
+// new variation or a distributed run drops or repeats a segment. A file that is not
+// strictly increasing stops the comparison: the merge would misreport everything after it.
+
+use std::fmt;
+
+/// Which of the two files a prime was found in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// Only in the first (reference) file: missing from the second
+    Missing,
+    /// Only in the second file
+    Extra,
+}
+
+/// A prime in one file and not the other
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Difference {
+    pub side: Side,
+    pub prime: usize,
+    /// Position in the file that has it, from 1
+    pub position: u64,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.side {
+            Side::Missing => write!(f, "- {} (#{} in A)", self.prime, self.position),
+            Side::Extra => write!(f, "+ {} (#{} in B)", self.prime, self.position),
+        }
+    }
+}
+
+/// Result of comparing two files
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Diff {
+    pub count_a: u64,
+    pub count_b: u64,
+    pub missing: u64,
+    pub extra: u64,
+    /// The first differences, in increasing order of prime
+    pub listed: Vec<Difference>,
+}
+
+impl Diff {
+    pub fn is_same(&self) -> bool {
+        self.missing == 0 && self.extra == 0
+    }
+}
+
+/// A file that goes down or repeats a prime
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfOrder {
+    /// "A" or "B"
+    pub file: &'static str,
+    pub position: u64,
+    pub prime: usize,
+    pub previous: usize,
+}
+
+impl fmt::Display for OutOfOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is not increasing: #{} is {} after {}",
+            self.file, self.position, self.prime, self.previous
+        )
+    }
+}
+
+/// One file being read, checked for order as it goes
+struct Cursor<I: Iterator<Item = usize>> {
+    file: &'static str,
+    primes: I,
+    head: Option<usize>,
+    /// Primes read so far, the head included
+    count: u64,
+}
+
+impl<I: Iterator<Item = usize>> Cursor<I> {
+    fn new(file: &'static str, primes: I) -> Result<Self, OutOfOrder> {
+        let mut cursor = Self {
+            file,
+            primes,
+            head: None,
+            count: 0,
+        };
+        cursor.advance()?;
+        Ok(cursor)
+    }
+
+    fn advance(&mut self) -> Result<(), OutOfOrder> {
+        let previous = self.head;
+        self.head = self.primes.next();
+        if let Some(prime) = self.head {
+            self.count += 1;
+            if let Some(previous) = previous
+                && prime <= previous
+            {
+                return Err(OutOfOrder {
+                    file: self.file,
+                    position: self.count,
+                    prime,
+                    previous,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compare the sorted primes of `a` (the reference) and `b`, listing at most `max_listed`
+/// differences
+pub fn diff(
+    a: impl Iterator<Item = usize>,
+    b: impl Iterator<Item = usize>,
+    max_listed: usize,
+) -> Result<Diff, OutOfOrder> {
+    let mut a = Cursor::new("A", a)?;
+    let mut b = Cursor::new("B", b)?;
+    let mut result = Diff::default();
+    let record = |result: &mut Diff, side: Side, prime: usize, position: u64| {
+        match side {
+            Side::Missing => result.missing += 1,
+            Side::Extra => result.extra += 1,
+        }
+        if result.listed.len() < max_listed {
+            result.listed.push(Difference {
+                side,
+                prime,
+                position,
+            });
+        }
+    };
+    loop {
+        match (a.head, b.head) {
+            (None, None) => break,
+            (Some(p), Some(q)) if p == q => {
+                a.advance()?;
+                b.advance()?;
+            }
+            (Some(p), q) if q.is_none_or(|q| p < q) => {
+                record(&mut result, Side::Missing, p, a.count);
+                a.advance()?;
+            }
+            (_, Some(q)) => {
+                record(&mut result, Side::Extra, q, b.count);
+                b.advance()?;
+            }
+            (Some(_), None) => unreachable!(),
+        }
+    }
+    result.count_a = a.count;
+    result.count_b = b.count;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lists_missing_and_extra_with_positions() {
+        let a = [2, 3, 5, 7, 11, 13, 17];
+        let b = [2, 3, 7, 9, 11, 13, 17, 19];
+        let result = diff(a.into_iter(), b.into_iter(), 10).unwrap();
+        assert_eq!((result.count_a, result.count_b), (7, 8));
+        assert_eq!((result.missing, result.extra), (1, 2));
+        assert_eq!(
+            result.listed,
+            vec![
+                Difference {
+                    side: Side::Missing,
+                    prime: 5,
+                    position: 3
+                },
+                Difference {
+                    side: Side::Extra,
+                    prime: 9,
+                    position: 4
+                },
+                Difference {
+                    side: Side::Extra,
+                    prime: 19,
+                    position: 8
+                },
+            ]
+        );
+        assert_eq!(result.listed[0].to_string(), "- 5 (#3 in A)");
+    }
+
+    #[test]
+    fn test_diff_counts_past_the_listing_limit() {
+        let primes = crate::primes::sieve(10_000);
+        let result = diff(primes.iter().copied(), std::iter::empty(), 3).unwrap();
+        assert_eq!(result.missing, primes.len() as u64);
+        assert_eq!(result.listed.len(), 3);
+        assert!(
+            diff(primes.iter().copied(), primes.iter().copied(), 3)
+                .unwrap()
+                .is_same()
+        );
+    }
+
+    #[test]
+    fn test_diff_rejects_unsorted_input() {
+        let err = diff([2, 3, 5].into_iter(), [2, 5, 3].into_iter(), 10).unwrap_err();
+        assert_eq!(
+            err,
+            OutOfOrder {
+                file: "B",
+                position: 3,
+                prime: 3,
+                previous: 5
+            }
+        );
+        // Repeats are out of order too
+        assert!(diff([2, 3, 3].into_iter(), [2, 3].into_iter(), 10).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+// Decimal digit statistics of the primes (`nt prime-digits`)
+//
+// One pass in increasing order over the primes up to a limit counts leading digits, last
+// digits, digit sums and the last-digit transitions between consecutive primes. The primes
+// are streamed from primes.bin or primes.txt so nothing is held in memory, and whatever the
+// stored file does not reach is sieved segment by segment after it.
+//
+// - Leading digits: Benford's law gives d a share of log10(1 + 1/d) for data spread over
+//   many orders of magnitude; the primes up to a power of ten are not, and their leading
+//   digits come out close to uniform, with the excess over Benford shrinking only slowly.
+// - Last digits: every prime past 5 ends in 1, 3, 7 or 9, and by Dirichlet each class
+//   gets a quarter of them.
+// - Digit sums: only 3 has a digit sum divisible by 3.
+// - Transitions: Lemke Oliver and Soundararajan (2016) showed that consecutive primes
+//   avoid repeating their last digit. A prime ending in 1 is followed by another ending in 1
+//   far less often than the 25% independence suggests, with the deficit fading like
+//   1 / ln x.
+
+use tracing::warn;
+
+use crate::primes;
+use crate::storage;
+
+/// Digit statistics of the primes up to `limit`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DigitStats {
+    pub limit: usize,
+    pub count: u64,
+    /// Indexed by leading digit
+    pub leading: [u64; 10],
+    /// Indexed by last digit
+    pub last: [u64; 10],
+    /// Indexed by digit sum
+    pub digit_sums: Vec<u64>,
+    /// `transitions[a][b]` counts consecutive primes ending in a and then b
+    pub transitions: [[u64; 10]; 10],
+    previous: Option<usize>,
+}
+
+impl DigitStats {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            count: 0,
+            leading: [0; 10],
+            last: [0; 10],
+            digit_sums: Vec::new(),
+            transitions: [[0; 10]; 10],
+            previous: None,
+        }
+    }
+
+    /// Count the next prime, which must be larger than the ones before
+    fn add(&mut self, p: usize) {
+        self.count += 1;
+        let last = p % 10;
+        self.last[last] += 1;
+        if let Some(previous) = self.previous {
+            self.transitions[previous % 10][last] += 1;
+        }
+        self.previous = Some(p);
+
+        let mut sum = 0;
+        let mut rest = p;
+        while rest >= 10 {
+            sum += rest % 10;
+            rest /= 10;
+        }
+        self.leading[rest] += 1;
+        sum += rest;
+        if sum >= self.digit_sums.len() {
+            self.digit_sums.resize(sum + 1, 0);
+        }
+        self.digit_sums[sum] += 1;
+    }
+
+    /// Share of the transitions out of last digit `from` that go to `to`
+    pub fn transition_share(&self, from: usize, to: usize) -> f64 {
+        let row: u64 = self.transitions[from].iter().sum();
+        if row == 0 {
+            return 0.0;
+        }
+        self.transitions[from][to] as f64 / row as f64
+    }
+}
+
+/// Benford's share for leading digit `d` (1-9)
+pub fn benford(d: usize) -> f64 {
+    (1.0 + 1.0 / d as f64).log10()
+}
+
+/// Digit statistics for the primes up to `limit`, and how many came from the stored file
+pub fn compute(limit: usize) -> (DigitStats, u64) {
+    let mut stats = DigitStats::new(limit);
+    let stored = storage::stream_primes(true).or_else(|_| storage::stream_primes(false));
+    if let Ok(stored) = stored {
+        for p in stored {
+            match p {
+                Ok(p) if p <= limit => stats.add(p),
+                Ok(_) => break,
+                // The sieve below picks up where the file stopped
+                Err(e) => {
+                    warn!("Error reading stored primes, sieving the rest: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+    let from_file = stats.count;
+
+    // Sieve whatever the file did not reach
+    let start = stats.previous.map_or(0, |p| p + 1);
+    if start <= limit {
+        primes::for_each_prime_in_range(start, limit, |segment| {
+            for &p in segment {
+                stats.add(p);
+            }
+        });
+    }
+    (stats, from_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_limit() {
+        let mut stats = DigitStats::new(100);
+        for p in primes::sieve(100) {
+            stats.add(p);
+        }
+        assert_eq!(stats.count, 25);
+        assert_eq!(stats.leading, [0, 4, 3, 3, 3, 3, 2, 4, 2, 1]);
+        assert_eq!(stats.last, [0, 5, 1, 7, 0, 1, 0, 6, 0, 5]);
+        // 89 has the largest digit sum
+        assert_eq!(stats.digit_sums.len(), 18);
+        assert_eq!(stats.digit_sums[17], 1);
+        assert_eq!(stats.digit_sums[3], 1);
+        // 2 → 3, 3 → 5, 5 → 7, 7 → 11
+        assert_eq!(stats.transitions[2][3], 1);
+        assert_eq!(stats.transitions[5][7], 1);
+        let total: u64 = stats.transitions.iter().flatten().sum();
+        assert_eq!(total, 24);
+    }
+
+    #[test]
+    fn test_repeated_last_digits_are_rare() {
+        let (stats, _) = compute(1_000_000);
+        assert_eq!(stats.count, 78_498);
+        for d in [1, 3, 7, 9] {
+            assert!(stats.transition_share(d, d) < 0.2, "{} → {}", d, d);
+        }
+        let benford_total: f64 = (1..=9).map(benford).sum();
+        assert!((benford_total - 1.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,324 @@
+// Rank and select over stored primes (`nt rank`, `nt select`)
+//
+// A sparse index beside the prime file (primes.bin.idx, primes.txt.idx) records every
+// STRIDE-th prime and the byte offset it starts at. A query binary searches those entries
+// in memory and then reads at most one block of STRIDE primes from the file, so finding
+// the index of a prime, or the kth prime, in a multi-gigabyte file costs one seek and a
+// few KB of reading. The index is built by one pass over the file the first time it is
+// needed, and rebuilt whenever the file is newer or a different size than when it was
+// indexed.
+//
+// Index layout (little-endian): b"NTIDX001", stride, prime count and source file length
+// as u64, then (prime, offset) u64 pairs for primes 1, STRIDE + 1, 2·STRIDE + 1, ...
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::segment_format::SegmentEncoding;
+use crate::storage::{commit_output, get_nt_data_dir, staging_path};
+use crate::storage_writer::file_name;
+
+const MAGIC: &[u8; 8] = b"NTIDX001";
+const HEADER_BYTES: usize = 32;
+
+// Primes per index entry: a block is 32 KB of primes.bin and an entry per 4096 primes
+// keeps the index of 10^9 primes near 4 MB
+const STRIDE: u64 = 4096;
+
+/// The sparse index of one prime file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrimeIndex {
+    source: PathBuf,
+    encoding: SegmentEncoding,
+    /// Primes in the file
+    pub count: u64,
+    /// (prime, byte offset) of primes 1, STRIDE + 1, ... (numbered from 1)
+    entries: Vec<(u64, u64)>,
+}
+
+/// Where a number falls among the stored primes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rank {
+    /// The number is the nth prime
+    Prime(u64),
+    /// Not in the file; this many primes are smaller
+    Between(u64),
+    /// Larger than the last stored prime
+    Beyond,
+}
+
+impl PrimeIndex {
+    /// Load the index of `source` (binary if `encoding` says so), building it first if it
+    /// is missing or stale
+    pub fn open(source: &Path, encoding: SegmentEncoding) -> io::Result<Self> {
+        let path = index_path(source);
+        let source_len = fs::metadata(source)?.len();
+        let fresh = match (modified(source), modified(&path)) {
+            (Some(source_time), Some(index_time)) => index_time >= source_time,
+            _ => false,
+        };
+        if fresh && let Ok(index) = Self::load(&path, source, encoding, source_len) {
+            return Ok(index);
+        }
+        let index = Self::build(source, encoding)?;
+        index.save(&path, source_len)?;
+        Ok(index)
+    }
+
+    /// Index `source` in one pass
+    pub fn build(source: &Path, encoding: SegmentEncoding) -> io::Result<Self> {
+        let mut reader = BufReader::with_capacity(256 * 1024, File::open(source)?);
+        let mut entries = Vec::new();
+        let mut count = 0;
+        let mut offset = 0;
+        while let Some((prime, len)) = read_prime(&mut reader, encoding)? {
+            if count % STRIDE == 0 {
+                entries.push((prime, offset));
+            }
+            count += 1;
+            offset += len;
+        }
+        Ok(Self {
+            source: source.to_path_buf(),
+            encoding,
+            count,
+            entries,
+        })
+    }
+
+    fn save(&self, path: &Path, source_len: u64) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(staging_path(path))?);
+        writer.write_all(MAGIC)?;
+        for value in [STRIDE, self.count, source_len] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        for &(prime, offset) in &self.entries {
+            writer.write_all(&prime.to_le_bytes())?;
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        writer.flush()?;
+        drop(writer);
+        commit_output(path)
+    }
+
+    fn load(
+        path: &Path,
+        source: &Path,
+        encoding: SegmentEncoding,
+        source_len: u64,
+    ) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let word = |i: usize| u64::from_le_bytes(bytes[8 * i..8 * i + 8].try_into().unwrap());
+        let stale = || io::Error::new(io::ErrorKind::InvalidData, "stale prime index");
+        if bytes.len() < HEADER_BYTES || &bytes[..8] != MAGIC {
+            return Err(stale());
+        }
+        let count = word(2);
+        if word(1) != STRIDE
+            || word(3) != source_len
+            || bytes.len() != HEADER_BYTES + 16 * count.div_ceil(STRIDE) as usize
+        {
+            return Err(stale());
+        }
+        let entries = (HEADER_BYTES / 8..bytes.len() / 8)
+            .step_by(2)
+            .map(|i| (word(i), word(i + 1)))
+            .collect();
+        Ok(Self {
+            source: source.to_path_buf(),
+            encoding,
+            count,
+            entries,
+        })
+    }
+
+    /// Read up to `n` primes starting at block `block`
+    fn read_block(&self, block: usize, n: u64) -> io::Result<Vec<u64>> {
+        let mut file = File::open(&self.source)?;
+        file.seek(SeekFrom::Start(self.entries[block].1))?;
+        let mut reader = BufReader::new(file);
+        let mut primes = Vec::new();
+        while (primes.len() as u64) < n {
+            match read_prime(&mut reader, self.encoding)? {
+                Some((prime, _)) => primes.push(prime),
+                None => break,
+            }
+        }
+        Ok(primes)
+    }
+
+    /// Where `n` falls among the stored primes
+    pub fn rank(&self, n: u64) -> io::Result<Rank> {
+        let Some(&(first, _)) = self.entries.first() else {
+            return Ok(Rank::Beyond);
+        };
+        if n < first {
+            return Ok(Rank::Between(0));
+        }
+        // Last entry <= n; its block holds n if anything does
+        let block = self.entries.partition_point(|&(prime, _)| prime <= n) - 1;
+        let before = block as u64 * STRIDE;
+        let primes = self.read_block(block, STRIDE)?;
+        let below = primes.partition_point(|&prime| prime < n);
+        if primes.get(below) == Some(&n) {
+            return Ok(Rank::Prime(before + below as u64 + 1));
+        }
+        if before + below as u64 == self.count {
+            return Ok(Rank::Beyond);
+        }
+        Ok(Rank::Between(before + below as u64))
+    }
+
+    /// The kth stored prime (k from 1), or None past the end
+    pub fn select(&self, k: u64) -> io::Result<Option<u64>> {
+        if k == 0 || k > self.count {
+            return Ok(None);
+        }
+        let block = ((k - 1) / STRIDE) as usize;
+        let within = (k - 1) % STRIDE;
+        if within == 0 {
+            return Ok(Some(self.entries[block].0));
+        }
+        let primes = self.read_block(block, within + 1)?;
+        Ok(primes.get(within as usize).copied())
+    }
+}
+
+/// Open the index of `file` (.bin is binary, anything else text), or by default of
+/// primes.bin in the data directory, falling back to primes.txt
+pub fn open_default(file: Option<&Path>) -> io::Result<PrimeIndex> {
+    let path = match file {
+        Some(path) => path.to_path_buf(),
+        None => {
+            let dir = get_nt_data_dir();
+            let binary = dir.join(file_name("primes", SegmentEncoding::Binary));
+            if binary.exists() {
+                binary
+            } else {
+                dir.join(file_name("primes", SegmentEncoding::Text))
+            }
+        }
+    };
+    let encoding = if path.extension().is_some_and(|ext| ext == "bin") {
+        SegmentEncoding::Binary
+    } else {
+        SegmentEncoding::Text
+    };
+    PrimeIndex::open(&path, encoding)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+/// `<source>.idx`
+pub fn index_path(source: &Path) -> PathBuf {
+    let mut name = source.file_name().unwrap_or_default().to_os_string();
+    name.push(".idx");
+    source.with_file_name(name)
+}
+
+fn modified(path: &Path) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The next prime in `reader` and the bytes it took, or None at the end
+fn read_prime(
+    reader: &mut impl BufRead,
+    encoding: SegmentEncoding,
+) -> io::Result<Option<(u64, u64)>> {
+    match encoding {
+        SegmentEncoding::Binary => {
+            let mut bytes = [0_u8; 8];
+            match reader.read_exact(&mut bytes) {
+                Ok(()) => Ok(Some((u64::from_le_bytes(bytes), 8))),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+                Err(e) => Err(e),
+            }
+        }
+        SegmentEncoding::Text => {
+            let mut line = Vec::new();
+            let len = reader.read_until(b'\n', &mut line)? as u64;
+            if len == 0 {
+                return Ok(None);
+            }
+            let text = std::str::from_utf8(&line).unwrap_or("").trim();
+            if text.is_empty() {
+                // A blank line belongs to the prime after it
+                return Ok(read_prime(reader, encoding)?.map(|(prime, rest)| (prime, len + rest)));
+            }
+            match text.parse() {
+                Ok(prime) => Ok(Some((prime, len))),
+                Err(_) => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("'{}' is not a number", text),
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_primes(name: &str, encoding: SegmentEncoding, primes: &[usize]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("nt_index_{}_{}", std::process::id(), name));
+        let bytes: Vec<u8> = match encoding {
+            SegmentEncoding::Binary => primes
+                .iter()
+                .flat_map(|&p| (p as u64).to_le_bytes())
+                .collect(),
+            SegmentEncoding::Text => primes
+                .iter()
+                .map(|p| format!("{}\n", p))
+                .collect::<String>()
+                .into_bytes(),
+        };
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_rank_and_select_in_both_formats() {
+        let primes = crate::primes::sieve(200_000);
+        for (name, encoding) in [
+            ("primes.bin", SegmentEncoding::Binary),
+            ("primes.txt", SegmentEncoding::Text),
+        ] {
+            let path = write_primes(name, encoding, &primes);
+            let index = PrimeIndex::open(&path, encoding).unwrap();
+            assert_eq!(index.count, primes.len() as u64);
+            for k in [1, 2, 4096, 4097, 8193, primes.len()] {
+                let p = primes[k - 1] as u64;
+                assert_eq!(index.select(k as u64).unwrap(), Some(p), "{} k {}", name, k);
+                assert_eq!(index.rank(p).unwrap(), Rank::Prime(k as u64), "{}", name);
+            }
+            assert_eq!(index.select(0).unwrap(), None);
+            assert_eq!(index.select(primes.len() as u64 + 1).unwrap(), None);
+            assert_eq!(index.rank(1).unwrap(), Rank::Between(0));
+            assert_eq!(index.rank(100).unwrap(), Rank::Between(25));
+            assert_eq!(index.rank(200_000).unwrap(), Rank::Beyond);
+
+            // The saved index is reused, and matches a fresh build
+            assert_eq!(PrimeIndex::open(&path, encoding).unwrap(), index);
+            fs::remove_file(index_path(&path)).unwrap();
+            fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_stale_index_is_rebuilt() {
+        let path = write_primes("stale.bin", SegmentEncoding::Binary, &[2, 3, 5]);
+        assert_eq!(
+            PrimeIndex::open(&path, SegmentEncoding::Binary)
+                .unwrap()
+                .count,
+            3
+        );
+        fs::write(&path, [2u64, 3, 5, 7].map(u64::to_le_bytes).concat()).unwrap();
+        let index = PrimeIndex::open(&path, SegmentEncoding::Binary).unwrap();
+        assert_eq!(index.count, 4);
+        assert_eq!(index.select(4).unwrap(), Some(7));
+        fs::remove_file(index_path(&path)).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+}
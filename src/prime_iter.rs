@@ -0,0 +1,148 @@
+// Unbounded iterator over the primes
+//
+// Sieves one odd-only window at a time and grows its base primes as the windows pass
+// their square, so callers never choose a limit up front and memory stays at
+// O(sqrt(n) + window).
+
+use crate::PrimeSet;
+
+// Odd numbers covered by each window (128 KB of bool)
+const WINDOW_ODDS: usize = 128 * 1024;
+
+/// Iterator yielding 2, 3, 5, 7, 11, ... until usize runs out
+pub struct PrimeIterator {
+    // Sieving primes (odd only) and the bound they are complete up to
+    base: Vec<usize>,
+    base_limit: usize,
+    // Primes of the current window and the next one to yield
+    window: Vec<usize>,
+    pos: usize,
+    // First odd number of the next window
+    next_low: usize,
+    yielded_two: bool,
+}
+
+impl PrimeIterator {
+    pub fn new() -> Self {
+        Self {
+            base: Vec::new(),
+            base_limit: 1,
+            window: Vec::new(),
+            pos: 0,
+            next_low: 3,
+            yielded_two: false,
+        }
+    }
+
+    /// Iterator over the primes >= `start`, sieving from there rather than from 2
+    pub fn starting_at(start: usize) -> Self {
+        let mut primes = Self::new();
+        if start > 2 {
+            primes.yielded_two = true;
+            primes.next_low = start | 1;
+        }
+        primes
+    }
+
+    /// Sieve the next window into `self.window`; false once usize is exhausted
+    fn fill_window(&mut self) -> bool {
+        let low = self.next_low;
+        let Some(high) = low.checked_add(2 * (WINDOW_ODDS - 1)) else {
+            return false;
+        };
+
+        // Make sure every prime <= sqrt(high) is available for sieving
+        let sqrt_high = high.isqrt();
+        if self.base_limit < sqrt_high {
+            self.base_limit = sqrt_high.max(self.base_limit * 2);
+            self.base = PrimeSet::new(self.base_limit).iter().skip(1).collect();
+        }
+
+        // Index i represents low + 2*i
+        let mut is_prime = vec![true; WINDOW_ODDS];
+        for &p in &self.base {
+            if p * p > high {
+                break;
+            }
+            // First odd multiple of p in [low, high], never p itself
+            let mut start = (p * p).max(low.div_ceil(p) * p);
+            if start.is_multiple_of(2) {
+                start += p;
+            }
+            let mut i = (start - low) / 2;
+            while i < WINDOW_ODDS {
+                is_prime[i] = false;
+                i += p;
+            }
+        }
+
+        self.window.clear();
+        self.window.extend(
+            is_prime
+                .iter()
+                .enumerate()
+                .filter(|&(_, &prime)| prime)
+                .map(|(i, _)| low + 2 * i),
+        );
+        self.pos = 0;
+        self.next_low = high + 2;
+        true
+    }
+}
+
+impl Default for PrimeIterator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Iterator for PrimeIterator {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if !self.yielded_two {
+            self.yielded_two = true;
+            return Some(2);
+        }
+
+        while self.pos >= self.window.len() {
+            if !self.fill_window() {
+                return None;
+            }
+        }
+
+        let prime = self.window[self.pos];
+        self.pos += 1;
+        Some(prime)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iterator_matches_prime_set_across_windows() {
+        // Past several windows so the base primes have to grow
+        let limit = 2_000_000;
+        let expected: Vec<usize> = PrimeSet::new(limit).iter().collect();
+        let actual: Vec<usize> = PrimeIterator::new().take_while(|&p| p <= limit).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_starting_at() {
+        let from = |start| {
+            PrimeIterator::starting_at(start)
+                .take(3)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(from(0), [2, 3, 5]);
+        assert_eq!(from(3), [3, 5, 7]);
+        assert_eq!(from(24), [29, 31, 37]);
+        assert_eq!(
+            from(1_000_000_000_000),
+            [1_000_000_000_039, 1_000_000_000_061, 1_000_000_000_063]
+        );
+    }
+}
@@ -0,0 +1,249 @@
+// Constant-time primality lookups up to a fixed limit
+//
+// Keeps the sieve itself instead of the list of primes: one bit per odd number, so a
+// set up to 10^9 takes ~60 MB where the Vec<usize> of its primes would take ~400 MB.
+// Sets can be saved to disk and reopened with mmap, so lookups into a large set cost
+// no sieving and only touch the pages actually queried.
+//
+// File layout (little-endian): b"NTPSET01", limit: u64, count: u64, then the bit words.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Deref;
+use std::path::Path;
+
+#[cfg(all(unix, target_endian = "little"))]
+use crate::mmap::MappedFile;
+
+const MAGIC: &[u8; 8] = b"NTPSET01";
+const HEADER_BYTES: usize = 24;
+
+/// Bit-packed odd-only set of the primes <= limit
+pub struct PrimeSet {
+    // Bit i represents the odd number 2*i + 1
+    bits: Bits,
+    limit: usize,
+    count: usize,
+}
+
+/// Bit words either sieved in memory or mapped from a saved set
+enum Bits {
+    Owned(Vec<u64>),
+    #[cfg(all(unix, target_endian = "little"))]
+    Mapped(MappedFile),
+}
+
+impl Deref for Bits {
+    type Target = [u64];
+
+    fn deref(&self) -> &[u64] {
+        match self {
+            Bits::Owned(words) => words,
+            #[cfg(all(unix, target_endian = "little"))]
+            Bits::Mapped(mapped) => mapped.u64s(HEADER_BYTES),
+        }
+    }
+}
+
+impl PrimeSet {
+    /// Sieve every prime up to and including `limit`
+    pub fn new(limit: usize) -> Self {
+        let odd_count = limit.div_ceil(2);
+        let mut bits = vec![!0_u64; odd_count.div_ceil(64)];
+
+        // 1 is not prime
+        if odd_count > 0 {
+            bits[0] &= !1;
+        }
+
+        let mut i = 1;
+        while (2 * i + 1) * (2 * i + 1) <= limit {
+            if bits[i / 64] & (1 << (i % 64)) != 0 {
+                let p = 2 * i + 1;
+                // Odd multiples of p from p², stepping by 2p
+                let mut j = (p * p) / 2;
+                while j < odd_count {
+                    bits[j / 64] &= !(1 << (j % 64));
+                    j += p;
+                }
+            }
+            i += 1;
+        }
+
+        // Clear the padding past the last odd number so counts stay exact
+        if !odd_count.is_multiple_of(64) {
+            let last = bits.len() - 1;
+            bits[last] &= (1_u64 << (odd_count % 64)) - 1;
+        }
+
+        let odd_primes: usize = bits.iter().map(|w| w.count_ones() as usize).sum();
+        let count = odd_primes + usize::from(limit >= 2);
+
+        Self {
+            bits: Bits::Owned(bits),
+            limit,
+            count,
+        }
+    }
+
+    /// Write the set to `path` so it can be reopened with `open`
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.limit as u64).to_le_bytes())?;
+        writer.write_all(&(self.count as u64).to_le_bytes())?;
+        for word in self.bits.iter() {
+            writer.write_all(&word.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+
+    /// Open a set written by `save`, memory-mapping its bits where supported
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+
+        #[cfg(all(unix, target_endian = "little"))]
+        {
+            let mapped = MappedFile::map(&file)?;
+            let (limit, count) = parse_header(mapped.bytes())?;
+            Self::check_word_bytes(limit, mapped.bytes().len() - HEADER_BYTES)?;
+            Ok(Self {
+                bits: Bits::Mapped(mapped),
+                limit,
+                count,
+            })
+        }
+
+        #[cfg(not(all(unix, target_endian = "little")))]
+        {
+            use std::io::Read;
+
+            let mut bytes = Vec::new();
+            let mut file = file;
+            file.read_to_end(&mut bytes)?;
+            let (limit, count) = parse_header(&bytes)?;
+            Self::check_word_bytes(limit, bytes.len() - HEADER_BYTES)?;
+            let words: Vec<u64> = bytes[HEADER_BYTES..]
+                .chunks_exact(8)
+                .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+                .collect();
+            Ok(Self {
+                bits: Bits::Owned(words),
+                limit,
+                count,
+            })
+        }
+    }
+
+    fn check_word_bytes(limit: usize, bytes: usize) -> io::Result<()> {
+        if bytes != limit.div_ceil(2).div_ceil(64) * 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "prime set file is truncated or has trailing data",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether `n` is prime; `n` must be <= limit
+    pub fn contains(&self, n: usize) -> bool {
+        assert!(
+            n <= self.limit,
+            "{} is above the PrimeSet limit {}",
+            n,
+            self.limit
+        );
+        if n == 2 {
+            return true;
+        }
+        if n.is_multiple_of(2) {
+            return false;
+        }
+        let i = n / 2;
+        self.bits[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    /// Largest number this set can answer for
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Number of primes <= limit
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Primes in the set in increasing order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        let two = (self.limit >= 2).then_some(2);
+        let odd = self.bits.iter().enumerate().flat_map(|(word_idx, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit_idx = word.trailing_zeros() as usize;
+                word &= word - 1; // Clear lowest set bit
+                Some(2 * (word_idx * 64 + bit_idx) + 1)
+            })
+        });
+        two.into_iter().chain(odd)
+    }
+}
+
+/// (limit, count) from a saved set's header
+fn parse_header(bytes: &[u8]) -> io::Result<(usize, usize)> {
+    if bytes.len() < HEADER_BYTES || &bytes[..8] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a prime set file",
+        ));
+    }
+    let limit = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+    let count = u64::from_le_bytes(bytes[16..24].try_into().unwrap());
+    Ok((limit as usize, count as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factor;
+
+    #[test]
+    fn test_prime_set_matches_primality_test() {
+        for limit in [0, 1, 2, 3, 63, 64, 127, 128, 1000, 10_007] {
+            let set = PrimeSet::new(limit);
+            let expected: Vec<usize> = (0..=limit)
+                .filter(|&n| factor::is_prime(n as u64))
+                .collect();
+            assert_eq!(set.iter().collect::<Vec<_>>(), expected, "limit {}", limit);
+            assert_eq!(set.len(), expected.len());
+            for n in 0..=limit {
+                assert_eq!(
+                    set.contains(n),
+                    expected.binary_search(&n).is_ok(),
+                    "n {}",
+                    n
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_saved_set_reopens() {
+        let path = std::env::temp_dir().join(format!("nt_primeset_{}.bin", std::process::id()));
+        let set = PrimeSet::new(100_003);
+        set.save(&path).unwrap();
+
+        let reopened = PrimeSet::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(reopened.limit(), set.limit());
+        assert_eq!(reopened.len(), set.len());
+        assert!(reopened.iter().eq(set.iter()));
+    }
+}
@@ -0,0 +1,151 @@
+// Prime sums and statistics (`nt prime-stats`)
+//
+// One pass over the primes up to a limit collects the count, the sum, the sum of
+// reciprocals and the count per residue class for a few small moduli. [0, limit] is cut
+// into chunks that workers take in turn, each streaming its chunk segment by segment
+// through primes::for_each_prime_in_range, so memory stays at one segment per worker
+// whatever the limit. Chunk results merge by adding, except the reciprocal sum, which is
+// compensated (Kahan) within a chunk so millions of tiny terms do not drift.
+//
+// Mertens' second theorem gives Σ 1/p = ln ln x + M + O(1 / ln x) with the Meissel-Mertens
+// constant M; by Dirichlet, primes not dividing m spread evenly over the φ(m) classes
+// coprime to m, with the small lead of the non-residues (Chebyshev's bias) visible in the
+// counts.
+
+use crate::parallel::{chunk_size, parallel_chunks};
+
+/// Meissel-Mertens constant
+pub const MEISSEL_MERTENS: f64 = 0.261_497_212_847_642_8;
+
+// Smallest chunk worth its own base-prime sieve
+const MIN_CHUNK: usize = 1 << 20;
+
+/// Prime counts per residue class for one modulus
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResidueCounts {
+    pub modulus: usize,
+    /// Indexed by residue
+    pub counts: Vec<u64>,
+}
+
+impl ResidueCounts {
+    /// (residue, count) for the classes coprime to the modulus, the ones Dirichlet's
+    /// theorem spreads primes over; primes dividing the modulus sit alone in the rest
+    pub fn coprime_classes(&self) -> Vec<(usize, u64)> {
+        (1..self.modulus)
+            .filter(|&r| gcd(r, self.modulus) == 1)
+            .map(|r| (r, self.counts[r]))
+            .collect()
+    }
+}
+
+fn gcd(mut a: usize, mut b: usize) -> usize {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Count, sum, reciprocal sum and residue classes of the primes up to `limit`
+#[derive(Clone, Debug, PartialEq)]
+pub struct PrimeStats {
+    pub limit: usize,
+    pub count: u64,
+    pub sum: u128,
+    pub reciprocal_sum: f64,
+    pub residues: Vec<ResidueCounts>,
+}
+
+impl PrimeStats {
+    fn empty(limit: usize, moduli: &[usize]) -> Self {
+        Self {
+            limit,
+            count: 0,
+            sum: 0,
+            reciprocal_sum: 0.0,
+            residues: moduli
+                .iter()
+                .map(|&modulus| ResidueCounts {
+                    modulus,
+                    counts: vec![0; modulus],
+                })
+                .collect(),
+        }
+    }
+
+    fn merge(&mut self, other: &PrimeStats) {
+        self.count += other.count;
+        self.sum += other.sum;
+        self.reciprocal_sum += other.reciprocal_sum;
+        for (mine, theirs) in self.residues.iter_mut().zip(&other.residues) {
+            for (a, b) in mine.counts.iter_mut().zip(&theirs.counts) {
+                *a += b;
+            }
+        }
+    }
+
+    /// ln ln x + M, Mertens' estimate of the reciprocal sum (None below 3)
+    pub fn mertens_estimate(&self) -> Option<f64> {
+        (self.limit >= 3).then(|| (self.limit as f64).ln().ln() + MEISSEL_MERTENS)
+    }
+}
+
+/// Statistics for the primes in [low, high] on the calling thread
+fn chunk_stats(low: usize, high: usize, moduli: &[usize]) -> PrimeStats {
+    let mut stats = PrimeStats::empty(high, moduli);
+    let mut compensation = 0.0;
+    crate::primes::for_each_prime_in_range(low, high, |segment| {
+        stats.count += segment.len() as u64;
+        for &p in segment {
+            stats.sum += p as u128;
+            let term = 1.0 / p as f64 - compensation;
+            let total = stats.reciprocal_sum + term;
+            compensation = (total - stats.reciprocal_sum) - term;
+            stats.reciprocal_sum = total;
+            for residue in &mut stats.residues {
+                residue.counts[p % residue.modulus] += 1;
+            }
+        }
+    });
+    stats
+}
+
+/// Statistics for the primes up to `limit`, with residue classes for each of `moduli`
+pub fn compute(limit: usize, moduli: &[usize], num_workers: usize) -> PrimeStats {
+    let mut total = PrimeStats::empty(limit, moduli);
+    parallel_chunks(
+        0..=limit,
+        chunk_size(limit, num_workers, MIN_CHUNK),
+        num_workers,
+        |low, high| chunk_stats(low, high, moduli),
+        |stats| total.merge(&stats),
+    );
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_limit() {
+        let stats = compute(100, &[3, 4], 2);
+        assert_eq!(stats.count, 25);
+        assert_eq!(stats.sum, 1060);
+        assert!((stats.reciprocal_sum - 1.802_817_201_048_870_6).abs() < 1e-12);
+        assert_eq!(stats.residues[0].counts, [1, 11, 13]);
+        assert_eq!(stats.residues[1].counts, [0, 11, 1, 13]);
+        assert_eq!(stats.residues[1].coprime_classes(), [(1, 11), (3, 13)]);
+    }
+
+    #[test]
+    fn test_chunks_merge_to_the_same_totals() {
+        let single = compute(3_000_000, &[10], 1);
+        let split = compute(3_000_000, &[10], 4);
+        assert_eq!(single.count, 216_816);
+        assert_eq!(split.count, single.count);
+        assert_eq!(split.sum, single.sum);
+        assert_eq!(split.residues, single.residues);
+        assert!((split.reciprocal_sum - single.reciprocal_sum).abs() < 1e-12);
+    }
+}
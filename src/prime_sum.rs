@@ -0,0 +1,170 @@
+// Exact sums of the primes in a range (`nt prime-sum`)
+//
+// Σ p passes u64 near x = 3·10^10, so the totals are rug Integers fed from u128 partial
+// sums. Chunks of [from, to] are streamed on worker threads as in prime_stats.
+
+use rug::Integer;
+
+use crate::parallel::{chunk_size, parallel_chunks};
+
+// Smallest chunk worth its own base-prime sieve
+const MIN_CHUNK: usize = 1 << 20;
+
+/// Count and exact sums of the primes in a range
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrimeSum {
+    pub count: u64,
+    pub sum: Integer,
+    /// Σ p², when asked for
+    pub sum_of_squares: Option<Integer>,
+}
+
+impl PrimeSum {
+    fn empty(squares: bool) -> Self {
+        Self {
+            count: 0,
+            sum: Integer::new(),
+            sum_of_squares: squares.then(Integer::new),
+        }
+    }
+
+    fn merge(&mut self, other: PrimeSum) {
+        self.count += other.count;
+        self.sum += other.sum;
+        if let (Some(mine), Some(theirs)) = (&mut self.sum_of_squares, other.sum_of_squares) {
+            *mine += theirs;
+        }
+    }
+}
+
+/// A sum kept in a u128 until it would overflow
+#[derive(Default)]
+struct Accumulator {
+    total: Integer,
+    pending: u128,
+}
+
+impl Accumulator {
+    fn add(&mut self, term: u128) {
+        match self.pending.checked_add(term) {
+            Some(sum) => self.pending = sum,
+            None => {
+                self.total += self.pending;
+                self.pending = term;
+            }
+        }
+    }
+
+    fn finish(self) -> Integer {
+        self.total + self.pending
+    }
+}
+
+/// Sums for the primes in [low, high] on the calling thread
+fn chunk_sum(low: usize, high: usize, squares: bool) -> PrimeSum {
+    let mut count = 0;
+    let mut sum = Accumulator::default();
+    let mut sum_of_squares = Accumulator::default();
+    crate::primes::for_each_prime_in_range(low, high, |segment| {
+        count += segment.len() as u64;
+        for &p in segment {
+            sum.add(p as u128);
+            if squares {
+                sum_of_squares.add(p as u128 * p as u128);
+            }
+        }
+    });
+    PrimeSum {
+        count,
+        sum: sum.finish(),
+        sum_of_squares: squares.then(|| sum_of_squares.finish()),
+    }
+}
+
+/// Count and sum (and with `squares` the sum of squares) of the primes in [from, to]
+pub fn compute(from: usize, to: usize, squares: bool, num_workers: usize) -> PrimeSum {
+    let mut total = PrimeSum::empty(squares);
+    parallel_chunks(
+        from..=to,
+        chunk_size(to.saturating_sub(from), num_workers, MIN_CHUNK),
+        num_workers,
+        |low, high| chunk_sum(low, high, squares),
+        |sum| total.merge(sum),
+    );
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sums() {
+        let small = compute(0, 100, true, 2);
+        assert_eq!(small.count, 25);
+        assert_eq!(small.sum, 1060);
+        assert_eq!(small.sum_of_squares, Some(Integer::from(65_796)));
+        assert_eq!(compute(0, 100, false, 1).sum_of_squares, None);
+
+        let range = compute(1_000_000, 2_000_000, true, 4);
+        assert_eq!(range.count, 70_435);
+        assert_eq!(range.sum, 105_363_426_899_u64);
+        assert_eq!(range.sum_of_squares.unwrap(), 163_495_178_681_722_651_u64);
+    }
+
+    #[test]
+    fn test_from_above_to_is_empty() {
+        assert_eq!(
+            compute(2_000_000, 1_000_000, true, 4),
+            PrimeSum::empty(true)
+        );
+        assert_eq!(compute(101, 100, false, 1), PrimeSum::empty(false));
+        assert_eq!(compute(97, 97, false, 1).sum, 97);
+    }
+
+    #[test]
+    fn test_ranges_across_segment_and_chunk_boundaries() {
+        // Several sieve segments, split into more than one chunk on 3 workers
+        let segment = crate::primes::SEGMENT_SIZE_NUMBERS;
+        let (from, to) = (segment - 101, MIN_CHUNK * 2 + segment + 7);
+        let primes: Vec<usize> = crate::primes::sieve(to)
+            .into_iter()
+            .filter(|&p| p >= from)
+            .collect();
+        let found = compute(from, to, true, 3);
+        assert_eq!(found.count, primes.len() as u64);
+        assert_eq!(found.sum, primes.iter().map(|&p| p as u64).sum::<u64>());
+        assert_eq!(
+            found.sum_of_squares.unwrap(),
+            primes.iter().map(|&p| p as u128 * p as u128).sum::<u128>()
+        );
+    }
+
+    #[test]
+    fn test_sum_of_squares_above_u64() {
+        // Every p² here is past u64::MAX ≈ 1.8·10^19
+        let (from, to): (usize, usize) = (5_000_000_000, 5_000_002_000);
+        let primes: Vec<u64> = (from as u64..=to as u64)
+            .filter(|&n| crate::factor::is_prime(n))
+            .collect();
+        let found = compute(from, to, true, 2);
+        let expected: u128 = primes.iter().map(|&p| p as u128 * p as u128).sum();
+        assert!(expected > u64::MAX as u128);
+        assert_eq!(found.count, primes.len() as u64);
+        assert_eq!(found.sum_of_squares.unwrap(), expected);
+    }
+
+    #[test]
+    fn test_accumulator_carries_past_u128() {
+        let mut sum = Accumulator::default();
+        sum.add(u128::MAX);
+        sum.add(u128::MAX);
+        sum.add(5);
+        assert_eq!(
+            sum.finish(),
+            "680564733841876926926749214863536422915"
+                .parse::<Integer>()
+                .unwrap()
+        );
+    }
+}
@@ -1,12 +1,23 @@
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::mpsc::{Sender, SyncSender};
 use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::mpsc::{Sender, SyncSender};
 use std::thread;
+use tracing::{debug, warn};
+
+use crate::affinity::CorePlan;
+use crate::backpressure::Backpressure;
+use crate::buffer_pool::BufferPool;
+use crate::huge_pages;
+use crate::segment_format::SegmentBuilder;
+use crate::segments::{Pipeline, SegmentPlan};
 
 // Segment size constants for variation 5+ (segmented sieve)
 pub const SEGMENT_SIZE_BITS: usize = 32 * 1024 * 8; // 32KB in bits = 262,144 odd numbers
 pub const SEGMENT_SIZE_NUMBERS: usize = SEGMENT_SIZE_BITS * 2; // 524,288 actual numbers
 
+// Initial capacity for a fresh segment prime buffer (~1 in 8 odd numbers is prime near 10^6)
+pub const SEGMENT_PRIMES_CAPACITY: usize = SEGMENT_SIZE_BITS / 8;
+
 /// Raw segment data for variation 7 (consumer-side unpacking)
 #[derive(Clone)]
 pub struct SegmentData {
@@ -22,6 +33,20 @@ pub struct SegmentPrimes {
     pub segment_id: usize, // For ordering in parallel processing
 }
 
+/// Upper bound on the number of primes <= limit (Rosser & Schoenfeld)
+/// π(x) < 1.25506 x / ln x for x > 1; used to size output files without sieving
+pub fn estimate_prime_count_upper(limit: usize) -> usize {
+    if limit < 2 {
+        return 0;
+    }
+    if limit < 17 {
+        return 6; // Bound needs x >= 17 to dominate small cases; π(16) = 6
+    }
+
+    let x = limit as f64;
+    (1.25506 * x / x.ln()).ceil() as usize
+}
+
 pub fn find_primes_streaming(limit: usize, variation: u32, sender: Sender<usize>) {
     match variation {
         1 => find_primes_v1_streaming(limit, sender),
@@ -29,8 +54,9 @@ pub fn find_primes_streaming(limit: usize, variation: u32, sender: Sender<usize>
         3 => find_primes_v3_streaming(limit, sender),
         4 => find_primes_v4_streaming(limit, sender),
         5 => find_primes_v5_streaming(limit, sender),
+        10 => find_primes_v10_streaming(limit, sender),
         _ => {
-            eprintln!("Unknown variation {}, using variation 1", variation);
+            warn!("Unknown variation {}, using variation 1", variation);
             find_primes_v1_streaming(limit, sender)
         }
     }
@@ -49,7 +75,7 @@ fn find_primes_v1_streaming(limit: usize, sender: Sender<usize>) {
         return;
     }
 
-    let mut is_prime = vec![true; limit + 1];
+    let mut is_prime = huge_pages::filled_vec(true, limit + 1);
     is_prime[0] = false;
     is_prime[1] = false;
 
@@ -96,7 +122,7 @@ fn find_primes_v2_streaming(limit: usize, sender: Sender<usize>) {
 
     // Array size is half since we only track odd numbers
     let size = (limit - 1) / 2;
-    let mut is_prime = vec![true; size];
+    let mut is_prime = huge_pages::filled_vec(true, size);
 
     let sqrt_limit = ((limit as f64).sqrt() as usize - 1) / 2;
 
@@ -146,7 +172,7 @@ fn find_primes_v3_streaming(limit: usize, sender: Sender<usize>) {
 
     // Array size is half since we only track odd numbers
     let size = (limit - 1) / 2;
-    let mut is_prime = vec![true; size];
+    let mut is_prime = huge_pages::filled_vec(true, size);
 
     let sqrt_limit = ((limit as f64).sqrt() as usize - 1) / 2;
 
@@ -215,7 +241,7 @@ fn find_primes_v4_streaming(limit: usize, sender: Sender<usize>) {
     // Index i represents number (2*i + 3)
     let odd_count = (limit - 1) / 2;
     let size = (odd_count + 63) / 64; // Number of u64 words needed
-    let mut is_prime = vec![!0_u64; size]; // All bits set to 1 (true)
+    let mut is_prime = huge_pages::filled_vec(!0_u64, size); // All bits set to 1 (true)
 
     // Helper: Get bit at position idx
     #[inline]
@@ -297,7 +323,7 @@ fn find_primes_v5_streaming(limit: usize, sender: Sender<usize>) {
         }
     }
 
-    // Step 2: Process segments (limit is already rounded to segment boundary)
+    // Step 2: Process segments (the last one is clamped to limit)
 
     // Helper function for bit operations
     #[inline]
@@ -318,8 +344,8 @@ fn find_primes_v5_streaming(limit: usize, sender: Sender<usize>) {
     let mut segment = vec![0_u64; segment_words];
 
     while low <= limit {
-        // Each segment is exactly SEGMENT_SIZE_NUMBERS (aligned boundary)
-        let high = low + SEGMENT_SIZE_NUMBERS - 1;
+        // Each segment is SEGMENT_SIZE_NUMBERS wide; the last one stops at limit
+        let high = (low + SEGMENT_SIZE_NUMBERS - 1).min(limit);
 
         // Reinitialize entire segment (all bits to 1 = prime)
         segment.fill(!0_u64);
@@ -350,7 +376,7 @@ fn find_primes_v5_streaming(limit: usize, sender: Sender<usize>) {
 
                 let num = low + idx * 2;
 
-                if num < limit {
+                if num <= high {
                     if sender.send(num).is_err() {
                         return; // Receiver dropped, stop sending
                     }
@@ -360,11 +386,60 @@ fn find_primes_v5_streaming(limit: usize, sender: Sender<usize>) {
             }
         }
 
+        crate::progress::inc(1);
+        crate::watchdog::wait_if_paused();
+
         // Move to next segment
-        low = high + 2; // Next odd number
+        low += SEGMENT_SIZE_NUMBERS; // Next odd number after this segment
+    }
+}
+
+/// Variation 10: Sieve of Sundaram
+///
+/// Included for comparison with Eratosthenes rather than speed.
+/// - Crosses out every m = i + j + 2ij (1 <= i <= j); each remaining m gives the prime 2m + 1
+/// - Time complexity: O(n log n) - strikes composites once per factor pair, not per prime
+/// - Space complexity: O(n/2) - one byte per odd number
+/// - Index mapping: is_prime[m] represents the number (2*m + 1)
+///
+/// Variation 10 with streaming: sends primes once the sieve is done
+fn find_primes_v10_streaming(limit: usize, sender: Sender<usize>) {
+    if limit < 2 {
+        return;
+    }
+    if sender.send(2).is_err() {
+        return;
+    }
+
+    let is_prime = sundaram(limit);
+
+    for (m, &is_p) in is_prime.iter().enumerate().skip(1) {
+        if is_p && sender.send(2 * m + 1).is_err() {
+            break; // Receiver dropped, stop sending
+        }
     }
 }
 
+/// The Sundaram sieve for variation 10: is_prime[m] for the odd number 2m + 1 <= limit
+/// Needs limit >= 2
+fn sundaram(limit: usize) -> Vec<bool> {
+    // Odd numbers up to limit are 2m + 1 for m in 1..=k
+    let k = (limit - 1) / 2;
+    let mut is_prime = huge_pages::filled_vec(true, k + 1);
+
+    // The first m crossed out for i is i + i + 2i² = 2i(i + 1); later ones step by 2i + 1
+    let mut i = 1;
+    while 2 * i * (i + 1) <= k {
+        let mut m = 2 * i * (i + 1);
+        while m <= k {
+            is_prime[m] = false;
+            m += 2 * i + 1;
+        }
+        i += 1;
+    }
+    is_prime
+}
+
 /// Variation 6: Segmented Sieve with Batched Streaming
 ///
 /// Sends entire segments as Vec<usize> for reduced channel overhead.
@@ -373,7 +448,12 @@ fn find_primes_v5_streaming(limit: usize, sender: Sender<usize>) {
 /// - Sends one Vec per segment (massive reduction in channel overhead)
 /// - Best for very large limits (billions+) with parallelization potential
 /// - Segment size: 32KB (fits in L1 cache)
-pub fn find_primes_v6_streaming(limit: usize, sqrt_limit: usize, sender: Sender<Vec<usize>>) {
+pub fn find_primes_v6_streaming(
+    limit: usize,
+    sqrt_limit: usize,
+    sender: Sender<Vec<usize>>,
+    pool: BufferPool<usize>,
+) {
     if limit < 2 {
         return;
     }
@@ -390,7 +470,7 @@ pub fn find_primes_v6_streaming(limit: usize, sqrt_limit: usize, sender: Sender<
         return; // Receiver dropped
     }
 
-    // Step 2: Process segments (limit is already rounded to segment boundary)
+    // Step 2: Process segments (the last one is clamped to limit)
 
     // Helper function for bit operations
     #[inline]
@@ -411,8 +491,8 @@ pub fn find_primes_v6_streaming(limit: usize, sqrt_limit: usize, sender: Sender<
     let mut segment = vec![0_u64; segment_words];
 
     while low <= limit {
-        // Each segment is exactly SEGMENT_SIZE_NUMBERS (aligned boundary)
-        let high = low + SEGMENT_SIZE_NUMBERS - 1;
+        // Each segment is SEGMENT_SIZE_NUMBERS wide; the last one stops at limit
+        let high = (low + SEGMENT_SIZE_NUMBERS - 1).min(limit);
 
         // Reinitialize entire segment (all bits to 1 = prime)
         segment.fill(!0_u64);
@@ -433,8 +513,8 @@ pub fn find_primes_v6_streaming(limit: usize, sqrt_limit: usize, sender: Sender<
             }
         }
 
-        // Step 4: Collect primes from this segment into a Vec
-        let mut segment_primes = Vec::new();
+        // Step 4: Collect primes from this segment into a recycled Vec
+        let mut segment_primes = pool.take(SEGMENT_PRIMES_CAPACITY);
         for word_idx in 0..segment_words {
             let mut word = segment[word_idx];
 
@@ -443,7 +523,7 @@ pub fn find_primes_v6_streaming(limit: usize, sqrt_limit: usize, sender: Sender<
                 let idx = word_idx * 64 + bit_idx;
 
                 let num = low + idx * 2;
-                if num < limit {
+                if num <= high {
                     segment_primes.push(num);
                 }
 
@@ -456,8 +536,11 @@ pub fn find_primes_v6_streaming(limit: usize, sqrt_limit: usize, sender: Sender<
             return; // Receiver dropped, stop sending
         }
 
+        crate::progress::inc(1);
+        crate::watchdog::wait_if_paused();
+
         // Move to next segment
-        low = high + 2; // Next odd number
+        low += SEGMENT_SIZE_NUMBERS; // Next odd number after this segment
     }
 }
 
@@ -470,7 +553,12 @@ pub fn find_primes_v6_streaming(limit: usize, sqrt_limit: usize, sender: Sender<
 /// - ~10% faster producer than v6 (no unpacking overhead)
 /// - Best for very large limits with parallel consumers
 /// - Segment size: 32KB (fits in L1 cache)
-pub fn find_primes_v7_streaming(limit: usize, sqrt_limit: usize, sender: Sender<SegmentData>) {
+pub fn find_primes_v7_streaming(
+    limit: usize,
+    sqrt_limit: usize,
+    sender: Sender<SegmentData>,
+    pool: BufferPool<u64>,
+) {
     // Step 1: Find small primes up to sqrt_limit using v2 (odd-only)
     let small_primes = find_primes_v2(sqrt_limit);
 
@@ -509,8 +597,8 @@ pub fn find_primes_v7_streaming(limit: usize, sqrt_limit: usize, sender: Sender<
     let mut segment = vec![0_u64; segment_words];
 
     while low <= limit {
-        // Each segment is exactly SEGMENT_SIZE_NUMBERS (aligned boundary)
-        let high = low + SEGMENT_SIZE_NUMBERS - 1;
+        // Each segment is SEGMENT_SIZE_NUMBERS wide; the last one stops at limit
+        let high = (low + SEGMENT_SIZE_NUMBERS - 1).min(limit);
 
         // Reinitialize entire segment (all bits to 1 = prime)
         segment.fill(!0_u64);
@@ -531,20 +619,18 @@ pub fn find_primes_v7_streaming(limit: usize, sqrt_limit: usize, sender: Sender<
             }
         }
 
-        // Step 4: Send raw segment (no unpacking!)
-        if sender
-            .send(SegmentData {
-                bits: segment.clone(),
-                low,
-                high,
-            })
-            .is_err()
-        {
+        // Step 4: Send raw segment (no unpacking!) in a recycled buffer
+        let mut bits = pool.take(segment_words);
+        bits.extend_from_slice(&segment);
+        if sender.send(SegmentData { bits, low, high }).is_err() {
             return; // Receiver dropped, stop sending
         }
 
+        crate::progress::inc(1);
+        crate::watchdog::wait_if_paused();
+
         // Move to next segment
-        low = high + 2; // Next odd number
+        low += SEGMENT_SIZE_NUMBERS; // Next odd number after this segment
     }
 }
 
@@ -602,11 +688,13 @@ fn pack_primes_to_bits(primes: &[usize]) -> Vec<u64> {
 /// - Best for very large limits on multi-core systems
 /// - Segment size: 32KB (fits in L1 cache per core)
 /// - Scales linearly with CPU cores
-pub fn find_primes_v8_parallel(
+pub fn find_primes_v8_parallel<B: SegmentBuilder>(
     limit: usize,
     sqrt_limit: usize,
-    sender: Sender<SegmentPrimes>,
+    sender: Sender<B::Output>,
     num_workers: usize,
+    builder: &B,
+    pinning: Option<&CorePlan>,
 ) {
     if limit < 2 {
         return;
@@ -617,10 +705,7 @@ pub fn find_primes_v8_parallel(
 
     // Send small primes as first segment (already unpacked)
     if sender
-        .send(SegmentPrimes {
-            primes: (*small_primes).clone(),
-            segment_id: 0,
-        })
+        .send(builder.build_from_primes(&small_primes, 0))
         .is_err()
     {
         return; // Receiver dropped
@@ -657,6 +742,11 @@ pub fn find_primes_v8_parallel(
                     bits[word_idx] &= !(1_u64 << bit_idx);
                 }
 
+                // Pin before allocating so the segment buffer is first touched on this core's node
+                if let Some(pinning) = pinning {
+                    pinning.pin_worker(worker_id);
+                }
+
                 // Allocate segment buffer for this worker
                 let mut segment = vec![0_u64; segment_words];
 
@@ -684,34 +774,15 @@ pub fn find_primes_v8_parallel(
                         }
                     }
 
-                    // Unpack segment into Vec<usize> (producer-side unpacking like v6)
-                    let mut segment_primes = Vec::new();
-                    for word_idx in 0..segment_words {
-                        let mut word = segment[word_idx];
-
-                        while word != 0 {
-                            let bit_idx = word.trailing_zeros() as usize;
-                            let idx = word_idx * 64 + bit_idx;
-
-                            let num = seg_low + idx * 2;
-                            if num <= seg_high {
-                                segment_primes.push(num);
-                            }
-
-                            word &= word - 1; // Clear lowest set bit
-                        }
-                    }
-
-                    // Send unpacked primes with proper ID (segment_idx + 1, since 0 is small primes)
-                    if sender
-                        .send(SegmentPrimes {
-                            primes: segment_primes,
-                            segment_id: segment_idx + 1,
-                        })
-                        .is_err()
-                    {
+                    // Unpack (or preformat) segment on the producer side like v6
+                    // Sent with proper ID (segment_idx + 1, since 0 is small primes)
+                    let output =
+                        builder.build_from_bits(&segment, seg_low, seg_high, segment_idx + 1);
+                    if sender.send(output).is_err() {
                         return; // Receiver dropped, stop this worker
                     }
+                    crate::progress::inc(1);
+                    crate::watchdog::wait_if_paused();
                 }
             });
         }
@@ -723,12 +794,18 @@ pub fn find_primes_v8_parallel(
 /// - Parallel workers compute segments
 /// - Segments distributed round-robin to N consumers
 /// - Each consumer writes to primes_{id}.bin
-pub fn find_primes_v9_multi_consumers(
+/// - `deterministic` assigns segments to workers statically (see segments.rs)
+#[allow(clippy::too_many_arguments)]
+pub fn find_primes_v9_multi_consumers<B: SegmentBuilder>(
     limit: usize,
     sqrt_limit: usize,
-    senders: Vec<SyncSender<SegmentPrimes>>,
+    senders: Vec<SyncSender<B::Output>>,
     num_workers: usize,
     total_sent: Arc<AtomicUsize>,
+    builder: &B,
+    backpressure: Option<&Backpressure>,
+    pinning: Option<&CorePlan>,
+    deterministic: bool,
 ) -> Vec<usize> {
     if limit < 2 {
         return vec![];
@@ -743,130 +820,140 @@ pub fn find_primes_v9_multi_consumers(
     let small_primes = Arc::new(find_primes_v2(sqrt_limit));
 
     // Step 2: Calculate segment ranges
-    let mut low = (sqrt_limit + 1) | 1; // Make odd
-    if low % 2 == 0 {
-        low += 1;
-    }
-
-    // Calculate total number of segments
-    let total_range = if limit >= low {
-        limit - low + 1
-    } else {
+    let low = (sqrt_limit + 1) | 1; // Make odd
+    let plan = SegmentPlan::new(low, limit, SEGMENT_SIZE_NUMBERS);
+    if plan.total_segments() == 0 {
         return vec![];
-    };
-    let total_segments = (total_range + SEGMENT_SIZE_NUMBERS - 1) / SEGMENT_SIZE_NUMBERS;
+    }
 
-    // Step 3: Spawn worker threads with atomic work queue
+    // Step 3: Run workers through the shared segment pipeline (atomic work queue)
     let segment_words = (SEGMENT_SIZE_BITS + 63) / 64;
-    let next_segment = Arc::new(AtomicUsize::new(0));
 
     // Memory monitoring: Log worker segment buffer allocations
     let segment_buffer_bytes = segment_words * std::mem::size_of::<u64>();
     let segment_buffer_kb = segment_buffer_bytes as f64 / 1024.0;
     let total_worker_buffers_mb = (segment_buffer_bytes * num_workers) as f64 / (1024.0 * 1024.0);
-    eprintln!(
+    debug!(
         "Worker buffers: {:.2} KB per worker × {} workers = {:.2} MB total",
         segment_buffer_kb, num_workers, total_worker_buffers_mb
     );
 
-    thread::scope(|scope| {
-        for _worker_id in 0..num_workers {
-            let senders = senders.clone();
-            let small_primes = Arc::clone(&small_primes);
-            let next_segment = Arc::clone(&next_segment);
-            let total_sent = Arc::clone(&total_sent);
-
-            scope.spawn(move || {
-                // Helper function for bit operations
-                #[inline]
-                fn clear_bit(bits: &mut [u64], idx: usize) {
-                    let word_idx = idx / 64;
-                    let bit_idx = idx % 64;
-                    bits[word_idx] &= !(1_u64 << bit_idx);
-                }
-
-                // Allocate segment buffer for this worker
-                let mut segment = vec![0_u64; segment_words];
-
-                // Workers pull segments sequentially from atomic counter
-                loop {
-                    let segment_idx = next_segment.fetch_add(1, Ordering::Relaxed);
-                    if segment_idx >= total_segments {
-                        break;
-                    }
-                    let seg_low = low + segment_idx * SEGMENT_SIZE_NUMBERS;
-                    let seg_high = (seg_low + SEGMENT_SIZE_NUMBERS - 1).min(limit);
-
-                    // Reinitialize segment (all bits to 1 = prime)
-                    segment.fill(!0_u64);
+    let pipeline = Pipeline {
+        num_workers,
+        total_sent,
+        backpressure,
+        pinning,
+        deterministic,
+    };
+    pipeline.run(
+        &plan,
+        senders,
+        B::byte_len,
+        // Allocate segment buffer for this worker
+        || vec![0_u64; segment_words],
+        |segment, seg_low, seg_high, segment_id| {
+            // Reinitialize segment (all bits to 1 = prime) and mark composites
+            sieve_segment(&small_primes, seg_low, seg_high, segment);
+
+            // Unpack (or preformat) segment into a recycled buffer
+            builder.build_from_bits(segment, seg_low, seg_high, segment_id)
+        },
+    );
 
-                    // Mark composites using small primes
-                    for &p in small_primes.iter().skip(1) {
-                        // Find first odd multiple of p in [seg_low, seg_high]
-                        let mut start = ((seg_low + p - 1) / p) * p;
-                        if start % 2 == 0 {
-                            start += p; // Make it odd
-                        }
+    // Clone from Arc to return (Arc will be dropped when thread::scope ends)
+    (*small_primes).clone()
+}
 
-                        // Mark multiples as composite
-                        while start <= seg_high {
-                            let idx = (start - seg_low) / 2;
-                            clear_bit(&mut segment, idx);
-                            start += p * 2; // Skip to next odd multiple
-                        }
-                    }
+/// Sieve the odd numbers in [seg_low, seg_high] into `segment` (bit i = seg_low + 2i)
+/// Used by variation 9 workers and by remote workers in distributed mode
+/// `small_primes` must hold every prime <= sqrt(seg_high), starting with 2
+pub fn sieve_segment(small_primes: &[usize], seg_low: usize, seg_high: usize, segment: &mut [u64]) {
+    // Helper function for bit operations
+    #[inline]
+    fn clear_bit(bits: &mut [u64], idx: usize) {
+        let word_idx = idx / 64;
+        let bit_idx = idx % 64;
+        bits[word_idx] &= !(1_u64 << bit_idx);
+    }
 
-                    // Unpack segment into Vec<usize>
-                    let mut segment_primes = Vec::new();
-                    for word_idx in 0..segment_words {
-                        let mut word = segment[word_idx];
+    // Reinitialize segment (all bits to 1 = prime)
+    segment.fill(!0_u64);
 
-                        while word != 0 {
-                            let bit_idx = word.trailing_zeros() as usize;
-                            let idx = word_idx * 64 + bit_idx;
+    // Mark composites using small primes
+    for &p in small_primes.iter().skip(1) {
+        // First odd multiple of p in [seg_low, seg_high], and never p itself: smaller
+        // multiples than p² have a smaller factor, so windows below sqrt(high) stay correct
+        let mut start = (seg_low.div_ceil(p) * p).max(p * p);
+        if start.is_multiple_of(2) {
+            start += p; // Make it odd
+        }
 
-                            let num = seg_low + idx * 2;
-                            if num <= seg_high {
-                                segment_primes.push(num);
-                            }
+        // Mark multiples as composite
+        while start <= seg_high {
+            let idx = (start - seg_low) / 2;
+            clear_bit(segment, idx);
+            start += p * 2; // Skip to next odd multiple
+        }
+    }
+}
 
-                            word &= word - 1; // Clear lowest set bit
-                        }
-                    }
+/// All primes <= limit in increasing order
+/// Library entry point; uses the bit-packed odd-only sieve (variation 4)
+pub fn sieve(limit: usize) -> Vec<usize> {
+    find_primes_v4(limit)
+}
 
-                    // Segment numbering starts at 1 (0 is reserved for small primes)
-                    let segment_id = segment_idx + 1;
-                    let segment_data = SegmentPrimes {
-                        primes: segment_primes,
-                        segment_id,
-                    };
-
-                    // Route to consumer based on segment_id: segment S → consumer ((S-1) % N)
-                    let consumer_idx = ((segment_id - 1) % num_consumers) as usize;
-                    if senders[consumer_idx].send(segment_data).is_err() {
-                        break; // Receiver dropped, stop this worker
-                    }
+/// Every prime <= bound, read from stored `nt primes` output when it reaches that far
+/// Base primes for sieving windows and factoring stages
+pub fn base_primes(bound: usize) -> Vec<usize> {
+    match crate::storage::stored_primes_up_to(bound) {
+        Some(primes) => {
+            debug!("Using {} stored primes up to {}", primes.len(), bound);
+            primes
+        }
+        None => sieve(bound),
+    }
+}
 
-                    // Increment send counter
-                    total_sent.fetch_add(1, Ordering::Relaxed);
-
-                    // Periodic memory reporting (every 1000 segments)
-                    if segment_idx % 1000 == 0 {
-                        if let Some((rss_mb, vm_mb)) = crate::storage::get_process_memory_mb() {
-                            let sent = total_sent.load(Ordering::Relaxed);
-                            eprintln!(
-                                "[Producer] Segment {} | Sent: {} | Process memory: RSS={:.2} MB, VM={:.2} MB",
-                                segment_idx, sent, rss_mb, vm_mb
-                            );
-                        }
-                    }
-                }
-            });
+/// Call `emit` with the primes in [a, b] in increasing order, one segment at a time
+/// Only the base primes up to sqrt(b) are sieved in full, so a window at 10^15 costs its
+/// width plus ~sqrt(b), not b
+pub fn for_each_prime_in_range(a: usize, b: usize, mut emit: impl FnMut(&[usize])) {
+    if b < a.max(2) {
+        return;
+    }
+    let base = base_primes(b.isqrt());
+    if a <= 2 {
+        emit(&[2]);
+    }
+
+    // Odd numbers only, from the first odd number >= max(a, 3)
+    let mut seg_low = a.max(3) | 1;
+    let mut segment = vec![0_u64; SEGMENT_SIZE_BITS / 64];
+    let mut primes = Vec::with_capacity(SEGMENT_PRIMES_CAPACITY);
+    while seg_low <= b {
+        let seg_high = seg_low.saturating_add(SEGMENT_SIZE_NUMBERS - 2).min(b);
+        let words = ((seg_high - seg_low) / 2 + 1).div_ceil(64);
+        sieve_segment(&base, seg_low, seg_high, &mut segment[..words]);
+
+        primes.clear();
+        crate::segment_format::for_each_prime(&segment[..words], seg_low, seg_high, |p| {
+            primes.push(p)
+        });
+        emit(&primes);
+
+        match seg_high.checked_add(2) {
+            Some(next) if seg_high < b => seg_low = next,
+            _ => break,
         }
-    });
+    }
+}
 
-    // Clone from Arc to return (Arc will be dropped when thread::scope ends)
-    (*small_primes).clone()
+/// All primes in [a, b]; see `for_each_prime_in_range` to stream large windows
+pub fn sieve_range(a: usize, b: usize) -> Vec<usize> {
+    let mut primes = Vec::new();
+    for_each_prime_in_range(a, b, |segment| primes.extend_from_slice(segment));
+    primes
 }
 
 pub fn find_primes(limit: usize, variation: u32) -> Vec<usize> {
@@ -876,8 +963,9 @@ pub fn find_primes(limit: usize, variation: u32) -> Vec<usize> {
         3 => find_primes_v3(limit),
         4 => find_primes_v4(limit),
         5 => find_primes_v5(limit),
+        10 => find_primes_v10(limit),
         _ => {
-            eprintln!("Unknown variation {}, using variation 1", variation);
+            warn!("Unknown variation {}, using variation 1", variation);
             find_primes_v1(limit)
         }
     }
@@ -895,7 +983,7 @@ fn find_primes_v1(limit: usize) -> Vec<usize> {
         return vec![];
     }
 
-    let mut is_prime = vec![true; limit + 1];
+    let mut is_prime = huge_pages::filled_vec(true, limit + 1);
     is_prime[0] = false;
     is_prime[1] = false;
 
@@ -937,7 +1025,7 @@ fn find_primes_v2(limit: usize) -> Vec<usize> {
 
     // Array size is half since we only track odd numbers
     let size = (limit - 1) / 2;
-    let mut is_prime = vec![true; size];
+    let mut is_prime = huge_pages::filled_vec(true, size);
 
     let sqrt_limit = ((limit as f64).sqrt() as usize - 1) / 2;
 
@@ -983,7 +1071,7 @@ fn find_primes_v4(limit: usize) -> Vec<usize> {
     // Index i represents number (2*i + 3)
     let odd_count = (limit - 1) / 2;
     let size = (odd_count + 63) / 64; // Number of u64 words needed
-    let mut is_prime = vec![!0_u64; size]; // All bits set to 1 (true)
+    let mut is_prime = huge_pages::filled_vec(!0_u64, size); // All bits set to 1 (true)
 
     // Helper: Get bit at position idx
     #[inline]
@@ -1061,7 +1149,7 @@ fn find_primes_v5(limit: usize) -> Vec<usize> {
     // Start with all small primes
     let mut all_primes = small_primes.clone();
 
-    // Step 2: Process segments (limit is already rounded to segment boundary)
+    // Step 2: Process segments (the last one is clamped to limit)
 
     // Helper function for bit operations
     #[inline]
@@ -1082,8 +1170,8 @@ fn find_primes_v5(limit: usize) -> Vec<usize> {
     let mut segment = vec![0_u64; segment_words];
 
     while low <= limit {
-        // Each segment is exactly SEGMENT_SIZE_NUMBERS (aligned boundary)
-        let high = low + SEGMENT_SIZE_NUMBERS - 1;
+        // Each segment is SEGMENT_SIZE_NUMBERS wide; the last one stops at limit
+        let high = (low + SEGMENT_SIZE_NUMBERS - 1).min(limit);
 
         // Reinitialize entire segment (all bits to 1 = prime)
         segment.fill(!0_u64);
@@ -1113,19 +1201,46 @@ fn find_primes_v5(limit: usize) -> Vec<usize> {
                 let idx = word_idx * 64 + bit_idx;
 
                 let num = low + idx * 2;
-                all_primes.push(num);
+                if num <= high {
+                    all_primes.push(num);
+                }
 
                 word &= word - 1; // Clear lowest set bit
             }
         }
 
         // Move to next segment
-        low = high + 2; // Next odd number
+        low += SEGMENT_SIZE_NUMBERS; // Next odd number after this segment
     }
 
     all_primes
 }
 
+/// Variation 10: Sieve of Sundaram
+///
+/// Included for comparison with Eratosthenes rather than speed.
+/// - Crosses out every m = i + j + 2ij (1 <= i <= j); each remaining m gives the prime 2m + 1
+/// - Time complexity: O(n log n) - strikes composites once per factor pair, not per prime
+/// - Space complexity: O(n/2) - one byte per odd number
+/// - Index mapping: is_prime[m] represents the number (2*m + 1)
+fn find_primes_v10(limit: usize) -> Vec<usize> {
+    if limit < 2 {
+        return vec![];
+    }
+
+    let is_prime = sundaram(limit);
+
+    let mut primes = vec![2];
+    primes.extend(
+        is_prime
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter_map(|(m, &is_p)| if is_p { Some(2 * m + 1) } else { None }),
+    );
+    primes
+}
+
 /// Variation 3: Bit-packed Sieve using Vec<u64>
 ///
 /// Uses 1 bit per number (8x memory savings vs Vec<bool>)
@@ -1140,7 +1255,7 @@ fn find_primes_v3(limit: usize) -> Vec<usize> {
 
     // Each u64 holds 64 bits
     let size = (limit + 64) / 64;
-    let mut is_prime = vec![!0_u64; size]; // All bits set to 1 (true)
+    let mut is_prime = huge_pages::filled_vec(!0_u64, size); // All bits set to 1 (true)
 
     // Helper: Get bit at position idx
     #[inline]
@@ -1183,3 +1298,81 @@ fn find_primes_v3(limit: usize) -> Vec<usize> {
 
     primes
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sieve_range_matches_full_sieve() {
+        let all = sieve(20_000);
+        for (a, b) in [
+            (0, 1),
+            (0, 2),
+            (2, 3),
+            (3, 100),
+            (90, 97),
+            (10, 20_000),
+            (1_500, 1_700),
+        ] {
+            let expected: Vec<usize> = all
+                .iter()
+                .copied()
+                .filter(|p| (a..=b).contains(p))
+                .collect();
+            assert_eq!(sieve_range(a, b), expected, "[{}, {}]", a, b);
+        }
+        assert!(sieve_range(20, 10).is_empty());
+    }
+
+    fn streamed(limit: usize, variation: u32) -> Vec<usize> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        find_primes_streaming(limit, variation, sender);
+        receiver.into_iter().collect()
+    }
+
+    /// A limit where v5's `segments`-th segment ends exactly on it
+    fn segment_boundary_limit(segments: usize) -> usize {
+        let mut limit = segments * SEGMENT_SIZE_NUMBERS;
+        loop {
+            let low = ((limit as f64).sqrt() as usize + 1) | 1;
+            let boundary = low + segments * SEGMENT_SIZE_NUMBERS - 1;
+            if boundary == limit {
+                return limit;
+            }
+            limit = boundary;
+        }
+    }
+
+    #[test]
+    fn test_v5_matches_v1_at_a_prime_and_a_segment_boundary() {
+        let boundary = segment_boundary_limit(2);
+        for limit in [1_000_003, boundary, boundary + 1, boundary + 2] {
+            let expected = find_primes(limit, 1);
+            assert_eq!(find_primes(limit, 5), expected, "v5 at {}", limit);
+            assert_eq!(streamed(limit, 5), expected, "v5 streaming at {}", limit);
+        }
+        assert_eq!(find_primes(1_000_003, 5).last(), Some(&1_000_003));
+    }
+
+    #[test]
+    fn test_v10_matches_v1() {
+        let boundary = segment_boundary_limit(1);
+        for limit in [0, 1, 2, 3, 4, 5, 1_000, boundary, boundary + 2] {
+            let expected = find_primes(limit, 1);
+            assert_eq!(find_primes(limit, 10), expected, "v10 at {}", limit);
+            assert_eq!(streamed(limit, 10), expected, "v10 streaming at {}", limit);
+        }
+    }
+
+    #[test]
+    fn test_sieve_range_far_window_spans_segments() {
+        let a = 1_000_000_000_000;
+        let b = a + 2 * SEGMENT_SIZE_NUMBERS + 12_345;
+        let primes = sieve_range(a, b);
+        let expected: Vec<usize> = (a..=b)
+            .filter(|&n| crate::factor::is_prime(n as u64))
+            .collect();
+        assert_eq!(primes, expected);
+    }
+}
@@ -1,127 +1,240 @@
+// Primes in every base from 2 to 62 (`nt primes-bases`)
+//
+// Each prime gets a row of its representations, with the palindromic ones highlighted and
+// a count of them at the end. A Report adds the rows up as they are printed: below the
+// table come the number of rows palindromic in each base and that number as a fraction of
+// the rows, so a --pal filter gives the totals of the rows it keeps. Sorting by palindrome
+// count has to hold every row until the end; otherwise rows are printed as they are made.
+// Primes piped in may be larger than usize, such as the 30-digit palindromic primes other
+// subcommands find; those rows are converted with GMP instead.
+
+#[cfg(feature = "native")]
+use std::io::{self, BufRead};
+
+#[cfg(feature = "native")]
+use clap::ValueEnum;
+#[cfg(feature = "native")]
+use rug::Integer;
+#[cfg(feature = "native")]
+use tracing::error;
+
+#[cfg(feature = "native")]
+use crate::radix::{to_base, to_base_big};
+#[cfg(feature = "native")]
 use crate::storage;
 
-pub fn run(pal_only: bool, pal: Option<String>) {
-    match storage::load_all_primes() {
-        Ok(primes) => {
-            // Track palindrome counts for each base (index 0 = base 2, index 60 = base 62)
-            let mut base_palindrome_counts = vec![0; 61];
-
-            // Print header
-            let header = vec![
-                "10", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15",
-                "16", "17", "18", "19", "20", "21", "22", "23", "24", "25", "26", "27", "28", "29",
-                "30", "31", "32", "33", "34", "35", "36", "37", "38", "39", "40", "41", "42", "43",
-                "44", "45", "46", "47", "48", "49", "50", "51", "52", "53", "54", "55", "56", "57",
-                "58", "59", "60", "61", "62", "total",
-            ];
-            println!("{}", header.join("\t"));
-
-            // Print each prime with tab-separated columns
-            for prime in primes {
-                let base_representations: Vec<String> =
-                    (2..=62).map(|base| to_base(prime, base)).collect();
-
-                // Count palindromes (skip base 10 in base_representations to avoid double counting)
-                let mut palindrome_count = 0;
-                if is_palindrome(&prime.to_string()) {
-                    palindrome_count += 1;
-                    base_palindrome_counts[8] += 1; // base 10 is at index 8
-                }
-                for (i, repr) in base_representations.iter().enumerate() {
-                    let base = i + 2; // base_representations[0] is base 2
-                    if base == 10 {
-                        continue; // Skip base 10 to avoid double counting
-                    }
-                    if is_palindrome(repr) {
-                        palindrome_count += 1;
-                        base_palindrome_counts[i] += 1;
-                    }
-                }
-
-                // Filter by specific palindrome value if provided
-                if let Some(ref pal_value) = pal {
-                    let mut found_match = false;
-
-                    // Check base 10
-                    if is_palindrome(&prime.to_string()) && prime.to_string() == *pal_value {
-                        found_match = true;
-                    }
-
-                    // Check other bases
-                    if !found_match {
-                        for repr in &base_representations {
-                            if is_palindrome(repr) && repr == pal_value {
-                                found_match = true;
-                                break;
-                            }
-                        }
-                    }
-
-                    if !found_match {
-                        continue; // Skip this row
-                    }
-                }
-
-                let mut row = vec![prime.to_string()];
-
-                // Add base representations (bases 2-36)
-                for (i, repr) in base_representations.iter().enumerate() {
-                    let base = i + 2;
-                    if base == 10 {
-                        row.push(colorize_duplicate_base10(repr));
-                    } else {
-                        row.push(format_value(repr, pal_only));
-                    }
-                }
-
-                // Add palindrome count
-                row.push(palindrome_count.to_string());
-
-                println!("{}", row.join("\t"));
-            }
+#[cfg(feature = "native")]
+const BASES: std::ops::RangeInclusive<usize> = 2..=62;
+
+/// Order of the table's rows
+#[cfg(feature = "native")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum RowOrder {
+    /// As the primes come
+    #[default]
+    Prime,
+    /// Most palindromes first, ties in prime order
+    Palindromes,
+}
 
-            // Print footer with totals
-            let total_palindromes: usize = base_palindrome_counts.iter().sum();
-            let mut footer = vec![base_palindrome_counts[8].to_string()]; // base 10
+/// A prime written in each base
+#[cfg(feature = "native")]
+pub struct Row {
+    /// The prime in decimal
+    pub prime: String,
+    /// Representations in bases 2 to 62
+    pub digits: Vec<String>,
+}
+
+#[cfg(feature = "native")]
+impl Row {
+    pub fn new(prime: usize) -> Self {
+        Self {
+            prime: prime.to_string(),
+            digits: BASES.map(|base| to_base(prime, base)).collect(),
+        }
+    }
+
+    pub fn big(prime: &Integer) -> Self {
+        Self {
+            prime: prime.to_string(),
+            digits: BASES.map(|base| to_base_big(prime, base as u32)).collect(),
+        }
+    }
 
-            // Add counts for bases 2-24
-            for (_i, &count) in base_palindrome_counts.iter().enumerate() {
-                footer.push(count.to_string());
+    /// The row for a prime written in decimal, through GMP only when it exceeds usize
+    pub fn parse(decimal: &str) -> Option<Self> {
+        if let Ok(prime) = decimal.parse::<usize>() {
+            return Some(Self::new(prime));
+        }
+        let prime = Integer::from_str_radix(decimal, 10).ok()?;
+        (prime > 0).then(|| Self::big(&prime))
+    }
+
+    /// Bases (from 2) the prime is a palindrome in
+    pub fn palindromic_bases(&self) -> impl Iterator<Item = usize> + '_ {
+        BASES
+            .zip(&self.digits)
+            .filter(|(_, repr)| is_palindrome(repr))
+            .map(|(base, _)| base)
+    }
+
+    pub fn palindrome_count(&self) -> usize {
+        self.palindromic_bases().count()
+    }
+
+    /// Whether the prime is the palindrome `value` in some base
+    pub fn has_palindrome(&self, value: &str) -> bool {
+        self.digits
+            .iter()
+            .any(|repr| repr == value && is_palindrome(repr))
+    }
+
+    fn render(&self, pal_only: bool) -> String {
+        let mut row = vec![self.prime.clone()];
+        for (base, repr) in BASES.zip(&self.digits) {
+            if base == 10 {
+                row.push(colorize_duplicate_base10(repr));
+            } else {
+                row.push(format_value(repr, pal_only));
             }
+        }
+        row.push(self.palindrome_count().to_string());
+        row.join("\t")
+    }
+}
 
-            // Add total palindromes
-            footer.push(total_palindromes.to_string());
+/// Palindrome totals over the rows of a table
+#[cfg(feature = "native")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    pub rows: usize,
+    /// Rows palindromic in each base, from base 2
+    pub per_base: Vec<usize>,
+}
 
-            println!("{}", footer.join("\t"));
+#[cfg(feature = "native")]
+impl Default for Report {
+    fn default() -> Self {
+        Self {
+            rows: 0,
+            per_base: vec![0; BASES.count()],
         }
-        Err(e) => eprintln!("Error loading primes.txt: {}", e),
     }
 }
 
-fn to_base(mut num: usize, base: usize) -> String {
-    if num == 0 {
-        return "0".to_string();
+#[cfg(feature = "native")]
+impl Report {
+    pub fn add(&mut self, row: &Row) {
+        self.rows += 1;
+        for base in row.palindromic_bases() {
+            self.per_base[base - 2] += 1;
+        }
     }
 
-    let mut digits = Vec::new();
-    while num > 0 {
-        let digit = num % base;
-        let digit_char = if digit < 10 {
-            (digit as u8 + b'0') as char
-        } else if digit < 36 {
-            (digit as u8 - 10 + b'A') as char
-        } else {
-            // For bases > 36, use lowercase letters (36='a', 37='b', etc.)
-            (digit as u8 - 36 + b'a') as char
-        };
-        digits.push(digit_char);
-        num /= base;
+    /// Palindromes over all rows and bases
+    pub fn total(&self) -> usize {
+        self.per_base.iter().sum()
+    }
+
+    /// Fraction of the rows palindromic in each base, from base 2
+    pub fn densities(&self) -> Vec<f64> {
+        self.per_base
+            .iter()
+            .map(|&count| count as f64 / self.rows.max(1) as f64)
+            .collect()
+    }
+
+    /// The footer lines, in the table's columns
+    fn render(&self) -> [String; 2] {
+        let mut totals = vec!["palindromes".to_string()];
+        totals.extend(self.per_base.iter().map(|count| count.to_string()));
+        totals.push(self.total().to_string());
+
+        let mut densities = vec!["density".to_string()];
+        densities.extend(self.densities().iter().map(|d| format!("{:.4}", d)));
+        densities.push(format!(
+            "{:.4}",
+            self.total() as f64 / self.rows.max(1) as f64
+        ));
+        [totals.join("\t"), densities.join("\t")]
+    }
+}
+
+/// Primes listed one per line in decimal, of any size; blank lines and lines starting
+/// with # are skipped
+#[cfg(feature = "native")]
+pub fn read_primes(reader: impl BufRead) -> io::Result<Vec<String>> {
+    let mut primes = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !line.bytes().all(|b| b.is_ascii_digit()) || line.trim_start_matches('0').is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: '{}' is not a positive number", i + 1, line),
+            ));
+        }
+        primes.push(line.to_string());
     }
-    digits.reverse();
-    digits.iter().collect()
+    Ok(primes)
 }
 
-fn is_palindrome(s: &str) -> bool {
+/// Print the table for the stored primes, or with `stdin` for the primes piped in
+#[cfg(feature = "native")]
+pub fn run(pal_only: bool, pal: Option<String>, stdin: bool, order: RowOrder) {
+    let rows: Box<dyn Iterator<Item = Row>> = if stdin {
+        match read_primes(io::stdin().lock()) {
+            Ok(primes) => Box::new(primes.into_iter().filter_map(|prime| Row::parse(&prime))),
+            Err(e) => {
+                error!("Error reading primes from stdin: {}", e);
+                return;
+            }
+        }
+    } else {
+        match storage::load_all_primes() {
+            Ok(primes) => Box::new(primes.into_iter().map(Row::new)),
+            Err(e) => {
+                error!("Error loading primes: {}", e);
+                return;
+            }
+        }
+    };
+
+    let mut header = vec!["prime".to_string()];
+    header.extend(BASES.map(|base| base.to_string()));
+    header.push("total".to_string());
+    println!("{}", header.join("\t"));
+
+    let mut report = Report::default();
+    let mut held = Vec::new();
+    for row in rows {
+        if let Some(value) = &pal
+            && !row.has_palindrome(value)
+        {
+            continue;
+        }
+        report.add(&row);
+        match order {
+            RowOrder::Prime => println!("{}", row.render(pal_only)),
+            RowOrder::Palindromes => held.push(row),
+        }
+    }
+    held.sort_by_key(|row| std::cmp::Reverse(row.palindrome_count()));
+    for row in &held {
+        println!("{}", row.render(pal_only));
+    }
+
+    for line in report.render() {
+        println!("{}", line);
+    }
+}
+
+/// Whether `s` reads the same backwards; single characters don't count
+pub fn is_palindrome(s: &str) -> bool {
     let chars: Vec<char> = s.chars().collect();
     let len = chars.len();
 
@@ -138,6 +251,7 @@ fn is_palindrome(s: &str) -> bool {
     true
 }
 
+#[cfg(feature = "native")]
 fn colorize_if_palindrome(s: &str) -> String {
     if is_palindrome(s) {
         format!("\x1b[1;93m{}\x1b[0m", s)
@@ -146,11 +260,13 @@ fn colorize_if_palindrome(s: &str) -> String {
     }
 }
 
+#[cfg(feature = "native")]
 fn colorize_duplicate_base10(s: &str) -> String {
     // Color duplicate base 10 in dim gray
     format!("\x1b[90m{}\x1b[0m", s)
 }
 
+#[cfg(feature = "native")]
 fn format_value(s: &str, pal_only: bool) -> String {
     if pal_only {
         if is_palindrome(s) {
@@ -167,6 +283,45 @@ fn format_value(s: &str, pal_only: bool) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_report_counts_each_base_once() {
+        // 5 = 101₂ = 12₃ = 11₄; 7 = 111₂ = 21₃ = 13₄ = 12₅ = 11₆;
+        // 11 = 1011₂ = 102₃ = 23₄ = 21₅ = 15₆ = 14₇ = 13₈ = 12₉ = 11₁₀
+        let mut report = Report::default();
+        for prime in [5, 7, 11] {
+            report.add(&Row::new(prime));
+        }
+        assert_eq!(Row::new(7).palindromic_bases().collect::<Vec<_>>(), [2, 6]);
+        assert_eq!(report.rows, 3);
+        assert_eq!(&report.per_base[..9], &[2, 0, 1, 0, 1, 0, 0, 0, 1]);
+        assert_eq!(report.total(), 5);
+        assert_eq!(report.densities()[0], 2.0 / 3.0);
+
+        assert!(Row::new(5).has_palindrome("101"));
+        assert!(!Row::new(5).has_palindrome("12"));
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_rows_beyond_usize() {
+        for prime in [2, 61, 1_000_003] {
+            let big = Row::big(&Integer::from(prime));
+            assert_eq!(big.digits, Row::new(prime).digits);
+        }
+        // 2^89 − 1 is 89 ones in binary
+        let mersenne = "618970019642690137449562111";
+        let row = Row::parse(mersenne).unwrap();
+        assert_eq!(row.prime, mersenne);
+        assert_eq!(row.digits[0], "1".repeat(89));
+        assert_eq!(row.palindromic_bases().next(), Some(2));
+
+        let input = "# primes\n2\n\n618970019642690137449562111\n";
+        assert_eq!(read_primes(input.as_bytes()).unwrap(), ["2", mersenne]);
+        assert!(read_primes("2\n3.5\n".as_bytes()).is_err());
+        assert!(read_primes("0\n".as_bytes()).is_err());
+    }
+
     #[test]
     fn test_is_palindrome_empty_string() {
         assert!(!is_palindrome(""));
@@ -235,55 +390,4 @@ mod tests {
         assert!(!is_palindrome("1010")); // not a palindrome
         assert!(is_palindrome("121"));  // base-3 palindrome
     }
-
-    #[test]
-    fn test_to_base_basic() {
-        // Base 2 (binary)
-        assert_eq!(to_base(5, 2), "101");
-        assert_eq!(to_base(10, 2), "1010");
-
-        // Base 10 (decimal)
-        assert_eq!(to_base(123, 10), "123");
-
-        // Base 16 (hexadecimal)
-        assert_eq!(to_base(255, 16), "FF");
-        assert_eq!(to_base(16, 16), "10");
-    }
-
-    #[test]
-    fn test_to_base_extended() {
-        // Base 36 (0-9, A-Z)
-        assert_eq!(to_base(35, 36), "Z");
-        assert_eq!(to_base(36, 36), "10");
-
-        // Base 37-62 (using lowercase letters for values 36+)
-        assert_eq!(to_base(36, 37), "a"); // value 36 in base 37 is 'a'
-        assert_eq!(to_base(37, 37), "10");
-
-        // Base 62 (0-9, A-Z, a-z)
-        assert_eq!(to_base(61, 62), "z"); // value 61 in base 62 is 'z'
-        assert_eq!(to_base(62, 62), "10");
-        assert_eq!(to_base(0, 62), "0");
-
-        // Additional base 62 examples
-        assert_eq!(to_base(10, 62), "A");  // value 10 is 'A'
-        assert_eq!(to_base(35, 62), "Z");  // value 35 is 'Z'
-        assert_eq!(to_base(36, 62), "a");  // value 36 is 'a'
-    }
-
-    #[test]
-    fn test_to_base_digit_ranges() {
-        // Verify digit representations
-        // 0-9 use '0'-'9'
-        assert_eq!(to_base(9, 10), "9");
-
-        // 10-35 use 'A'-'Z' for bases > 10
-        assert_eq!(to_base(10, 16), "A");
-        assert_eq!(to_base(15, 16), "F");
-        assert_eq!(to_base(35, 36), "Z");
-
-        // 36-61 use 'a'-'z' for bases > 36
-        assert_eq!(to_base(36, 62), "a");
-        assert_eq!(to_base(61, 62), "z");
-    }
 }
@@ -0,0 +1,77 @@
+// Primorials (`nt primorial`)
+//
+// p_n# is the product of the first n primes and x# the product of the primes <= x. The
+// primes come from primes::base_primes (stored files when they cover the bound, otherwise
+// a sieve) and are multiplied in a product tree. ln(x#) = θ(x) ~ x, so x# has about
+// x / ln 10 digits: 10^8# is ~43 million digits and takes seconds, most of it in the final
+// multiplications and the decimal conversion.
+//
+// With --prime the neighbours p# ± 1 are tested as well. Since p#'s factors are known,
+// the probable primes among them are then proven prime from n ∓ 1 (pocklington.rs).
+
+use rug::Integer;
+
+use crate::product_tree;
+
+/// Upper bound for the nth prime (Rosser: p_n < n (ln n + ln ln n) for n >= 6)
+pub fn nth_prime_bound(n: usize) -> usize {
+    if n < 6 {
+        return 13;
+    }
+    let n = n as f64;
+    (n * (n.ln() + n.ln().ln())).ceil() as usize
+}
+
+/// The primes <= n with `up_to`, otherwise the first n primes
+pub fn primorial_primes(n: usize, up_to: bool) -> Vec<u64> {
+    let bound = if up_to { n } else { nth_prime_bound(n) };
+    let primes = crate::primes::base_primes(bound).into_iter();
+    if up_to {
+        primes.take_while(|&p| p <= n).map(|p| p as u64).collect()
+    } else {
+        primes.take(n).map(|p| p as u64).collect()
+    }
+}
+
+/// Product of the primes <= x
+pub fn primorial(x: usize, workers: usize) -> Integer {
+    product_tree::product(&primorial_primes(x, true), workers)
+}
+
+/// p_n#, the product of the first n primes, and p_n (None for n = 0)
+pub fn nth_primorial(n: usize, workers: usize) -> (Integer, Option<usize>) {
+    let primes = primorial_primes(n, false);
+    let last = primes.last().map(|&p| p as usize);
+    (product_tree::product(&primes, workers), last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_small_primorials() {
+        assert_eq!(primorial(1, 1), 1);
+        assert_eq!(primorial(30, 2), 6_469_693_230u64);
+        assert_eq!(
+            nth_primorial(10, 2),
+            (Integer::from(6_469_693_230u64), Some(29))
+        );
+        assert_eq!(nth_primorial(0, 1), (Integer::from(1), None));
+        // 1000# has 416 digits
+        assert_eq!(primorial(1000, 4).to_string().len(), 416);
+    }
+
+    #[test]
+    fn test_nth_prime_bound() {
+        for (n, p_n) in [
+            (1, 2),
+            (5, 11),
+            (6, 13),
+            (1000, 7919),
+            (1_000_000, 15_485_863),
+        ] {
+            assert!(nth_prime_bound(n) >= p_n, "n {}", n);
+        }
+    }
+}
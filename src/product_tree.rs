@@ -0,0 +1,53 @@
+// Products of many small factors (primorials, the p−1 stage 1 exponent)
+//
+// A running product multiplies a growing big integer by one word at a time, which is
+// quadratic in the size of the result. Pairing the factors up in a balanced tree keeps
+// both operands of each multiplication about the same size, where GMP switches to
+// Toom-Cook and FFT multiplication, so the whole product costs little more than its
+// final multiplication. Leaves multiply a short run of words directly, and the top levels
+// of the tree split across threads; the largest multiplication, at the root, is serial.
+
+use rug::Integer;
+use std::thread;
+
+// Factors multiplied one by one at a leaf
+const LEAF_SIZE: usize = 64;
+
+/// Product of `values`, splitting the top of the tree over up to `workers` threads
+pub fn product(values: &[u64], workers: usize) -> Integer {
+    if values.len() <= LEAF_SIZE {
+        let mut result = Integer::from(1);
+        for &value in values {
+            result *= value;
+        }
+        return result;
+    }
+    let (left, right) = values.split_at(values.len() / 2);
+    if workers > 1 {
+        let (left_workers, right_workers) = (workers / 2, workers - workers / 2);
+        thread::scope(|scope| {
+            let left = scope.spawn(|| product(left, left_workers));
+            let right = product(right, right_workers);
+            left.join().unwrap() * right
+        })
+    } else {
+        product(left, 1) * product(right, 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_running_product() {
+        let values: Vec<u64> = (1..=1000).collect();
+        let mut expected = Integer::from(1);
+        for &v in &values {
+            expected *= v;
+        }
+        assert_eq!(product(&values, 1), expected);
+        assert_eq!(product(&values, 3), expected);
+        assert_eq!(product(&[], 4), 1);
+    }
+}
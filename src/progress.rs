@@ -0,0 +1,43 @@
+// Progress display for long runs (--progress)
+//
+// A single process-wide bar so producers deep in primes.rs and pi.rs can report work
+// without threading a handle through every signature. When no bar was started, `inc`
+// is a cheap no-op.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+static BAR: OnceLock<ProgressBar> = OnceLock::new();
+
+/// Start the progress bar with `total` units of work (e.g. "segments", "terms")
+/// Only the first call in a process has any effect
+pub fn start(total: u64, unit: &str) {
+    let bar = ProgressBar::new(total);
+    let template = format!(
+        "[{{elapsed_precise}}] {{bar:40.cyan/blue}} {{pos}}/{{len}} {} ({{percent}}%, ETA {{eta_precise}})",
+        unit
+    );
+    if let Ok(style) = ProgressStyle::with_template(&template) {
+        bar.set_style(style.progress_chars("=> "));
+    }
+    // Redraw even when no work completes for a while (large segments, slow disks)
+    bar.enable_steady_tick(Duration::from_millis(500));
+
+    let _ = BAR.set(bar);
+}
+
+/// Record `n` completed units of work
+#[inline]
+pub fn inc(n: u64) {
+    if let Some(bar) = BAR.get() {
+        bar.inc(n);
+    }
+}
+
+/// Leave the bar at its final state
+pub fn finish() {
+    if let Some(bar) = BAR.get() {
+        bar.finish();
+    }
+}
@@ -0,0 +1,230 @@
+// Numbers written in bases 2 through 62
+//
+// Digits run 0-9, then A-Z for 10-35, then a-z for 36-61, the alphabet `nt primes-bases`
+// has always printed. Up to base 36 letters are read in either case, as usual for hex;
+// above it case matters, since 'A' is 10 and 'a' is 36. The usize conversion is pure
+// Rust for the wasm build. Big integers (`native`) go through GMP's own conversions up to
+// base 36 and, above that, peel off as many digits per division as fit in a u64.
+
+#[cfg(feature = "native")]
+use rug::Integer;
+
+/// Character for one digit value below 62
+pub fn digit_char(digit: u32) -> char {
+    match digit {
+        0..=9 => (digit as u8 + b'0') as char,
+        10..=35 => (digit as u8 - 10 + b'A') as char,
+        _ => (digit as u8 - 36 + b'a') as char,
+    }
+}
+
+/// Value of the digit `c` in `base`, None if it is not one
+pub fn digit_value(c: char, base: u32) -> Option<u32> {
+    let value = match c {
+        '0'..='9' => c as u32 - '0' as u32,
+        'A'..='Z' => c as u32 - 'A' as u32 + 10,
+        'a'..='z' if base <= 36 => c as u32 - 'a' as u32 + 10,
+        'a'..='z' => c as u32 - 'a' as u32 + 36,
+        _ => return None,
+    };
+    (value < base).then_some(value)
+}
+
+/// `num` written in `base` (2-62): digits 0-9, then A-Z, then a-z
+pub fn to_base(mut num: usize, base: usize) -> String {
+    if num == 0 {
+        return "0".to_string();
+    }
+
+    let mut digits = Vec::new();
+    while num > 0 {
+        digits.push(digit_char((num % base) as u32));
+        num /= base;
+    }
+    digits.reverse();
+    digits.iter().collect()
+}
+
+/// Digit values of `num` in `base` (at least 2), most significant first
+pub fn digits(mut num: u64, base: u64) -> Vec<u64> {
+    let mut digits = vec![num % base];
+    num /= base;
+    while num > 0 {
+        digits.push(num % base);
+        num /= base;
+    }
+    digits.reverse();
+    digits
+}
+
+/// Digits of `base` that fit in one u64 chunk, and base^digits
+#[cfg(feature = "native")]
+fn chunk(base: u32) -> (usize, u64) {
+    let mut digits = 0;
+    let mut power: u64 = 1;
+    while let Some(next) = power.checked_mul(base as u64) {
+        power = next;
+        digits += 1;
+    }
+    (digits, power)
+}
+
+/// Parse a big integer written in `base` (2-62), with an optional leading '-'
+#[cfg(feature = "native")]
+pub fn parse_big(input: &str, base: u32) -> Result<Integer, String> {
+    let text = input.trim().replace('_', "");
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.as_str()),
+    };
+    if digits.is_empty() {
+        return Err(format!("'{}' has no digits", input));
+    }
+    let values: Vec<u32> = digits
+        .chars()
+        .map(|c| digit_value(c, base))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("'{}' is not a base {} number", input, base))?;
+
+    let mut value = if base <= 36 {
+        Integer::from_str_radix(digits, base as i32).unwrap()
+    } else {
+        let (chunk_digits, chunk_power) = chunk(base);
+        let mut value = Integer::new();
+        for piece in values.chunks(chunk_digits) {
+            let piece_value = piece
+                .iter()
+                .fold(0u64, |acc, &d| acc * base as u64 + d as u64);
+            let scale = if piece.len() == chunk_digits {
+                chunk_power
+            } else {
+                (base as u64).pow(piece.len() as u32)
+            };
+            value *= scale;
+            value += piece_value;
+        }
+        value
+    };
+    if negative {
+        value = -value;
+    }
+    Ok(value)
+}
+
+/// `value` written in `base` (2-62) with the same digits as `to_base`
+#[cfg(feature = "native")]
+pub fn to_base_big(value: &Integer, base: u32) -> String {
+    if base <= 36 {
+        return value.to_string_radix(base as i32).to_uppercase();
+    }
+    if *value == 0 {
+        return "0".to_string();
+    }
+    let (chunk_digits, chunk_power) = chunk(base);
+    let chunk_power = Integer::from(chunk_power);
+    let mut remaining = Integer::from(value.abs_ref());
+    // Least significant digit first
+    let mut digits = Vec::new();
+    while remaining != 0 {
+        let (quotient, piece) = remaining.div_rem(chunk_power.clone());
+        let mut piece = piece.to_u64().unwrap();
+        for _ in 0..chunk_digits {
+            digits.push(digit_char((piece % base as u64) as u32));
+            piece /= base as u64;
+        }
+        remaining = quotient;
+    }
+    while digits.last() == Some(&'0') {
+        digits.pop();
+    }
+    if *value < 0 {
+        digits.push('-');
+    }
+    digits.iter().rev().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "native")]
+    use rug::ops::Pow;
+
+    #[test]
+    fn test_to_base_basic() {
+        // Base 2 (binary)
+        assert_eq!(to_base(5, 2), "101");
+        assert_eq!(to_base(10, 2), "1010");
+
+        // Base 10 (decimal)
+        assert_eq!(to_base(123, 10), "123");
+
+        // Base 16 (hexadecimal)
+        assert_eq!(to_base(255, 16), "FF");
+        assert_eq!(to_base(16, 16), "10");
+    }
+
+    #[test]
+    fn test_to_base_extended() {
+        // Base 36 (0-9, A-Z)
+        assert_eq!(to_base(35, 36), "Z");
+        assert_eq!(to_base(36, 36), "10");
+
+        // Base 37-62 (using lowercase letters for values 36+)
+        assert_eq!(to_base(36, 37), "a"); // value 36 in base 37 is 'a'
+        assert_eq!(to_base(37, 37), "10");
+
+        // Base 62 (0-9, A-Z, a-z)
+        assert_eq!(to_base(61, 62), "z"); // value 61 in base 62 is 'z'
+        assert_eq!(to_base(62, 62), "10");
+        assert_eq!(to_base(0, 62), "0");
+
+        // Additional base 62 examples
+        assert_eq!(to_base(10, 62), "A"); // value 10 is 'A'
+        assert_eq!(to_base(35, 62), "Z"); // value 35 is 'Z'
+        assert_eq!(to_base(36, 62), "a"); // value 36 is 'a'
+    }
+
+    #[test]
+    fn test_to_base_digit_ranges() {
+        // Verify digit representations
+        // 0-9 use '0'-'9'
+        assert_eq!(to_base(9, 10), "9");
+
+        // 10-35 use 'A'-'Z' for bases > 10
+        assert_eq!(to_base(10, 16), "A");
+        assert_eq!(to_base(15, 16), "F");
+        assert_eq!(to_base(35, 36), "Z");
+
+        // 36-61 use 'a'-'z' for bases > 36
+        assert_eq!(to_base(36, 62), "a");
+        assert_eq!(to_base(61, 62), "z");
+    }
+
+    #[test]
+    fn test_digits() {
+        assert_eq!(digits(0, 7), [0]);
+        assert_eq!(digits(255, 16), [15, 15]);
+        assert_eq!(digits(1000, 255), [3, 235]);
+        assert_eq!(digits(u64::MAX, 2).len(), 64);
+    }
+
+    #[cfg(feature = "native")]
+    #[test]
+    fn test_big_round_trips() {
+        let value = Integer::from(3).pow(200) - 1u32;
+        for base in [2, 10, 16, 36, 37, 61, 62] {
+            let text = to_base_big(&value, base);
+            assert_eq!(parse_big(&text, base).unwrap(), value, "base {}", base);
+        }
+        assert_eq!(
+            to_base_big(&Integer::from(u64::MAX), 62),
+            to_base(usize::MAX, 62)
+        );
+        assert_eq!(to_base_big(&Integer::from(-255), 16), "-FF");
+        assert_eq!(parse_big("ff", 16).unwrap(), 255);
+        assert_eq!(parse_big("a", 62).unwrap(), 36);
+        assert_eq!(to_base_big(&Integer::new(), 62), "0");
+        assert!(parse_big("12", 2).is_err());
+        assert!(parse_big("-", 10).is_err());
+    }
+}
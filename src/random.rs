@@ -1,31 +1,73 @@
-use crate::scan;
+use crate::digit_source::{DigitSource, RandomDigits};
+use crate::scan::{self, Pattern};
 use std::collections::hash_map::RandomState;
-use std::hash::{BuildHasher, Hash, Hasher};
+use std::hash::{BuildHasher, Hasher};
 
-pub fn generate_and_scan(digits: usize) {
-    // Generate random digits
-    let random_digits = generate_random_digits(digits);
+pub fn generate_and_scan(digits: usize, pattern: Pattern, max_digits: usize) {
+    let random_digits = RandomDigits(Rng::from_entropy())
+        .digits(digits)
+        .unwrap_or_default();
 
     println!("Generated {} random digits:", digits);
     println!("{}", random_digits);
     println!();
 
-    // Scan for primes
-    println!("Scanning for primes in random digits...");
-    scan::scan_for_primes(&random_digits);
+    println!("Scanning random digits...");
+    scan::scan_for(&random_digits, pattern, max_digits, false);
 }
 
-fn generate_random_digits(count: usize) -> String {
-    let random_state = RandomState::new();
-    let mut digits = String::with_capacity(count);
+/// Small, fast, seedable generator (SplitMix64); not for cryptography
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Same seed, same sequence
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Seeded from std's randomly keyed hasher
+    pub fn from_entropy() -> Self {
+        Self::new(RandomState::new().build_hasher().finish())
+    }
 
-    for i in 0..count {
-        let mut hasher = random_state.build_hasher();
-        i.hash(&mut hasher);
-        let random_value = hasher.finish();
-        let digit = random_value % 10;
-        digits.push_str(&digit.to_string());
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
     }
 
-    digits
+    /// Uniform in [0, bound); bound must be positive
+    pub fn below(&mut self, bound: u64) -> u64 {
+        // Multiply-shift with rejection of the biased low zone
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let product = self.next_u64() as u128 * bound as u128;
+            if (product as u64) >= threshold {
+                return (product >> 64) as u64;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_is_seeded_and_in_range() {
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+        for _ in 0..1000 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        let mut counts = [0; 10];
+        for _ in 0..100_000 {
+            counts[a.below(10) as usize] += 1;
+        }
+        assert!(counts.iter().all(|&c| (9_000..11_000).contains(&c)));
+    }
 }
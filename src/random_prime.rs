@@ -0,0 +1,299 @@
+// Random primes with a given number of digits or digit pattern (`nt random-prime`)
+//
+// A pattern is a string of digits and wildcards: `?` stands for one digit, `*` for a run
+// of digits whose length makes the whole number `--digits` long. Each candidate fills the
+// wildcards at random, with the leading digit never 0 and a free last digit always one of
+// 1, 3, 7, 9, and is kept if Baillie–PSW (bpsw.rs) calls it prime. Runs share the free
+// length at random, so a pattern with several `*` does not favour one layout.
+
+use std::fmt;
+
+use rug::Integer;
+
+use crate::bpsw;
+use crate::random::Rng;
+
+const LAST_DIGITS: [u8; 4] = [1, 3, 7, 9];
+
+/// One position of a pattern
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slot {
+    Digit(u8),
+    /// `?`
+    Any,
+    /// `*`
+    Run,
+}
+
+/// Why a pattern cannot produce primes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PatternError {
+    BadChar(char),
+    /// Zero digits asked for
+    Empty,
+    /// A pattern with `*` needs a length
+    NeedsDigits,
+    /// The pattern's fixed length does not fit the digit count
+    Length {
+        pattern: usize,
+        digits: usize,
+    },
+    LeadingZero,
+    /// A fixed last digit that no prime of this length ends in
+    LastDigit(u8),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::BadChar(c) => {
+                write!(f, "'{}' is not a digit, '?' or '*'", c)
+            }
+            PatternError::Empty => write!(f, "a prime needs at least one digit"),
+            PatternError::NeedsDigits => write!(f, "a pattern with '*' needs --digits"),
+            PatternError::Length { pattern, digits } => write!(
+                f,
+                "the pattern has {} fixed positions but the prime has {} digits",
+                pattern, digits
+            ),
+            PatternError::LeadingZero => write!(f, "the pattern starts with 0"),
+            PatternError::LastDigit(d) => {
+                write!(f, "no prime of more than one digit ends in {}", d)
+            }
+        }
+    }
+}
+
+/// A pattern checked against a digit count
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    slots: Vec<Slot>,
+    digits: usize,
+}
+
+impl Template {
+    /// `pattern` defaults to `digits` wildcards, `digits` to the pattern's length
+    pub fn new(pattern: Option<&str>, digits: Option<usize>) -> Result<Self, PatternError> {
+        let text = match (pattern, digits) {
+            (Some(text), _) => text.to_string(),
+            (None, Some(digits)) => "?".repeat(digits),
+            (None, None) => return Err(PatternError::Empty),
+        };
+        let slots = text
+            .chars()
+            .map(|c| match c {
+                '?' => Ok(Slot::Any),
+                '*' => Ok(Slot::Run),
+                _ => c
+                    .to_digit(10)
+                    .map(|d| Slot::Digit(d as u8))
+                    .ok_or(PatternError::BadChar(c)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let fixed = slots.iter().filter(|&&slot| slot != Slot::Run).count();
+        let has_run = fixed < slots.len();
+        let digits = match digits {
+            Some(digits) if digits < fixed || (!has_run && digits != fixed) => {
+                return Err(PatternError::Length {
+                    pattern: fixed,
+                    digits,
+                });
+            }
+            Some(digits) => digits,
+            None if has_run => return Err(PatternError::NeedsDigits),
+            None => fixed,
+        };
+        if digits == 0 {
+            return Err(PatternError::Empty);
+        }
+        if slots.first() == Some(&Slot::Digit(0)) {
+            return Err(PatternError::LeadingZero);
+        }
+        if let Some(&Slot::Digit(d)) = slots.last()
+            && digits > 1
+            && !LAST_DIGITS.contains(&d)
+        {
+            return Err(PatternError::LastDigit(d));
+        }
+        Ok(Self { slots, digits })
+    }
+
+    pub fn digits(&self) -> usize {
+        self.digits
+    }
+
+    /// Whether every candidate is the same number
+    pub fn is_fixed(&self) -> bool {
+        let fixed = self.slots.iter().filter(|&&slot| slot != Slot::Run).count();
+        !self.slots.contains(&Slot::Any) && fixed == self.digits
+    }
+
+    /// A random filling of the pattern, in decimal
+    pub fn candidate(&self, rng: &mut Rng) -> String {
+        // Split the free length among the runs: sorted cut points, stars and bars
+        let runs = self.slots.iter().filter(|&&slot| slot == Slot::Run).count();
+        let free = self.digits - (self.slots.len() - runs);
+        let mut cuts: Vec<usize> = (1..runs)
+            .map(|_| rng.below(free as u64 + 1) as usize)
+            .collect();
+        cuts.sort_unstable();
+        cuts.push(free);
+        let mut lengths = Vec::with_capacity(runs);
+        let mut previous = 0;
+        for cut in cuts {
+            lengths.push(cut - previous);
+            previous = cut;
+        }
+
+        let mut text = String::with_capacity(self.digits);
+        let mut lengths = lengths.into_iter();
+        let random_digit = |position: usize, rng: &mut Rng| {
+            let digit = if position + 1 == self.digits && self.digits > 1 {
+                LAST_DIGITS[rng.below(4) as usize]
+            } else if position == 0 {
+                1 + rng.below(9) as u8
+            } else {
+                rng.below(10) as u8
+            };
+            char::from(b'0' + digit)
+        };
+        for slot in &self.slots {
+            match *slot {
+                Slot::Digit(d) => text.push(char::from(b'0' + d)),
+                Slot::Any => text.push(random_digit(text.len(), rng)),
+                Slot::Run => {
+                    for _ in 0..lengths.next().unwrap_or(0) {
+                        text.push(random_digit(text.len(), rng));
+                    }
+                }
+            }
+        }
+        text
+    }
+}
+
+/// The primes one search found
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Search {
+    /// Distinct primes, in the order they were found
+    pub primes: Vec<Integer>,
+    /// Candidates generated
+    pub attempts: usize,
+}
+
+/// Up to `count` distinct primes matching `template`, generating at most `max_attempts`
+/// candidates
+pub fn search(template: &Template, count: usize, max_attempts: usize, rng: &mut Rng) -> Search {
+    let max_attempts = if template.is_fixed() {
+        max_attempts.min(1)
+    } else {
+        max_attempts
+    };
+    let mut primes: Vec<Integer> = Vec::new();
+    let mut attempts = 0;
+    while primes.len() < count && attempts < max_attempts {
+        attempts += 1;
+        let text = template.candidate(rng);
+        // A run in front of a fixed 0 can leave a leading zero
+        if text.len() > 1 && text.starts_with('0') {
+            continue;
+        }
+        let n: Integer = text.parse().unwrap();
+        if bpsw::is_probable_prime(&n) && !primes.contains(&n) {
+            primes.push(n);
+        }
+    }
+    Search { primes, attempts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(text: &str, pattern: &str) -> bool {
+        text.len() == pattern.len()
+            && text
+                .chars()
+                .zip(pattern.chars())
+                .all(|(c, p)| p == '?' || c == p)
+    }
+
+    #[test]
+    fn test_template_validation() {
+        assert_eq!(
+            Template::new(Some("12a"), None),
+            Err(PatternError::BadChar('a'))
+        );
+        assert_eq!(
+            Template::new(Some("1*7"), None),
+            Err(PatternError::NeedsDigits)
+        );
+        assert_eq!(
+            Template::new(Some("1??7"), Some(5)),
+            Err(PatternError::Length {
+                pattern: 4,
+                digits: 5
+            })
+        );
+        assert_eq!(
+            Template::new(Some("0?7"), None),
+            Err(PatternError::LeadingZero)
+        );
+        assert_eq!(
+            Template::new(Some("1?5"), None),
+            Err(PatternError::LastDigit(5))
+        );
+        assert_eq!(Template::new(None, Some(0)), Err(PatternError::Empty));
+        assert!(Template::new(Some("5"), None).is_ok());
+        assert_eq!(
+            Template::new(Some("12??3*7"), Some(50)).unwrap().digits(),
+            50
+        );
+    }
+
+    #[test]
+    fn test_candidates_follow_the_pattern() {
+        let mut rng = Rng::new(7);
+        let template = Template::new(Some("12??3*7"), Some(20)).unwrap();
+        for _ in 0..200 {
+            let text = template.candidate(&mut rng);
+            assert_eq!(text.len(), 20);
+            assert!(text.starts_with("12") && text.ends_with('7'), "{}", text);
+            assert_eq!(&text[4..5], "3", "{}", text);
+        }
+        // Several runs share the free length
+        let template = Template::new(Some("*1*"), Some(9)).unwrap();
+        for _ in 0..200 {
+            let text = template.candidate(&mut rng);
+            assert_eq!(text.len(), 9);
+            assert!(text.contains('1') && !text.starts_with('0'), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_search_finds_matching_primes() {
+        let mut rng = Rng::new(11);
+        let template = Template::new(Some("9??1"), None).unwrap();
+        let found = search(&template, 5, 10_000, &mut rng);
+        assert_eq!(found.primes.len(), 5);
+        for p in &found.primes {
+            assert!(matches(&p.to_string(), "9??1"), "{}", p);
+            assert!(crate::factor::is_prime(p.to_u64().unwrap()), "{}", p);
+        }
+
+        let template = Template::new(None, Some(40)).unwrap();
+        let found = search(&template, 2, 100_000, &mut rng);
+        assert_eq!(found.primes.len(), 2);
+        assert!(found.primes.iter().all(|p| p.to_string().len() == 40));
+
+        // 91 = 7 · 13 can only ever be tried once
+        let template = Template::new(Some("91"), None).unwrap();
+        assert_eq!(
+            search(&template, 1, 1000, &mut rng),
+            Search {
+                primes: Vec::new(),
+                attempts: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,124 @@
+// Exact non-negative rationals for the Farey and Stern–Brocot tools
+//
+// A Ratio is kept in lowest terms with a positive denominator, so equal values compare
+// equal field by field. It always prints as p/q, 0/1 and 1/1 included, the way Farey
+// sequences are written. Ordering cross-multiplies in u128, which is exact for any pair
+// of u64 numerators and denominators. The mediant (a + c)/(b + d) is what both the Farey
+// sequences and the Stern–Brocot tree are built from.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// A non-negative rational num/den in lowest terms
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Ratio {
+    num: u64,
+    den: u64,
+}
+
+pub fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl Ratio {
+    /// num/den reduced to lowest terms, or None if den is 0
+    pub fn new(num: u64, den: u64) -> Option<Self> {
+        if den == 0 {
+            return None;
+        }
+        let g = gcd(num, den);
+        Some(Self {
+            num: num / g,
+            den: den / g,
+        })
+    }
+
+    pub fn integer(n: u64) -> Self {
+        Self { num: n, den: 1 }
+    }
+
+    pub fn num(self) -> u64 {
+        self.num
+    }
+
+    pub fn den(self) -> u64 {
+        self.den
+    }
+
+    /// (a + c)/(b + d) for a/b and c/d, which lies strictly between them when they differ
+    /// None if a sum overflows
+    pub fn mediant(self, other: Self) -> Option<Self> {
+        Self::new(
+            self.num.checked_add(other.num)?,
+            self.den.checked_add(other.den)?,
+        )
+    }
+
+    /// The value as the nearest f64
+    pub fn to_f64(self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+}
+
+impl Ord for Ratio {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.num as u128 * other.den as u128).cmp(&(other.num as u128 * self.den as u128))
+    }
+}
+
+impl PartialOrd for Ratio {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Ratio {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.num, self.den)
+    }
+}
+
+impl FromStr for Ratio {
+    type Err = String;
+
+    /// "p/q" or a whole number "p"
+    fn from_str(input: &str) -> Result<Self, String> {
+        let parse = |part: &str| {
+            part.trim()
+                .parse::<u64>()
+                .map_err(|_| format!("'{}' is not a fraction like 3/7", input))
+        };
+        match input.split_once('/') {
+            Some((num, den)) => Ratio::new(parse(num)?, parse(den)?)
+                .ok_or_else(|| format!("'{}' has a zero denominator", input)),
+            None => Ok(Ratio::integer(parse(input)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduces_orders_and_parses() {
+        let half: Ratio = "2/4".parse().unwrap();
+        assert_eq!(half, Ratio::new(1, 2).unwrap());
+        assert_eq!(half.to_string(), "1/2");
+        assert_eq!("6".parse::<Ratio>().unwrap().to_string(), "6/1");
+        assert!("1/0".parse::<Ratio>().is_err());
+        assert!("-1/2".parse::<Ratio>().is_err());
+
+        let third = Ratio::new(1, 3).unwrap();
+        assert!(third < half);
+        assert_eq!(third.mediant(half), Ratio::new(2, 5));
+        // Cross products far beyond u64
+        let big = Ratio::new(u64::MAX - 1, u64::MAX).unwrap();
+        assert!(big < Ratio::integer(1));
+        assert!(Ratio::new(u64::MAX - 2, u64::MAX - 1).unwrap() < big);
+    }
+}
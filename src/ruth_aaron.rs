@@ -0,0 +1,100 @@
+// Ruth–Aaron pairs (`nt ruth-aaron`)
+//
+// n and n + 1 form a Ruth–Aaron pair when their prime factors have the same sum: 714 =
+// 2·3·7·17 and 715 = 5·11·13 both give 29 (Babe Ruth's 714 home runs, Hank Aaron's 715th).
+// Factors count with multiplicity by default (sopfr, 5, 8, 15, 77, ...) or once each with
+// --distinct (sopf, 5, 24, 49, 77, ...). Every n up to the limit is factored, so the sums
+// come from a smallest-prime-factor table: a handful of lookups per number instead of trial
+// division. [2, limit] is split into chunks over worker threads; each chunk computes the sum
+// just before its start and then walks forward comparing neighbours.
+
+use crate::SpfTable;
+use crate::parallel::{MIN_CHUNK, chunk_size, parallel_chunks};
+
+/// Sum of the prime factors of n >= 2, each distinct prime once if `distinct`
+pub fn prime_factor_sum(table: &SpfTable, n: usize, distinct: bool) -> usize {
+    let mut sum = 0;
+    let mut last = 0;
+    let mut n = n;
+    while n > 1 {
+        let p = table.smallest_factor(n);
+        if !distinct || p != last {
+            sum += p;
+        }
+        last = p;
+        n /= p;
+    }
+    sum
+}
+
+/// (n, sum) for every Ruth–Aaron pair n, n + 1 with n + 1 <= limit, in increasing order
+/// `table` must cover `limit`
+pub fn find_pairs(
+    table: &SpfTable,
+    limit: usize,
+    distinct: bool,
+    num_workers: usize,
+) -> Vec<(usize, usize)> {
+    assert!(
+        limit <= table.limit(),
+        "limit {} is above the spf table limit {}",
+        limit,
+        table.limit()
+    );
+    if limit < 3 {
+        return Vec::new();
+    }
+    // n runs over [2, limit − 1]
+    let mut chunks = Vec::new();
+    parallel_chunks(
+        2..=limit - 1,
+        chunk_size(limit - 2, num_workers, MIN_CHUNK),
+        num_workers,
+        |low, high| {
+            let mut pairs = Vec::new();
+            let mut previous = prime_factor_sum(table, low, distinct);
+            for n in low..=high {
+                let sum = prime_factor_sum(table, n + 1, distinct);
+                if sum == previous {
+                    pairs.push((n, sum));
+                }
+                previous = sum;
+            }
+            (low, pairs)
+        },
+        |chunk| chunks.push(chunk),
+    );
+
+    chunks.sort_unstable_by_key(|&(low, _)| low);
+    chunks.into_iter().flat_map(|(_, pairs)| pairs).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairs_match_oeis() {
+        let table = SpfTable::new(200_000);
+        // A039752 (with multiplicity) and A006145 (distinct primes)
+        let with_multiplicity = find_pairs(&table, 20_000, false, 3);
+        let starts: Vec<usize> = with_multiplicity.iter().map(|&(n, _)| n).collect();
+        assert_eq!(
+            &starts[..12],
+            [5, 8, 15, 77, 125, 714, 948, 1330, 1520, 1862, 2491, 3248]
+        );
+        assert!(with_multiplicity.contains(&(714, 29)));
+        let distinct: Vec<usize> = find_pairs(&table, 20_000, true, 3)
+            .into_iter()
+            .map(|(n, _)| n)
+            .collect();
+        assert_eq!(
+            &distinct[..12],
+            [5, 24, 49, 77, 104, 153, 369, 492, 714, 1682, 2107, 2299]
+        );
+
+        // Chunk boundaries must not drop or duplicate pairs
+        assert_eq!(find_pairs(&table, 100_000, false, 4).len(), 57);
+        assert_eq!(find_pairs(&table, 100_000, true, 4).len(), 40);
+    }
+}
@@ -1,59 +1,725 @@
+// Analyses of digit strings from any DigitSource (digit_source.rs)
+//
+// The main one searches the string for numbers of a kind, given by a PatternSource: stored
+// primes, or squares, cubes and Fibonacci numbers generated on the fly. Every window of 4
+// up to the source's digit bound that does not start with 0 is read as a number and
+// tested, so the cost is the string length times the number of window lengths, however
+// many numbers the source holds. The others list palindromic runs and check the digit
+// frequencies against uniformly random digits.
+
+use std::ops::RangeInclusive;
+
+use clap::ValueEnum;
+use tracing::error;
+
+use crate::primes_bases::is_palindrome;
 use crate::storage;
 
+// Shorter matches are everywhere in a long enough string
+const MIN_DIGITS: usize = 4;
+
+// Windows are read as u64
+pub const MAX_DIGITS: usize = 19;
+
+// Matches printed with their context
+const LISTED: usize = 50;
+
+/// Numbers a digit string can be searched for
+pub trait PatternSource {
+    /// Plural name, e.g. "primes"
+    fn name(&self) -> &'static str;
+    /// Lengths of the numbers searched for
+    fn digits(&self) -> RangeInclusive<usize>;
+    fn contains(&self, n: u64) -> bool;
+    /// How many of the numbers have exactly `digits` digits, for the expected density
+    fn count(&self, digits: usize) -> u64;
+}
+
+/// Which PatternSource `nt scan --pattern` uses
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum Pattern {
+    /// Primes in primes.txt (or the variation 9 shards)
+    #[default]
+    Primes,
+    /// Perfect squares
+    Squares,
+    /// Perfect cubes
+    Cubes,
+    /// Fibonacci numbers
+    Fibonacci,
+}
+
+impl Pattern {
+    /// The source for numbers of up to `max_digits` digits; only Primes can fail, as it
+    /// loads the stored primes
+    pub fn source(self, max_digits: usize) -> Result<Box<dyn PatternSource>, String> {
+        let max_digits = max_digits.clamp(MIN_DIGITS, MAX_DIGITS);
+        Ok(match self {
+            Pattern::Primes => Box::new(StoredPrimes::load()?),
+            Pattern::Squares => Box::new(Powers {
+                exponent: 2,
+                max_digits,
+            }),
+            Pattern::Cubes => Box::new(Powers {
+                exponent: 3,
+                max_digits,
+            }),
+            Pattern::Fibonacci => Box::new(Fibonacci::new(max_digits)),
+        })
+    }
+}
+
+/// The stored primes of 4 or more digits
+pub struct StoredPrimes {
+    primes: Vec<u64>,
+}
+
+impl StoredPrimes {
+    pub fn load() -> Result<Self, String> {
+        let primes = storage::load_all_primes().map_err(|e| e.to_string())?;
+        Ok(Self {
+            primes: primes
+                .into_iter()
+                .filter(|&p| p >= 1000)
+                .map(|p| p as u64)
+                .collect(),
+        })
+    }
+}
+
+impl PatternSource for StoredPrimes {
+    fn name(&self) -> &'static str {
+        "primes"
+    }
+
+    fn digits(&self) -> RangeInclusive<usize> {
+        let largest = self.primes.last().map_or(0, |p| p.to_string().len());
+        MIN_DIGITS..=largest.min(MAX_DIGITS)
+    }
+
+    fn contains(&self, n: u64) -> bool {
+        self.primes.binary_search(&n).is_ok()
+    }
+
+    fn count(&self, digits: usize) -> u64 {
+        count_in(&self.primes, digits)
+    }
+}
+
+/// Perfect squares (exponent 2) or cubes (3)
+pub struct Powers {
+    pub exponent: u32,
+    pub max_digits: usize,
+}
+
+impl PatternSource for Powers {
+    fn name(&self) -> &'static str {
+        if self.exponent == 2 {
+            "squares"
+        } else {
+            "cubes"
+        }
+    }
+
+    fn digits(&self) -> RangeInclusive<usize> {
+        MIN_DIGITS..=self.max_digits
+    }
+
+    fn contains(&self, n: u64) -> bool {
+        floor_root(n, self.exponent).pow(self.exponent) == n
+    }
+
+    fn count(&self, digits: usize) -> u64 {
+        let below = |digits: usize| floor_root(10_u64.pow(digits as u32 - 1) - 1, self.exponent);
+        below(digits + 1) - below(digits)
+    }
+}
+
+/// The largest r with r^exponent <= n, for exponent 2 or 3
+fn floor_root(n: u64, exponent: u32) -> u64 {
+    if exponent == 2 {
+        return n.isqrt();
+    }
+    // The float cube root is within one of the true root for any u64
+    let root = (n as f64).cbrt().round() as u64;
+    (root.saturating_sub(1)..=root + 1)
+        .rev()
+        .find(|r| r.checked_pow(3).is_some_and(|cube| cube <= n))
+        .unwrap_or(0)
+}
+
+/// Fibonacci numbers up to a digit bound
+pub struct Fibonacci {
+    numbers: Vec<u64>,
+    max_digits: usize,
+}
+
+impl Fibonacci {
+    pub fn new(max_digits: usize) -> Self {
+        let bound = 10_u64.checked_pow(max_digits as u32).unwrap_or(u64::MAX);
+        let mut numbers = Vec::new();
+        let (mut a, mut b) = (1_u64, 2_u64);
+        while a < bound {
+            numbers.push(a);
+            let Some(next) = a.checked_add(b) else { break };
+            (a, b) = (b, next);
+        }
+        if b < bound && numbers.last() != Some(&b) {
+            numbers.push(b);
+        }
+        Self {
+            numbers,
+            max_digits,
+        }
+    }
+}
+
+impl PatternSource for Fibonacci {
+    fn name(&self) -> &'static str {
+        "Fibonacci numbers"
+    }
+
+    fn digits(&self) -> RangeInclusive<usize> {
+        MIN_DIGITS..=self.max_digits
+    }
+
+    fn contains(&self, n: u64) -> bool {
+        self.numbers.binary_search(&n).is_ok()
+    }
+
+    fn count(&self, digits: usize) -> u64 {
+        count_in(&self.numbers, digits)
+    }
+}
+
+/// Numbers of exactly `digits` digits in sorted `numbers`
+fn count_in(numbers: &[u64], digits: usize) -> u64 {
+    let low = 10_u64.pow(digits as u32 - 1);
+    let high = 10_u64.checked_pow(digits as u32).unwrap_or(u64::MAX);
+    (numbers.partition_point(|&n| n < high) - numbers.partition_point(|&n| n < low)) as u64
+}
+
+/// Every (number, position) in `digit_str` that `source` contains, in order of position
+/// and then length; occurrences may overlap
+pub fn find(digit_str: &str, source: &dyn PatternSource) -> Vec<(u64, usize)> {
+    find_from(digit_str, source, 0)
+}
+
+/// `find` for the occurrences starting at `from` or later
+pub fn find_from(digit_str: &str, source: &dyn PatternSource, from: usize) -> Vec<(u64, usize)> {
+    let bytes = digit_str.as_bytes();
+    let lengths = source.digits();
+    let mut found = Vec::new();
+    for (pos, &first) in bytes.iter().enumerate().skip(from) {
+        if first == b'0' {
+            continue;
+        }
+        let mut value = 0_u64;
+        for (length, &digit) in (1..).zip(&bytes[pos..]) {
+            if length > *lengths.end() || !digit.is_ascii_digit() {
+                break;
+            }
+            value = value * 10 + (digit - b'0') as u64;
+            if length >= *lengths.start() && source.contains(value) {
+                found.push((value, pos));
+            }
+        }
+    }
+    found
+}
+
+/// The occurrences in `found` (sorted by position, in a string of `len` digits) that cover
+/// the most digits without sharing any: weighted interval scheduling with each occurrence
+/// weighted by its length, solved from the end of the string back
+pub fn max_coverage(found: &[(u64, usize)], len: usize) -> Vec<(u64, usize)> {
+    // best[i]: most digits coverable from position i on; choice[i]: the occurrence
+    // starting at i that achieves it, if any
+    let mut best = vec![0; len + 1];
+    let mut choice = vec![None; len + 1];
+    let mut next = found.len();
+    for pos in (0..len).rev() {
+        best[pos] = best[pos + 1];
+        while next > 0 && found[next - 1].1 == pos {
+            next -= 1;
+            let (number, start) = found[next];
+            let end = start + number.to_string().len();
+            if end - start + best[end] > best[pos] {
+                best[pos] = end - start + best[end];
+                choice[pos] = Some(next);
+            }
+        }
+    }
+    let mut chosen = Vec::new();
+    let mut pos = 0;
+    while pos < len {
+        match choice[pos] {
+            Some(index) => {
+                chosen.push(found[index]);
+                pos += found[index].0.to_string().len();
+            }
+            None => pos += 1,
+        }
+    }
+    chosen
+}
+
 pub fn scan_for_primes(digit_str: &str) {
-    // Load primes from primes.txt
-    let primes = match storage::load_all_primes() {
-        Ok(primes) => primes,
+    scan_for(digit_str, Pattern::Primes, MAX_DIGITS, false);
+}
+
+/// Search `digit_str` for `pattern` numbers of up to `max_digits` digits and print what is
+/// found with its context; with `non_overlapping`, only the occurrences that cover the
+/// most digits without overlapping, and how many digits they cover
+pub fn scan_for(digit_str: &str, pattern: Pattern, max_digits: usize, non_overlapping: bool) {
+    let source = match pattern.source(max_digits) {
+        Ok(source) => source,
         Err(e) => {
-            eprintln!("Error loading primes.txt: {}", e);
+            error!("Error loading primes: {}", e);
             return;
         }
     };
+    print_header(digit_str, source.as_ref());
+    let found = find(digit_str, source.as_ref());
+    print_found(digit_str, found, non_overlapping);
+}
 
-    // Filter to only primes with 4 or more digits
-    let primes: Vec<usize> = primes.into_iter().filter(|p| *p >= 1000).collect();
-
+/// What is about to be searched for, and in how many digits
+pub fn print_header(digit_str: &str, source: &dyn PatternSource) {
+    let lengths = source.digits();
     println!("Digits to scan: {} digits", digit_str.len());
-    println!("Number of primes (4+ digits) loaded: {}", primes.len());
+    println!(
+        "Searching for {} of {} to {} digits",
+        source.name(),
+        lengths.start(),
+        lengths.end()
+    );
+    println!();
+}
+
+/// Print the occurrences `found` in `digit_str` with their context, or with
+/// `non_overlapping` only those that cover the most digits without overlapping and how
+/// many digits they cover
+pub fn print_found(digit_str: &str, mut found: Vec<(u64, usize)>, non_overlapping: bool) {
+    println!("Found {} occurrences:", found.len());
+    if non_overlapping {
+        found = max_coverage(&found, digit_str.len());
+        let covered: usize = found.iter().map(|(n, _)| n.to_string().len()).sum();
+        println!(
+            "{} non-overlapping occurrences cover {} of {} digits ({:.2}%)",
+            found.len(),
+            covered,
+            digit_str.len(),
+            100.0 * covered as f64 / digit_str.len().max(1) as f64
+        );
+    }
     println!();
+    println!("Number\tPosition\tContext");
+    println!("------\t--------\t-------");
 
-    let mut found_primes = Vec::new();
+    for (number, pos) in found.iter().take(LISTED) {
+        let number_str = number.to_string();
+        let context_start = pos.saturating_sub(3);
+        let context_end = (pos + number_str.len() + 3).min(digit_str.len());
+        let context = &digit_str[context_start..context_end];
 
-    // Check each prime to see if it appears in the digit string
-    for prime in &primes {
-        let prime_str = prime.to_string();
+        // Highlight the number in context
+        let prefix = &context[0..(pos - context_start)];
+        let suffix = &context[(pos - context_start + number_str.len())..];
+
+        println!(
+            "{}\t{}\t\t{}[{}]{}",
+            number, pos, prefix, number_str, suffix
+        );
+    }
 
-        // Find all occurrences of this prime
-        let mut start = 0;
-        while let Some(pos) = digit_str[start..].find(&prime_str) {
-            let actual_pos = start + pos;
-            found_primes.push((*prime, actual_pos));
-            start = actual_pos + 1;
+    if found.len() > LISTED {
+        println!("\n... and {} more", found.len() - LISTED);
+    }
+}
+
+/// How `nt scan --heatmap` prints the buckets
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum HeatmapFormat {
+    /// Rows of block characters, found over expected
+    #[default]
+    Sparkline,
+    /// start,end,found,expected lines
+    Csv,
+}
+
+/// Occurrences starting in one slice of the digit string
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bucket {
+    /// Positions start..end
+    pub start: usize,
+    pub end: usize,
+    pub found: u64,
+    /// Occurrences a string of uniformly random digits would have here
+    pub expected: f64,
+}
+
+/// Split `len` digits into `buckets` equal slices and count the occurrences in `found`
+/// starting in each. A window of d digits matches a random string with probability
+/// count(d) / 10^d, so the expected count at a position is that summed over the window
+/// lengths that fit before the end of the string.
+pub fn heatmap(
+    len: usize,
+    found: &[(u64, usize)],
+    source: &dyn PatternSource,
+    buckets: usize,
+) -> Vec<Bucket> {
+    let lengths = source.digits();
+    // density[k]: expected matches at a position with k digits left (capped at the longest)
+    let mut density = vec![0.0];
+    for k in 1..=*lengths.end() {
+        let mut next = density[k - 1];
+        if lengths.contains(&k) {
+            next += source.count(k) as f64 / 10_f64.powi(k as i32);
         }
+        density.push(next);
+    }
+    let width = len.div_ceil(buckets.max(1)).max(1);
+    let mut result: Vec<Bucket> = (0..len)
+        .step_by(width)
+        .map(|start| Bucket {
+            start,
+            end: (start + width).min(len),
+            found: 0,
+            expected: (start..(start + width).min(len))
+                .map(|pos| density[(len - pos).min(density.len() - 1)])
+                .sum(),
+        })
+        .collect();
+    for &(_, pos) in found {
+        result[pos / width].found += 1;
     }
+    result
+}
 
-    // Sort by position
-    found_primes.sort_by_key(|(_, pos)| *pos);
+/// One block character per value, scaled so `max` is a full block
+pub fn sparkline(values: impl Iterator<Item = f64>, max: f64) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    values
+        .map(|value| {
+            if max <= 0.0 {
+                return BLOCKS[0];
+            }
+            let level = (value / max * (BLOCKS.len() - 1) as f64).round() as usize;
+            BLOCKS[level.min(BLOCKS.len() - 1)]
+        })
+        .collect()
+}
 
-    println!("Found {} prime occurrences:", found_primes.len());
-    println!();
-    println!("Prime\tPosition\tContext");
-    println!("-----\t--------\t-------");
+/// Search `digit_str` like `scan_for`, but print how the occurrences spread over
+/// `buckets` slices of it next to the density expected of random digits
+pub fn heatmap_for(
+    digit_str: &str,
+    pattern: Pattern,
+    max_digits: usize,
+    buckets: usize,
+    format: HeatmapFormat,
+) {
+    let source = match pattern.source(max_digits) {
+        Ok(source) => source,
+        Err(e) => {
+            error!("Error loading primes: {}", e);
+            return;
+        }
+    };
+    let found = find(digit_str, source.as_ref());
+    let map = heatmap(digit_str.len(), &found, source.as_ref(), buckets);
 
-    for (prime, pos) in found_primes.iter().take(50) {
-        let prime_str = prime.to_string();
-        let context_start = pos.saturating_sub(3);
-        let context_end = (pos + prime_str.len() + 3).min(digit_str.len());
-        let context = &digit_str[context_start..context_end];
+    match format {
+        HeatmapFormat::Csv => {
+            println!("start,end,found,expected");
+            for bucket in &map {
+                println!(
+                    "{},{},{},{:.3}",
+                    bucket.start, bucket.end, bucket.found, bucket.expected
+                );
+            }
+        }
+        HeatmapFormat::Sparkline => {
+            let max = map
+                .iter()
+                .map(|bucket| (bucket.found as f64).max(bucket.expected))
+                .fold(0.0, f64::max);
+            println!(
+                "found    {}",
+                sparkline(map.iter().map(|bucket| bucket.found as f64), max)
+            );
+            println!(
+                "expected {}",
+                sparkline(map.iter().map(|bucket| bucket.expected), max)
+            );
+            let expected: f64 = map.iter().map(|bucket| bucket.expected).sum();
+            println!(
+                "{} {} in {} digits ({} per bucket); {:.1} expected of random digits",
+                found.len(),
+                source.name(),
+                digit_str.len(),
+                map.first().map_or(0, |bucket| bucket.end - bucket.start),
+                expected
+            );
+            if let Some(densest) = map.iter().max_by_key(|bucket| bucket.found)
+                && densest.found > 0
+            {
+                println!(
+                    "Densest: positions {}..{} with {} ({:.1} expected)",
+                    densest.start, densest.end, densest.found, densest.expected
+                );
+            }
+        }
+    }
+}
 
-        // Highlight the prime in context
-        let prefix = &context[0..(pos - context_start)];
-        let suffix = &context[(pos - context_start + prime_str.len())..];
+/// A palindromic run of digits
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Palindrome {
+    /// Position of its first digit, from 1
+    pub position: usize,
+    pub digits: String,
+}
+
+/// Every maximal palindrome of at least `min_length` digits in `digits`, in order of
+/// position. Any such palindrome has a window of `min_length` or `min_length + 1` digits
+/// (whichever has its parity) at its centre, so those windows are tested and each hit is
+/// widened while its ends match; each centre is tested once, so nothing is reported twice.
+pub fn palindromes(digits: &str, min_length: usize) -> Vec<Palindrome> {
+    let bytes = digits.as_bytes();
+    let mut found = Vec::new();
+    for start in 0..bytes.len() {
+        for length in [min_length.max(2), min_length.max(2) + 1] {
+            let end = start + length;
+            if end > bytes.len() || !is_palindrome(&digits[start..end]) {
+                continue;
+            }
+            let (mut low, mut high) = (start, end);
+            while low > 0 && high < bytes.len() && bytes[low - 1] == bytes[high] {
+                low -= 1;
+                high += 1;
+            }
+            found.push(Palindrome {
+                position: low + 1,
+                digits: digits[low..high].to_string(),
+            });
+        }
+    }
+    found.sort_by_key(|palindrome| (palindrome.position, palindrome.digits.len()));
+    found
+}
+
+/// List the palindromes of at least `min_length` in `digits`, which are `what` (e.g.
+/// "digits of π")
+pub fn print_palindromes(digits: &str, min_length: usize, what: &str) {
+    let found = palindromes(digits, min_length);
+    for palindrome in &found {
+        println!(
+            "{}\t{}\t{}",
+            palindrome.position,
+            palindrome.digits.len(),
+            palindrome.digits
+        );
+    }
+    println!(
+        "{} palindromes of {} or more digits in {} {}",
+        found.len(),
+        min_length,
+        digits.len(),
+        what
+    );
+}
+
+/// How often each digit occurs in `digits`
+pub fn digit_counts(digits: &str) -> [u64; 10] {
+    let mut counts = [0; 10];
+    for digit in digits.bytes().filter(u8::is_ascii_digit) {
+        counts[(digit - b'0') as usize] += 1;
+    }
+    counts
+}
+
+/// Pearson's χ² of `counts` against equal shares, with 9 degrees of freedom
+pub fn chi_square(counts: &[u64; 10]) -> f64 {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let expected = total as f64 / 10.0;
+    counts
+        .iter()
+        .map(|&count| (count as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+// χ² with 9 degrees of freedom exceeds this with probability 5%
+const CHI_SQUARE_95: f64 = 16.919;
+
+/// Print the digit frequencies of `digits`, which are `what`, and whether they are
+/// consistent with uniformly random digits
+pub fn print_statistics(digits: &str, what: &str) {
+    let counts = digit_counts(digits);
+    let total: u64 = counts.iter().sum();
+    println!("Digit frequencies in {} {}:", total, what);
+    for (digit, &count) in counts.iter().enumerate() {
+        println!(
+            "{}\t{}\t{:.4}%",
+            digit,
+            count,
+            100.0 * count as f64 / total.max(1) as f64
+        );
+    }
+    let chi = chi_square(&counts);
+    println!(
+        "χ² = {:.3} (9 degrees of freedom): {}",
+        chi,
+        if chi > CHI_SQUARE_95 {
+            "unlikely for random digits (p < 0.05)"
+        } else {
+            "consistent with random digits"
+        }
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_squares_cubes_and_fibonacci() {
+        let squares = Powers {
+            exponent: 2,
+            max_digits: 6,
+        };
+        // 1444 = 38², 42025 = 205², 2025 = 45²; windows starting with 0 are skipped
+        assert_eq!(
+            find("14442025", &squares),
+            vec![(1444, 0), (42025, 3), (2025, 4)]
+        );
+        assert_eq!(find("0004096", &squares), vec![(4096, 3)]);
+        assert_eq!(
+            find(
+                "x15625x",
+                &Powers {
+                    exponent: 3,
+                    max_digits: 6
+                }
+            ),
+            vec![(15625, 1)]
+        );
+
+        let fibonacci = Fibonacci::new(8);
+        assert!(fibonacci.contains(1597) && fibonacci.contains(14_930_352));
+        assert!(!fibonacci.contains(1598));
+        assert!(
+            !fibonacci.contains(102_334_155),
+            "9 digits is past the bound"
+        );
+        assert_eq!(find("6765109460", &fibonacci), vec![(6765, 0), (10946, 4)]);
+    }
+
+    #[test]
+    fn test_heatmap_buckets_and_expected_density() {
+        let squares = Powers {
+            exponent: 2,
+            max_digits: 4,
+        };
+        // 68 four-digit squares (32² to 99²), so 0.0068 expected per position
+        assert_eq!(squares.count(4), 68);
+        assert_eq!(
+            Powers {
+                exponent: 3,
+                max_digits: 6
+            }
+            .count(5),
+            25
+        );
+        let digits = "1444000000002025";
+        let found = find(digits, &squares);
+        let map = heatmap(digits.len(), &found, &squares, 4);
+        assert_eq!(
+            map.iter().map(|bucket| bucket.found).collect::<Vec<_>>(),
+            vec![1, 0, 0, 1]
+        );
+        assert_eq!((map[3].start, map[3].end), (12, 16));
+        assert!((map[0].expected - 4.0 * 0.0068).abs() < 1e-9);
+        // Only position 12 has room for four digits in the last bucket
+        assert!((map[3].expected - 0.0068).abs() < 1e-9);
+        assert_eq!(sparkline([0.0, 0.5, 1.0].into_iter(), 1.0), "▁▅█");
+    }
+
+    #[test]
+    fn test_max_coverage_picks_non_overlapping_occurrences() {
+        let squares = Powers {
+            exponent: 2,
+            max_digits: 6,
+        };
+        // 1444, 42025 and 2025 overlap; 1444 + 2025 cover 8 digits, 42025 alone 5
+        let digits = "14442025";
+        let found = find(digits, &squares);
+        assert_eq!(
+            max_coverage(&found, digits.len()),
+            vec![(1444, 0), (2025, 4)]
+        );
+        // One long occurrence beats two short ones it overlaps
+        let found = vec![(1000, 0), (123456, 2), (7000, 6)];
+        assert_eq!(max_coverage(&found, 10), vec![(1000, 0), (7000, 6)]);
+        let found = vec![(1000, 1), (123456789, 2), (8000, 7)];
+        assert_eq!(max_coverage(&found, 11), vec![(123456789, 2)]);
+        assert!(max_coverage(&[], 5).is_empty());
+    }
+
+    #[test]
+    fn test_palindromes_are_maximal_and_positioned() {
+        let found = palindromes("1234321005500123", 3);
+        let listed: Vec<(usize, &str)> = found
+            .iter()
+            .map(|palindrome| (palindrome.position, palindrome.digits.as_str()))
+            .collect();
+        assert_eq!(listed, vec![(1, "1234321"), (5, "321005500123")]);
+
+        // The first 50 places of π
+        let pi = "14159265358979323846264338327950288419716939937510";
+        let listed: Vec<(usize, String)> = palindromes(pi, 3)
+            .into_iter()
+            .map(|palindrome| (palindrome.position, palindrome.digits))
+            .collect();
+        let expected = [
+            (1, "141"),
+            (8, "535"),
+            (12, "979"),
+            (15, "323"),
+            (19, "46264"),
+            (25, "383"),
+            (42, "939"),
+            (43, "3993"),
+        ];
+        assert_eq!(
+            listed,
+            expected.map(|(position, digits)| (position, digits.to_string()))
+        );
+        assert!(palindromes(pi, 6).is_empty());
+    }
 
-        println!("{}\t{}\t\t{}[{}]{}", prime, pos, prefix, prime_str, suffix);
+    #[test]
+    fn test_digit_statistics() {
+        assert_eq!(digit_counts("0123456789"), [1; 10]);
+        assert_eq!(chi_square(&[1; 10]), 0.0);
+        // All of one digit: (n - n/10)²/(n/10) + 9·(n/10) = 9n
+        let counts = digit_counts(&"7".repeat(100));
+        assert_eq!(counts[7], 100);
+        assert!((chi_square(&counts) - 900.0).abs() < 1e-9);
     }
 
-    if found_primes.len() > 50 {
-        println!("\n... and {} more", found_primes.len() - 50);
+    #[test]
+    fn test_cube_roots_near_u64_max() {
+        let cubes = Powers {
+            exponent: 3,
+            max_digits: MAX_DIGITS,
+        };
+        let root = 2_642_245_u64;
+        assert!(cubes.contains(root.pow(3)));
+        assert!(!cubes.contains(root.pow(3) + 1));
+        assert!(!cubes.contains(u64::MAX));
     }
 }
@@ -0,0 +1,274 @@
+// Saved scan results (`nt scan --save`, `nt scan --resume`)
+//
+// Scanning 10^8 digits of π for stored primes takes long enough that the occurrences are
+// worth keeping. Each (source, pattern) pair has a results file in <data dir>/scans:
+//
+//   # nt scan results
+//   # source pi
+//   # pattern primes 4-12
+//   # digits 1000000 fnv1a 9d6a1c0c6b4f3a21
+//   1009	4521
+//   ...
+//
+// one "number<TAB>position" line per occurrence, positions from 0. The digit count and
+// the FNV-1a hash of exactly those digits say what was scanned, so --resume can check that
+// a longer expansion still starts with them and search only where new occurrences can
+// start: the last (longest - 1) old digits onward, keeping only matches that reach past
+// them.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::scan::{self, Pattern, PatternSource};
+use crate::storage::{commit_output, get_nt_data_dir, staging_path};
+
+const HEADER: &str = "# nt scan results";
+
+/// What a results file says was scanned, and what was found
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScanRecord {
+    pub source: String,
+    /// Pattern name and the lengths searched, e.g. "primes 4-12"
+    pub pattern: String,
+    pub digits: usize,
+    pub hash: u64,
+    pub found: Vec<(u64, usize)>,
+}
+
+/// Why a scan could not be resumed
+#[derive(Debug)]
+pub enum ResumeError {
+    Io(io::Error),
+    /// A results file that does not parse
+    Corrupt(String),
+    /// The source now gives fewer digits than were scanned
+    Shorter {
+        scanned: usize,
+        now: usize,
+    },
+    /// The first digits are not the ones that were scanned
+    Changed,
+    /// The results are for other patterns or lengths
+    OtherPattern(String),
+}
+
+impl fmt::Display for ResumeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResumeError::Io(e) => write!(f, "{}", e),
+            ResumeError::Corrupt(line) => write!(f, "unreadable results line '{}'", line),
+            ResumeError::Shorter { scanned, now } => write!(
+                f,
+                "{} digits were scanned but the source now has {}",
+                scanned, now
+            ),
+            ResumeError::Changed => write!(
+                f,
+                "the digits differ from the ones scanned; scan again without --resume"
+            ),
+            ResumeError::OtherPattern(pattern) => {
+                write!(f, "the saved results are for {}", pattern)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for ResumeError {
+    fn from(e: io::Error) -> Self {
+        ResumeError::Io(e)
+    }
+}
+
+/// 64-bit FNV-1a, which unlike std's hashers is the same on every build
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// "primes 4-12" for `source`
+pub fn pattern_label(source: &dyn PatternSource) -> String {
+    let lengths = source.digits();
+    format!("{} {}-{}", source.name(), lengths.start(), lengths.end())
+}
+
+/// Where the results of scanning `source` (as written on the command line) for `pattern`
+/// are kept
+pub fn results_path(source: &str, pattern: Pattern) -> PathBuf {
+    let stem: String = source
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let pattern = format!("{:?}", pattern).to_lowercase();
+    get_nt_data_dir()
+        .join("scans")
+        .join(format!("{}_{}.tsv", stem, pattern))
+}
+
+/// The results at `path`, or None if there are none yet
+pub fn read(path: &Path) -> Result<Option<ScanRecord>, ResumeError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let mut lines = text.lines();
+    let corrupt = |line: &str| ResumeError::Corrupt(line.to_string());
+    if lines.next() != Some(HEADER) {
+        return Err(corrupt(text.lines().next().unwrap_or("")));
+    }
+    let mut field = |name: &str| {
+        let line = lines.next().unwrap_or("");
+        line.strip_prefix("# ")
+            .and_then(|rest| rest.strip_prefix(name))
+            .and_then(|rest| rest.strip_prefix(' '))
+            .map(str::to_string)
+            .ok_or_else(|| corrupt(line))
+    };
+    let source = field("source")?;
+    let pattern = field("pattern")?;
+    let digits_line = field("digits")?;
+    let (digits, hash) = digits_line
+        .split_once(" fnv1a ")
+        .and_then(|(digits, hash)| {
+            Some((digits.parse().ok()?, u64::from_str_radix(hash, 16).ok()?))
+        })
+        .ok_or_else(|| corrupt(&digits_line))?;
+    let found = lines
+        .map(|line| {
+            line.split_once('\t')
+                .and_then(|(number, position)| Some((number.parse().ok()?, position.parse().ok()?)))
+                .ok_or_else(|| corrupt(line))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Some(ScanRecord {
+        source,
+        pattern,
+        digits,
+        hash,
+        found,
+    }))
+}
+
+/// Replace the results at `path` with `record`
+pub fn write(path: &Path, record: &ScanRecord) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut text = format!(
+        "{}\n# source {}\n# pattern {}\n# digits {} fnv1a {:016x}\n",
+        HEADER, record.source, record.pattern, record.digits, record.hash
+    );
+    for (number, position) in &record.found {
+        text.push_str(&format!("{}\t{}\n", number, position));
+    }
+    fs::write(staging_path(path), text)?;
+    commit_output(path)
+}
+
+/// Scan `digits` of `source_name` for `source`'s numbers, continuing from `previous` when
+/// given. Returns the record to save and how many of its occurrences are new
+pub fn scan(
+    source_name: &str,
+    digits: &str,
+    source: &dyn PatternSource,
+    previous: Option<ScanRecord>,
+) -> Result<(ScanRecord, usize), ResumeError> {
+    let pattern = pattern_label(source);
+    let mut record = ScanRecord {
+        source: source_name.to_string(),
+        pattern: pattern.clone(),
+        digits: digits.len(),
+        hash: fnv1a(digits.as_bytes()),
+        found: Vec::new(),
+    };
+    let Some(previous) = previous else {
+        record.found = scan::find(digits, source);
+        let new = record.found.len();
+        return Ok((record, new));
+    };
+
+    if previous.pattern != pattern {
+        return Err(ResumeError::OtherPattern(previous.pattern));
+    }
+    if previous.digits > digits.len() {
+        return Err(ResumeError::Shorter {
+            scanned: previous.digits,
+            now: digits.len(),
+        });
+    }
+    if fnv1a(&digits.as_bytes()[..previous.digits]) != previous.hash {
+        return Err(ResumeError::Changed);
+    }
+    let longest = *source.digits().end();
+    let from = previous.digits.saturating_sub(longest.saturating_sub(1));
+    let new: Vec<(u64, usize)> = scan::find_from(digits, source, from)
+        .into_iter()
+        .filter(|&(number, position)| position + number.to_string().len() > previous.digits)
+        .collect();
+    let count = new.len();
+    record.found = previous.found;
+    record.found.extend(new);
+    record
+        .found
+        .sort_by_key(|&(number, position)| (position, number.to_string().len()));
+    Ok((record, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scan::Powers;
+
+    #[test]
+    fn test_resume_finds_only_new_occurrences() {
+        let squares = Powers {
+            exponent: 2,
+            max_digits: 5,
+        };
+        // 1444 = 38² and 2025 = 45²; 42025 = 205² straddles the first 6 digits
+        let digits = "14442025";
+        let (first, new) = scan("test", &digits[..6], &squares, None).unwrap();
+        assert_eq!((first.found.clone(), new), (vec![(1444, 0)], 1));
+
+        let (resumed, new) = scan("test", digits, &squares, Some(first.clone())).unwrap();
+        assert_eq!(new, 2);
+        assert_eq!(resumed.found, vec![(1444, 0), (42025, 3), (2025, 4)]);
+        assert_eq!(
+            resumed,
+            scan("test", digits, &squares, None).unwrap().0,
+            "resuming gives what a full scan does"
+        );
+
+        assert!(matches!(
+            scan("test", "1444", &squares, Some(first.clone())),
+            Err(ResumeError::Shorter { .. })
+        ));
+        assert!(matches!(
+            scan("test", "19442025", &squares, Some(first)),
+            Err(ResumeError::Changed)
+        ));
+    }
+
+    #[test]
+    fn test_results_file_round_trip() {
+        let record = ScanRecord {
+            source: "file:digits.txt".to_string(),
+            pattern: "squares 4-5".to_string(),
+            digits: 8,
+            hash: fnv1a(b"14442025"),
+            found: vec![(1444, 0), (2025, 4)],
+        };
+        let path = std::env::temp_dir().join(format!("nt_scan_{}.tsv", std::process::id()));
+        assert_eq!(read(&path).unwrap(), None);
+        write(&path, &record).unwrap();
+        assert_eq!(read(&path).unwrap(), Some(record));
+        fs::remove_file(&path).unwrap();
+
+        // FNV-1a test vectors
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
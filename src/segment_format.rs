@@ -0,0 +1,188 @@
+// What parallel sieve workers (variations 8 and 9) send for each sieved segment
+//
+// By default workers unpack a segment's bitmap into a Vec<usize> and the consumer formats
+// it. With --preformat the workers format straight from the bitmap into a recycled byte
+// buffer (decimal text or little-endian u64), so the consumer only reorders and writes.
+
+use crate::buffer_pool::BufferPool;
+use crate::primes::{SEGMENT_PRIMES_CAPACITY, SegmentPrimes};
+
+// Initial capacity for a fresh encoded buffer (20 digits + newline covers any u64)
+const SEGMENT_BYTES_CAPACITY: usize = SEGMENT_PRIMES_CAPACITY * 8;
+
+/// Builds the per-segment payload sent from workers to consumers
+pub trait SegmentBuilder: Sync {
+    type Output: Send;
+
+    /// Build a payload from a sieved odd-only bitmap covering [low, high]
+    fn build_from_bits(
+        &self,
+        bits: &[u64],
+        low: usize,
+        high: usize,
+        segment_id: usize,
+    ) -> Self::Output;
+
+    /// Build a payload from already known primes (the small primes segment)
+    fn build_from_primes(&self, primes: &[usize], segment_id: usize) -> Self::Output;
+
+    /// Bytes held by a payload, used for memory-based backpressure
+    fn byte_len(output: &Self::Output) -> usize;
+}
+
+/// Call `emit` for every prime in an odd-only bitmap starting at `low`, up to `high`
+#[inline]
+pub(crate) fn for_each_prime(bits: &[u64], low: usize, high: usize, mut emit: impl FnMut(usize)) {
+    for (word_idx, &word) in bits.iter().enumerate() {
+        let mut word = word;
+
+        while word != 0 {
+            let bit_idx = word.trailing_zeros() as usize;
+            let idx = word_idx * 64 + bit_idx;
+
+            let num = low + idx * 2;
+            if num <= high {
+                emit(num);
+            }
+
+            word &= word - 1; // Clear lowest set bit
+        }
+    }
+}
+
+/// Default path: unpack into recycled Vec<usize> (consumer formats)
+pub struct UnpackedSegments {
+    pub pool: BufferPool<usize>,
+}
+
+impl SegmentBuilder for UnpackedSegments {
+    type Output = SegmentPrimes;
+
+    fn build_from_bits(
+        &self,
+        bits: &[u64],
+        low: usize,
+        high: usize,
+        segment_id: usize,
+    ) -> SegmentPrimes {
+        let mut primes = self.pool.take(SEGMENT_PRIMES_CAPACITY);
+        for_each_prime(bits, low, high, |num| primes.push(num));
+        SegmentPrimes { primes, segment_id }
+    }
+
+    fn build_from_primes(&self, primes: &[usize], segment_id: usize) -> SegmentPrimes {
+        SegmentPrimes {
+            primes: primes.to_vec(),
+            segment_id,
+        }
+    }
+
+    fn byte_len(output: &SegmentPrimes) -> usize {
+        output.primes.len() * std::mem::size_of::<usize>()
+    }
+}
+
+/// Output encoding for preformatted segments
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SegmentEncoding {
+    /// One decimal prime per line (primes.txt)
+    Text,
+    /// 8 bytes per prime, little-endian (primes.bin)
+    Binary,
+}
+
+/// Segment already formatted into output bytes by a worker
+pub struct EncodedSegment {
+    pub bytes: Vec<u8>,
+    pub count: usize,
+    pub segment_id: usize,
+}
+
+/// Preformat path: encode into recycled byte buffers (consumer only writes)
+pub struct EncodedSegments {
+    pub pool: BufferPool<u8>,
+    pub encoding: SegmentEncoding,
+}
+
+impl EncodedSegments {
+    fn encode(&self, bytes: &mut Vec<u8>, num: usize, itoa_buf: &mut itoa::Buffer) {
+        match self.encoding {
+            SegmentEncoding::Text => {
+                bytes.extend_from_slice(itoa_buf.format(num).as_bytes());
+                bytes.push(b'\n');
+            }
+            SegmentEncoding::Binary => bytes.extend_from_slice(&(num as u64).to_le_bytes()),
+        }
+    }
+}
+
+impl SegmentBuilder for EncodedSegments {
+    type Output = EncodedSegment;
+
+    fn build_from_bits(
+        &self,
+        bits: &[u64],
+        low: usize,
+        high: usize,
+        segment_id: usize,
+    ) -> EncodedSegment {
+        let mut bytes = self.pool.take(SEGMENT_BYTES_CAPACITY);
+        let mut itoa_buf = itoa::Buffer::new();
+        let mut count = 0;
+
+        for_each_prime(bits, low, high, |num| {
+            self.encode(&mut bytes, num, &mut itoa_buf);
+            count += 1;
+        });
+
+        EncodedSegment {
+            bytes,
+            count,
+            segment_id,
+        }
+    }
+
+    fn build_from_primes(&self, primes: &[usize], segment_id: usize) -> EncodedSegment {
+        let mut bytes = self.pool.take(SEGMENT_BYTES_CAPACITY);
+        let mut itoa_buf = itoa::Buffer::new();
+
+        for &num in primes {
+            self.encode(&mut bytes, num, &mut itoa_buf);
+        }
+
+        EncodedSegment {
+            bytes,
+            count: primes.len(),
+            segment_id,
+        }
+    }
+
+    fn byte_len(output: &EncodedSegment) -> usize {
+        output.bytes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encoded_text_matches_unpacked_primes() {
+        // Odd-only bitmap starting at 11: 11, 13, 17, 19, 23 (15 and 21 cleared)
+        let bits = vec![0b11011011_u64];
+        let unpacked = UnpackedSegments {
+            pool: BufferPool::new(),
+        };
+        let encoded = EncodedSegments {
+            pool: BufferPool::new(),
+            encoding: SegmentEncoding::Text,
+        };
+
+        let primes = unpacked.build_from_bits(&bits, 11, 23, 1);
+        assert_eq!(primes.primes, vec![11, 13, 17, 19, 23]);
+
+        let text = encoded.build_from_bits(&bits, 11, 23, 1);
+        assert_eq!(text.count, 5);
+        assert_eq!(text.bytes, b"11\n13\n17\n19\n23\n");
+    }
+}
@@ -0,0 +1,290 @@
+// Parallel segment pipeline: workers -> routed bounded channels -> reordering consumers
+//
+// Variation 9 splits [low, limit] into fixed-size segments that workers claim from an
+// atomic counter, routes segment S (numbered from 1) to consumer (S - 1) % N and has each
+// consumer put its segments back in order before writing. With --deterministic, worker w
+// instead takes segments w, w + W, w + 2W, ... as variation 8 always does, so which worker
+// sieves what, and every log line that follows from it, is the same on every run. None of
+// that is specific to primes, so it lives here: the prime sieve supplies the per-segment
+// work, and the arithmetic-function sieves in arith.rs (φ, μ, σ) reuse the same workers,
+// routing, backpressure and progress reporting to stream their tables.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::thread;
+use tracing::debug;
+
+use crate::affinity::CorePlan;
+use crate::backpressure::Backpressure;
+use crate::storage_writer::Reorder;
+
+/// [low, limit] split into segments of `size` numbers; the last one is clamped to limit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SegmentPlan {
+    pub low: usize,
+    pub limit: usize,
+    pub size: usize,
+}
+
+impl SegmentPlan {
+    pub fn new(low: usize, limit: usize, size: usize) -> Self {
+        Self { low, limit, size }
+    }
+
+    pub fn total_segments(&self) -> usize {
+        if self.limit < self.low {
+            0
+        } else {
+            (self.limit - self.low + 1).div_ceil(self.size)
+        }
+    }
+
+    /// [low, high] covered by segment `index` (0-based)
+    pub fn bounds(&self, index: usize) -> (usize, usize) {
+        let low = self.low + index * self.size;
+        (low, (low + self.size - 1).min(self.limit))
+    }
+}
+
+/// Consumer (0-based) that segment `segment_id` (1-based) is routed to
+#[inline]
+pub fn route(segment_id: usize, num_consumers: usize) -> usize {
+    (segment_id - 1) % num_consumers
+}
+
+/// Threads and flow control shared by one run of the pipeline
+pub struct Pipeline<'a> {
+    pub num_workers: usize,
+    /// Segments handed to consumers so far (read by backpressure and the TUI)
+    pub total_sent: Arc<AtomicUsize>,
+    pub backpressure: Option<&'a Backpressure>,
+    pub pinning: Option<&'a CorePlan>,
+    /// Assign segments to workers statically instead of first come, first served
+    pub deterministic: bool,
+}
+
+impl Pipeline<'_> {
+    /// Build every segment of `plan` on the worker threads and send it to its consumer
+    ///
+    /// Each worker calls `scratch` once, after pinning, for its reusable buffers; `build` then
+    /// turns (scratch, low, high, segment_id) into the payload sent. `byte_len` sizes a payload
+    /// for backpressure. Returns once every segment is sent or all consumers have hung up.
+    pub fn run<T, S>(
+        &self,
+        plan: &SegmentPlan,
+        senders: Vec<SyncSender<T>>,
+        byte_len: impl Fn(&T) -> usize + Sync,
+        scratch: impl Fn() -> S + Sync,
+        build: impl Fn(&mut S, usize, usize, usize) -> T + Sync,
+    ) where
+        T: Send,
+    {
+        let num_consumers = senders.len();
+        if num_consumers == 0 {
+            return;
+        }
+        let total_segments = plan.total_segments();
+        let next_segment = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for worker_id in 0..self.num_workers {
+                let senders = senders.clone();
+                let (next_segment, byte_len, scratch, build) =
+                    (&next_segment, &byte_len, &scratch, &build);
+
+                scope.spawn(move || {
+                    // Pin before allocating so scratch buffers are first touched on this
+                    // core's node
+                    if let Some(pinning) = self.pinning {
+                        pinning.pin_worker(worker_id);
+                    }
+                    let mut state = scratch();
+                    let mut own_segment = worker_id;
+
+                    // Workers pull segments sequentially from atomic counter
+                    loop {
+                        let segment_idx = if self.deterministic {
+                            own_segment += self.num_workers;
+                            own_segment - self.num_workers
+                        } else {
+                            next_segment.fetch_add(1, Ordering::Relaxed)
+                        };
+                        if segment_idx >= total_segments {
+                            break;
+                        }
+                        let (seg_low, seg_high) = plan.bounds(segment_idx);
+
+                        // Segment numbering starts at 1 (variation 9 keeps 0 for the small primes)
+                        let segment_id = segment_idx + 1;
+                        let payload = build(&mut state, seg_low, seg_high, segment_id);
+
+                        // Adaptive mode: wait while consumers are too far behind for the
+                        // memory budget
+                        if let Some(backpressure) = self.backpressure {
+                            backpressure.wait_for_capacity(byte_len(&payload));
+                        }
+
+                        if senders[route(segment_id, num_consumers)]
+                            .send(payload)
+                            .is_err()
+                        {
+                            break; // Receiver dropped, stop this worker
+                        }
+
+                        // Increment send counter
+                        self.total_sent.fetch_add(1, Ordering::Relaxed);
+                        crate::progress::inc(1);
+                        crate::watchdog::wait_if_paused();
+                        crate::tui::record_worker_segment(worker_id);
+
+                        // Periodic memory reporting (every 1000 segments)
+                        if segment_idx.is_multiple_of(1000)
+                            && let Some((rss_mb, vm_mb)) = crate::storage::get_process_memory_mb()
+                        {
+                            debug!(
+                                "[Producer] Segment {} | Sent: {} | Process memory: RSS={:.2} MB, VM={:.2} MB",
+                                segment_idx,
+                                self.total_sent.load(Ordering::Relaxed),
+                                rss_mb,
+                                vm_mb
+                            );
+                        }
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Receive the segments routed to consumer `consumer_id` (1-based) and hand them to `write`
+/// in segment order; `id` reads a payload's segment id
+/// Returns the number of segments written
+pub fn consume_in_order<T>(
+    rx: Receiver<T>,
+    consumer_id: usize,
+    num_consumers: usize,
+    total_received: &AtomicUsize,
+    id: impl Fn(&T) -> usize,
+    mut write: impl FnMut(T),
+) -> usize {
+    let mut reorder = Reorder::new(consumer_id, num_consumers);
+    let mut written = 0;
+
+    for payload in rx {
+        total_received.fetch_add(1, Ordering::Relaxed);
+        reorder.insert(id(&payload), payload);
+        while let Some(payload) = reorder.pop_ready() {
+            write(payload);
+            written += 1;
+        }
+    }
+
+    // Only reached with a gap, which means a producer bug; keep the data anyway
+    while let Some(payload) = reorder.pop_remaining() {
+        write(payload);
+        written += 1;
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_plan_bounds_cover_range() {
+        let plan = SegmentPlan::new(1, 1000, 64);
+        assert_eq!(plan.total_segments(), 16);
+        assert_eq!(plan.bounds(0), (1, 64));
+        assert_eq!(plan.bounds(15), (961, 1000));
+        assert_eq!(SegmentPlan::new(11, 10, 64).total_segments(), 0);
+    }
+
+    #[test]
+    fn test_consumers_receive_their_segments_in_order() {
+        let plan = SegmentPlan::new(1, 10_000, 100);
+        let num_consumers = 3;
+        let total_received = AtomicUsize::new(0);
+        let pipeline = Pipeline {
+            num_workers: 4,
+            total_sent: Arc::new(AtomicUsize::new(0)),
+            backpressure: None,
+            pinning: None,
+            deterministic: false,
+        };
+
+        let received = thread::scope(|scope| {
+            let mut senders = Vec::new();
+            let mut handles = Vec::new();
+            for consumer_id in 1..=num_consumers {
+                let (tx, rx) = mpsc::sync_channel::<(usize, usize)>(4);
+                senders.push(tx);
+                let total_received = &total_received;
+                handles.push(scope.spawn(move || {
+                    let mut lows = Vec::new();
+                    consume_in_order(
+                        rx,
+                        consumer_id,
+                        num_consumers,
+                        total_received,
+                        |&(id, _)| id,
+                        |(_, low)| lows.push(low),
+                    );
+                    lows
+                }));
+            }
+            pipeline.run(&plan, senders, |_| 16, || (), |_, low, _, id| (id, low));
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        for (idx, lows) in received.iter().enumerate() {
+            let expected: Vec<usize> = (idx..plan.total_segments())
+                .step_by(num_consumers)
+                .map(|segment_idx| plan.bounds(segment_idx).0)
+                .collect();
+            assert_eq!(*lows, expected);
+        }
+        assert_eq!(total_received.load(Ordering::Relaxed), 100);
+    }
+
+    #[test]
+    fn test_deterministic_assigns_segments_statically() {
+        let plan = SegmentPlan::new(1, 10_000, 100);
+        let num_workers = 3;
+        let pipeline = Pipeline {
+            num_workers,
+            total_sent: Arc::new(AtomicUsize::new(0)),
+            backpressure: None,
+            pinning: None,
+            deterministic: true,
+        };
+        let (tx, rx) = mpsc::sync_channel::<(usize, thread::ThreadId)>(200);
+        pipeline.run(
+            &plan,
+            vec![tx],
+            |_| 16,
+            || (),
+            |_, _, _, id| (id, thread::current().id()),
+        );
+
+        // Segments w + 1, w + 1 + W, ... all come from one worker
+        let mut workers = vec![None; num_workers];
+        let mut count = 0;
+        for (id, worker) in rx {
+            let first = workers[(id - 1) % num_workers].get_or_insert(worker);
+            assert_eq!(*first, worker, "segment {}", id);
+            count += 1;
+        }
+        assert_eq!(count, plan.total_segments());
+        let workers: Vec<_> = workers.into_iter().flatten().collect();
+        assert_eq!(workers.len(), num_workers);
+        for (i, worker) in workers.iter().enumerate() {
+            assert!(!workers[i + 1..].contains(worker));
+        }
+    }
+}
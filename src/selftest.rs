@@ -0,0 +1,359 @@
+// Differential self-test (`nt selftest`)
+//
+// Runs `nt primes` for every variation, in both output formats and through the optional
+// write paths (preformat, async, direct I/O), each in its own scratch data directory. Every
+// run must produce the same primes as the first, byte for byte within a format, and the
+// shared result must agree with known values of π(10^k). Runs go through the real binary so
+// the whole pipeline is covered: sieve, channels, consumers and file commit.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+use tracing::{error, info};
+
+use crate::primes::SEGMENT_SIZE_NUMBERS;
+use crate::segment_format::SegmentEncoding;
+use crate::storage_writer::file_name;
+
+/// π(10^k) for k = 1..=12
+pub const PI_POWERS_OF_TEN: [(u64, u64); 12] = [
+    (10, 4),
+    (100, 25),
+    (1_000, 168),
+    (10_000, 1_229),
+    (100_000, 9_592),
+    (1_000_000, 78_498),
+    (10_000_000, 664_579),
+    (100_000_000, 5_761_455),
+    (1_000_000_000, 50_847_534),
+    (10_000_000_000, 455_052_511),
+    (100_000_000_000, 4_118_054_813),
+    (1_000_000_000_000, 37_607_912_018),
+];
+
+/// One `nt primes` invocation to compare
+pub struct Case {
+    pub variation: u32,
+    pub encoding: SegmentEncoding,
+    pub extra_args: &'static [&'static str],
+}
+
+impl Case {
+    fn label(&self) -> String {
+        let format = match self.encoding {
+            SegmentEncoding::Text => "text",
+            SegmentEncoding::Binary => "binary",
+        };
+        let mut label = format!("v{} {}", self.variation, format);
+        for arg in self.extra_args {
+            label.push(' ');
+            label.push_str(arg);
+        }
+        label
+    }
+}
+
+/// Every case run for `limit`; segmented variations need at least one full segment
+pub fn cases(limit: usize) -> Vec<Case> {
+    let last_variation = if limit >= SEGMENT_SIZE_NUMBERS { 9 } else { 4 };
+
+    let mut cases = Vec::new();
+    // Variation 10 (Sundaram) is not segmented and runs at any limit
+    let variations = (1..=last_variation).chain([10]);
+    for variation in variations {
+        for encoding in [SegmentEncoding::Text, SegmentEncoding::Binary] {
+            cases.push(Case {
+                variation,
+                encoding,
+                extra_args: &[],
+            });
+        }
+    }
+    if last_variation == 9 {
+        cases.push(Case {
+            variation: 8,
+            encoding: SegmentEncoding::Text,
+            extra_args: &["--preformat"],
+        });
+        cases.push(Case {
+            variation: 9,
+            encoding: SegmentEncoding::Text,
+            extra_args: &["--preformat"],
+        });
+        cases.push(Case {
+            variation: 9,
+            encoding: SegmentEncoding::Binary,
+            extra_args: &["--async-io"],
+        });
+        cases.push(Case {
+            variation: 9,
+            encoding: SegmentEncoding::Text,
+            extra_args: &["--direct-io", "--preallocate"],
+        });
+    }
+    cases
+}
+
+/// Output of one case, decoded and as written
+struct Output {
+    primes: Vec<u64>,
+    // Raw file contents; None for variation 9, whose shards interleave segments
+    bytes: Option<Vec<u8>>,
+}
+
+/// Run every case up to `limit` and report; returns whether all checks passed
+pub fn run(limit: usize) -> bool {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            error!("Error locating the nt binary: {}", e);
+            return false;
+        }
+    };
+    let scratch = std::env::temp_dir().join(format!("nt-selftest-{}", std::process::id()));
+
+    let cases = cases(limit);
+    if limit < SEGMENT_SIZE_NUMBERS {
+        info!(
+            "Limit below {}: skipping segmented variations 5-9",
+            SEGMENT_SIZE_NUMBERS
+        );
+    }
+    info!("Self-test up to {} ({} runs)", limit, cases.len());
+
+    // The first run is the reference; the first run of each format is its byte reference
+    let mut reference: Option<(String, Vec<u64>)> = None;
+    let mut text_bytes: Option<Vec<u8>> = None;
+    let mut binary_bytes: Option<Vec<u8>> = None;
+    let mut failures = 0;
+
+    for case in &cases {
+        let label = case.label();
+        let data_home = scratch.join(format!("case-{}", label.replace([' ', '-'], "")));
+        let start = Instant::now();
+
+        let output = match run_case(&exe, limit, case, &data_home) {
+            Ok(output) => output,
+            Err(e) => {
+                error!("FAIL {}: {}", label, e);
+                failures += 1;
+                continue;
+            }
+        };
+        let _ = fs::remove_dir_all(&data_home);
+
+        let mut problems = Vec::new();
+        match &reference {
+            None => reference = Some((label.clone(), output.primes.clone())),
+            Some((reference_label, primes)) => {
+                if let Some(problem) = compare_primes(primes, &output.primes) {
+                    problems.push(format!("differs from {}: {}", reference_label, problem));
+                }
+            }
+        }
+        if let Some(bytes) = output.bytes {
+            let format_reference = match case.encoding {
+                SegmentEncoding::Text => &mut text_bytes,
+                SegmentEncoding::Binary => &mut binary_bytes,
+            };
+            match format_reference {
+                None => *format_reference = Some(bytes),
+                Some(expected) if *expected != bytes => {
+                    problems.push("file is not byte-identical to the first run".to_string());
+                }
+                Some(_) => {}
+            }
+        }
+
+        if problems.is_empty() {
+            info!(
+                "ok   {:<36} {:>12} primes {:>8.2}s",
+                label,
+                output.primes.len(),
+                start.elapsed().as_secs_f64()
+            );
+        } else {
+            for problem in problems {
+                error!("FAIL {}: {}", label, problem);
+            }
+            failures += 1;
+        }
+    }
+    let _ = fs::remove_dir_all(&scratch);
+
+    // Known prime counts catch a bug every variation shares
+    if let Some((_, primes)) = &reference {
+        for &(power, expected) in PI_POWERS_OF_TEN.iter().filter(|(p, _)| *p <= limit as u64) {
+            let count = primes.partition_point(|&p| p <= power) as u64;
+            if count == expected {
+                info!("ok   π({}) = {}", power, count);
+            } else {
+                error!("FAIL π({}) = {}, expected {}", power, count, expected);
+                failures += 1;
+            }
+        }
+    }
+
+    if failures == 0 {
+        info!("All checks passed");
+    } else {
+        error!("{} checks failed", failures);
+    }
+    failures == 0
+}
+
+/// Run one case in its own data directory and read back what it wrote
+fn run_case(exe: &Path, limit: usize, case: &Case, data_home: &Path) -> io::Result<Output> {
+    let _ = fs::remove_dir_all(data_home);
+    fs::create_dir_all(data_home)?;
+
+    let mut command = Command::new(exe);
+    command
+        .env("XDG_DATA_HOME", data_home)
+        .args(["-q", "primes", &limit.to_string()])
+        .args(["--variation", &case.variation.to_string()])
+        .args(case.extra_args);
+    if case.encoding == SegmentEncoding::Binary {
+        command.arg("--binary");
+    }
+
+    let result = command.output()?;
+    if !result.status.success() || !result.stderr.is_empty() {
+        return Err(io::Error::other(format!(
+            "nt primes exited with {}: {}",
+            result.status,
+            String::from_utf8_lossy(&result.stderr).trim()
+        )));
+    }
+
+    let data_dir = data_home.join("nt");
+    if case.variation == 9 {
+        read_shards(&data_dir, case.encoding)
+    } else {
+        let bytes = fs::read(data_dir.join(file_name("primes", case.encoding)))?;
+        Ok(Output {
+            primes: decode(&bytes, case.encoding)?,
+            bytes: Some(bytes),
+        })
+    }
+}
+
+/// primes_small followed by every shard, in order
+fn read_shards(data_dir: &Path, encoding: SegmentEncoding) -> io::Result<Output> {
+    let mut primes = decode(
+        &fs::read(data_dir.join(file_name("primes_small", encoding)))?,
+        encoding,
+    )?;
+
+    let mut shards: Vec<PathBuf> = Vec::new();
+    for id in 1.. {
+        let path = data_dir.join(file_name(&format!("primes_{}", id), encoding));
+        if !path.exists() {
+            break;
+        }
+        shards.push(path);
+    }
+    if shards.is_empty() {
+        return Err(io::Error::other("no shard files written"));
+    }
+
+    // Consumers take segments round-robin, so each shard is sorted but they interleave
+    let mut rest = Vec::new();
+    for shard in &shards {
+        rest.extend(decode(&fs::read(shard)?, encoding)?);
+    }
+    rest.sort_unstable();
+    primes.extend(rest);
+
+    Ok(Output {
+        primes,
+        bytes: None,
+    })
+}
+
+fn decode(bytes: &[u8], encoding: SegmentEncoding) -> io::Result<Vec<u64>> {
+    match encoding {
+        SegmentEncoding::Binary => {
+            if !bytes.len().is_multiple_of(8) {
+                return Err(io::Error::other(format!(
+                    "binary output is {} bytes, not a multiple of 8",
+                    bytes.len()
+                )));
+            }
+            Ok(bytes
+                .chunks_exact(8)
+                .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+                .collect())
+        }
+        SegmentEncoding::Text => {
+            let text = std::str::from_utf8(bytes).map_err(io::Error::other)?;
+            text.lines()
+                .map(|line| {
+                    line.parse()
+                        .map_err(|_| io::Error::other(format!("bad line '{}'", line)))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Describe the first difference between two prime lists, or None if they are equal
+fn compare_primes(expected: &[u64], actual: &[u64]) -> Option<String> {
+    if let Some(index) = expected.iter().zip(actual).position(|(a, b)| a != b) {
+        return Some(format!(
+            "prime #{} is {}, expected {}",
+            index + 1,
+            actual[index],
+            expected[index]
+        ));
+    }
+    match actual.len().cmp(&expected.len()) {
+        std::cmp::Ordering::Equal => None,
+        std::cmp::Ordering::Greater => Some(format!(
+            "{} extra primes, first {}",
+            actual.len() - expected.len(),
+            actual[expected.len()]
+        )),
+        std::cmp::Ordering::Less => Some(format!(
+            "{} primes missing, first {}",
+            expected.len() - actual.len(),
+            expected[actual.len()]
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pi_table_matches_sieve() {
+        let primes = crate::primes::sieve(1_000_000);
+        for &(power, expected) in PI_POWERS_OF_TEN.iter().filter(|(p, _)| *p <= 1_000_000) {
+            let count = primes.partition_point(|&p| p as u64 <= power);
+            assert_eq!(count as u64, expected);
+        }
+    }
+
+    #[test]
+    fn test_compare_primes() {
+        assert_eq!(compare_primes(&[2, 3, 5], &[2, 3, 5]), None);
+        assert!(
+            compare_primes(&[2, 3, 5], &[2, 3, 7])
+                .unwrap()
+                .contains("#3")
+        );
+        assert!(
+            compare_primes(&[2, 3], &[2, 3, 5])
+                .unwrap()
+                .contains("extra")
+        );
+        assert!(
+            compare_primes(&[2, 3, 5], &[2, 3])
+                .unwrap()
+                .contains("missing")
+        );
+    }
+}
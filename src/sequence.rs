@@ -0,0 +1,110 @@
+// Digit-sequence generators (`nt sequence`)
+//
+// Each generator yields its terms as decimal digit strings, so the concatenation is a
+// single long digit string that `nt scan --stdin` searches for primes the same way it
+// searches the digits of π:
+//
+// - look-and-say: 1, 11, 21, 1211, 111221, ... each term reads the runs of the one before
+//   (lengths grow by Conway's constant ≈ 1.3036 per term)
+// - champernowne: 1, 2, 3, ... whose concatenation is Champernowne's constant
+// - copeland-erdos: 2, 3, 5, 7, 11, ... the primes, giving the Copeland–Erdős constant
+// - kolakoski: 1, 2, 2, 1, 1, 2, ... the 1/2 sequence that is its own run-length encoding
+
+use clap::ValueEnum;
+
+/// A sequence whose terms are digit strings
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DigitSequence {
+    /// Conway's look-and-say sequence from 1
+    LookAndSay,
+    /// The positive integers
+    Champernowne,
+    /// The primes
+    CopelandErdos,
+    /// The Kolakoski sequence of 1s and 2s
+    Kolakoski,
+}
+
+/// The term after `term` in the look-and-say sequence
+pub fn look_and_say(term: &str) -> String {
+    let bytes = term.as_bytes();
+    let mut next = String::with_capacity(bytes.len() * 2);
+    let mut i = 0;
+    while i < bytes.len() {
+        let run = bytes[i..].iter().take_while(|&&b| b == bytes[i]).count();
+        next.push_str(&run.to_string());
+        next.push(bytes[i] as char);
+        i += run;
+    }
+    next
+}
+
+/// The first `count` terms of the Kolakoski sequence
+pub fn kolakoski(count: usize) -> Vec<u8> {
+    let mut sequence: Vec<u8> = vec![1, 2, 2];
+    // sequence[i] is the length of the i-th run, and runs alternate 1s and 2s
+    let mut run = 2;
+    while sequence.len() < count {
+        let value = if run % 2 == 0 { 1 } else { 2 };
+        for _ in 0..sequence[run] {
+            sequence.push(value);
+        }
+        run += 1;
+    }
+    sequence.truncate(count);
+    sequence
+}
+
+/// The first `count` terms of `sequence` as digit strings
+pub fn terms(sequence: DigitSequence, count: usize) -> Vec<String> {
+    match sequence {
+        DigitSequence::LookAndSay => {
+            let mut terms: Vec<String> = Vec::with_capacity(count);
+            for i in 0..count {
+                let term = match i {
+                    0 => "1".to_string(),
+                    _ => look_and_say(&terms[i - 1]),
+                };
+                terms.push(term);
+            }
+            terms
+        }
+        DigitSequence::Champernowne => (1..=count).map(|n| n.to_string()).collect(),
+        DigitSequence::CopelandErdos => {
+            crate::primes::sieve(crate::primorial::nth_prime_bound(count))
+                .into_iter()
+                .take(count)
+                .map(|p| p.to_string())
+                .collect()
+        }
+        DigitSequence::Kolakoski => kolakoski(count)
+            .into_iter()
+            .map(|digit| digit.to_string())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequences() {
+        assert_eq!(
+            terms(DigitSequence::LookAndSay, 6),
+            ["1", "11", "21", "1211", "111221", "312211"]
+        );
+        assert_eq!(
+            terms(DigitSequence::Champernowne, 12).concat(),
+            "123456789101112"
+        );
+        assert_eq!(terms(DigitSequence::CopelandErdos, 6).concat(), "23571113");
+        // A000002
+        assert_eq!(
+            terms(DigitSequence::Kolakoski, 20).concat(),
+            "12211212212211211221"
+        );
+        // Term 50 of look-and-say has 894810 digits (A005341)
+        assert_eq!(terms(DigitSequence::LookAndSay, 50)[49].len(), 894_810);
+    }
+}
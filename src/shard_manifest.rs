@@ -0,0 +1,148 @@
+// Manifest of a sharded prime output (primes_manifest.json)
+//
+// Sharded runs (variation 9's consumers, or --shards in variations 6 and 8) split the primes
+// over primes_small plus primes_1 to primes_N, .txt or .bin. The small primes up to
+// sqrt(limit) go in primes_small and segment s in primes_((s - 1) % N + 1), so every shard
+// holds an increasing run of whole segments and readers merge them back in order. The
+// manifest records that layout with each file's prime count and size, so a set of shards
+// can be checked for completeness, or moved and put back together, without reading them.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::primes::SEGMENT_SIZE_NUMBERS;
+use crate::segment_format::SegmentEncoding;
+use crate::storage::{commit_output, get_nt_data_dir, staging_path};
+use crate::storage_writer::file_name;
+
+pub const MANIFEST_NAME: &str = "primes_manifest.json";
+
+/// One file of a sharded output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShardFile {
+    pub name: String,
+    pub primes: usize,
+    pub bytes: u64,
+}
+
+/// The files of a sharded output, primes_small first
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShardManifest {
+    pub encoding: SegmentEncoding,
+    pub limit: usize,
+    pub files: Vec<ShardFile>,
+}
+
+impl ShardManifest {
+    /// Describe the files in the data directory, given the primes in primes_small and then
+    /// in each of primes_1 to primes_N
+    pub fn new(encoding: SegmentEncoding, limit: usize, counts: &[usize]) -> Self {
+        let data_dir = get_nt_data_dir();
+        let files = counts
+            .iter()
+            .enumerate()
+            .map(|(i, &primes)| {
+                let stem = match i {
+                    0 => "primes_small".to_string(),
+                    _ => format!("primes_{}", i),
+                };
+                let name = file_name(&stem, encoding);
+                let bytes = fs::metadata(data_dir.join(&name)).map_or(0, |m| m.len());
+                ShardFile {
+                    name,
+                    primes,
+                    bytes,
+                }
+            })
+            .collect();
+        Self {
+            encoding,
+            limit,
+            files,
+        }
+    }
+
+    /// Shards besides primes_small
+    pub fn shards(&self) -> usize {
+        self.files.len().saturating_sub(1)
+    }
+
+    pub fn primes(&self) -> usize {
+        self.files.iter().map(|file| file.primes).sum()
+    }
+
+    /// The manifest as a JSON object
+    pub fn to_json(&self) -> String {
+        let encoding = match self.encoding {
+            SegmentEncoding::Text => "text",
+            SegmentEncoding::Binary => "binary",
+        };
+        let mut json = String::from("{\n");
+        let _ = writeln!(json, "  \"encoding\": \"{}\",", encoding);
+        let _ = writeln!(json, "  \"limit\": {},", self.limit);
+        let _ = writeln!(json, "  \"primes\": {},", self.primes());
+        let _ = writeln!(json, "  \"shards\": {},", self.shards());
+        let _ = writeln!(json, "  \"segment_numbers\": {},", SEGMENT_SIZE_NUMBERS);
+        let _ = writeln!(
+            json,
+            "  \"routing\": \"primes up to sqrt(limit) in primes_small, segment s in primes_((s - 1) % shards + 1)\","
+        );
+        let entries: Vec<String> = self
+            .files
+            .iter()
+            .map(|file| {
+                format!(
+                    "    {{ \"name\": \"{}\", \"primes\": {}, \"bytes\": {} }}",
+                    file.name, file.primes, file.bytes
+                )
+            })
+            .collect();
+        let _ = writeln!(json, "  \"files\": [\n{}\n  ]", entries.join(",\n"));
+        json.push_str("}\n");
+        json
+    }
+
+    /// Write primes_manifest.json to the data directory
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = get_nt_data_dir().join(MANIFEST_NAME);
+        fs::write(staging_path(&path), self.to_json())?;
+        commit_output(&path)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_json() {
+        let manifest = ShardManifest {
+            encoding: SegmentEncoding::Text,
+            limit: 1000,
+            files: vec![
+                ShardFile {
+                    name: "primes_small.txt".to_string(),
+                    primes: 11,
+                    bytes: 31,
+                },
+                ShardFile {
+                    name: "primes_1.txt".to_string(),
+                    primes: 157,
+                    bytes: 612,
+                },
+            ],
+        };
+        assert_eq!(manifest.shards(), 1);
+        assert_eq!(manifest.primes(), 168);
+        let json = manifest.to_json();
+        assert!(json.starts_with("{\n  \"encoding\": \"text\",\n  \"limit\": 1000,"));
+        assert!(json.contains("\"primes\": 168,"));
+        assert!(json.contains(
+            "    { \"name\": \"primes_small.txt\", \"primes\": 11, \"bytes\": 31 },\n    { \"name\": \"primes_1.txt\""
+        ));
+        assert!(json.ends_with("  ]\n}\n"));
+    }
+}
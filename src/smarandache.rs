@@ -0,0 +1,108 @@
+// Smarandache–Wellin numbers (`nt smarandache`)
+//
+// The kth Smarandache–Wellin number concatenates the first k primes: 2, 23, 235, 2357,
+// 235711, ... The known primes among them are at k = 1, 2, 4, 128, 174, 342, 435, 1429
+// (A046035), and the next is past k = 10^4 if it exists. A number and its digit sum agree
+// mod 3, so the kth concatenation is divisible by 3 exactly when p_1 + ... + p_k is, and
+// about a third of the k are ruled out by a running sum before any big-integer work.
+//
+// The digits of the longest concatenation are built once; workers take k in turn, parse
+// that prefix and run Baillie–PSW (bpsw.rs). The tests dominate: the kth number has about
+// k ln k / ln 10 digits and the cost of a test grows faster than the square of that.
+
+use rug::Integer;
+
+use crate::bpsw;
+use crate::parallel::parallel_chunks;
+use crate::primes;
+use crate::primorial::nth_prime_bound;
+use crate::progress;
+
+/// A Smarandache–Wellin number that tested (probably) prime
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WellinPrime {
+    /// Number of primes concatenated
+    pub terms: usize,
+    /// The last of them
+    pub last_prime: usize,
+    pub digits: usize,
+}
+
+/// The first `count` primes concatenated
+pub fn concatenation(count: usize) -> String {
+    primes::base_primes(nth_prime_bound(count))
+        .into_iter()
+        .take(count)
+        .map(|p| p.to_string())
+        .collect()
+}
+
+/// Every k <= max_terms whose concatenation of the first k primes is a probable prime
+pub fn prime_terms(max_terms: usize, workers: usize, show_progress: bool) -> Vec<WellinPrime> {
+    let first: Vec<usize> = primes::base_primes(nth_prime_bound(max_terms))
+        .into_iter()
+        .take(max_terms)
+        .collect();
+
+    // Digits and candidates: (k, last prime, prefix length) with k's sum not divisible by 3
+    let mut digits = String::new();
+    let mut candidates = Vec::new();
+    let mut sum_mod_3 = 0;
+    for (i, &p) in first.iter().enumerate() {
+        digits.push_str(&p.to_string());
+        sum_mod_3 = (sum_mod_3 + p) % 3;
+        if sum_mod_3 != 0 {
+            candidates.push((i + 1, p, digits.len()));
+        }
+    }
+    if show_progress {
+        progress::start(candidates.len() as u64, "candidates");
+    }
+
+    let mut found = Vec::new();
+    if !candidates.is_empty() {
+        parallel_chunks(
+            0..=candidates.len() - 1,
+            1,
+            workers,
+            |index, _| {
+                let (terms, last_prime, len) = candidates[index];
+                let value: Integer = digits[..len].parse().unwrap();
+                let prime = bpsw::is_probable_prime(&value).then_some(WellinPrime {
+                    terms,
+                    last_prime,
+                    digits: len,
+                });
+                progress::inc(1);
+                prime
+            },
+            |prime| found.extend(prime),
+        );
+    }
+    progress::finish();
+
+    found.sort_by_key(|prime| prime.terms);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concatenation() {
+        assert_eq!(concatenation(6), "23571113");
+        assert_eq!(concatenation(0), "");
+    }
+
+    #[test]
+    fn test_prime_terms_match_oeis() {
+        // A046035
+        let found = prime_terms(200, 3, false);
+        let terms: Vec<usize> = found.iter().map(|prime| prime.terms).collect();
+        assert_eq!(terms, [1, 2, 4, 128, 174]);
+        assert_eq!(found[2].last_prime, 7);
+        assert_eq!(found[3].last_prime, 719);
+        assert_eq!(found[3].digits, 355);
+    }
+}
@@ -0,0 +1,319 @@
+// Smallest-prime-factor tables (`nt spf build / query`)
+//
+// With spf(n) for every n up to a limit, factoring any n in range is a handful of lookups
+// and divisions. The table only stores odd n (even n have spf 2), and instead of the factor
+// itself each entry holds its index among the odd primes <= sqrt(limit), with 0 meaning n
+// is prime (a composite's spf never exceeds its square root). That index fits one byte up
+// to limit ~2.6M and two bytes up to ~6.7 × 10^11, so a table costs 1 byte per number
+// where a plain u32 per n would take 4. Building streams fixed-size segments to disk, and
+// saved tables are reopened with mmap, so a query only touches the pages it reads.
+//
+// File layout (little-endian): b"NTSPF001", limit: u64, width: u64 (bytes per entry),
+// prime count: u64, the odd primes <= sqrt(limit) as u32, then one entry per odd n.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::PrimeSet;
+#[cfg(unix)]
+use crate::mmap::MappedFile;
+#[cfg(feature = "native")]
+use crate::storage::{commit_output, staging_path};
+
+const MAGIC: &[u8; 8] = b"NTSPF001";
+const HEADER_BYTES: usize = 32;
+
+// Odd numbers per build segment (entries are sieved in a u32 buffer of this length)
+const SEGMENT_ODDS: usize = 1 << 20;
+
+/// Smallest prime factor of every n <= limit
+pub struct SpfTable {
+    limit: usize,
+    // Bytes per entry: 1, 2 or 4
+    width: usize,
+    // Odd primes <= sqrt(limit); entry k > 0 means spf = primes[k - 1]
+    primes: Vec<u32>,
+    entries: Entries,
+}
+
+/// Entry bytes either built in memory or mapped from a saved table
+enum Entries {
+    Owned(Vec<u8>),
+    #[cfg(unix)]
+    // The entries start at the offset
+    Mapped(MappedFile, usize),
+}
+
+impl Entries {
+    fn bytes(&self) -> &[u8] {
+        match self {
+            Entries::Owned(bytes) => bytes,
+            #[cfg(unix)]
+            Entries::Mapped(mapped, offset) => &mapped.bytes()[*offset..],
+        }
+    }
+}
+
+/// Odd primes <= sqrt(limit) and the entry width they need
+fn index_primes(limit: usize) -> (Vec<u32>, usize) {
+    let primes: Vec<u32> = PrimeSet::new(limit.isqrt())
+        .iter()
+        .skip(1)
+        .map(|p| p as u32)
+        .collect();
+    let width = match primes.len() {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        _ => 4,
+    };
+    (primes, width)
+}
+
+/// Entries for the odd numbers 2i + 1 with i in [first, first + count), appended to `out`
+fn sieve_segment(primes: &[u32], width: usize, first: usize, count: usize, out: &mut Vec<u8>) {
+    let mut indices = vec![0u32; count];
+    let low = 2 * first + 1;
+    let high = 2 * (first + count - 1) + 1;
+
+    // Increasing order, so the first prime to mark an entry is its smallest factor
+    for (k, &p) in primes.iter().enumerate() {
+        let p = p as usize;
+        if p * p > high {
+            break;
+        }
+        // First odd multiple of p that is >= max(p², low)
+        let mut multiple = (p * p).max(low.div_ceil(p) * p);
+        if multiple.is_multiple_of(2) {
+            multiple += p;
+        }
+        let mut idx = (multiple - low) / 2;
+        while idx < count {
+            if indices[idx] == 0 {
+                indices[idx] = k as u32 + 1;
+            }
+            idx += p;
+        }
+    }
+
+    for index in indices {
+        out.extend_from_slice(&index.to_le_bytes()[..width]);
+    }
+}
+
+impl SpfTable {
+    /// Sieve the table for every n up to and including `limit` in memory
+    pub fn new(limit: usize) -> Self {
+        let (primes, width) = index_primes(limit);
+        let odd_count = limit.div_ceil(2);
+        let mut bytes = Vec::with_capacity(odd_count * width);
+        for first in (0..odd_count).step_by(SEGMENT_ODDS) {
+            let count = SEGMENT_ODDS.min(odd_count - first);
+            sieve_segment(&primes, width, first, count, &mut bytes);
+        }
+        Self {
+            limit,
+            width,
+            primes,
+            entries: Entries::Owned(bytes),
+        }
+    }
+
+    /// Sieve the table up to `limit` straight into `path`, one segment at a time
+    /// The file is staged beside `path` and only moved into place once complete
+    /// Returns the file size in bytes
+    #[cfg(feature = "native")]
+    pub fn build(limit: usize, path: &Path) -> io::Result<u64> {
+        let (primes, width) = index_primes(limit);
+        let odd_count = limit.div_ceil(2);
+
+        let mut writer = BufWriter::with_capacity(1 << 20, File::create(staging_path(path))?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(limit as u64).to_le_bytes())?;
+        writer.write_all(&(width as u64).to_le_bytes())?;
+        writer.write_all(&(primes.len() as u64).to_le_bytes())?;
+        for p in &primes {
+            writer.write_all(&p.to_le_bytes())?;
+        }
+
+        let mut bytes = Vec::with_capacity(SEGMENT_ODDS * width);
+        for first in (0..odd_count).step_by(SEGMENT_ODDS) {
+            let count = SEGMENT_ODDS.min(odd_count - first);
+            bytes.clear();
+            sieve_segment(&primes, width, first, count, &mut bytes);
+            writer.write_all(&bytes)?;
+        }
+        writer.flush()?;
+        drop(writer);
+        commit_output(path)?;
+        Ok((HEADER_BYTES + primes.len() * 4 + odd_count * width) as u64)
+    }
+
+    /// Write the table to `path` so it can be reopened with `open`
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(self.limit as u64).to_le_bytes())?;
+        writer.write_all(&(self.width as u64).to_le_bytes())?;
+        writer.write_all(&(self.primes.len() as u64).to_le_bytes())?;
+        for p in &self.primes {
+            writer.write_all(&p.to_le_bytes())?;
+        }
+        writer.write_all(self.entries.bytes())?;
+        writer.flush()
+    }
+
+    /// Open a table written by `build` or `save`, memory-mapping its entries where supported
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+
+        #[cfg(unix)]
+        {
+            let mapped = MappedFile::map(&file)?;
+            let (limit, width, primes) = parse_header(mapped.bytes())?;
+            let offset = HEADER_BYTES + primes.len() * 4;
+            Self::check_entry_bytes(limit, width, mapped.bytes().len() - offset)?;
+            Ok(Self {
+                limit,
+                width,
+                primes,
+                entries: Entries::Mapped(mapped, offset),
+            })
+        }
+
+        #[cfg(not(unix))]
+        {
+            use std::io::Read;
+
+            let mut bytes = Vec::new();
+            let mut file = file;
+            file.read_to_end(&mut bytes)?;
+            let (limit, width, primes) = parse_header(&bytes)?;
+            let entries = bytes.split_off(HEADER_BYTES + primes.len() * 4);
+            Self::check_entry_bytes(limit, width, entries.len())?;
+            Ok(Self {
+                limit,
+                width,
+                primes,
+                entries: Entries::Owned(entries),
+            })
+        }
+    }
+
+    fn check_entry_bytes(limit: usize, width: usize, bytes: usize) -> io::Result<()> {
+        if bytes != limit.div_ceil(2) * width {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "spf table file is truncated or has trailing data",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Largest number this table can answer for
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Smallest prime factor of `n` (n itself when prime); `n` must be in [2, limit]
+    pub fn smallest_factor(&self, n: usize) -> usize {
+        assert!(
+            (2..=self.limit).contains(&n),
+            "{} is outside the spf table range [2, {}]",
+            n,
+            self.limit
+        );
+        if n.is_multiple_of(2) {
+            return 2;
+        }
+        let start = n / 2 * self.width;
+        let mut index = [0u8; 4];
+        index[..self.width].copy_from_slice(&self.entries.bytes()[start..start + self.width]);
+        match u32::from_le_bytes(index) {
+            0 => n,
+            k => self.primes[k as usize - 1] as usize,
+        }
+    }
+
+    /// Prime factorization of `n` as (prime, exponent) pairs in increasing order
+    /// `n` must be <= limit; 0 and 1 have no factors
+    pub fn factorize(&self, n: usize) -> Vec<(usize, u32)> {
+        let mut factors: Vec<(usize, u32)> = Vec::new();
+        let mut n = n;
+        while n > 1 {
+            let p = self.smallest_factor(n);
+            n /= p;
+            match factors.last_mut() {
+                Some((last, exp)) if *last == p => *exp += 1,
+                _ => factors.push((p, 1)),
+            }
+        }
+        factors
+    }
+}
+
+/// (limit, width, primes) from a saved table's header
+fn parse_header(bytes: &[u8]) -> io::Result<(usize, usize, Vec<u32>)> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not an spf table file");
+    if bytes.len() < HEADER_BYTES || &bytes[..8] != MAGIC {
+        return Err(invalid());
+    }
+    let field = |i: usize| u64::from_le_bytes(bytes[8 * i..8 * i + 8].try_into().unwrap()) as usize;
+    let (limit, width, prime_count) = (field(1), field(2), field(3));
+    if ![1, 2, 4].contains(&width) || bytes.len() < HEADER_BYTES + prime_count * 4 {
+        return Err(invalid());
+    }
+    let primes = bytes[HEADER_BYTES..HEADER_BYTES + prime_count * 4]
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    Ok((limit, width, primes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::factor;
+
+    #[test]
+    fn test_factorizations_match_trial_division() {
+        for limit in [0, 1, 2, 9, 10, 1000, 70_000] {
+            let table = SpfTable::new(limit);
+            for n in 0..=limit {
+                let expected: Vec<(usize, u32)> = factor::factorize(n as u64)
+                    .into_iter()
+                    .map(|(p, e)| (p as usize, e))
+                    .collect();
+                assert_eq!(table.factorize(n), expected, "n {}", n);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "native")]
+    fn test_built_table_reopens() {
+        // More than 255 odd primes up to sqrt(limit), so entries need two bytes
+        let limit = 3_000_017;
+        let path = std::env::temp_dir().join(format!("nt_spf_{}.bin", std::process::id()));
+        let size = SpfTable::build(limit, &path).unwrap();
+        let table = SpfTable::open(&path).unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), size);
+        std::fs::remove_file(&path).unwrap();
+
+        let in_memory = SpfTable::new(limit);
+        assert_eq!(table.limit(), limit);
+        assert_eq!(table.width, 2);
+        for n in (2..=limit).step_by(997).chain([limit - 1, limit]) {
+            assert_eq!(
+                table.smallest_factor(n),
+                in_memory.smallest_factor(n),
+                "n {}",
+                n
+            );
+        }
+        assert_eq!(table.factorize(2_999_999), [(2_999_999, 1)]);
+        // 1621 is the 256th odd prime, the first index past one byte
+        assert_eq!(table.factorize(2_993_987), [(1621, 1), (1847, 1)]);
+        assert_eq!(table.factorize(2_999_998), [(2, 1), (211, 1), (7109, 1)]);
+    }
+}
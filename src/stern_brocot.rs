@@ -0,0 +1,104 @@
+// Paths in the Stern–Brocot tree (`nt stern-brocot`)
+//
+// Every positive rational appears exactly once in the tree, which starts at 1/1 between
+// 0/1 and 1/0 and gives each node the mediant of its two bounds. Going right replaces the
+// left bound with the node, going left replaces the right bound. The path to p/q is read
+// off the continued fraction [a_0; a_1, ..., a_k] of p/q: R^a_0 L^a_1 R^a_2 ... with the
+// last run one shorter, so the path to 1/1000000 is 999999 steps but only one run, and
+// the path to any u64 fraction has fewer than a hundred runs.
+
+use crate::rational::Ratio;
+
+/// Which child to move to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn letter(self) -> char {
+        match self {
+            Direction::Left => 'L',
+            Direction::Right => 'R',
+        }
+    }
+}
+
+/// A run of moves in one direction and the node it ends on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Run {
+    pub direction: Direction,
+    pub steps: u64,
+    pub node: Ratio,
+}
+
+/// The runs from the root 1/1 down to x > 0 (none for 1/1 itself)
+pub fn path(x: Ratio) -> Vec<Run> {
+    assert!(x.num() > 0, "0 is not in the Stern–Brocot tree");
+    let mut runs = Vec::new();
+    // Bounds as (num, den); 1/0 stands in for infinity
+    let (mut left, mut right) = ((0u64, 1u64), (1u64, 0u64));
+    let (mut p, mut q) = (x.num(), x.den());
+    let mut direction = Direction::Right;
+    while q != 0 {
+        let mut steps = p / q;
+        (p, q) = (q, p % q);
+        if q == 0 {
+            // The node is reached one step before the final bound
+            steps -= 1;
+        }
+        if steps > 0 {
+            match direction {
+                Direction::Right => left = (left.0 + steps * right.0, left.1 + steps * right.1),
+                Direction::Left => right = (right.0 + steps * left.0, right.1 + steps * left.1),
+            }
+            let node = Ratio::new(left.0 + right.0, left.1 + right.1).unwrap();
+            runs.push(Run {
+                direction,
+                steps,
+                node,
+            });
+        }
+        direction = match direction {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        };
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letters(x: &str) -> String {
+        path(x.parse().unwrap())
+            .iter()
+            .map(|run| {
+                run.direction
+                    .letter()
+                    .to_string()
+                    .repeat(run.steps as usize)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_paths() {
+        assert_eq!(letters("1/1"), "");
+        assert_eq!(letters("3/7"), "LLRR");
+        assert_eq!(letters("5/2"), "RRL");
+        assert_eq!(letters("1/4"), "LLL");
+
+        let runs = path("3/7".parse().unwrap());
+        let nodes: Vec<String> = runs.iter().map(|run| run.node.to_string()).collect();
+        assert_eq!(nodes, ["1/3", "3/7"]);
+
+        // Consecutive Fibonacci numbers alternate one step at a time
+        let x = Ratio::new(12_200_160_415_121_876_738, 7_540_113_804_746_346_429).unwrap();
+        let runs = path(x);
+        assert_eq!(runs.len(), 91);
+        assert_eq!(runs.last().unwrap().node, x);
+    }
+}
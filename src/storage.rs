@@ -1,14 +1,25 @@
 use chrono::Local;
-use std::collections::BTreeMap;
 use std::env;
 use std::fs::{self, OpenOptions};
-use std::io::{BufRead, BufReader, BufWriter, Write};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::Receiver;
+use tracing::{debug, error, info, warn};
 
-use crate::primes::{SegmentData, SegmentPrimes};
+use crate::buffer_pool::BufferPool;
+use crate::gaps::GapStats;
+use crate::primes::{SegmentData, SegmentPrimes, estimate_prime_count_upper};
+use crate::segment_format::{EncodedSegment, SegmentEncoding};
+use crate::shard_manifest::{MANIFEST_NAME, ShardManifest};
+use crate::storage_direct::BinaryOutputOptions;
+use crate::storage_writer::{PrimeWriter, Reorder, file_name};
+use crate::throughput::{Meter, Throughput};
+
+// Outputs are written to a temp file and renamed over the old one when complete, so a
+// crashed run leaves the previous dataset intact
+static ATOMIC_WRITES: AtomicBool = AtomicBool::new(true);
 
 /// Read current process memory usage from /proc/self/status
 /// Returns (VmRSS in MB, VmSize in MB) or None if unable to read
@@ -39,21 +50,112 @@ pub fn get_process_memory_mb() -> Option<(f64, f64)> {
     Some((vm_rss_kb? / 1024.0, vm_size_kb? / 1024.0))
 }
 
-/// Remove all primes_*.bin files from the data directory
-/// Used to clean up before variation 9 runs to avoid leftover files from previous runs
-pub fn cleanup_prime_files() {
-    if let Ok(data_dir) = get_nt_data_dir().canonicalize() {
-        if let Ok(entries) = fs::read_dir(&data_dir) {
-            for entry in entries.flatten() {
-                if let Some(filename) = entry.file_name().to_str() {
-                    if filename.starts_with("primes_") && filename.ends_with(".bin") {
-                        if let Err(e) = fs::remove_file(entry.path()) {
-                            eprintln!("Warning: Could not remove old file {}: {}", filename, e);
-                        }
-                    }
-                }
-            }
+/// Write outputs in place instead of through a temp file (--no-atomic)
+/// Halves peak disk use on huge runs, at the cost of losing the old file if the run dies
+pub fn disable_atomic_writes() {
+    ATOMIC_WRITES.store(false, Ordering::Relaxed);
+}
+
+/// Where to write the output file `path`: `<name>.tmp` beside it, or `path` itself with --no-atomic
+/// Pair with `commit_output` once the file is complete
+pub fn staging_path(path: &Path) -> PathBuf {
+    if !ATOMIC_WRITES.load(Ordering::Relaxed) {
+        return path.to_path_buf();
+    }
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Atomically replace `path` with its finished staging file
+/// On failure the temp file is left in place so the new data is not lost
+pub fn commit_output(path: &Path) -> std::io::Result<()> {
+    let staged = staging_path(path);
+    if staged == path {
+        return Ok(());
+    }
+    fs::rename(&staged, path).map_err(|e| {
+        std::io::Error::new(
+            e.kind(),
+            format!("moving {} into place: {}", staged.display(), e),
+        )
+    })
+}
+
+/// Upper bound on the bytes written for every prime <= limit
+/// Binary is 8 bytes per prime; text is at most as many digits as `limit` plus a newline
+pub fn estimate_output_bytes(limit: usize, binary: bool) -> u64 {
+    let bytes_per_prime = if binary {
+        8
+    } else {
+        limit.max(1).ilog10() as u64 + 2
+    };
+    estimate_prime_count_upper(limit) as u64 * bytes_per_prime
+}
+
+/// Bytes available to unprivileged writes on the filesystem holding `path`
+#[cfg(unix)]
+pub fn available_space(path: &Path) -> std::io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn available_space(_path: &Path) -> std::io::Result<u64> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "free space probe is only implemented on unix",
+    ))
+}
+
+/// Check the data directory has room for `required_bytes` before a long run starts
+/// Returns false when the run should be aborted; with `force` a shortfall only warns
+pub fn check_disk_space(required_bytes: u64, force: bool) -> bool {
+    let data_dir = get_nt_data_dir();
+    if let Err(e) = fs::create_dir_all(&data_dir) {
+        warn!("Warning: Could not create data directory: {}", e);
+        return true;
+    }
+
+    let available = match available_space(&data_dir) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            debug!("Skipping disk space check: {}", e);
+            return true;
         }
+    };
+
+    let to_gb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0 * 1024.0);
+    debug!(
+        "Disk space: {:.2} GB needed (estimate), {:.2} GB available in {}",
+        to_gb(required_bytes),
+        to_gb(available),
+        data_dir.display()
+    );
+    if available >= required_bytes {
+        return true;
+    }
+
+    let message = format!(
+        "Output needs up to {:.2} GB but only {:.2} GB is free in {}",
+        to_gb(required_bytes),
+        to_gb(available),
+        data_dir.display()
+    );
+    if force {
+        warn!("Warning: {} (continuing because of --force)", message);
+        true
+    } else {
+        error!("{}", message);
+        error!("Free some space (see `nt data size`) or pass --force to run anyway.");
+        false
     }
 }
 
@@ -77,23 +179,143 @@ pub fn get_nt_data_dir() -> PathBuf {
     xdg_data_home.join("nt")
 }
 
-pub fn save_property(number: usize, property: &str) -> std::io::Result<()> {
+/// The stored prime file a command reads when not given one: primes.bin when it exists,
+/// primes.txt otherwise
+pub fn default_prime_file() -> PathBuf {
+    let dir = get_nt_data_dir();
+    let binary = dir.join("primes.bin");
+    if binary.exists() {
+        binary
+    } else {
+        dir.join("primes.txt")
+    }
+}
+
+/// Properties recorded for `number`, one per line of `<number>.txt`
+pub fn load_properties(number: usize) -> std::io::Result<Vec<String>> {
+    let path = get_nt_data_dir().join(format!("{}.txt", number));
+    match fs::read_to_string(&path) {
+        Ok(content) => Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Record `property` for `number` in `<number>.txt`, keeping the properties already there
+/// Returns false if the number already had it
+pub fn save_property(number: usize, property: &str) -> std::io::Result<bool> {
+    if property.is_empty() || property.contains(char::is_whitespace) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("invalid property name '{}'", property),
+        ));
+    }
     let data_dir = get_nt_data_dir();
     fs::create_dir_all(&data_dir)?;
 
-    let filename = format!("{}.txt", number);
-    let path = data_dir.join(&filename);
+    let mut properties = load_properties(number)?;
+    if properties.iter().any(|existing| existing == property) {
+        return Ok(false);
+    }
+    properties.push(property.to_string());
+
+    // The whole file is replaced at once, so an interrupted tag never loses earlier ones
+    let path = data_dir.join(format!("{}.txt", number));
+    let staged = staging_path(&path);
+    fs::write(&staged, properties.join("\n") + "\n")?;
+    if staged != path {
+        fs::rename(&staged, &path)?;
+    }
+    Ok(true)
+}
 
-    if path.exists() {
-        if let Ok(content) = fs::read_to_string(&path) {
-            if content.contains(property) {
-                return Ok(());
-            }
+/// What a batch of tags changed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TagReport {
+    /// Numbers that got the property
+    pub tagged: usize,
+    /// Numbers that already had it
+    pub already: usize,
+}
+
+/// Record `property` for every number in `numbers`
+/// Tagging is idempotent, so rerunning an interrupted batch only adds the missing tags
+pub fn tag_all(
+    numbers: impl IntoIterator<Item = usize>,
+    property: &str,
+) -> std::io::Result<TagReport> {
+    let mut report = TagReport::default();
+    for number in numbers {
+        if save_property(number, property)? {
+            report.tagged += 1;
+        } else {
+            report.already += 1;
         }
     }
+    Ok(report)
+}
 
-    fs::write(&path, property)?;
-    Ok(())
+/// Numbers in `low..=high` whose property file holds every one of `properties`, in order
+pub fn query_properties(
+    properties: &[String],
+    low: usize,
+    high: usize,
+) -> std::io::Result<Vec<usize>> {
+    let data_dir = get_nt_data_dir();
+    if !data_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut found = Vec::new();
+    for entry in fs::read_dir(&data_dir)? {
+        let name = entry?.file_name();
+        let Some(number) = name
+            .to_str()
+            .and_then(|name| name.strip_suffix(".txt"))
+            .filter(|stem| stem.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|stem| stem.parse::<usize>().ok())
+        else {
+            continue;
+        };
+        if number < low || number > high {
+            continue;
+        }
+        let stored = load_properties(number)?;
+        if properties.iter().all(|property| stored.contains(property)) {
+            found.push(number);
+        }
+    }
+    found.sort_unstable();
+    Ok(found)
+}
+
+/// Numbers listed one per line in `path`; blank lines and lines starting with # are skipped
+pub fn read_number_list(path: &Path) -> std::io::Result<Vec<usize>> {
+    read_numbers(BufReader::new(fs::File::open(path)?))
+}
+
+/// `read_number_list` for any reader, such as stdin
+pub fn read_numbers(reader: impl BufRead) -> std::io::Result<Vec<usize>> {
+    let mut numbers = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let number = line.parse().map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("line {}: '{}' is not a number", i + 1, line),
+            )
+        })?;
+        numbers.push(number);
+    }
+    Ok(numbers)
 }
 
 pub fn save_all_primes(primes: &[usize]) -> std::io::Result<()> {
@@ -107,13 +329,21 @@ pub fn save_all_primes(primes: &[usize]) -> std::io::Result<()> {
         .collect::<Vec<String>>()
         .join("\n");
 
-    fs::write(&primes_path, primes_text)?;
-    Ok(())
+    fs::write(staging_path(&primes_path), primes_text)?;
+    commit_output(&primes_path)
 }
+/// Load every prime from primes.txt into memory
+/// Falls back to the variation 9 shards when they are newer than primes.txt (or it is missing)
 pub fn load_all_primes() -> std::io::Result<Vec<usize>> {
     let data_dir = get_nt_data_dir();
     let primes_path = data_dir.join("primes.txt");
 
+    if let Some((_, shards_time)) = newest_shards(&data_dir)
+        && modified(&primes_path).is_none_or(|text_time| shards_time > text_time)
+    {
+        return Ok(read_sharded_primes()?.collect());
+    }
+
     let content = fs::read_to_string(&primes_path)?;
     let primes = content
         .lines()
@@ -123,11 +353,219 @@ pub fn load_all_primes() -> std::io::Result<Vec<usize>> {
     Ok(primes)
 }
 
+/// Primes read one at a time, with read errors passed on rather than ending the stream
+pub type PrimeStream = Box<dyn Iterator<Item = std::io::Result<usize>>>;
+
+/// Stream primes from primes.txt (or primes.bin when `binary` is set) one at a time
+/// Avoids loading multi-GB prime files into memory for export and analysis passes
+pub fn stream_primes(binary: bool) -> std::io::Result<PrimeStream> {
+    let encoding = if binary {
+        SegmentEncoding::Binary
+    } else {
+        SegmentEncoding::Text
+    };
+    let path = get_nt_data_dir().join(file_name("primes", encoding));
+    let reader = BufReader::with_capacity(256 * 1024, fs::File::open(path)?);
+    Ok(prime_stream(reader, encoding))
+}
+
+/// The primes in `reader`, in either format
+fn prime_stream(reader: impl BufRead + 'static, encoding: SegmentEncoding) -> PrimeStream {
+    match encoding {
+        SegmentEncoding::Binary => Box::new(BinaryPrimeReader { reader }),
+        SegmentEncoding::Text => Box::new(reader.lines().filter_map(|line| match line {
+            Ok(line) => line.trim().parse::<usize>().ok().map(Ok),
+            Err(e) => Some(Err(e)),
+        })),
+    }
+}
+
+/// Every prime <= bound from stored output (primes.bin, primes.txt or the variation 9 shards)
+/// None unless a stored file reaches past `bound`, so a shorter run is never taken as complete
+pub fn stored_primes_up_to(bound: usize) -> Option<Vec<usize>> {
+    type Source = fn() -> std::io::Result<PrimeStream>;
+    let sources: [Source; 3] = [
+        || stream_primes(true),
+        || stream_primes(false),
+        || Ok(Box::new(read_sharded_primes()?.map(Ok))),
+    ];
+
+    'sources: for source in sources {
+        let Ok(stored) = source() else {
+            continue;
+        };
+        let mut primes = Vec::new();
+        for prime in stored {
+            // A file that fails part way vouches for nothing
+            let Ok(prime) = prime else {
+                continue 'sources;
+            };
+            if prime > bound {
+                return Some(primes);
+            }
+            primes.push(prime);
+        }
+    }
+    None
+}
+
+/// Stream the primes in one output file of either format
+fn open_prime_file(
+    path: &Path,
+    encoding: SegmentEncoding,
+) -> std::io::Result<Box<dyn Iterator<Item = usize>>> {
+    let reader = BufReader::with_capacity(256 * 1024, fs::File::open(path)?);
+    match encoding {
+        SegmentEncoding::Binary => Ok(Box::new(BinaryPrimeReader { reader }.map_while(Result::ok))),
+        SegmentEncoding::Text => Ok(Box::new(
+            reader
+                .lines()
+                .map_while(Result::ok)
+                .filter_map(|line| line.trim().parse::<usize>().ok()),
+        )),
+    }
+}
+
+fn modified(path: &Path) -> Option<std::time::SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Format and age of the most recent sharded output (variation 9 or --shards), if there is any
+fn newest_shards(data_dir: &Path) -> Option<(SegmentEncoding, std::time::SystemTime)> {
+    [SegmentEncoding::Binary, SegmentEncoding::Text]
+        .into_iter()
+        .filter_map(|encoding| {
+            let small = data_dir.join(file_name("primes_small", encoding));
+            modified(&small).map(|time| (encoding, time))
+        })
+        .max_by_key(|&(_, time)| time)
+}
+
+/// Iterator over a binary prime file (8 bytes per prime, little-endian u64)
+/// Ends at the end of the file; any other read error is passed on
+struct BinaryPrimeReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> Iterator for BinaryPrimeReader<R> {
+    type Item = std::io::Result<usize>;
+
+    fn next(&mut self) -> Option<std::io::Result<usize>> {
+        let mut bytes = [0_u8; 8];
+        match self.reader.read_exact(&mut bytes) {
+            Ok(()) => Some(Ok(u64::from_le_bytes(bytes) as usize)),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// Stream sharded output in global order: primes_small, then primes_1..N interleaved
+/// Shards hold round-robin segments, so each is sorted but covers alternating ranges
+/// Reads whichever format (.bin or .txt) the most recent run wrote
+pub fn read_sharded_primes() -> std::io::Result<Box<dyn Iterator<Item = usize>>> {
+    let data_dir = get_nt_data_dir();
+    let Some((encoding, _)) = newest_shards(&data_dir) else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no variation 9 output (primes_small.bin or primes_small.txt)",
+        ));
+    };
+    read_shards_in(&data_dir, encoding)
+}
+
+/// Stream the primes in any output file: .bin is binary, anything else text, and
+/// primes_small.bin or .txt stands for the whole sharded output beside it
+pub fn open_primes(path: &Path) -> std::io::Result<Box<dyn Iterator<Item = usize>>> {
+    let encoding = if path.extension().is_some_and(|ext| ext == "bin") {
+        SegmentEncoding::Binary
+    } else {
+        SegmentEncoding::Text
+    };
+    let small = file_name("primes_small", encoding);
+    if path.file_name().and_then(|name| name.to_str()) == Some(small.as_str()) {
+        let dir = path.parent().unwrap_or(Path::new("."));
+        return read_shards_in(dir, encoding);
+    }
+    open_prime_file(path, encoding)
+}
+
+/// Sharded output in `dir`, merged as in `read_sharded_primes`
+fn read_shards_in(
+    dir: &Path,
+    encoding: SegmentEncoding,
+) -> std::io::Result<Box<dyn Iterator<Item = usize>>> {
+    let open = |stem: &str| open_prime_file(&dir.join(file_name(stem, encoding)), encoding);
+
+    let small = open("primes_small")?;
+
+    // Consumers are numbered 1..=N with no gaps
+    let mut shards = Vec::new();
+    while let Ok(shard) = open(&format!("primes_{}", shards.len() + 1)) {
+        shards.push(shard.peekable());
+    }
+
+    Ok(Box::new(small.chain(ShardMerge::new(shards))))
+}
+
+/// Merge sorted shards whose value ranges alternate in runs (one run per segment)
+/// Stays on the current shard until it passes the smallest head of the others, so the
+/// per-prime cost is one comparison and shards are only rescanned at segment boundaries
+struct ShardMerge<I: Iterator<Item = usize>> {
+    shards: Vec<std::iter::Peekable<I>>,
+    current: usize,
+    // Smallest head among the other shards (None when they are all exhausted)
+    bound: Option<usize>,
+}
+
+impl<I: Iterator<Item = usize>> ShardMerge<I> {
+    fn new(shards: Vec<std::iter::Peekable<I>>) -> Self {
+        // bound = Some(0) forces a rescan before the first prime
+        Self {
+            shards,
+            current: 0,
+            bound: Some(0),
+        }
+    }
+}
+
+impl<I: Iterator<Item = usize>> Iterator for ShardMerge<I> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if let Some(shard) = self.shards.get_mut(self.current)
+            && let Some(&prime) = shard.peek()
+            && self.bound.is_none_or(|bound| prime < bound)
+        {
+            shard.next();
+            return Some(prime);
+        }
+
+        // Switch to the shard holding the smallest head
+        let mut heads: Vec<(usize, usize)> = self
+            .shards
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(i, shard)| shard.peek().map(|&prime| (prime, i)))
+            .collect();
+        heads.sort_unstable();
+
+        let &(prime, index) = heads.first()?;
+        self.current = index;
+        self.bound = heads.get(1).map(|&(head, _)| head);
+
+        self.shards[index].next();
+        Some(prime)
+    }
+}
+
+/// Append a run to execution_log.txt, with the consumers' throughput when they wrote files
 pub fn log_execution(
     subcommand: &str,
     args: &str,
     variation: u32,
     duration_us: u128,
+    throughput: Option<&Throughput>,
 ) -> std::io::Result<()> {
     let data_dir = get_nt_data_dir();
     fs::create_dir_all(&data_dir)?;
@@ -140,169 +578,139 @@ pub fn log_execution(
 
     let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
 
-    writeln!(
+    write!(
         file,
         "{} | {} | {} | v{} | {}us",
         timestamp, subcommand, args, variation, duration_us
     )?;
+    if let Some(t) = throughput {
+        write!(
+            file,
+            " | {:.0} primes/s | {:.1} segments/s | {:.2} MB/s",
+            t.primes_per_second(),
+            t.segments_per_second(),
+            t.megabytes_per_second()
+        )?;
+    }
+    writeln!(file)?;
 
     Ok(())
 }
 
-/// Save primes from a channel, streaming them to primes.txt one at a time
+/// Save primes from a channel, streaming them to primes.txt (or primes.bin) one at a time
 /// Optionally saves each prime as an individual property file
 /// Returns the count of primes saved
-pub fn save_primes_streaming(rx: Receiver<usize>, save_as_property: bool) -> usize {
-    let mut count = 0;
-
-    // Open primes.txt in write mode (truncate)
-    let data_dir = get_nt_data_dir();
-    if let Err(e) = fs::create_dir_all(&data_dir) {
-        eprintln!("Error creating data directory: {}", e);
-        return 0;
-    }
-
-    let primes_path = data_dir.join("primes.txt");
-
-    let file = match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&primes_path)
-    {
-        Ok(f) => f,
+pub fn save_primes_streaming(
+    rx: Receiver<usize>,
+    encoding: SegmentEncoding,
+    options: BinaryOutputOptions,
+    save_as_property: bool,
+) -> usize {
+    let mut writer = match PrimeWriter::create("primes", encoding, &options, 8 * 1024) {
+        Ok(w) => w,
         Err(e) => {
-            eprintln!("Error opening primes.txt: {}", e);
+            error!("Error opening {}: {}", file_name("primes", encoding), e);
             return 0;
         }
     };
+    // Primes arrive one at a time, so there are no segments to count
+    let meter = Meter::new();
 
-    // Use BufWriter to buffer writes in memory
-    let mut writer = BufWriter::new(file);
+    // Gaps are gathered locally and handed over once, as segment 0
+    let track_gaps = crate::gaps::is_enabled();
+    let mut gaps = GapStats::default();
 
     // Process each prime from the channel
     for prime in rx {
+        if track_gaps {
+            gaps.push(prime);
+        }
         if save_as_property {
             match save_property(prime, "prime") {
-                Ok(_) => println!("Saved: {}.txt", prime),
-                Err(e) => eprintln!("Error saving {}.txt: {}", prime, e),
+                Ok(_) => info!("Saved: {}.txt", prime),
+                Err(e) => error!("Error saving {}.txt: {}", prime, e),
             }
         }
 
-        // Append prime to primes.txt (buffered) using itoa for speed
-        let mut itoa_buf = itoa::Buffer::new();
-        if let Err(e) = writer.write_all(itoa_buf.format(prime).as_bytes()) {
-            eprintln!("Error writing to primes.txt: {}", e);
-        }
-        if let Err(e) = writer.write_all(b"\n") {
-            eprintln!("Error writing newline to primes.txt: {}", e);
+        if let Err(e) = writer.write_prime(prime) {
+            error!("Error writing to {}: {}", writer.name(), e);
         }
-
-        count += 1;
     }
-
-    // Flush buffer before returning
-    if let Err(e) = writer.flush() {
-        eprintln!("Error flushing primes.txt: {}", e);
+    if track_gaps {
+        crate::gaps::submit(0, gaps);
     }
 
-    println!("\nSaved all primes to primes.txt");
-    count
+    finish_output(writer, meter, "")
 }
 
-/// Save primes from a channel that sends batched segments
+/// Save primes from a channel that sends batched segments (variation 6)
 /// Receives Vec<usize> instead of individual primes for better performance
-/// Optionally saves each prime as an individual property file
+/// With `write_behind`, segments are formatted here and written from a second thread
 /// Returns the count of primes saved
-pub fn save_primes_streaming_batched(rx: Receiver<Vec<usize>>) -> usize {
-    let mut count = 0;
-
-    // Open primes.txt in write mode (truncate)
-    let data_dir = get_nt_data_dir();
-    if let Err(e) = fs::create_dir_all(&data_dir) {
-        eprintln!("Error creating data directory: {}", e);
-        return 0;
-    }
-
-    let primes_path = data_dir.join("primes.txt");
-
-    let file = match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&primes_path)
-    {
-        Ok(f) => f,
+pub fn save_primes_streaming_batched(
+    rx: Receiver<Vec<usize>>,
+    encoding: SegmentEncoding,
+    options: BinaryOutputOptions,
+    pool: BufferPool<usize>,
+    write_behind: bool,
+) -> usize {
+    let mut writer = match PrimeWriter::create("primes", encoding, &options, 256 * 1024) {
+        Ok(w) => w,
         Err(e) => {
-            eprintln!("Error opening primes.txt: {}", e);
+            error!("Error opening {}: {}", file_name("primes", encoding), e);
             return 0;
         }
     };
-
-    // Use BufWriter to buffer writes in memory
-    let mut writer = BufWriter::with_capacity(256 * 1024, file); // 256KB
-
-    // Process each segment of primes from the channel
-    let mut itoa_buf = itoa::Buffer::new();
-    for segment_primes in rx {
-        for prime in segment_primes {
-            // Append prime to primes.txt (buffered) using itoa for speed
-            if let Err(e) = writer.write_all(itoa_buf.format(prime).as_bytes()) {
-                eprintln!("Error writing to primes.txt: {}", e);
-            }
-            if let Err(e) = writer.write_all(b"\n") {
-                eprintln!("Error writing newline to primes.txt: {}", e);
-            }
-
-            count += 1;
-        }
+    if write_behind {
+        writer = writer.write_behind();
     }
+    let mut meter = Meter::new();
 
-    // Flush buffer before returning
-    if let Err(e) = writer.flush() {
-        eprintln!("Error flushing primes.txt: {}", e);
+    // Process each segment of primes from the channel (they arrive in order)
+    for (segment_id, segment_primes) in rx.into_iter().enumerate() {
+        if let Err(e) = writer.write_primes(&segment_primes) {
+            error!("Error writing to {}: {}", writer.name(), e);
+        }
+        meter.segment();
+        crate::gaps::record(segment_id, &segment_primes);
+        pool.give_back(segment_primes);
     }
 
-    println!("\nSaved all primes to primes.txt");
-    count
+    let detail = if write_behind { " (write-behind)" } else { "" };
+    finish_output(writer, meter, detail)
 }
 
 /// Save primes from raw segment data (variation 7)
-/// Unpacks segments on consumer side and saves to primes.txt
-/// Optionally saves each prime as an individual property file
+/// Unpacks segments on consumer side and saves to primes.txt (or primes.bin)
 /// Returns the count of primes saved
-pub fn save_primes_streaming_segments(rx: Receiver<SegmentData>, limit: usize) -> usize {
-    // Open primes.txt in write mode (truncate)
-    let data_dir = get_nt_data_dir();
-    if let Err(e) = fs::create_dir_all(&data_dir) {
-        eprintln!("Error creating data directory: {}", e);
-        return 0;
-    }
-
-    let primes_path = data_dir.join("primes.txt");
-
-    let file = match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&primes_path)
-    {
-        Ok(f) => f,
+pub fn save_primes_streaming_segments(
+    rx: Receiver<SegmentData>,
+    limit: usize,
+    encoding: SegmentEncoding,
+    options: BinaryOutputOptions,
+    pool: BufferPool<u64>,
+) -> usize {
+    let mut writer = match PrimeWriter::create("primes", encoding, &options, 128 * 1024) {
+        Ok(w) => w,
         Err(e) => {
-            eprintln!("Error opening primes.txt: {}", e);
+            error!("Error opening {}: {}", file_name("primes", encoding), e);
             return 0;
         }
     };
+    let mut meter = Meter::new();
+
+    let track_gaps = crate::gaps::is_enabled();
+    let mut gaps = GapStats::default();
 
-    // Use BufWriter to buffer writes in memory
-    let mut writer = BufWriter::with_capacity(128 * 1024, file);
-    if let Err(e) = writeln!(writer, "2") {
-        eprintln!("Error writing to primes.txt: {}", e);
+    // Segments only hold odd numbers
+    if let Err(e) = writer.write_prime(2) {
+        error!("Error writing to {}: {}", writer.name(), e);
+    }
+    if track_gaps {
+        gaps.push(2);
     }
-    let mut count = 1;
 
     // Process each segment from the channel
-    let mut itoa_buf = itoa::Buffer::new();
     for segment_data in rx {
         // Unpack and write directly (no intermediate Vec allocation!)
         for word_idx in 0..segment_data.bits.len() {
@@ -313,351 +721,244 @@ pub fn save_primes_streaming_segments(rx: Receiver<SegmentData>, limit: usize) -
                 let idx = word_idx * 64 + bit_idx;
 
                 let num = segment_data.low + idx * 2;
-                // Append prime to primes.txt (buffered) using itoa for speed
                 if num > segment_data.high || num > limit {
                     break;
                 }
 
-                if let Err(e) = writer.write_all(itoa_buf.format(num).as_bytes()) {
-                    eprintln!("Error writing to primes.txt: {}", e);
+                if let Err(e) = writer.write_prime(num) {
+                    error!("Error writing to {}: {}", writer.name(), e);
                 }
-                if let Err(e) = writer.write_all(b"\n") {
-                    eprintln!("Error writing newline to primes.txt: {}", e);
+                if track_gaps {
+                    gaps.push(num);
                 }
-                count += 1;
 
                 word &= word - 1; // Clear lowest set bit
             }
         }
+        meter.segment();
+        pool.give_back(segment_data.bits);
     }
-
-    // Flush buffer before returning
-    if let Err(e) = writer.flush() {
-        eprintln!("Error flushing primes.txt: {}", e);
+    if track_gaps {
+        crate::gaps::submit(0, gaps);
     }
 
-    println!("\nSaved all primes to primes.txt");
-    count
+    finish_output(writer, meter, "")
 }
 
 /// Save primes from unpacked segment data with reordering (variation 8)
 /// Receives segments out-of-order from parallel workers and writes in order
 /// Segments are already unpacked by workers (producer-side unpacking like v6)
+/// With `write_behind`, segments are formatted here and written from a second thread
 /// Returns the count of primes saved
-pub fn save_primes_streaming_segments_parallel(rx: Receiver<SegmentPrimes>) -> usize {
-    let mut count = 0;
-
-    // Open primes.txt in write mode (truncate)
-    let data_dir = get_nt_data_dir();
-    if let Err(e) = fs::create_dir_all(&data_dir) {
-        eprintln!("Error creating data directory: {}", e);
-        return 0;
-    }
-
-    let primes_path = data_dir.join("primes.txt");
-
-    let file = match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&primes_path)
-    {
-        Ok(f) => f,
+pub fn save_primes_streaming_segments_parallel(
+    rx: Receiver<SegmentPrimes>,
+    encoding: SegmentEncoding,
+    options: BinaryOutputOptions,
+    pool: BufferPool<usize>,
+    write_behind: bool,
+) -> usize {
+    let mut writer = match PrimeWriter::create("primes", encoding, &options, 128 * 1024) {
+        Ok(w) => w,
         Err(e) => {
-            eprintln!("Error opening primes.txt: {}", e);
+            error!("Error opening {}: {}", file_name("primes", encoding), e);
             return 0;
         }
     };
-
-    // Use BufWriter with larger buffer for better performance
-    let mut writer = BufWriter::with_capacity(128 * 1024, file);
+    if write_behind {
+        writer = writer.write_behind();
+    }
 
     // Buffer for out-of-order segments
-    let mut segment_buffer: BTreeMap<usize, SegmentPrimes> = BTreeMap::new();
-    let mut next_expected_id = 0;
-
-    // String buffer for batch writing (reused across segments)
-    let mut string_buffer = String::with_capacity(2 * 1024 * 1024); // 2MB initial
-
-    // Helper function to process a segment
-    let process_segment = |segment_primes: &SegmentPrimes,
-                           writer: &mut BufWriter<_>,
-                           string_buffer: &mut String|
-     -> usize {
-        let local_count = segment_primes.primes.len();
-
-        // Batch write: build string then write once
-        string_buffer.clear();
-
-        // Pre-allocate estimated capacity (avg ~10 bytes per prime with newline)
-        let estimated_size = local_count * 11;
-        if string_buffer.capacity() < estimated_size {
-            string_buffer.reserve(estimated_size - string_buffer.capacity());
-        }
+    let mut reorder = Reorder::new(0, 1);
+    let mut meter = Meter::new();
 
-        // Build batch string using itoa (fastest integer formatting)
-        let mut itoa_buf = itoa::Buffer::new();
-        for &prime in &segment_primes.primes {
-            string_buffer.push_str(itoa_buf.format(prime));
-            string_buffer.push('\n');
+    let mut write_segment = |seg: SegmentPrimes, writer: &mut PrimeWriter| {
+        if let Err(e) = writer.write_primes(&seg.primes) {
+            error!("Error writing to {}: {}", writer.name(), e);
         }
-
-        // Single write call for entire segment
-        if let Err(e) = writer.write_all(string_buffer.as_bytes()) {
-            eprintln!("Error writing to primes.txt: {}", e);
-        }
-
-        local_count
+        meter.segment();
+        crate::gaps::record(seg.segment_id, &seg.primes);
+        pool.give_back(seg.primes);
     };
 
     // Process segments in order
     for segment_primes in rx {
-        let segment_id = segment_primes.segment_id;
-
-        // Add to buffer
-        segment_buffer.insert(segment_id, segment_primes);
+        reorder.insert(segment_primes.segment_id, segment_primes);
 
-        // Process all consecutive segments starting from next_expected_id
-        while let Some(seg) = segment_buffer.remove(&next_expected_id) {
-            count += process_segment(&seg, &mut writer, &mut string_buffer);
-            next_expected_id += 1;
+        // Process all consecutive segments starting from the next expected id
+        while let Some(seg) = reorder.pop_ready() {
+            write_segment(seg, &mut writer);
         }
     }
 
     // Process any remaining buffered segments (shouldn't happen if producer is correct)
-    while let Some((_, seg)) = segment_buffer.pop_first() {
-        count += process_segment(&seg, &mut writer, &mut string_buffer);
+    while let Some(seg) = reorder.pop_remaining() {
+        write_segment(seg, &mut writer);
     }
 
-    // Flush buffer before returning
-    if let Err(e) = writer.flush() {
-        eprintln!("Error flushing primes.txt: {}", e);
-    }
-
-    println!("\nSaved all primes to primes.txt (parallel)");
-    count
+    let detail = if write_behind {
+        " (parallel, write-behind)"
+    } else {
+        " (parallel)"
+    };
+    finish_output(writer, meter, detail)
 }
 
-/// Save primes from unpacked segment data with reordering in BINARY format (variation 8)
-/// Receives segments out-of-order from parallel workers and writes in order
-/// Binary format: 8 bytes per prime (little-endian u64)
+/// Save in-order or parallel segments as shards laid out like variation 9's (--shards)
+/// Segment 0 (the small primes) goes to primes_small and segment s to
+/// primes_((s - 1) % num_shards + 1); each shard is written from a thread of its own
 /// Returns the count of primes saved
-pub fn save_primes_streaming_segments_parallel_binary(rx: Receiver<SegmentPrimes>) -> usize {
-    let mut count = 0;
-
-    // Open primes.bin in write mode (truncate)
-    let data_dir = get_nt_data_dir();
-    if let Err(e) = fs::create_dir_all(&data_dir) {
-        eprintln!("Error creating data directory: {}", e);
+pub fn save_primes_sharded(
+    segments: impl Iterator<Item = SegmentPrimes>,
+    num_shards: usize,
+    limit: usize,
+    encoding: SegmentEncoding,
+    options: BinaryOutputOptions,
+    pool: BufferPool<usize>,
+) -> usize {
+    let open = |stem: &str| {
+        PrimeWriter::create(stem, encoding, &options, 1024 * 1024)
+            .map(PrimeWriter::write_behind)
+            .map_err(|e| error!("Error opening {}: {}", file_name(stem, encoding), e))
+    };
+    let Ok(mut writers) = std::iter::once("primes_small".to_string())
+        .chain((1..=num_shards).map(|id| format!("primes_{}", id)))
+        .map(|stem| open(&stem))
+        .collect::<Result<Vec<_>, _>>()
+    else {
         return 0;
-    }
-
-    let primes_path = data_dir.join("primes.bin");
-
-    let file = match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&primes_path)
-    {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("Error opening primes.bin: {}", e);
-            return 0;
-        }
     };
 
-    // Use BufWriter with larger buffer for better performance
-    let mut writer = BufWriter::with_capacity(128 * 1024, file);
-
-    // Buffer for out-of-order segments
-    let mut segment_buffer: BTreeMap<usize, SegmentPrimes> = BTreeMap::new();
-    let mut next_expected_id = 0;
-
-    // Helper function to process a segment
-    let process_segment = |segment_primes: &SegmentPrimes, writer: &mut BufWriter<_>| -> usize {
-        let local_count = segment_primes.primes.len();
-
-        // Write primes as binary (8 bytes each, little-endian)
-        for &prime in &segment_primes.primes {
-            let bytes = (prime as u64).to_le_bytes();
-            if let Err(e) = writer.write_all(&bytes) {
-                eprintln!("Error writing to primes.bin: {}", e);
-            }
+    let mut reorder = Reorder::new(0, 1);
+    let mut meter = Meter::new();
+    let mut write_segment = |seg: SegmentPrimes, writers: &mut [PrimeWriter]| {
+        let shard = match seg.segment_id {
+            0 => 0,
+            id => (id - 1) % num_shards + 1,
+        };
+        let writer = &mut writers[shard];
+        if let Err(e) = writer.write_primes(&seg.primes) {
+            error!("Error writing to {}: {}", writer.name(), e);
         }
-
-        local_count
+        meter.segment();
+        crate::gaps::record(seg.segment_id, &seg.primes);
+        pool.give_back(seg.primes);
     };
 
-    // Process segments in order
-    for segment_primes in rx {
-        let segment_id = segment_primes.segment_id;
-
-        // Add to buffer
-        segment_buffer.insert(segment_id, segment_primes);
-
-        // Process all consecutive segments starting from next_expected_id
-        while let Some(seg) = segment_buffer.remove(&next_expected_id) {
-            count += process_segment(&seg, &mut writer);
-            next_expected_id += 1;
+    for segment in segments {
+        reorder.insert(segment.segment_id, segment);
+        while let Some(seg) = reorder.pop_ready() {
+            write_segment(seg, &mut writers);
         }
     }
-
-    // Process any remaining buffered segments (shouldn't happen if producer is correct)
-    while let Some((_, seg)) = segment_buffer.pop_first() {
-        count += process_segment(&seg, &mut writer);
+    while let Some(seg) = reorder.pop_remaining() {
+        write_segment(seg, &mut writers);
     }
 
-    // Flush buffer before returning
-    if let Err(e) = writer.flush() {
-        eprintln!("Error flushing primes.bin: {}", e);
+    let mut counts = Vec::new();
+    let mut bytes = 0;
+    for writer in writers {
+        let name = writer.name().to_string();
+        counts.push(writer.count());
+        bytes += writer.bytes_written();
+        if let Err(e) = writer.finish() {
+            error!("Error flushing {}: {}", name, e);
+        }
     }
+    let count = counts.iter().sum();
+    let throughput = meter.finish(count, bytes);
+    info!(
+        "\nSaved all primes to primes_small and {} shards | {}",
+        num_shards,
+        throughput.summary()
+    );
 
-    println!("\nSaved all primes to primes.bin (parallel, binary format)");
+    save_manifest(encoding, limit, &counts);
     count
 }
 
-/// Save primes from batched segments in BINARY format (variation 6)
-/// Binary format: 8 bytes per prime (little-endian u64)
-/// Returns the count of primes saved
-pub fn save_primes_streaming_batched_binary(rx: Receiver<Vec<usize>>) -> usize {
-    let mut count = 0;
-
-    // Open primes.bin in write mode (truncate)
-    let data_dir = get_nt_data_dir();
-    if let Err(e) = fs::create_dir_all(&data_dir) {
-        eprintln!("Error creating data directory: {}", e);
-        return 0;
-    }
-
-    let primes_path = data_dir.join("primes.bin");
-
-    let file = match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&primes_path)
-    {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("Error opening primes.bin: {}", e);
-            return 0;
-        }
-    };
-
-    // Use BufWriter to buffer writes in memory
-    let mut writer = BufWriter::with_capacity(256 * 1024, file);
-
-    // Process each segment of primes from the channel
-    for segment_primes in rx {
-        for prime in segment_primes {
-            // Write as binary (8 bytes, little-endian)
-            let bytes = (prime as u64).to_le_bytes();
-            if let Err(e) = writer.write_all(&bytes) {
-                eprintln!("Error writing to primes.bin: {}", e);
-            }
-
-            count += 1;
-        }
-    }
-
-    // Flush buffer before returning
-    if let Err(e) = writer.flush() {
-        eprintln!("Error flushing primes.bin: {}", e);
+/// Write primes_manifest.json for the shards just saved, given the primes in primes_small
+/// and then in each shard
+pub fn save_manifest(encoding: SegmentEncoding, limit: usize, counts: &[usize]) {
+    let manifest = ShardManifest::new(encoding, limit, counts);
+    match manifest.save() {
+        Ok(path) => info!("Shard manifest: {}", path.display()),
+        Err(e) => error!("Error saving {}: {}", MANIFEST_NAME, e),
     }
-
-    println!("\nSaved all primes to primes.bin (binary format)");
-    count
 }
 
-/// Save small primes to primes_small.bin (for variation 9)
-/// Binary format: 8 bytes per prime (little-endian u64)
+/// Save small primes to primes_small.txt / primes_small.bin (for variation 9)
 /// Returns the count of primes saved
-pub fn save_small_primes_binary(primes: &[usize]) -> usize {
-    let data_dir = get_nt_data_dir();
-    if let Err(e) = fs::create_dir_all(&data_dir) {
-        eprintln!("Error creating data directory: {}", e);
-        return 0;
-    }
-
-    let primes_path = data_dir.join("primes_small.bin");
-
-    let file = match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&primes_path)
-    {
-        Ok(f) => f,
+pub fn save_small_primes(primes: &[usize], encoding: SegmentEncoding) -> usize {
+    let options = BinaryOutputOptions::default();
+    let mut writer = match PrimeWriter::create("primes_small", encoding, &options, 64 * 1024) {
+        Ok(w) => w,
         Err(e) => {
-            eprintln!("Error opening primes_small.bin: {}", e);
+            error!(
+                "Error opening {}: {}",
+                file_name("primes_small", encoding),
+                e
+            );
             return 0;
         }
     };
 
-    let mut writer = BufWriter::with_capacity(64 * 1024, file);
-
-    for &prime in primes {
-        let bytes = (prime as u64).to_le_bytes();
-        if let Err(e) = writer.write_all(&bytes) {
-            eprintln!("Error writing to primes_small.bin: {}", e);
-        }
+    let mut meter = Meter::new();
+    if let Err(e) = writer.write_primes(primes) {
+        error!("Error writing to {}: {}", writer.name(), e);
     }
-
-    if let Err(e) = writer.flush() {
-        eprintln!("Error flushing primes_small.bin: {}", e);
+    meter.segment();
+    // Small primes come before segment 1
+    crate::gaps::record(0, primes);
+
+    let name = writer.name().to_string();
+    let bytes = writer.bytes_written();
+    if let Err(e) = writer.finish() {
+        error!("Error flushing {}: {}", name, e);
     }
 
     let count = primes.len();
-    println!("Saved {} small primes to primes_small.bin", count);
+    let throughput = meter.finish(count, bytes);
+    info!(
+        "Saved {} small primes to {} | {}",
+        count,
+        name,
+        throughput.summary()
+    );
     count
 }
 
 /// Multi-consumer for variation 9 with N consumers
-/// Writes segments to primes_{consumer_id}.bin
+/// Writes segments to primes_{consumer_id}.txt / .bin
 /// Each consumer processes segments where (segment_id - 1) % num_consumers == (consumer_id - 1)
-/// Binary format: 8 bytes per prime (little-endian u64)
 /// Returns the count of primes saved
-pub fn save_primes_multi_consumer_binary(
+#[allow(clippy::too_many_arguments)]
+pub fn save_primes_multi_consumer(
     rx: Receiver<SegmentPrimes>,
     consumer_id: usize,
     num_consumers: usize,
     total_received: Arc<AtomicUsize>,
     total_sent: Arc<AtomicUsize>,
+    encoding: SegmentEncoding,
+    options: BinaryOutputOptions,
+    pool: BufferPool<usize>,
 ) -> usize {
-    let mut count = 0;
-
-    let data_dir = get_nt_data_dir();
-    if let Err(e) = fs::create_dir_all(&data_dir) {
-        eprintln!("Error creating data directory: {}", e);
-        return 0;
-    }
-
-    let filename = format!("primes_{}.bin", consumer_id);
-    let primes_path = data_dir.join(&filename);
-
-    let file = match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&primes_path)
-    {
-        Ok(f) => f,
+    let stem = format!("primes_{}", consumer_id);
+    let mut writer = match PrimeWriter::create(&stem, encoding, &options, 8 * 1024 * 1024) {
+        Ok(w) => w,
         Err(e) => {
-            eprintln!("Error opening {}: {}", filename, e);
+            error!("Error opening {}: {}", file_name(&stem, encoding), e);
             return 0;
         }
     };
 
-    let mut writer = BufWriter::with_capacity(8 * 1024 * 1024, file);
-
     // Buffer for out-of-order segments
-    let mut segment_buffer: BTreeMap<usize, SegmentPrimes> = BTreeMap::new();
     // This consumer handles segments where (segment_id - 1) % num_consumers == (consumer_id - 1)
     // So first segment is consumer_id, next is consumer_id + num_consumers, etc.
-    let mut next_expected_id = consumer_id;
+    let mut reorder = Reorder::new(consumer_id, num_consumers);
 
     let warning_threshold = 100;
+    let mut meter = Meter::new();
 
     // Memory monitoring
     let mut peak_buffer_size = 0;
@@ -665,19 +966,6 @@ pub fn save_primes_multi_consumer_binary(
     let mut total_segments_received = 0;
     let memory_report_interval = 1000; // Report every 1000 segments processed
 
-    // Helper to process segment
-    let process_segment =
-        |segment_primes: &SegmentPrimes, writer: &mut BufWriter<_>, filename: &str| -> usize {
-            let local_count = segment_primes.primes.len();
-            for &prime in &segment_primes.primes {
-                let bytes = (prime as u64).to_le_bytes();
-                if let Err(e) = writer.write_all(&bytes) {
-                    eprintln!("Error writing to {}: {}", filename, e);
-                }
-            }
-            local_count
-        };
-
     // Process segments in order
     for segment_primes in rx {
         let segment_id = segment_primes.segment_id;
@@ -686,20 +974,32 @@ pub fn save_primes_multi_consumer_binary(
         // Increment receive counter
         total_received.fetch_add(1, Ordering::Relaxed);
 
-        segment_buffer.insert(segment_id, segment_primes);
+        reorder.insert(segment_id, segment_primes);
 
         // Process all consecutive segments for this consumer
-        while let Some(seg) = segment_buffer.remove(&next_expected_id) {
-            count += process_segment(&seg, &mut writer, &filename);
-            next_expected_id += num_consumers; // Skip to next segment for this consumer
+        while let Some(seg) = reorder.pop_ready() {
+            let bytes_before = writer.bytes_written();
+            match writer.write_primes(&seg.primes) {
+                Ok(()) => crate::audit::record_segment(consumer_id, seg.segment_id),
+                Err(e) => error!("Error writing to {}: {}", writer.name(), e),
+            }
+            meter.segment();
+            crate::gaps::record(seg.segment_id, &seg.primes);
+            crate::tui::record_consumer_segment(
+                consumer_id,
+                writer.bytes_written() - bytes_before,
+                reorder.len(),
+            );
+            pool.give_back(seg.primes);
 
             // Periodic memory reporting
+            let next_expected_id = reorder.next_id();
             if (next_expected_id / num_consumers) % memory_report_interval == 0 {
                 if let Some((rss_mb, vm_mb)) = get_process_memory_mb() {
                     let sent = total_sent.load(Ordering::Relaxed);
                     let received = total_received.load(Ordering::Relaxed);
                     let gap = sent.saturating_sub(received);
-                    eprintln!(
+                    debug!(
                         "[Consumer {}/{}] Processed {} segments | Sent: {} | Received: {} | Gap: {} | RSS={:.2} MB, VM={:.2} MB",
                         consumer_id,
                         num_consumers,
@@ -715,7 +1015,7 @@ pub fn save_primes_multi_consumer_binary(
         }
 
         // Memory monitoring: calculate current buffer memory usage
-        let buffer_size = segment_buffer.len();
+        let buffer_size = reorder.len();
         if buffer_size > peak_buffer_size {
             peak_buffer_size = buffer_size;
         }
@@ -724,7 +1024,7 @@ pub fn save_primes_multi_consumer_binary(
         // - BTreeMap node overhead: ~32 bytes per entry
         // - SegmentPrimes: 8 bytes (segment_id) + Vec overhead (24 bytes) + data
         let mut buffer_memory_bytes = 0;
-        for seg in segment_buffer.values() {
+        for seg in reorder.values() {
             let seg_size = std::mem::size_of::<usize>() // segment_id
                 + std::mem::size_of::<Vec<usize>>() // Vec overhead
                 + (seg.primes.len() * std::mem::size_of::<usize>()) // actual primes
@@ -738,14 +1038,14 @@ pub fn save_primes_multi_consumer_binary(
         }
 
         // Warn if buffer grows too large (indicates out-of-order arrival)
-        if segment_buffer.len() > warning_threshold {
-            eprintln!(
+        if reorder.len() > warning_threshold {
+            warn!(
                 "Warning: Consumer {}/{} buffer: {} segments, {:.2} MB (expected next: {}, received: {})",
                 consumer_id,
                 num_consumers,
-                segment_buffer.len(),
+                reorder.len(),
                 buffer_memory_mb,
-                next_expected_id,
+                reorder.next_id(),
                 total_segments_received
             );
         }
@@ -754,7 +1054,7 @@ pub fn save_primes_multi_consumer_binary(
         if total_segments_received % 10000 == 0 {
             let received_total = total_received.load(Ordering::Relaxed);
             // Channel depth is a rough estimate (sent might be slightly ahead due to concurrency)
-            eprintln!(
+            debug!(
                 "[Consumer {}/{}] Channel check at {} local received | Global received: {}",
                 consumer_id, num_consumers, total_segments_received, received_total
             );
@@ -762,17 +1062,288 @@ pub fn save_primes_multi_consumer_binary(
     }
 
     // Process remaining
-    while let Some((_, seg)) = segment_buffer.pop_first() {
-        count += process_segment(&seg, &mut writer, &filename);
+    while let Some(seg) = reorder.pop_remaining() {
+        match writer.write_primes(&seg.primes) {
+            Ok(()) => crate::audit::record_segment(consumer_id, seg.segment_id),
+            Err(e) => error!("Error writing to {}: {}", writer.name(), e),
+        }
+        meter.segment();
+        crate::gaps::record(seg.segment_id, &seg.primes);
+        pool.give_back(seg.primes);
+    }
+
+    let name = writer.name().to_string();
+    let count = writer.count();
+    let bytes = writer.bytes_written();
+    if let Err(e) = writer.finish() {
+        error!("Error flushing {}: {}", name, e);
+    }
+    let throughput = meter.finish(count, bytes);
+
+    info!(
+        "Consumer {}: Saved {} primes to {} | Peak buffer: {} segments, {:.2} MB | {}",
+        consumer_id,
+        count,
+        name,
+        peak_buffer_size,
+        peak_buffer_memory_mb,
+        throughput.summary()
+    );
+    count
+}
+
+/// Save preformatted segments with reordering (variation 8 with --preformat)
+/// Workers already encoded each segment, so this only reorders and writes bytes
+/// Returns the count of primes saved
+pub fn save_encoded_segments_parallel(
+    rx: Receiver<EncodedSegment>,
+    encoding: SegmentEncoding,
+    options: BinaryOutputOptions,
+    pool: BufferPool<u8>,
+) -> usize {
+    let mut writer = match PrimeWriter::create("primes", encoding, &options, 256 * 1024) {
+        Ok(w) => w,
+        Err(e) => {
+            error!("Error opening {}: {}", file_name("primes", encoding), e);
+            return 0;
+        }
+    };
+
+    // Buffer for out-of-order segments
+    let mut reorder = Reorder::new(0, 1);
+    let mut meter = Meter::new();
+
+    let mut write_segment = |seg: EncodedSegment, writer: &mut PrimeWriter| {
+        if let Err(e) = writer.write_encoded(&seg.bytes, seg.count) {
+            error!("Error writing to {}: {}", writer.name(), e);
+        }
+        meter.segment();
+        crate::gaps::record_encoded(seg.segment_id, &seg.bytes, encoding);
+        pool.give_back(seg.bytes);
+    };
+
+    // Process segments in order
+    for segment in rx {
+        reorder.insert(segment.segment_id, segment);
+
+        // Process all consecutive segments starting from the next expected id
+        while let Some(seg) = reorder.pop_ready() {
+            write_segment(seg, &mut writer);
+        }
+    }
+
+    // Process any remaining buffered segments (shouldn't happen if producer is correct)
+    while let Some(seg) = reorder.pop_remaining() {
+        write_segment(seg, &mut writer);
+    }
+
+    finish_output(writer, meter, " (parallel, preformatted)")
+}
+
+/// Multi-consumer for preformatted segments (variation 9 with --preformat)
+/// Same shard layout as save_primes_multi_consumer, but only writes bytes
+/// Returns the count of primes saved
+pub fn save_encoded_multi_consumer(
+    rx: Receiver<EncodedSegment>,
+    consumer_id: usize,
+    num_consumers: usize,
+    total_received: Arc<AtomicUsize>,
+    encoding: SegmentEncoding,
+    options: BinaryOutputOptions,
+    pool: BufferPool<u8>,
+) -> usize {
+    let stem = format!("primes_{}", consumer_id);
+    let mut writer = match PrimeWriter::create(&stem, encoding, &options, 256 * 1024) {
+        Ok(w) => w,
+        Err(e) => {
+            error!("Error opening {}: {}", file_name(&stem, encoding), e);
+            return 0;
+        }
+    };
+
+    // Buffer for out-of-order segments; first segment for this consumer is consumer_id
+    let mut reorder = Reorder::new(consumer_id, num_consumers);
+    let mut peak_buffer_size = 0;
+    let mut meter = Meter::new();
+
+    let mut write_segment = |seg: EncodedSegment, writer: &mut PrimeWriter| {
+        match writer.write_encoded(&seg.bytes, seg.count) {
+            Ok(()) => crate::audit::record_segment(consumer_id, seg.segment_id),
+            Err(e) => error!("Error writing to {}: {}", writer.name(), e),
+        }
+        meter.segment();
+        crate::gaps::record_encoded(seg.segment_id, &seg.bytes, encoding);
+        pool.give_back(seg.bytes);
+    };
+
+    for segment in rx {
+        total_received.fetch_add(1, Ordering::Relaxed);
+        reorder.insert(segment.segment_id, segment);
+
+        // Process all consecutive segments for this consumer
+        while let Some(seg) = reorder.pop_ready() {
+            let bytes = seg.bytes.len();
+            write_segment(seg, &mut writer);
+            crate::tui::record_consumer_segment(consumer_id, bytes, reorder.len());
+        }
+
+        peak_buffer_size = peak_buffer_size.max(reorder.len());
+    }
+
+    // Process remaining
+    while let Some(seg) = reorder.pop_remaining() {
+        write_segment(seg, &mut writer);
     }
 
-    if let Err(e) = writer.flush() {
-        eprintln!("Error flushing {}: {}", filename, e);
+    let name = writer.name().to_string();
+    let count = writer.count();
+    let bytes = writer.bytes_written();
+    if let Err(e) = writer.finish() {
+        error!("Error flushing {}: {}", name, e);
     }
+    let throughput = meter.finish(count, bytes);
+
+    info!(
+        "Consumer {}: Saved {} primes to {} | Peak buffer: {} segments | {}",
+        consumer_id,
+        count,
+        name,
+        peak_buffer_size,
+        throughput.summary()
+    );
+    count
+}
+
+/// Finish a single-file consumer and log where the primes went and how fast
+/// `detail` is appended to the log line, e.g. " (parallel)"
+fn finish_output(writer: PrimeWriter, meter: Meter, detail: &str) -> usize {
+    let name = writer.name().to_string();
+    let count = writer.count();
+    let bytes = writer.bytes_written();
 
-    println!(
-        "Consumer {}: Saved {} primes to {} | Peak buffer: {} segments, {:.2} MB",
-        consumer_id, count, filename, peak_buffer_size, peak_buffer_memory_mb
+    // Flush buffer, then move the finished file over the previous one
+    if let Err(e) = writer.finish() {
+        error!("Error flushing {}: {}", name, e);
+    }
+    let throughput = meter.finish(count, bytes);
+
+    info!(
+        "\nSaved all primes to {}{} | {}",
+        name,
+        detail,
+        throughput.summary()
     );
     count
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shard_merge_restores_global_order() {
+        // Three consumers, segments of unequal length assigned round-robin
+        let shards = vec![
+            vec![11, 13, 41, 43, 47],
+            vec![17, 19, 23, 53],
+            vec![29, 31, 37],
+        ];
+        let shards = shards
+            .into_iter()
+            .map(|s| s.into_iter().peekable())
+            .collect();
+        let merged: Vec<usize> = ShardMerge::new(shards).collect();
+
+        assert_eq!(merged, vec![11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53]);
+    }
+
+    #[test]
+    fn test_prime_stream_passes_read_errors_on() {
+        // Two good lines, then a reader that fails instead of reaching the end
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk gone"))
+            }
+        }
+        let text = BufReader::new(b"2\n3\n".chain(Failing));
+        let read: Vec<_> = prime_stream(text, SegmentEncoding::Text).take(3).collect();
+        assert_eq!(
+            read[..2]
+                .iter()
+                .map(|p| *p.as_ref().unwrap())
+                .sum::<usize>(),
+            5
+        );
+        assert_eq!(read[2].as_ref().unwrap_err().to_string(), "disk gone");
+
+        let mut bytes = 7_u64.to_le_bytes().to_vec();
+        let binary = BufReader::new(std::io::Cursor::new(bytes.clone()).chain(Failing));
+        let read: Vec<_> = prime_stream(binary, SegmentEncoding::Binary)
+            .take(2)
+            .collect();
+        assert_eq!(*read[0].as_ref().unwrap(), 7);
+        assert!(read[1].is_err());
+
+        // A clean end of file ends the stream
+        bytes.extend(11_u64.to_le_bytes());
+        let binary = BufReader::new(std::io::Cursor::new(bytes));
+        let read: Vec<usize> = prime_stream(binary, SegmentEncoding::Binary)
+            .collect::<std::io::Result<_>>()
+            .unwrap();
+        assert_eq!(read, [7, 11]);
+    }
+
+    #[test]
+    fn test_commit_output_reports_a_failed_move() {
+        let path = std::env::temp_dir().join(format!("nt_commit_{}.txt", std::process::id()));
+        fs::write(staging_path(&path), "2\n").unwrap();
+        commit_output(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "2\n");
+        fs::remove_file(&path).unwrap();
+
+        // Nothing staged: the error reaches the caller instead of only the log
+        assert!(commit_output(&path).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_output_estimate_covers_actual_size() {
+        // primes.txt up to 10^6: 78498 primes, 5 or 6 digits plus a newline each
+        let text_bytes: u64 = crate::primes::sieve(1_000_000)
+            .iter()
+            .map(|p| p.to_string().len() as u64 + 1)
+            .sum();
+        assert!(estimate_output_bytes(1_000_000, false) >= text_bytes);
+        assert!(estimate_output_bytes(1_000_000, true) >= 78_498 * 8);
+    }
+
+    #[test]
+    fn test_read_number_list() {
+        let path = std::env::temp_dir().join(format!("nt_numbers_{}.txt", std::process::id()));
+        fs::write(&path, "# twin primes\n3\n 5 \n\n11\n").unwrap();
+        assert_eq!(read_number_list(&path).unwrap(), vec![3, 5, 11]);
+        fs::write(&path, "3\nfive\n").unwrap();
+        let err = read_number_list(&path).unwrap_err();
+        assert!(err.to_string().starts_with("line 2:"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_primes_reads_either_format_and_shards() {
+        let dir = std::env::temp_dir().join(format!("nt_open_primes_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let bin: Vec<u8> = [2u64, 3, 5].iter().flat_map(|p| p.to_le_bytes()).collect();
+        fs::write(dir.join("a.bin"), bin).unwrap();
+        fs::write(dir.join("a.txt"), "2\n3\n5\n").unwrap();
+        fs::write(dir.join("primes_small.txt"), "2\n3\n").unwrap();
+        fs::write(dir.join("primes_1.txt"), "5\n7\n").unwrap();
+        fs::write(dir.join("primes_2.txt"), "11\n").unwrap();
+
+        let read = |name: &str| open_primes(&dir.join(name)).unwrap().collect::<Vec<_>>();
+        assert_eq!(read("a.bin"), vec![2, 3, 5]);
+        assert_eq!(read("a.txt"), vec![2, 3, 5]);
+        assert_eq!(read("primes_small.txt"), vec![2, 3, 5, 7, 11]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
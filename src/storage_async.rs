@@ -0,0 +1,495 @@
+// Backend-independent async I/O for the variation 9 binary consumers
+//
+// Linux uses io_uring (storage_uring.rs); other platforms, or Linux kernels without
+// io_uring support, fall back to a small thread pool issuing positioned writes.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tracing::{debug, error, info, warn};
+
+use crate::buffer_pool::BufferPool;
+use crate::primes::SegmentPrimes;
+use crate::storage::{commit_output, get_nt_data_dir, staging_path};
+use crate::storage_direct::{BinaryOutputOptions, preallocate};
+
+// Number of threads issuing positioned writes in the portable backend
+const PWRITE_THREADS: usize = 4;
+
+/// Writer that accepts buffers without blocking and completes them in the background
+/// Buffers are written back-to-back in submission order at increasing file offsets
+pub trait AsyncPrimeWriter {
+    /// Submit a write operation (non-blocking)
+    fn submit_write(&mut self, data: Vec<u8>) -> std::io::Result<()>;
+
+    /// Submit primes as little-endian u64s
+    /// Backends with their own buffer pools override this to skip the intermediate Vec
+    fn submit_primes(&mut self, primes: &[usize]) -> std::io::Result<()> {
+        let mut buffer = Vec::with_capacity(primes.len() * 8);
+        for &prime in primes {
+            buffer.extend_from_slice(&(prime as u64).to_le_bytes());
+        }
+        self.submit_write(buffer)
+    }
+
+    /// Submit all pending operations to the backend
+    fn submit_batch(&mut self) -> std::io::Result<()>;
+
+    /// Poll for completions (non-blocking)
+    fn poll_completions(&mut self) -> std::io::Result<usize>;
+
+    /// Wait for specific number of completions
+    fn wait_completions(&mut self, count: usize) -> std::io::Result<()>;
+
+    /// Get number of in-flight operations
+    fn in_flight(&self) -> usize;
+
+    /// Short name of the I/O backend for diagnostics
+    fn backend_name(&self) -> &'static str;
+}
+
+/// Open the best available async writer for this platform
+/// On Linux, tries io_uring first and falls back to the pwrite pool if the kernel refuses
+/// `pool_size` is the number of write buffers that may be in flight at once
+pub fn open_async_writer(
+    file: File,
+    queue_depth: u32,
+    pool_size: usize,
+) -> std::io::Result<Box<dyn AsyncPrimeWriter>> {
+    #[cfg(target_os = "linux")]
+    {
+        match crate::storage_uring::UringBatchWriter::new(file.try_clone()?, queue_depth, pool_size)
+        {
+            Ok(writer) => return Ok(Box::new(writer)),
+            Err(e) => warn!("io_uring unavailable ({}), falling back to pwrite pool", e),
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    let _ = queue_depth;
+
+    Ok(Box::new(PwriteBatchWriter::new(
+        file,
+        PWRITE_THREADS,
+        pool_size,
+    )))
+}
+
+struct WriteJob {
+    data: Vec<u8>,
+    offset: u64,
+}
+
+/// Portable batch writer: a pool of threads performing positioned writes
+/// Offsets are assigned at submission time, so completion order does not matter
+pub struct PwriteBatchWriter {
+    jobs: Option<mpsc::SyncSender<WriteJob>>,
+    completions: Receiver<std::io::Result<()>>,
+    workers: Vec<JoinHandle<()>>,
+    offset: u64,
+    submitted: usize,
+    completed: usize,
+}
+
+impl PwriteBatchWriter {
+    pub fn new(file: File, num_threads: usize, queue_depth: usize) -> Self {
+        let file = Arc::new(file);
+        let (job_tx, job_rx) = mpsc::sync_channel::<WriteJob>(queue_depth);
+        let job_rx = Arc::new(Mutex::new(job_rx));
+        let (done_tx, done_rx): (Sender<std::io::Result<()>>, _) = mpsc::channel();
+
+        let workers = (0..num_threads.max(1))
+            .map(|_| {
+                let file = Arc::clone(&file);
+                let job_rx = Arc::clone(&job_rx);
+                let done_tx = done_tx.clone();
+
+                thread::spawn(move || {
+                    loop {
+                        // Hold the lock only while taking a job, not while writing
+                        let job = match job_rx.lock().unwrap().recv() {
+                            Ok(job) => job,
+                            Err(_) => break, // Writer dropped, no more jobs
+                        };
+                        let result = write_all_at(&file, &job.data, job.offset);
+                        if done_tx.send(result).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            jobs: Some(job_tx),
+            completions: done_rx,
+            workers,
+            offset: 0,
+            submitted: 0,
+            completed: 0,
+        }
+    }
+
+    fn complete(&mut self, result: std::io::Result<()>) -> std::io::Result<()> {
+        self.completed += 1;
+        result
+    }
+}
+
+impl AsyncPrimeWriter for PwriteBatchWriter {
+    fn submit_write(&mut self, data: Vec<u8>) -> std::io::Result<()> {
+        let len = data.len() as u64;
+        let jobs = self
+            .jobs
+            .as_ref()
+            .ok_or_else(|| std::io::Error::other("writer closed"))?;
+
+        jobs.send(WriteJob {
+            data,
+            offset: self.offset,
+        })
+        .map_err(|_| std::io::Error::other("write pool stopped"))?;
+
+        self.offset += len;
+        self.submitted += 1;
+        Ok(())
+    }
+
+    fn submit_batch(&mut self) -> std::io::Result<()> {
+        // Jobs are dispatched to the pool as soon as they are submitted
+        Ok(())
+    }
+
+    fn poll_completions(&mut self) -> std::io::Result<usize> {
+        let mut completed_count = 0;
+        while let Ok(result) = self.completions.try_recv() {
+            self.complete(result)?;
+            completed_count += 1;
+        }
+        Ok(completed_count)
+    }
+
+    fn wait_completions(&mut self, count: usize) -> std::io::Result<()> {
+        for _ in 0..count {
+            let result = self
+                .completions
+                .recv()
+                .map_err(|_| std::io::Error::other("no completion"))?;
+            self.complete(result)?;
+        }
+        Ok(())
+    }
+
+    fn in_flight(&self) -> usize {
+        self.submitted - self.completed
+    }
+
+    fn backend_name(&self) -> &'static str {
+        "pwrite thread pool"
+    }
+}
+
+impl Drop for PwriteBatchWriter {
+    fn drop(&mut self) {
+        // Closing the job channel lets the workers drain and exit
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn write_all_at(file: &File, buf: &[u8], offset: u64) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.write_all_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn write_all_at(file: &File, mut buf: &[u8], mut offset: u64) -> std::io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        let written = file.seek_write(buf, offset)?;
+        if written == 0 {
+            return Err(std::io::ErrorKind::WriteZero.into());
+        }
+        buf = &buf[written..];
+        offset += written as u64;
+    }
+    Ok(())
+}
+
+/// Multi-consumer using async I/O (io_uring on Linux, pwrite thread pool elsewhere)
+/// Provides 2-3× better throughput on disk-bound workloads
+#[allow(clippy::too_many_arguments)]
+pub fn save_primes_multi_consumer_async(
+    rx: Receiver<SegmentPrimes>,
+    consumer_id: usize,
+    num_consumers: usize,
+    total_received: Arc<AtomicUsize>,
+    total_sent: Arc<AtomicUsize>,
+    options: BinaryOutputOptions,
+    io_buffers: usize,
+    pool: BufferPool<usize>,
+) -> usize {
+    const QUEUE_DEPTH: u32 = 256; // io_uring queue depth / pwrite pool queue size
+    const MAX_IN_FLIGHT: usize = 200; // Backpressure threshold
+    const BATCH_SIZE: usize = 64; // Submit every N segments
+
+    let mut count = 0;
+    let mut meter = crate::throughput::Meter::new();
+
+    let data_dir = match get_nt_data_dir().canonicalize() {
+        Ok(dir) => {
+            if let Err(e) = fs::create_dir_all(&dir) {
+                error!("Error creating data directory: {}", e);
+                return 0;
+            }
+            dir
+        }
+        Err(e) => {
+            error!("Error getting data directory: {}", e);
+            return 0;
+        }
+    };
+
+    let filename = format!("primes_{}.bin", consumer_id);
+    let primes_path = data_dir.join(&filename);
+
+    let file = match OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(staging_path(&primes_path))
+    {
+        Ok(f) => f,
+        Err(e) => {
+            error!("Error opening {}: {}", filename, e);
+            return 0;
+        }
+    };
+
+    // Async writes go straight from segment buffers, which aren't aligned for O_DIRECT
+    if options.direct_io && consumer_id == 1 {
+        warn!("Warning: --direct-io is not supported with --async-io, using buffered I/O");
+    }
+
+    if options.preallocate_bytes > 0
+        && let Err(e) = preallocate(&file, options.preallocate_bytes)
+    {
+        warn!("Warning: Could not preallocate {}: {}", filename, e);
+    }
+
+    // Second handle to trim the file to its real length once all writes complete
+    let trim_handle = file.try_clone().ok();
+
+    let mut writer = match open_async_writer(file, QUEUE_DEPTH, io_buffers) {
+        Ok(w) => w,
+        Err(e) => {
+            error!("Error creating async writer: {}", e);
+            return 0;
+        }
+    };
+
+    info!(
+        "Consumer {}: Using {} (queue depth: {})",
+        consumer_id,
+        writer.backend_name(),
+        QUEUE_DEPTH
+    );
+
+    // Reordering buffer for out-of-order segments
+    let mut segment_buffer: BTreeMap<usize, SegmentPrimes> = BTreeMap::new();
+    let mut next_expected_id = consumer_id;
+
+    let memory_report_interval = 1000;
+    let mut batch_count = 0;
+
+    // Peak tracking
+    let mut peak_buffer_size = 0;
+    let mut peak_in_flight = 0;
+
+    // Set by the first failed submit or wait; the file is then never committed
+    let mut failed = false;
+
+    // Process segments in order
+    for segment_primes in rx {
+        let segment_id = segment_primes.segment_id;
+
+        // Increment receive counter
+        total_received.fetch_add(1, Ordering::Relaxed);
+
+        // After a failure, keep draining so the producers are not blocked, but write nothing
+        if failed {
+            pool.give_back(segment_primes.primes);
+            continue;
+        }
+        segment_buffer.insert(segment_id, segment_primes);
+
+        // Process all consecutive segments for this consumer
+        while let Some(seg) = segment_buffer.remove(&next_expected_id) {
+            count += seg.primes.len();
+
+            // Submit write (non-blocking; io_uring encodes into its pooled buffers)
+            if let Err(e) = writer.submit_primes(&seg.primes) {
+                error!("Error submitting write: {}", e);
+                failed = true;
+                break;
+            }
+            crate::audit::record_segment(consumer_id, seg.segment_id);
+            meter.segment();
+            crate::gaps::record(seg.segment_id, &seg.primes);
+            crate::tui::record_consumer_segment(
+                consumer_id,
+                seg.primes.len() * 8,
+                segment_buffer.len(),
+            );
+            // Both backends copy the primes out, so the segment buffer can be recycled now
+            pool.give_back(seg.primes);
+
+            batch_count += 1;
+            next_expected_id += num_consumers;
+
+            // Submit batch periodically
+            if batch_count >= BATCH_SIZE {
+                if let Err(e) = writer.submit_batch() {
+                    error!("Error submitting batch: {}", e);
+                    failed = true;
+                    break;
+                }
+                batch_count = 0;
+            }
+
+            // Backpressure: if too many in-flight, wait for some to complete
+            if writer.in_flight() > MAX_IN_FLIGHT
+                && let Err(e) = writer.wait_completions(100)
+            {
+                error!("Error waiting for completions: {}", e);
+                failed = true;
+                break;
+            }
+
+            // Poll completions (non-blocking)
+            if let Err(e) = writer.poll_completions() {
+                error!("Error polling completions: {}", e);
+                failed = true;
+                break;
+            }
+
+            // Track peak in-flight
+            if writer.in_flight() > peak_in_flight {
+                peak_in_flight = writer.in_flight();
+            }
+
+            // Periodic memory reporting
+            if (next_expected_id / num_consumers) % memory_report_interval == 0 {
+                if let Some((rss_mb, _vm_mb)) = crate::storage::get_process_memory_mb() {
+                    let sent = total_sent.load(Ordering::Relaxed);
+                    let received = total_received.load(Ordering::Relaxed);
+                    let gap = sent.saturating_sub(received);
+                    debug!(
+                        "[Consumer {}/{}] Processed {} segments | Sent: {} | Received: {} | Gap: {} | In-flight: {} | RSS={:.2} MB",
+                        consumer_id,
+                        num_consumers,
+                        next_expected_id / num_consumers,
+                        sent,
+                        received,
+                        gap,
+                        writer.in_flight(),
+                        rss_mb
+                    );
+                }
+            }
+        }
+
+        // Track peak buffer size
+        if segment_buffer.len() > peak_buffer_size {
+            peak_buffer_size = segment_buffer.len();
+        }
+    }
+
+    // Final batch submission
+    if !failed && let Err(e) = writer.submit_batch() {
+        error!("Error submitting final batch: {}", e);
+        failed = true;
+    }
+
+    // Wait for all remaining completions
+    let remaining = writer.in_flight();
+    if remaining > 0
+        && let Err(e) = writer.wait_completions(remaining)
+    {
+        error!("Error waiting for final completions: {}", e);
+        failed = true;
+    }
+
+    // A partial file stays in staging and the previous output is left alone
+    if failed {
+        drop(writer);
+        error!(
+            "Consumer {}: {} is incomplete, left at {}",
+            consumer_id,
+            filename,
+            staging_path(&primes_path).display()
+        );
+        return 0;
+    }
+
+    if let Some(file) = trim_handle
+        && let Err(e) = file.set_len((count * 8) as u64)
+    {
+        error!("Error trimming {}: {}", filename, e);
+    }
+    drop(writer);
+    if let Err(e) = commit_output(&primes_path) {
+        error!("Error saving {}: {}", filename, e);
+    }
+    let throughput = meter.finish(count, count * 8);
+
+    info!(
+        "Consumer {}: Saved {} primes to {} | Peak buffer: {} segments | Peak in-flight: {} ops | {}",
+        consumer_id,
+        count,
+        filename,
+        peak_buffer_size,
+        peak_in_flight,
+        throughput.summary()
+    );
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_pwrite_writer_preserves_submission_order() {
+        let path = std::env::temp_dir().join(format!("nt_pwrite_test_{}.bin", std::process::id()));
+        let file = File::create(&path).unwrap();
+
+        let mut writer = PwriteBatchWriter::new(file, 4, 8);
+        for i in 0..100_u64 {
+            writer.submit_write(i.to_le_bytes().to_vec()).unwrap();
+            writer.poll_completions().unwrap();
+        }
+        let remaining = writer.in_flight();
+        writer.wait_completions(remaining).unwrap();
+        assert_eq!(writer.in_flight(), 0);
+        drop(writer);
+
+        let mut bytes = Vec::new();
+        File::open(&path).unwrap().read_to_end(&mut bytes).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let values: Vec<u64> = bytes
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(values, (0..100).collect::<Vec<u64>>());
+    }
+}
@@ -0,0 +1,249 @@
+// O_DIRECT and preallocation support for the binary prime writers
+//
+// Direct I/O bypasses the page cache so 100GB+ runs don't evict everything else on the
+// machine. The kernel requires buffer addresses, lengths, and file offsets to be
+// multiples of the logical block size, so writes go through an aligned staging buffer.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Seek, Write};
+use std::path::Path;
+use tracing::warn;
+
+// Alignment for O_DIRECT buffers, offsets, and lengths (covers 512B and 4K sector disks)
+const DIRECT_IO_ALIGN: usize = 4096;
+
+// Size of the aligned staging buffer for direct writes (must be a multiple of the alignment)
+const DIRECT_IO_BUFFER_SIZE: usize = 8 * 1024 * 1024;
+
+/// How binary output files are opened
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BinaryOutputOptions {
+    /// Open with O_DIRECT and write through an aligned buffer (Linux only)
+    pub direct_io: bool,
+    /// Reserve this many bytes on disk up front with fallocate (0 = no preallocation)
+    pub preallocate_bytes: u64,
+}
+
+/// Writer for binary prime files, either page-cache buffered or direct
+/// Call `finish` instead of `flush` so direct writes can pad and trim the final block
+pub enum BinaryWriter {
+    Buffered(BufWriter<File>),
+    Direct(DirectWriter),
+}
+
+impl BinaryWriter {
+    /// Write out any buffered data and leave the file at its exact logical length
+    pub fn finish(&mut self) -> std::io::Result<()> {
+        match self {
+            BinaryWriter::Buffered(writer) => {
+                writer.flush()?;
+                // Trim any preallocated space past the data actually written
+                let file = writer.get_mut();
+                let len = file.stream_position()?;
+                file.set_len(len)
+            }
+            BinaryWriter::Direct(writer) => writer.finish(),
+        }
+    }
+}
+
+impl Write for BinaryWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            BinaryWriter::Buffered(writer) => writer.write(buf),
+            BinaryWriter::Direct(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            BinaryWriter::Buffered(writer) => writer.flush(),
+            // Partial blocks can't be written with O_DIRECT; they are handled by finish()
+            BinaryWriter::Direct(_) => Ok(()),
+        }
+    }
+}
+
+/// Open (create + truncate) a binary output file according to `options`
+/// `buffer_capacity` is used for the buffered writer; direct writers use a fixed aligned buffer
+pub fn open_binary_output(
+    path: &Path,
+    options: &BinaryOutputOptions,
+    buffer_capacity: usize,
+) -> std::io::Result<BinaryWriter> {
+    let mut open_options = OpenOptions::new();
+    open_options.create(true).write(true).truncate(true);
+
+    if options.direct_io {
+        set_direct_flag(&mut open_options);
+    }
+
+    let file = open_options.open(path)?;
+
+    if options.preallocate_bytes > 0
+        && let Err(e) = preallocate(&file, options.preallocate_bytes)
+    {
+        warn!(
+            "Warning: Could not preallocate {} bytes for {}: {}",
+            options.preallocate_bytes,
+            path.display(),
+            e
+        );
+    }
+
+    if options.direct_io {
+        Ok(BinaryWriter::Direct(DirectWriter::new(file)))
+    } else {
+        Ok(BinaryWriter::Buffered(BufWriter::with_capacity(
+            buffer_capacity,
+            file,
+        )))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_direct_flag(open_options: &mut OpenOptions) {
+    use std::os::unix::fs::OpenOptionsExt;
+    open_options.custom_flags(libc::O_DIRECT);
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_direct_flag(_open_options: &mut OpenOptions) {
+    warn!("Warning: --direct-io is only supported on Linux, using buffered I/O");
+}
+
+/// Reserve disk blocks for the file up front so large runs don't fragment or fail late
+#[cfg(target_os = "linux")]
+pub fn preallocate(file: &File, bytes: u64) -> std::io::Result<()> {
+    use std::os::unix::io::AsRawFd;
+
+    // The file grows to the estimated size; writers trim it back to the real length
+    // when they finish, which also releases any blocks the estimate overshot
+    let result = unsafe { libc::fallocate(file.as_raw_fd(), 0, 0, bytes as libc::off_t) };
+
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn preallocate(_file: &File, _bytes: u64) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "fallocate is only available on Linux",
+    ))
+}
+
+/// Sequential writer for files opened with O_DIRECT
+/// Stages data in an aligned buffer and only ever writes whole aligned blocks
+pub struct DirectWriter {
+    file: File,
+    // Over-allocated by DIRECT_IO_ALIGN so an aligned window can be carved out safely
+    storage: Vec<u8>,
+    start: usize,
+    len: usize,
+    bytes_written: u64,
+}
+
+impl DirectWriter {
+    fn new(file: File) -> Self {
+        let storage = vec![0_u8; DIRECT_IO_BUFFER_SIZE + DIRECT_IO_ALIGN];
+        let start = storage.as_ptr().align_offset(DIRECT_IO_ALIGN);
+
+        Self {
+            file,
+            storage,
+            start,
+            len: 0,
+            bytes_written: 0,
+        }
+    }
+
+    fn buffer(&mut self) -> &mut [u8] {
+        &mut self.storage[self.start..self.start + DIRECT_IO_BUFFER_SIZE]
+    }
+
+    /// Write the whole staging buffer (always a multiple of the alignment)
+    fn write_full_buffer(&mut self) -> std::io::Result<()> {
+        let start = self.start;
+        self.file
+            .write_all(&self.storage[start..start + DIRECT_IO_BUFFER_SIZE])?;
+        self.bytes_written += DIRECT_IO_BUFFER_SIZE as u64;
+        self.len = 0;
+        Ok(())
+    }
+
+    /// Pad the last partial block with zeros, write it, then trim the padding off
+    fn finish(&mut self) -> std::io::Result<()> {
+        if self.len == 0 {
+            // Still trim preallocated space past the last full block
+            return self.file.set_len(self.bytes_written);
+        }
+
+        let logical_len = self.bytes_written + self.len as u64;
+        let padded_len = self.len.div_ceil(DIRECT_IO_ALIGN) * DIRECT_IO_ALIGN;
+        let len = self.len;
+        self.buffer()[len..padded_len].fill(0);
+
+        let start = self.start;
+        self.file
+            .write_all(&self.storage[start..start + padded_len])?;
+        self.file.set_len(logical_len)?;
+
+        self.bytes_written = logical_len;
+        self.len = 0;
+        Ok(())
+    }
+}
+
+impl Write for DirectWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // Write out a full buffer before taking new bytes, so a failed write returns Err
+        // without having accepted any of `buf`
+        if self.len == DIRECT_IO_BUFFER_SIZE {
+            self.write_full_buffer()?;
+        }
+
+        let space = DIRECT_IO_BUFFER_SIZE - self.len;
+        let n = buf.len().min(space);
+        let len = self.len;
+        self.buffer()[len..len + n].copy_from_slice(&buf[..n]);
+        self.len += n;
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failed_block_write_accepts_no_bytes() {
+        let path = std::env::temp_dir().join(format!("nt_direct_{}", std::process::id()));
+        std::fs::write(&path, b"").unwrap();
+
+        // Read-only, so writing the first full buffer fails
+        let mut writer = DirectWriter::new(File::open(&path).unwrap());
+        let block = vec![7_u8; DIRECT_IO_BUFFER_SIZE];
+        assert_eq!(writer.write(&block).unwrap(), DIRECT_IO_BUFFER_SIZE);
+        assert!(writer.write(&[1, 2, 3]).is_err());
+        assert_eq!(writer.len, DIRECT_IO_BUFFER_SIZE);
+
+        // The same bytes through a writable file all arrive, in order
+        let mut writer = DirectWriter::new(File::create(&path).unwrap());
+        writer.write_all(&block).unwrap();
+        writer.write_all(&[1, 2, 3]).unwrap();
+        writer.finish().unwrap();
+        let written = std::fs::read(&path).unwrap();
+        assert_eq!(written.len(), DIRECT_IO_BUFFER_SIZE + 3);
+        assert_eq!(written[DIRECT_IO_BUFFER_SIZE..], [1, 2, 3]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
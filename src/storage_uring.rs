@@ -1,64 +1,159 @@
 // io_uring-based async I/O implementation for maximum disk throughput
+//
+// Uses registered (fixed) buffers from a reusable pool and a registered file descriptor,
+// so steady-state writes need no allocation and the kernel skips per-op fd/page lookups.
 
 use io_uring::{IoUring, opcode, types};
-use std::collections::{BTreeMap, VecDeque};
-use std::fs::{self, File, OpenOptions};
+use std::fs::File;
 use std::os::unix::io::AsRawFd;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::mpsc::Receiver;
 
-use crate::primes::SegmentPrimes;
-use crate::storage::get_nt_data_dir;
+use crate::storage_async::AsyncPrimeWriter;
+
+// Size of each registered buffer; larger writes are split across several buffers
+const FIXED_BUFFER_SIZE: usize = 256 * 1024;
+
+// Index of our file in the registered file table
+const FIXED_FILE_INDEX: u32 = 0;
 
 /// Batch writer using io_uring for async I/O
-struct UringBatchWriter {
+pub struct UringBatchWriter {
     ring: IoUring,
-    file: File,  // Keep file alive to prevent FD from being closed
-    pending_buffers: VecDeque<Vec<u8>>,
+    _file: File, // Keep file alive to prevent FD from being closed
+    // Registered buffer pool; never reallocated after registration
+    buffers: Vec<Vec<u8>>,
+    free_buffers: Vec<u16>,
     offset: u64,
     submitted: usize,
     completed: usize,
 }
 
 impl UringBatchWriter {
-    fn new(file: File, queue_depth: u32) -> std::io::Result<Self> {
+    /// Create a writer with `pool_size` registered buffers of FIXED_BUFFER_SIZE bytes
+    /// Registration counts against RLIMIT_MEMLOCK on older kernels
+    pub fn new(file: File, queue_depth: u32, pool_size: usize) -> std::io::Result<Self> {
+        let pool_size = pool_size.clamp(1, u16::MAX as usize);
+
+        // Every buffer may be in flight at once, so the queue must hold the whole pool
+        let queue_depth = queue_depth.max(pool_size.next_power_of_two() as u32);
+        let ring = IoUring::new(queue_depth)?;
+
+        ring.submitter().register_files(&[file.as_raw_fd()])?;
+
+        let mut buffers: Vec<Vec<u8>> = (0..pool_size)
+            .map(|_| vec![0_u8; FIXED_BUFFER_SIZE])
+            .collect();
+        let iovecs: Vec<libc::iovec> = buffers
+            .iter_mut()
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_mut_ptr() as *mut libc::c_void,
+                iov_len: buffer.len(),
+            })
+            .collect();
+
+        // Safety: the buffers live as long as the ring and are never resized
+        unsafe { ring.submitter().register_buffers(&iovecs)? };
+
         Ok(Self {
-            ring: IoUring::new(queue_depth)?,
-            file,
-            pending_buffers: VecDeque::new(),
+            ring,
+            _file: file,
+            buffers,
+            free_buffers: (0..pool_size as u16).rev().collect(),
             offset: 0,
             submitted: 0,
             completed: 0,
         })
     }
 
-    /// Submit a write operation (non-blocking)
-    fn submit_write(&mut self, data: Vec<u8>) -> std::io::Result<()> {
-        let len = data.len();
+    /// Take a free buffer from the pool, waiting for an in-flight write if none is free
+    fn acquire_buffer(&mut self) -> std::io::Result<u16> {
+        loop {
+            if let Some(index) = self.free_buffers.pop() {
+                return Ok(index);
+            }
+            self.wait_completions(1)?;
+        }
+    }
 
-        // Create write operation
-        let write_op = opcode::Write::new(
-            types::Fd(self.file.as_raw_fd()),
-            data.as_ptr(),
+    /// Queue a write of the first `len` bytes of registered buffer `index`
+    fn push_fixed_write(&mut self, index: u16, len: usize) -> std::io::Result<()> {
+        let write_op = opcode::WriteFixed::new(
+            types::Fixed(FIXED_FILE_INDEX),
+            self.buffers[index as usize].as_ptr(),
             len as u32,
+            index,
         )
-        .offset(self.offset);
+        .offset(self.offset)
+        .build()
+        .user_data(((len as u64) << 16) | index as u64);
 
-        // Submit to submission queue
+        // Safety: the buffer stays checked out of the pool until its completion arrives
         unsafe {
-            self.ring
-                .submission()
-                .push(&write_op.build())
-                .map_err(|_| {
-                    std::io::Error::new(std::io::ErrorKind::Other, "submission queue full")
-                })?;
+            if self.ring.submission().push(&write_op).is_err() {
+                // Queue full: hand what we have to the kernel and retry once
+                self.ring.submit()?;
+                self.ring
+                    .submission()
+                    .push(&write_op)
+                    .map_err(|_| std::io::Error::other("submission queue full"))?;
+            }
         }
 
-        self.pending_buffers.push_back(data); // Keep buffer alive
         self.offset += len as u64;
         self.submitted += 1;
+        Ok(())
+    }
+
+    /// Copy bytes into pooled buffers and queue them
+    fn submit_bytes(&mut self, mut data: &[u8]) -> std::io::Result<()> {
+        while !data.is_empty() {
+            let index = self.acquire_buffer()?;
+            let len = data.len().min(FIXED_BUFFER_SIZE);
+            self.buffers[index as usize][..len].copy_from_slice(&data[..len]);
+            self.push_fixed_write(index, len)?;
+            data = &data[len..];
+        }
+        Ok(())
+    }
+
+    /// Return a completed buffer to the pool, checking the write result
+    fn complete(&mut self, user_data: u64, result: i32) -> std::io::Result<()> {
+        let index = (user_data & 0xFFFF) as u16;
+        let expected_len = (user_data >> 16) as usize;
+
+        self.free_buffers.push(index);
+        self.completed += 1;
+
+        if result < 0 {
+            return Err(std::io::Error::from_raw_os_error(-result));
+        }
+        if result as usize != expected_len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WriteZero,
+                format!("short write: {} of {} bytes", result, expected_len),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl AsyncPrimeWriter for UringBatchWriter {
+    /// Submit a write operation (non-blocking unless the buffer pool is exhausted)
+    fn submit_write(&mut self, data: Vec<u8>) -> std::io::Result<()> {
+        self.submit_bytes(&data)
+    }
+
+    /// Encode primes straight into registered buffers (no intermediate Vec<u8>)
+    fn submit_primes(&mut self, primes: &[usize]) -> std::io::Result<()> {
+        const PRIMES_PER_BUFFER: usize = FIXED_BUFFER_SIZE / 8;
 
+        for chunk in primes.chunks(PRIMES_PER_BUFFER) {
+            let index = self.acquire_buffer()?;
+            let buffer = &mut self.buffers[index as usize];
+            for (slot, &prime) in buffer.chunks_exact_mut(8).zip(chunk) {
+                slot.copy_from_slice(&(prime as u64).to_le_bytes());
+            }
+            self.push_fixed_write(index, chunk.len() * 8)?;
+        }
         Ok(())
     }
 
@@ -72,12 +167,11 @@ impl UringBatchWriter {
     fn poll_completions(&mut self) -> std::io::Result<usize> {
         let mut completed_count = 0;
 
-        while let Some(cqe) = self.ring.completion().next() {
-            if cqe.result() < 0 {
-                return Err(std::io::Error::from_raw_os_error(-cqe.result()));
-            }
-            self.pending_buffers.pop_front(); // Free buffer
-            self.completed += 1;
+        loop {
+            let Some(cqe) = self.ring.completion().next() else {
+                break;
+            };
+            self.complete(cqe.user_data(), cqe.result())?;
             completed_count += 1;
         }
 
@@ -88,16 +182,13 @@ impl UringBatchWriter {
     fn wait_completions(&mut self, count: usize) -> std::io::Result<()> {
         for _ in 0..count {
             self.ring.submit_and_wait(1)?;
-            let cqe =
-                self.ring.completion().next().ok_or_else(|| {
-                    std::io::Error::new(std::io::ErrorKind::Other, "no completion")
-                })?;
+            let cqe = self
+                .ring
+                .completion()
+                .next()
+                .ok_or_else(|| std::io::Error::other("no completion"))?;
 
-            if cqe.result() < 0 {
-                return Err(std::io::Error::from_raw_os_error(-cqe.result()));
-            }
-            self.pending_buffers.pop_front();
-            self.completed += 1;
+            self.complete(cqe.user_data(), cqe.result())?;
         }
         Ok(())
     }
@@ -106,178 +197,8 @@ impl UringBatchWriter {
     fn in_flight(&self) -> usize {
         self.submitted - self.completed
     }
-}
-
-/// Multi-consumer using io_uring for async I/O
-/// Provides 2-3× better throughput on disk-bound workloads
-pub fn save_primes_multi_consumer_uring(
-    rx: Receiver<SegmentPrimes>,
-    consumer_id: usize,
-    num_consumers: usize,
-    total_received: Arc<AtomicUsize>,
-    total_sent: Arc<AtomicUsize>,
-) -> usize {
-    const QUEUE_DEPTH: u32 = 256; // io_uring queue depth
-    const MAX_IN_FLIGHT: usize = 200; // Backpressure threshold
-    const BATCH_SIZE: usize = 64; // Submit every N segments
-
-    let mut count = 0;
-
-    let data_dir = match get_nt_data_dir().canonicalize() {
-        Ok(dir) => {
-            if let Err(e) = fs::create_dir_all(&dir) {
-                eprintln!("Error creating data directory: {}", e);
-                return 0;
-            }
-            dir
-        }
-        Err(e) => {
-            eprintln!("Error getting data directory: {}", e);
-            return 0;
-        }
-    };
-
-    let filename = format!("primes_{}.bin", consumer_id);
-    let primes_path = data_dir.join(&filename);
-
-    let file = match OpenOptions::new()
-        .create(true)
-        .write(true)
-        .truncate(true)
-        .open(&primes_path)
-    {
-        Ok(f) => f,
-        Err(e) => {
-            eprintln!("Error opening {}: {}", filename, e);
-            return 0;
-        }
-    };
-
-    eprintln!(
-        "Consumer {}: Using io_uring (queue depth: {})",
-        consumer_id, QUEUE_DEPTH
-    );
-
-    let mut writer = match UringBatchWriter::new(file, QUEUE_DEPTH) {
-        Ok(w) => w,
-        Err(e) => {
-            eprintln!("Error creating io_uring writer: {}", e);
-            return 0;
-        }
-    };
-
-    // Reordering buffer for out-of-order segments
-    let mut segment_buffer: BTreeMap<usize, SegmentPrimes> = BTreeMap::new();
-    let mut next_expected_id = consumer_id;
-
-    let memory_report_interval = 1000;
-    let mut batch_count = 0;
-
-    // Peak tracking
-    let mut peak_buffer_size = 0;
-    let mut peak_in_flight = 0;
-
-    // Process segments in order
-    for segment_primes in rx {
-        let segment_id = segment_primes.segment_id;
-
-        // Increment receive counter
-        total_received.fetch_add(1, Ordering::Relaxed);
-
-        segment_buffer.insert(segment_id, segment_primes);
-
-        // Process all consecutive segments for this consumer
-        while let Some(seg) = segment_buffer.remove(&next_expected_id) {
-            // Convert primes to bytes
-            let mut buffer = Vec::with_capacity(seg.primes.len() * 8);
-            for &prime in &seg.primes {
-                buffer.extend_from_slice(&prime.to_le_bytes());
-            }
-
-            count += seg.primes.len();
-
-            // Submit write (non-blocking)
-            if let Err(e) = writer.submit_write(buffer) {
-                eprintln!("Error submitting write: {}", e);
-                break;
-            }
-
-            batch_count += 1;
-            next_expected_id += num_consumers;
-
-            // Submit batch periodically
-            if batch_count >= BATCH_SIZE {
-                if let Err(e) = writer.submit_batch() {
-                    eprintln!("Error submitting batch: {}", e);
-                    break;
-                }
-                batch_count = 0;
-            }
-
-            // Backpressure: if too many in-flight, wait for some to complete
-            if writer.in_flight() > MAX_IN_FLIGHT {
-                if let Err(e) = writer.wait_completions(100) {
-                    eprintln!("Error waiting for completions: {}", e);
-                    break;
-                }
-            }
-
-            // Poll completions (non-blocking)
-            if let Err(e) = writer.poll_completions() {
-                eprintln!("Error polling completions: {}", e);
-                //exit program
-                std::process::exit(1);
-            }
-
-            // Track peak in-flight
-            if writer.in_flight() > peak_in_flight {
-                peak_in_flight = writer.in_flight();
-            }
-
-            // Periodic memory reporting
-            if (next_expected_id / num_consumers) % memory_report_interval == 0 {
-                if let Some((rss_mb, _vm_mb)) = crate::storage::get_process_memory_mb() {
-                    let sent = total_sent.load(Ordering::Relaxed);
-                    let received = total_received.load(Ordering::Relaxed);
-                    let gap = sent.saturating_sub(received);
-                    eprintln!(
-                        "[Consumer {}/{}] Processed {} segments | Sent: {} | Received: {} | Gap: {} | In-flight: {} | RSS={:.2} MB",
-                        consumer_id,
-                        num_consumers,
-                        next_expected_id / num_consumers,
-                        sent,
-                        received,
-                        gap,
-                        writer.in_flight(),
-                        rss_mb
-                    );
-                }
-            }
-        }
-
-        // Track peak buffer size
-        if segment_buffer.len() > peak_buffer_size {
-            peak_buffer_size = segment_buffer.len();
-        }
-    }
 
-    // Final batch submission
-    if let Err(e) = writer.submit_batch() {
-        eprintln!("Error submitting final batch: {}", e);
+    fn backend_name(&self) -> &'static str {
+        "io_uring (fixed buffers)"
     }
-
-    // Wait for all remaining completions
-    let remaining = writer.in_flight();
-    if remaining > 0 {
-        if let Err(e) = writer.wait_completions(remaining) {
-            eprintln!("Error waiting for final completions: {}", e);
-        }
-    }
-
-    println!(
-        "Consumer {}: Saved {} primes to {} | Peak buffer: {} segments | Peak in-flight: {} ops",
-        consumer_id, count, filename, peak_buffer_size, peak_in_flight
-    );
-
-    count
 }
@@ -0,0 +1,660 @@
+// Output strategy shared by every consumer: format × ordering × backend
+//
+// - Format: SegmentEncoding::Text (one decimal prime per line) or Binary (little-endian u64)
+// - Ordering: in-order streams write as they receive; parallel variations put segments
+//   through a Reorder buffer keyed by segment id first
+// - Backend: BinaryWriter (page-cache buffered or O_DIRECT, for either format), or the
+//   async writers in storage_async.rs for binary shards
+//
+// Every variation picks one of each instead of having its own text and binary consumer.
+//
+// A PrimeWriter can also hand its writes to a thread of their own (--write-behind): the
+// consumer formats the next segment into one buffer while the previous one is being
+// written from the other, so formatting text and waiting on the disk overlap instead of
+// taking turns. And it can pass everything it writes on to more PrimeWriters (--tee), so
+// one run leaves both primes.txt and primes.bin, or prints the primes on stdout as well.
+// Tees are set once for the whole run, like atomic writes, and every writer opened after
+// that picks them up. The same goes for --output: the first single-file writer opened
+// after `redirect_output` writes to that pipe or socket instead of its file.
+
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::{self, JoinHandle};
+use tracing::warn;
+
+use crate::segment_format::SegmentEncoding;
+use crate::storage::{commit_output, get_nt_data_dir, staging_path};
+use crate::storage_direct::{BinaryOutputOptions, BinaryWriter, open_binary_output};
+
+/// File name for `stem` in the given format, e.g. primes.txt or primes_3.bin
+pub fn file_name(stem: &str, encoding: SegmentEncoding) -> String {
+    match encoding {
+        SegmentEncoding::Text => format!("{}.txt", stem),
+        SegmentEncoding::Binary => format!("{}.bin", stem),
+    }
+}
+
+// Buffers shared by the formatting and writing threads: one filling while one is written
+const WRITE_BEHIND_BUFFERS: usize = 2;
+
+// Primes written one at a time are handed over in batches of this many bytes
+const WRITE_BEHIND_BATCH: usize = 256 * 1024;
+
+/// Extra destination for everything the run writes (--tee)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum TeeTarget {
+    /// `<stem>.txt` beside each binary output
+    Text,
+    /// `<stem>.bin` beside each text output
+    Binary,
+    /// The primes as text on standard output (single-file outputs, not shards)
+    Stdout,
+}
+
+static TEES: Mutex<Vec<TeeTarget>> = Mutex::new(Vec::new());
+
+/// Have every PrimeWriter created from now on also write to `targets`
+pub fn tee_outputs(targets: &[TeeTarget]) {
+    *TEES.lock().unwrap() = targets.to_vec();
+}
+
+// Stream taken by the next single-file writer in place of its file (--output)
+type Redirect = (String, Box<dyn Write + Send>);
+static REDIRECT: Mutex<Option<Redirect>> = Mutex::new(None);
+
+/// Have the next PrimeWriter created for a single-file output write to `stream` instead;
+/// `name` is used in log messages
+pub fn redirect_output(name: &str, stream: Box<dyn Write + Send>) {
+    *REDIRECT.lock().unwrap() = Some((name.to_string(), stream));
+}
+
+/// Writes primes to one output file in the data directory, or to a stream
+/// A file goes to a staging file until `finish`, which moves it into place, unless a
+/// write failed along the way
+pub struct PrimeWriter {
+    sink: Sink,
+    encoding: SegmentEncoding,
+    // None for streams, which have nothing to move into place
+    path: Option<PathBuf>,
+    name: String,
+    // Reused per call so a whole segment goes out in one write; with write-behind, the
+    // buffer being filled for the writing thread
+    scratch: Vec<u8>,
+    itoa_buf: itoa::Buffer,
+    count: usize,
+    bytes: usize,
+    // Gets a copy of every prime written here (--tee)
+    tee: Option<Box<PrimeWriter>>,
+    // First error writing to this writer's own output; every later write and `finish`
+    // return it, so a file missing primes is never moved into place
+    failed: Option<io::Error>,
+}
+
+enum Sink {
+    File(BinaryWriter),
+    Stream(BufWriter<Box<dyn Write + Send>>),
+    Behind(WriteBehind),
+}
+
+impl Sink {
+    /// The writer to write to on this thread, None when a write-behind thread does it
+    fn direct(&mut self) -> Option<&mut dyn Write> {
+        match self {
+            Sink::File(writer) => Some(writer),
+            Sink::Stream(writer) => Some(writer),
+            Sink::Behind(_) => None,
+        }
+    }
+}
+
+// The writing thread and the ring of buffers passed back and forth with it
+struct WriteBehind {
+    filled: SyncSender<Vec<u8>>,
+    emptied: Receiver<Vec<u8>>,
+    thread: JoinHandle<io::Result<BinaryWriter>>,
+}
+
+impl WriteBehind {
+    fn start(mut writer: BinaryWriter, buffer_capacity: usize) -> Self {
+        let (filled, to_write) = mpsc::sync_channel::<Vec<u8>>(WRITE_BEHIND_BUFFERS);
+        let (written, emptied) = mpsc::channel();
+        // The formatting side starts out holding one buffer
+        for _ in 1..WRITE_BEHIND_BUFFERS {
+            let _ = written.send(Vec::with_capacity(buffer_capacity));
+        }
+
+        let thread = thread::spawn(move || {
+            for mut bytes in to_write {
+                writer.write_all(&bytes)?;
+                bytes.clear();
+                let _ = written.send(bytes);
+            }
+            Ok(writer)
+        });
+
+        Self {
+            filled,
+            emptied,
+            thread,
+        }
+    }
+
+    /// Send `bytes` to be written and get back an empty buffer once one is free
+    fn hand_off(&self, bytes: &mut Vec<u8>) -> io::Result<()> {
+        let stopped = || io::Error::other("write-behind thread stopped");
+        let empty = self.emptied.recv().map_err(|_| stopped())?;
+        let full = std::mem::replace(bytes, empty);
+        self.filled.send(full).map_err(|_| stopped())
+    }
+
+    /// Write what is left and take the writer back from its thread
+    fn finish(self, last: Vec<u8>) -> io::Result<BinaryWriter> {
+        if !last.is_empty() {
+            // A failed send means the thread already stopped on an error, returned by join
+            let _ = self.filled.send(last);
+        }
+        drop(self.filled);
+        self.thread
+            .join()
+            .map_err(|_| io::Error::other("write-behind thread panicked"))?
+    }
+}
+
+/// io::Error is not Clone; keep its kind and message
+fn copy_error(e: &io::Error) -> io::Error {
+    io::Error::new(e.kind(), e.to_string())
+}
+
+/// Append `prime` to `out` in `encoding`
+#[inline]
+fn encode(encoding: SegmentEncoding, itoa_buf: &mut itoa::Buffer, out: &mut Vec<u8>, prime: usize) {
+    match encoding {
+        SegmentEncoding::Text => {
+            out.extend_from_slice(itoa_buf.format(prime).as_bytes());
+            out.push(b'\n');
+        }
+        SegmentEncoding::Binary => out.extend_from_slice(&(prime as u64).to_le_bytes()),
+    }
+}
+
+impl PrimeWriter {
+    /// Create (or truncate) `<stem>.txt` / `<stem>.bin` in the data directory, with the
+    /// run's tees, or write to the run's --output stream in its place
+    pub fn create(
+        stem: &str,
+        encoding: SegmentEncoding,
+        options: &BinaryOutputOptions,
+        buffer_capacity: usize,
+    ) -> io::Result<Self> {
+        // Shards cover alternating ranges, so only single-file outputs are redirected
+        let redirect = if stem.starts_with("primes_") {
+            None
+        } else {
+            REDIRECT.lock().unwrap().take()
+        };
+        let mut writer = match redirect {
+            Some((name, stream)) => Self::to_stream(&name, encoding, stream),
+            None => Self::open(stem, encoding, options, buffer_capacity)?,
+        };
+
+        let targets = TEES.lock().unwrap().clone();
+        for target in targets {
+            let tee = match target {
+                TeeTarget::Text | TeeTarget::Binary => {
+                    let tee_encoding = match target {
+                        TeeTarget::Text => SegmentEncoding::Text,
+                        _ => SegmentEncoding::Binary,
+                    };
+                    if tee_encoding == encoding {
+                        continue;
+                    }
+                    // Preallocation was sized for the other format
+                    let options = BinaryOutputOptions {
+                        preallocate_bytes: 0,
+                        ..*options
+                    };
+                    Self::open(stem, tee_encoding, &options, buffer_capacity)?
+                }
+                // Shards cover alternating ranges, so their primes would come out of order
+                TeeTarget::Stdout if stem.starts_with("primes_") => continue,
+                TeeTarget::Stdout => {
+                    Self::to_stream("stdout", SegmentEncoding::Text, Box::new(io::stdout()))
+                }
+            };
+            writer = writer.with_tee(tee);
+        }
+        Ok(writer)
+    }
+
+    /// `create` without the tees
+    fn open(
+        stem: &str,
+        encoding: SegmentEncoding,
+        options: &BinaryOutputOptions,
+        buffer_capacity: usize,
+    ) -> io::Result<Self> {
+        let data_dir = get_nt_data_dir();
+        fs::create_dir_all(&data_dir)?;
+
+        let name = file_name(stem, encoding);
+        let path = data_dir.join(&name);
+        let writer = open_binary_output(&staging_path(&path), options, buffer_capacity)?;
+        Ok(Self::new(Sink::File(writer), encoding, Some(path), name))
+    }
+
+    /// Write to `stream` instead of a file; `name` is used in log messages
+    pub fn to_stream(name: &str, encoding: SegmentEncoding, stream: Box<dyn Write + Send>) -> Self {
+        let sink = Sink::Stream(BufWriter::with_capacity(256 * 1024, stream));
+        Self::new(sink, encoding, None, name.to_string())
+    }
+
+    fn new(sink: Sink, encoding: SegmentEncoding, path: Option<PathBuf>, name: String) -> Self {
+        Self {
+            sink,
+            encoding,
+            path,
+            name,
+            scratch: Vec::new(),
+            itoa_buf: itoa::Buffer::new(),
+            count: 0,
+            bytes: 0,
+            tee: None,
+            failed: None,
+        }
+    }
+
+    /// Also write everything to `tee`, after any tees already attached
+    pub fn with_tee(mut self, tee: PrimeWriter) -> Self {
+        self.tee = Some(Box::new(match self.tee.take() {
+            Some(first) => first.with_tee(tee),
+            None => tee,
+        }));
+        self
+    }
+
+    /// Do the writes on a thread of their own from now on (--write-behind)
+    /// Streams are left as they are
+    pub fn write_behind(mut self) -> Self {
+        if let Sink::File(writer) = self.sink {
+            self.scratch = Vec::with_capacity(WRITE_BEHIND_BATCH);
+            self.sink = Sink::Behind(WriteBehind::start(writer, WRITE_BEHIND_BATCH));
+        }
+        self
+    }
+
+    /// File name being written, for log messages
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Primes written so far
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Bytes written so far
+    pub fn bytes_written(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn write_prime(&mut self, prime: usize) -> io::Result<()> {
+        self.check_failed()?;
+        let written = self.write_prime_to_sink(prime);
+        self.record(written)?;
+        self.count += 1;
+        self.write_tee(|tee| tee.write_prime(prime))
+    }
+
+    fn write_prime_to_sink(&mut self, prime: usize) -> io::Result<()> {
+        if let Some(writer) = self.sink.direct() {
+            match self.encoding {
+                SegmentEncoding::Text => {
+                    let digits = self.itoa_buf.format(prime).as_bytes();
+                    writer.write_all(digits)?;
+                    writer.write_all(b"\n")?;
+                    self.bytes += digits.len() + 1;
+                }
+                SegmentEncoding::Binary => {
+                    writer.write_all(&(prime as u64).to_le_bytes())?;
+                    self.bytes += 8;
+                }
+            }
+        } else if let Sink::Behind(behind) = &self.sink {
+            let before = self.scratch.len();
+            encode(self.encoding, &mut self.itoa_buf, &mut self.scratch, prime);
+            self.bytes += self.scratch.len() - before;
+            if self.scratch.len() >= WRITE_BEHIND_BATCH {
+                behind.hand_off(&mut self.scratch)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Format a batch of primes and write it with a single call
+    pub fn write_primes(&mut self, primes: &[usize]) -> io::Result<()> {
+        self.check_failed()?;
+        if !matches!(self.sink, Sink::Behind(_)) {
+            self.scratch.clear();
+        }
+        let before = self.scratch.len();
+        for &prime in primes {
+            encode(self.encoding, &mut self.itoa_buf, &mut self.scratch, prime);
+        }
+        let bytes = self.scratch.len() - before;
+        let written = match &mut self.sink {
+            Sink::Behind(behind) => behind.hand_off(&mut self.scratch),
+            sink => match sink.direct() {
+                Some(writer) => writer.write_all(&self.scratch),
+                None => Ok(()),
+            },
+        };
+        self.record(written)?;
+        self.count += primes.len();
+        self.bytes += bytes;
+        self.write_tee(|tee| tee.write_primes(primes))
+    }
+
+    /// Write bytes a worker already encoded in this writer's format (--preformat)
+    /// Tees cannot be fed this way, since they may want the other format
+    pub fn write_encoded(&mut self, bytes: &[u8], count: usize) -> io::Result<()> {
+        if self.tee.is_some() {
+            return Err(io::Error::other("--tee cannot copy preformatted segments"));
+        }
+        self.check_failed()?;
+        let written = match &mut self.sink {
+            Sink::Behind(behind) => {
+                self.scratch.extend_from_slice(bytes);
+                behind.hand_off(&mut self.scratch)
+            }
+            sink => match sink.direct() {
+                Some(writer) => writer.write_all(bytes),
+                None => Ok(()),
+            },
+        };
+        self.record(written)?;
+        self.count += count;
+        self.bytes += bytes.len();
+        Ok(())
+    }
+
+    /// The earlier write error, if there was one
+    fn check_failed(&self) -> io::Result<()> {
+        match &self.failed {
+            Some(e) => Err(copy_error(e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Keep the first error from this writer's own output
+    fn record(&mut self, written: io::Result<()>) -> io::Result<()> {
+        if let Err(e) = &written
+            && self.failed.is_none()
+        {
+            self.failed = Some(copy_error(e));
+        }
+        written
+    }
+
+    /// Pass a write on to the tee; a stream whose reader went away (`| head`) is dropped
+    /// instead of failing the run
+    fn write_tee(
+        &mut self,
+        write: impl FnOnce(&mut PrimeWriter) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let Some(tee) = self.tee.as_mut() else {
+            return Ok(());
+        };
+        match write(tee) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe && tee.path.is_none() => {
+                warn!("{} closed, no longer writing to it", tee.name);
+                self.tee = tee.tee.take();
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Flush, trim and move the file into place; returns the number of primes written
+    /// On error, now or in an earlier write, the staging file is left behind and the
+    /// previous output is untouched
+    pub fn finish(self) -> io::Result<usize> {
+        let flushed = match self.sink {
+            Sink::File(mut writer) => writer.finish(),
+            Sink::Behind(behind) => behind
+                .finish(self.scratch)
+                .and_then(|mut writer| writer.finish()),
+            Sink::Stream(mut writer) => writer.flush(),
+        };
+        let flushed = match self.failed {
+            Some(e) => Err(e),
+            None => flushed,
+        };
+        let flushed = match &self.path {
+            Some(path) => flushed.and_then(|()| commit_output(path)),
+            None => flushed,
+        };
+
+        // Tees are finished even when this writer failed
+        if let Some(tee) = self.tee {
+            let name = tee.name.clone();
+            let stream = tee.path.is_none();
+            match tee.finish() {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe && stream => {
+                    warn!("{} closed before the end of the output", name);
+                }
+                result => {
+                    result?;
+                }
+            }
+        }
+        flushed?;
+        Ok(self.count)
+    }
+}
+
+/// Releases segments in id order when they arrive out of order from parallel workers
+/// Ids start at `first` and advance by `step` (the consumer count for variation 9 shards)
+pub struct Reorder<T> {
+    pending: BTreeMap<usize, T>,
+    next_id: usize,
+    step: usize,
+}
+
+impl<T> Reorder<T> {
+    pub fn new(first: usize, step: usize) -> Self {
+        Self {
+            pending: BTreeMap::new(),
+            next_id: first,
+            step,
+        }
+    }
+
+    pub fn insert(&mut self, id: usize, item: T) {
+        self.pending.insert(id, item);
+    }
+
+    /// Next segment in order, if it has arrived
+    pub fn pop_ready(&mut self) -> Option<T> {
+        let item = self.pending.remove(&self.next_id)?;
+        self.next_id += self.step;
+        Some(item)
+    }
+
+    /// Whatever is still buffered, lowest id first (a gap means a producer bug)
+    pub fn pop_remaining(&mut self) -> Option<T> {
+        self.pending.pop_first().map(|(_, item)| item)
+    }
+
+    /// Segments waiting for an earlier one
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Id of the segment being waited for
+    pub fn next_id(&self) -> usize {
+        self.next_id
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.pending.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reorder_releases_in_steps() {
+        // Consumer 2 of 3: segments 2, 5, 8, ...
+        let mut reorder = Reorder::new(2, 3);
+        reorder.insert(5, "b");
+        assert_eq!(reorder.pop_ready(), None);
+        reorder.insert(2, "a");
+        reorder.insert(11, "d");
+        assert_eq!(reorder.pop_ready(), Some("a"));
+        assert_eq!(reorder.pop_ready(), Some("b"));
+        assert_eq!(reorder.pop_ready(), None);
+        assert_eq!(reorder.next_id(), 8);
+        assert_eq!(reorder.pop_remaining(), Some("d"));
+        assert!(reorder.is_empty());
+    }
+
+    // A stream the test can read back
+    #[derive(Clone, Default)]
+    struct Shared(std::sync::Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // A reader that has gone away
+    struct Closed;
+
+    impl Write for Closed {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+    }
+
+    #[test]
+    fn test_tee_writes_both_formats() {
+        let (binary, text) = (Shared::default(), Shared::default());
+        let mut writer =
+            PrimeWriter::to_stream("binary", SegmentEncoding::Binary, Box::new(binary.clone()))
+                .with_tee(PrimeWriter::to_stream(
+                    "closed",
+                    SegmentEncoding::Text,
+                    Box::new(BufWriter::with_capacity(0, Closed)),
+                ))
+                .with_tee(PrimeWriter::to_stream(
+                    "text",
+                    SegmentEncoding::Text,
+                    Box::new(text.clone()),
+                ));
+        writer.write_primes(&[2, 3, 5]).unwrap();
+        writer.write_prime(7).unwrap();
+        assert_eq!(writer.finish().unwrap(), 4);
+
+        let binary = binary.0.lock().unwrap();
+        let values: Vec<u64> = binary
+            .chunks(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(values, [2, 3, 5, 7]);
+        // The closed tee was dropped and the one after it kept going
+        assert_eq!(*text.0.lock().unwrap(), b"2\n3\n5\n7\n");
+    }
+
+    // Fails one write as if the disk filled up, then accepts writes again
+    struct FailsOnce(bool);
+
+    impl Write for FailsOnce {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if !self.0 {
+                self.0 = true;
+                return Err(io::ErrorKind::StorageFull.into());
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_failed_write_keeps_the_previous_file() {
+        let path = std::env::temp_dir().join(format!("nt_failed_write_{}.txt", std::process::id()));
+        fs::write(&path, "2\n3\n5\n").unwrap();
+        fs::write(staging_path(&path), "2\n").unwrap();
+
+        // Unbuffered, so the failure comes from the write itself and the final flush is clean
+        let sink = Sink::Stream(BufWriter::with_capacity(0, Box::new(FailsOnce(false))));
+        let mut writer = PrimeWriter::new(
+            sink,
+            SegmentEncoding::Text,
+            Some(path.clone()),
+            "test".into(),
+        );
+        assert!(writer.write_primes(&[2, 3]).is_err());
+        assert_eq!(
+            writer.write_prime(5).unwrap_err().kind(),
+            io::ErrorKind::StorageFull
+        );
+        assert_eq!(
+            writer.finish().unwrap_err().kind(),
+            io::ErrorKind::StorageFull
+        );
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "2\n3\n5\n");
+        assert!(staging_path(&path).exists());
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(staging_path(&path)).unwrap();
+    }
+
+    #[test]
+    fn test_write_behind_keeps_order() {
+        let path = std::env::temp_dir().join(format!("nt_write_behind_{}.txt", std::process::id()));
+        let writer = open_binary_output(&path, &BinaryOutputOptions::default(), 1024).unwrap();
+        let behind = WriteBehind::start(writer, 16);
+
+        let mut itoa_buf = itoa::Buffer::new();
+        let mut bytes = Vec::new();
+        let mut expected = String::new();
+        for prime in crate::primes::sieve(10_000) {
+            encode(SegmentEncoding::Text, &mut itoa_buf, &mut bytes, prime);
+            expected.push_str(&format!("{}\n", prime));
+            if bytes.len() >= 100 {
+                behind.hand_off(&mut bytes).unwrap();
+                assert!(bytes.is_empty());
+            }
+        }
+        let mut writer = behind.finish(bytes).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+// Live output to another process or machine (--output fifo:/path, --output tcp:host:port,
+// --output - for stdout)
+//
+// Instead of primes.txt / primes.bin, the run writes the same bytes to a named pipe, a
+// TCP connection or stdout, so a downstream program can consume primes as they are found without
+// waiting for, or making room for, the whole file. The stream is opened before sieving
+// starts, since opening a pipe blocks until a reader appears and a refused connection
+// should fail the run at once rather than after the producers have filled the channels;
+// PrimeWriter then picks it up in place of its file. Only single-file outputs can be
+// streamed: shards cover alternating ranges and would arrive out of order.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Where the output goes instead of the data directory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OutputTarget {
+    /// A named pipe, created if it does not exist
+    Fifo(PathBuf),
+    /// A TCP listener at host:port
+    Tcp(String),
+    /// Standard output, for piping into another command
+    Stdout,
+}
+
+impl fmt::Display for OutputTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputTarget::Fifo(path) => write!(f, "fifo:{}", path.display()),
+            OutputTarget::Tcp(address) => write!(f, "tcp:{}", address),
+            OutputTarget::Stdout => write!(f, "-"),
+        }
+    }
+}
+
+impl FromStr for OutputTarget {
+    type Err = String;
+
+    /// "fifo:/path", "tcp:host:port" or "-"
+    fn from_str(input: &str) -> Result<Self, String> {
+        if input == "-" {
+            return Ok(OutputTarget::Stdout);
+        }
+        match input.split_once(':') {
+            Some(("fifo", path)) if !path.is_empty() => Ok(OutputTarget::Fifo(path.into())),
+            Some(("tcp", address)) if address.rsplit_once(':').is_some() => {
+                Ok(OutputTarget::Tcp(address.to_string()))
+            }
+            _ => Err(format!("'{}' is not fifo:/path, tcp:host:port or -", input)),
+        }
+    }
+}
+
+impl OutputTarget {
+    /// Open the stream, waiting for a reader on a pipe
+    pub fn open(&self) -> io::Result<Box<dyn Write + Send>> {
+        match self {
+            OutputTarget::Fifo(path) => {
+                make_fifo(path)?;
+                Ok(Box::new(OpenOptions::new().write(true).open(path)?))
+            }
+            OutputTarget::Tcp(address) => {
+                let stream = TcpStream::connect(address)?;
+                stream.set_nodelay(true)?;
+                Ok(Box::new(stream))
+            }
+            OutputTarget::Stdout => Ok(Box::new(io::stdout())),
+        }
+    }
+}
+
+/// Create a named pipe at `path` unless one is already there
+fn make_fifo(path: &PathBuf) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::FileTypeExt;
+
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.file_type().is_fifo() => return Ok(()),
+            Ok(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a named pipe", path.display()),
+                ));
+            }
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            Err(_) => {}
+        }
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"))?;
+        // SAFETY: c_path is a valid NUL-terminated string for the duration of the call
+        if unsafe { libc::mkfifo(c_path.as_ptr(), 0o644) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = fs::metadata(path);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "named pipes need a Unix system",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_parse_targets() {
+        assert_eq!(
+            "fifo:/tmp/primes".parse(),
+            Ok(OutputTarget::Fifo("/tmp/primes".into()))
+        );
+        let tcp: OutputTarget = "tcp:localhost:9000".parse().unwrap();
+        assert_eq!(tcp, OutputTarget::Tcp("localhost:9000".to_string()));
+        assert_eq!(tcp.to_string(), "tcp:localhost:9000");
+        assert!("tcp:localhost".parse::<OutputTarget>().is_err());
+        assert!("file:/tmp/primes".parse::<OutputTarget>().is_err());
+        assert!("fifo:".parse::<OutputTarget>().is_err());
+        assert_eq!("-".parse(), Ok(OutputTarget::Stdout));
+        assert_eq!(OutputTarget::Stdout.to_string(), "-");
+    }
+
+    #[test]
+    fn test_tcp_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let target = OutputTarget::Tcp(listener.local_addr().unwrap().to_string());
+        let mut stream = target.open().unwrap();
+        stream.write_all(b"2\n3\n5\n").unwrap();
+        drop(stream);
+
+        let (mut received, _) = listener.accept().unwrap();
+        let mut text = String::new();
+        received.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "2\n3\n5\n");
+    }
+}
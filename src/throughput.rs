@@ -0,0 +1,156 @@
+// Consumer throughput: primes/s, segments/s and MB/s
+//
+// Every consumer in storage.rs (and the async one) starts a Meter when it opens its output
+// and counts the segments it writes; when it finishes, the meter turns the primes and bytes
+// its writer reports into rates for the consumer's closing log line and adds them to
+// process-wide totals (like gaps.rs and audit.rs, so nothing is threaded through the
+// producers). The totals are timed from `start`, at the beginning of the run, and go into
+// the execution log so runs with different variations and flags can be compared.
+//
+// Megabytes are 2^20 bytes, as in the memory reports.
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+const BYTES_PER_MB: f64 = 1024.0 * 1024.0;
+
+/// Work done over some time, by one consumer or all of them
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Throughput {
+    pub primes: u64,
+    pub segments: u64,
+    pub bytes: u64,
+    pub seconds: f64,
+}
+
+impl Throughput {
+    fn rate(&self, amount: f64) -> f64 {
+        if self.seconds > 0.0 {
+            amount / self.seconds
+        } else {
+            0.0
+        }
+    }
+
+    pub fn primes_per_second(&self) -> f64 {
+        self.rate(self.primes as f64)
+    }
+
+    pub fn segments_per_second(&self) -> f64 {
+        self.rate(self.segments as f64)
+    }
+
+    pub fn megabytes_per_second(&self) -> f64 {
+        self.rate(self.bytes as f64 / BYTES_PER_MB)
+    }
+
+    /// e.g. "12500000 primes/s, 95.4 segments/s, 142.31 MB/s"
+    pub fn summary(&self) -> String {
+        format!(
+            "{:.0} primes/s, {:.1} segments/s, {:.2} MB/s",
+            self.primes_per_second(),
+            self.segments_per_second(),
+            self.megabytes_per_second()
+        )
+    }
+}
+
+/// Times one consumer from when its output opens
+pub struct Meter {
+    started: Instant,
+    segments: u64,
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Meter {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            segments: 0,
+        }
+    }
+
+    /// Count one segment written
+    #[inline]
+    pub fn segment(&mut self) {
+        self.segments += 1;
+    }
+
+    /// This consumer's throughput, also added to the process totals
+    pub fn finish(self, primes: usize, bytes: usize) -> Throughput {
+        let throughput = Throughput {
+            primes: primes as u64,
+            segments: self.segments,
+            bytes: bytes as u64,
+            seconds: self.started.elapsed().as_secs_f64(),
+        };
+        let mut totals = TOTALS.lock().unwrap();
+        if let Some((_, total)) = totals.as_mut() {
+            total.primes += throughput.primes;
+            total.segments += throughput.segments;
+            total.bytes += throughput.bytes;
+        }
+        throughput
+    }
+}
+
+// Start of the run and the work every finished consumer has added
+static TOTALS: Mutex<Option<(Instant, Throughput)>> = Mutex::new(None);
+
+/// Start timing a run; consumers finished before this are not counted
+pub fn start() {
+    *TOTALS.lock().unwrap() = Some((Instant::now(), Throughput::default()));
+}
+
+/// Everything the consumers wrote since `start`, timed up to now
+pub fn totals() -> Option<Throughput> {
+    let totals = TOTALS.lock().unwrap();
+    totals.map(|(started, total)| Throughput {
+        seconds: started.elapsed().as_secs_f64(),
+        ..total
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rates() {
+        let throughput = Throughput {
+            primes: 1_000_000,
+            segments: 50,
+            bytes: 8 * 1024 * 1024,
+            seconds: 2.0,
+        };
+        assert_eq!(throughput.primes_per_second(), 500_000.0);
+        assert_eq!(throughput.segments_per_second(), 25.0);
+        assert_eq!(throughput.megabytes_per_second(), 4.0);
+        assert_eq!(
+            throughput.summary(),
+            "500000 primes/s, 25.0 segments/s, 4.00 MB/s"
+        );
+        assert_eq!(Throughput::default().primes_per_second(), 0.0);
+    }
+
+    #[test]
+    fn test_meters_add_to_totals() {
+        start();
+        let mut meter = Meter::new();
+        meter.segment();
+        meter.segment();
+        let own = meter.finish(100, 800);
+        assert_eq!((own.primes, own.segments, own.bytes), (100, 2, 800));
+        Meter::new().finish(50, 400);
+        let totals = totals().unwrap();
+        assert_eq!(
+            (totals.primes, totals.segments, totals.bytes),
+            (150, 2, 1200)
+        );
+    }
+}
@@ -0,0 +1,312 @@
+// Live dashboard for variation 9 (--tui)
+//
+// Workers and consumers bump per-thread counters in a process-wide table (like progress.rs,
+// so nothing is threaded through the sieve signatures) and a render thread samples them a
+// few times a second. Console logging is suspended while the dashboard owns the screen.
+
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::{cursor, execute, terminal};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, Paragraph, Row, Table};
+use std::io::{self, IsTerminal, Stderr};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+use crate::logging;
+use crate::storage::get_process_memory_mb;
+
+// How often the dashboard is redrawn
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Counters shared by workers, consumers and the render thread
+struct Stats {
+    total_segments: usize,
+    total_sent: Arc<AtomicUsize>,
+    total_received: Arc<AtomicUsize>,
+    worker_segments: Vec<AtomicUsize>,
+    consumer_segments: Vec<AtomicUsize>,
+    consumer_bytes: Vec<AtomicUsize>,
+    // Segments waiting in each consumer's reorder buffer
+    consumer_pending: Vec<AtomicUsize>,
+}
+
+static STATS: OnceLock<Stats> = OnceLock::new();
+
+// Stop flag and handle of the render thread while the dashboard is up
+static RENDERER: Mutex<Option<(Arc<AtomicBool>, JoinHandle<()>)>> = Mutex::new(None);
+
+/// Show the dashboard until `finish` is called
+/// Falls back to plain logging when stderr is not a terminal
+pub fn start(
+    total_segments: usize,
+    num_workers: usize,
+    num_consumers: usize,
+    total_sent: Arc<AtomicUsize>,
+    total_received: Arc<AtomicUsize>,
+) {
+    if !io::stderr().is_terminal() {
+        warn!("--tui needs a terminal on stderr, continuing without it");
+        return;
+    }
+
+    let counters = |n: usize| (0..n).map(|_| AtomicUsize::new(0)).collect::<Vec<_>>();
+    let stats = Stats {
+        total_segments,
+        total_sent,
+        total_received,
+        worker_segments: counters(num_workers),
+        consumer_segments: counters(num_consumers),
+        consumer_bytes: counters(num_consumers),
+        consumer_pending: counters(num_consumers),
+    };
+    if STATS.set(stats).is_err() {
+        return;
+    }
+
+    let mut terminal = match enter_screen() {
+        Ok(terminal) => terminal,
+        Err(e) => {
+            warn!("Warning: Could not start dashboard: {}", e);
+            return;
+        }
+    };
+
+    logging::suspend();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_flag = Arc::clone(&stop);
+    let handle = thread::spawn(move || {
+        let Some(stats) = STATS.get() else {
+            return;
+        };
+        let started = Instant::now();
+        let mut previous = Sample::take(stats);
+
+        while !stop_flag.load(Ordering::Relaxed) {
+            thread::sleep(REFRESH_INTERVAL);
+            let current = Sample::take(stats);
+            let _ = terminal.draw(|frame| draw(frame, stats, &previous, &current, started));
+            previous = current;
+        }
+
+        leave_screen(&mut terminal);
+    });
+
+    *RENDERER.lock().unwrap() = Some((stop, handle));
+}
+
+/// Record one segment sieved and sent by worker `worker_id` (0-based)
+#[inline]
+pub fn record_worker_segment(worker_id: usize) {
+    if let Some(counter) = STATS.get().and_then(|s| s.worker_segments.get(worker_id)) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Record one segment of `bytes` written by consumer `consumer_id` (1-based)
+/// `pending` is the number of out-of-order segments still buffered by that consumer
+#[inline]
+pub fn record_consumer_segment(consumer_id: usize, bytes: usize, pending: usize) {
+    let Some(stats) = STATS.get() else {
+        return;
+    };
+    let idx = consumer_id - 1;
+    if idx < stats.consumer_segments.len() {
+        stats.consumer_segments[idx].fetch_add(1, Ordering::Relaxed);
+        stats.consumer_bytes[idx].fetch_add(bytes, Ordering::Relaxed);
+        stats.consumer_pending[idx].store(pending, Ordering::Relaxed);
+    }
+}
+
+/// Close the dashboard and print the log lines held back while it was up
+pub fn finish() {
+    let renderer = RENDERER.lock().unwrap().take();
+    if let Some((stop, handle)) = renderer {
+        stop.store(true, Ordering::Relaxed);
+        let _ = handle.join();
+        logging::resume();
+    }
+}
+
+fn enter_screen() -> io::Result<Terminal<CrosstermBackend<Stderr>>> {
+    let mut stderr = io::stderr();
+    execute!(stderr, terminal::EnterAlternateScreen, cursor::Hide)?;
+    Terminal::new(CrosstermBackend::new(stderr))
+}
+
+fn leave_screen(terminal: &mut Terminal<CrosstermBackend<Stderr>>) {
+    let _ = execute!(
+        terminal.backend_mut(),
+        terminal::LeaveAlternateScreen,
+        cursor::Show
+    );
+}
+
+/// Counter values at one refresh, used to turn totals into rates
+struct Sample {
+    at: Instant,
+    worker_segments: Vec<usize>,
+    consumer_bytes: Vec<usize>,
+}
+
+impl Sample {
+    fn take(stats: &Stats) -> Self {
+        let load = |counters: &[AtomicUsize]| {
+            counters
+                .iter()
+                .map(|c| c.load(Ordering::Relaxed))
+                .collect::<Vec<_>>()
+        };
+        Self {
+            at: Instant::now(),
+            worker_segments: load(&stats.worker_segments),
+            consumer_bytes: load(&stats.consumer_bytes),
+        }
+    }
+}
+
+/// Per-second rates between two snapshots of the same counters
+fn rates(previous: &[usize], current: &[usize], elapsed: Duration) -> Vec<f64> {
+    let secs = elapsed.as_secs_f64().max(f64::EPSILON);
+    previous
+        .iter()
+        .zip(current)
+        .map(|(&before, &after)| after.saturating_sub(before) as f64 / secs)
+        .collect()
+}
+
+fn draw(
+    frame: &mut ratatui::Frame,
+    stats: &Stats,
+    previous: &Sample,
+    current: &Sample,
+    started: Instant,
+) {
+    let interval = current.at - previous.at;
+    let worker_rates = rates(
+        &previous.worker_segments,
+        &current.worker_segments,
+        interval,
+    );
+    let write_rates = rates(&previous.consumer_bytes, &current.consumer_bytes, interval);
+
+    let sent = stats.total_sent.load(Ordering::Relaxed);
+    let received = stats.total_received.load(Ordering::Relaxed);
+    let rss = get_process_memory_mb()
+        .map(|(rss_mb, _)| format!("{:.1} MB", rss_mb))
+        .unwrap_or_else(|| "n/a".to_string());
+    let disk_mb_s: f64 = write_rates.iter().sum::<f64>() / (1024.0 * 1024.0);
+
+    let [progress_area, summary_area, tables_area] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(3),
+        Constraint::Min(0),
+    ])
+    .areas(frame.area());
+    let [workers_area, consumers_area] =
+        Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .areas(tables_area);
+
+    let ratio = if stats.total_segments > 0 {
+        (received as f64 / stats.total_segments as f64).min(1.0)
+    } else {
+        0.0
+    };
+    let gauge = Gauge::default()
+        .block(Block::bordered().title(" nt primes (variation 9) "))
+        .gauge_style(Style::default().fg(Color::Cyan))
+        .ratio(ratio)
+        .label(format!(
+            "{}/{} segments received by consumers ({:.1}%)",
+            received,
+            stats.total_segments,
+            ratio * 100.0
+        ));
+    frame.render_widget(gauge, progress_area);
+
+    let summary = Paragraph::new(Line::from(format!(
+        "Elapsed: {:.1}s | Channel depth: {} segments | RSS: {} | Disk writes: {:.1} MB/s",
+        started.elapsed().as_secs_f64(),
+        sent.saturating_sub(received),
+        rss,
+        disk_mb_s
+    )))
+    .block(Block::bordered().title(" Pipeline "));
+    frame.render_widget(summary, summary_area);
+
+    let worker_rows = current
+        .worker_segments
+        .iter()
+        .zip(&worker_rates)
+        .enumerate()
+        .map(|(worker_id, (segments, rate))| {
+            Row::new(vec![
+                worker_id.to_string(),
+                segments.to_string(),
+                format!("{:.0}", rate),
+            ])
+        });
+    let workers = Table::new(
+        worker_rows,
+        [
+            Constraint::Length(8),
+            Constraint::Length(12),
+            Constraint::Length(12),
+        ],
+    )
+    .header(Row::new(vec!["Worker", "Segments", "Segments/s"]).bold())
+    .block(Block::bordered().title(" Workers "));
+    frame.render_widget(workers, workers_area);
+
+    let consumer_rows = (0..stats.consumer_segments.len()).map(|idx| {
+        let segments = stats.consumer_segments[idx].load(Ordering::Relaxed);
+        let pending = stats.consumer_pending[idx].load(Ordering::Relaxed);
+        let written_mb = current.consumer_bytes[idx] as f64 / (1024.0 * 1024.0);
+        Row::new(vec![
+            (idx + 1).to_string(),
+            segments.to_string(),
+            pending.to_string(),
+            format!("{:.1}", written_mb),
+            format!("{:.1}", write_rates[idx] / (1024.0 * 1024.0)),
+        ])
+    });
+    let consumers = Table::new(
+        consumer_rows,
+        [
+            Constraint::Length(9),
+            Constraint::Length(12),
+            Constraint::Length(10),
+            Constraint::Length(14),
+            Constraint::Length(8),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Consumer",
+            "Segments",
+            "Reorder",
+            "Written (MB)",
+            "MB/s",
+        ])
+        .bold(),
+    )
+    .block(Block::bordered().title(" Consumers "));
+    frame.render_widget(consumers, consumers_area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rates_are_per_second_deltas() {
+        let rates = rates(&[10, 0, 5], &[30, 4, 5], Duration::from_millis(500));
+        assert_eq!(rates, vec![40.0, 8.0, 0.0]);
+    }
+}
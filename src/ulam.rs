@@ -0,0 +1,237 @@
+// The Ulam spiral and its prime-rich diagonals (`nt ulam`)
+//
+// Writing 1, 2, 3, ... in a square spiral (1 at the origin, 2 to its right, turning
+// counterclockwise) lines the primes up along diagonals. Ring k holds (2k − 1)² + 1 to
+// (2k + 1)², so a step outward along a diagonal adds a ring of 8 more cells than the last
+// and the values along any diagonal ray are a quadratic 4n² + bn + c once the ray stays on
+// one side of the rings. The main diagonals from 1 are 4n² − 2n + 1, 4n² + 1, 4n² + 2n + 1
+// and (2n + 1)².
+//
+// The analysis starts a ray in each diagonal direction from every cell up to some value,
+// fits 4n² + bn + c to its first two cells and keeps it if the spiral agrees with the fit
+// for all the terms asked for. Only rays heading outward count, and a ray whose cell one
+// step further in also fits is part of a longer one and is skipped, so each diagonal is
+// counted once, from where it becomes quadratic.
+// Rays are ranked by how many of their terms are prime; the cells are split over worker
+// threads and each term is tested with the deterministic u64 Miller–Rabin in factor.rs.
+
+use crate::factor;
+use crate::parallel::parallel_chunks;
+
+// Start cells per chunk handed to a worker
+const CHUNK: usize = 64;
+
+/// The four diagonal directions
+pub const DIRECTIONS: [(i64, i64); 4] = [(1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+/// Value at cell (x, y) of the spiral
+pub fn value_at(x: i64, y: i64) -> u64 {
+    let k = x.abs().max(y.abs());
+    if k == 0 {
+        return 1;
+    }
+    let inner = (2 * k - 1) * (2 * k - 1);
+    let value = if x == k && y > -k {
+        // Right side, going up
+        inner + y + k
+    } else if y == k {
+        // Top, going left
+        inner + 3 * k - x
+    } else if x == -k {
+        // Left side, going down
+        inner + 5 * k - y
+    } else {
+        // Bottom, going right
+        inner + 7 * k + x
+    };
+    value as u64
+}
+
+/// The spiral's cells from (−radius, radius) to (radius, −radius), rows top first, marked
+/// '#' for primes and '.' otherwise
+pub fn render(radius: i64) -> Vec<String> {
+    (-radius..=radius)
+        .rev()
+        .map(|y| {
+            (-radius..=radius)
+                .map(|x| {
+                    if factor::is_prime(value_at(x, y)) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// A diagonal ray whose values are 4n² + bn + c for n = 0, 1, 2, ...
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagonal {
+    pub b: i64,
+    pub c: i64,
+    /// Cell of n = 0
+    pub start: (i64, i64),
+    pub direction: (i64, i64),
+    /// Terms checked and how many were prime
+    pub terms: usize,
+    pub primes: usize,
+}
+
+impl Diagonal {
+    /// Share of the terms that are prime
+    pub fn hit_rate(&self) -> f64 {
+        self.primes as f64 / self.terms as f64
+    }
+
+    /// The polynomial, e.g. "4n² − 2n + 1"
+    pub fn polynomial(&self) -> String {
+        let mut text = "4n²".to_string();
+        if self.b != 0 {
+            let sign = if self.b < 0 { '−' } else { '+' };
+            text.push_str(&format!(" {} {}n", sign, self.b.abs()));
+        }
+        if self.c != 0 {
+            let sign = if self.c < 0 { '−' } else { '+' };
+            text.push_str(&format!(" {} {}", sign, self.c.abs()));
+        }
+        text
+    }
+
+    /// Compass heading of the ray, with y pointing up
+    pub fn heading(&self) -> &'static str {
+        match self.direction {
+            (1, 1) => "NE",
+            (-1, 1) => "NW",
+            (-1, -1) => "SW",
+            _ => "SE",
+        }
+    }
+}
+
+/// The ray from `start` in `direction`, if it starts a diagonal that stays quadratic for
+/// `terms` terms
+pub fn ray(start: (i64, i64), direction: (i64, i64), terms: usize) -> Option<Diagonal> {
+    let at = |n: i64| (start.0 + n * direction.0, start.1 + n * direction.1);
+    let ring = |n: i64| {
+        let (x, y) = at(n);
+        x.abs().max(y.abs())
+    };
+    let cell = |n: i64| {
+        let (x, y) = at(n);
+        value_at(x, y) as i64
+    };
+    // Rays heading inward turn around at the center
+    if ring(1) <= ring(0) {
+        return None;
+    }
+    let c = cell(0);
+    let b = cell(1) - c - 4;
+    let fit = |n: i64| 4 * n * n + b * n + c;
+    // Part of a longer diagonal that started further in
+    if ring(-1) < ring(0) && cell(-1) == fit(-1) {
+        return None;
+    }
+
+    let mut primes = 0;
+    for n in 0..terms as i64 {
+        let value = fit(n);
+        if cell(n) != value {
+            return None;
+        }
+        if factor::is_prime(value as u64) {
+            primes += 1;
+        }
+    }
+    Some(Diagonal {
+        b,
+        c,
+        start,
+        direction,
+        terms,
+        primes,
+    })
+}
+
+/// The `top` diagonals with the most primes in their first `terms` terms, over the rays
+/// starting at values up to `max_start`; ties go to the smaller start
+pub fn hot_spots(max_start: u64, terms: usize, top: usize, workers: usize) -> Vec<Diagonal> {
+    // Ring k ends at (2k + 1)²
+    let radius = ((max_start as f64).sqrt() as i64 + 1) / 2 + 1;
+    let cells: Vec<(i64, i64)> = (-radius..=radius)
+        .flat_map(|x| (-radius..=radius).map(move |y| (x, y)))
+        .filter(|&(x, y)| value_at(x, y) <= max_start)
+        .collect();
+    let mut found = Vec::new();
+    if !cells.is_empty() {
+        parallel_chunks(
+            0..=cells.len() - 1,
+            CHUNK,
+            workers,
+            |low, high| {
+                cells[low..=high]
+                    .iter()
+                    .flat_map(|&start| DIRECTIONS.iter().map(move |&d| (start, d)))
+                    .filter_map(|(start, direction)| ray(start, direction, terms))
+                    .collect::<Vec<Diagonal>>()
+            },
+            |rays| found.extend(rays),
+        );
+    }
+
+    found.sort_by_key(|d| (std::cmp::Reverse(d.primes), d.c, d.b));
+    found.truncate(top);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spiral_layout() {
+        let cells = [
+            (0, 0),
+            (1, 0),
+            (1, 1),
+            (0, 1),
+            (-1, 1),
+            (-1, 0),
+            (-1, -1),
+            (0, -1),
+            (1, -1),
+            (2, -1),
+        ];
+        let values: Vec<u64> = cells.iter().map(|&(x, y)| value_at(x, y)).collect();
+        assert_eq!(values, (1..=10).collect::<Vec<_>>());
+        assert_eq!(value_at(2, -2), 25);
+        assert_eq!(render(1), ["#.#", "..#", "#.."].map(String::from));
+    }
+
+    #[test]
+    fn test_main_diagonals() {
+        // 1, 5, 17, 37, ... up and to the left from 1
+        let nw = ray((0, 0), (-1, 1), 50).unwrap();
+        assert_eq!((nw.b, nw.c), (0, 1));
+        assert_eq!(nw.polynomial(), "4n² + 1");
+        // The odd squares
+        let se = ray((0, 0), (1, -1), 50).unwrap();
+        assert_eq!((se.b, se.c, se.primes), (4, 1, 0));
+        assert_eq!(se.polynomial(), "4n² + 4n + 1");
+        // 4n² − 2n + 1 from 1 to the NE, continued by 13 and read inward from 21
+        let ne = ray((0, 0), (1, 1), 50).unwrap();
+        assert_eq!(ne.polynomial(), "4n² − 2n + 1");
+        assert_eq!(ray((2, 2), (1, 1), 50), None);
+        assert_eq!(ray((-2, -2), (1, 1), 50), None);
+    }
+
+    #[test]
+    fn test_hot_spots() {
+        let top = hot_spots(1000, 200, 3, 3);
+        let found: Vec<(usize, i64, i64)> = top.iter().map(|d| (d.primes, d.b, d.c)).collect();
+        assert_eq!(found, [(95, 72, 307), (83, 66, 289), (82, 62, 257)]);
+        assert_eq!(top[0].start, (9, 9));
+        assert_eq!(top[0].heading(), "NW");
+    }
+}
@@ -0,0 +1,367 @@
+// Unbounded prime streaming (`nt primes --unbounded`)
+//
+// Generates primes with no limit using PrimeIterator, which sieves one window at a time
+// and grows its base primes as the windows pass their square. Output rolls over to a new
+// file every `roll_bytes` (stream_000001.bin, stream_000002.bin, ...) so each finished
+// file is complete and can be read, copied or deleted while the run goes on. Stops on
+// Ctrl-C or when the --max-bytes / --max-seconds budget runs out; the file being written
+// is finished either way, and --max-bytes trims the last write so it is never overshot.
+// With --resume a new run picks up after the last prime of the highest finished file.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+use crate::PrimeIterator;
+use crate::import::{self, InputFormat};
+use crate::segment_format::SegmentEncoding;
+use crate::storage::get_nt_data_dir;
+use crate::storage_direct::BinaryOutputOptions;
+use crate::storage_writer::{PrimeWriter, file_name};
+
+// Primes collected before each write
+const BATCH_PRIMES: usize = 64 * 1024;
+
+// Set by the SIGINT handler; checked once per batch
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// When to stop streaming (None = no limit)
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Budget {
+    pub max_bytes: Option<u64>,
+    pub max_duration: Option<Duration>,
+}
+
+/// Name of the `index`-th rolling file (1-based), e.g. stream_000001.bin
+pub fn stream_file_name(index: usize, encoding: SegmentEncoding) -> String {
+    file_name(&stream_stem(index), encoding)
+}
+
+fn stream_stem(index: usize) -> String {
+    format!("stream_{:06}", index)
+}
+
+/// Stream primes into rolling files until stopped or the budget is spent
+/// With `resume`, continue after the files a previous run finished
+/// Returns the number of primes written
+pub fn run(
+    encoding: SegmentEncoding,
+    options: BinaryOutputOptions,
+    roll_bytes: u64,
+    budget: Budget,
+    resume: bool,
+) -> usize {
+    install_stop_handler();
+
+    let (first_index, primes) = if resume {
+        match resume_point(&get_nt_data_dir(), encoding) {
+            Ok(Some((index, last))) => {
+                info!(
+                    "Resuming after {} in {}",
+                    last,
+                    stream_file_name(index, encoding)
+                );
+                (index + 1, PrimeIterator::starting_at(last + 1))
+            }
+            Ok(None) => (1, PrimeIterator::new()),
+            Err(e) => {
+                error!("Error finding where to resume: {}", e);
+                return 0;
+            }
+        }
+    } else {
+        (1, PrimeIterator::new())
+    };
+
+    stream(primes, encoding, first_index, roll_bytes, budget, |index| {
+        PrimeWriter::create(&stream_stem(index), encoding, &options, 8 * 1024 * 1024)
+    })
+}
+
+/// The highest finished stream file in `dir` and its last prime, or None if there are none
+/// Staging files of an interrupted run are skipped, so it starts that file over
+pub fn resume_point(dir: &Path, encoding: SegmentEncoding) -> io::Result<Option<(usize, usize)>> {
+    let extension = file_name("", encoding);
+    let mut highest = None;
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let name = entry?.file_name();
+        let index = name
+            .to_str()
+            .and_then(|name| name.strip_prefix("stream_")?.strip_suffix(&extension))
+            .and_then(|digits| digits.parse::<usize>().ok());
+        if index.is_some() && index > highest {
+            highest = index;
+        }
+    }
+    let Some(index) = highest else {
+        return Ok(None);
+    };
+
+    let format = match encoding {
+        SegmentEncoding::Text => InputFormat::Text,
+        SegmentEncoding::Binary => InputFormat::Binary,
+    };
+    let name = stream_file_name(index, encoding);
+    match import::last_number(&dir.join(&name), format)? {
+        Some(last) => Ok(Some((index, last))),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} holds no primes; delete it to resume", name),
+        )),
+    }
+}
+
+/// The loop behind `run`: write `primes` into files from `open(first_index)` on
+fn stream(
+    mut primes: impl Iterator<Item = usize>,
+    encoding: SegmentEncoding,
+    first_index: usize,
+    roll_bytes: u64,
+    budget: Budget,
+    mut open: impl FnMut(usize) -> io::Result<PrimeWriter>,
+) -> usize {
+    let start = Instant::now();
+    let mut batch = Vec::with_capacity(BATCH_PRIMES);
+    let mut total_primes = 0;
+    let mut total_bytes = 0u64;
+    let mut largest = 0;
+    let mut file_index = first_index;
+    let mut writer: Option<PrimeWriter> = None;
+
+    loop {
+        // Stop checks happen between batches so a file never ends mid-prime
+        if STOP.load(Ordering::Relaxed) {
+            info!("Interrupted, finishing the current file");
+            break;
+        }
+        if budget
+            .max_duration
+            .is_some_and(|max| start.elapsed() >= max)
+        {
+            info!("Reached --max-seconds");
+            break;
+        }
+
+        batch.clear();
+        batch.extend(primes.by_ref().take(BATCH_PRIMES));
+        if batch.is_empty() {
+            info!("Reached the end of usize");
+            break;
+        }
+
+        // Write only the primes that fit in --max-bytes
+        let fits = match budget.max_bytes {
+            Some(max) => fitting(&batch, encoding, max.saturating_sub(total_bytes)),
+            None => batch.len(),
+        };
+        let out_of_bytes = fits < batch.len();
+        batch.truncate(fits);
+        if batch.is_empty() {
+            info!("Reached --max-bytes");
+            break;
+        }
+
+        let out = match writer.as_mut() {
+            Some(out) => out,
+            None => match open(file_index) {
+                Ok(out) => writer.insert(out),
+                Err(e) => {
+                    error!(
+                        "Error opening {}: {}",
+                        stream_file_name(file_index, encoding),
+                        e
+                    );
+                    break;
+                }
+            },
+        };
+
+        let bytes_before = out.bytes_written();
+        if let Err(e) = out.write_primes(&batch) {
+            error!("Error writing to {}: {}", out.name(), e);
+            break;
+        }
+        total_bytes += (out.bytes_written() - bytes_before) as u64;
+        total_primes += batch.len();
+        largest = batch[batch.len() - 1];
+
+        // Roll over once the file is full
+        if out.bytes_written() as u64 >= roll_bytes
+            && let Some(full) = writer.take()
+        {
+            finish_file(full, largest, start);
+            file_index += 1;
+        }
+        if out_of_bytes {
+            info!("Reached --max-bytes");
+            break;
+        }
+    }
+
+    if let Some(last) = writer.take() {
+        finish_file(last, largest, start);
+    }
+
+    info!(
+        "Streamed {} primes ({} bytes) in {:.2}s",
+        total_primes,
+        total_bytes,
+        start.elapsed().as_secs_f64()
+    );
+    total_primes
+}
+
+/// How many primes from the front of `batch` fit in `bytes_left` once encoded
+fn fitting(batch: &[usize], encoding: SegmentEncoding, bytes_left: u64) -> usize {
+    let mut left = bytes_left;
+    for (count, &prime) in batch.iter().enumerate() {
+        let bytes = match encoding {
+            SegmentEncoding::Text => prime.checked_ilog10().unwrap_or(0) as u64 + 2,
+            SegmentEncoding::Binary => 8,
+        };
+        if bytes > left {
+            return count;
+        }
+        left -= bytes;
+    }
+    batch.len()
+}
+
+fn finish_file(writer: PrimeWriter, largest: usize, start: Instant) {
+    let name = writer.name().to_string();
+    let bytes = writer.bytes_written();
+    match writer.finish() {
+        Ok(count) => info!(
+            "Wrote {}: {} primes, {} bytes, up to {} ({:.1}s)",
+            name,
+            count,
+            bytes,
+            largest,
+            start.elapsed().as_secs_f64()
+        ),
+        Err(e) => error!("Error flushing {}: {}", name, e),
+    }
+}
+
+/// Turn Ctrl-C into a request to stop after the current batch
+#[cfg(unix)]
+fn install_stop_handler() {
+    extern "C" fn on_sigint(_: libc::c_int) {
+        STOP.store(true, Ordering::Relaxed);
+    }
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        );
+    }
+}
+
+#[cfg(not(unix))]
+fn install_stop_handler() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    /// A stream file kept in memory
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Stream text files into memory; returns the primes written and each file's contents
+    fn stream_text(
+        primes: impl Iterator<Item = usize>,
+        roll_bytes: u64,
+        max_bytes: u64,
+    ) -> (usize, Vec<String>) {
+        let files = Mutex::new(Vec::new());
+        let budget = Budget {
+            max_bytes: Some(max_bytes),
+            max_duration: None,
+        };
+        let count = stream(
+            primes,
+            SegmentEncoding::Text,
+            1,
+            roll_bytes,
+            budget,
+            |index| {
+                let file = Shared::default();
+                files.lock().unwrap().push(file.clone());
+                Ok(PrimeWriter::to_stream(
+                    &stream_file_name(index, SegmentEncoding::Text),
+                    SegmentEncoding::Text,
+                    Box::new(file),
+                ))
+            },
+        );
+        let files = files.into_inner().unwrap();
+        let contents = files
+            .iter()
+            .map(|file| String::from_utf8(file.0.lock().unwrap().clone()).unwrap())
+            .collect();
+        (count, contents)
+    }
+
+    #[test]
+    fn test_max_bytes_is_never_overshot() {
+        // "2\n3\n5\n7\n" is 8 bytes and "11\n" would make it 11
+        let (count, files) = stream_text(PrimeIterator::new(), 1 << 20, 10);
+        assert_eq!(count, 4);
+        assert_eq!(files, ["2\n3\n5\n7\n"]);
+
+        // A budget inside the first batch stops well short of BATCH_PRIMES
+        let (count, files) = stream_text(PrimeIterator::new(), 1 << 20, 100_000);
+        let bytes = files[0].len();
+        assert!(bytes <= 100_000 && bytes > 100_000 - 7, "{} bytes", bytes);
+        assert_eq!(count, files[0].lines().count());
+
+        assert_eq!(fitting(&[2, 3, 5], SegmentEncoding::Binary, 23), 2);
+        assert_eq!(fitting(&[2, 3, 5], SegmentEncoding::Binary, 24), 3);
+        assert_eq!(fitting(&[], SegmentEncoding::Text, 0), 0);
+    }
+
+    #[test]
+    fn test_resume_continues_after_the_last_finished_file() {
+        let dir = std::env::temp_dir().join(format!("nt_unbounded_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let encoding = SegmentEncoding::Text;
+        assert_eq!(resume_point(&dir, encoding).unwrap(), None);
+
+        fs::write(dir.join(stream_file_name(1, encoding)), "2\n3\n5\n").unwrap();
+        fs::write(dir.join(stream_file_name(2, encoding)), "7\n11\n").unwrap();
+        // An interrupted run's staging file and the other format are not finished files
+        fs::write(dir.join("stream_000003.txt.tmp"), "13\n1").unwrap();
+        fs::write(dir.join(stream_file_name(9, SegmentEncoding::Binary)), []).unwrap();
+        assert_eq!(resume_point(&dir, encoding).unwrap(), Some((2, 11)));
+
+        // The resumed stream picks up at the next prime
+        let (_, files) = stream_text(PrimeIterator::starting_at(12), 1 << 20, 12);
+        assert_eq!(files, ["13\n17\n19\n23\n"]);
+
+        fs::write(dir.join(stream_file_name(4, encoding)), "").unwrap();
+        assert!(resume_point(&dir, encoding).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,48 @@
+// JavaScript bindings for the pure Rust core (--features wasm)
+//
+// Build with `wasm-pack build --no-default-features --features wasm`. Everything here
+// runs without GMP or a filesystem, so the same functions back the browser playground.
+// u64 arguments and results cross the boundary as BigInt.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{PrimeSet, constants, factor, radix};
+
+/// All primes <= limit as a Uint32Array
+#[wasm_bindgen]
+pub fn sieve(limit: u32) -> Vec<u32> {
+    PrimeSet::new(limit as usize)
+        .iter()
+        .map(|p| p as u32)
+        .collect()
+}
+
+/// Deterministic primality test for any u64
+#[wasm_bindgen(js_name = isPrime)]
+pub fn is_prime(n: u64) -> bool {
+    factor::is_prime(n)
+}
+
+/// Prime factors of `n` with multiplicity, in increasing order (360 → [2, 2, 2, 3, 3, 5])
+#[wasm_bindgen]
+pub fn factorize(n: u64) -> Vec<u64> {
+    factor::factorize(n)
+        .into_iter()
+        .flat_map(|(p, exp)| std::iter::repeat_n(p, exp as usize))
+        .collect()
+}
+
+/// `n` written in `base` (2-62), using the same digits as `nt primes-bases`
+#[wasm_bindgen(js_name = toBase)]
+pub fn to_base(n: u32, base: u32) -> Result<String, JsError> {
+    if !(2..=62).contains(&base) {
+        return Err(JsError::new("base must be between 2 and 62"));
+    }
+    Ok(radix::to_base(n as usize, base as usize))
+}
+
+/// π truncated to `digits` decimal places
+#[wasm_bindgen(js_name = piDigits)]
+pub fn pi_digits(digits: u32) -> String {
+    constants::pi(digits as usize)
+}
@@ -0,0 +1,268 @@
+// Memory watchdog for prime runs (--max-memory)
+//
+// Backpressure in variation 9 sizes the producer lead from the budget, but nothing stops
+// the other variations, or a run whose consumers leak buffers, from growing until the
+// kernel's OOM killer ends it without a word. The watchdog is a thread that samples VmRSS
+// from /proc/self/status a few times a second:
+//
+// - At 90% of the budget it pauses the producers: they block on a condition variable
+//   between segments (like progress.rs, one process-wide instance, so the sieves in
+//   primes.rs need no extra arguments) while the consumers drain their queues, and
+//   resume once RSS falls back under 80%. If RSS does not fall within a few seconds the
+//   memory is held by something other than queued segments and they resume anyway.
+// - At 100% it stops the run with a diagnostic instead of letting it be killed. With
+//   --on-memory-limit abort it stops at 90% without trying to pause first.
+//
+// Outputs are written to temp files and renamed when complete (see storage.rs), so a run
+// stopped by the watchdog leaves the previous dataset untouched.
+
+use clap::ValueEnum;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::storage::get_process_memory_mb;
+
+// How often the watchdog reads RSS
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+// Shares of the budget at which producers pause and resume
+const PAUSE_AT: f64 = 0.9;
+const RESUME_AT: f64 = 0.8;
+
+// Longest a pause lasts when RSS does not come down
+const MAX_PAUSE: Duration = Duration::from_secs(5);
+
+/// What the watchdog does as RSS approaches the budget
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum OnLimit {
+    /// Pause producers until consumers catch up; stop only at the budget itself
+    #[default]
+    Throttle,
+    /// Stop the run as soon as RSS nears the budget
+    Abort,
+}
+
+/// How a watched run used its memory
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Report {
+    pub peak_rss_mb: f64,
+    /// Times the producers were paused
+    pub pauses: usize,
+    /// Total time spent paused
+    pub paused: Duration,
+}
+
+struct Watchdog {
+    max_memory_mb: usize,
+    on_limit: OnLimit,
+    started: Instant,
+    paused: Mutex<bool>,
+    resumed: Condvar,
+    // Mirrors `paused` so producers can skip the lock when nothing is paused
+    pausing: AtomicBool,
+    running: AtomicBool,
+    report: Mutex<Report>,
+    waiting: AtomicUsize,
+}
+
+static WATCHDOG: OnceLock<Watchdog> = OnceLock::new();
+
+/// Start watching RSS against a budget of `max_memory_mb`
+/// Only the first call in a process has any effect
+pub fn start(max_memory_mb: usize, on_limit: OnLimit) {
+    let watchdog = Watchdog {
+        max_memory_mb,
+        on_limit,
+        started: Instant::now(),
+        paused: Mutex::new(false),
+        resumed: Condvar::new(),
+        pausing: AtomicBool::new(false),
+        running: AtomicBool::new(true),
+        report: Mutex::new(Report::default()),
+        waiting: AtomicUsize::new(0),
+    };
+    if WATCHDOG.set(watchdog).is_err() {
+        return;
+    }
+    if get_process_memory_mb().is_none() {
+        warn!("Memory watchdog: cannot read /proc/self/status, --max-memory is not enforced");
+        return;
+    }
+    info!(
+        "Memory watchdog: {} MB budget, {} at {:.0}%",
+        max_memory_mb,
+        match on_limit {
+            OnLimit::Throttle => "pausing producers",
+            OnLimit::Abort => "stopping",
+        },
+        PAUSE_AT * 100.0
+    );
+    thread::spawn(watch);
+}
+
+/// Block the calling producer while the watchdog has paused production
+/// A no-op when no watchdog is running
+#[inline]
+pub fn wait_if_paused() {
+    if let Some(watchdog) = WATCHDOG.get()
+        && watchdog.pausing.load(Ordering::Relaxed)
+    {
+        watchdog.waiting.fetch_add(1, Ordering::Relaxed);
+        let mut paused = watchdog.paused.lock().unwrap();
+        while *paused {
+            paused = watchdog.resumed.wait(paused).unwrap();
+        }
+        watchdog.waiting.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Stop watching and release any paused producers; None if no watchdog was started
+pub fn stop() -> Option<Report> {
+    let watchdog = WATCHDOG.get()?;
+    watchdog.running.store(false, Ordering::Relaxed);
+    watchdog.set_paused(false);
+    Some(*watchdog.report.lock().unwrap())
+}
+
+impl Watchdog {
+    fn set_paused(&self, paused: bool) {
+        *self.paused.lock().unwrap() = paused;
+        self.pausing.store(paused, Ordering::Relaxed);
+        if !paused {
+            self.resumed.notify_all();
+        }
+    }
+
+    /// Log what the process was doing and exit
+    fn abort(&self, rss_mb: f64, vm_mb: f64) -> ! {
+        let report = *self.report.lock().unwrap();
+        error!(
+            "Memory watchdog: RSS {:.2} MB reached {:.0}% of the {} MB budget (VM {:.2} MB) after {:.2}s; stopping the run",
+            rss_mb,
+            rss_mb / self.max_memory_mb as f64 * 100.0,
+            self.max_memory_mb,
+            vm_mb,
+            self.started.elapsed().as_secs_f64()
+        );
+        error!(
+            "Memory watchdog: producers paused {} times for {:.2}s, {} waiting now",
+            report.pauses,
+            report.paused.as_secs_f64(),
+            self.waiting.load(Ordering::Relaxed)
+        );
+        if let Some(throughput) = crate::throughput::totals() {
+            error!(
+                "Memory watchdog: consumers had finished {} primes in {} segments",
+                throughput.primes, throughput.segments
+            );
+        }
+        error!(
+            "Incomplete outputs were left as temp files and previous results are untouched; rerun with a larger --max-memory, fewer --consumers or a smaller --channel-capacity"
+        );
+        std::process::exit(1);
+    }
+}
+
+// What one RSS sample calls for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Action {
+    Continue,
+    Pause,
+    Resume,
+    Abort,
+}
+
+/// Decide on a sample of `used` (RSS as a share of the budget), given how long producers
+/// have been paused, if they are
+fn decide(used: f64, on_limit: OnLimit, paused_for: Option<Duration>) -> Action {
+    match (on_limit, paused_for) {
+        (OnLimit::Abort, _) if used >= PAUSE_AT => Action::Abort,
+        (OnLimit::Abort, _) => Action::Continue,
+        _ if used >= 1.0 => Action::Abort,
+        (OnLimit::Throttle, None) if used >= PAUSE_AT => Action::Pause,
+        (OnLimit::Throttle, Some(paused)) if used < RESUME_AT || paused >= MAX_PAUSE => {
+            Action::Resume
+        }
+        _ => Action::Continue,
+    }
+}
+
+/// The watchdog thread: sample RSS until `stop`
+fn watch() {
+    let watchdog = WATCHDOG.get().unwrap();
+    let budget = watchdog.max_memory_mb as f64;
+    let mut paused_since: Option<Instant> = None;
+
+    while watchdog.running.load(Ordering::Relaxed) {
+        thread::sleep(SAMPLE_INTERVAL);
+        let Some((rss_mb, vm_mb)) = get_process_memory_mb() else {
+            continue;
+        };
+        {
+            let mut report = watchdog.report.lock().unwrap();
+            report.peak_rss_mb = report.peak_rss_mb.max(rss_mb);
+        }
+
+        let paused_for = paused_since.map(|since| since.elapsed());
+        match decide(rss_mb / budget, watchdog.on_limit, paused_for) {
+            Action::Continue => {}
+            Action::Abort => watchdog.abort(rss_mb, vm_mb),
+            Action::Pause => {
+                warn!(
+                    "Memory watchdog: RSS {:.2} MB over {:.0}% of {} MB, pausing producers",
+                    rss_mb,
+                    PAUSE_AT * 100.0,
+                    watchdog.max_memory_mb
+                );
+                watchdog.set_paused(true);
+                watchdog.report.lock().unwrap().pauses += 1;
+                paused_since = Some(Instant::now());
+            }
+            Action::Resume => {
+                if rss_mb >= budget * RESUME_AT {
+                    warn!(
+                        "Memory watchdog: RSS still {:.2} MB after {:.0}s paused, resuming producers",
+                        rss_mb,
+                        MAX_PAUSE.as_secs_f64()
+                    );
+                }
+                watchdog.set_paused(false);
+                watchdog.report.lock().unwrap().paused += paused_for.unwrap_or_default();
+                paused_since = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_throttle_pauses_then_resumes() {
+        let throttle = OnLimit::Throttle;
+        assert_eq!(decide(0.5, throttle, None), Action::Continue);
+        assert_eq!(decide(0.95, throttle, None), Action::Pause);
+        let paused = Some(Duration::from_secs(1));
+        assert_eq!(decide(0.85, throttle, paused), Action::Continue);
+        assert_eq!(decide(0.7, throttle, paused), Action::Resume);
+        // Memory that does not come back is not waited on forever
+        assert_eq!(decide(0.85, throttle, Some(MAX_PAUSE)), Action::Resume);
+        assert_eq!(decide(1.0, throttle, paused), Action::Abort);
+    }
+
+    #[test]
+    fn test_abort_stops_early() {
+        assert_eq!(decide(0.85, OnLimit::Abort, None), Action::Continue);
+        assert_eq!(decide(0.9, OnLimit::Abort, None), Action::Abort);
+    }
+
+    #[test]
+    fn test_unstarted_watchdog_never_blocks() {
+        wait_if_paused();
+        assert_eq!(stop(), None);
+    }
+}
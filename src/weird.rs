@@ -0,0 +1,189 @@
+// Weird and semiperfect numbers (`nt weird`)
+//
+// n is abundant when σ(n) > 2n, semiperfect when some of its proper divisors sum to n, and
+// weird when it is abundant but not semiperfect (70, 836, 4030, ...). The proper divisors
+// sum to n + a with abundance a = σ(n) − 2n, so the divisors left out of a subset summing
+// to n sum to a: the search looks for a subset summing to a instead, which is almost
+// always far smaller than n and only involves the divisors <= a. Divisors are tried
+// largest first and a branch is dropped as soon as the divisors left cannot reach the
+// target; semiperfect numbers usually succeed on the first greedy path.
+//
+// Segments of [1, limit] run on the shared pipeline in segments.rs: workers sieve σ for
+// their segment with arith::sieve_segment, factor each abundant n for its divisors and
+// classify it, and one consumer collects the weird numbers back in order.
+
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::mpsc;
+use std::thread;
+
+use crate::arith::{self, ArithFunction, Scratch};
+use crate::factor;
+use crate::segments::{Pipeline, SegmentPlan, consume_in_order};
+
+// Numbers per segment
+const SEGMENT_SIZE: usize = 16 * 1024;
+
+// Segments buffered between the workers and the consumer
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Abundant numbers up to a limit, split into semiperfect and weird
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WeirdStats {
+    pub abundant: usize,
+    pub semiperfect: usize,
+    /// In increasing order
+    pub weird: Vec<u64>,
+}
+
+/// One segment's share of the stats
+struct SegmentResult {
+    segment_id: usize,
+    abundant: usize,
+    weird: Vec<u64>,
+}
+
+/// Proper divisors of n in decreasing order
+fn proper_divisors(n: u64) -> Vec<u64> {
+    let mut divisors = vec![1];
+    for (p, exp) in factor::factorize(n) {
+        let count = divisors.len();
+        let mut power = 1;
+        for _ in 0..exp {
+            power *= p;
+            for i in 0..count {
+                divisors.push(divisors[i] * power);
+            }
+        }
+    }
+    divisors.sort_unstable_by(|a, b| b.cmp(a));
+    // The largest divisor is n itself
+    divisors.remove(0);
+    divisors
+}
+
+/// Whether some of `divisors` (decreasing) sum to exactly `target`
+/// `suffix[i]` is the sum of `divisors[i..]`
+fn subset_sums_to(divisors: &[u64], suffix: &[u64], target: u64) -> bool {
+    let Some((&first, rest)) = divisors.split_first() else {
+        return target == 0;
+    };
+    if target == 0 || suffix[0] == target {
+        return true;
+    }
+    if suffix[0] < target {
+        return false;
+    }
+    (first <= target && subset_sums_to(rest, &suffix[1..], target - first))
+        || subset_sums_to(rest, &suffix[1..], target)
+}
+
+/// Whether the abundant number n (with divisor sum `sigma`) is semiperfect
+pub fn is_semiperfect(n: u64, sigma: u64) -> bool {
+    let abundance = sigma - 2 * n;
+    // Divisors above the abundance can never be left out
+    let divisors: Vec<u64> = proper_divisors(n)
+        .into_iter()
+        .filter(|&d| d <= abundance)
+        .collect();
+    let mut suffix = vec![0; divisors.len() + 1];
+    for i in (0..divisors.len()).rev() {
+        suffix[i] = suffix[i + 1] + divisors[i];
+    }
+    subset_sums_to(&divisors, &suffix, abundance)
+}
+
+/// Classify every abundant number up to `limit` on `num_workers` threads
+pub fn classify(limit: usize, num_workers: usize) -> WeirdStats {
+    let base_primes = crate::primes::sieve(limit.isqrt());
+    let plan = SegmentPlan::new(1, limit, SEGMENT_SIZE);
+    let pipeline = Pipeline {
+        num_workers,
+        total_sent: Arc::new(AtomicUsize::new(0)),
+        backpressure: None,
+        pinning: None,
+        deterministic: false,
+    };
+    let total_received = AtomicUsize::new(0);
+
+    thread::scope(|scope| {
+        let (tx, rx) = mpsc::sync_channel::<SegmentResult>(CHANNEL_CAPACITY);
+        let total_received = &total_received;
+        let consumer = scope.spawn(move || {
+            let mut stats = WeirdStats::default();
+            consume_in_order(
+                rx,
+                1,
+                1,
+                total_received,
+                |result| result.segment_id,
+                |result| {
+                    stats.abundant += result.abundant;
+                    stats.semiperfect += result.abundant - result.weird.len();
+                    stats.weird.extend(result.weird);
+                },
+            );
+            stats
+        });
+
+        pipeline.run(
+            &plan,
+            vec![tx],
+            |result: &SegmentResult| result.weird.len() * 8,
+            Scratch::default,
+            |scratch, low, high, segment_id| {
+                let sigmas =
+                    arith::sieve_segment(ArithFunction::Sigma, &base_primes, low, high, scratch);
+                let mut result = SegmentResult {
+                    segment_id,
+                    abundant: 0,
+                    weird: Vec::new(),
+                };
+                for (n, &sigma) in (low as u64..).zip(sigmas) {
+                    let sigma = sigma as u64;
+                    if sigma <= 2 * n {
+                        continue;
+                    }
+                    result.abundant += 1;
+                    if !is_semiperfect(n, sigma) {
+                        result.weird.push(n);
+                    }
+                }
+                result
+            },
+        );
+
+        consumer.join().unwrap()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weird_numbers_match_oeis() {
+        // A006037
+        let stats = classify(20_000, 3);
+        assert_eq!(
+            stats.weird,
+            [
+                70, 836, 4030, 5830, 7192, 7912, 9272, 10430, 10570, 10792, 10990, 11410, 11690,
+                12110, 12530, 12670, 13370, 13510, 13790, 13930, 14770, 15610, 15890, 16030, 16310,
+                16730, 16870, 17272, 17570, 17990, 18410, 18830, 18970, 19390, 19670, 19810
+            ]
+        );
+        // A005101: 4953 abundant numbers up to 20000
+        assert_eq!(stats.abundant, 4953);
+        assert_eq!(stats.semiperfect + stats.weird.len(), stats.abundant);
+    }
+
+    #[test]
+    fn test_semiperfect_divisor_subsets() {
+        // 12 = 2 + 4 + 6, 20 = 10 + 5 + 4 + 1, 70 has no subset of 1, 2, 5, 7, 10, 14, 35
+        assert!(is_semiperfect(12, 28));
+        assert!(is_semiperfect(20, 42));
+        assert!(!is_semiperfect(70, 144));
+        assert_eq!(proper_divisors(12), [6, 4, 3, 2, 1]);
+    }
+}
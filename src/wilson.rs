@@ -0,0 +1,155 @@
+// Wilson's theorem and factorials modulo n (`nt wilson`)
+//
+// n > 1 is prime exactly when (n − 1)! ≡ −1 (mod n); for composite n > 4 the factorial
+// is 0 instead. The product runs over [1, n − 1] in chunks that workers multiply
+// independently and that combine at the end, so a 10-digit prime takes about a minute on
+// a few cores. Below 2^32 two consecutive factors still fit in a u64, which halves the
+// 128-bit reductions.
+//
+// The same pass gives two related values. With h = (n − 1) / 2, Wilson's theorem pairs k
+// with n − k to give (h!)² ≡ (−1)^(h+1), so h! is ±1 when n ≡ 3 (mod 4) and a square root
+// of −1 when n ≡ 1 (mod 4). Working modulo n² (possible while n < 2^32) also gives the
+// Wilson quotient ((n − 1)! + 1) / n mod n, which is 0 only for the Wilson primes 5, 13
+// and 563 below 2 × 10^13.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::factor::{self, mul_mod};
+use crate::parallel::{MIN_CHUNK, chunk_size, parallel_chunks};
+
+/// Everything `nt wilson` reports for n
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WilsonResult {
+    pub n: u64,
+    /// (n − 1)! mod n
+    pub factorial: u64,
+    /// ((n − 1) / 2)! mod n, for odd n
+    pub half_factorial: Option<u64>,
+    /// ((n − 1)! + 1) / n mod n, for primes below 2^32
+    pub wilson_quotient: Option<u64>,
+}
+
+impl WilsonResult {
+    /// Whether Wilson's criterion (n − 1)! ≡ −1 (mod n) holds, i.e. n is prime
+    pub fn is_prime(&self) -> bool {
+        self.n > 1 && self.factorial == self.n - 1
+    }
+}
+
+/// Product of the integers in [low, high] modulo m on the calling thread; stops early
+/// once `zero` is set, since the whole product is then 0
+fn chunk_product(low: u64, high: u64, m: u64, zero: &AtomicBool) -> u64 {
+    let mut product = 1 % m;
+    let mut k = low;
+    let mut steps: u32 = 0;
+    while k <= high && product != 0 {
+        if k < high && high < 1 << 32 {
+            product = mul_mod(product, k * (k + 1), m);
+            k += 2;
+        } else {
+            product = mul_mod(product, k, m);
+            k += 1;
+        }
+        steps = steps.wrapping_add(1);
+        if steps.is_multiple_of(1 << 16) && zero.load(Ordering::Relaxed) {
+            return 0;
+        }
+    }
+    if product == 0 {
+        zero.store(true, Ordering::Relaxed);
+    }
+    product
+}
+
+/// Product of the integers in [low, high] modulo m, split over `workers` threads
+pub fn product_mod(low: u64, high: u64, m: u64, workers: usize) -> u64 {
+    if low > high {
+        return 1 % m;
+    }
+    let (low, high) = (low as usize, high as usize);
+    let zero = AtomicBool::new(false);
+    let mut total = 1 % m;
+    parallel_chunks(
+        low..=high,
+        chunk_size(high - low, workers, MIN_CHUNK),
+        workers,
+        |chunk_low, chunk_high| {
+            if zero.load(Ordering::Relaxed) {
+                return 0;
+            }
+            chunk_product(chunk_low as u64, chunk_high as u64, m, &zero)
+        },
+        |product| total = mul_mod(total, product, m),
+    );
+    total
+}
+
+/// k! mod m
+pub fn factorial_mod(k: u64, m: u64, workers: usize) -> u64 {
+    product_mod(2, k, m, workers)
+}
+
+/// (n − 1)!, ((n − 1) / 2)! and the Wilson quotient modulo n (n >= 2)
+pub fn wilson(n: u64, workers: usize) -> WilsonResult {
+    // Modulo n² where it fits, to read off the Wilson quotient
+    let square = (n < 1 << 32 && factor::is_prime(n)).then(|| n * n);
+    let m = square.unwrap_or(n);
+
+    let half = (n - 1) / 2;
+    let lower = factorial_mod(half, m, workers);
+    let upper = product_mod(half + 1, n - 1, m, workers);
+    let full = mul_mod(lower, upper, m);
+
+    WilsonResult {
+        n,
+        factorial: full % n,
+        half_factorial: (n % 2 == 1).then_some(lower % n),
+        wilson_quotient: square.map(|square| {
+            // (n − 1)! + 1 is a multiple of n, and below n² + 1 as a residue
+            let numerator = (full as u128 + 1) % square as u128;
+            (numerator / n as u128) as u64 % n
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wilson_criterion() {
+        for n in 2..2000 {
+            assert_eq!(wilson(n, 2).is_prime(), factor::is_prime(n), "n {}", n);
+        }
+        assert_eq!(wilson(4, 1).factorial, 2);
+        assert_eq!(wilson(1_000_001, 3).factorial, 0);
+    }
+
+    #[test]
+    fn test_half_factorial_and_quotient() {
+        // 1009 ≡ 1 (mod 4): h! is a square root of −1
+        let result = wilson(1009, 2);
+        let h = result.half_factorial.unwrap();
+        assert_eq!(h * h % 1009, 1008);
+        // 1019 ≡ 3 (mod 4): h! is ±1
+        let h = wilson(1019, 2).half_factorial.unwrap();
+        assert!(h == 1 || h == 1018);
+
+        let wilson_primes: Vec<u64> = (2..1000)
+            .filter(|&n| wilson(n, 1).wilson_quotient == Some(0))
+            .collect();
+        assert_eq!(wilson_primes, [5, 13, 563]);
+        // (6! + 1) / 7 = 103 ≡ 5 (mod 7)
+        assert_eq!(wilson(7, 1).wilson_quotient, Some(5));
+    }
+
+    #[test]
+    fn test_chunked_product_matches_serial() {
+        let m = 1_000_000_007;
+        let mut expected = 1;
+        for k in 2..=300_000u64 {
+            expected = expected * k % m;
+        }
+        assert_eq!(factorial_mod(300_000, m, 4), expected);
+    }
+}